@@ -0,0 +1,113 @@
+//! A small benchmark of the encode -> send -> read -> parse hot path.
+//!
+//! Run it with `cargo bench --features async --bench hot_path`
+use std::time::{Duration, Instant};
+use twitchchat::{
+    commands,
+    irc::TagIndices,
+    messages::{Commands, Privmsg},
+    writer::MpscWriter,
+    AsyncDecoder, AsyncEncoder, FromIrcMessage, IntoOwned,
+};
+
+const MESSAGES: usize = 200_000;
+const LINE: &str = "@badge-info=;badges=broadcaster/1;color=#FF69B4;display-name=museun;emotes=;id=11111111-2222-3333-4444-555555555555;mod=0;room-id=23196011;subscriber=0;tmi-sent-ts=1601079032426;turbo=0;user-id=23196011;user-type= :museun!museun@museun.tmi.twitch.tv PRIVMSG #museun :this is a test message\r\n";
+
+fn bench(name: &str, mut f: impl FnMut()) {
+    // warm up
+    f();
+
+    let mut best = Duration::from_secs(u64::MAX);
+    for _ in 0..5 {
+        let start = Instant::now();
+        f();
+        best = best.min(start.elapsed());
+    }
+
+    println!(
+        "{:<28} {:>10.2?} ({:.0} ns/msg)",
+        name,
+        best,
+        best.as_nanos() as f64 / MESSAGES as f64
+    );
+}
+
+fn main() {
+    // `cargo test --all-targets` runs this without `--bench`, so only do the work when benchmarking
+    if !std::env::args().any(|arg| arg == "--bench") {
+        return;
+    }
+
+    bench("MpscWriter::encode", || {
+        let (tx, rx) = twitchchat::channel::unbounded();
+        let mut writer = MpscWriter::new(tx);
+        for _ in 0..MESSAGES {
+            writer
+                .encode(commands::privmsg("museun", "this is a test message"))
+                .unwrap();
+            rx.try_recv().unwrap();
+        }
+    });
+
+    bench("AsyncEncoder<MpscWriter>", || {
+        let (tx, rx) = twitchchat::channel::unbounded();
+        let mut encoder = AsyncEncoder::new(MpscWriter::new(tx));
+        futures_lite::future::block_on(async {
+            for _ in 0..MESSAGES {
+                encoder
+                    .encode(commands::privmsg("museun", "this is a test message"))
+                    .await
+                    .unwrap();
+                rx.try_recv().unwrap();
+            }
+        });
+    });
+
+    let input = LINE.repeat(MESSAGES);
+    // compare these with and without the `simd` feature
+    bench("irc::parse", || {
+        assert_eq!(
+            twitchchat::irc::parse(&input).filter(Result::is_ok).count(),
+            MESSAGES
+        );
+    });
+
+    let tags = &LINE[..LINE.find(' ').unwrap()];
+    bench("TagIndices::build_indices", || {
+        for _ in 0..MESSAGES {
+            assert_eq!(TagIndices::build_indices(tags).unwrap().len(), 13);
+        }
+    });
+
+    bench("AsyncDecoder::read_message", || {
+        let mut decoder = AsyncDecoder::new(input.as_bytes());
+        futures_lite::future::block_on(async {
+            for _ in 0..MESSAGES {
+                decoder.read_message().await.unwrap();
+            }
+        });
+    });
+
+    bench("AsyncDecoder -> Privmsg<'_>", || {
+        let mut decoder = AsyncDecoder::new(input.as_bytes());
+        futures_lite::future::block_on(async {
+            for _ in 0..MESSAGES {
+                // parse and filter without making an owned copy
+                let msg = decoder.read_message().await.unwrap();
+                let msg = Privmsg::from_irc(msg).unwrap();
+                assert_eq!(msg.display_name().as_deref(), Some("museun"));
+            }
+        });
+    });
+
+    bench("AsyncDecoder -> Commands", || {
+        let mut decoder = AsyncDecoder::new(input.as_bytes());
+        futures_lite::future::block_on(async {
+            for _ in 0..MESSAGES {
+                let msg = decoder.read_message().await.unwrap();
+                let msg = Commands::from_irc(msg).unwrap().into_owned();
+                assert!(matches!(msg, Commands::Privmsg(..)));
+            }
+        });
+    });
+}
@@ -42,16 +42,28 @@ pub async fn main_loop(mut runner: AsyncRunner) -> anyhow::Result<()> {
                 handle_message(msg).await;
             }
 
+            // a line that couldn't be parsed. it was skipped
+            Status::Invalid(invalid) => {
+                println!("skipped an invalid message: {}", invalid);
+            }
+
             // you signaled a quit
             Status::Quit => {
                 println!("we signaled we wanted to quit");
                 break;
             }
+            // a shutdown was requested, and everything queued was sent
+            Status::Stopped => {
+                println!("we were shut down");
+                break;
+            }
             // the connection closed normally
             Status::Eof => {
                 println!("we got a 'normal' eof");
                 break;
             }
+            // new statuses can be added in future releases of this crate
+            _ => {}
         }
     }
 
@@ -129,9 +129,9 @@ impl Bot {
                     }
                 }
                 // stop if we're stopping
-                Status::Quit | Status::Eof => break,
+                Status::Quit | Status::Stopped | Status::Eof => break,
                 // ignore the rest
-                Status::Message(..) => continue,
+                _ => continue,
             }
         }
 
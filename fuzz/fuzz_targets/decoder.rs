@@ -0,0 +1,7 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+// any bytes read from the connection
+fuzz_target!(|data: &[u8]| {
+    twitchchat::test::exercise(data);
+});
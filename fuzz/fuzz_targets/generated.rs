@@ -0,0 +1,10 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use twitchchat::test::{exercise, Generator};
+
+// Twitch-like lines, from a seed chosen by the fuzzer
+fuzz_target!(|seed: u64| {
+    let mut gen = Generator::with_seed(seed);
+    exercise(gen.line().as_bytes());
+    exercise(&gen.mangled_line());
+});
@@ -45,6 +45,18 @@ impl<T> Sender<T> {
             async_channel::TrySendError::Closed(t) => TrySendError::Closed(t),
         })
     }
+
+    /// Is the channel closed? (e.g. every receiver was dropped)
+    pub fn is_closed(&self) -> bool {
+        self.inner.is_closed()
+    }
+
+    // how many more items fit in the channel. this is None if it is unbounded
+    pub(crate) fn room(&self) -> Option<usize> {
+        self.inner
+            .capacity()
+            .map(|cap| cap.saturating_sub(self.inner.len()))
+    }
 }
 
 pin_project_lite::pin_project! {
@@ -78,6 +90,16 @@ impl<T> Receiver<T> {
     pub fn try_recv(&self) -> Option<T> {
         self.inner.try_recv().ok()
     }
+
+    /// How many items are waiting in the channel
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Whether the channel has no waiting items
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
 }
 
 impl<T> futures_lite::Stream for Receiver<T> {
@@ -4,6 +4,8 @@
 //!
 //! The [functions][functions] in this module produce borrowed types in the [`types`][types] module. You can store the [`types`][types] for multiple encodings.
 //!
+//! They take their channels and users as `&str`, so a [`ChannelName`](crate::twitch::ChannelName) or a [`Login`](crate::twitch::Login) can be passed by reference. A channel is given its leading `#` (and lowercased) either way.
+//!
 //! ### Some provided encoders:
 //! * [`Encoder`](../struct.Encoder.html)
 //!
@@ -59,6 +61,7 @@ macro_rules! export_commands {
 }
 
 export_commands! {
+    announce        => Announce
     ban             => Ban
     clear           => Clear
     color           => Color
@@ -81,16 +84,19 @@ export_commands! {
     ping            => Ping
     pong            => Pong
     privmsg         => Privmsg
+    privmsg_split   => PrivmsgSplit
     r9k_beta        => R9kBeta
     r9k_beta_off    => R9kBetaOff
     raid            => Raid
     raw             => Raw
     register        => Register
     reply           => Reply
+    shoutout        => Shoutout
     slow            => Slow
     slow_off        => SlowOff
     subscribers     => Subscribers
     subscribers_off => SubscribersOff
+    tagged          => Tagged
     timeout         => Timeout
     unban           => Unban
     unhost          => Unhost
@@ -135,6 +141,7 @@ macro_rules! serde_for_commands {
 }
 
 serde_for_commands! {
+    Announce { channel, msg, color };
     Ban { channel, username, reason };
     Clear { channel };
     Color { color };
@@ -157,12 +164,14 @@ serde_for_commands! {
     Part { channel };
     Pong { token };
     Privmsg { channel, msg };
+    PrivmsgSplit { channel, msg };
     R9kBeta { channel };
     R9kBetaOff { channel };
     Raid { source, target };
     Raw { data };
     Register { user_config };
     Reply { channel, msg_id, msg };
+    Shoutout { source, target };
     Slow { channel, duration };
     SlowOff { channel };
     Subscribers { channel };
@@ -219,6 +228,8 @@ where
     }
 }
 
+pub use privmsg_split::{split_message, SplitMessage, MAX_MESSAGE_LENGTH};
+
 /// A channel wrapper that when `.to_string()` is called will ensure a leading `#` is added.
 #[derive(Copy, Clone, Debug, PartialEq, PartialOrd, Eq, Ord, Hash)]
 pub struct Channel<'a>(pub(crate) &'a str);
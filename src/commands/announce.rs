@@ -0,0 +1,75 @@
+use super::{Channel, Encodable};
+use crate::twitch::AnnouncementColor;
+use std::io::{Result, Write};
+
+/// Sends an announcement to the channel.
+#[non_exhaustive]
+#[must_use = "commands must be encoded"]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(::serde::Deserialize))]
+pub struct Announce<'a> {
+    pub(crate) channel: &'a str,
+    pub(crate) msg: &'a str,
+    pub(crate) color: AnnouncementColor,
+}
+
+/// Sends an announcement to the channel, highlighted with `color` (**default=primary**).
+///
+/// This uses the `/announce` chat command, which Twitch no longer supports
+/// over IRC. Use `helix::Client::send_announcement` (behind the `helix`
+/// feature) to reliably send announcements.
+pub fn announce<'a>(
+    channel: &'a str,
+    msg: &'a str,
+    color: impl Into<Option<AnnouncementColor>>,
+) -> Announce<'a> {
+    Announce {
+        channel,
+        msg,
+        color: color.into().unwrap_or_default(),
+    }
+}
+
+impl<'a> Encodable for Announce<'a> {
+    fn encode<W>(&self, buf: &mut W) -> Result<()>
+    where
+        W: Write + ?Sized,
+    {
+        let color = match self.color {
+            AnnouncementColor::Primary => "",
+            color => color.as_str(),
+        };
+        write_cmd!(buf, Channel(self.channel) => "/announce{} {}", color, self.msg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::*;
+    use super::*;
+
+    #[test]
+    fn announce_encode() {
+        test_encode(
+            announce("#museun", "hello world", None),
+            "PRIVMSG #museun :/announce hello world\r\n",
+        );
+        test_encode(
+            announce("museun", "hello world", AnnouncementColor::Blue),
+            "PRIVMSG #museun :/announceblue hello world\r\n",
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn announce_serde() {
+        test_serde(
+            announce("#museun", "hello world", None),
+            "PRIVMSG #museun :/announce hello world\r\n",
+        );
+        test_serde(
+            announce("#museun", "hello world", AnnouncementColor::Purple),
+            "PRIVMSG #museun :/announcepurple hello world\r\n",
+        );
+    }
+}
@@ -18,6 +18,9 @@ pub struct Ban<'a> {
 ///
 /// Use [unban] to remove a ban.
 ///
+/// Twitch no longer supports this command over IRC. Use `helix::ModTools::ban` (behind the `helix`
+/// feature) to reliably do this.
+///
 /// [unban]: super::unban()
 pub fn ban<'a>(channel: &'a str, username: &'a str, reason: impl Into<Option<&'a str>>) -> Ban<'a> {
     Ban {
@@ -11,6 +11,9 @@ pub struct Clear<'a> {
 }
 
 /// Clear chat history for all users on `channel`.
+///
+/// Twitch no longer supports this command over IRC. Use `helix::ModTools::clear_chat` (behind the `helix`
+/// feature) to reliably do this.
 pub const fn clear(channel: &str) -> Clear<'_> {
     Clear { channel }
 }
@@ -14,6 +14,9 @@ pub struct EmoteOnly<'a> {
 ///
 /// Use [emote_only_off] to disable.
 ///
+/// Twitch no longer supports this command over IRC. Use `helix::ModTools::emote_only` (behind the `helix`
+/// feature) to reliably do this.
+///
 /// [emote_only_off]: super::emote_only_off()
 pub const fn emote_only(channel: &str) -> EmoteOnly<'_> {
     EmoteOnly { channel }
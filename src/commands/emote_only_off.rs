@@ -11,6 +11,9 @@ pub struct EmoteOnlyOff<'a> {
 }
 
 /// Disables emote-only mode.
+///
+/// Twitch no longer supports this command over IRC. Use `helix::ModTools::emote_only` (behind the `helix`
+/// feature) to reliably do this.
 pub const fn emote_only_off(channel: &str) -> EmoteOnlyOff<'_> {
     EmoteOnlyOff { channel }
 }
@@ -19,6 +19,9 @@ pub struct Followers<'a> {
 ///
 /// Use [followers_off] to disable.
 ///
+/// Twitch no longer supports this command over IRC. Use `helix::ModTools::followers_only` (behind the `helix`
+/// feature) to reliably do this.
+///
 /// [followers_off]: super::followers_off()
 pub const fn followers<'a>(channel: &'a str, duration: &'a str) -> Followers<'a> {
     Followers { channel, duration }
@@ -11,6 +11,9 @@ pub struct FollowersOff<'a> {
 }
 
 /// Disables followers-only mode.
+///
+/// Twitch no longer supports this command over IRC. Use `helix::ModTools::followers_only` (behind the `helix`
+/// feature) to reliably do this.
 pub const fn followers_off(channel: &str) -> FollowersOff<'_> {
     FollowersOff { channel }
 }
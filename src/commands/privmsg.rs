@@ -43,6 +43,12 @@ mod tests {
         );
     }
 
+    #[test]
+    fn privmsg_channel_name() {
+        let channel = crate::twitch::ChannelName::from("Museun");
+        test_encode(privmsg(&channel, "hello"), "PRIVMSG #museun :hello\r\n");
+    }
+
     #[test]
     fn privmsg_ensure_channel_encode() {
         test_encode(
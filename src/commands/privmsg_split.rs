@@ -0,0 +1,167 @@
+use super::{Channel, Encodable};
+use std::io::{Result, Write};
+
+/// The maximum length (in characters) of a message Twitch will accept
+pub const MAX_MESSAGE_LENGTH: usize = 500;
+
+/// Send a message to a channel, splitting it into multiple messages if it is too long
+#[non_exhaustive]
+#[must_use = "commands must be encoded"]
+#[derive(Debug, Copy, Clone, PartialEq, Ord, PartialOrd, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(::serde::Deserialize))]
+pub struct PrivmsgSplit<'a> {
+    pub(crate) channel: &'a str,
+    pub(crate) msg: &'a str,
+}
+
+/// Send a message to a channel, splitting it into multiple messages if it is too long
+///
+/// Twitch truncates messages longer than [MAX_MESSAGE_LENGTH] characters. This will instead send multiple `PRIVMSG`s, splitting on whitespace where possible (and never in the middle of a character).
+///
+/// An empty (or whitespace-only) message won't send anything.
+pub const fn privmsg_split<'a>(channel: &'a str, msg: &'a str) -> PrivmsgSplit<'a> {
+    PrivmsgSplit { channel, msg }
+}
+
+impl<'a> PrivmsgSplit<'a> {
+    /// Get the parts this message will be split into
+    pub fn parts(&self) -> SplitMessage<'a> {
+        split_message(self.msg, MAX_MESSAGE_LENGTH)
+    }
+}
+
+impl<'a> Encodable for PrivmsgSplit<'a> {
+    fn encode<W>(&self, buf: &mut W) -> Result<()>
+    where
+        W: Write + ?Sized,
+    {
+        for part in self.parts() {
+            write_nl!(buf, "PRIVMSG {} :{}", Channel(self.channel), part)?;
+        }
+        Ok(())
+    }
+}
+
+/// Split `msg` into parts of at most `max` characters, preferring to split on whitespace.
+///
+/// The whitespace at the split points is removed.
+pub fn split_message(msg: &str, max: usize) -> SplitMessage<'_> {
+    SplitMessage {
+        rest: msg,
+        max: max.max(1),
+    }
+}
+
+/// An iterator over the parts of a split message. This is produced by [split_message]
+#[derive(Debug, Clone)]
+pub struct SplitMessage<'a> {
+    rest: &'a str,
+    max: usize,
+}
+
+impl<'a> Iterator for SplitMessage<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let rest = self.rest.trim_start();
+        if rest.is_empty() {
+            self.rest = rest;
+            return None;
+        }
+
+        // the byte index of the first character that doesn't fit
+        let end = match rest.char_indices().nth(self.max) {
+            Some((end, _)) => end,
+            None => {
+                self.rest = "";
+                return Some(rest.trim_end());
+            }
+        };
+
+        let cut = if rest[end..].starts_with(char::is_whitespace) {
+            end
+        } else {
+            rest[..end]
+                .rfind(char::is_whitespace)
+                .filter(|&pos| pos > 0)
+                .unwrap_or(end)
+        };
+
+        self.rest = &rest[cut..];
+        Some(rest[..cut].trim_end())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::*;
+    use super::*;
+
+    #[test]
+    fn privmsg_split_encode() {
+        test_encode(
+            privmsg_split("#museun", "this is a test of a line"),
+            "PRIVMSG #museun :this is a test of a line\r\n",
+        );
+
+        // 125 * 4 = 500, so the trailing space is dropped and it fits
+        test_encode(
+            privmsg_split("museun", &"foo ".repeat(125)),
+            format!("PRIVMSG #museun :{}\r\n", "foo ".repeat(125).trim_end()),
+        );
+
+        let expected = format!(
+            "PRIVMSG #museun :{}\r\nPRIVMSG #museun :{}\r\n",
+            "foo ".repeat(125).trim_end(),
+            "foo ".repeat(75).trim_end(),
+        );
+        test_encode(privmsg_split("#museun", &"foo ".repeat(200)), expected);
+
+        test_encode(privmsg_split("#museun", "   "), "");
+    }
+
+    #[test]
+    fn split_on_whitespace() {
+        let parts = split_message("hello world this is a test", 11).collect::<Vec<_>>();
+        assert_eq!(parts, vec!["hello world", "this is a", "test"]);
+
+        let parts = split_message("hello   world", 6).collect::<Vec<_>>();
+        assert_eq!(parts, vec!["hello", "world"]);
+    }
+
+    #[test]
+    fn split_long_words() {
+        let parts = split_message("abcdefghij klm", 4).collect::<Vec<_>>();
+        assert_eq!(parts, vec!["abcd", "efgh", "ij", "klm"]);
+    }
+
+    #[test]
+    fn split_multibyte() {
+        // each of these is 3 bytes
+        let msg = "日本語のテキスト";
+        let parts = split_message(msg, 3).collect::<Vec<_>>();
+        assert_eq!(parts, vec!["日本語", "のテキ", "スト"]);
+
+        let msg = "🦀".repeat(MAX_MESSAGE_LENGTH + 1);
+        let parts = privmsg_split("#museun", &msg).parts().collect::<Vec<_>>();
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[0].chars().count(), MAX_MESSAGE_LENGTH);
+        assert_eq!(parts[1], "🦀");
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn privmsg_split_serde() {
+        test_serde(
+            privmsg_split("#museun", "this is a test of a line"),
+            "PRIVMSG #museun :this is a test of a line\r\n",
+        );
+
+        let expected = format!(
+            "PRIVMSG #museun :{}\r\nPRIVMSG #museun :{}\r\n",
+            "foo ".repeat(125).trim_end(),
+            "foo ".repeat(75).trim_end(),
+        );
+        test_serde(privmsg_split("#museun", &"foo ".repeat(200)), expected);
+    }
+}
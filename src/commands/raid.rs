@@ -15,6 +15,9 @@ pub struct Raid<'a> {
 ///
 /// Use [unraid] to cancel the Raid.
 ///
+/// Twitch no longer supports this command over IRC. Use `helix::Client::start_raid` (behind the `helix`
+/// feature) to reliably do this.
+///
 /// [unraid]: super::unraid()
 pub const fn raid<'a>(source: &'a str, target: &'a str) -> Raid<'a> {
     Raid { source, target }
@@ -32,6 +32,7 @@ impl<'a> Encodable for Register<'a> {
             name,
             token,
             capabilities,
+            ..
         } = &self.user_config;
 
         // the caps have to be written first
@@ -40,7 +41,10 @@ impl<'a> Encodable for Register<'a> {
             write!(buf, "{}\r\n", cap)?;
         }
 
-        write!(buf, "PASS {}\r\n", token)?;
+        // anonymous logins don't need a PASS
+        if !token.is_empty() {
+            write!(buf, "PASS {}\r\n", token)?;
+        }
         write!(buf, "NICK {}\r\n", name)?;
         buf.flush()
     }
@@ -69,6 +73,12 @@ mod tests {
         )
     }
 
+    #[test]
+    fn register_encode_random_anonymous() {
+        let config = UserConfig::anonymous();
+        test_encode(register(&config), format!("NICK {}\r\n", config.name))
+    }
+
     #[test]
     #[cfg(feature = "serde")]
     fn register_serde() {
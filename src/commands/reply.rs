@@ -26,7 +26,7 @@ impl<'a> Encodable for Reply<'a> {
         write_nl!(
             buf,
             "@reply-parent-msg-id={} PRIVMSG {} :{}",
-            self.msg_id,
+            crate::irc::tags::escape_str(self.msg_id),
             Channel(self.channel),
             self.msg
         )
@@ -60,6 +60,14 @@ mod tests {
         );
     }
 
+    #[test]
+    fn reply_escapes_msg_id() {
+        test_encode(
+            reply("#museun", "a b;c", "hello"),
+            "@reply-parent-msg-id=a\\sb\\:c PRIVMSG #museun :hello\r\n",
+        );
+    }
+
     #[test]
     fn reply_ensure_channel_encode() {
         test_encode(
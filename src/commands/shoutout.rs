@@ -0,0 +1,61 @@
+use super::{Channel, Encodable};
+use std::io::{Result, Write};
+
+/// Give another broadcaster a shoutout.
+#[non_exhaustive]
+#[must_use = "commands must be encoded"]
+#[derive(Debug, Copy, Clone, PartialEq, Ord, PartialOrd, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(::serde::Deserialize))]
+pub struct Shoutout<'a> {
+    pub(crate) source: &'a str,
+    pub(crate) target: &'a str,
+}
+
+/// Give another broadcaster a shoutout, in the `source` channel.
+///
+/// This uses the `/shoutout` chat command, which Twitch no longer supports
+/// over IRC. Use `helix::Client::send_shoutout` (behind the `helix`
+/// feature) to reliably send shoutouts.
+pub const fn shoutout<'a>(source: &'a str, target: &'a str) -> Shoutout<'a> {
+    Shoutout { source, target }
+}
+
+impl<'a> Encodable for Shoutout<'a> {
+    fn encode<W>(&self, buf: &mut W) -> Result<()>
+    where
+        W: Write + ?Sized,
+    {
+        write_cmd!(buf, Channel(self.source) => "/shoutout {}", self.target.trim_start_matches('#'))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::*;
+    use super::*;
+
+    #[test]
+    fn shoutout_encode() {
+        test_encode(
+            shoutout("#museun", "shaken_bot"),
+            "PRIVMSG #museun :/shoutout shaken_bot\r\n",
+        );
+    }
+
+    #[test]
+    fn shoutout_ensure_channel_encode() {
+        test_encode(
+            shoutout("museun", "#shaken_bot"),
+            "PRIVMSG #museun :/shoutout shaken_bot\r\n",
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn shoutout_serde() {
+        test_serde(
+            shoutout("#museun", "shaken_bot"),
+            "PRIVMSG #museun :/shoutout shaken_bot\r\n",
+        );
+    }
+}
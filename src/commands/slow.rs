@@ -17,6 +17,9 @@ pub struct Slow<'a> {
 ///
 /// Use [slow_off] to disable.
 ///
+/// Twitch no longer supports this command over IRC. Use `helix::ModTools::slow` (behind the `helix`
+/// feature) to reliably do this.
+///
 /// [slow_off]: super::slow_off()
 pub fn slow(channel: &str, duration: impl Into<Option<usize>>) -> Slow<'_> {
     Slow {
@@ -11,6 +11,9 @@ pub struct SlowOff<'a> {
 }
 
 /// Disables slow mode.
+///
+/// Twitch no longer supports this command over IRC. Use `helix::ModTools::slow` (behind the `helix`
+/// feature) to reliably do this.
 pub const fn slow_off(channel: &str) -> SlowOff<'_> {
     SlowOff { channel }
 }
@@ -0,0 +1,145 @@
+use super::Encodable;
+use std::io::{Result, Write};
+
+/// Attaches client tags to another command
+///
+/// Each line the command encodes is prefixed with the tags (with their values [escaped](crate::irc::tags::escape_str)).
+/// If the command already has tags (e.g. a [reply](super::reply())), these are added before its own.
+#[non_exhaustive]
+#[must_use = "commands must be encoded"]
+#[derive(Debug, Clone, PartialEq, Ord, PartialOrd, Eq, Hash)]
+pub struct Tagged<'a, E> {
+    pub(crate) msg: E,
+    pub(crate) tags: Vec<(&'a str, &'a str)>,
+}
+
+/// Attaches client tags to another command
+///
+/// ```rust
+/// # use twitchchat::{commands, Encodable as _};
+/// let msg = commands::tagged(commands::privmsg("#museun", "hello"))
+///     .tag("reply-parent-msg-id", "abc-123")
+///     .tag("client-nonce", "my nonce");
+///
+/// let mut data = Vec::new();
+/// msg.encode(&mut data).unwrap();
+/// assert_eq!(
+///     data,
+///     b"@reply-parent-msg-id=abc-123;client-nonce=my\\snonce PRIVMSG #museun :hello\r\n"
+/// );
+/// ```
+pub fn tagged<'a, E>(msg: E) -> Tagged<'a, E>
+where
+    E: Encodable,
+{
+    Tagged {
+        msg,
+        tags: Vec::new(),
+    }
+}
+
+impl<'a, E> Tagged<'a, E> {
+    /// Add the tag `key` with this `value`, replacing it if it was already added
+    pub fn tag(mut self, key: &'a str, value: &'a str) -> Self {
+        match self.tags.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, v)) => *v = value,
+            None => self.tags.push((key, value)),
+        }
+        self
+    }
+}
+
+impl<'a, E> Encodable for Tagged<'a, E>
+where
+    E: Encodable,
+{
+    fn encode<W>(&self, buf: &mut W) -> Result<()>
+    where
+        W: Write + ?Sized,
+    {
+        let mut data = Vec::new();
+        self.msg.encode(&mut data)?;
+        if self.tags.is_empty() {
+            return buf.write_all(&data);
+        }
+
+        for line in data.split_inclusive(|&c| c == b'\n') {
+            for (i, (key, value)) in self.tags.iter().enumerate() {
+                let sep = if i == 0 { '@' } else { ';' };
+                write!(
+                    buf,
+                    "{}{}={}",
+                    sep,
+                    key,
+                    crate::irc::tags::escape_str(value)
+                )?;
+            }
+            match line.split_first() {
+                Some((b'@', tags)) => {
+                    buf.write_all(b";")?;
+                    buf.write_all(tags)?;
+                }
+                _ => {
+                    buf.write_all(b" ")?;
+                    buf.write_all(line)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::*;
+    use super::*;
+
+    #[test]
+    fn tagged_encode() {
+        test_encode(
+            tagged(privmsg("#museun", "hello")).tag("client-nonce", "abc"),
+            "@client-nonce=abc PRIVMSG #museun :hello\r\n",
+        );
+
+        test_encode(
+            tagged(privmsg("#museun", "hello")),
+            "PRIVMSG #museun :hello\r\n",
+        );
+    }
+
+    #[test]
+    fn tagged_escapes_values() {
+        test_encode(
+            tagged(privmsg("#museun", "hello"))
+                .tag("a", "b c;d\\")
+                .tag("e", ""),
+            "@a=b\\sc\\:d\\\\;e= PRIVMSG #museun :hello\r\n",
+        );
+    }
+
+    #[test]
+    fn tagged_replaces_tags() {
+        test_encode(
+            tagged(privmsg("#museun", "hello"))
+                .tag("client-nonce", "abc")
+                .tag("client-nonce", "def"),
+            "@client-nonce=def PRIVMSG #museun :hello\r\n",
+        );
+    }
+
+    #[test]
+    fn tagged_merges_tags() {
+        test_encode(
+            tagged(reply("#museun", "abc-123", "hello")).tag("client-nonce", "abc"),
+            "@client-nonce=abc;reply-parent-msg-id=abc-123 PRIVMSG #museun :hello\r\n",
+        );
+    }
+
+    #[test]
+    fn tagged_every_line() {
+        test_encode(
+            tagged(raw("PRIVMSG #museun :a\r\nPRIVMSG #museun :b")).tag("client-nonce", "abc"),
+            "@client-nonce=abc PRIVMSG #museun :a\r\n@client-nonce=abc PRIVMSG #museun :b\r\n",
+        );
+    }
+}
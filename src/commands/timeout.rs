@@ -30,6 +30,9 @@ pub struct Timeout<'a> {
 ///
 /// Use [untimeout] to remove a timeout.
 ///
+/// Twitch no longer supports this command over IRC. Use `helix::ModTools::timeout` (behind the `helix`
+/// feature) to reliably do this.
+///
 /// [untimeout]: super::untimeout()
 pub fn timeout<'a>(
     channel: &'a str,
@@ -12,6 +12,9 @@ pub struct Unban<'a> {
 }
 
 /// Removes a ban on a user.
+///
+/// Twitch no longer supports this command over IRC. Use `helix::ModTools::unban` (behind the `helix`
+/// feature) to reliably do this.
 pub const fn unban<'a>(channel: &'a str, username: &'a str) -> Unban<'a> {
     Unban { channel, username }
 }
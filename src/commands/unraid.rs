@@ -11,6 +11,9 @@ pub struct Unraid<'a> {
 }
 
 /// Cancel the raid.
+///
+/// Twitch no longer supports this command over IRC. Use `helix::Client::cancel_raid` (behind the `helix`
+/// feature) to reliably do this.
 pub const fn unraid(channel: &str) -> Unraid<'_> {
     Unraid { channel }
 }
@@ -12,6 +12,9 @@ pub struct Whisper<'a> {
 }
 
 /// Whispers a message to the username.
+///
+/// Twitch no longer supports the `/w` command over IRC. Use
+/// `helix::Client::send_whisper` (behind the `helix` feature) to send whispers.
 pub const fn whisper<'a>(username: &'a str, message: &'a str) -> Whisper<'a> {
     Whisper { username, message }
 }
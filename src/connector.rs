@@ -24,10 +24,27 @@
 //! | [`tokio`](https://docs.rs/tokio/0.2/tokio/)                | `tokio`     | `"tokio-util"`, `"tokio-native-tls"`, `"native-tls"` | [`native-tls`][native-tls] |
 //! | [`tokio`](https://docs.rs/tokio/0.2/tokio/)                | `tokio`     | `"tokio-util"`, `"tokio-openssl"`, `"openssl"`       | [`openssl`][openssl]       |
 //!
+//! The `tokio` [`rustls`][rustls] and [`native-tls`][native-tls] connectors can be configured with a [`TlsConfig`], e.g. for custom root certificates or a different server name.
+//!
 //! [rustls]: https://docs.rs/rustls/0.18.1/rustls/
 //! [native-tls]: https://docs.rs/native-tls/0.2.4/native_tls/
 //! [openssl]: https://docs.rs/openssl/0.10/openssl/
 //!
+//! ## Proxies
+//!
+//! All of the above connectors can tunnel their connection through a SOCKS5 or HTTP `CONNECT` proxy, see [`ProxyConfig`].
+//!
+//! ## Timeouts
+//!
+//! All of the above connectors give up if the TCP connection (or the TLS handshake) takes longer than 10 seconds, with a [`ConnectTimeout`] error.
+//! This can be changed with `with_connect_timeout` (and `with_tls_timeout`).
+//!
+//! ## WebSockets
+//!
+//! Any of the above connectors can be wrapped with a [`websocket::Connector`] to speak to Twitch's WebSocket endpoint (`irc-ws.chat.twitch.tv`) instead of the raw IRC socket.
+//!
+//! In the browser (on `wasm32-unknown-unknown`), the `browser` feature provides `browser::WebSocket`, which uses the browser's own WebSocket.
+//!
 use futures_lite::{AsyncRead, AsyncWrite};
 use std::{future::Future, io::Result as IoResult, net::SocketAddr};
 
@@ -50,11 +67,29 @@ macro_rules! connector_ctor {
         {
             addrs.to_socket_addrs().map(|addrs| Self {
                 addrs: addrs.collect(),
+                proxy: None,
+                timeouts: ::std::default::Default::default(),
             })
         }
+
+        connector_ctor!(@proxy);
     };
 
     (tls: $(#[$meta:meta])*) => {
+        connector_ctor!(@tls [] $(#[$meta])*);
+    };
+
+    (tls_config: $(#[$meta:meta])*) => {
+        connector_ctor!(@tls [tls_config: ::std::default::Default::default(),] $(#[$meta])*);
+
+        /// Use this [TlsConfig](crate::connector::TlsConfig), e.g. for custom root certificates or a different server name.
+        pub fn with_tls_config(mut self, tls_config: $crate::connector::TlsConfig) -> Self {
+            self.tls_config = tls_config;
+            self
+        }
+    };
+
+    (@tls [$($extra:tt)*] $(#[$meta:meta])*) => {
         #[doc = "Create a new"]
         $(#[$meta])*
         #[doc = "TLS connector that connects to the ***default Twitch*** address."]
@@ -75,8 +110,37 @@ macro_rules! connector_ctor {
             addrs.to_socket_addrs().map(|addrs| Self {
                 addrs: addrs.collect(),
                 tls_domain,
+                proxy: None,
+                timeouts: ::std::default::Default::default(),
+                $($extra)*
             })
         }
+
+        /// Give up on the TLS handshake after this long. This defaults to 10 seconds.
+        ///
+        /// This fails with a [ConnectTimeout::TlsHandshake](crate::connector::ConnectTimeout::TlsHandshake) error.
+        pub fn with_tls_timeout(mut self, timeout: ::std::time::Duration) -> Self {
+            self.timeouts.tls_handshake = timeout;
+            self
+        }
+
+        connector_ctor!(@proxy);
+    };
+
+    (@proxy) => {
+        /// Tunnel the connection through this [ProxyConfig](crate::connector::ProxyConfig).
+        pub fn with_proxy(mut self, proxy: $crate::connector::ProxyConfig) -> Self {
+            self.proxy.replace(proxy);
+            self
+        }
+
+        /// Give up on the TCP connection (including the proxy's handshake) after this long. This defaults to 10 seconds.
+        ///
+        /// This fails with a [ConnectTimeout::Connect](crate::connector::ConnectTimeout::Connect) error.
+        pub fn with_connect_timeout(mut self, timeout: ::std::time::Duration) -> Self {
+            self.timeouts.connect = timeout;
+            self
+        }
     };
 }
 
@@ -151,9 +215,47 @@ pub use self::tokio::ConnectorNativeTls as TokioConnectorNativeTls;
 #[doc(inline)]
 pub use self::tokio::ConnectorOpenSsl as TokioConnectorOpenSsl;
 
+mod from_fn;
+pub use from_fn::{from_fn, FnConnector};
+
+mod tls_config;
+pub use tls_config::TlsConfig;
+
+mod proxy;
+pub use proxy::ProxyConfig;
+
+/// Connector for speaking IRC over a WebSocket, wrapping any other connector
+pub mod websocket;
+
+#[cfg(all(feature = "browser", target_arch = "wasm32"))]
+/// A WebSocket connection made by the browser, for `wasm32-unknown-unknown`
+pub mod browser;
+
+#[doc(inline)]
+pub use self::websocket::Connector as WebSocketConnector;
+
 /// The connector trait. This is used to abstract out runtimes.
 ///
-/// You can implement this on your own type to provide a custom connection behavior.
+/// You can implement this on your own type to provide a custom connection behavior (e.g. a unix socket to a local relay, or an instrumented socket).
+/// For simple cases, [from_fn()] creates one from a function.
+///
+/// The runner needs to both read and write to the stream at the same time, so `&Output` must also be `AsyncRead + AsyncWrite`. Wrapping your stream in an [`async_dup::Mutex`](https://docs.rs/async-dup/latest/async_dup/struct.Mutex.html) does that.
+///
+/// ```no_run
+/// # use twitchchat::{connector::Connector, BoxedFuture};
+/// # type MyStream = futures_lite::io::Cursor<Vec<u8>>;
+/// # async fn open_my_stream() -> std::io::Result<MyStream> { unimplemented!() }
+/// #[derive(Clone)]
+/// struct MyConnector;
+///
+/// impl Connector for MyConnector {
+///     type Output = async_dup::Mutex<MyStream>;
+///
+///     fn connect(&mut self) -> BoxedFuture<std::io::Result<Self::Output>> {
+///         Box::pin(async move { open_my_stream().await.map(async_dup::Mutex::new) })
+///     }
+/// }
+/// ```
 pub trait Connector: Send + Sync + Clone {
     /// Output IO type returned by calling `connect`
     ///
@@ -165,6 +267,159 @@ pub trait Connector: Send + Sync + Clone {
     fn connect(&mut self) -> crate::BoxedFuture<IoResult<Self::Output>>;
 }
 
+/// A step of connecting took too long.
+///
+/// This is returned wrapped in an [`std::io::Error`] with the [`TimedOut`](std::io::ErrorKind::TimedOut) kind, use [`ConnectTimeout::of()`] to get it back.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ConnectTimeout {
+    /// The TCP connection (or the proxy's handshake) wasn't established in time
+    Connect,
+    /// The TLS handshake didn't finish in time
+    TlsHandshake,
+}
+
+impl ConnectTimeout {
+    /// Get the timeout from this error, if it is one
+    pub fn of(err: &std::io::Error) -> Option<Self> {
+        err.get_ref()
+            .and_then(|e| e.downcast_ref::<Self>())
+            .copied()
+    }
+}
+
+impl std::fmt::Display for ConnectTimeout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Connect => f.write_str("timed out connecting"),
+            Self::TlsHandshake => f.write_str("timed out during the TLS handshake"),
+        }
+    }
+}
+
+impl std::error::Error for ConnectTimeout {}
+
+/// A TLS connector couldn't set up TLS, e.g. the handshake failed or a root certificate was invalid.
+///
+/// This is returned wrapped in an [`std::io::Error`] (with the kind of the original error), use [`TlsError::is_tls_error()`] to check for it.
+/// The original error is its [`source()`](std::error::Error::source()).
+#[derive(Debug)]
+pub struct TlsError {
+    inner: Box<dyn std::error::Error + Send + Sync>,
+}
+
+impl TlsError {
+    /// Was this error caused by TLS?
+    pub fn is_tls_error(err: &std::io::Error) -> bool {
+        err.get_ref().map_or(false, |err| err.is::<Self>())
+    }
+
+    #[allow(dead_code)]
+    pub(crate) fn wrap<E>(kind: std::io::ErrorKind, err: E) -> std::io::Error
+    where
+        E: Into<Box<dyn std::error::Error + Send + Sync>>,
+    {
+        std::io::Error::new(kind, Self { inner: err.into() })
+    }
+
+    #[allow(dead_code)]
+    pub(crate) fn from_io(err: std::io::Error) -> std::io::Error {
+        Self::wrap(err.kind(), err)
+    }
+}
+
+impl std::fmt::Display for TlsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "tls error: {}", self.inner)
+    }
+}
+
+impl std::error::Error for TlsError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&*self.inner)
+    }
+}
+
+// the timeouts used by the connectors
+#[cfg(any(
+    feature = "async-io",
+    feature = "async-std",
+    feature = "smol",
+    all(feature = "tokio", feature = "tokio-util")
+))]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) struct Timeouts {
+    pub(crate) connect: std::time::Duration,
+    pub(crate) tls_handshake: std::time::Duration,
+}
+
+#[cfg(any(
+    feature = "async-io",
+    feature = "async-std",
+    feature = "smol",
+    all(feature = "tokio", feature = "tokio-util")
+))]
+impl Default for Timeouts {
+    fn default() -> Self {
+        Self {
+            connect: std::time::Duration::from_secs(10),
+            tls_handshake: std::time::Duration::from_secs(10),
+        }
+    }
+}
+
+// run this step of connecting, failing with the `step` if it takes longer than `timeout`
+#[cfg(any(
+    feature = "async-io",
+    feature = "async-std",
+    feature = "smol",
+    all(feature = "tokio", feature = "tokio-util")
+))]
+pub(crate) async fn timeout<F, T>(
+    timeout: std::time::Duration,
+    step: ConnectTimeout,
+    fut: F,
+) -> IoResult<T>
+where
+    F: Future<Output = IoResult<T>>,
+{
+    let expired = async move {
+        futures_timer::Delay::new(timeout).await;
+        Err(std::io::Error::new(std::io::ErrorKind::TimedOut, step))
+    };
+    futures_lite::future::or(fut, expired).await
+}
+
+// standard base64, with padding
+pub(crate) fn base64(data: &[u8]) -> String {
+    const TABLE: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut out = String::with_capacity(data.len() * 4 / 3 + 4);
+    for chunk in data.chunks(3) {
+        let b = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let n = (u32::from(b[0]) << 16) | (u32::from(b[1]) << 8) | u32::from(b[2]);
+
+        out.push(TABLE[(n >> 18) as usize & 63] as char);
+        out.push(TABLE[(n >> 12) as usize & 63] as char);
+        match chunk.len() {
+            1 => out.push_str("=="),
+            2 => {
+                out.push(TABLE[(n >> 6) as usize & 63] as char);
+                out.push('=');
+            }
+            _ => {
+                out.push(TABLE[(n >> 6) as usize & 63] as char);
+                out.push(TABLE[n as usize & 63] as char);
+            }
+        }
+    }
+    out
+}
+
 // This is used because smol/async_io uses an indv. SocketAddr for their connect
 // instead of the normal ToSocketAddrs trait
 //
@@ -233,9 +488,75 @@ mod required {
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(any(
+        feature = "async-io",
+        feature = "async-std",
+        feature = "smol",
+        all(feature = "tokio", feature = "tokio-util")
+    ))]
+    fn timeouts() {
+        use std::time::Duration;
+
+        let fut = timeout(
+            Duration::from_millis(10),
+            ConnectTimeout::TlsHandshake,
+            futures_lite::future::pending::<IoResult<()>>(),
+        );
+        let err = futures_lite::future::block_on(fut).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::TimedOut);
+        assert_eq!(ConnectTimeout::of(&err), Some(ConnectTimeout::TlsHandshake));
+        assert!(matches!(
+            crate::runner::Error::from(err),
+            crate::runner::Error::TlsTimedOut
+        ));
+
+        let fut = timeout(Duration::from_secs(10), ConnectTimeout::Connect, async {
+            Ok(42)
+        });
+        assert_eq!(futures_lite::future::block_on(fut).unwrap(), 42);
+
+        let other = std::io::Error::new(std::io::ErrorKind::TimedOut, "something else");
+        assert_eq!(ConnectTimeout::of(&other), None);
+    }
+
+    #[test]
+    fn connect_errors() {
+        use crate::runner::Error;
+        use std::error::Error as _;
+
+        // the tls error keeps the kind, and the original error as its source
+        let err = TlsError::wrap(std::io::ErrorKind::InvalidData, "bad certificate");
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        let err = Error::from(err);
+        assert!(matches!(err, Error::Tls(..)));
+        let io = err.source().unwrap();
+        assert_eq!(io.to_string(), "tls error: bad certificate");
+        assert_eq!(io.source().unwrap().to_string(), "bad certificate");
+
+        let connector = from_fn(|| async {
+            Err::<futures_lite::io::Cursor<Vec<u8>>, _>(std::io::Error::new(
+                std::io::ErrorKind::ConnectionRefused,
+                "refused",
+            ))
+        });
+        let user_config = crate::UserConfig::anonymous();
+        let err =
+            futures_lite::future::block_on(crate::AsyncRunner::connect(connector, &user_config))
+                .unwrap_err();
+        assert!(
+            matches!(err, Error::Connect(err) if err.kind() == std::io::ErrorKind::ConnectionRefused)
+        );
+    }
+}
+
 #[cfg(test)]
 #[allow(dead_code)]
-mod testing {
+pub(crate) mod testing {
     use crate::connector::Connector as ConnectorTrait;
     use futures_lite::{AsyncRead, AsyncWrite};
 
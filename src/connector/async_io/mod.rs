@@ -3,6 +3,21 @@ use crate::BoxedFuture;
 
 type TcpStream = async_io::Async<std::net::TcpStream>;
 
+// connect directly, or through the proxy
+async fn tcp_connect(
+    addrs: &[std::net::SocketAddr],
+    proxy: &Option<crate::connector::ProxyConfig>,
+) -> std::io::Result<TcpStream> {
+    match proxy {
+        Some(proxy) => {
+            let mut stream = try_connect(proxy.addrs(), TcpStream::connect).await?;
+            proxy.tunnel(&mut stream, addrs).await?;
+            Ok(stream)
+        }
+        None => try_connect(addrs, TcpStream::connect).await,
+    }
+}
+
 mod non_tls;
 pub use non_tls::*;
 
@@ -1,5 +1,20 @@
 use crate::BoxedFuture;
 
+// connect directly, or through the proxy
+async fn tcp_connect(
+    addrs: &[std::net::SocketAddr],
+    proxy: &Option<crate::connector::ProxyConfig>,
+) -> std::io::Result<async_std::net::TcpStream> {
+    match proxy {
+        Some(proxy) => {
+            let mut stream = async_std::net::TcpStream::connect(proxy.addrs()).await?;
+            proxy.tunnel(&mut stream, addrs).await?;
+            Ok(stream)
+        }
+        None => async_std::net::TcpStream::connect(addrs).await,
+    }
+}
+
 mod non_tls;
 pub use non_tls::*;
 
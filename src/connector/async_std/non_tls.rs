@@ -4,6 +4,8 @@ use super::*;
 #[derive(Debug, Clone, PartialEq)]
 pub struct Connector {
     addrs: Vec<std::net::SocketAddr>,
+    proxy: Option<crate::connector::ProxyConfig>,
+    timeouts: crate::connector::Timeouts,
 }
 
 impl Connector {
@@ -16,8 +18,15 @@ impl crate::connector::Connector for Connector {
     type Output = async_std::net::TcpStream;
 
     fn connect(&mut self) -> BoxedFuture<std::io::Result<Self::Output>> {
-        let addrs = self.addrs.clone();
-        let fut = async move { async_std::net::TcpStream::connect(&*addrs).await };
+        let this = self.clone();
+        let fut = async move {
+            crate::connector::timeout(
+                this.timeouts.connect,
+                crate::connector::ConnectTimeout::Connect,
+                tcp_connect(&this.addrs, &this.proxy),
+            )
+            .await
+        };
         Box::pin(fut)
     }
 }
@@ -0,0 +1,301 @@
+use crate::channel::{Receiver, Sender};
+
+use futures_lite::{AsyncRead, AsyncWrite, StreamExt as _};
+use std::{
+    io::{Error, ErrorKind, Result},
+    pin::Pin,
+    task::{Context, Poll},
+};
+use wasm_bindgen::{closure::Closure, JsCast as _, JsValue};
+use web_sys::{CloseEvent, Event, MessageEvent};
+
+/// An IRC connection over the browser's [`WebSocket`](https://developer.mozilla.org/en-US/docs/Web/API/WebSocket).
+///
+/// The browser does the WebSocket (and TLS) handshake, so this only needs the URL, e.g. [`TWITCH_WS_ADDRESS_TLS`](crate::TWITCH_WS_ADDRESS_TLS).
+/// Reading from this yields the IRC lines of the text frames Twitch sent. Writing to this sends each complete IRC line (`\r\n` terminated) as its own text frame. Any trailing partial line is sent when it is flushed.
+///
+/// There is no clock on `wasm32-unknown-unknown`, so the [AsyncRunner](crate::AsyncRunner) can't be used in the browser. Use this with an [AsyncDecoder](crate::AsyncDecoder) and an [AsyncEncoder](crate::AsyncEncoder) instead, see [WebSocket::split()].
+///
+/// The connection is closed once both halves are dropped.
+///
+/// # Example
+/// ```no_run
+/// # #[cfg(all(feature = "browser", target_arch = "wasm32"))]
+/// # async fn demo() -> std::io::Result<()> {
+/// use twitchchat::{commands, connector::browser::WebSocket, messages::Commands, *};
+///
+/// let (read, write) = WebSocket::connect(TWITCH_WS_ADDRESS_TLS).await?.split();
+///
+/// let user_config = UserConfig::builder().anonymous().build().unwrap();
+/// let mut encoder = AsyncEncoder::new(write);
+/// encoder.encode(commands::register(&user_config)).await?;
+/// encoder.encode(commands::join("museun")).await?;
+///
+/// let mut decoder = AsyncDecoder::new(read);
+/// while let Ok(msg) = decoder.read_message().await {
+///     if let Ok(Commands::Privmsg(msg)) = Commands::from_irc(msg) {
+///         // show it on the overlay
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// # fn main() {}
+/// ```
+#[derive(Debug)]
+pub struct WebSocket {
+    read: ReadHalf,
+    write: WriteHalf,
+}
+
+impl WebSocket {
+    /// Open a WebSocket to this URL, and wait for it to connect
+    pub async fn connect(url: &str) -> Result<Self> {
+        // this fails if the url is invalid
+        let socket = web_sys::WebSocket::new(url).map_err(js_error)?;
+
+        let (events_tx, events) = crate::channel::unbounded();
+        let callbacks = Callbacks::attach(&socket, events_tx);
+        if !matches!(events.recv().await, Some(Incoming::Opened)) {
+            callbacks.detach(&socket);
+            let _ = socket.close();
+            return Err(Error::new(
+                ErrorKind::ConnectionRefused,
+                "the WebSocket couldn't connect",
+            ));
+        }
+
+        let (outgoing_tx, outgoing) = crate::channel::unbounded();
+        wasm_bindgen_futures::spawn_local(drive(socket, outgoing, callbacks));
+
+        Ok(Self {
+            read: ReadHalf {
+                events,
+                buf: Vec::new(),
+                pos: 0,
+                _outgoing: outgoing_tx.clone(),
+            },
+            write: WriteHalf {
+                outgoing: outgoing_tx,
+                partial: Vec::new(),
+            },
+        })
+    }
+
+    /// Split this into its reading and writing halves, e.g. for an [AsyncDecoder](crate::AsyncDecoder) and an [AsyncEncoder](crate::AsyncEncoder)
+    pub fn split(self) -> (ReadHalf, WriteHalf) {
+        (self.read, self.write)
+    }
+}
+
+impl AsyncRead for WebSocket {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<Result<usize>> {
+        Pin::new(&mut self.read).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for WebSocket {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize>> {
+        Pin::new(&mut self.write).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        Pin::new(&mut self.write).poll_flush(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        Pin::new(&mut self.write).poll_close(cx)
+    }
+}
+
+/// The reading half of a [WebSocket]
+pub struct ReadHalf {
+    events: Receiver<Incoming>,
+    // the frame being read
+    buf: Vec<u8>,
+    pos: usize,
+    // the connection stays open while this is alive
+    _outgoing: Sender<String>,
+}
+
+impl std::fmt::Debug for ReadHalf {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ReadHalf").finish()
+    }
+}
+
+impl AsyncRead for ReadHalf {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<Result<usize>> {
+        let this = &mut *self;
+        while this.pos == this.buf.len() {
+            match futures_lite::ready!(this.events.poll_next(cx)) {
+                Some(Incoming::Text(text)) => {
+                    this.buf = text.into_bytes();
+                    this.pos = 0;
+                }
+                Some(Incoming::Opened) => {}
+                Some(Incoming::Failed) => {
+                    return Poll::Ready(Err(Error::new(
+                        ErrorKind::ConnectionAborted,
+                        "the WebSocket failed",
+                    )))
+                }
+                Some(Incoming::Closed) | None => return Poll::Ready(Ok(0)),
+            }
+        }
+
+        let n = std::cmp::min(buf.len(), this.buf.len() - this.pos);
+        buf[..n].copy_from_slice(&this.buf[this.pos..this.pos + n]);
+        this.pos += n;
+        Poll::Ready(Ok(n))
+    }
+}
+
+/// The writing half of a [WebSocket]
+pub struct WriteHalf {
+    outgoing: Sender<String>,
+    // outgoing data which hasn't been terminated with a '\n' yet
+    partial: Vec<u8>,
+}
+
+impl std::fmt::Debug for WriteHalf {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WriteHalf").finish()
+    }
+}
+
+impl WriteHalf {
+    fn send(&self, frame: Vec<u8>) -> Result<()> {
+        let frame =
+            String::from_utf8(frame).map_err(|err| Error::new(ErrorKind::InvalidData, err))?;
+        self.outgoing
+            .try_send(frame)
+            .map_err(|_| Error::new(ErrorKind::BrokenPipe, "the WebSocket was closed"))
+    }
+}
+
+impl AsyncWrite for WriteHalf {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize>> {
+        let this = &mut *self;
+        this.partial.extend_from_slice(buf);
+        while let Some(end) = this.partial.iter().position(|&c| c == b'\n') {
+            let line = this.partial.drain(..=end).collect();
+            if let Err(err) = this.send(line) {
+                return Poll::Ready(Err(err));
+            }
+        }
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<()>> {
+        let this = &mut *self;
+        if this.partial.is_empty() {
+            return Poll::Ready(Ok(()));
+        }
+        let partial = std::mem::take(&mut this.partial);
+        Poll::Ready(this.send(partial))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        self.poll_flush(cx)
+    }
+}
+
+// what the socket's callbacks saw
+#[derive(Clone)]
+enum Incoming {
+    Opened,
+    Text(String),
+    Closed,
+    Failed,
+}
+
+// the socket only borrows these, so they have to be kept alive while it's open
+struct Callbacks {
+    _on_open: Closure<dyn FnMut(Event)>,
+    _on_message: Closure<dyn FnMut(MessageEvent)>,
+    _on_close: Closure<dyn FnMut(CloseEvent)>,
+    _on_error: Closure<dyn FnMut(Event)>,
+}
+
+impl Callbacks {
+    fn attach(socket: &web_sys::WebSocket, events: Sender<Incoming>) -> Self {
+        let tx = events.clone();
+        let on_open = Closure::<dyn FnMut(Event)>::new(move |_: Event| {
+            let _ = tx.try_send(Incoming::Opened);
+        });
+
+        let tx = events.clone();
+        let on_message = Closure::<dyn FnMut(MessageEvent)>::new(move |msg: MessageEvent| {
+            // Twitch only sends text frames
+            if let Some(text) = msg.data().as_string() {
+                let _ = tx.try_send(Incoming::Text(text));
+            }
+        });
+
+        let tx = events.clone();
+        let on_close = Closure::<dyn FnMut(CloseEvent)>::new(move |_: CloseEvent| {
+            let _ = tx.try_send(Incoming::Closed);
+        });
+
+        let on_error = Closure::<dyn FnMut(Event)>::new(move |_: Event| {
+            let _ = events.try_send(Incoming::Failed);
+        });
+
+        socket.set_onopen(Some(on_open.as_ref().unchecked_ref()));
+        socket.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+        socket.set_onclose(Some(on_close.as_ref().unchecked_ref()));
+        socket.set_onerror(Some(on_error.as_ref().unchecked_ref()));
+
+        Self {
+            _on_open: on_open,
+            _on_message: on_message,
+            _on_close: on_close,
+            _on_error: on_error,
+        }
+    }
+
+    // this has to be done before they're dropped, or the browser would call a freed closure
+    fn detach(self, socket: &web_sys::WebSocket) {
+        socket.set_onopen(None);
+        socket.set_onmessage(None);
+        socket.set_onclose(None);
+        socket.set_onerror(None);
+        drop(self);
+    }
+}
+
+// the socket (and its callbacks) aren't Send, so they stay on this task. it sends the written
+// lines until both halves are dropped, then closes the socket
+async fn drive(socket: web_sys::WebSocket, outgoing: Receiver<String>, callbacks: Callbacks) {
+    while let Some(frame) = outgoing.recv().await {
+        if let Err(err) = socket.send_with_str(&frame) {
+            log::warn!("cannot send on the WebSocket: {:?}", err);
+            break;
+        }
+    }
+
+    // closing the receiver fails any later writes
+    outgoing.close();
+    callbacks.detach(&socket);
+    let _ = socket.close();
+}
+
+fn js_error(err: JsValue) -> Error {
+    Error::new(ErrorKind::InvalidInput, format!("{:?}", err))
+}
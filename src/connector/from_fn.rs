@@ -0,0 +1,80 @@
+use crate::BoxedFuture;
+
+use futures_lite::{AsyncRead, AsyncWrite};
+use std::{future::Future, io::Result};
+
+/// Create a [Connector](super::Connector) from a function that opens a stream.
+///
+/// This lets you use any transport (e.g. a unix socket to a local relay, an in-memory stream for tests, or an instrumented socket) without implementing the trait yourself.
+///
+/// The function is called for every connection attempt. The stream is wrapped in an [`async_dup::Mutex`](https://docs.rs/async-dup/latest/async_dup/struct.Mutex.html) so it can be shared between the reader and the writer.
+///
+/// ```no_run
+/// # use twitchchat::connector;
+/// // e.g. replay a recorded session
+/// let connector = connector::from_fn(|| async {
+///     let data = std::fs::read("session.log")?;
+///     Ok(futures_lite::io::Cursor::new(data))
+/// });
+/// ```
+pub fn from_fn<F, R, S>(func: F) -> FnConnector<F>
+where
+    F: FnMut() -> R + Send + Sync + Clone,
+    R: Future<Output = Result<S>> + Send + Sync + 'static,
+    S: AsyncRead + AsyncWrite + Send + Unpin + 'static,
+{
+    FnConnector { func }
+}
+
+/// A [Connector](super::Connector) created by [from_fn()]
+#[derive(Clone)]
+pub struct FnConnector<F> {
+    func: F,
+}
+
+impl<F> std::fmt::Debug for FnConnector<F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FnConnector").finish()
+    }
+}
+
+impl<F, R, S> super::Connector for FnConnector<F>
+where
+    F: FnMut() -> R + Send + Sync + Clone,
+    R: Future<Output = Result<S>> + Send + Sync + 'static,
+    S: AsyncRead + AsyncWrite + Send + Unpin + 'static,
+{
+    type Output = async_dup::Mutex<S>;
+
+    fn connect(&mut self) -> BoxedFuture<Result<Self::Output>> {
+        let fut = (self.func)();
+        Box::pin(async move { fut.await.map(async_dup::Mutex::new) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connector::Connector as _;
+    use futures_lite::{io::Cursor, AsyncReadExt as _};
+
+    #[test]
+    fn assert_connector_trait_is_fulfilled() {
+        use crate::connector::testing::*;
+
+        let connector = from_fn(|| async { Ok(Cursor::new(Vec::<u8>::new())) });
+        assert_obj_is_sane(connector);
+    }
+
+    #[test]
+    fn connect() {
+        let mut connector = from_fn(|| async { Ok(Cursor::new(b"PING :foo\r\n".to_vec())) });
+
+        for _ in 0..2 {
+            let stream = futures_lite::future::block_on(connector.connect()).unwrap();
+            let mut out = String::new();
+            futures_lite::future::block_on((&stream).read_to_string(&mut out)).unwrap();
+            assert_eq!(out, "PING :foo\r\n");
+        }
+    }
+}
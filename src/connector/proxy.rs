@@ -0,0 +1,433 @@
+use std::{
+    io::Result,
+    net::{SocketAddr, ToSocketAddrs},
+};
+
+// for the tunnel, which is only used by the runtime connectors
+#[cfg(any(
+    feature = "async-io",
+    feature = "async-std",
+    feature = "smol",
+    all(feature = "tokio", feature = "tokio-util")
+))]
+use {
+    futures_lite::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    std::io::{Error, ErrorKind},
+};
+
+// the CONNECT response shouldn't ever be this large
+#[cfg(any(
+    feature = "async-io",
+    feature = "async-std",
+    feature = "smol",
+    all(feature = "tokio", feature = "tokio-util")
+))]
+const MAX_HEADER_SIZE: usize = 8 * 1024;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Kind {
+    Socks5,
+    HttpConnect,
+}
+
+/// A proxy that a connector should tunnel its connection through.
+///
+/// This supports [SOCKS5](https://tools.ietf.org/html/rfc1928) (optionally with a [username and password](https://tools.ietf.org/html/rfc1929)) and HTTP `CONNECT` tunnels (optionally with basic auth).
+///
+/// For the TLS connectors, the TLS handshake happens inside of the tunnel.
+///
+/// ```no_run
+/// # use twitchchat::connector::ProxyConfig;
+/// # fn main() -> std::io::Result<()> {
+/// let proxy = ProxyConfig::socks5("127.0.0.1:1080")?.with_auth("user", "hunter2");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProxyConfig {
+    kind: Kind,
+    addrs: Vec<SocketAddr>,
+    auth: Option<(String, String)>,
+}
+
+impl ProxyConfig {
+    /// Use a SOCKS5 proxy at this address
+    pub fn socks5<A>(addrs: A) -> Result<Self>
+    where
+        A: ToSocketAddrs,
+    {
+        Self::new(Kind::Socks5, addrs)
+    }
+
+    /// Use an HTTP proxy at this address, via a `CONNECT` tunnel
+    pub fn http_connect<A>(addrs: A) -> Result<Self>
+    where
+        A: ToSocketAddrs,
+    {
+        Self::new(Kind::HttpConnect, addrs)
+    }
+
+    /// Authenticate with the proxy using this username and password
+    pub fn with_auth(mut self, username: impl Into<String>, password: impl Into<String>) -> Self {
+        self.auth.replace((username.into(), password.into()));
+        self
+    }
+
+    /// The addresses of the proxy
+    pub fn addrs(&self) -> &[SocketAddr] {
+        &self.addrs
+    }
+
+    fn new<A>(kind: Kind, addrs: A) -> Result<Self>
+    where
+        A: ToSocketAddrs,
+    {
+        addrs.to_socket_addrs().map(|addrs| Self {
+            kind,
+            addrs: addrs.collect(),
+            auth: None,
+        })
+    }
+}
+
+// these are only used by the runtime connectors
+#[cfg(any(
+    feature = "async-io",
+    feature = "async-std",
+    feature = "smol",
+    all(feature = "tokio", feature = "tokio-util")
+))]
+impl ProxyConfig {
+    /// Ask the proxy on the other end of this `stream` to open a tunnel to the first of the `targets`
+    pub(crate) async fn tunnel<S>(&self, stream: &mut S, targets: &[SocketAddr]) -> Result<()>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        let target = targets.first().ok_or_else(|| {
+            Error::new(
+                ErrorKind::AddrNotAvailable,
+                "no address to connect to through the proxy",
+            )
+        })?;
+
+        match self.kind {
+            Kind::Socks5 => self.socks5_tunnel(stream, target).await,
+            Kind::HttpConnect => self.http_tunnel(stream, target).await,
+        }
+    }
+
+    async fn socks5_tunnel<S>(&self, stream: &mut S, target: &SocketAddr) -> Result<()>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        const VERSION: u8 = 5;
+        const NO_AUTH: u8 = 0x00;
+        const USER_PASS: u8 = 0x02;
+        const NO_ACCEPTABLE: u8 = 0xFF;
+
+        let greeting: &[u8] = match self.auth {
+            Some(..) => &[VERSION, 2, NO_AUTH, USER_PASS],
+            None => &[VERSION, 1, NO_AUTH],
+        };
+        stream.write_all(greeting).await?;
+        stream.flush().await?;
+
+        let mut reply = [0_u8; 2];
+        stream.read_exact(&mut reply).await?;
+        if reply[0] != VERSION {
+            return Err(invalid_data("the proxy is not a SOCKS5 proxy"));
+        }
+
+        match (reply[1], &self.auth) {
+            (NO_AUTH, _) => {}
+            (USER_PASS, Some((user, pass))) => {
+                if user.len() > 255 || pass.len() > 255 {
+                    return Err(Error::new(
+                        ErrorKind::InvalidInput,
+                        "SOCKS5 username and password must be at most 255 bytes",
+                    ));
+                }
+
+                let mut req = vec![1, user.len() as u8];
+                req.extend_from_slice(user.as_bytes());
+                req.push(pass.len() as u8);
+                req.extend_from_slice(pass.as_bytes());
+                stream.write_all(&req).await?;
+                stream.flush().await?;
+
+                let mut reply = [0_u8; 2];
+                stream.read_exact(&mut reply).await?;
+                if reply[1] != 0 {
+                    return Err(Error::new(
+                        ErrorKind::PermissionDenied,
+                        "the SOCKS5 proxy rejected the username and password",
+                    ));
+                }
+            }
+            (NO_ACCEPTABLE, _) | (USER_PASS, None) => {
+                return Err(Error::new(
+                    ErrorKind::PermissionDenied,
+                    "the SOCKS5 proxy requires authentication",
+                ))
+            }
+            (method, _) => {
+                return Err(invalid_data(format!(
+                    "the SOCKS5 proxy chose an unsupported method: {}",
+                    method
+                )))
+            }
+        }
+
+        // CONNECT
+        let mut req = vec![VERSION, 1, 0];
+        match target {
+            SocketAddr::V4(addr) => {
+                req.push(1);
+                req.extend_from_slice(&addr.ip().octets());
+            }
+            SocketAddr::V6(addr) => {
+                req.push(4);
+                req.extend_from_slice(&addr.ip().octets());
+            }
+        }
+        req.extend_from_slice(&target.port().to_be_bytes());
+        stream.write_all(&req).await?;
+        stream.flush().await?;
+
+        let mut reply = [0_u8; 4];
+        stream.read_exact(&mut reply).await?;
+        if reply[1] != 0 {
+            let reason = match reply[1] {
+                1 => "general failure",
+                2 => "connection not allowed by ruleset",
+                3 => "network unreachable",
+                4 => "host unreachable",
+                5 => "connection refused",
+                6 => "TTL expired",
+                7 => "command not supported",
+                8 => "address type not supported",
+                _ => "unknown error",
+            };
+            return Err(Error::new(
+                ErrorKind::ConnectionRefused,
+                format!("the SOCKS5 proxy could not connect: {}", reason),
+            ));
+        }
+
+        // skip the bound address
+        let len = match reply[3] {
+            1 => 4,
+            4 => 16,
+            3 => {
+                let mut len = [0_u8; 1];
+                stream.read_exact(&mut len).await?;
+                len[0] as usize
+            }
+            _ => return Err(invalid_data("the SOCKS5 proxy sent an invalid address")),
+        };
+        let mut bound = vec![0_u8; len + 2];
+        stream.read_exact(&mut bound).await?;
+
+        Ok(())
+    }
+
+    async fn http_tunnel<S>(&self, stream: &mut S, target: &SocketAddr) -> Result<()>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        let mut req = format!(
+            "CONNECT {target} HTTP/1.1\r\nHost: {target}\r\n",
+            target = target
+        );
+        if let Some((user, pass)) = &self.auth {
+            let credentials = super::base64(format!("{}:{}", user, pass).as_bytes());
+            req.push_str(&format!("Proxy-Authorization: Basic {}\r\n", credentials));
+        }
+        req.push_str("\r\n");
+        stream.write_all(req.as_bytes()).await?;
+        stream.flush().await?;
+
+        // read a byte at a time so nothing after the response is consumed
+        let mut buf = Vec::with_capacity(128);
+        let mut byte = [0_u8; 1];
+        while !buf.ends_with(b"\r\n\r\n") {
+            if buf.len() > MAX_HEADER_SIZE {
+                return Err(invalid_data("the proxy response was too large"));
+            }
+            stream.read_exact(&mut byte).await?;
+            buf.push(byte[0]);
+        }
+
+        let head =
+            std::str::from_utf8(&buf).map_err(|err| Error::new(ErrorKind::InvalidData, err))?;
+        let status = head.lines().next().unwrap_or_default();
+        match status.split(' ').nth(1) {
+            Some(code) if code.starts_with('2') => Ok(()),
+            Some("407") => Err(Error::new(
+                ErrorKind::PermissionDenied,
+                format!("the proxy requires authentication: {}", status),
+            )),
+            _ => Err(Error::new(
+                ErrorKind::ConnectionRefused,
+                format!("the proxy rejected the tunnel: {}", status),
+            )),
+        }
+    }
+}
+
+#[cfg(any(
+    feature = "async-io",
+    feature = "async-std",
+    feature = "smol",
+    all(feature = "tokio", feature = "tokio-util")
+))]
+fn invalid_data(msg: impl Into<String>) -> Error {
+    Error::new(ErrorKind::InvalidData, msg.into())
+}
+
+#[cfg(all(
+    test,
+    any(
+        feature = "async-io",
+        feature = "async-std",
+        feature = "smol",
+        all(feature = "tokio", feature = "tokio-util")
+    )
+))]
+mod tests {
+    use super::*;
+    use futures_lite::future::block_on;
+    use std::{
+        pin::Pin,
+        task::{Context, Poll},
+    };
+
+    // reads come from 'input', writes go to 'output'
+    struct Mock {
+        input: futures_lite::io::Cursor<Vec<u8>>,
+        output: Vec<u8>,
+    }
+
+    impl Mock {
+        fn new(input: &[u8]) -> Self {
+            Self {
+                input: futures_lite::io::Cursor::new(input.to_vec()),
+                output: Vec::new(),
+            }
+        }
+    }
+
+    impl AsyncRead for Mock {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut [u8],
+        ) -> Poll<Result<usize>> {
+            Pin::new(&mut self.input).poll_read(cx, buf)
+        }
+    }
+
+    impl AsyncWrite for Mock {
+        fn poll_write(
+            mut self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<Result<usize>> {
+            self.output.extend_from_slice(buf);
+            Poll::Ready(Ok(buf.len()))
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    fn target() -> Vec<SocketAddr> {
+        vec!["10.0.0.1:6667".parse().unwrap()]
+    }
+
+    #[test]
+    fn socks5() {
+        let proxy = ProxyConfig::socks5("127.0.0.1:1080").unwrap();
+        let mut mock = Mock::new(&[5, 0, 5, 0, 0, 1, 10, 0, 0, 2, 0x1A, 0x0B]);
+        block_on(proxy.tunnel(&mut mock, &target())).unwrap();
+
+        assert_eq!(
+            mock.output,
+            vec![5, 1, 0, 5, 1, 0, 1, 10, 0, 0, 1, 0x1A, 0x0B] //
+        );
+    }
+
+    #[test]
+    fn socks5_auth() {
+        let proxy = ProxyConfig::socks5("127.0.0.1:1080")
+            .unwrap()
+            .with_auth("foo", "bar");
+
+        let mut mock = Mock::new(&[5, 2, 1, 0, 5, 0, 0, 1, 10, 0, 0, 2, 0x1A, 0x0B]);
+        block_on(proxy.tunnel(&mut mock, &target())).unwrap();
+
+        let mut expected = vec![5, 2, 0, 2];
+        expected.extend_from_slice(&[1, 3, b'f', b'o', b'o', 3, b'b', b'a', b'r']);
+        expected.extend_from_slice(&[5, 1, 0, 1, 10, 0, 0, 1, 0x1A, 0x0B]);
+        assert_eq!(mock.output, expected);
+
+        // bad credentials
+        let mut mock = Mock::new(&[5, 2, 1, 1]);
+        let err = block_on(proxy.tunnel(&mut mock, &target())).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+    }
+
+    #[test]
+    fn socks5_rejected() {
+        let proxy = ProxyConfig::socks5("127.0.0.1:1080").unwrap();
+
+        let mut mock = Mock::new(&[5, 0xFF]);
+        let err = block_on(proxy.tunnel(&mut mock, &target())).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+
+        let mut mock = Mock::new(&[5, 0, 5, 5, 0, 1, 0, 0, 0, 0, 0, 0]);
+        let err = block_on(proxy.tunnel(&mut mock, &target())).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::ConnectionRefused);
+    }
+
+    #[test]
+    fn http_connect() {
+        let proxy = ProxyConfig::http_connect("127.0.0.1:8080")
+            .unwrap()
+            .with_auth("foo", "bar");
+
+        let mut mock = Mock::new(b"HTTP/1.1 200 Connection established\r\n\r\n:tmi.twitch.tv");
+        block_on(proxy.tunnel(&mut mock, &target())).unwrap();
+
+        assert_eq!(
+            std::str::from_utf8(&mock.output).unwrap(),
+            "CONNECT 10.0.0.1:6667 HTTP/1.1\r\n\
+             Host: 10.0.0.1:6667\r\n\
+             Proxy-Authorization: Basic Zm9vOmJhcg==\r\n\
+             \r\n"
+        );
+
+        // nothing after the response was read
+        let mut rest = String::new();
+        block_on(mock.input.read_to_string(&mut rest)).unwrap();
+        assert_eq!(rest, ":tmi.twitch.tv");
+    }
+
+    #[test]
+    fn http_connect_rejected() {
+        let proxy = ProxyConfig::http_connect("127.0.0.1:8080").unwrap();
+
+        let mut mock = Mock::new(b"HTTP/1.1 407 Proxy Authentication Required\r\n\r\n");
+        let err = block_on(proxy.tunnel(&mut mock, &target())).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+
+        let mut mock = Mock::new(b"HTTP/1.1 403 Forbidden\r\n\r\n");
+        let err = block_on(proxy.tunnel(&mut mock, &target())).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::ConnectionRefused);
+    }
+}
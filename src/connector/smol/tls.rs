@@ -8,6 +8,8 @@ use super::*;
 pub struct ConnectorTls {
     addrs: Vec<std::net::SocketAddr>,
     tls_domain: String,
+    proxy: Option<crate::connector::ProxyConfig>,
+    timeouts: crate::connector::Timeouts,
 }
 
 impl ConnectorTls {
@@ -22,11 +24,22 @@ impl crate::connector::Connector for ConnectorTls {
     fn connect(&mut self) -> BoxedFuture<std::io::Result<Self::Output>> {
         let this = self.clone();
         let fut = async move {
-            let stream = try_connect(&*this.addrs, TcpStream::connect).await?;
-            async_tls::TlsConnector::new()
-                .connect(this.tls_domain, stream)
-                .await
-                .map(async_dup::Mutex::new)
+            let stream = crate::connector::timeout(
+                this.timeouts.connect,
+                crate::connector::ConnectTimeout::Connect,
+                tcp_connect(&this.addrs, &this.proxy),
+            )
+            .await?;
+            let handshake = async_tls::TlsConnector::new().connect(this.tls_domain, stream);
+            let handshake =
+                async move { handshake.await.map_err(crate::connector::TlsError::from_io) };
+            crate::connector::timeout(
+                this.timeouts.tls_handshake,
+                crate::connector::ConnectTimeout::TlsHandshake,
+                handshake,
+            )
+            .await
+            .map(async_dup::Mutex::new)
         };
         Box::pin(fut)
     }
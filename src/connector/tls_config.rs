@@ -0,0 +1,109 @@
+/// Configuration for the TLS connectors.
+///
+/// This is supported by the [`rustls`][rustls] and [`native-tls`][native-tls] `tokio` connectors, via their `with_tls_config` methods.
+///
+/// By default, this uses the backend's default root certificates and sends the TLS domain as the SNI.
+///
+/// [rustls]: https://docs.rs/rustls/0.19/rustls/
+/// [native-tls]: https://docs.rs/native-tls/0.2/native_tls/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TlsConfig {
+    root_certificates: Vec<Vec<u8>>,
+    default_roots: bool,
+    server_name: Option<String>,
+    sni: bool,
+}
+
+impl Default for TlsConfig {
+    fn default() -> Self {
+        Self {
+            root_certificates: Vec::new(),
+            default_roots: true,
+            server_name: None,
+            sni: true,
+        }
+    }
+}
+
+impl TlsConfig {
+    /// Trust this (DER encoded) root certificate, in addition to the default ones.
+    pub fn add_root_certificate(mut self, der: impl Into<Vec<u8>>) -> Self {
+        self.root_certificates.push(der.into());
+        self
+    }
+
+    /// Don't trust the backend's default root certificates, only the ones added with [TlsConfig::add_root_certificate()].
+    pub fn without_default_roots(mut self) -> Self {
+        self.default_roots = false;
+        self
+    }
+
+    /// Verify the server's certificate against this name, instead of the connector's TLS domain.
+    ///
+    /// This is also the name sent as the SNI.
+    pub fn server_name(mut self, name: impl Into<String>) -> Self {
+        self.server_name.replace(name.into());
+        self
+    }
+
+    /// Set whether the server name should be sent as the SNI.
+    pub fn sni(mut self, sni: bool) -> Self {
+        self.sni = sni;
+        self
+    }
+
+    /// The (DER encoded) root certificates that were added
+    pub fn root_certificates(&self) -> impl Iterator<Item = &[u8]> + '_ {
+        self.root_certificates.iter().map(|s| &**s)
+    }
+
+    /// Whether the backend's default roots certificates are trusted
+    pub fn uses_default_roots(&self) -> bool {
+        self.default_roots
+    }
+
+    /// The name the server's certificate is verified against, falling back to the connector's `tls_domain`
+    pub fn get_server_name<'a>(&'a self, tls_domain: &'a str) -> &'a str {
+        self.server_name.as_deref().unwrap_or(tls_domain)
+    }
+
+    /// Whether the server name is sent as the SNI
+    pub fn uses_sni(&self) -> bool {
+        self.sni
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tls_config() {
+        let config = TlsConfig::default();
+        assert!(config.uses_default_roots());
+        assert!(config.uses_sni());
+        assert_eq!(config.root_certificates().count(), 0);
+        assert_eq!(
+            config.get_server_name(crate::TWITCH_TLS_DOMAIN),
+            crate::TWITCH_TLS_DOMAIN
+        );
+
+        let config = TlsConfig::default()
+            .add_root_certificate(vec![1, 2, 3])
+            .add_root_certificate(&[4, 5][..])
+            .without_default_roots()
+            .server_name("localhost")
+            .sni(false);
+
+        assert!(!config.uses_default_roots());
+        assert!(!config.uses_sni());
+        assert_eq!(
+            config.root_certificates().collect::<Vec<_>>(),
+            vec![&[1, 2, 3][..], &[4, 5][..]]
+        );
+        assert_eq!(
+            config.get_server_name(crate::TWITCH_TLS_DOMAIN),
+            "localhost"
+        );
+    }
+}
@@ -1,5 +1,23 @@
 use crate::BoxedFuture;
 
+// connect directly, or through the proxy
+async fn tcp_connect(
+    addrs: &[std::net::SocketAddr],
+    proxy: &Option<crate::connector::ProxyConfig>,
+) -> std::io::Result<tokio::net::TcpStream> {
+    use tokio_util::compat::TokioAsyncReadCompatExt as _;
+    match proxy {
+        Some(proxy) => {
+            let mut stream = tokio::net::TcpStream::connect(proxy.addrs())
+                .await?
+                .compat();
+            proxy.tunnel(&mut stream, addrs).await?;
+            Ok(stream.into_inner())
+        }
+        None => tokio::net::TcpStream::connect(addrs).await,
+    }
+}
+
 mod non_tls;
 pub use non_tls::*;
 
@@ -1,18 +1,24 @@
 use super::*;
+use crate::connector::TlsError;
 
 /// A `tokio` connector that uses `tokio-native-tls` (a `native-tls` wrapper). This uses TLS.
 ///
 /// To use this type, ensure you set up the 'TLS Domain' in the configuration.
 ///
 /// The crate provides the 'TLS domain' for Twitch in the root of this crate.
+///
+/// Use [ConnectorNativeTls::with_tls_config()] for custom root certificates.
 #[derive(Debug, Clone, PartialEq)]
 pub struct ConnectorNativeTls {
     addrs: Vec<std::net::SocketAddr>,
     tls_domain: String,
+    tls_config: crate::connector::TlsConfig,
+    proxy: Option<crate::connector::ProxyConfig>,
+    timeouts: crate::connector::Timeouts,
 }
 
 impl ConnectorNativeTls {
-    connector_ctor!(tls:
+    connector_ctor!(tls_config:
         /// [`tokio`](https://docs.rs/tokio/0.2/tokio/) (using [`tokio-native-tls`](https://docs.rs/tokio-native-tls/latest/tokio_native_tls/))
     );
 }
@@ -29,15 +35,41 @@ impl crate::connector::Connector for ConnectorNativeTls {
         let fut = async move {
             use tokio_util::compat::TokioAsyncReadCompatExt as _;
 
-            let connector: tokio_native_tls::TlsConnector = ::native_tls::TlsConnector::new()
-                .map_err(|err| std::io::Error::new(std::io::ErrorKind::Other, err))?
+            let config = &this.tls_config;
+
+            let mut builder = ::native_tls::TlsConnector::builder();
+            builder
+                .disable_built_in_roots(!config.uses_default_roots())
+                .use_sni(config.uses_sni());
+            for der in config.root_certificates() {
+                let cert = ::native_tls::Certificate::from_der(der)
+                    .map_err(|err| TlsError::wrap(std::io::ErrorKind::InvalidData, err))?;
+                builder.add_root_certificate(cert);
+            }
+
+            let connector: tokio_native_tls::TlsConnector = builder
+                .build()
+                .map_err(|err| TlsError::wrap(std::io::ErrorKind::Other, err))?
                 .into();
 
-            let stream = tokio::net::TcpStream::connect(&*this.addrs).await?;
-            let stream = connector
-                .connect(&this.tls_domain, stream)
-                .await
-                .map_err(|err| std::io::Error::new(std::io::ErrorKind::Other, err))?;
+            let stream = crate::connector::timeout(
+                this.timeouts.connect,
+                crate::connector::ConnectTimeout::Connect,
+                tcp_connect(&this.addrs, &this.proxy),
+            )
+            .await?;
+            let handshake = async {
+                connector
+                    .connect(config.get_server_name(&this.tls_domain), stream)
+                    .await
+                    .map_err(|err| TlsError::wrap(std::io::ErrorKind::Other, err))
+            };
+            let stream = crate::connector::timeout(
+                this.timeouts.tls_handshake,
+                crate::connector::ConnectTimeout::TlsHandshake,
+                handshake,
+            )
+            .await?;
 
             Ok(async_dup::Mutex::new(stream.compat()))
         };
@@ -58,4 +90,17 @@ mod tests {
         assert_type_is_read_write::<<ConnectorNativeTls as C>::Output>();
         assert_obj_is_sane(ConnectorNativeTls::twitch().unwrap());
     }
+
+    #[test]
+    fn invalid_root_certificate() {
+        use crate::connector::{Connector as _, TlsConfig};
+
+        let mut connector = ConnectorNativeTls::custom("127.0.0.1:6697", crate::TWITCH_TLS_DOMAIN)
+            .unwrap()
+            .with_tls_config(TlsConfig::default().add_root_certificate(&b"not a cert"[..]));
+
+        // this fails before anything is connected
+        let err = futures_lite::future::block_on(connector.connect()).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    }
 }
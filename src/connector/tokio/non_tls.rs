@@ -4,6 +4,8 @@ use super::*;
 #[derive(Debug, Clone, PartialEq)]
 pub struct Connector {
     addrs: Vec<std::net::SocketAddr>,
+    proxy: Option<crate::connector::ProxyConfig>,
+    timeouts: crate::connector::Timeouts,
 }
 
 impl Connector {
@@ -16,10 +18,15 @@ impl crate::connector::Connector for Connector {
     type Output = async_dup::Mutex<tokio_util::compat::Compat<tokio::net::TcpStream>>;
 
     fn connect(&mut self) -> BoxedFuture<std::io::Result<Self::Output>> {
-        let addrs = self.addrs.clone();
+        let this = self.clone();
         let fut = async move {
             use tokio_util::compat::TokioAsyncReadCompatExt as _;
-            let stream = tokio::net::TcpStream::connect(&*addrs).await?;
+            let stream = crate::connector::timeout(
+                this.timeouts.connect,
+                crate::connector::ConnectTimeout::Connect,
+                tcp_connect(&this.addrs, &this.proxy),
+            )
+            .await?;
             Ok(async_dup::Mutex::new(stream.compat()))
         };
         Box::pin(fut)
@@ -1,6 +1,7 @@
 use super::*;
 
-use std::io::{Error, ErrorKind};
+use crate::connector::TlsError;
+use std::io::ErrorKind;
 
 /// A `tokio` connector that uses `tokio-openssl` (an `openssl` wrapper). This uses TLS.
 ///
@@ -11,6 +12,8 @@ use std::io::{Error, ErrorKind};
 pub struct ConnectorOpenSsl {
     addrs: Vec<std::net::SocketAddr>,
     tls_domain: String,
+    proxy: Option<crate::connector::ProxyConfig>,
+    timeouts: crate::connector::Timeouts,
 }
 
 impl ConnectorOpenSsl {
@@ -33,16 +36,31 @@ impl crate::connector::Connector for ConnectorOpenSsl {
 
             let config = ::openssl::ssl::SslConnector::builder(::openssl::ssl::SslMethod::tls())
                 .and_then(|c| c.build().configure())
-                .map_err(|err| Error::new(ErrorKind::Other, err))?;
+                .map_err(|err| TlsError::wrap(ErrorKind::Other, err))?;
 
-            let stream = tokio::net::TcpStream::connect(&*this.addrs).await?;
-            let ssl = config.into_ssl(&this.tls_domain).map_err(|err| Error::new(ErrorKind::Other, err))?;
+            let stream = crate::connector::timeout(
+                this.timeouts.connect,
+                crate::connector::ConnectTimeout::Connect,
+                tcp_connect(&this.addrs, &this.proxy),
+            )
+            .await?;
+            let ssl = config
+                .into_ssl(&this.tls_domain)
+                .map_err(|err| TlsError::wrap(ErrorKind::Other, err))?;
             let mut stream = tokio_openssl::SslStream::new(ssl, stream)
-                .map_err(|err| Error::new(ErrorKind::Other, err))?;
-            std::pin::Pin::new(&mut stream)
-                .connect()
-                .await
-                .map_err(|err| Error::new(ErrorKind::Other, err))?;
+                .map_err(|err| TlsError::wrap(ErrorKind::Other, err))?;
+            let handshake = async {
+                std::pin::Pin::new(&mut stream)
+                    .connect()
+                    .await
+                    .map_err(|err| TlsError::wrap(ErrorKind::Other, err))
+            };
+            crate::connector::timeout(
+                this.timeouts.tls_handshake,
+                crate::connector::ConnectTimeout::TlsHandshake,
+                handshake,
+            )
+            .await?;
 
             Ok(async_dup::Mutex::new(stream.compat()))
         };
@@ -1,18 +1,24 @@
 use super::*;
+use crate::connector::TlsError;
 
 /// A `tokio` connector that uses `tokio-rustls` (a `rustls` wrapper). This uses TLS.
 ///
 /// To use this type, ensure you set up the 'TLS Domain' in the configuration.
 ///
 /// The crate provides the 'TLS domain' for Twitch in the root of this crate.
+///
+/// Use [ConnectorRustTls::with_tls_config()] for custom root certificates.
 #[derive(Debug, Clone, PartialEq)]
 pub struct ConnectorRustTls {
     addrs: Vec<std::net::SocketAddr>,
     tls_domain: String,
+    tls_config: crate::connector::TlsConfig,
+    proxy: Option<crate::connector::ProxyConfig>,
+    timeouts: crate::connector::Timeouts,
 }
 
 impl ConnectorRustTls {
-    connector_ctor!(tls:
+    connector_ctor!(tls_config:
         /// [`tokio`](https://docs.rs/tokio/0.2/tokio/) (using [`tokio-rustls`](https://docs.rs/tokio-rustls/latest/tokio_rustls/))
     );
 }
@@ -26,19 +32,42 @@ impl crate::connector::Connector for ConnectorRustTls {
         let this = self.clone();
         let fut = async move {
             use tokio_util::compat::TokioAsyncReadCompatExt as _;
-            let domain = tokio_rustls::webpki::DNSNameRef::try_from_ascii_str(&this.tls_domain)
-                .map_err(|err| std::io::Error::new(std::io::ErrorKind::Other, err))?;
+            let config = &this.tls_config;
+            let domain = config.get_server_name(&this.tls_domain);
+            let domain = tokio_rustls::webpki::DNSNameRef::try_from_ascii_str(domain)
+                .map_err(|err| TlsError::wrap(std::io::ErrorKind::Other, err))?;
 
-            let connector: tokio_rustls::TlsConnector = std::sync::Arc::new({
-                let mut c = tokio_rustls::rustls::ClientConfig::new();
+            let mut c = tokio_rustls::rustls::ClientConfig::new();
+            if config.uses_default_roots() {
                 c.root_store
                     .add_server_trust_anchors(&webpki_roots::TLS_SERVER_ROOTS);
-                c
-            })
-            .into();
+            }
+            for der in config.root_certificates() {
+                c.root_store
+                    .add(&tokio_rustls::rustls::Certificate(der.to_vec()))
+                    .map_err(|err| TlsError::wrap(std::io::ErrorKind::InvalidData, err))?;
+            }
+            c.enable_sni = config.uses_sni();
 
-            let stream = tokio::net::TcpStream::connect(&*this.addrs).await?;
-            let stream = connector.connect(domain, stream).await?;
+            let connector: tokio_rustls::TlsConnector = std::sync::Arc::new(c).into();
+
+            let stream = crate::connector::timeout(
+                this.timeouts.connect,
+                crate::connector::ConnectTimeout::Connect,
+                tcp_connect(&this.addrs, &this.proxy),
+            )
+            .await?;
+            let stream = crate::connector::timeout(
+                this.timeouts.tls_handshake,
+                crate::connector::ConnectTimeout::TlsHandshake,
+                async {
+                    connector
+                        .connect(domain, stream)
+                        .await
+                        .map_err(TlsError::from_io)
+                },
+            )
+            .await?;
             Ok(async_dup::Mutex::new(stream.compat()))
         };
         Box::pin(fut)
@@ -58,4 +87,17 @@ mod tests {
         assert_type_is_read_write::<<ConnectorRustTls as C>::Output>();
         assert_obj_is_sane(ConnectorRustTls::twitch().unwrap());
     }
+
+    #[test]
+    fn invalid_root_certificate() {
+        use crate::connector::{Connector as _, TlsConfig};
+
+        let mut connector = ConnectorRustTls::custom("127.0.0.1:6697", crate::TWITCH_TLS_DOMAIN)
+            .unwrap()
+            .with_tls_config(TlsConfig::default().add_root_certificate(&b"not a cert"[..]));
+
+        // this fails before anything is connected
+        let err = futures_lite::future::block_on(connector.connect()).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    }
 }
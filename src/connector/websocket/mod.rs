@@ -0,0 +1,98 @@
+use crate::BoxedFuture;
+use std::io::Result;
+
+mod stream;
+pub use stream::WebSocketStream;
+
+/// The default host for the Twitch WebSocket endpoint
+pub const TWITCH_WS_HOST: &str = "irc-ws.chat.twitch.tv";
+
+/// The socket address for the non-TLS [`TWITCH_WS_ADDRESS`](crate::TWITCH_WS_ADDRESS) endpoint
+pub const TWITCH_WS_SOCKET_ADDRESS: &str = "irc-ws.chat.twitch.tv:80";
+
+/// The socket address for the TLS [`TWITCH_WS_ADDRESS_TLS`](crate::TWITCH_WS_ADDRESS_TLS) endpoint
+pub const TWITCH_WS_SOCKET_ADDRESS_TLS: &str = "irc-ws.chat.twitch.tv:443";
+
+/// A connector that speaks IRC over a WebSocket.
+///
+/// This wraps another [`Connector`](crate::connector::Connector) which provides the underlying socket (e.g. a TLS connector pointed at [`TWITCH_WS_SOCKET_ADDRESS_TLS`]).
+/// After the inner connection is established, the WebSocket handshake is done and the IRC lines are framed/unframed for you -- so the decoder only ever sees plain IRC lines.
+///
+/// # Example
+/// ```no_run
+/// # #[cfg(all(feature = "async-io", feature = "async-tls"))]
+/// # fn main() -> std::io::Result<()> {
+/// use twitchchat::connector::{async_io::ConnectorTls, websocket};
+///
+/// let inner = ConnectorTls::custom(websocket::TWITCH_WS_SOCKET_ADDRESS_TLS, websocket::TWITCH_WS_HOST)?;
+/// let connector = websocket::Connector::new(inner);
+/// # Ok(())
+/// # }
+/// # #[cfg(not(all(feature = "async-io", feature = "async-tls")))]
+/// # fn main() {}
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Connector<C> {
+    inner: C,
+    host: String,
+    path: String,
+}
+
+impl<C> Connector<C>
+where
+    C: crate::connector::Connector,
+{
+    /// Create a new WebSocket connector, over the `inner` connector, for the ***default Twitch*** host.
+    pub fn new(inner: C) -> Self {
+        Self::custom(inner, TWITCH_WS_HOST, "/")
+    }
+
+    /// Create a new WebSocket connector, over the `inner` connector, with a custom host and path.
+    ///
+    /// The `host` is sent in the `Host` header during the handshake.
+    pub fn custom<H, P>(inner: C, host: H, path: P) -> Self
+    where
+        H: Into<String>,
+        P: Into<String>,
+    {
+        Self {
+            inner,
+            host: host.into(),
+            path: path.into(),
+        }
+    }
+}
+
+impl<C> crate::connector::Connector for Connector<C>
+where
+    C: crate::connector::Connector + 'static,
+{
+    type Output = async_dup::Mutex<WebSocketStream<C::Output>>;
+
+    fn connect(&mut self) -> BoxedFuture<Result<Self::Output>> {
+        let mut this = self.clone();
+        let fut = async move {
+            let stream = this.inner.connect().await?;
+            WebSocketStream::handshake(stream, &this.host, &this.path)
+                .await
+                .map(async_dup::Mutex::new)
+        };
+        Box::pin(fut)
+    }
+}
+
+#[cfg(all(test, feature = "async-io"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn assert_connector_trait_is_fulfilled() {
+        use crate::connector::async_io::Connector as Inner;
+        use crate::connector::testing::*;
+        use crate::connector::Connector as C;
+
+        assert_connector::<Connector<Inner>>();
+        assert_type_is_read_write::<<Connector<Inner> as C>::Output>();
+        assert_obj_is_sane(Connector::new(Inner::custom("127.0.0.1:80").unwrap()));
+    }
+}
@@ -0,0 +1,757 @@
+use futures_lite::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use std::{
+    convert::TryFrom,
+    io::{Error, ErrorKind, Result},
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+// the handshake response shouldn't ever be this large
+const MAX_HEADER_SIZE: usize = 8 * 1024;
+
+// the largest frame (or message) we'll accept. Twitch's lines are much smaller than this
+const MAX_FRAME_SIZE: usize = 1024 * 1024;
+
+// appended to the key to get the expected `Sec-WebSocket-Accept`
+const ACCEPT_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+const OP_CONTINUATION: u8 = 0x0;
+const OP_TEXT: u8 = 0x1;
+const OP_BINARY: u8 = 0x2;
+const OP_CLOSE: u8 = 0x8;
+const OP_PING: u8 = 0x9;
+const OP_PONG: u8 = 0xA;
+
+/// A stream that wraps IRC lines in WebSocket frames.
+///
+/// Reading from this yields the payloads of the text (and binary) frames that the server sent.
+/// Writing to this will send each complete IRC line (`\r\n` terminated) as its own text frame. Any trailing partial line is sent when the stream is flushed.
+///
+/// Control frames are handled internally: a `PING` is answered with a `PONG` and a `CLOSE` ends the stream.
+pub struct WebSocketStream<S> {
+    stream: S,
+    // raw bytes from the socket which haven't been parsed into a frame yet
+    frames: Vec<u8>,
+    // decoded payload waiting to be read
+    read_buf: Vec<u8>,
+    read_pos: usize,
+    // a message which hasn't been completed yet (for read_message)
+    message: Vec<u8>,
+    // outgoing data which hasn't been terminated with a '\n' yet
+    partial: Vec<u8>,
+    // encoded frames waiting to be written to the socket
+    write_buf: Vec<u8>,
+    closed: bool,
+}
+
+impl<S> std::fmt::Debug for WebSocketStream<S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WebSocketStream").finish()
+    }
+}
+
+impl<S> WebSocketStream<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    /// Do the client handshake over this stream.
+    ///
+    /// The `host` is used for the `Host` header and `path` is the requested resource (generally just `/`).
+    pub async fn handshake(stream: S, host: &str, path: &str) -> Result<Self> {
+        Self::handshake_with_key(stream, host, path, &generate_key()).await
+    }
+
+    async fn handshake_with_key(mut stream: S, host: &str, path: &str, key: &str) -> Result<Self> {
+        let request = format!(
+            "GET {path} HTTP/1.1\r\n\
+             Host: {host}\r\n\
+             Upgrade: websocket\r\n\
+             Connection: Upgrade\r\n\
+             Sec-WebSocket-Key: {key}\r\n\
+             Sec-WebSocket-Version: 13\r\n\
+             \r\n",
+            path = path,
+            host = host,
+            key = key
+        );
+        stream.write_all(request.as_bytes()).await?;
+        stream.flush().await?;
+
+        let mut buf = Vec::with_capacity(1024);
+        let mut tmp = [0_u8; 1024];
+        let end = loop {
+            if let Some(pos) = find_subsequence(&buf, b"\r\n\r\n") {
+                break pos + 4;
+            }
+
+            if buf.len() > MAX_HEADER_SIZE {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    "websocket handshake response was too large",
+                ));
+            }
+
+            match stream.read(&mut tmp).await? {
+                0 => return Err(ErrorKind::UnexpectedEof.into()),
+                n => buf.extend_from_slice(&tmp[..n]),
+            }
+        };
+
+        let head = std::str::from_utf8(&buf[..end])
+            .map_err(|err| Error::new(ErrorKind::InvalidData, err))?;
+
+        let status = head.lines().next().unwrap_or_default();
+        match status.split(' ').nth(1) {
+            Some("101") => {}
+            _ => {
+                return Err(Error::new(
+                    ErrorKind::ConnectionRefused,
+                    format!("websocket upgrade was rejected: {}", status),
+                ))
+            }
+        }
+
+        let expected = accept_key(key);
+        let accept = head
+            .lines()
+            .filter_map(|line| line.split_once(':'))
+            .find(|(name, _)| name.trim().eq_ignore_ascii_case("Sec-WebSocket-Accept"))
+            .map(|(_, value)| value.trim());
+        if accept != Some(expected.as_str()) {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "websocket upgrade had an invalid Sec-WebSocket-Accept",
+            ));
+        }
+
+        Ok(Self {
+            stream,
+            // the server could've sent frames right after the handshake
+            frames: buf[end..].to_vec(),
+            read_buf: Vec::new(),
+            read_pos: 0,
+            message: Vec::new(),
+            partial: Vec::new(),
+            write_buf: Vec::new(),
+            closed: false,
+        })
+    }
+
+    /// Get a reference to underlying stream
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    /// Consume this, returning the underlying stream
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+
+    /// Read the next complete (text or binary) message.
+    ///
+    /// This is for protocols which care about message boundaries. Don't mix this with the [AsyncRead] impl.
+    ///
+    /// Returns `None` when the stream has been closed.
+    pub async fn read_message(&mut self) -> Result<Option<Vec<u8>>> {
+        let mut tmp = [0_u8; 4096];
+
+        loop {
+            if self.closed {
+                return Ok(None);
+            }
+
+            let frame = match Frame::parse(&self.frames)? {
+                Some(frame) => frame,
+                None => {
+                    match self.stream.read(&mut tmp).await? {
+                        0 => return Ok(None),
+                        n => self.frames.extend_from_slice(&tmp[..n]),
+                    }
+                    continue;
+                }
+            };
+            self.frames.drain(..frame.len);
+
+            match frame.opcode {
+                OP_TEXT | OP_BINARY | OP_CONTINUATION => {
+                    if self.message.len() + frame.payload.len() > MAX_FRAME_SIZE {
+                        return Err(too_large());
+                    }
+                    self.message.extend_from_slice(&frame.payload);
+                    if frame.fin {
+                        return Ok(Some(std::mem::take(&mut self.message)));
+                    }
+                }
+                OP_PING => {
+                    let mut pong = vec![];
+                    encode_frame(OP_PONG, &frame.payload, &mut pong);
+                    self.stream.write_all(&pong).await?;
+                }
+                OP_PONG => {}
+                OP_CLOSE => self.closed = true,
+                op => {
+                    return Err(Error::new(
+                        ErrorKind::InvalidData,
+                        format!("unknown websocket opcode: 0x{:X}", op),
+                    ))
+                }
+            }
+        }
+    }
+
+    fn poll_drain(&mut self, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        while !self.write_buf.is_empty() {
+            match Pin::new(&mut self.stream).poll_write(cx, &self.write_buf)? {
+                Poll::Ready(0) => return Poll::Ready(Err(ErrorKind::WriteZero.into())),
+                Poll::Ready(n) => drop(self.write_buf.drain(..n)),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    // returns whether a frame was consumed
+    fn process_frame(&mut self) -> Result<bool> {
+        let frame = match Frame::parse(&self.frames)? {
+            Some(frame) => frame,
+            None => return Ok(false),
+        };
+
+        match frame.opcode {
+            OP_TEXT | OP_BINARY | OP_CONTINUATION => {
+                self.read_buf.extend_from_slice(&frame.payload);
+            }
+            OP_PING => encode_frame(OP_PONG, &frame.payload, &mut self.write_buf),
+            OP_PONG => {}
+            OP_CLOSE => self.closed = true,
+            op => {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!("unknown websocket opcode: 0x{:X}", op),
+                ))
+            }
+        }
+
+        self.frames.drain(..frame.len);
+        Ok(true)
+    }
+}
+
+impl<S> AsyncRead for WebSocketStream<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<Result<usize>> {
+        let this = self.get_mut();
+
+        loop {
+            if this.read_pos < this.read_buf.len() {
+                let available = &this.read_buf[this.read_pos..];
+                let n = available.len().min(buf.len());
+                buf[..n].copy_from_slice(&available[..n]);
+                this.read_pos += n;
+                if this.read_pos == this.read_buf.len() {
+                    this.read_buf.clear();
+                    this.read_pos = 0;
+                }
+                return Poll::Ready(Ok(n));
+            }
+
+            if this.closed {
+                return Poll::Ready(Ok(0));
+            }
+
+            if this.process_frame()? {
+                // try to send any pongs right away; if this would block they'll be sent on the next write
+                if let Poll::Ready(Err(err)) = this.poll_drain(cx) {
+                    return Poll::Ready(Err(err));
+                }
+                continue;
+            }
+
+            let mut tmp = [0_u8; 4096];
+            match Pin::new(&mut this.stream).poll_read(cx, &mut tmp)? {
+                Poll::Ready(0) => return Poll::Ready(Ok(0)),
+                Poll::Ready(n) => this.frames.extend_from_slice(&tmp[..n]),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+impl<S> AsyncWrite for WebSocketStream<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<Result<usize>> {
+        let this = self.get_mut();
+
+        // apply back pressure until the previous frames have been written out
+        futures_lite::ready!(this.poll_drain(cx))?;
+
+        this.partial.extend_from_slice(buf);
+        while let Some(pos) = this.partial.iter().position(|&c| c == b'\n') {
+            let line = this.partial.drain(..=pos).collect::<Vec<_>>();
+            encode_frame(OP_TEXT, &line, &mut this.write_buf);
+        }
+
+        // the data is buffered at this point, so errors will show up on the next write or flush
+        let _ = this.poll_drain(cx);
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        let this = self.get_mut();
+        if !this.partial.is_empty() {
+            let line = std::mem::take(&mut this.partial);
+            encode_frame(OP_TEXT, &line, &mut this.write_buf);
+        }
+        futures_lite::ready!(this.poll_drain(cx))?;
+        Pin::new(&mut this.stream).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        let this = self.get_mut();
+        if !this.closed {
+            this.closed = true;
+            encode_frame(OP_CLOSE, &[], &mut this.write_buf);
+        }
+        futures_lite::ready!(this.poll_drain(cx))?;
+        Pin::new(&mut this.stream).poll_close(cx)
+    }
+}
+
+struct Frame {
+    fin: bool,
+    opcode: u8,
+    payload: Vec<u8>,
+    // the total length of the frame, including the header
+    len: usize,
+}
+
+impl Frame {
+    fn parse(data: &[u8]) -> Result<Option<Self>> {
+        if data.len() < 2 {
+            return Ok(None);
+        }
+
+        let fin = data[0] & 0x80 != 0;
+        let opcode = data[0] & 0x0F;
+        let masked = data[1] & 0x80 != 0;
+
+        let (payload_len, mut offset) = match data[1] & 0x7F {
+            126 if data.len() < 4 => return Ok(None),
+            126 => (u64::from(u16::from_be_bytes([data[2], data[3]])), 4),
+            127 if data.len() < 10 => return Ok(None),
+            127 => {
+                let mut len = [0_u8; 8];
+                len.copy_from_slice(&data[2..10]);
+                (u64::from_be_bytes(len), 10)
+            }
+            len => (u64::from(len), 2),
+        };
+
+        let payload_len = usize::try_from(payload_len)
+            .ok()
+            .filter(|&len| len <= MAX_FRAME_SIZE)
+            .ok_or_else(too_large)?;
+
+        let mask = if masked {
+            if data.len() < offset + 4 {
+                return Ok(None);
+            }
+            let mask = [
+                data[offset],
+                data[offset + 1],
+                data[offset + 2],
+                data[offset + 3],
+            ];
+            offset += 4;
+            Some(mask)
+        } else {
+            None
+        };
+
+        let len = offset.checked_add(payload_len).ok_or_else(too_large)?;
+        if data.len() < len {
+            return Ok(None);
+        }
+
+        let mut payload = data[offset..len].to_vec();
+        if let Some(mask) = mask {
+            apply_mask(&mut payload, mask);
+        }
+
+        Ok(Some(Self {
+            fin,
+            opcode,
+            payload,
+            len,
+        }))
+    }
+}
+
+fn too_large() -> Error {
+    Error::new(ErrorKind::InvalidData, "websocket frame is too large")
+}
+
+// clients must always mask their frames
+fn encode_frame(opcode: u8, payload: &[u8], out: &mut Vec<u8>) {
+    out.push(0x80 | opcode);
+
+    match payload.len() {
+        len if len < 126 => out.push(0x80 | len as u8),
+        len if len <= u16::MAX as usize => {
+            out.push(0x80 | 126);
+            out.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        len => {
+            out.push(0x80 | 127);
+            out.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+
+    let mask = fastrand::u32(..).to_be_bytes();
+    out.extend_from_slice(&mask);
+
+    let start = out.len();
+    out.extend_from_slice(payload);
+    apply_mask(&mut out[start..], mask);
+}
+
+fn apply_mask(data: &mut [u8], mask: [u8; 4]) {
+    for (i, byte) in data.iter_mut().enumerate() {
+        *byte ^= mask[i % 4];
+    }
+}
+
+fn find_subsequence(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+// a base64 encoded 16-byte nonce
+fn generate_key() -> String {
+    let nonce = fastrand::u128(..).to_be_bytes();
+    crate::connector::base64(&nonce)
+}
+
+// the `Sec-WebSocket-Accept` the server must reply with
+fn accept_key(key: &str) -> String {
+    crate::connector::base64(&sha1(format!("{}{}", key, ACCEPT_GUID).as_bytes()))
+}
+
+// this is only used for the handshake, so it doesn't need to be fast
+fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut h: [u32; 5] = [
+        0x6745_2301,
+        0xEFCD_AB89,
+        0x98BA_DCFE,
+        0x1032_5476,
+        0xC3D2_E1F0,
+    ];
+
+    let mut msg = data.to_vec();
+    msg.push(0x80);
+    while msg.len() % 64 != 56 {
+        msg.push(0);
+    }
+    msg.extend_from_slice(&((data.len() as u64) * 8).to_be_bytes());
+
+    for chunk in msg.chunks(64) {
+        let mut w = [0_u32; 80];
+        for (i, word) in chunk.chunks(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e] = h;
+        for (i, &w) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5A82_7999),
+                20..=39 => (b ^ c ^ d, 0x6ED9_EBA1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1B_BCDC),
+                _ => (b ^ c ^ d, 0xCA62_C1D6),
+            };
+            let temp = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(w);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+
+        for (h, v) in h.iter_mut().zip(&[a, b, c, d, e]) {
+            *h = h.wrapping_add(*v);
+        }
+    }
+
+    let mut out = [0_u8; 20];
+    for (out, h) in out.chunks_mut(4).zip(&h) {
+        out.copy_from_slice(&h.to_be_bytes());
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_lite::future::block_on;
+
+    // reads come from 'input', writes go to 'output'
+    #[derive(Default)]
+    struct Mock {
+        input: futures_lite::io::Cursor<Vec<u8>>,
+        output: Vec<u8>,
+    }
+
+    impl AsyncRead for Mock {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut [u8],
+        ) -> Poll<Result<usize>> {
+            Pin::new(&mut self.input).poll_read(cx, buf)
+        }
+    }
+
+    impl AsyncWrite for Mock {
+        fn poll_write(
+            mut self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<Result<usize>> {
+            self.output.extend_from_slice(buf);
+            Poll::Ready(Ok(buf.len()))
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    fn server_frame(opcode: u8, payload: &[u8]) -> Vec<u8> {
+        let mut out = vec![0x80 | opcode, payload.len() as u8];
+        out.extend_from_slice(payload);
+        out
+    }
+
+    // the example from RFC 6455
+    const KEY: &str = "dGhlIHNhbXBsZSBub25jZQ==";
+
+    fn mock(frames: &[Vec<u8>]) -> Mock {
+        let mut input = b"HTTP/1.1 101 Switching Protocols\r\n\
+            Upgrade: websocket\r\n\
+            Connection: Upgrade\r\n\
+            Sec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=\r\n\
+            \r\n"
+            .to_vec();
+        for frame in frames {
+            input.extend_from_slice(frame);
+        }
+        Mock {
+            input: futures_lite::io::Cursor::new(input),
+            output: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn key() {
+        let key = generate_key();
+        assert_eq!(key.len(), 24);
+        assert!(key.ends_with("=="));
+    }
+
+    #[test]
+    fn frame_round_trip() {
+        for len in &[0, 1, 125, 126, 127, 1024, 70_000] {
+            let payload = vec![b'a'; *len];
+            let mut out = vec![];
+            encode_frame(OP_TEXT, &payload, &mut out);
+
+            let frame = Frame::parse(&out).unwrap().unwrap();
+            assert_eq!(frame.opcode, OP_TEXT);
+            assert_eq!(frame.payload, payload);
+            assert_eq!(frame.len, out.len());
+
+            // an incomplete frame
+            assert!(Frame::parse(&out[..out.len() - 1]).unwrap().is_none());
+        }
+    }
+
+    #[test]
+    fn handshake() {
+        block_on(async move {
+            let stream = WebSocketStream::handshake_with_key(mock(&[]), "localhost", "/", KEY)
+                .await
+                .unwrap();
+
+            let request = std::str::from_utf8(&stream.get_ref().output).unwrap();
+            assert!(request.starts_with("GET / HTTP/1.1\r\n"));
+            assert!(request.contains("Host: localhost\r\n"));
+            assert!(request.contains("Upgrade: websocket\r\n"));
+            assert!(request.contains("Sec-WebSocket-Version: 13\r\n"));
+            assert!(request.ends_with("\r\n\r\n"));
+        });
+    }
+
+    #[test]
+    fn accept_key() {
+        assert_eq!(super::accept_key(KEY), "s3pPLMBiTxaQ9kYGzzhZRbK+xOo=");
+        assert_eq!(
+            crate::connector::base64(&sha1(b"abc")),
+            "qZk+NkcGgWq6PiVxeFDCbJzQ2J0="
+        );
+    }
+
+    #[test]
+    fn handshake_invalid_accept() {
+        block_on(async move {
+            let err =
+                WebSocketStream::handshake_with_key(mock(&[]), "localhost", "/", "a different key")
+                    .await
+                    .unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::InvalidData);
+
+            // or it's missing
+            let mock = Mock {
+                input: futures_lite::io::Cursor::new(
+                    b"HTTP/1.1 101 Switching Protocols\r\n\r\n".to_vec(),
+                ),
+                output: Vec::new(),
+            };
+            let err = WebSocketStream::handshake_with_key(mock, "localhost", "/", KEY)
+                .await
+                .unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::InvalidData);
+        });
+    }
+
+    #[test]
+    fn frame_too_large() {
+        // a 127-length frame with the largest length
+        let mut frame = vec![0x80 | OP_TEXT, 127];
+        frame.extend_from_slice(&u64::MAX.to_be_bytes());
+        let err = Frame::parse(&frame).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+
+        // and one just over the limit, which is rejected before its payload arrives
+        let mut frame = vec![0x80 | OP_TEXT, 127];
+        frame.extend_from_slice(&(MAX_FRAME_SIZE as u64 + 1).to_be_bytes());
+        assert!(Frame::parse(&frame).is_err());
+    }
+
+    #[test]
+    fn handshake_rejected() {
+        block_on(async move {
+            let mock = Mock {
+                input: futures_lite::io::Cursor::new(b"HTTP/1.1 400 Bad Request\r\n\r\n".to_vec()),
+                output: Vec::new(),
+            };
+            let err = WebSocketStream::handshake_with_key(mock, "localhost", "/", KEY)
+                .await
+                .unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::ConnectionRefused);
+        });
+    }
+
+    #[test]
+    fn read_lines() {
+        block_on(async move {
+            let frames = &[
+                server_frame(OP_TEXT, b":tmi.twitch.tv PING 1234\r\n"),
+                server_frame(OP_PING, b"hello"),
+                server_frame(OP_TEXT, b"PING :foo\r\nPING :bar\r\n"),
+                server_frame(OP_CLOSE, b""),
+                server_frame(OP_TEXT, b"this shouldn't be read\r\n"),
+            ];
+
+            let mut stream =
+                WebSocketStream::handshake_with_key(mock(frames), "localhost", "/", KEY)
+                    .await
+                    .unwrap();
+            let start = stream.get_ref().output.len();
+
+            let mut out = String::new();
+            stream.read_to_string(&mut out).await.unwrap();
+            assert_eq!(
+                out,
+                ":tmi.twitch.tv PING 1234\r\nPING :foo\r\nPING :bar\r\n"
+            );
+
+            // the ping should have been answered
+            let pong = Frame::parse(&stream.get_ref().output[start..])
+                .unwrap()
+                .unwrap();
+            assert_eq!(pong.opcode, OP_PONG);
+            assert_eq!(pong.payload, b"hello");
+        });
+    }
+
+    #[test]
+    fn read_messages() {
+        block_on(async move {
+            let mut first = server_frame(OP_TEXT, b"{\"hello\":");
+            first[0] &= 0x7F; // not finished
+            let frames = &[
+                first,
+                server_frame(OP_PING, b"hello"),
+                server_frame(OP_CONTINUATION, b"\"world\"}"),
+                server_frame(OP_TEXT, b"{}"),
+                server_frame(OP_CLOSE, b""),
+            ];
+
+            let mut stream =
+                WebSocketStream::handshake_with_key(mock(frames), "localhost", "/", KEY)
+                    .await
+                    .unwrap();
+
+            let msg = stream.read_message().await.unwrap().unwrap();
+            assert_eq!(msg, b"{\"hello\":\"world\"}");
+
+            let msg = stream.read_message().await.unwrap().unwrap();
+            assert_eq!(msg, b"{}");
+
+            assert!(stream.read_message().await.unwrap().is_none());
+        });
+    }
+
+    #[test]
+    fn write_lines() {
+        block_on(async move {
+            let mut stream = WebSocketStream::handshake_with_key(mock(&[]), "localhost", "/", KEY)
+                .await
+                .unwrap();
+            let start = stream.get_ref().output.len();
+
+            stream.write_all(b"PASS foo\r\nNICK ").await.unwrap();
+            stream.write_all(b"bar\r\nPA").await.unwrap();
+            stream.flush().await.unwrap();
+
+            let mut data = &stream.get_ref().output[start..];
+            let mut lines = vec![];
+            while let Some(frame) = Frame::parse(data).unwrap() {
+                assert_eq!(frame.opcode, OP_TEXT);
+                lines.push(String::from_utf8(frame.payload).unwrap());
+                data = &data[frame.len..];
+            }
+            assert_eq!(lines, vec!["PASS foo\r\n", "NICK bar\r\n", "PA"]);
+        });
+    }
+}
@@ -7,16 +7,21 @@ use std::{
     task::{Context, Poll},
 };
 
-use futures_lite::{io::BufReader as AsyncBufReader, AsyncBufReadExt, AsyncRead, Stream};
+use futures_lite::{AsyncRead, AsyncReadExt, Stream};
+
+use super::buffer::LineBuffer;
 
 /// A decoder over [futures_lite::AsyncRead] that produces [IrcMessage]s
 ///
 /// This will return an [DecodeError::Eof] when its done reading manually.
 ///
 /// When reading it as a stream, `Eof` will signal the end of the stream (e.g. `None`)
+///
+/// The reader is read in large chunks, and every complete line in a chunk is decoded before reading again. The messages borrow from that buffer, so they aren't copied.
 pub struct AsyncDecoder<R> {
-    reader: AsyncBufReader<R>,
-    buf: Vec<u8>,
+    reader: R,
+    buf: LineBuffer,
+    lossy: bool,
 }
 
 impl<R> std::fmt::Debug for AsyncDecoder<R> {
@@ -29,39 +34,64 @@ impl<R: AsyncRead + Send + Sync + Unpin> AsyncDecoder<R> {
     /// Create a new AsyncDecoder from this [futures_lite::AsyncRead] instance
     pub fn new(reader: R) -> Self {
         Self {
-            reader: AsyncBufReader::new(reader),
-            buf: Vec::with_capacity(1024),
+            reader,
+            buf: LineBuffer::default(),
+            lossy: false,
         }
     }
 
+    /// Report lines that can't be decoded (invalid UTF-8, or unparsable) as a [DecodeError::Invalid], which has the line and where the problem is.
+    ///
+    /// Either way, the decoder carries on with the next line after an error.
+    pub fn lossy(mut self, lossy: bool) -> Self {
+        self.lossy = lossy;
+        self
+    }
+
     /// Read the next message.
     ///
     /// This returns a borrowed [IrcMessage] which is valid until the next AsyncDecoder call is made.
     ///
     /// If you just want an owned one, use the [AsyncDecoder] as an stream. e.g. dec.next().
     pub async fn read_message(&mut self) -> Result<IrcMessage<'_>, DecodeError> {
-        self.buf.clear();
-        let n = self
-            .reader
-            .read_until(b'\n', &mut self.buf)
-            .await
-            .map_err(DecodeError::Io)?;
-        if n == 0 {
-            return Err(DecodeError::Eof);
-        }
+        // hand out the lines that were already read, before reading again
+        let line = loop {
+            if let Some(line) = self.buf.next_line() {
+                break line;
+            }
 
-        let str = std::str::from_utf8(&self.buf[..n]).map_err(DecodeError::InvalidUtf8)?;
-        log::trace!("< {}", str.escape_debug());
+            match self.reader.read(self.buf.spare()).await {
+                Ok(0) => match self.buf.rest() {
+                    Some(rest) => break rest,
+                    None => return Err(DecodeError::Eof),
+                },
+                Ok(n) => self.buf.filled(n),
+                Err(err) if err.kind() == std::io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(DecodeError::Io(err)),
+            }
+        };
 
-        // this should only ever parse 1 message
-        crate::irc::parse_one(str)
-            .map_err(DecodeError::ParseError)
-            .map(|(_, msg)| msg)
+        let line = self.buf.get(line);
+        event!(trace, "read", "< {}", String::from_utf8_lossy(line).escape_debug());
+
+        super::decode_line(line, self.lossy)
     }
 
     /// Consume the decoder returning the inner Reader
     pub fn into_inner(self) -> R {
-        self.reader.into_inner()
+        self.reader
+    }
+}
+
+#[cfg(all(feature = "tokio", feature = "tokio-util"))]
+impl<R> AsyncDecoder<tokio_util::compat::Compat<R>>
+where
+    R: tokio::io::AsyncRead + Send + Sync + Unpin,
+{
+    /// Create a new AsyncDecoder from this [tokio::io::AsyncRead] instance
+    pub fn from_tokio(reader: R) -> Self {
+        use tokio_util::compat::TokioAsyncReadCompatExt as _;
+        Self::new(reader.compat())
     }
 }
 
@@ -121,5 +151,21 @@ mod tests {
 
         futures_lite::future::block_on(fut);
     }
+
+    #[test]
+    #[cfg(all(feature = "tokio", feature = "tokio-util"))]
+    fn read_tokio() {
+        let data: &[u8] = b"hello\r\nworld\r\n";
+        let mut dec = AsyncDecoder::from_tokio(data);
+
+        futures_lite::future::block_on(async move {
+            assert_eq!(dec.read_message().await.unwrap().get_raw(), "hello\r\n");
+            assert_eq!(dec.read_message().await.unwrap().get_raw(), "world\r\n");
+            assert!(matches!(
+                dec.read_message().await.unwrap_err(),
+                DecodeError::Eof
+            ))
+        });
+    }
 }
 }
@@ -0,0 +1,159 @@
+use std::ops::Range;
+
+// the size of the first read. the buffer grows if a line doesn't fit
+const INITIAL_CAPACITY: usize = 8 * 1024;
+
+// a buffer that lines are read into, and then handed out from without copying them.
+//
+// a single read can contain many lines, so they are all handed out before reading again.
+// a partial line at the end is moved to the front before the next read.
+#[derive(Default)]
+pub(super) struct LineBuffer {
+    buf: Vec<u8>,
+    start: usize,
+    end: usize,
+}
+
+impl LineBuffer {
+    // the next complete line (including its `\n`), if one is buffered
+    pub(super) fn next_line(&mut self) -> Option<Range<usize>> {
+        let pos = find_newline(&self.buf[self.start..self.end])?;
+        let line = self.start..self.start + pos + 1;
+        self.start = line.end;
+        Some(line)
+    }
+
+    // the partial line left over, once the reader is done
+    pub(super) fn rest(&mut self) -> Option<Range<usize>> {
+        if self.start == self.end {
+            return None;
+        }
+        let rest = self.start..self.end;
+        self.start = self.end;
+        Some(rest)
+    }
+
+    // some space to read into, after the partial line
+    pub(super) fn spare(&mut self) -> &mut [u8] {
+        if self.start == self.end {
+            self.start = 0;
+            self.end = 0;
+        } else if self.start > 0 {
+            self.buf.copy_within(self.start..self.end, 0);
+            self.end -= self.start;
+            self.start = 0;
+        }
+
+        if self.end == self.buf.len() {
+            let len = (self.buf.len() * 2).max(INITIAL_CAPACITY);
+            self.buf.resize(len, 0);
+        }
+        &mut self.buf[self.end..]
+    }
+
+    // this many bytes were read into the spare space
+    pub(super) fn filled(&mut self, n: usize) {
+        self.end += n;
+    }
+
+    pub(super) fn get(&self, range: Range<usize>) -> &[u8] {
+        &self.buf[range]
+    }
+}
+
+#[cfg(feature = "simd")]
+fn find_newline(data: &[u8]) -> Option<usize> {
+    memchr::memchr(b'\n', data)
+}
+
+// checks two words at a time, which is much faster than checking each byte
+#[cfg(not(feature = "simd"))]
+fn find_newline(data: &[u8]) -> Option<usize> {
+    use std::convert::TryInto as _;
+
+    const LO: u64 = u64::from_ne_bytes([0x01; 8]);
+    const HI: u64 = u64::from_ne_bytes([0x80; 8]);
+    const NEEDLE: u64 = u64::from_ne_bytes([b'\n'; 8]);
+
+    // this is only true if one of the bytes is a '\n'
+    let has_newline = |chunk: &[u8]| {
+        let word = u64::from_ne_bytes(chunk.try_into().unwrap()) ^ NEEDLE;
+        word.wrapping_sub(LO) & !word & HI != 0
+    };
+    let position = |chunk: &[u8]| chunk.iter().position(|&c| c == b'\n');
+
+    let mut chunks = data.chunks_exact(16);
+    for (i, chunk) in (&mut chunks).enumerate() {
+        if has_newline(&chunk[..8]) || has_newline(&chunk[8..]) {
+            return position(chunk).map(|pos| i * 16 + pos);
+        }
+    }
+
+    let rest = chunks.remainder();
+    position(rest).map(|pos| data.len() - rest.len() + pos)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // like a reader, this only fills the spare space it's given
+    fn fill(buf: &mut LineBuffer, mut data: &[u8]) {
+        while !data.is_empty() {
+            let spare = buf.spare();
+            let n = spare.len().min(data.len());
+            spare[..n].copy_from_slice(&data[..n]);
+            buf.filled(n);
+            data = &data[n..];
+        }
+    }
+
+    fn lines(buf: &mut LineBuffer) -> Vec<String> {
+        let mut lines = Vec::new();
+        while let Some(line) = buf.next_line() {
+            lines.push(String::from_utf8(buf.get(line).to_vec()).unwrap());
+        }
+        lines
+    }
+
+    #[test]
+    fn find_newline() {
+        let data = b"0123456789abcdef\n0123\n01";
+        for start in 0..data.len() {
+            let data = &data[start..];
+            assert_eq!(
+                super::find_newline(data),
+                data.iter().position(|&c| c == b'\n')
+            );
+        }
+    }
+
+    #[test]
+    fn carries_partial_lines() {
+        let mut buf = LineBuffer::default();
+
+        fill(&mut buf, b"PING :a\r\nPING :b\r\nPI");
+        assert_eq!(lines(&mut buf), vec!["PING :a\r\n", "PING :b\r\n"]);
+
+        fill(&mut buf, b"NG :c\r");
+        assert!(lines(&mut buf).is_empty());
+
+        fill(&mut buf, b"\nPING :d");
+        assert_eq!(lines(&mut buf), vec!["PING :c\r\n"]);
+
+        let rest = buf.rest().unwrap();
+        assert_eq!(buf.get(rest), b"PING :d");
+        assert!(buf.rest().is_none());
+    }
+
+    #[test]
+    fn grows_for_long_lines() {
+        let mut buf = LineBuffer::default();
+        let line = format!("PRIVMSG #museun :{}\r\n", "a".repeat(INITIAL_CAPACITY * 2));
+
+        for chunk in line.as_bytes().chunks(1000) {
+            fill(&mut buf, chunk);
+        }
+        assert_eq!(lines(&mut buf), vec![line]);
+    }
+}
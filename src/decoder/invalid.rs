@@ -0,0 +1,160 @@
+use crate::MessageError;
+
+/// A line that couldn't be decoded, returned by a [lossy](super::Decoder::lossy) decoder as a [DecodeError::Invalid](super::DecodeError::Invalid).
+///
+/// The decoder carries on with the next line.
+#[derive(Debug)]
+pub struct InvalidMessage {
+    /// The line, with any invalid UTF-8 replaced with `U+FFFD`
+    pub raw: String,
+    /// The byte offset, in the line, where the problem was found
+    pub offset: usize,
+    /// Why the line couldn't be decoded
+    pub reason: InvalidReason,
+}
+
+/// Why a line couldn't be decoded. See [InvalidMessage]
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum InvalidReason {
+    /// The line wasn't valid UTF-8
+    InvalidUtf8(std::str::Utf8Error),
+    /// The line couldn't be parsed as an IRC message, or as the Twitch message its command names
+    ParseError(MessageError),
+}
+
+impl InvalidMessage {
+    pub(crate) fn invalid_utf8(line: &[u8], err: std::str::Utf8Error) -> Self {
+        Self {
+            raw: String::from_utf8_lossy(line).into_owned(),
+            offset: err.valid_up_to(),
+            reason: InvalidReason::InvalidUtf8(err),
+        }
+    }
+
+    /// An `InvalidMessage` for a line which failed to parse with this error
+    pub fn parse_error(line: &str, err: MessageError) -> Self {
+        Self {
+            raw: line.to_string(),
+            offset: offset_of(line, &err),
+            reason: InvalidReason::ParseError(err),
+        }
+    }
+}
+
+impl std::fmt::Display for InvalidMessage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.reason {
+            InvalidReason::InvalidUtf8(err) => {
+                write!(f, "invalid utf8 at byte {}: {}", self.offset, err)
+            }
+            InvalidReason::ParseError(err) => {
+                write!(f, "parse error at byte {}: {}", self.offset, err)
+            }
+        }?;
+        write!(f, " (in {:?})", self.raw)
+    }
+}
+
+impl std::error::Error for InvalidMessage {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match &self.reason {
+            InvalidReason::InvalidUtf8(err) => Some(err),
+            InvalidReason::ParseError(err) => Some(err),
+        }
+    }
+}
+
+// where, in the line, the parse error was found
+fn offset_of(line: &str, err: &MessageError) -> usize {
+    // the tags are separated from the rest of the message by a space
+    let tags = if line.starts_with('@') {
+        line.find(' ').unwrap_or(line.len())
+    } else {
+        0
+    };
+
+    // the n-th tag (after the '@')
+    let nth_tag = |n: usize| {
+        line[..tags]
+            .match_indices(';')
+            .nth(n.wrapping_sub(1))
+            .map(|(pos, _)| pos + 1)
+            .unwrap_or(1)
+            .min(tags)
+    };
+
+    // the value of a tag
+    let tag = |name: &str| {
+        let key = format!("{}=", name);
+        line[..tags]
+            .match_indices(&*key)
+            .find(|&(pos, _)| matches!(line.as_bytes()[pos - 1], b'@' | b';'))
+            .map(|(pos, _)| pos + key.len())
+    };
+
+    // past the tags and the prefix
+    let command = || {
+        let rest = line[tags..].trim_start();
+        let start = line.len() - rest.len();
+        match rest.strip_prefix(':') {
+            Some(prefix) => prefix
+                .find(' ')
+                .map(|pos| start + pos + 2)
+                .unwrap_or(line.len()),
+            None => start,
+        }
+    };
+
+    match err {
+        MessageError::MissingTagKey(n) | MessageError::MissingTagValue(n) => nth_tag(*n),
+        MessageError::CannotParseTag { name, .. } => tag(name).unwrap_or(0),
+        MessageError::ExpectedNick => line[tags..].len() - line[tags..].trim_start().len() + tags,
+        MessageError::InvalidCommand { .. } => command(),
+        // these are missing from the end of the line
+        MessageError::ExpectedArg { .. }
+        | MessageError::ExpectedData
+        | MessageError::IncompleteMessage { .. } => line.trim_end().len(),
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn offsets() {
+        let offset = |line: &str, err| InvalidMessage::parse_error(line, err).offset;
+
+        let line = "@a=1;;c=3 :museun!museun@museun PRIVMSG #museun :hello\r\n";
+        assert_eq!(offset(line, MessageError::MissingTagKey(1)), 5);
+        assert_eq!(offset(line, MessageError::MissingTagKey(0)), 1);
+
+        let line = "@color=#FFF;room-id=abc :tmi.twitch.tv ROOMSTATE #museun\r\n";
+        let err = MessageError::CannotParseTag {
+            name: "room-id".into(),
+            error: "not a number".into(),
+        };
+        assert_eq!(offset(line, err), 20);
+
+        let err = MessageError::InvalidCommand {
+            expected: "PRIVMSG".into(),
+            got: "ROOMSTATE".into(),
+        };
+        assert_eq!(offset(line, err), 39);
+        assert_eq!(offset(line, MessageError::ExpectedData), line.len() - 2);
+
+        let line = "@a=1 PRIVMSG #museun :hi\r\n";
+        assert_eq!(offset(line, MessageError::ExpectedNick), 5);
+    }
+
+    #[test]
+    fn invalid_utf8() {
+        let line = b"PRIVMSG #museun :\xFF\r\n".to_vec();
+        let err = std::str::from_utf8(&line).unwrap_err();
+        let invalid = InvalidMessage::invalid_utf8(&line, err);
+        assert_eq!(invalid.offset, 17);
+        assert_eq!(invalid.raw, "PRIVMSG #museun :\u{FFFD}\r\n");
+    }
+}
@@ -45,6 +45,11 @@
 //! }
 //! ```
 
+mod buffer;
+
+mod invalid;
+pub use invalid::{InvalidMessage, InvalidReason};
+
 cfg_async! {
     mod r#async;
     pub use r#async::*;
@@ -1,5 +1,7 @@
 use crate::{IntoOwned as _, IrcMessage, MessageError};
-use std::io::{BufRead, BufReader, Read};
+use std::io::Read;
+
+use super::{buffer::LineBuffer, InvalidMessage};
 
 /// An error produced by a Decoder.
 #[derive(Debug)]
@@ -13,6 +15,8 @@ pub enum DecodeError {
     ParseError(MessageError),
     /// EOF was reached
     Eof,
+    /// A [lossy](Decoder::lossy) decoder read a line it couldn't decode. The next line can still be read
+    Invalid(InvalidMessage),
 }
 
 impl std::fmt::Display for DecodeError {
@@ -22,6 +26,7 @@ impl std::fmt::Display for DecodeError {
             Self::InvalidUtf8(err) => write!(f, "invalid utf8: {}", err),
             Self::ParseError(err) => write!(f, "parse error: {}", err),
             Self::Eof => f.write_str("end of file reached"),
+            Self::Invalid(err) => write!(f, "invalid message: {}", err),
         }
     }
 }
@@ -32,6 +37,7 @@ impl std::error::Error for DecodeError {
             Self::Io(err) => Some(err),
             Self::InvalidUtf8(err) => Some(err),
             Self::ParseError(err) => Some(err),
+            Self::Invalid(err) => Some(err),
             _ => None,
         }
     }
@@ -42,9 +48,12 @@ impl std::error::Error for DecodeError {
 /// This will return an [DecodeError::Eof] when reading manually.
 ///
 /// When reading it as a iterator, `Eof` will signal the end of the iterator (e.g. `None`)
+///
+/// The reader is read in large chunks, and every complete line in a chunk is decoded before reading again. The messages borrow from that buffer, so they aren't copied.
 pub struct Decoder<R> {
-    reader: BufReader<R>,
-    buf: Vec<u8>,
+    reader: R,
+    buf: LineBuffer,
+    lossy: bool,
 }
 
 impl<R> std::fmt::Debug for Decoder<R> {
@@ -60,32 +69,44 @@ where
     /// Create a new Decoder from this [std::io::Read] instance
     pub fn new(reader: R) -> Self {
         Self {
-            reader: BufReader::new(reader),
-            buf: Vec::with_capacity(1024),
+            reader,
+            buf: LineBuffer::default(),
+            lossy: false,
         }
     }
 
+    /// Report lines that can't be decoded (invalid UTF-8, or unparsable) as a [DecodeError::Invalid], which has the line and where the problem is.
+    ///
+    /// Either way, the decoder carries on with the next line after an error.
+    pub fn lossy(mut self, lossy: bool) -> Self {
+        self.lossy = lossy;
+        self
+    }
+
     /// Read the next message.
     ///
     /// This returns a borrowed [IrcMessage] which is valid until the next Decoder call is made.
     ///
     /// If you just want an owned one, use the [Decoder] as an iterator. e.g. dec.next().
     pub fn read_message(&mut self) -> Result<IrcMessage<'_>, DecodeError> {
-        self.buf.clear();
-        let n = self
-            .reader
-            .read_until(b'\n', &mut self.buf)
-            .map_err(DecodeError::Io)?;
-        if n == 0 {
-            return Err(DecodeError::Eof);
-        }
-
-        let str = std::str::from_utf8(&self.buf[..n]).map_err(DecodeError::InvalidUtf8)?;
-
-        // this should only ever parse 1 message
-        crate::irc::parse_one(str)
-            .map_err(DecodeError::ParseError)
-            .map(|(_, msg)| msg)
+        // hand out the lines that were already read, before reading again
+        let line = loop {
+            if let Some(line) = self.buf.next_line() {
+                break line;
+            }
+
+            match self.reader.read(self.buf.spare()) {
+                Ok(0) => match self.buf.rest() {
+                    Some(rest) => break rest,
+                    None => return Err(DecodeError::Eof),
+                },
+                Ok(n) => self.buf.filled(n),
+                Err(err) if err.kind() == std::io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(DecodeError::Io(err)),
+            }
+        };
+
+        decode_line(self.buf.get(line), self.lossy)
     }
 
     /// Returns an iterator over messages.
@@ -97,10 +118,30 @@ where
 
     /// Consume the decoder returning the inner Reader
     pub fn into_inner(self) -> R {
-        self.reader.into_inner()
+        self.reader
     }
 }
 
+pub(super) fn decode_line(line: &[u8], lossy: bool) -> Result<IrcMessage<'_>, DecodeError> {
+    let str = match std::str::from_utf8(line) {
+        Ok(str) => str,
+        Err(err) if lossy => {
+            return Err(DecodeError::Invalid(InvalidMessage::invalid_utf8(
+                line, err,
+            )))
+        }
+        Err(err) => return Err(DecodeError::InvalidUtf8(err)),
+    };
+
+    // this should only ever parse 1 message
+    crate::irc::parse_one(str)
+        .map_err(|err| match lossy {
+            true => DecodeError::Invalid(InvalidMessage::parse_error(str, err)),
+            false => DecodeError::ParseError(err),
+        })
+        .map(|(_, msg)| msg)
+}
+
 /// This will produce `Result<IrcMessage<'static>, DecodeError>` until an `Eof` is received
 impl<R: Read> Iterator for Decoder<R> {
     type Item = Result<IrcMessage<'static>, DecodeError>;
@@ -117,6 +158,7 @@ impl<R: Read> Iterator for Decoder<R> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::decoder::InvalidReason;
 
     #[test]
     fn read_sync() {
@@ -139,4 +181,52 @@ mod tests {
         }
         assert!(matches!(dec.read_message().unwrap_err(), DecodeError::Eof))
     }
+
+    #[test]
+    fn read_lossy() {
+        let data = b"PING :a\r\nPRIVMSG #museun :\xFF\r\nPING :b\n\nPING :c\r\n".to_vec();
+
+        let mut dec = Decoder::new(&data[..]);
+        dec.read_message().unwrap();
+        assert!(matches!(
+            dec.read_message().unwrap_err(),
+            DecodeError::InvalidUtf8(..)
+        ));
+        assert!(matches!(
+            dec.read_message().unwrap_err(),
+            DecodeError::ParseError(..)
+        ));
+        assert!(matches!(
+            dec.read_message().unwrap_err(),
+            DecodeError::ParseError(..)
+        ));
+        assert_eq!(dec.read_message().unwrap().get_raw(), "PING :c\r\n");
+
+        let mut dec = Decoder::new(&data[..]).lossy(true);
+        dec.read_message().unwrap();
+        match dec.read_message().unwrap_err() {
+            DecodeError::Invalid(err) => {
+                assert!(matches!(err.reason, InvalidReason::InvalidUtf8(..)));
+                assert_eq!(err.offset, 17);
+                assert_eq!(err.raw, "PRIVMSG #museun :\u{FFFD}\r\n");
+            }
+            err => panic!("unexpected error: {}", err),
+        }
+        match dec.read_message().unwrap_err() {
+            DecodeError::Invalid(err) => {
+                assert!(matches!(
+                    err.reason,
+                    InvalidReason::ParseError(MessageError::IncompleteMessage { .. })
+                ));
+                assert_eq!(err.raw, "PING :b\n");
+                assert_eq!(err.offset, 7);
+            }
+            err => panic!("unexpected error: {}", err),
+        }
+        assert!(matches!(
+            dec.read_message().unwrap_err(),
+            DecodeError::Invalid(..)
+        ));
+        assert_eq!(dec.read_message().unwrap().get_raw(), "PING :c\r\n");
+    }
 }
@@ -1,3 +1,4 @@
+use crate::{irc::IrcMessage, messages::*};
 use std::{
     io::{Result as IoResult, Write},
     rc::Rc,
@@ -5,6 +6,21 @@ use std::{
 };
 
 /// A trait to allow writing messags to any [std::io::Write] implementation
+///
+/// This is also implemented for the [messages](crate::messages) parsed from Twitch (and [Commands] and [IrcMessage]):
+/// they encode as the line they were parsed from, without any leading or trailing whitespace, ending with `\r\n`.
+/// So a proxy can filter the messages it reads, and forward the rest unchanged.
+///
+/// ```
+/// # use twitchchat::{messages::Commands, Encodable, FromIrcMessage};
+/// let input = "@badges=;color=#FF0000 :museun!museun@museun.tmi.twitch.tv PRIVMSG #museun :hello\r\n";
+/// let msg = twitchchat::irc::parse(input).next().unwrap().unwrap();
+/// let msg = Commands::from_irc(msg).unwrap();
+///
+/// let mut out = Vec::new();
+/// msg.encode(&mut out).unwrap();
+/// assert_eq!(out, input.as_bytes());
+/// ```
 pub trait Encodable {
     /// Encode this message to the provided [std::io::Write] implementation
     fn encode<W>(&self, buf: &mut W) -> IoResult<()>
@@ -59,3 +75,152 @@ encodable_byte_slice! {
     Arc<[u8]>
     Vec<u8>
 }
+
+// the raw line, as it would have been read
+fn encode_raw<W>(raw: &str, buf: &mut W) -> IoResult<()>
+where
+    W: Write + ?Sized,
+{
+    buf.write_all(raw.trim().as_bytes())?;
+    buf.write_all(b"\r\n")
+}
+
+macro_rules! encodable_message {
+    ($($ty:ident)*) => {
+        $(impl<'a> Encodable for $ty<'a> {
+            fn encode<W: Write + ?Sized>(&self, buf: &mut W) -> IoResult<()> {
+                encode_raw(self.raw(), buf)
+            }
+        })*
+    };
+}
+
+encodable_message! {
+    IrcReady
+    Ready
+    Cap
+    ClearChat
+    ClearMsg
+    GlobalUserState
+    HostTarget
+    Join
+    Notice
+    Part
+    Ping
+    Pong
+    Privmsg
+    Reconnect
+    RoomState
+    UserNotice
+    UserState
+    Whisper
+}
+
+impl<'a> Encodable for IrcMessage<'a> {
+    fn encode<W: Write + ?Sized>(&self, buf: &mut W) -> IoResult<()> {
+        encode_raw(self.get_raw(), buf)
+    }
+}
+
+impl<'a> Encodable for Commands<'a> {
+    fn encode<W: Write + ?Sized>(&self, buf: &mut W) -> IoResult<()> {
+        encode_raw(self.raw(), buf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FromIrcMessage as _, IntoOwned as _};
+
+    // a line for each message type
+    const CORPUS: &[&str] = &[
+        ":tmi.twitch.tv 001 shaken_bot :Welcome, GLHF!\r\n",
+        ":tmi.twitch.tv 376 shaken_bot :>\r\n",
+        ":tmi.twitch.tv CAP * ACK :twitch.tv/membership\r\n",
+        "@room-id=12345678;target-user-id=87654321;tmi-sent-ts=1642715756806 :tmi.twitch.tv CLEARCHAT #museun :spammer\r\n",
+        "@ban-duration=600;room-id=12345678;target-user-id=87654321 :tmi.twitch.tv CLEARCHAT #museun :spammer\r\n",
+        ":tmi.twitch.tv CLEARCHAT #museun\r\n",
+        "@login=spammer;room-id=;target-msg-id=abc-123;tmi-sent-ts=1642720582342 :tmi.twitch.tv CLEARMSG #museun :buy followers\r\n",
+        "@badge-info=;badges=;color=#0000FF;display-name=shaken_bot;emote-sets=0,300374282;user-id=12345678;user-type= :tmi.twitch.tv GLOBALUSERSTATE\r\n",
+        ":tmi.twitch.tv HOSTTARGET #museun :shaken_bot 10\r\n",
+        ":museun!museun@museun.tmi.twitch.tv JOIN #museun\r\n",
+        "@msg-id=msg_ratelimit :tmi.twitch.tv NOTICE #museun :Your message was not sent because you are sending messages too quickly.\r\n",
+        ":tmi.twitch.tv NOTICE * :Login authentication failed\r\n",
+        ":museun!museun@museun.tmi.twitch.tv PART #museun\r\n",
+        "PING :tmi.twitch.tv\r\n",
+        ":tmi.twitch.tv PONG tmi.twitch.tv :1234\r\n",
+        "@badge-info=subscriber/8;badges=moderator/1,subscriber/6;color=#FF0000;display-name=Museun;emotes=25:0-4;id=b34ccfc7-4977-403a-8a94-33c6bac34fb8;mod=1;room-id=12345678;tmi-sent-ts=1507246572675;user-id=87654321 :museun!museun@museun.tmi.twitch.tv PRIVMSG #museun :Kappa hello\r\n",
+        ":museun!museun@museun.tmi.twitch.tv PRIVMSG #museun :\x01ACTION waves\x01\r\n",
+        "@display-name=Mus\\seun\\:;emotes= :museun!museun@museun.tmi.twitch.tv PRIVMSG #museun :héllo 🦀\r\n",
+        ":tmi.twitch.tv RECONNECT\r\n",
+        "@emote-only=0;followers-only=-1;r9k=0;room-id=12345678;slow=0;subs-only=0 :tmi.twitch.tv ROOMSTATE #museun\r\n",
+        "@badge-info=;badges=staff/1,broadcaster/1;color=#008000;display-name=ronni;emotes=;id=db25007f-7a18-43eb-9379-80131e44d633;login=ronni;mod=0;msg-id=resub;msg-param-cumulative-months=6;msg-param-sub-plan=Prime;room-id=12345678;system-msg=ronni\\shas\\ssubscribed\\sfor\\s6\\smonths!;tmi-sent-ts=1507246572675;user-id=87654321 :tmi.twitch.tv USERNOTICE #museun :Great stream -- keep it up!\r\n",
+        "@badge-info=;badges=;color=;display-name=shaken_bot;emote-sets=0;mod=0;subscriber=0;user-type= :tmi.twitch.tv USERSTATE #museun\r\n",
+        "@badges=;color=;display-name=museun;emotes=;message-id=1;thread-id=1_2;turbo=0;user-id=1;user-type= :museun!museun@museun.tmi.twitch.tv WHISPER shaken_bot :hello\r\n",
+        ":tmi.twitch.tv 421 shaken_bot FOOBAR :Unknown command\r\n",
+    ];
+
+    fn encoded(msg: &impl Encodable) -> String {
+        let mut out = Vec::new();
+        msg.encode(&mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn round_trip_corpus() {
+        for input in CORPUS {
+            let (_, irc) = crate::irc::parse_one(input).unwrap();
+            assert_eq!(encoded(&irc), *input);
+
+            let msg = Commands::from_irc(irc).unwrap();
+            assert_eq!(encoded(&msg), *input, "{:?}", msg);
+
+            // and after being parsed again
+            let output = encoded(&msg.into_owned());
+            let (_, irc) = crate::irc::parse_one(&output).unwrap();
+            assert_eq!(encoded(&Commands::from_irc(irc).unwrap()), *input);
+        }
+    }
+
+    #[test]
+    fn round_trip_typed() {
+        let input = CORPUS[15];
+        let (_, irc) = crate::irc::parse_one(input).unwrap();
+        assert_eq!(encoded(&Privmsg::from_irc(irc).unwrap()), input);
+
+        let input = CORPUS[9];
+        let (_, irc) = crate::irc::parse_one(input).unwrap();
+        assert_eq!(encoded(&Join::from_irc(irc).unwrap()), input);
+    }
+
+    #[test]
+    fn canonical() {
+        // surrounding whitespace goes, and the line ending is added
+        let (_, irc) =
+            crate::irc::parse_one("  :museun!museun@museun.tmi.twitch.tv JOIN #museun  \r\n")
+                .unwrap();
+        assert_eq!(
+            encoded(&irc),
+            ":museun!museun@museun.tmi.twitch.tv JOIN #museun\r\n"
+        );
+
+        let irc = IrcMessage::parse(crate::MaybeOwned::Borrowed("PING :1234")).unwrap();
+        assert_eq!(encoded(&Ping::from_irc(irc).unwrap()), "PING :1234\r\n");
+    }
+
+    #[test]
+    #[cfg(feature = "testing")]
+    fn round_trip_generated() {
+        let mut gen = crate::test::Generator::with_seed(87);
+        for _ in 0..1000 {
+            let msg = gen.message();
+            let output = encoded(&msg);
+            assert_eq!(output.trim_end(), msg.raw().trim());
+
+            // which is already canonical
+            let (_, irc) = crate::irc::parse_one(&output).unwrap();
+            assert_eq!(encoded(&Commands::from_irc(irc).unwrap()), output);
+        }
+    }
+}
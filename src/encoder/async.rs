@@ -1,17 +1,42 @@
 cfg_async! {
 use std::{
-    io::{Result as IoResult, Write},
+    io::{Error as IoError, ErrorKind, IoSlice, Result as IoResult, Write},
     pin::Pin,
     task::{Context, Poll},
 };
 
 use futures_lite::{AsyncWrite, AsyncWriteExt};
 
+/// When an [AsyncEncoder] writes the messages it has batched with [AsyncEncoder::batch()].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum FlushThreshold {
+    /// Once at least this many bytes are batched
+    Bytes(usize),
+    /// Once at least this many messages are batched
+    Messages(usize),
+}
+
+impl Default for FlushThreshold {
+    fn default() -> Self {
+        Self::Bytes(16 * 1024)
+    }
+}
+
 /// An asynchronous encoder.
+///
+/// This writes straight into the [AsyncWrite](futures_lite::AsyncWrite) it wraps, so it's also a [MessageSink](crate::writer::MessageSink).
+///
+/// Messages can also be batched, with [AsyncEncoder::batch()]. These are written with a single vectored write
+/// once the [FlushThreshold] is reached (or when [AsyncEncoder::flush_batch()] is called), rather than a write per line.
+/// The [AsyncRunner](crate::AsyncRunner) batches the messages it sends together, see [AsyncRunner::set_flush_threshold()](crate::AsyncRunner::set_flush_threshold()).
 pub struct AsyncEncoder<W> {
     pub(crate) writer: W,
     pos: usize,
     data: Vec<u8>,
+    batch: Vec<Box<[u8]>>,
+    batched: usize,
+    threshold: FlushThreshold,
 }
 
 impl<W> std::fmt::Debug for AsyncEncoder<W> {
@@ -29,6 +54,9 @@ where
             writer: self.writer.clone(),
             pos: 0,
             data: vec![],
+            batch: Vec::new(),
+            batched: 0,
+            threshold: self.threshold,
         }
     }
 }
@@ -77,13 +105,33 @@ where
             writer,
             pos: 0,
             data: Vec::with_capacity(1024),
+            batch: Vec::new(),
+            batched: 0,
+            threshold: FlushThreshold::default(),
         }
     }
 
+    /// Write the batched messages once this threshold is reached
+    pub fn flush_threshold(mut self, threshold: FlushThreshold) -> Self {
+        self.set_flush_threshold(threshold);
+        self
+    }
+
+    /// Write the batched messages once this threshold is reached
+    pub fn set_flush_threshold(&mut self, threshold: FlushThreshold) {
+        self.threshold = threshold;
+    }
+
+    /// How many messages are batched, waiting to be written
+    pub fn batched(&self) -> usize {
+        self.batch.len()
+    }
+
     /// Get the inner [futures_lite::AsyncWrite] instance out
     ///
     /// This writes and flushes any buffered data before it consumes self.
     pub async fn into_inner(mut self) -> IoResult<W> {
+        self.flush_batch().await?;
         if self.data.is_empty() {
             return Ok(self.writer);
         }
@@ -96,14 +144,21 @@ where
 
     /// Encode this [Encodable](crate::Encodable) message to the writer.
     ///
-    /// This flushes the data before returning
+    /// This flushes the data before returning. Any batched messages are written first.
     pub async fn encode<M>(&mut self, msg: M) -> IoResult<()>
     where
         M: crate::Encodable + Send + Sync,
         W: Unpin,
     {
+        if !self.batch.is_empty() {
+            let mut data = Vec::new();
+            msg.encode(&mut data)?;
+            return self.encode_bytes(&data).await;
+        }
+
         msg.encode(&mut self.data)?;
         let data = &self.data[self.pos..];
+        event!(trace, "write", "> {}", String::from_utf8_lossy(data).escape_debug());
 
         self.writer.write_all(data).await?;
         self.writer.flush().await?;
@@ -112,6 +167,105 @@ where
         self.pos = 0;
         Ok(())
     }
+
+    // writes (and flushes) an already encoded message, so the caller can reuse its buffer
+    pub(crate) async fn encode_bytes(&mut self, data: &[u8]) -> IoResult<()> {
+        if !self.batch.is_empty() {
+            // this keeps the order, and it's still one write
+            self.batch_bytes(data.into()).await?;
+            return self.flush_batch().await;
+        }
+
+        event!(trace, "write", "> {}", String::from_utf8_lossy(data).escape_debug());
+
+        self.writer.write_all(data).await?;
+        self.writer.flush().await
+    }
+
+    /// Encode this [Encodable](crate::Encodable) message into the batch.
+    ///
+    /// The batch is written once the [FlushThreshold] is reached, or when [AsyncEncoder::flush_batch()] is called.
+    pub async fn batch<M>(&mut self, msg: M) -> IoResult<()>
+    where
+        M: crate::Encodable + Send + Sync,
+    {
+        let mut data = Vec::new();
+        msg.encode(&mut data)?;
+        self.batch_bytes(data.into()).await
+    }
+
+    // batches an already encoded message, writing the batch if it's over the threshold
+    pub(crate) async fn batch_bytes(&mut self, data: Box<[u8]>) -> IoResult<()> {
+        if data.is_empty() {
+            return Ok(());
+        }
+        self.batched += data.len();
+        self.batch.push(data);
+
+        let full = match self.threshold {
+            FlushThreshold::Bytes(bytes) => self.batched >= bytes,
+            FlushThreshold::Messages(messages) => self.batch.len() >= messages,
+        };
+        if full {
+            self.flush_batch().await?;
+        }
+        Ok(())
+    }
+
+    /// Write (and flush) the batched messages, with as few vectored writes as the writer allows.
+    pub async fn flush_batch(&mut self) -> IoResult<()> {
+        if self.batch.is_empty() {
+            return Ok(());
+        }
+
+        for data in &self.batch {
+            event!(trace, "write", "> {}", String::from_utf8_lossy(data).escape_debug());
+        }
+
+        // the message, and how much of it was written
+        let (mut index, mut offset) = (0, 0);
+        while index < self.batch.len() {
+            let slices = std::iter::once(&self.batch[index][offset..])
+                .chain(self.batch[index + 1..].iter().map(|data| &**data))
+                .map(IoSlice::new)
+                .collect::<Vec<_>>();
+
+            let mut written = self.writer.write_vectored(&slices).await?;
+            if written == 0 {
+                return Err(IoError::new(
+                    ErrorKind::WriteZero,
+                    "failed to write the batched messages",
+                ));
+            }
+
+            while written > 0 {
+                let left = self.batch[index].len() - offset;
+                if written < left {
+                    offset += written;
+                    break;
+                }
+                written -= left;
+                index += 1;
+                offset = 0;
+            }
+        }
+
+        self.batch.clear();
+        self.batched = 0;
+        self.writer.flush().await
+    }
+}
+
+#[cfg(all(feature = "tokio", feature = "tokio-util"))]
+impl<W> AsyncEncoder<tokio_util::compat::Compat<W>>
+where
+    W: tokio::io::AsyncWrite + Send + Sync + Unpin,
+{
+    /// Create a new Encoder over this [tokio::io::AsyncWrite] instance
+    pub fn from_tokio(writer: W) -> Self {
+        use tokio_util::compat::TokioAsyncWriteCompatExt as _;
+        Self::new(writer.compat_write())
+    }
 }
 
 impl<W> AsyncWrite for AsyncEncoder<W>
@@ -165,5 +319,90 @@ mod tests {
         };
         futures_lite::future::block_on(fut);
     }
+
+    // a writer which takes at most 5 bytes per write, and counts the writes
+    #[derive(Default)]
+    struct Trickle {
+        data: Vec<u8>,
+        writes: usize,
+    }
+
+    impl AsyncWrite for Trickle {
+        fn poll_write(
+            self: Pin<&mut Self>,
+            ctx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<IoResult<usize>> {
+            self.poll_write_vectored(ctx, &[IoSlice::new(buf)])
+        }
+
+        fn poll_write_vectored(
+            mut self: Pin<&mut Self>,
+            _ctx: &mut Context<'_>,
+            bufs: &[IoSlice<'_>],
+        ) -> Poll<IoResult<usize>> {
+            self.writes += 1;
+            let mut left = 5;
+            for buf in bufs {
+                let n = buf.len().min(left);
+                self.data.extend_from_slice(&buf[..n]);
+                left -= n;
+            }
+            Poll::Ready(Ok(5 - left))
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _ctx: &mut Context<'_>) -> Poll<IoResult<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_close(self: Pin<&mut Self>, _ctx: &mut Context<'_>) -> Poll<IoResult<()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    #[test]
+    fn encoder_batch() {
+        futures_lite::future::block_on(async move {
+            let mut encoder = AsyncEncoder::new(Vec::new()).flush_threshold(FlushThreshold::Messages(3));
+
+            encoder.batch(join("#museun")).await.unwrap();
+            encoder.batch(join("#shaken_bot")).await.unwrap();
+            assert_eq!(encoder.batched(), 2);
+            assert!(encoder.writer.is_empty());
+
+            // the threshold writes them
+            encoder.batch(join("#test")).await.unwrap();
+            assert_eq!(encoder.batched(), 0);
+            assert_eq!(encoder.writer, b"JOIN #museun\r\nJOIN #shaken_bot\r\nJOIN #test\r\n");
+
+            // and an immediate write keeps the order
+            let mut encoder = AsyncEncoder::new(Vec::new());
+            encoder.batch(join("#museun")).await.unwrap();
+            encoder.encode(join("#shaken_bot")).await.unwrap();
+            assert_eq!(encoder.writer, b"JOIN #museun\r\nJOIN #shaken_bot\r\n");
+
+            // or once there are enough bytes
+            let mut encoder = AsyncEncoder::new(Vec::new()).flush_threshold(FlushThreshold::Bytes(20));
+            encoder.batch(join("#museun")).await.unwrap();
+            encoder.batch(join("#shaken_bot")).await.unwrap();
+            assert_eq!(encoder.batched(), 0);
+        });
+    }
+
+    #[test]
+    fn encoder_batch_partial_writes() {
+        futures_lite::future::block_on(async move {
+            let mut encoder = AsyncEncoder::new(Trickle::default());
+            for channel in &["#a", "#bb", "#ccc"] {
+                encoder.batch(join(channel)).await.unwrap();
+            }
+            encoder.flush_batch().await.unwrap();
+
+            let expected = b"JOIN #a\r\nJOIN #bb\r\nJOIN #ccc\r\n";
+            assert_eq!(encoder.writer.data, expected);
+            // 30 bytes, 5 at a time
+            assert_eq!(encoder.writer.writes, 6);
+        });
+    }
 }
 }
@@ -0,0 +1,71 @@
+use std::time::Duration;
+
+/// An error returned by the [EventSub](super::EventSub) client
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// An I/O error occured
+    Io(std::io::Error),
+    /// Twitch sent a message we could not deserialize
+    Json(serde_json::Error),
+    /// Twitch sent a message we didn't expect (e.g. something other than `session_welcome` after connecting)
+    UnexpectedMessage {
+        /// The message type that was received
+        message_type: String,
+    },
+    /// Twitch did not send anything (not even a keepalive) within the keepalive timeout.
+    ///
+    /// You should connect again and recreate your subscriptions.
+    KeepaliveTimeout {
+        /// How long we waited
+        timeout: Duration,
+    },
+    /// An HTTP request to Twitch failed
+    Http {
+        /// The HTTP status code
+        status: u16,
+        /// The response body
+        body: String,
+    },
+    /// The connection was closed before the session was established
+    UnexpectedEof,
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "io error: {}", err),
+            Self::Json(err) => write!(f, "could not deserialize message: {}", err),
+            Self::UnexpectedMessage { message_type } => {
+                write!(f, "unexpected message type: '{}'", message_type)
+            }
+            Self::KeepaliveTimeout { timeout } => {
+                write!(f, "no message was received within {:.2?}", timeout)
+            }
+            Self::Http { status, body } => write!(f, "http error ({}): {}", status, body),
+            Self::UnexpectedEof => write!(f, "reached an unexpected EOF"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            Self::Json(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        Self::Json(err)
+    }
+}
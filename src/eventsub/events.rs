@@ -0,0 +1,274 @@
+//! Typed EventSub event payloads
+use serde::{de::DeserializeOwned, Deserialize};
+
+/// A typed EventSub event.
+///
+/// This ties a payload type to its subscription type and version.
+pub trait EventType: DeserializeOwned {
+    /// The subscription type. e.g. `channel.poll.begin`
+    const TYPE: &'static str;
+    /// The subscription version. e.g. `1`
+    const VERSION: &'static str;
+}
+
+/// The reward that was redeemed
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[non_exhaustive]
+pub struct Reward {
+    /// The id of the reward
+    pub id: String,
+    /// The title of the reward
+    pub title: String,
+    /// How much the reward costs
+    pub cost: u64,
+    /// The prompt shown for the reward
+    #[serde(default)]
+    pub prompt: String,
+}
+
+/// A channel points custom reward redemption
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[non_exhaustive]
+pub struct Redemption {
+    /// The id of the redemption
+    pub id: String,
+    /// The broadcaster's user id
+    pub broadcaster_user_id: String,
+    /// The broadcaster's login
+    pub broadcaster_user_login: String,
+    /// The broadcaster's display name
+    pub broadcaster_user_name: String,
+    /// The user id of the redeemer
+    pub user_id: String,
+    /// The login of the redeemer
+    pub user_login: String,
+    /// The display name of the redeemer
+    pub user_name: String,
+    /// What the user entered, if the reward requires input
+    #[serde(default)]
+    pub user_input: String,
+    /// The status of the redemption. e.g. `unfulfilled`
+    pub status: String,
+    /// The reward that was redeemed
+    pub reward: Reward,
+    /// When this was redeemed, as an RFC3339 timestamp
+    pub redeemed_at: String,
+}
+
+impl Redemption {
+    /// Is this the redemption that sent this chat message?
+    ///
+    /// Rewards which require input send a chat message (with a `custom-reward-id` tag) as well as this notification.
+    /// They're matched by the reward, the user and what they entered.
+    pub fn matches(&self, msg: &crate::messages::Privmsg<'_>) -> bool {
+        let redemption = match msg.redemption() {
+            Some(redemption) => redemption,
+            None => return false,
+        };
+        redemption.reward_id == self.reward.id
+            && redemption.user_login.eq_ignore_ascii_case(&self.user_login)
+            && redemption.user_input.trim() == self.user_input.trim()
+    }
+}
+
+/// A user followed a channel
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[non_exhaustive]
+pub struct Follow {
+    /// The user id of the follower
+    pub user_id: String,
+    /// The login of the follower
+    pub user_login: String,
+    /// The display name of the follower
+    pub user_name: String,
+    /// The broadcaster's user id
+    pub broadcaster_user_id: String,
+    /// The broadcaster's login
+    pub broadcaster_user_login: String,
+    /// The broadcaster's display name
+    pub broadcaster_user_name: String,
+    /// When the user followed, as an RFC3339 timestamp
+    pub followed_at: String,
+}
+
+/// A choice in a poll
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[non_exhaustive]
+pub struct PollChoice {
+    /// The id of the choice
+    pub id: String,
+    /// The title of the choice
+    pub title: String,
+    /// The number of votes, this isn't sent when the poll begins
+    #[serde(default)]
+    pub votes: Option<u64>,
+}
+
+/// A poll
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[non_exhaustive]
+pub struct Poll {
+    /// The id of the poll
+    pub id: String,
+    /// The broadcaster's user id
+    pub broadcaster_user_id: String,
+    /// The broadcaster's login
+    pub broadcaster_user_login: String,
+    /// The broadcaster's display name
+    pub broadcaster_user_name: String,
+    /// The question being asked
+    pub title: String,
+    /// The choices
+    pub choices: Vec<PollChoice>,
+    /// When the poll started, as an RFC3339 timestamp
+    pub started_at: String,
+    /// When the poll will end, as an RFC3339 timestamp
+    #[serde(default)]
+    pub ends_at: Option<String>,
+    /// When the poll ended, as an RFC3339 timestamp
+    #[serde(default)]
+    pub ended_at: Option<String>,
+    /// How the poll ended. e.g. `completed`
+    #[serde(default)]
+    pub status: Option<String>,
+}
+
+/// A possible outcome of a prediction
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[non_exhaustive]
+pub struct PredictionOutcome {
+    /// The id of the outcome
+    pub id: String,
+    /// The title of the outcome
+    pub title: String,
+    /// The color of the outcome. e.g. `blue` or `pink`
+    pub color: String,
+    /// How many users predicted this outcome
+    #[serde(default)]
+    pub users: Option<u64>,
+    /// How many channel points were spent on this outcome
+    #[serde(default)]
+    pub channel_points: Option<u64>,
+}
+
+/// A prediction
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[non_exhaustive]
+pub struct Prediction {
+    /// The id of the prediction
+    pub id: String,
+    /// The broadcaster's user id
+    pub broadcaster_user_id: String,
+    /// The broadcaster's login
+    pub broadcaster_user_login: String,
+    /// The broadcaster's display name
+    pub broadcaster_user_name: String,
+    /// The title of the prediction
+    pub title: String,
+    /// The possible outcomes
+    pub outcomes: Vec<PredictionOutcome>,
+    /// When the prediction started, as an RFC3339 timestamp
+    pub started_at: String,
+    /// When the prediction will be locked, as an RFC3339 timestamp
+    #[serde(default)]
+    pub locks_at: Option<String>,
+    /// When the prediction was locked, as an RFC3339 timestamp
+    #[serde(default)]
+    pub locked_at: Option<String>,
+    /// When the prediction ended, as an RFC3339 timestamp
+    #[serde(default)]
+    pub ended_at: Option<String>,
+    /// The outcome that won
+    #[serde(default)]
+    pub winning_outcome_id: Option<String>,
+    /// How the prediction ended. e.g. `resolved`
+    #[serde(default)]
+    pub status: Option<String>,
+}
+
+/// A hype train
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[non_exhaustive]
+pub struct HypeTrain {
+    /// The id of the hype train
+    pub id: String,
+    /// The broadcaster's user id
+    pub broadcaster_user_id: String,
+    /// The broadcaster's login
+    pub broadcaster_user_login: String,
+    /// The broadcaster's display name
+    pub broadcaster_user_name: String,
+    /// The current level
+    pub level: u64,
+    /// Total points contributed
+    pub total: u64,
+    /// Points contributed towards the current level
+    #[serde(default)]
+    pub progress: Option<u64>,
+    /// Points needed to reach the next level
+    #[serde(default)]
+    pub goal: Option<u64>,
+    /// When the hype train started, as an RFC3339 timestamp
+    pub started_at: String,
+    /// When the hype train will expire, as an RFC3339 timestamp
+    #[serde(default)]
+    pub expires_at: Option<String>,
+    /// When the hype train ended, as an RFC3339 timestamp
+    #[serde(default)]
+    pub ended_at: Option<String>,
+    /// When the next hype train can start, as an RFC3339 timestamp
+    #[serde(default)]
+    pub cooldown_ends_at: Option<String>,
+}
+
+macro_rules! event_types {
+    ($($(#[$meta:meta])* $ident:ident($inner:ty) => $ty:expr, $version:expr;)*) => {
+        $(
+            $(#[$meta])*
+            #[derive(Debug, Clone, PartialEq, Deserialize)]
+            #[serde(transparent)]
+            pub struct $ident(pub $inner);
+
+            impl EventType for $ident {
+                const TYPE: &'static str = $ty;
+                const VERSION: &'static str = $version;
+            }
+
+            impl std::ops::Deref for $ident {
+                type Target = $inner;
+                fn deref(&self) -> &Self::Target {
+                    &self.0
+                }
+            }
+        )*
+    };
+}
+
+event_types! {
+    /// `channel.channel_points_custom_reward_redemption.add`
+    RedemptionAdd(Redemption) => "channel.channel_points_custom_reward_redemption.add", "1";
+    /// `channel.channel_points_custom_reward_redemption.update`
+    RedemptionUpdate(Redemption) => "channel.channel_points_custom_reward_redemption.update", "1";
+    /// `channel.follow`
+    ChannelFollow(Follow) => "channel.follow", "2";
+    /// `channel.poll.begin`
+    PollBegin(Poll) => "channel.poll.begin", "1";
+    /// `channel.poll.progress`
+    PollProgress(Poll) => "channel.poll.progress", "1";
+    /// `channel.poll.end`
+    PollEnd(Poll) => "channel.poll.end", "1";
+    /// `channel.prediction.begin`
+    PredictionBegin(Prediction) => "channel.prediction.begin", "1";
+    /// `channel.prediction.progress`
+    PredictionProgress(Prediction) => "channel.prediction.progress", "1";
+    /// `channel.prediction.lock`
+    PredictionLock(Prediction) => "channel.prediction.lock", "1";
+    /// `channel.prediction.end`
+    PredictionEnd(Prediction) => "channel.prediction.end", "1";
+    /// `channel.hype_train.begin`
+    HypeTrainBegin(HypeTrain) => "channel.hype_train.begin", "1";
+    /// `channel.hype_train.progress`
+    HypeTrainProgress(HypeTrain) => "channel.hype_train.progress", "1";
+    /// `channel.hype_train.end`
+    HypeTrainEnd(HypeTrain) => "channel.hype_train.end", "1";
+}
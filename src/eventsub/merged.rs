@@ -0,0 +1,325 @@
+use super::{
+    events::{self, Follow, Poll, Redemption},
+    EventSub, EventType, Notification,
+};
+use crate::{
+    connector::Connector,
+    messages::{Commands, NoticeType, Privmsg, UserNotice},
+    runner::EventStream,
+    AsyncRunner,
+};
+
+use futures_lite::Stream;
+use std::{
+    pin::Pin,
+    task::{Context, Poll as TaskPoll},
+    time::Instant,
+};
+
+/// Where an [Event] came from
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Source {
+    /// The chat connection (an [AsyncRunner])
+    Chat,
+    /// The [EventSub] connection
+    EventSub,
+}
+
+/// A higher-level event, from either chat or EventSub. Produced by [MergedEvents].
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub enum Event {
+    /// A chat message
+    ChatMessage(Privmsg<'static>),
+    /// A channel points reward was redeemed
+    Redemption(Redemption),
+    /// A user followed the channel
+    Follow(Follow),
+    /// A subscription, resubscription or gifted subscription (from a chat `USERNOTICE`)
+    SubscriptionEvent(UserNotice<'static>),
+    /// A poll began, progressed or ended
+    PollUpdate(Poll),
+    /// Any other chat message
+    Chat(Commands<'static>),
+    /// Any other EventSub notification
+    Notification(Notification),
+}
+
+impl Event {
+    fn from_chat(msg: &Commands<'static>) -> Self {
+        match msg {
+            Commands::Privmsg(msg) => Self::ChatMessage(msg.clone()),
+            Commands::UserNotice(msg) if is_subscription(msg) => {
+                Self::SubscriptionEvent(msg.clone())
+            }
+            msg => Self::Chat(msg.clone()),
+        }
+    }
+
+    fn from_eventsub(msg: &Notification) -> Self {
+        fn try_event<T: EventType>(msg: &Notification) -> Option<T> {
+            match msg.event::<T>()? {
+                Ok(event) => Some(event),
+                Err(err) => {
+                    log::warn!("cannot deserialize '{}': {}", T::TYPE, err);
+                    None
+                }
+            }
+        }
+
+        if let Some(events::RedemptionAdd(redemption)) = try_event(msg) {
+            return Self::Redemption(redemption);
+        }
+        if let Some(events::ChannelFollow(follow)) = try_event(msg) {
+            return Self::Follow(follow);
+        }
+        let poll = try_event(msg)
+            .map(|events::PollBegin(poll)| poll)
+            .or_else(|| try_event(msg).map(|events::PollProgress(poll)| poll))
+            .or_else(|| try_event(msg).map(|events::PollEnd(poll)| poll));
+        if let Some(poll) = poll {
+            return Self::PollUpdate(poll);
+        }
+        Self::Notification(msg.clone())
+    }
+}
+
+fn is_subscription(msg: &UserNotice<'_>) -> bool {
+    matches!(
+        msg.msg_id(),
+        Some(NoticeType::Sub)
+            | Some(NoticeType::Resub)
+            | Some(NoticeType::SubGift)
+            | Some(NoticeType::AnonSubGift)
+            | Some(NoticeType::SubMysteryGift)
+            | Some(NoticeType::GiftPaidUpgrade)
+            | Some(NoticeType::AnonGiftPaidUpgrade)
+    )
+}
+
+/// An [Event], with where it came from and when it was received
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct SourcedEvent {
+    /// Where the event came from
+    pub source: Source,
+    /// When the event was received (when its runner or client produced it)
+    pub received_at: Instant,
+    /// The event
+    pub event: Event,
+}
+
+pin_project_lite::pin_project! {
+    /// A stream of [SourcedEvent]s from both a chat connection and an EventSub connection, in the order they were received.
+    ///
+    /// Create this with [merge()].
+    /// The runner and the client must still be driven (e.g. by calling their `next_message` in a loop) for events to arrive.
+    ///
+    /// This stream ends when both the runner and the client are dropped.
+    pub struct MergedEvents {
+        #[pin]
+        chat: EventStream<SourcedEvent>,
+        #[pin]
+        eventsub: EventStream<SourcedEvent>,
+        // the next event from each side, so the earlier one can be returned first
+        next_chat: Option<SourcedEvent>,
+        next_eventsub: Option<SourcedEvent>,
+        chat_done: bool,
+        eventsub_done: bool,
+    }
+}
+
+impl std::fmt::Debug for MergedEvents {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MergedEvents").finish()
+    }
+}
+
+/// Merge the events from a chat connection and an EventSub connection into one stream.
+///
+/// # Example
+/// ```no_run
+/// # use twitchchat::{AsyncRunner, connector::Connector};
+/// # use twitchchat::eventsub::{self, Event, EventSub, Source};
+/// # use futures_lite::StreamExt as _;
+/// # async fn demo<C: Connector>(runner: &mut AsyncRunner, client: &mut EventSub<C>) {
+/// let mut events = eventsub::merge(runner, client);
+///
+/// // (drive the runner and the client elsewhere)
+/// while let Some(sourced) = events.next().await {
+///     match sourced.event {
+///         Event::ChatMessage(msg) => println!("{}: {}", msg.name(), msg.data()),
+///         Event::Redemption(redemption) => println!("{} redeemed {}", redemption.user_name, redemption.reward.title),
+///         Event::Follow(follow) => println!("{} followed", follow.user_name),
+///         _ => {}
+///     }
+/// }
+/// # }
+/// ```
+pub fn merge<C>(runner: &mut AsyncRunner, client: &mut EventSub<C>) -> MergedEvents
+where
+    C: Connector,
+{
+    let chat = runner.dispatcher().subscribe_map(|msg| {
+        Some(SourcedEvent {
+            source: Source::Chat,
+            received_at: Instant::now(),
+            event: Event::from_chat(msg),
+        })
+    });
+
+    let eventsub = client.dispatcher.subscribe_map(|msg| {
+        Some(SourcedEvent {
+            source: Source::EventSub,
+            received_at: Instant::now(),
+            event: Event::from_eventsub(msg),
+        })
+    });
+
+    MergedEvents {
+        chat,
+        eventsub,
+        next_chat: None,
+        next_eventsub: None,
+        chat_done: false,
+        eventsub_done: false,
+    }
+}
+
+impl Stream for MergedEvents {
+    type Item = SourcedEvent;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> TaskPoll<Option<Self::Item>> {
+        let this = self.project();
+
+        if this.next_chat.is_none() && !*this.chat_done {
+            match this.chat.poll_next(cx) {
+                TaskPoll::Ready(Some(event)) => *this.next_chat = Some(event),
+                TaskPoll::Ready(None) => *this.chat_done = true,
+                TaskPoll::Pending => {}
+            }
+        }
+
+        if this.next_eventsub.is_none() && !*this.eventsub_done {
+            match this.eventsub.poll_next(cx) {
+                TaskPoll::Ready(Some(event)) => *this.next_eventsub = Some(event),
+                TaskPoll::Ready(None) => *this.eventsub_done = true,
+                TaskPoll::Pending => {}
+            }
+        }
+
+        // events are timestamped as they're dispatched, so anything still pending was received after these
+        let chat_first = match (&*this.next_chat, &*this.next_eventsub) {
+            (Some(chat), Some(eventsub)) => chat.received_at <= eventsub.received_at,
+            (Some(..), None) => true,
+            (None, Some(..)) => false,
+            (None, None) if *this.chat_done && *this.eventsub_done => return TaskPoll::Ready(None),
+            (None, None) => return TaskPoll::Pending,
+        };
+
+        TaskPoll::Ready(if chat_first {
+            this.next_chat.take()
+        } else {
+            this.next_eventsub.take()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FromIrcMessage as _, IntoOwned as _};
+
+    fn chat(input: &str) -> Event {
+        let (_, msg) = crate::irc::parse_one(input).unwrap();
+        Event::from_chat(&Commands::from_irc(msg).unwrap().into_owned())
+    }
+
+    fn notification(kind: &str, event: serde_json::Value) -> Event {
+        let msg = serde_json::from_value(serde_json::json!({
+            "subscription": { "id": "1", "status": "enabled", "type": kind, "version": "1" },
+            "event": event,
+        }))
+        .unwrap();
+        Event::from_eventsub(&msg)
+    }
+
+    #[test]
+    fn chat_events() {
+        let event = chat(":museun!museun@museun PRIVMSG #museun :hello\r\n");
+        assert!(matches!(event, Event::ChatMessage(msg) if msg.data() == "hello"));
+
+        let event = chat("@msg-id=resub :tmi.twitch.tv USERNOTICE #museun :hello\r\n");
+        assert!(matches!(event, Event::SubscriptionEvent(..)));
+
+        let event = chat("@msg-id=raid :tmi.twitch.tv USERNOTICE #museun\r\n");
+        assert!(matches!(event, Event::Chat(Commands::UserNotice(..))));
+    }
+
+    #[test]
+    fn eventsub_events() {
+        let follow = serde_json::json!({
+            "user_id": "1234",
+            "user_login": "cool_user",
+            "user_name": "Cool_User",
+            "broadcaster_user_id": "1337",
+            "broadcaster_user_login": "cooler_user",
+            "broadcaster_user_name": "Cooler_User",
+            "followed_at": "2020-07-15T18:16:11.17106713Z"
+        });
+        let event = notification("channel.follow", follow);
+        assert!(matches!(event, Event::Follow(follow) if follow.user_login == "cool_user"));
+
+        let poll = serde_json::json!({
+            "id": "1243456",
+            "broadcaster_user_id": "1337",
+            "broadcaster_user_login": "cool_user",
+            "broadcaster_user_name": "Cool_User",
+            "title": "Aren't shoes just really hard socks?",
+            "choices": [],
+            "started_at": "2020-07-15T17:16:03.17106713Z"
+        });
+        let event = notification("channel.poll.progress", poll);
+        assert!(matches!(event, Event::PollUpdate(..)));
+
+        let event = notification("channel.raid", serde_json::json!({}));
+        assert!(matches!(event, Event::Notification(..)));
+    }
+
+    #[test]
+    fn merged_in_order() {
+        use crate::runner::Dispatcher;
+        use futures_lite::{future::block_on, StreamExt as _};
+
+        let mut chat_tx = Dispatcher::<SourcedEvent>::default();
+        let mut eventsub_tx = Dispatcher::<SourcedEvent>::default();
+        let merged = MergedEvents {
+            chat: chat_tx.subscribe_map(|msg: &SourcedEvent| Some(msg.clone())),
+            eventsub: eventsub_tx.subscribe_map(|msg: &SourcedEvent| Some(msg.clone())),
+            next_chat: None,
+            next_eventsub: None,
+            chat_done: false,
+            eventsub_done: false,
+        };
+
+        let now = Instant::now();
+        let sourced = |source, millis| SourcedEvent {
+            source,
+            received_at: now + std::time::Duration::from_millis(millis),
+            event: chat(":museun!museun@museun PRIVMSG #museun :hello\r\n"),
+        };
+        let first = sourced(Source::EventSub, 1);
+        let second = sourced(Source::Chat, 2);
+        let third = sourced(Source::EventSub, 3);
+        chat_tx.dispatch(&second);
+        eventsub_tx.dispatch(&first);
+        eventsub_tx.dispatch(&third);
+        drop((chat_tx, eventsub_tx));
+
+        let sources = block_on(merged.map(|e| e.source).collect::<Vec<_>>());
+        assert_eq!(
+            sources,
+            vec![Source::EventSub, Source::Chat, Source::EventSub]
+        );
+    }
+}
@@ -0,0 +1,108 @@
+use serde::{de::DeserializeOwned, Deserialize};
+
+use super::EventType;
+
+/// The metadata attached to every EventSub message
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[non_exhaustive]
+pub struct Metadata {
+    /// An unique id for this message. Twitch may resend messages, this can be used to detect that
+    pub message_id: String,
+    /// The type of this message. e.g. `notification`
+    pub message_type: String,
+    /// When this message was sent, as an RFC3339 timestamp
+    pub message_timestamp: String,
+    /// The subscription type, if this is a `notification` or `revocation`
+    #[serde(default)]
+    pub subscription_type: Option<String>,
+    /// The subscription version, if this is a `notification` or `revocation`
+    #[serde(default)]
+    pub subscription_version: Option<String>,
+}
+
+/// An EventSub WebSocket session
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[non_exhaustive]
+pub struct Session {
+    /// The id of this session. This is used when creating subscriptions
+    pub id: String,
+    /// The status of this session. e.g. `connected` or `reconnecting`
+    pub status: String,
+    /// When this session was connected, as an RFC3339 timestamp
+    #[serde(default)]
+    pub connected_at: Option<String>,
+    /// How many seconds Twitch will wait before sending a keepalive message
+    #[serde(default)]
+    pub keepalive_timeout_seconds: Option<u64>,
+    /// Where to reconnect to, if this is a `session_reconnect` message
+    #[serde(default)]
+    pub reconnect_url: Option<String>,
+}
+
+/// An EventSub subscription
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[non_exhaustive]
+pub struct Subscription {
+    /// The id of this subscription
+    pub id: String,
+    /// The status of this subscription. e.g. `enabled` or `authorization_revoked`
+    pub status: String,
+    /// The subscription type. e.g. `channel.poll.begin`
+    #[serde(rename = "type")]
+    pub kind: String,
+    /// The version of the subscription type
+    pub version: String,
+    /// How much this subscription costs
+    #[serde(default)]
+    pub cost: u64,
+    /// The conditions of this subscription (e.g. the `broadcaster_user_id`)
+    #[serde(default)]
+    pub condition: serde_json::Value,
+    /// When this subscription was created, as an RFC3339 timestamp
+    #[serde(default)]
+    pub created_at: Option<String>,
+}
+
+/// A notification for a subscription
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[non_exhaustive]
+pub struct Notification {
+    /// The subscription this notification is for
+    pub subscription: Subscription,
+    /// The raw event data
+    pub event: serde_json::Value,
+}
+
+impl Notification {
+    /// Try to deserialize the event as a specific [EventType].
+    ///
+    /// Returns `None` if this notification is for a different subscription type.
+    pub fn event<T: EventType>(&self) -> Option<Result<T, serde_json::Error>> {
+        if self.subscription.kind != T::TYPE {
+            return None;
+        }
+        Some(T::deserialize(&self.event))
+    }
+
+    /// Deserialize the event data as any type.
+    pub fn event_as<T: DeserializeOwned>(&self) -> Result<T, serde_json::Error> {
+        T::deserialize(&self.event)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub(super) struct Envelope {
+    pub(super) metadata: Metadata,
+    #[serde(default)]
+    pub(super) payload: serde_json::Value,
+}
+
+#[derive(Debug, Deserialize)]
+pub(super) struct SessionPayload {
+    pub(super) session: Session,
+}
+
+#[derive(Debug, Deserialize)]
+pub(super) struct RevocationPayload {
+    pub(super) subscription: Subscription,
+}
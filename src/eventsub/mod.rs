@@ -0,0 +1,555 @@
+//! A client for Twitch's [EventSub](https://dev.twitch.tv/docs/eventsub/handling-websocket-events) WebSocket transport.
+//!
+//! Some events (channel point redemptions, polls, predictions, hype trains) are not sent over IRC. This lets you receive them alongside your chat connection.
+//!
+//! To use the [EventSub] client:
+//! 1. choose a ***TLS*** [Connector](crate::connector::Connector) pointed at [EVENTSUB_ADDRESS] (with the domain [EVENTSUB_HOST]).
+//! 1. connect with [EventSub::connect()], this waits for the `session_welcome` message.
+//! 1. create your subscriptions with [subscribe()] (or [create_subscription()]) using the [session id](EventSub::session_id()).
+//!     * this requires a TLS connector pointed at [HELIX_ADDRESS] (with the domain [HELIX_HOST]).
+//! 1. call [EventSub::next_message()] in a loop.
+//!     * you can get typed streams of events with [EventSub::events()].
+//!
+//! Keepalives and `session_reconnect` messages are handled for you.
+//!
+//! To handle chat and EventSub events in one place, [merge()] them into one stream of [Event]s.
+//!
+//! # Example
+//! ```no_run
+//! # #[cfg(all(feature = "async-io", feature = "async-tls"))]
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use twitchchat::connector::async_io::ConnectorTls;
+//! use twitchchat::eventsub::{self, events::PollBegin, EventSub, Status};
+//!
+//! futures_lite::future::block_on(async move {
+//!     let connector = ConnectorTls::custom(eventsub::EVENTSUB_ADDRESS, eventsub::EVENTSUB_HOST)?;
+//!     let mut client = EventSub::connect(connector).await?;
+//!
+//!     let mut api = ConnectorTls::custom(eventsub::HELIX_ADDRESS, eventsub::HELIX_HOST)?;
+//!     let condition = serde_json::json!({ "broadcaster_user_id": "12345" });
+//!     eventsub::subscribe::<PollBegin, _>(
+//!         &mut api,
+//!         "client-id",
+//!         "oauth-token",
+//!         client.session_id(),
+//!         condition,
+//!     )
+//!     .await?;
+//!
+//!     while let Status::Notification(msg) = client.next_message().await? {
+//!         if let Some(Ok(poll)) = msg.event::<PollBegin>() {
+//!             println!("a poll started: {}", poll.title);
+//!         }
+//!     }
+//!     Ok(())
+//! })
+//! # }
+//! # #[cfg(not(all(feature = "async-io", feature = "async-tls")))]
+//! # fn main() {}
+//! ```
+use crate::{
+    connector::{websocket::WebSocketStream, Connector},
+    runner::{Dispatcher, EventStream},
+    util::{Either::*, FutExt as _},
+};
+
+use std::{collections::VecDeque, time::Duration};
+
+mod error;
+pub use error::Error;
+
+mod messages;
+use messages::{Envelope, RevocationPayload, SessionPayload};
+pub use messages::{Metadata, Notification, Session, Subscription};
+
+pub mod events;
+pub use events::EventType;
+
+mod merged;
+pub use merged::{merge, Event, MergedEvents, Source, SourcedEvent};
+
+/// The host for the EventSub WebSocket endpoint
+pub const EVENTSUB_HOST: &str = "eventsub.wss.twitch.tv";
+
+/// The socket address for the EventSub WebSocket endpoint (this requires TLS)
+pub const EVENTSUB_ADDRESS: &str = "eventsub.wss.twitch.tv:443";
+
+/// The path for the EventSub WebSocket endpoint
+pub const EVENTSUB_PATH: &str = "/ws";
+
+#[doc(inline)]
+pub use crate::http::{HELIX_ADDRESS, HELIX_HOST};
+
+// how many message ids to remember, Twitch may resend notifications
+const SEEN_CAPACITY: usize = 64;
+
+// Twitch uses this if it wasn't provided in the welcome message
+const DEFAULT_KEEPALIVE: Duration = Duration::from_secs(10);
+
+// some slack for the keepalive, so network latency doesn't cause a timeout
+const KEEPALIVE_GRACE: Duration = Duration::from_secs(2);
+
+/// Status produced by [EventSub::next_message()]
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Status {
+    /// A notification for one of your subscriptions
+    Notification(Notification),
+    /// Twitch revoked one of your subscriptions (see its `status` for why)
+    Revocation(Subscription),
+    /// Twitch asked us to reconnect, and the new session was established.
+    ///
+    /// Your subscriptions carry over to the new session.
+    Reconnected,
+    /// The connection was closed
+    Closed,
+}
+
+/// An EventSub WebSocket session.
+pub struct EventSub<C>
+where
+    C: Connector,
+{
+    connector: C,
+    stream: WebSocketStream<C::Output>,
+    session: Session,
+    keepalive: Duration,
+    seen: VecDeque<String>,
+    dispatcher: Dispatcher<Notification>,
+}
+
+impl<C> std::fmt::Debug for EventSub<C>
+where
+    C: Connector,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EventSub")
+            .field("session", &self.session)
+            .finish()
+    }
+}
+
+impl<C> EventSub<C>
+where
+    C: Connector,
+{
+    /// Connect to the ***default Twitch*** EventSub endpoint and wait for the session to be established.
+    pub async fn connect(connector: C) -> Result<Self, Error> {
+        Self::connect_to(connector, EVENTSUB_HOST, EVENTSUB_PATH).await
+    }
+
+    /// Connect to the ***default Twitch*** EventSub endpoint, requesting a specific keepalive timeout.
+    ///
+    /// Twitch clamps this to between 10 and 600 seconds.
+    pub async fn connect_with_keepalive(connector: C, keepalive: Duration) -> Result<Self, Error> {
+        let secs = keepalive.as_secs().clamp(10, 600);
+        let path = format!("{}?keepalive_timeout_seconds={}", EVENTSUB_PATH, secs);
+        Self::connect_to(connector, EVENTSUB_HOST, &path).await
+    }
+
+    /// Connect to a custom EventSub endpoint (e.g. the Twitch CLI's mock server).
+    ///
+    /// The `host` is sent in the `Host` header during the WebSocket handshake.
+    pub async fn connect_to(mut connector: C, host: &str, path: &str) -> Result<Self, Error> {
+        let (stream, session) = Self::open(&mut connector, host, path).await?;
+        log::debug!("eventsub session established: {}", session.id);
+
+        Ok(Self {
+            connector,
+            stream,
+            keepalive: keepalive_for(&session),
+            session,
+            seen: VecDeque::with_capacity(SEEN_CAPACITY),
+            dispatcher: Dispatcher::default(),
+        })
+    }
+
+    /// Get the id of the current session. This is needed to create subscriptions
+    pub fn session_id(&self) -> &str {
+        &self.session.id
+    }
+
+    /// Get the current session
+    pub fn session(&self) -> &Session {
+        &self.session
+    }
+
+    /// Get a stream of a specific [EventType].
+    ///
+    /// The client must still be driven (by calling [EventSub::next_message()] in a loop) for events to arrive.
+    pub fn events<T>(&mut self) -> EventStream<T>
+    where
+        T: EventType + Send + Sync + 'static,
+    {
+        self.dispatcher
+            .subscribe_map(|msg: &Notification| match msg.event::<T>()? {
+                Ok(event) => Some(event),
+                Err(err) => {
+                    log::warn!("cannot deserialize '{}': {}", T::TYPE, err);
+                    None
+                }
+            })
+    }
+
+    /// Get the next notification (or session state change). You'll usually want to call this in a loop.
+    ///
+    /// This returns [Error::KeepaliveTimeout] if Twitch goes quiet for longer than the session's keepalive timeout.
+    pub async fn next_message(&mut self) -> Result<Status, Error> {
+        loop {
+            let timeout = self.keepalive;
+            let read = self.stream.read_message();
+            let data = match read.either(futures_timer::Delay::new(timeout)).await {
+                Left(Ok(Some(data))) => data,
+                Left(Ok(None)) => return Ok(Status::Closed),
+                Left(Err(err)) => return Err(err.into()),
+                Right(()) => return Err(Error::KeepaliveTimeout { timeout }),
+            };
+
+            let Envelope { metadata, payload } = serde_json::from_slice(&data)?;
+            match &*metadata.message_type {
+                "session_keepalive" => continue,
+
+                "notification" => {
+                    if !self.first_time_seen(metadata.message_id) {
+                        continue;
+                    }
+                    let msg: Notification = serde_json::from_value(payload)?;
+                    self.dispatcher.dispatch(&msg);
+                    return Ok(Status::Notification(msg));
+                }
+
+                "revocation" => {
+                    let RevocationPayload { subscription } = serde_json::from_value(payload)?;
+                    log::warn!(
+                        "subscription '{}' was revoked: {}",
+                        subscription.kind,
+                        subscription.status
+                    );
+                    return Ok(Status::Revocation(subscription));
+                }
+
+                "session_reconnect" => {
+                    let SessionPayload { session } = serde_json::from_value(payload)?;
+                    self.reconnect(session).await?;
+                    return Ok(Status::Reconnected);
+                }
+
+                ty => log::debug!("ignoring unknown eventsub message: {}", ty),
+            }
+        }
+    }
+
+    async fn reconnect(&mut self, session: Session) -> Result<(), Error> {
+        let url = session
+            .reconnect_url
+            .ok_or_else(|| Error::UnexpectedMessage {
+                message_type: String::from("session_reconnect"),
+            })?;
+
+        let (host, path) = split_url(&url);
+        log::debug!("eventsub is reconnecting to: {}{}", host, path);
+
+        // the old connection is kept open until the new one is welcomed
+        let (stream, session) = Self::open(&mut self.connector, host, &path).await?;
+        self.stream = stream;
+        self.keepalive = keepalive_for(&session);
+        self.session = session;
+        Ok(())
+    }
+
+    async fn open(
+        connector: &mut C,
+        host: &str,
+        path: &str,
+    ) -> Result<(WebSocketStream<C::Output>, Session), Error> {
+        let stream = connector.connect().await?;
+        let mut stream = WebSocketStream::handshake(stream, host, path).await?;
+
+        let data = stream.read_message().await?.ok_or(Error::UnexpectedEof)?;
+        let Envelope { metadata, payload } = serde_json::from_slice(&data)?;
+        if metadata.message_type != "session_welcome" {
+            return Err(Error::UnexpectedMessage {
+                message_type: metadata.message_type,
+            });
+        }
+
+        let SessionPayload { session } = serde_json::from_value(payload)?;
+        Ok((stream, session))
+    }
+
+    fn first_time_seen(&mut self, id: String) -> bool {
+        if self.seen.contains(&id) {
+            return false;
+        }
+        if self.seen.len() == SEEN_CAPACITY {
+            self.seen.pop_front();
+        }
+        self.seen.push_back(id);
+        true
+    }
+}
+
+/// Create a subscription for the [EventType] `T` on this WebSocket session.
+///
+/// The `connector` should be a TLS connector pointed at [HELIX_ADDRESS].
+pub async fn subscribe<T, C>(
+    connector: &mut C,
+    client_id: &str,
+    token: &str,
+    session_id: &str,
+    condition: serde_json::Value,
+) -> Result<Subscription, Error>
+where
+    T: EventType,
+    C: Connector,
+{
+    create_subscription(
+        connector,
+        client_id,
+        token,
+        session_id,
+        T::TYPE,
+        T::VERSION,
+        condition,
+    )
+    .await
+}
+
+/// Create a subscription for the subscription type `kind` (at `version`) on this WebSocket session.
+///
+/// The `connector` should be a TLS connector pointed at [HELIX_ADDRESS].
+pub async fn create_subscription<C>(
+    connector: &mut C,
+    client_id: &str,
+    token: &str,
+    session_id: &str,
+    kind: &str,
+    version: &str,
+    condition: serde_json::Value,
+) -> Result<Subscription, Error>
+where
+    C: Connector,
+{
+    #[derive(serde::Deserialize)]
+    struct Response {
+        data: Vec<Subscription>,
+    }
+
+    let body = serde_json::json!({
+        "type": kind,
+        "version": version,
+        "condition": condition,
+        "transport": {
+            "method": "websocket",
+            "session_id": session_id,
+        }
+    });
+
+    let req = crate::http::Request::new("POST", HELIX_HOST, "/helix/eventsub/subscriptions")
+        .header("Client-Id", client_id)
+        .header("Authorization", format!("Bearer {}", token))
+        .json(&body)?;
+
+    let resp = crate::http::send(connector, req).await?;
+    if !resp.is_success() {
+        return Err(Error::Http {
+            status: resp.status,
+            body: String::from_utf8_lossy(&resp.body).into_owned(),
+        });
+    }
+
+    let Response { data } = resp.json()?;
+    data.into_iter().next().ok_or(Error::Http {
+        status: resp.status,
+        body: String::from("no subscription was returned"),
+    })
+}
+
+fn keepalive_for(session: &Session) -> Duration {
+    session
+        .keepalive_timeout_seconds
+        .map(Duration::from_secs)
+        .unwrap_or(DEFAULT_KEEPALIVE)
+        + KEEPALIVE_GRACE
+}
+
+// splits 'wss://host/path?query' into ('host', '/path?query')
+fn split_url(url: &str) -> (&str, String) {
+    let url = url.splitn(2, "://").last().unwrap_or(url);
+    match url.find(['/', '?']) {
+        Some(pos) if url[pos..].starts_with('?') => (&url[..pos], format!("/{}", &url[pos..])),
+        Some(pos) => (&url[..pos], url[pos..].to_string()),
+        None => (url, String::from("/")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_reconnect_url() {
+        assert_eq!(
+            split_url("wss://eventsub.wss.twitch.tv/ws?id=1234"),
+            ("eventsub.wss.twitch.tv", String::from("/ws?id=1234"))
+        );
+        assert_eq!(
+            split_url("wss://eventsub.wss.twitch.tv?id=1234"),
+            ("eventsub.wss.twitch.tv", String::from("/?id=1234"))
+        );
+        assert_eq!(
+            split_url("eventsub.wss.twitch.tv"),
+            ("eventsub.wss.twitch.tv", String::from("/"))
+        );
+    }
+
+    #[test]
+    fn parse_welcome() {
+        let input = r#"{
+            "metadata": {
+                "message_id": "96a3f3b5-5dec-4eed-908e-e11ee657416c",
+                "message_type": "session_welcome",
+                "message_timestamp": "2023-07-19T14:56:51.634234626Z"
+            },
+            "payload": {
+                "session": {
+                    "id": "AQoQILE98gtqShGmLD7AM6yJThAB",
+                    "status": "connected",
+                    "connected_at": "2023-07-19T14:56:51.616329898Z",
+                    "keepalive_timeout_seconds": 10,
+                    "reconnect_url": null
+                }
+            }
+        }"#;
+
+        let Envelope { metadata, payload } = serde_json::from_str(input).unwrap();
+        assert_eq!(metadata.message_type, "session_welcome");
+        assert!(metadata.subscription_type.is_none());
+
+        let SessionPayload { session } = serde_json::from_value(payload).unwrap();
+        assert_eq!(session.id, "AQoQILE98gtqShGmLD7AM6yJThAB");
+        assert_eq!(keepalive_for(&session), Duration::from_secs(12));
+        assert!(session.reconnect_url.is_none());
+    }
+
+    #[test]
+    fn parse_notification() {
+        let input = r#"{
+            "metadata": {
+                "message_id": "befa7b53-d79d-478f-86b9-120f112b044e",
+                "message_type": "notification",
+                "message_timestamp": "2022-11-16T10:11:12.464757833Z",
+                "subscription_type": "channel.poll.begin",
+                "subscription_version": "1"
+            },
+            "payload": {
+                "subscription": {
+                    "id": "f1c2a387-161a-49f9-a165-0f21d7a4e1c4",
+                    "status": "enabled",
+                    "type": "channel.poll.begin",
+                    "version": "1",
+                    "cost": 0,
+                    "condition": { "broadcaster_user_id": "1337" },
+                    "transport": { "method": "websocket", "session_id": "AQoQexAWVYKSTIu4ec_2VAxyuhAB" },
+                    "created_at": "2022-11-16T10:11:12.464757833Z"
+                },
+                "event": {
+                    "id": "1243456",
+                    "broadcaster_user_id": "1337",
+                    "broadcaster_user_login": "cool_user",
+                    "broadcaster_user_name": "Cool_User",
+                    "title": "Aren't shoes just really hard socks?",
+                    "choices": [
+                        { "id": "123", "title": "Yeah!" },
+                        { "id": "124", "title": "No!" }
+                    ],
+                    "bits_voting": { "is_enabled": true, "amount_per_vote": 10 },
+                    "started_at": "2020-07-15T17:16:03.17106713Z",
+                    "ends_at": "2020-07-15T17:16:08.17106713Z"
+                }
+            }
+        }"#;
+
+        let Envelope { metadata, payload } = serde_json::from_str(input).unwrap();
+        assert_eq!(
+            metadata.subscription_type.as_deref(),
+            Some("channel.poll.begin")
+        );
+
+        let msg: Notification = serde_json::from_value(payload).unwrap();
+        assert!(msg.event::<events::PollEnd>().is_none());
+        assert!(msg.event::<events::RedemptionAdd>().is_none());
+
+        let poll = msg.event::<events::PollBegin>().unwrap().unwrap();
+        assert_eq!(poll.title, "Aren't shoes just really hard socks?");
+        assert_eq!(poll.choices.len(), 2);
+        assert_eq!(poll.choices[0].votes, None);
+
+        let mut dispatcher = Dispatcher::default();
+        let polls =
+            dispatcher.subscribe_map(|msg: &Notification| msg.event::<events::PollBegin>()?.ok());
+        let ends =
+            dispatcher.subscribe_map(|msg: &Notification| msg.event::<events::PollEnd>()?.ok());
+        dispatcher.dispatch(&msg);
+        assert_eq!(polls.try_next().unwrap().id, "1243456");
+        assert!(ends.try_next().is_none());
+    }
+
+    #[test]
+    fn parse_redemption() {
+        let input = r#"{
+            "id": "17fa2df1-ad76-4804-bfa5-a40ef63efe63",
+            "broadcaster_user_id": "1337",
+            "broadcaster_user_login": "cool_user",
+            "broadcaster_user_name": "Cool_User",
+            "user_id": "9001",
+            "user_login": "cooler_user",
+            "user_name": "Cooler_User",
+            "user_input": "pogchamp",
+            "status": "unfulfilled",
+            "reward": {
+                "id": "92af127c-7326-4483-a52b-b0da0be61c01",
+                "title": "title",
+                "cost": 100,
+                "prompt": "reward prompt"
+            },
+            "redeemed_at": "2020-07-15T17:16:03.17106713Z"
+        }"#;
+
+        let redemption: events::RedemptionAdd = serde_json::from_str(input).unwrap();
+        assert_eq!(redemption.user_input, "pogchamp");
+        assert_eq!(redemption.reward.cost, 100);
+
+        use crate::{messages::Privmsg, FromIrcMessage as _};
+        let privmsg = |input| Privmsg::from_irc(crate::irc::parse_one(input).unwrap().1).unwrap();
+
+        let msg = privmsg("@custom-reward-id=92af127c-7326-4483-a52b-b0da0be61c01 :cooler_user!cooler_user@cooler_user PRIVMSG #cool_user :pogchamp\r\n");
+        assert!(redemption.matches(&msg));
+
+        let msg = privmsg("@custom-reward-id=92af127c-7326-4483-a52b-b0da0be61c01 :cooler_user!cooler_user@cooler_user PRIVMSG #cool_user :kappa\r\n");
+        assert!(!redemption.matches(&msg));
+
+        let msg = privmsg(":cooler_user!cooler_user@cooler_user PRIVMSG #cool_user :pogchamp\r\n");
+        assert!(!redemption.matches(&msg));
+    }
+
+    #[test]
+    fn parse_revocation() {
+        let input = r#"{
+            "subscription": {
+                "id": "f1c2a387-161a-49f9-a165-0f21d7a4e1c4",
+                "status": "authorization_revoked",
+                "type": "channel.follow",
+                "version": "1",
+                "cost": 1,
+                "condition": { "broadcaster_user_id": "12826" },
+                "transport": { "method": "websocket", "session_id": "AQoQexAWVYKSTIu4ec_2VAxyuhAB" },
+                "created_at": "2022-11-16T10:11:12.464757833Z"
+            }
+        }"#;
+
+        let RevocationPayload { subscription } = serde_json::from_str(input).unwrap();
+        assert_eq!(subscription.status, "authorization_revoked");
+        assert_eq!(subscription.kind, "channel.follow");
+    }
+}
@@ -0,0 +1,151 @@
+// top-level domains which are recognized without a scheme (`example.com`), so `e.g.` or `1.5` aren't links
+const TLDS: &[&str] = &[
+    "app", "be", "biz", "ca", "cc", "click", "club", "co", "com", "de", "dev", "eu", "fm", "fr",
+    "gg", "info", "io", "link", "live", "ly", "me", "net", "online", "org", "ru", "sh", "shop",
+    "site", "store", "to", "top", "tv", "uk", "us", "xyz",
+];
+
+/// A link found in a message, with [find_links]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Link<'a> {
+    /// The link, as it was written
+    pub text: &'a str,
+    /// The host it points at, e.g. `clips.twitch.tv`
+    pub host: &'a str,
+    /// The byte offset of the link in the message
+    pub start: usize,
+}
+
+impl<'a> Link<'a> {
+    /// Whether the host is this domain, or a subdomain of it
+    pub fn is_on(&self, domain: &str) -> bool {
+        let host = self.host.trim_end_matches('.');
+        host.eq_ignore_ascii_case(domain)
+            || host.len() > domain.len()
+                && host.as_bytes()[host.len() - domain.len() - 1] == b'.'
+                && host[host.len() - domain.len()..].eq_ignore_ascii_case(domain)
+    }
+}
+
+/// Find the links in a message.
+///
+/// This finds links with a scheme (`https://..`), links starting with `www.`, and bare domains
+/// with a common top-level domain (`example.com/path`)
+///
+/// ```
+/// # use twitchchat::filters::find_links;
+/// let links = find_links("check out (https://clips.twitch.tv/abc) or twitch.tv/museun, e.g. not this")
+///     .map(|link| link.host)
+///     .collect::<Vec<_>>();
+/// assert_eq!(links, vec!["clips.twitch.tv", "twitch.tv"]);
+/// ```
+pub fn find_links(text: &str) -> impl Iterator<Item = Link<'_>> + '_ {
+    words(text).filter_map(|(start, word)| {
+        let (offset, link) = trim(word);
+        parse(link).map(|host| Link {
+            text: link,
+            host,
+            start: start + offset,
+        })
+    })
+}
+
+fn words(text: &str) -> impl Iterator<Item = (usize, &str)> + '_ {
+    text.split(char::is_whitespace)
+        .filter(|word| !word.is_empty())
+        .map(move |word| (word.as_ptr() as usize - text.as_ptr() as usize, word))
+}
+
+// links are often wrapped in brackets or quotes, or end a sentence
+fn trim(word: &str) -> (usize, &str) {
+    const LEADING: &[char] = &['(', '[', '<', '"', '\''];
+    const TRAILING: &[char] = &[')', ']', '>', '"', '\'', ',', '.', '!', '?', ':', ';'];
+    let trimmed = word.trim_start_matches(LEADING);
+    let offset = word.len() - trimmed.len();
+    (offset, trimmed.trim_end_matches(TRAILING))
+}
+
+fn parse(word: &str) -> Option<&str> {
+    let (rest, explicit) = match strip_prefix_ignore_case(word, "https://")
+        .or_else(|| strip_prefix_ignore_case(word, "http://"))
+    {
+        Some(rest) => (rest, true),
+        None => (word, false),
+    };
+
+    let host = rest.split(['/', '?', '#']).next()?;
+    // user info, and the port
+    let host = host.rsplit('@').next()?;
+    let host = host.split(':').next()?;
+
+    let mut labels = host.split('.');
+    let valid = labels.clone().count() >= 2
+        && labels.all(|label| {
+            !label.is_empty()
+                && label
+                    .chars()
+                    .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+        });
+    if !valid {
+        return None;
+    }
+
+    let tld = host.rsplit('.').next()?;
+    let is_link = explicit
+        || strip_prefix_ignore_case(host, "www.").is_some()
+        || TLDS.iter().any(|known| known.eq_ignore_ascii_case(tld));
+    Some(host).filter(|_| is_link)
+}
+
+fn strip_prefix_ignore_case<'a>(input: &'a str, prefix: &str) -> Option<&'a str> {
+    match input.get(..prefix.len()) {
+        Some(head) if head.eq_ignore_ascii_case(prefix) => Some(&input[prefix.len()..]),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+
+    #[test]
+    fn links() {
+        let find = |text| find_links(text).collect::<Vec<_>>();
+
+        let text = "go to HTTPS://Example.org:8080/path?q=1, now";
+        assert_eq!(
+            find(text),
+            vec![Link {
+                text: "HTTPS://Example.org:8080/path?q=1",
+                host: "Example.org",
+                start: 6,
+            }]
+        );
+        assert_eq!(find("www.example.info")[0].host, "www.example.info");
+        assert_eq!(find("\"bit.ly/abc\"")[0].start, 1);
+        // unknown top-level domains need a scheme
+        assert_eq!(find("www.example.invalid").len(), 1);
+        assert!(find("example.invalid").is_empty());
+        assert_eq!(find("http://localhost.invalid").len(), 1);
+
+        for text in &[
+            "e.g. this",
+            "1.5 seconds",
+            "...",
+            "a..com",
+            "http://",
+            "hello.",
+            "ok.ok",
+        ] {
+            assert!(find(text).is_empty(), "{}", text);
+        }
+
+        let link = find("clips.twitch.tv/abc").remove(0);
+        assert!(link.is_on("twitch.tv"));
+        assert!(link.is_on("TWITCH.TV"));
+        assert!(link.is_on("clips.twitch.tv"));
+        assert!(!link.is_on("witch.tv"));
+        assert!(!link.is_on("tv.twitch"));
+    }
+}
@@ -0,0 +1,284 @@
+//! Detectors for links and spam, for moderation bots.
+//!
+//! A [SpamFilter] runs the detectors you've enabled on a message, and returns a [Verdict] listing each [Detection].
+//! The detectors are also available on their own, e.g. [find_links] and [caps_percent].
+//!
+//! # Example
+//! ```
+//! # use twitchchat::{filters::{Detection, SpamFilter}, messages::Privmsg, FromIrcMessage};
+//! let filter = SpamFilter::new()
+//!     .links()
+//!     .allow_domain("twitch.tv")
+//!     .caps(10, 50)
+//!     .repeated(10);
+//!
+//! # let input = ":museun!museun@museun PRIVMSG #museun :FREE FOLLOWERS AT bigfollows.com!!!\r\n";
+//! # let msg = Privmsg::from_irc(twitchchat::irc::parse_one(input).unwrap().1).unwrap();
+//! // with `msg` being "FREE FOLLOWERS AT bigfollows.com!!!"
+//! let verdict = filter.check(&msg);
+//! assert!(!verdict.is_clean());
+//! assert_eq!(
+//!     verdict.detections(),
+//!     &[
+//!         Detection::Link { host: "bigfollows.com".into() },
+//!         Detection::Caps { percent: 53 },
+//!     ]
+//! );
+//!
+//! // clips are fine
+//! assert!(filter.check_text("look at this twitch.tv/museun/clip/abc").is_clean());
+//! ```
+use crate::messages::Privmsg;
+use crate::prelude::*;
+
+mod links;
+pub use links::{find_links, Link};
+
+mod text;
+pub use text::{caps_percent, combining_marks, drawing_symbols, longest_run};
+
+/// Something a [SpamFilter] found in a message
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Detection {
+    /// A link to a domain which isn't allowed
+    Link {
+        /// The host it points at
+        host: String,
+    },
+    /// Too much of the message is in capitals
+    Caps {
+        /// How much of the message is in capitals, as a percentage of its letters
+        percent: u8,
+    },
+    /// The same character is repeated too many times
+    Repeated {
+        /// The character
+        ch: char,
+        /// How many times it was repeated
+        count: usize,
+    },
+    /// There are too many combining marks (this is 'zalgo' text)
+    Zalgo {
+        /// How many combining marks there are
+        marks: usize,
+    },
+    /// There are too many drawing characters (this is ascii art)
+    Symbols {
+        /// How many drawing characters there are
+        count: usize,
+    },
+    /// There are too many emotes
+    Emotes {
+        /// How many emotes there are
+        count: usize,
+    },
+}
+
+/// What a [SpamFilter] found in a message
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Verdict {
+    detections: Vec<Detection>,
+}
+
+impl Verdict {
+    /// Whether nothing was found
+    pub fn is_clean(&self) -> bool {
+        self.detections.is_empty()
+    }
+
+    /// What was found, in the order the detectors ran
+    pub fn detections(&self) -> &[Detection] {
+        &self.detections
+    }
+
+    /// Take what was found
+    pub fn into_detections(self) -> Vec<Detection> {
+        self.detections
+    }
+}
+
+/// Runs the detectors which are enabled on messages. See the [module docs](self) for an example.
+///
+/// [SpamFilter::new()] doesn't enable anything, [SpamFilter::default()] enables each detector with a lenient threshold.
+#[derive(Debug, Clone)]
+pub struct SpamFilter {
+    links: bool,
+    allowed_domains: Vec<String>,
+    caps: Option<(usize, u8)>,
+    repeated: Option<usize>,
+    zalgo: Option<usize>,
+    symbols: Option<usize>,
+    emotes: Option<usize>,
+}
+
+impl Default for SpamFilter {
+    fn default() -> Self {
+        Self::new()
+            .links()
+            .caps(15, 80)
+            .repeated(15)
+            .zalgo(10)
+            .symbols(20)
+            .emotes(20)
+    }
+}
+
+impl SpamFilter {
+    /// Create a filter which doesn't detect anything, until the detectors are enabled
+    pub fn new() -> Self {
+        Self {
+            links: false,
+            allowed_domains: Vec::new(),
+            caps: None,
+            repeated: None,
+            zalgo: None,
+            symbols: None,
+            emotes: None,
+        }
+    }
+
+    /// Detect links, except to the allowed domains
+    pub fn links(mut self) -> Self {
+        self.links = true;
+        self
+    }
+
+    /// Allow links to this domain (and its subdomains), e.g. `twitch.tv`
+    pub fn allow_domain(mut self, domain: impl Into<String>) -> Self {
+        self.allowed_domains.push(domain.into());
+        self
+    }
+
+    /// Detect messages with at least `min_letters` letters, where at least `percent` of them are capitals
+    pub fn caps(mut self, min_letters: usize, percent: u8) -> Self {
+        self.caps.replace((min_letters, percent));
+        self
+    }
+
+    /// Detect the same character repeated more than `max` times in a row
+    pub fn repeated(mut self, max: usize) -> Self {
+        self.repeated.replace(max);
+        self
+    }
+
+    /// Detect more than `max` combining marks
+    pub fn zalgo(mut self, max: usize) -> Self {
+        self.zalgo.replace(max);
+        self
+    }
+
+    /// Detect more than `max` drawing characters
+    pub fn symbols(mut self, max: usize) -> Self {
+        self.symbols.replace(max);
+        self
+    }
+
+    /// Detect more than `max` emotes. This needs the `tags` capability, and is only checked by [SpamFilter::check()]
+    pub fn emotes(mut self, max: usize) -> Self {
+        self.emotes.replace(max);
+        self
+    }
+
+    /// Check a message
+    pub fn check(&self, msg: &Privmsg<'_>) -> Verdict {
+        let mut verdict = self.check_text(msg.data());
+        if let Some(max) = self.emotes {
+            let count = msg.emotes().iter().map(|emote| emote.ranges.len()).sum();
+            if count > max {
+                verdict.detections.push(Detection::Emotes { count })
+            }
+        }
+        verdict
+    }
+
+    /// Check some text
+    pub fn check_text(&self, text: &str) -> Verdict {
+        let mut detections = Vec::new();
+
+        if self.links {
+            detections.extend(
+                find_links(text)
+                    .filter(|link| !self.allowed_domains.iter().any(|d| link.is_on(d)))
+                    .map(|link| Detection::Link {
+                        host: link.host.to_ascii_lowercase(),
+                    }),
+            );
+        }
+
+        if let Some((min_letters, max)) = self.caps {
+            match caps_percent(text, min_letters) {
+                Some(percent) if percent >= max => detections.push(Detection::Caps { percent }),
+                _ => {}
+            }
+        }
+
+        if let Some(max) = self.repeated {
+            match longest_run(text) {
+                Some((ch, count)) if count > max => {
+                    detections.push(Detection::Repeated { ch, count })
+                }
+                _ => {}
+            }
+        }
+
+        if let Some(max) = self.zalgo {
+            let marks = combining_marks(text);
+            if marks > max {
+                detections.push(Detection::Zalgo { marks })
+            }
+        }
+
+        if let Some(max) = self.symbols {
+            let count = drawing_symbols(text);
+            if count > max {
+                detections.push(Detection::Symbols { count })
+            }
+        }
+
+        Verdict { detections }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FromIrcMessage as _;
+
+    #[test]
+    fn detections() {
+        let filter = SpamFilter::default();
+        assert!(filter.check_text("hello world, how's it going?").is_clean());
+        assert!(SpamFilter::new()
+            .check_text("WWW.SPAM.COM !!!!!!!!!!!!!!!!!!!!!!!!!!!!")
+            .is_clean());
+
+        let verdict = filter.check_text("ZALGO IS COMING FOR YOU z\u{0301}\u{0302}\u{0303}\u{0304}\u{0305}\u{0306}\u{0307}\u{0308}\u{0309}\u{030A}\u{030B}");
+        assert_eq!(
+            verdict.into_detections(),
+            vec![
+                Detection::Caps { percent: 95 },
+                Detection::Zalgo { marks: 11 }
+            ]
+        );
+
+        let art = "⣿".repeat(30);
+        assert_eq!(
+            filter.check_text(&art).detections(),
+            &[
+                Detection::Repeated {
+                    ch: '⣿', count: 30
+                },
+                Detection::Symbols { count: 30 }
+            ]
+        );
+
+        let input = "@emotes=25:0-4,6-10,12-16/1902:18-22 :museun!museun@museun PRIVMSG #museun :Kappa Kappa Kappa Keepo\r\n";
+        let msg = Privmsg::from_irc(crate::irc::parse_one(input).unwrap().1).unwrap();
+        assert!(filter.check(&msg).is_clean());
+        assert_eq!(
+            SpamFilter::new().emotes(3).check(&msg).detections(),
+            &[Detection::Emotes { count: 4 }]
+        );
+    }
+}
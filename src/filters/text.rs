@@ -0,0 +1,70 @@
+/// How much of the text is in capitals, as a percentage of its letters. This is `None` if it has fewer than `min_letters` letters
+pub fn caps_percent(text: &str, min_letters: usize) -> Option<u8> {
+    let (letters, upper) = text
+        .chars()
+        .filter(|c| c.is_alphabetic())
+        .fold((0_usize, 0_usize), |(letters, upper), c| {
+            (letters + 1, upper + c.is_uppercase() as usize)
+        });
+    if letters == 0 || letters < min_letters {
+        return None;
+    }
+    Some((upper * 100 / letters) as u8)
+}
+
+/// The longest run of the same character (e.g. `!!!!!!!`), ignoring whitespace
+pub fn longest_run(text: &str) -> Option<(char, usize)> {
+    let mut longest: Option<(char, usize)> = None;
+    let mut current: Option<(char, usize)> = None;
+    for c in text.chars().filter(|c| !c.is_whitespace()) {
+        current = match current {
+            Some((prev, count)) if prev == c => Some((c, count + 1)),
+            _ => Some((c, 1)),
+        };
+        if current.map(|(_, n)| n) > longest.map(|(_, n)| n) {
+            longest = current;
+        }
+    }
+    longest
+}
+
+/// How many combining marks there are. Stacking these on letters is 'zalgo' text
+pub fn combining_marks(text: &str) -> usize {
+    text.chars().filter(|&c| is_combining_mark(c)).count()
+}
+
+/// How many drawing characters (braille, box drawing, blocks and shapes) there are. These are used for ascii art
+pub fn drawing_symbols(text: &str) -> usize {
+    text.chars()
+        .filter(|c| matches!(*c as u32, 0x2500..=0x25FF | 0x2800..=0x28FF))
+        .count()
+}
+
+fn is_combining_mark(c: char) -> bool {
+    matches!(
+        c as u32,
+        0x0300..=0x036F | 0x0483..=0x0489 | 0x1AB0..=0x1AFF | 0x1DC0..=0x1DFF | 0x20D0..=0x20FF | 0xFE20..=0xFE2F
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detectors() {
+        assert_eq!(caps_percent("HELLO world", 5), Some(50));
+        assert_eq!(caps_percent("HI", 5), None);
+        assert_eq!(caps_percent("1234 !!!", 0), None);
+        assert_eq!(caps_percent("ÀÉÎ", 0), Some(100));
+
+        assert_eq!(longest_run("woooo ! ! !"), Some(('o', 4)));
+        assert_eq!(longest_run("! ! ! ! !"), Some(('!', 5)));
+        assert_eq!(longest_run(""), None);
+
+        assert_eq!(combining_marks("café"), 0);
+        assert_eq!(combining_marks("h\u{0301}\u{0302}\u{0303}i\u{20D0}"), 4);
+
+        assert_eq!(drawing_symbols("⣿⣿⣿ ░▒▓ █ hi"), 7);
+    }
+}
@@ -0,0 +1,58 @@
+/// An error returned by the [Helix client](super::Client)
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// An I/O error occured
+    Io(std::io::Error),
+    /// Twitch sent a response we could not deserialize
+    Json(serde_json::Error),
+    /// Twitch rejected the request
+    Http {
+        /// The HTTP status code
+        status: u16,
+        /// The error message Twitch gave
+        message: String,
+    },
+    /// An argument was outside of the range Twitch accepts
+    InvalidArgument {
+        /// The name of the argument
+        name: &'static str,
+        /// Why it is invalid
+        reason: &'static str,
+    },
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "io error: {}", err),
+            Self::Json(err) => write!(f, "could not deserialize response: {}", err),
+            Self::Http { status, message } => write!(f, "http error ({}): {}", status, message),
+            Self::InvalidArgument { name, reason } => {
+                write!(f, "invalid argument '{}': {}", name, reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            Self::Json(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        Self::Json(err)
+    }
+}
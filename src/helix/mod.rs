@@ -0,0 +1,852 @@
+//! A minimal client for the chat-related [Helix](https://dev.twitch.tv/docs/api/reference) endpoints.
+//!
+//! Twitch removed most of the IRC slash commands (e.g. `/ban`, `/announce`), so these are the way to moderate a chat now.
+//!
+//! To use the [Client]:
+//! 1. choose a ***TLS*** [Connector](crate::connector::Connector) pointed at [HELIX_ADDRESS] (with the domain [HELIX_HOST]).
+//! 1. create a [Client] with your ***client id*** and ***OAuth token***.
+//!     * the token must have the scopes required by the endpoints you use (e.g. `moderator:manage:banned_users`).
+//! 1. call the endpoints you need.
+//!
+//! Each request uses a new connection from the connector.
+//!
+//! For moderating one channel, [Client::mod_tools()] binds the broadcaster and moderator ids (see [ModTools]).
+//!
+//! # Example
+//! ```no_run
+//! # #[cfg(all(feature = "async-io", feature = "async-tls"))]
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use twitchchat::connector::async_io::ConnectorTls;
+//! use twitchchat::helix::{self, Client};
+//!
+//! futures_lite::future::block_on(async move {
+//!     let connector = ConnectorTls::custom(helix::HELIX_ADDRESS, helix::HELIX_HOST)?;
+//!     let mut client = Client::new(connector, "client-id", "oauth-token");
+//!
+//!     let msg = client.send_chat_message("1234", "5678", "hello world", None).await?;
+//!     assert!(msg.is_sent);
+//!
+//!     let timeout = std::time::Duration::from_secs(60);
+//!     client.timeout_user("1234", "5678", "9001", timeout, "too loud").await?;
+//!     Ok(())
+//! })
+//! # }
+//! # #[cfg(not(all(feature = "async-io", feature = "async-tls")))]
+//! # fn main() {}
+//! ```
+use crate::{
+    connector::Connector,
+    http::{self, Request, Response},
+};
+
+use serde::{de::DeserializeOwned, Deserialize};
+use std::time::Duration;
+
+mod error;
+pub use error::Error;
+
+mod mod_tools;
+pub use mod_tools::ModTools;
+
+mod types;
+pub use types::{
+    AnnouncementColor, Ban, ChatSettings, Chatter, Chatters, ChattersByRole, DropReason,
+    SentMessage, StartedRaid,
+};
+
+#[doc(inline)]
+pub use crate::http::{HELIX_ADDRESS, HELIX_HOST};
+
+// the longest timeout Twitch allows, 2 weeks
+const MAX_TIMEOUT: Duration = Duration::from_secs(1_209_600);
+
+/// A client for the chat-related Helix endpoints
+#[derive(Clone)]
+pub struct Client<C> {
+    connector: C,
+    client_id: String,
+    token: String,
+}
+
+impl<C> std::fmt::Debug for Client<C> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Client")
+            .field("client_id", &self.client_id)
+            .finish()
+    }
+}
+
+impl<C> Client<C>
+where
+    C: Connector,
+{
+    /// Create a new client with this connector, client id and OAuth token.
+    ///
+    /// The token can be in the form used by [UserConfig](crate::UserConfig) (with an `oauth:` prefix), or without it.
+    pub fn new(connector: C, client_id: impl Into<String>, token: impl Into<String>) -> Self {
+        let mut this = Self {
+            connector,
+            client_id: client_id.into(),
+            token: String::new(),
+        };
+        this.set_token(token);
+        this
+    }
+
+    /// Replace the OAuth token (e.g. after it was refreshed)
+    pub fn set_token(&mut self, token: impl Into<String>) {
+        let token = token.into();
+        self.token = match token.strip_prefix("oauth:") {
+            Some(token) => token.to_string(),
+            None => token,
+        };
+    }
+
+    /// Send a chat message to the broadcaster's chat, as `sender_id`.
+    ///
+    /// If `reply_parent_message_id` is provided, this message will be a reply to that message.
+    ///
+    /// Requires the `user:write:chat` scope.
+    pub async fn send_chat_message(
+        &mut self,
+        broadcaster_id: &str,
+        sender_id: &str,
+        message: &str,
+        reply_parent_message_id: Option<&str>,
+    ) -> Result<SentMessage, Error> {
+        let mut body = serde_json::json!({
+            "broadcaster_id": broadcaster_id,
+            "sender_id": sender_id,
+            "message": message,
+        });
+        if let Some(id) = reply_parent_message_id {
+            body["reply_parent_message_id"] = id.into();
+        }
+
+        let resp = self
+            .request("POST", "chat/messages", &[], Some(&body))
+            .await?;
+        first(&resp)
+    }
+
+    /// Send an announcement to the broadcaster's chat.
+    ///
+    /// Requires the `moderator:manage:announcements` scope.
+    pub async fn send_announcement(
+        &mut self,
+        broadcaster_id: &str,
+        moderator_id: &str,
+        message: &str,
+        color: AnnouncementColor,
+    ) -> Result<(), Error> {
+        let query = [
+            ("broadcaster_id", broadcaster_id),
+            ("moderator_id", moderator_id),
+        ];
+        let body = serde_json::json!({
+            "message": message,
+            "color": color.as_str(),
+        });
+
+        self.request("POST", "chat/announcements", &query, Some(&body))
+            .await
+            .map(drop)
+    }
+
+    /// Send a whisper from `from_user_id` to `to_user_id`.
+    ///
+    /// The sending user must have a verified phone number. Twitch limits how many whispers (and to how many different users) can be sent.
+    ///
+    /// Requires the `user:manage:whispers` scope.
+    pub async fn send_whisper(
+        &mut self,
+        from_user_id: &str,
+        to_user_id: &str,
+        message: &str,
+    ) -> Result<(), Error> {
+        let query = [("from_user_id", from_user_id), ("to_user_id", to_user_id)];
+        let body = serde_json::json!({ "message": message });
+
+        self.request("POST", "whispers", &query, Some(&body))
+            .await
+            .map(drop)
+    }
+
+    /// Permanently ban `user_id` from the broadcaster's chat.
+    ///
+    /// Requires the `moderator:manage:banned_users` scope.
+    pub async fn ban_user(
+        &mut self,
+        broadcaster_id: &str,
+        moderator_id: &str,
+        user_id: &str,
+        reason: &str,
+    ) -> Result<Ban, Error> {
+        let data = serde_json::json!({
+            "user_id": user_id,
+            "reason": reason,
+        });
+        self.ban(broadcaster_id, moderator_id, data).await
+    }
+
+    /// Timeout `user_id` from the broadcaster's chat for `duration`.
+    ///
+    /// The duration must be between 1 second and 2 weeks.
+    ///
+    /// Requires the `moderator:manage:banned_users` scope.
+    pub async fn timeout_user(
+        &mut self,
+        broadcaster_id: &str,
+        moderator_id: &str,
+        user_id: &str,
+        duration: Duration,
+        reason: &str,
+    ) -> Result<Ban, Error> {
+        if duration.as_secs() == 0 || duration > MAX_TIMEOUT {
+            return Err(Error::InvalidArgument {
+                name: "duration",
+                reason: "must be between 1 second and 2 weeks",
+            });
+        }
+
+        let data = serde_json::json!({
+            "user_id": user_id,
+            "duration": duration.as_secs(),
+            "reason": reason,
+        });
+        self.ban(broadcaster_id, moderator_id, data).await
+    }
+
+    /// Remove a ban (or timeout) for `user_id` from the broadcaster's chat.
+    ///
+    /// Requires the `moderator:manage:banned_users` scope.
+    pub async fn unban_user(
+        &mut self,
+        broadcaster_id: &str,
+        moderator_id: &str,
+        user_id: &str,
+    ) -> Result<(), Error> {
+        let query = [
+            ("broadcaster_id", broadcaster_id),
+            ("moderator_id", moderator_id),
+            ("user_id", user_id),
+        ];
+        self.request("DELETE", "moderation/bans", &query, None)
+            .await
+            .map(drop)
+    }
+
+    /// Delete a message (by its `id` tag) from the broadcaster's chat.
+    ///
+    /// If `message_id` is `None`, ***all*** messages are deleted (like `/clear`).
+    ///
+    /// Requires the `moderator:manage:chat_messages` scope.
+    pub async fn delete_message(
+        &mut self,
+        broadcaster_id: &str,
+        moderator_id: &str,
+        message_id: Option<&str>,
+    ) -> Result<(), Error> {
+        let mut query = vec![
+            ("broadcaster_id", broadcaster_id),
+            ("moderator_id", moderator_id),
+        ];
+        query.extend(message_id.map(|id| ("message_id", id)));
+
+        self.request("DELETE", "moderation/chat", &query, None)
+            .await
+            .map(drop)
+    }
+
+    /// Update the broadcaster's chat settings, returning the new settings.
+    ///
+    /// Only the fields of `settings` which are `Some` are changed.
+    ///
+    /// Requires the `moderator:manage:chat_settings` scope.
+    pub async fn update_chat_settings(
+        &mut self,
+        broadcaster_id: &str,
+        moderator_id: &str,
+        settings: &ChatSettings,
+    ) -> Result<ChatSettings, Error> {
+        let query = [
+            ("broadcaster_id", broadcaster_id),
+            ("moderator_id", moderator_id),
+        ];
+        let body = serde_json::to_value(settings)?;
+
+        let resp = self
+            .request("PATCH", "chat/settings", &query, Some(&body))
+            .await?;
+        first(&resp)
+    }
+
+    /// Get a page of the users connected to the broadcaster's chat.
+    ///
+    /// `first` is the page size (at most 1000) and `after` is the [cursor](Chatters::cursor) from a previous page.
+    ///
+    /// Requires the `moderator:read:chatters` scope.
+    pub async fn get_chatters(
+        &mut self,
+        broadcaster_id: &str,
+        moderator_id: &str,
+        first: Option<usize>,
+        after: Option<&str>,
+    ) -> Result<Chatters, Error> {
+        let first = first.map(|n| n.min(1000).to_string());
+        let mut query = vec![
+            ("broadcaster_id", broadcaster_id),
+            ("moderator_id", moderator_id),
+        ];
+        query.extend(first.as_deref().map(|n| ("first", n)));
+        query.extend(after.map(|cursor| ("after", cursor)));
+
+        let resp = self.request("GET", "chat/chatters", &query, None).await?;
+        let Page {
+            data,
+            total,
+            pagination,
+        } = resp.json()?;
+
+        Ok(Chatters {
+            chatters: data,
+            total,
+            cursor: pagination.and_then(|p| p.cursor),
+        })
+    }
+
+    /// Get every user connected to the broadcaster's chat, split by their role.
+    ///
+    /// Above 1000 chatters, Twitch stops sending `JOIN`s and `PART`s, so this is the only reliable list of who is in a big chat.
+    /// This follows every page of the chatters, and then gets the channel's moderators and VIPs to sort them.
+    ///
+    /// Requires the `moderator:read:chatters`, `moderator:read:moderators` and `moderator:read:vips` scopes
+    /// (or, for the broadcaster, `moderation:read` and `channel:read:vips` instead of the last two).
+    pub async fn chatters(
+        &mut self,
+        broadcaster_id: &str,
+        moderator_id: &str,
+    ) -> Result<ChattersByRole, Error> {
+        let query = [
+            ("broadcaster_id", broadcaster_id),
+            ("moderator_id", moderator_id),
+        ];
+        let chatters = self.all_pages("chat/chatters", &query, 1000).await?;
+
+        let query = [("broadcaster_id", broadcaster_id)];
+        let moderators = self.all_pages("moderation/moderators", &query, 100).await?;
+        let vips = self.all_pages("channels/vips", &query, 100).await?;
+
+        let has = |list: &[Chatter], chatter: &Chatter| {
+            list.iter().any(|user| user.user_id == chatter.user_id)
+        };
+
+        let mut by_role = ChattersByRole::default();
+        for chatter in chatters {
+            if chatter.user_id == broadcaster_id {
+                by_role.broadcaster.replace(chatter);
+            } else if has(&moderators, &chatter) {
+                by_role.moderators.push(chatter);
+            } else if has(&vips, &chatter) {
+                by_role.vips.push(chatter);
+            } else {
+                by_role.viewers.push(chatter);
+            }
+        }
+        Ok(by_role)
+    }
+
+    /// Give `to_broadcaster_id` a shoutout in `from_broadcaster_id`'s chat.
+    ///
+    /// Twitch limits how often shoutouts can be sent (once every 2 minutes, and once an hour for the same broadcaster).
+    ///
+    /// Requires the `moderator:manage:shoutouts` scope.
+    pub async fn send_shoutout(
+        &mut self,
+        from_broadcaster_id: &str,
+        to_broadcaster_id: &str,
+        moderator_id: &str,
+    ) -> Result<(), Error> {
+        let query = [
+            ("from_broadcaster_id", from_broadcaster_id),
+            ("to_broadcaster_id", to_broadcaster_id),
+            ("moderator_id", moderator_id),
+        ];
+        self.request("POST", "chat/shoutouts", &query, None)
+            .await
+            .map(drop)
+    }
+
+    /// Raid `to_broadcaster_id` from `from_broadcaster_id`'s channel.
+    ///
+    /// The raid happens after a 90 second countdown, or when the broadcaster clicks `Raid Now`. Use [cancel_raid](Self::cancel_raid()) to cancel it.
+    ///
+    /// Requires the `channel:manage:raids` scope.
+    pub async fn start_raid(
+        &mut self,
+        from_broadcaster_id: &str,
+        to_broadcaster_id: &str,
+    ) -> Result<StartedRaid, Error> {
+        let query = [
+            ("from_broadcaster_id", from_broadcaster_id),
+            ("to_broadcaster_id", to_broadcaster_id),
+        ];
+        let resp = self.request("POST", "raids", &query, None).await?;
+        first(&resp)
+    }
+
+    /// Cancel a pending raid from the broadcaster's channel.
+    ///
+    /// Requires the `channel:manage:raids` scope.
+    pub async fn cancel_raid(&mut self, broadcaster_id: &str) -> Result<(), Error> {
+        let query = [("broadcaster_id", broadcaster_id)];
+        self.request("DELETE", "raids", &query, None)
+            .await
+            .map(drop)
+    }
+
+    // the users on every page of this endpoint, following the cursors
+    async fn all_pages(
+        &mut self,
+        endpoint: &str,
+        query: &[(&str, &str)],
+        page_size: usize,
+    ) -> Result<Vec<Chatter>, Error> {
+        let page_size = page_size.to_string();
+        let mut users = vec![];
+        let mut cursor = None;
+
+        loop {
+            let mut query = query.to_vec();
+            query.push(("first", &page_size));
+            query.extend(cursor.as_deref().map(|cursor| ("after", cursor)));
+
+            let resp = self.request("GET", endpoint, &query, None).await?;
+            let Page {
+                data, pagination, ..
+            } = resp.json()?;
+
+            let empty = data.is_empty();
+            users.extend(data);
+            cursor = pagination.and_then(|p| p.cursor).filter(|s| !s.is_empty());
+            if cursor.is_none() || empty {
+                break Ok(users);
+            }
+        }
+    }
+
+    async fn ban(
+        &mut self,
+        broadcaster_id: &str,
+        moderator_id: &str,
+        data: serde_json::Value,
+    ) -> Result<Ban, Error> {
+        let query = [
+            ("broadcaster_id", broadcaster_id),
+            ("moderator_id", moderator_id),
+        ];
+        let body = serde_json::json!({ "data": data });
+
+        let resp = self
+            .request("POST", "moderation/bans", &query, Some(&body))
+            .await?;
+        first(&resp)
+    }
+
+    async fn request(
+        &mut self,
+        method: &'static str,
+        endpoint: &str,
+        query: &[(&str, &str)],
+        body: Option<&serde_json::Value>,
+    ) -> Result<Response, Error> {
+        #[derive(Deserialize)]
+        struct ErrorBody {
+            message: String,
+        }
+
+        let mut path = format!("/helix/{}", endpoint);
+        if !query.is_empty() {
+            path.push('?');
+            path.push_str(&http::form_encode(query));
+        }
+
+        let mut req = Request::new(method, HELIX_HOST, path)
+            .header("Client-Id", self.client_id.clone())
+            .header("Authorization", format!("Bearer {}", self.token));
+        if let Some(body) = body {
+            req = req.json(body)?;
+        }
+
+        let resp = http::send(&mut self.connector, req).await?;
+        if resp.is_success() {
+            return Ok(resp);
+        }
+
+        let message = match resp.json::<ErrorBody>() {
+            Ok(ErrorBody { message }) => message,
+            Err(..) => String::from_utf8_lossy(&resp.body).into_owned(),
+        };
+        Err(Error::Http {
+            status: resp.status,
+            message,
+        })
+    }
+}
+
+// a page of users, e.g. from `chat/chatters`
+#[derive(Deserialize)]
+struct Page {
+    data: Vec<Chatter>,
+    #[serde(default)]
+    total: u64,
+    pagination: Option<Pagination>,
+}
+
+#[derive(Deserialize)]
+struct Pagination {
+    #[serde(default)]
+    cursor: Option<String>,
+}
+
+// Helix wraps (almost) everything in a `data` array
+fn first<T: DeserializeOwned>(resp: &Response) -> Result<T, Error> {
+    #[derive(Deserialize)]
+    struct Data<T> {
+        data: Vec<T>,
+    }
+
+    let Data { data } = resp.json()?;
+    data.into_iter().next().ok_or_else(|| Error::Http {
+        status: resp.status,
+        message: String::from("response contained no data"),
+    })
+}
+
+#[cfg(all(test, feature = "testing"))]
+mod tests {
+    use super::*;
+    use crate::test::TestConnector;
+    use futures_lite::future::block_on;
+
+    fn respond(connector: &TestConnector, status: &str, body: &str) {
+        let resp = format!(
+            "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        );
+        block_on(connector.conn.write_data(resp));
+    }
+
+    fn sent(connector: &TestConnector) -> Vec<String> {
+        block_on(connector.conn.read_all_lines()).unwrap()
+    }
+
+    #[test]
+    fn send_chat_message() {
+        let connector = TestConnector::default();
+        respond(
+            &connector,
+            "200 OK",
+            r#"{"data":[{"message_id":"abc-123","is_sent":true}]}"#,
+        );
+
+        let mut client = Client::new(connector.clone(), "my-client", "oauth:my-token");
+        let msg =
+            block_on(client.send_chat_message("1234", "5678", "hello", Some("parent"))).unwrap();
+        assert_eq!(msg.message_id, "abc-123");
+        assert!(msg.is_sent);
+        assert!(msg.drop_reason.is_none());
+
+        let lines = sent(&connector);
+        assert_eq!(lines[0], "POST /helix/chat/messages HTTP/1.1\r\n");
+        assert!(lines.contains(&"Authorization: Bearer my-token\r\n".to_string()));
+        assert!(lines.contains(&"Client-Id: my-client\r\n".to_string()));
+
+        let body: serde_json::Value = serde_json::from_str(lines.last().unwrap()).unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!({
+                "broadcaster_id": "1234",
+                "sender_id": "5678",
+                "message": "hello",
+                "reply_parent_message_id": "parent",
+            })
+        );
+    }
+
+    #[test]
+    fn send_whisper() {
+        let connector = TestConnector::default();
+        block_on(connector.conn.write_data("HTTP/1.1 204 No Content\r\n\r\n"));
+
+        let mut client = Client::new(connector.clone(), "my-client", "my-token");
+        block_on(client.send_whisper("1234", "5678", "hello")).unwrap();
+
+        let lines = sent(&connector);
+        assert_eq!(
+            lines[0],
+            "POST /helix/whispers?from_user_id=1234&to_user_id=5678 HTTP/1.1\r\n"
+        );
+        let body: serde_json::Value = serde_json::from_str(lines.last().unwrap()).unwrap();
+        assert_eq!(body, serde_json::json!({ "message": "hello" }));
+    }
+
+    #[test]
+    fn timeout_user() {
+        let connector = TestConnector::default();
+        respond(
+            &connector,
+            "200 OK",
+            r#"{"data":[{
+                "broadcaster_id":"1234",
+                "moderator_id":"5678",
+                "user_id":"9001",
+                "created_at":"2021-09-28T19:22:31Z",
+                "end_time":"2021-09-28T19:23:31Z"
+            }]}"#,
+        );
+
+        let mut client = Client::new(connector.clone(), "my-client", "my-token");
+        let ban = block_on(client.timeout_user(
+            "1234",
+            "5678",
+            "9001",
+            Duration::from_secs(60),
+            "too loud",
+        ))
+        .unwrap();
+        assert_eq!(ban.user_id, "9001");
+        assert!(ban.end_time.is_some());
+
+        let lines = sent(&connector);
+        assert_eq!(
+            lines[0],
+            "POST /helix/moderation/bans?broadcaster_id=1234&moderator_id=5678 HTTP/1.1\r\n"
+        );
+        let body: serde_json::Value = serde_json::from_str(lines.last().unwrap()).unwrap();
+        assert_eq!(body["data"]["duration"], 60);
+
+        let err =
+            block_on(client.timeout_user("1234", "5678", "9001", MAX_TIMEOUT * 2, "")).unwrap_err();
+        assert!(matches!(
+            err,
+            Error::InvalidArgument {
+                name: "duration",
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn delete_all_messages() {
+        let connector = TestConnector::default();
+        block_on(connector.conn.write_data("HTTP/1.1 204 No Content\r\n\r\n"));
+
+        let mut client = Client::new(connector.clone(), "my-client", "my-token");
+        block_on(client.delete_message("1234", "5678", None)).unwrap();
+
+        let lines = sent(&connector);
+        assert_eq!(
+            lines[0],
+            "DELETE /helix/moderation/chat?broadcaster_id=1234&moderator_id=5678 HTTP/1.1\r\n"
+        );
+    }
+
+    #[test]
+    fn update_chat_settings() {
+        let connector = TestConnector::default();
+        respond(
+            &connector,
+            "200 OK",
+            r#"{"data":[{
+                "broadcaster_id":"1234",
+                "moderator_id":"5678",
+                "emote_mode":false,
+                "follower_mode":false,
+                "follower_mode_duration":null,
+                "slow_mode":true,
+                "slow_mode_wait_time":30,
+                "subscriber_mode":false,
+                "unique_chat_mode":false
+            }]}"#,
+        );
+
+        let mut client = Client::new(connector.clone(), "my-client", "my-token");
+        let settings = ChatSettings {
+            slow_mode: Some(true),
+            slow_mode_wait_time: Some(30),
+            ..ChatSettings::default()
+        };
+        let new = block_on(client.update_chat_settings("1234", "5678", &settings)).unwrap();
+        assert_eq!(new.slow_mode_wait_time, Some(30));
+        assert_eq!(new.emote_mode, Some(false));
+        assert_eq!(new.follower_mode_duration, None);
+
+        let lines = sent(&connector);
+        let body: serde_json::Value = serde_json::from_str(lines.last().unwrap()).unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!({ "slow_mode": true, "slow_mode_wait_time": 30 })
+        );
+    }
+
+    #[test]
+    fn get_chatters() {
+        let connector = TestConnector::default();
+        respond(
+            &connector,
+            "200 OK",
+            r#"{
+                "data":[{"user_id":"128393656","user_login":"smittysmithers","user_name":"smittysmithers"}],
+                "pagination":{"cursor":"eyJiIjpudWxsLCJhIjp7Ik9mZnNldCI6NX19"},
+                "total":8
+            }"#,
+        );
+
+        let mut client = Client::new(connector.clone(), "my-client", "my-token");
+        let page = block_on(client.get_chatters("1234", "5678", Some(5000), None)).unwrap();
+        assert_eq!(page.total, 8);
+        assert_eq!(page.chatters[0].user_login, "smittysmithers");
+        assert_eq!(
+            page.cursor.as_deref(),
+            Some("eyJiIjpudWxsLCJhIjp7Ik9mZnNldCI6NX19")
+        );
+
+        let lines = sent(&connector);
+        assert_eq!(
+            lines[0],
+            "GET /helix/chat/chatters?broadcaster_id=1234&moderator_id=5678&first=1000 HTTP/1.1\r\n"
+        );
+    }
+
+    #[test]
+    fn chatters_by_role() {
+        use crate::test::MockServer;
+        use futures_lite::future::zip;
+
+        let user = |id: &str| {
+            format!(
+                r#"{{"user_id":"{0}","user_login":"user{0}","user_name":"User{0}"}}"#,
+                id
+            )
+        };
+        let page = |ids: &[&str], cursor: Option<&str>| {
+            let data = ids.iter().map(|id| user(id)).collect::<Vec<_>>().join(",");
+            let pagination = cursor
+                .map(|c| format!(r#"{{"cursor":"{}"}}"#, c))
+                .unwrap_or_else(|| "{}".to_string());
+            format!(
+                r#"{{"data":[{}],"pagination":{},"total":5}}"#,
+                data, pagination
+            )
+        };
+
+        let responses = vec![
+            page(&["1234", "1", "2"], Some("next")),
+            page(&["3", "4"], None),
+            page(&["1", "9"], None),
+            page(&["3"], None),
+        ];
+
+        let server = MockServer::new();
+        let serve = async {
+            let mut requests = vec![];
+            for body in &responses {
+                let mut conn = server.accept_raw().await.unwrap();
+                requests.push(conn.read_line().await.unwrap());
+                conn.send(format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+                    body.len(),
+                    body
+                ))
+                .unwrap();
+                conn.close();
+            }
+            requests
+        };
+
+        let mut client = Client::new(server.clone(), "my-client", "my-token");
+        let (chatters, requests) = block_on(zip(
+            async { client.mod_tools("1234", "5678").chatters().await },
+            serve,
+        ));
+        let chatters = chatters.unwrap();
+
+        assert_eq!(chatters.broadcaster.unwrap().user_login, "user1234");
+        assert_eq!(chatters.moderators[0].user_id, "1");
+        assert_eq!(chatters.vips[0].user_id, "3");
+        let viewers = chatters
+            .viewers
+            .iter()
+            .map(|c| &*c.user_id)
+            .collect::<Vec<_>>();
+        assert_eq!(viewers, vec!["2", "4"]);
+
+        assert_eq!(
+            requests,
+            vec![
+                "GET /helix/chat/chatters?broadcaster_id=1234&moderator_id=5678&first=1000 HTTP/1.1\r\n",
+                "GET /helix/chat/chatters?broadcaster_id=1234&moderator_id=5678&first=1000&after=next HTTP/1.1\r\n",
+                "GET /helix/moderation/moderators?broadcaster_id=1234&first=100 HTTP/1.1\r\n",
+                "GET /helix/channels/vips?broadcaster_id=1234&first=100 HTTP/1.1\r\n",
+            ]
+        );
+    }
+
+    #[test]
+    fn raids_and_shoutouts() {
+        let connector = TestConnector::default();
+        let mut client = Client::new(connector.clone(), "my-client", "my-token");
+
+        respond(
+            &connector,
+            "200 OK",
+            r#"{"data":[{"created_at":"2022-02-18T07:20:50.52Z","is_mature":false}]}"#,
+        );
+        let raid = block_on(client.start_raid("1234", "5678")).unwrap();
+        assert!(!raid.is_mature);
+        assert_eq!(
+            sent(&connector)[0],
+            "POST /helix/raids?from_broadcaster_id=1234&to_broadcaster_id=5678 HTTP/1.1\r\n"
+        );
+
+        block_on(connector.conn.write_data("HTTP/1.1 204 No Content\r\n\r\n"));
+        block_on(client.cancel_raid("1234")).unwrap();
+        assert_eq!(
+            sent(&connector)[0],
+            "DELETE /helix/raids?broadcaster_id=1234 HTTP/1.1\r\n"
+        );
+
+        block_on(connector.conn.write_data("HTTP/1.1 204 No Content\r\n\r\n"));
+        block_on(client.send_shoutout("1234", "5678", "1234")).unwrap();
+        assert_eq!(
+            sent(&connector)[0],
+            "POST /helix/chat/shoutouts?from_broadcaster_id=1234&to_broadcaster_id=5678&moderator_id=1234 HTTP/1.1\r\n"
+        );
+    }
+
+    #[test]
+    fn http_error() {
+        let connector = TestConnector::default();
+        respond(
+            &connector,
+            "401 Unauthorized",
+            r#"{"error":"Unauthorized","status":401,"message":"Missing scope: moderator:manage:announcements"}"#,
+        );
+
+        let mut client = Client::new(connector, "my-client", "my-token");
+        let err =
+            block_on(client.send_announcement("1234", "5678", "hello", AnnouncementColor::Purple))
+                .unwrap_err();
+
+        match err {
+            Error::Http { status, message } => {
+                assert_eq!(status, 401);
+                assert_eq!(message, "Missing scope: moderator:manage:announcements");
+            }
+            err => panic!("unexpected error: {}", err),
+        }
+    }
+}
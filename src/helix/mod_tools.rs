@@ -0,0 +1,310 @@
+use super::{Ban, ChatSettings, ChattersByRole, Client, Error};
+use crate::connector::Connector;
+
+use std::time::Duration;
+
+// the range of slow mode wait times Twitch allows, in seconds
+const SLOW_MODE_RANGE: std::ops::RangeInclusive<u64> = 3..=120;
+
+// the longest follow duration Twitch allows, 3 months (in minutes)
+const MAX_FOLLOWERS_DURATION: u64 = 129_600;
+
+/// The moderation actions for one channel, as one moderator.
+///
+/// These replace the removed IRC slash commands (e.g. `/timeout`, `/slow`). Get this from [Client::mod_tools()].
+///
+/// Every action returns the same [Error] type, and Twitch's rejections are [Error::Http].
+///
+/// # Example
+/// ```no_run
+/// # use twitchchat::helix::{Client, Error};
+/// # async fn demo<C: twitchchat::connector::Connector>(client: &mut Client<C>) -> Result<(), Error> {
+/// let mut tools = client.mod_tools("1234", "5678");
+/// tools.timeout("9001", std::time::Duration::from_secs(60), "too loud").await?;
+/// tools.slow(Some(std::time::Duration::from_secs(30))).await?;
+/// tools.emote_only(false).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct ModTools<'a, C> {
+    client: &'a mut Client<C>,
+    broadcaster_id: &'a str,
+    moderator_id: &'a str,
+}
+
+impl<'a, C> std::fmt::Debug for ModTools<'a, C> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ModTools")
+            .field("broadcaster_id", &self.broadcaster_id)
+            .field("moderator_id", &self.moderator_id)
+            .finish()
+    }
+}
+
+impl<C> Client<C>
+where
+    C: Connector,
+{
+    /// Moderate the broadcaster's chat as `moderator_id` (which can be the broadcaster).
+    pub fn mod_tools<'a>(
+        &'a mut self,
+        broadcaster_id: &'a str,
+        moderator_id: &'a str,
+    ) -> ModTools<'a, C> {
+        ModTools {
+            client: self,
+            broadcaster_id,
+            moderator_id,
+        }
+    }
+}
+
+impl<'a, C> ModTools<'a, C>
+where
+    C: Connector,
+{
+    /// Timeout `user_id` for `duration` (between 1 second and 2 weeks).
+    ///
+    /// Requires the `moderator:manage:banned_users` scope.
+    pub async fn timeout(
+        &mut self,
+        user_id: &str,
+        duration: Duration,
+        reason: &str,
+    ) -> Result<Ban, Error> {
+        self.client
+            .timeout_user(
+                self.broadcaster_id,
+                self.moderator_id,
+                user_id,
+                duration,
+                reason,
+            )
+            .await
+    }
+
+    /// Permanently ban `user_id`.
+    ///
+    /// Requires the `moderator:manage:banned_users` scope.
+    pub async fn ban(&mut self, user_id: &str, reason: &str) -> Result<Ban, Error> {
+        self.client
+            .ban_user(self.broadcaster_id, self.moderator_id, user_id, reason)
+            .await
+    }
+
+    /// Remove a ban (or timeout) for `user_id`.
+    ///
+    /// Requires the `moderator:manage:banned_users` scope.
+    pub async fn unban(&mut self, user_id: &str) -> Result<(), Error> {
+        self.client
+            .unban_user(self.broadcaster_id, self.moderator_id, user_id)
+            .await
+    }
+
+    /// Delete a message by its `id` tag (e.g. [Privmsg::tags()](crate::messages::Privmsg::tags())).
+    ///
+    /// Requires the `moderator:manage:chat_messages` scope.
+    pub async fn delete_message(&mut self, msg_id: &str) -> Result<(), Error> {
+        self.client
+            .delete_message(self.broadcaster_id, self.moderator_id, Some(msg_id))
+            .await
+    }
+
+    /// Delete ***all*** messages (like `/clear`).
+    ///
+    /// Requires the `moderator:manage:chat_messages` scope.
+    pub async fn clear_chat(&mut self) -> Result<(), Error> {
+        self.client
+            .delete_message(self.broadcaster_id, self.moderator_id, None)
+            .await
+    }
+
+    /// Enable slow mode with this wait time (between 3 seconds and 2 minutes), or disable it with `None`.
+    ///
+    /// Requires the `moderator:manage:chat_settings` scope.
+    pub async fn slow(&mut self, duration: Option<Duration>) -> Result<ChatSettings, Error> {
+        let settings = match duration.map(|d| d.as_secs()) {
+            Some(secs) if !SLOW_MODE_RANGE.contains(&secs) => {
+                return Err(Error::InvalidArgument {
+                    name: "duration",
+                    reason: "must be between 3 seconds and 2 minutes",
+                })
+            }
+            Some(secs) => ChatSettings {
+                slow_mode: Some(true),
+                slow_mode_wait_time: Some(secs),
+                ..ChatSettings::default()
+            },
+            None => ChatSettings {
+                slow_mode: Some(false),
+                ..ChatSettings::default()
+            },
+        };
+        self.update(&settings).await
+    }
+
+    /// Enable (or disable) emote-only mode.
+    ///
+    /// Requires the `moderator:manage:chat_settings` scope.
+    pub async fn emote_only(&mut self, enabled: bool) -> Result<ChatSettings, Error> {
+        let settings = ChatSettings {
+            emote_mode: Some(enabled),
+            ..ChatSettings::default()
+        };
+        self.update(&settings).await
+    }
+
+    /// Enable followers-only mode, requiring chatters to have followed for this long (rounded down to minutes, up to 3 months), or disable it with `None`.
+    ///
+    /// Requires the `moderator:manage:chat_settings` scope.
+    pub async fn followers_only(
+        &mut self,
+        duration: Option<Duration>,
+    ) -> Result<ChatSettings, Error> {
+        let settings = match duration.map(|d| d.as_secs() / 60) {
+            Some(minutes) if minutes > MAX_FOLLOWERS_DURATION => {
+                return Err(Error::InvalidArgument {
+                    name: "duration",
+                    reason: "must be at most 3 months",
+                })
+            }
+            Some(minutes) => ChatSettings {
+                follower_mode: Some(true),
+                follower_mode_duration: Some(minutes),
+                ..ChatSettings::default()
+            },
+            None => ChatSettings {
+                follower_mode: Some(false),
+                ..ChatSettings::default()
+            },
+        };
+        self.update(&settings).await
+    }
+
+    /// Get every user connected to the chat, split by their role. See [Client::chatters()] for the scopes this requires.
+    pub async fn chatters(&mut self) -> Result<ChattersByRole, Error> {
+        self.client
+            .chatters(self.broadcaster_id, self.moderator_id)
+            .await
+    }
+
+    async fn update(&mut self, settings: &ChatSettings) -> Result<ChatSettings, Error> {
+        self.client
+            .update_chat_settings(self.broadcaster_id, self.moderator_id, settings)
+            .await
+    }
+}
+
+#[cfg(all(test, feature = "testing"))]
+mod tests {
+    use super::*;
+    use crate::test::TestConnector;
+    use futures_lite::future::block_on;
+
+    const SETTINGS: &str = r#"{"data":[{"broadcaster_id":"1234","moderator_id":"5678"}]}"#;
+
+    fn respond(connector: &TestConnector, body: Option<&str>) {
+        let resp = match body {
+            Some(body) => format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+                body.len(),
+                body
+            ),
+            None => "HTTP/1.1 204 No Content\r\n\r\n".to_string(),
+        };
+        block_on(connector.conn.write_data(resp));
+    }
+
+    fn sent(connector: &TestConnector) -> (String, Option<serde_json::Value>) {
+        let lines = block_on(connector.conn.read_all_lines()).unwrap();
+        let body = serde_json::from_str(lines.last().unwrap()).ok();
+        (lines[0].clone(), body)
+    }
+
+    #[test]
+    fn clear_and_delete() {
+        let connector = TestConnector::default();
+        let mut client = Client::new(connector.clone(), "my-client", "my-token");
+        let mut tools = client.mod_tools("1234", "5678");
+
+        respond(&connector, None);
+        block_on(tools.delete_message("abc-123")).unwrap();
+        assert_eq!(
+            sent(&connector).0,
+            "DELETE /helix/moderation/chat?broadcaster_id=1234&moderator_id=5678&message_id=abc-123 HTTP/1.1\r\n"
+        );
+
+        respond(&connector, None);
+        block_on(tools.clear_chat()).unwrap();
+        assert_eq!(
+            sent(&connector).0,
+            "DELETE /helix/moderation/chat?broadcaster_id=1234&moderator_id=5678 HTTP/1.1\r\n"
+        );
+    }
+
+    #[test]
+    fn chat_settings() {
+        let connector = TestConnector::default();
+        let mut client = Client::new(connector.clone(), "my-client", "my-token");
+        let mut tools = client.mod_tools("1234", "5678");
+
+        respond(&connector, Some(SETTINGS));
+        block_on(tools.slow(Some(Duration::from_secs(30)))).unwrap();
+        let (line, body) = sent(&connector);
+        assert_eq!(
+            line,
+            "PATCH /helix/chat/settings?broadcaster_id=1234&moderator_id=5678 HTTP/1.1\r\n"
+        );
+        assert_eq!(
+            body.unwrap(),
+            serde_json::json!({ "slow_mode": true, "slow_mode_wait_time": 30 })
+        );
+
+        respond(&connector, Some(SETTINGS));
+        block_on(tools.emote_only(true)).unwrap();
+        assert_eq!(
+            sent(&connector).1.unwrap(),
+            serde_json::json!({ "emote_mode": true })
+        );
+
+        respond(&connector, Some(SETTINGS));
+        block_on(tools.followers_only(Some(Duration::from_secs(600)))).unwrap();
+        assert_eq!(
+            sent(&connector).1.unwrap(),
+            serde_json::json!({ "follower_mode": true, "follower_mode_duration": 10 })
+        );
+
+        respond(&connector, Some(SETTINGS));
+        block_on(tools.followers_only(None)).unwrap();
+        assert_eq!(
+            sent(&connector).1.unwrap(),
+            serde_json::json!({ "follower_mode": false })
+        );
+    }
+
+    #[test]
+    fn invalid_durations() {
+        let connector = TestConnector::default();
+        let mut client = Client::new(connector, "my-client", "my-token");
+        let mut tools = client.mod_tools("1234", "5678");
+
+        let err = block_on(tools.slow(Some(Duration::from_secs(1)))).unwrap_err();
+        assert!(matches!(
+            err,
+            Error::InvalidArgument {
+                name: "duration",
+                ..
+            }
+        ));
+
+        let err = block_on(tools.followers_only(Some(Duration::from_secs(60 * 60 * 24 * 365))))
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::InvalidArgument {
+                name: "duration",
+                ..
+            }
+        ));
+    }
+}
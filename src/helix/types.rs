@@ -0,0 +1,151 @@
+use serde::{Deserialize, Serialize};
+
+pub use crate::twitch::AnnouncementColor;
+
+/// The result of sending a chat message
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[non_exhaustive]
+pub struct SentMessage {
+    /// The id of the message that was sent
+    pub message_id: String,
+    /// Whether the message passed all checks and was sent
+    pub is_sent: bool,
+    /// Why the message was dropped, if it wasn't sent
+    #[serde(default)]
+    pub drop_reason: Option<DropReason>,
+}
+
+/// Why a chat message was dropped
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[non_exhaustive]
+pub struct DropReason {
+    /// A code for the reason. e.g. `msg_duplicate`
+    pub code: String,
+    /// A message describing the reason
+    pub message: String,
+}
+
+/// A ban (or timeout) that was applied
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[non_exhaustive]
+pub struct Ban {
+    /// The broadcaster's user id
+    pub broadcaster_id: String,
+    /// The moderator's user id
+    pub moderator_id: String,
+    /// The user id of the banned user
+    pub user_id: String,
+    /// When the ban was created, as an RFC3339 timestamp
+    pub created_at: String,
+    /// When the timeout ends, as an RFC3339 timestamp. This is `None` for a permanent ban
+    #[serde(default)]
+    pub end_time: Option<String>,
+}
+
+/// A channel's chat settings.
+///
+/// When updating, only the fields which are `Some` are changed.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+#[non_exhaustive]
+pub struct ChatSettings {
+    /// Whether chatters can only use emotes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub emote_mode: Option<bool>,
+    /// Whether chatters must follow the broadcaster
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub follower_mode: Option<bool>,
+    /// How long (in minutes) chatters must have followed the broadcaster
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub follower_mode_duration: Option<u64>,
+    /// Whether non-moderator messages are delayed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub non_moderator_chat_delay: Option<bool>,
+    /// How long (in seconds) non-moderator messages are delayed. Twitch only allows 2, 4 or 6
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub non_moderator_chat_delay_duration: Option<u64>,
+    /// Whether chatters must wait between sending messages
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub slow_mode: Option<bool>,
+    /// How long (in seconds) chatters must wait between sending messages
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub slow_mode_wait_time: Option<u64>,
+    /// Whether chatters must be subscribed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subscriber_mode: Option<bool>,
+    /// Whether chatters must send unique messages
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unique_chat_mode: Option<bool>,
+}
+
+/// A user connected to a channel's chat
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[non_exhaustive]
+pub struct Chatter {
+    /// The user id
+    pub user_id: String,
+    /// The user's login
+    pub user_login: String,
+    /// The user's display name
+    pub user_name: String,
+}
+
+/// A page of chatters
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct Chatters {
+    /// The chatters on this page
+    pub chatters: Vec<Chatter>,
+    /// The total number of chatters
+    pub total: u64,
+    /// The cursor for the next page, if there is one
+    pub cursor: Option<String>,
+}
+
+/// Every user connected to a channel's chat, split by their role. From [Client::chatters()](super::Client::chatters())
+#[derive(Debug, Clone, Default, PartialEq)]
+#[non_exhaustive]
+pub struct ChattersByRole {
+    /// The broadcaster, if they're connected
+    pub broadcaster: Option<Chatter>,
+    /// The channel's moderators
+    pub moderators: Vec<Chatter>,
+    /// The channel's VIPs
+    pub vips: Vec<Chatter>,
+    /// Everyone else
+    pub viewers: Vec<Chatter>,
+}
+
+impl ChattersByRole {
+    /// How many users are connected
+    pub fn len(&self) -> usize {
+        self.broadcaster.iter().count()
+            + self.moderators.len()
+            + self.vips.len()
+            + self.viewers.len()
+    }
+
+    /// Whether no users are connected
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Every connected user, starting with the broadcaster, then the moderators, VIPs and viewers
+    pub fn iter(&self) -> impl Iterator<Item = &Chatter> + '_ {
+        self.broadcaster
+            .iter()
+            .chain(&self.moderators)
+            .chain(&self.vips)
+            .chain(&self.viewers)
+    }
+}
+
+/// A raid that was started, which happens after a countdown
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[non_exhaustive]
+pub struct StartedRaid {
+    /// When the raid was started, as an RFC3339 timestamp
+    pub created_at: String,
+    /// Whether the raided channel is for mature audiences
+    pub is_mature: bool,
+}
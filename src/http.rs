@@ -0,0 +1,277 @@
+//! A very small HTTP/1.1 client, used for the few Twitch HTTP endpoints this crate talks to.
+//!
+//! This uses a [Connector] for the transport, so it works with whichever runtime (and TLS backend) you've chosen.
+use crate::connector::Connector;
+
+use futures_lite::{AsyncReadExt, AsyncWriteExt};
+use std::io::{Error, ErrorKind, Result};
+
+/// The host for the Helix API
+#[cfg(any(feature = "eventsub", feature = "helix"))]
+pub const HELIX_HOST: &str = "api.twitch.tv";
+
+/// The socket address for the Helix API (this requires TLS)
+#[cfg(any(feature = "eventsub", feature = "helix"))]
+pub const HELIX_ADDRESS: &str = "api.twitch.tv:443";
+
+/// A request to send.
+#[derive(Debug)]
+pub(crate) struct Request<'a> {
+    method: &'static str,
+    host: &'a str,
+    path: String,
+    headers: Vec<(&'static str, String)>,
+    body: Option<Vec<u8>>,
+}
+
+impl<'a> Request<'a> {
+    pub(crate) fn new(method: &'static str, host: &'a str, path: impl Into<String>) -> Self {
+        Self {
+            method,
+            host,
+            path: path.into(),
+            headers: Vec::new(),
+            body: None,
+        }
+    }
+
+    pub(crate) fn header(mut self, key: &'static str, val: impl Into<String>) -> Self {
+        self.headers.push((key, val.into()));
+        self
+    }
+
+    #[cfg(any(feature = "eventsub", feature = "helix"))]
+    pub(crate) fn json<T: ::serde::Serialize + ?Sized>(self, body: &T) -> Result<Self> {
+        let body =
+            serde_json::to_vec(body).map_err(|err| Error::new(ErrorKind::InvalidData, err))?;
+        let mut this = self.header("Content-Type", "application/json");
+        this.body.replace(body);
+        Ok(this)
+    }
+
+    #[cfg(feature = "oauth")]
+    pub(crate) fn form(self, pairs: &[(&str, &str)]) -> Self {
+        let body = form_encode(pairs);
+        let mut this = self.header("Content-Type", "application/x-www-form-urlencoded");
+        this.body.replace(body.into_bytes());
+        this
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        use std::io::Write as _;
+
+        let mut buf = Vec::new();
+        // writing to a vec cannot fail
+        let _ = write!(
+            buf,
+            "{} {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\nUser-Agent: twitchchat/{}\r\n",
+            self.method,
+            self.path,
+            self.host,
+            env!("CARGO_PKG_VERSION")
+        );
+        for (key, val) in &self.headers {
+            let _ = write!(buf, "{}: {}\r\n", key, val);
+        }
+        let body = self.body.as_deref().unwrap_or_default();
+        if !body.is_empty() || self.method != "GET" {
+            let _ = write!(buf, "Content-Length: {}\r\n", body.len());
+        }
+        buf.extend_from_slice(b"\r\n");
+        buf.extend_from_slice(body);
+        buf
+    }
+}
+
+/// A response that was read.
+#[derive(Debug)]
+pub(crate) struct Response {
+    pub(crate) status: u16,
+    pub(crate) headers: Vec<(String, String)>,
+    pub(crate) body: Vec<u8>,
+}
+
+impl Response {
+    pub(crate) fn header(&self, key: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(key))
+            .map(|(_, v)| &**v)
+    }
+
+    pub(crate) fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    pub(crate) fn json<T: ::serde::de::DeserializeOwned>(&self) -> Result<T> {
+        serde_json::from_slice(&self.body).map_err(|err| Error::new(ErrorKind::InvalidData, err))
+    }
+
+    fn parse(data: &[u8]) -> Result<Self> {
+        let end = data
+            .windows(4)
+            .position(|w| w == b"\r\n\r\n")
+            .ok_or_else(|| invalid("incomplete http response"))?;
+
+        let head = std::str::from_utf8(&data[..end])
+            .map_err(|err| Error::new(ErrorKind::InvalidData, err))?;
+        let mut lines = head.split("\r\n");
+
+        let status = lines
+            .next()
+            .and_then(|line| line.split(' ').nth(1))
+            .and_then(|status| status.parse().ok())
+            .ok_or_else(|| invalid("invalid http status line"))?;
+
+        let headers = lines
+            .filter_map(|line| {
+                let mut parts = line.splitn(2, ':');
+                let key = parts.next()?.trim();
+                let val = parts.next()?.trim();
+                Some((key.to_string(), val.to_string()))
+            })
+            .collect();
+
+        let mut this = Self {
+            status,
+            headers,
+            body: Vec::new(),
+        };
+
+        let body = &data[end + 4..];
+        this.body = match this.header("Transfer-Encoding") {
+            Some(enc) if enc.eq_ignore_ascii_case("chunked") => decode_chunked(body)?,
+            _ => match this.header("Content-Length").and_then(|s| s.parse().ok()) {
+                Some(len) if len <= body.len() => body[..len].to_vec(),
+                _ => body.to_vec(),
+            },
+        };
+
+        Ok(this)
+    }
+}
+
+/// Send this request over a new connection from the `connector`, reading the entire response.
+pub(crate) async fn send<C>(connector: &mut C, req: Request<'_>) -> Result<Response>
+where
+    C: Connector,
+{
+    let mut stream = connector.connect().await?;
+    stream.write_all(&req.encode()).await?;
+    stream.flush().await?;
+
+    let mut data = Vec::new();
+    stream.read_to_end(&mut data).await?;
+    Response::parse(&data)
+}
+
+fn decode_chunked(mut data: &[u8]) -> Result<Vec<u8>> {
+    let mut out = Vec::new();
+    loop {
+        let end = data
+            .windows(2)
+            .position(|w| w == b"\r\n")
+            .ok_or_else(|| invalid("incomplete chunk"))?;
+
+        let size = std::str::from_utf8(&data[..end])
+            .ok()
+            .and_then(|s| s.split(';').next())
+            .and_then(|s| usize::from_str_radix(s.trim(), 16).ok())
+            .ok_or_else(|| invalid("invalid chunk size"))?;
+
+        data = &data[end + 2..];
+        if size == 0 {
+            return Ok(out);
+        }
+
+        if data.len() < size {
+            return Err(invalid("incomplete chunk"));
+        }
+        out.extend_from_slice(&data[..size]);
+        data = data.get(size + 2..).unwrap_or_default();
+    }
+}
+
+/// Percent-encode these pairs as an `application/x-www-form-urlencoded` string
+#[cfg(any(feature = "helix", feature = "oauth"))]
+pub(crate) fn form_encode(pairs: &[(&str, &str)]) -> String {
+    let mut out = String::new();
+    for (i, (key, val)) in pairs.iter().enumerate() {
+        if i > 0 {
+            out.push('&');
+        }
+        percent_encode(key, &mut out);
+        out.push('=');
+        percent_encode(val, &mut out);
+    }
+    out
+}
+
+#[cfg(any(feature = "helix", feature = "oauth"))]
+fn percent_encode(input: &str, out: &mut String) {
+    use std::fmt::Write as _;
+    for byte in input.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                out.push(byte as char)
+            }
+            _ => {
+                let _ = write!(out, "%{:02X}", byte);
+            }
+        }
+    }
+}
+
+fn invalid(msg: &'static str) -> Error {
+    Error::new(ErrorKind::InvalidData, msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(feature = "oauth")]
+    fn encode_request() {
+        let req = Request::new("POST", "api.twitch.tv", "/helix/foo")
+            .header("Client-Id", "1234")
+            .form(&[("a", "b c"), ("d", "e&f")]);
+
+        let data = String::from_utf8(req.encode()).unwrap();
+        assert_eq!(
+            data,
+            format!(
+                "POST /helix/foo HTTP/1.1\r\n\
+                Host: api.twitch.tv\r\n\
+                Connection: close\r\n\
+                User-Agent: twitchchat/{}\r\n\
+                Client-Id: 1234\r\n\
+                Content-Type: application/x-www-form-urlencoded\r\n\
+                Content-Length: 15\r\n\
+                \r\n\
+                a=b%20c&d=e%26f",
+                env!("CARGO_PKG_VERSION")
+            )
+        );
+    }
+
+    #[test]
+    fn parse_response() {
+        let data =
+            b"HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: 2\r\n\r\n{}";
+        let resp = Response::parse(data).unwrap();
+        assert_eq!(resp.status, 200);
+        assert!(resp.is_success());
+        assert_eq!(resp.header("content-type"), Some("application/json"));
+        assert_eq!(resp.body, b"{}");
+    }
+
+    #[test]
+    fn parse_chunked_response() {
+        let data = b"HTTP/1.1 401 Unauthorized\r\nTransfer-Encoding: chunked\r\n\r\n4\r\nWiki\r\n5\r\npedia\r\n0\r\n\r\n";
+        let resp = Response::parse(data).unwrap();
+        assert_eq!(resp.status, 401);
+        assert!(!resp.is_success());
+        assert_eq!(resp.body, b"Wikipedia");
+    }
+}
@@ -73,6 +73,8 @@ pub use error::MessageError;
 mod parser;
 pub use parser::IrcParserIter;
 
+mod scan;
+
 /// Parses a string and returns an iterator over the `IrcMessages` in it.
 ///
 /// This borrows from the input string.
@@ -86,9 +88,7 @@ pub fn parse(input: &str) -> IrcParserIter<'_> {
 pub fn parse_one(input: &str) -> Result<(usize, IrcMessage<'_>), MessageError> {
     const CRLF: &str = "\r\n";
 
-    let pos = input
-        .find(CRLF)
-        .ok_or(MessageError::IncompleteMessage { pos: 0 })?
+    let pos = scan::find_pair(input, CRLF).ok_or(MessageError::IncompleteMessage { pos: 0 })?
         + CRLF.len();
 
     let next = &input[..pos];
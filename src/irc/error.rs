@@ -1,3 +1,5 @@
+use crate::prelude::*;
+
 /// An invalid message was either provided, or could not be parsed
 #[derive(Debug)]
 #[non_exhaustive]
@@ -33,7 +35,7 @@ pub enum MessageError {
         /// The tag name
         name: String,
         /// The parse error
-        error: Box<dyn std::error::Error + Send + Sync>,
+        error: Box<dyn crate::StdError + Send + Sync>,
     },
 
     /// An empty key in the tags was provided
@@ -62,12 +64,12 @@ pub enum MessageError {
     /// A custom error message
     Custom {
         /// The inner error
-        error: Box<dyn std::error::Error + Send + Sync>,
+        error: Box<dyn crate::StdError + Send + Sync>,
     },
 }
 
-impl std::fmt::Display for MessageError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl core::fmt::Display for MessageError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match self {
             Self::InvalidCommand { expected, got } => {
                 write!(f, "invalid command. expected '{}' got '{}'", expected, got)
@@ -86,8 +88,8 @@ impl std::fmt::Display for MessageError {
     }
 }
 
-impl std::error::Error for MessageError {
-    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+impl crate::StdError for MessageError {
+    fn source(&self) -> Option<&(dyn crate::StdError + 'static)> {
         match self {
             Self::CannotParseTag { error, .. } => Some(&**error),
             Self::Custom { error } => Some(&**error),
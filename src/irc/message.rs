@@ -1,6 +1,6 @@
-use super::{parser::Parser, MessageError, Prefix, PrefixIndex};
+use super::{parser::Parser, MessageError, Prefix, PrefixIndex, TagIndices};
 use crate::{FromIrcMessage, MaybeOwned, MaybeOwnedIndex};
-use std::convert::Infallible;
+use core::convert::Infallible;
 
 /// A raw irc message `@tags :prefix COMMAND args :data\r\n`
 #[derive(Clone, PartialEq)]
@@ -28,14 +28,16 @@ impl<'a> IrcMessage<'a> {
             input.as_ref()
         };
 
-        let data = data.trim();
-        if data.is_empty() {
+        // the indices are into the raw input, so leading whitespace is skipped rather than trimmed off
+        let data = data.trim_end();
+        let start = data.len() - data.trim_start().len();
+        if start == data.len() {
             return Err(super::MessageError::EmptyMessage);
         }
 
         let mut p = Parser {
             input: data,
-            pos: 0,
+            pos: start,
         };
 
         let this = Self {
@@ -79,6 +81,46 @@ impl<'a> IrcMessage<'a> {
         self.data.map(|index| &self.raw[index])
     }
 
+    /// Get the prefix, if there was one
+    pub fn prefix(&self) -> Option<Prefix<'_>> {
+        self.prefix.map(|index| Prefix {
+            data: &self.raw,
+            index,
+        })
+    }
+
+    /// Parse the tags of this message.
+    ///
+    /// Use this with [Tags::from_data_indices](crate::irc::Tags::from_data_indices()) and [IrcMessage::raw] to get a typed [Tags](crate::irc::Tags) view:
+    /// ```
+    /// # use twitchchat::irc::*;
+    /// let input = "@badge-info=;color=#FF0000 :tmi.twitch.tv NEWCOMMAND #museun :hello\r\n";
+    /// let (_, msg) = parse_one(input).unwrap();
+    ///
+    /// let indices = msg.tag_indices().unwrap();
+    /// let tags = Tags::from_data_indices(&msg.raw, &indices);
+    /// assert_eq!(tags.get("color"), Some("#FF0000"));
+    /// ```
+    pub fn tag_indices(&self) -> Result<TagIndices, MessageError> {
+        self.get_tags()
+            .map_or_else(|| Ok(TagIndices::default()), TagIndices::build_indices)
+    }
+
+    /// Get the value of the tag `key` -- this does not unescape the value.
+    ///
+    /// This parses the tags on each call, so prefer [IrcMessage::tag_indices()] if you're looking up many tags.
+    pub fn get_tag(&self, key: &str) -> Option<&str> {
+        let tags = self.get_tags()?;
+        TagIndices::build_indices(tags).ok()?.get(tags, key)
+    }
+
+    /// Get an iterator over the args
+    pub fn iter_args(&self) -> impl Iterator<Item = &str> + '_ {
+        self.get_args()
+            .into_iter()
+            .flat_map(|args| args.split_ascii_whitespace())
+    }
+
     /// Consumes this type returning the raw `MaybeOwned<'a>`
     pub fn into_inner(self) -> MaybeOwned<'a> {
         self.raw
@@ -111,7 +153,7 @@ impl<'a> IrcMessage<'a> {
                 seen += 1;
             }
 
-            tail += 1;
+            tail += ch.len_utf8() as u16;
         }
 
         if seen == nth {
@@ -131,6 +173,12 @@ impl<'a> IrcMessage<'a> {
     ///
     /// This is sent by Twitch with your user information.
     pub const READY: &'static str = "376";
+    /// A list of users on a channel -- `353`.
+    ///
+    /// This is sent after you join a channel, if you have the `MEMBERSHIP` capability.
+    pub const NAMES: &'static str = "353";
+    /// The end of the list of users on a channel -- `366`.
+    pub const END_OF_NAMES: &'static str = "366";
     /// A capability response -- `CAP`.
     ///
     /// This is sent to acknowledge whether the capability requested is valid and applied to your connections.
@@ -193,18 +241,12 @@ into_owned! {
     }
 }
 
-impl<'a> std::fmt::Debug for IrcMessage<'a> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl<'a> core::fmt::Debug for IrcMessage<'a> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         f.debug_struct("IrcMessage")
             .field("raw", &&*self.raw)
             .field("tags", &self.get_tags())
-            .field(
-                "prefix",
-                &self.prefix.map(|index| Prefix {
-                    data: &self.raw,
-                    index,
-                }),
-            )
+            .field("prefix", &self.prefix())
             .field("command", &self.get_command())
             .field("args", &self.get_args())
             .field("data", &self.get_data())
@@ -253,6 +295,47 @@ mod tests {
         crate::serde::round_trip_rmp::<IrcMessage>(input);
     }
 
+    #[test]
+    fn unknown_command_accessors() {
+        let input = "@badge-info=;display-name=Museun :museun!museun@museun.tmi.twitch.tv NEWCOMMAND #museun foo bar :hello world\r\n";
+        let (_, msg) = crate::irc::parse_one(input).unwrap();
+
+        assert_eq!(msg.get_command(), "NEWCOMMAND");
+        assert_eq!(msg.get_tag("display-name"), Some("Museun"));
+        assert_eq!(msg.get_tag("badge-info"), Some(""));
+        assert_eq!(msg.get_tag("color"), None);
+        assert_eq!(msg.tag_indices().unwrap().len(), 2);
+
+        let prefix = msg.prefix().unwrap();
+        assert!(prefix.is_user());
+        assert_eq!(prefix.get_nick(), Some("museun"));
+
+        assert_eq!(
+            msg.iter_args().collect::<Vec<_>>(),
+            vec!["#museun", "foo", "bar"]
+        );
+        assert_eq!(msg.get_data(), Some("hello world"));
+
+        let (_, msg) = crate::irc::parse_one("PING\r\n").unwrap();
+        assert!(msg.prefix().is_none());
+        assert!(msg.tag_indices().unwrap().is_empty());
+        assert_eq!(msg.iter_args().count(), 0);
+    }
+
+    #[test]
+    fn leading_whitespace_and_multibyte_args() {
+        let (_, msg) = crate::irc::parse_one("  :tmi.twitch.tv 376 a🦀b c :é\r\n").unwrap();
+        assert_eq!(msg.get_command(), "376");
+        assert_eq!(msg.nth_arg(0), Some("a🦀b"));
+        assert_eq!(&msg.raw[msg.nth_arg_index(0).unwrap()], "a🦀b");
+        assert_eq!(&msg.raw[msg.nth_arg_index(1).unwrap()], "c");
+        assert_eq!(msg.get_data(), Some("é"));
+
+        // a '!' after the prefix isn't part of it
+        let (_, msg) = crate::irc::parse_one(":tmi.twitch.tv PRIVMSG #museun :hi!\r\n").unwrap();
+        assert!(!msg.prefix().unwrap().is_user());
+    }
+
     #[test]
     fn parse_empty_spaces() {
         for i in 0..10 {
@@ -1,4 +1,4 @@
-use super::{IrcMessage, MessageError, PrefixIndex};
+use super::{scan, IrcMessage, MessageError, PrefixIndex};
 use crate::{MaybeOwned, MaybeOwnedIndex};
 
 pub(super) struct Parser<'a> {
@@ -16,7 +16,7 @@ impl<'a> Parser<'a> {
     pub(super) fn tags(&mut self) -> Option<MaybeOwnedIndex> {
         let input = self.input.get(self.pos..)?;
         if input.starts_with('@') {
-            if let Some(end) = input.find(' ') {
+            if let Some(end) = scan::find(input, b' ') {
                 return Some(self.mark_index(end, end + 1));
             }
         }
@@ -26,9 +26,10 @@ impl<'a> Parser<'a> {
     pub(super) fn prefix(&mut self) -> Option<PrefixIndex> {
         let input = self.input.get(self.pos..)?;
         if input.starts_with(':') {
-            if let Some(pos) = input.find(' ') {
+            if let Some(pos) = scan::find(input, b' ') {
                 self.pos += 1;
-                let prefix = match input.find('!') {
+                // only a '!' in the prefix separates the nick
+                let prefix = match scan::find(&input[..pos], b'!') {
                     Some(bang) => PrefixIndex::User {
                         nick: self.mark_index(bang - 1, pos),
                     },
@@ -45,7 +46,7 @@ impl<'a> Parser<'a> {
 
     pub(super) fn command(&mut self) -> MaybeOwnedIndex {
         let input = &self.input[self.pos..];
-        let pos = input.find(' ').unwrap_or_else(|| input.len());
+        let pos = scan::find(input, b' ').unwrap_or(input.len());
         self.mark_index(pos, pos + 1)
     }
 
@@ -55,12 +56,15 @@ impl<'a> Parser<'a> {
         }
 
         let input = self.input.get(self.pos..)?;
-        let pos = input.find(" :").unwrap_or_else(|| input.len());
+        let pos = scan::find_pair(input, " :").unwrap_or(input.len());
         Some(self.mark_index(pos, pos))
     }
 
     pub(super) fn data(self) -> Option<MaybeOwnedIndex> {
-        let pos = self.input.get(self.pos..).and_then(|s| s.find(':'))?;
+        let pos = self
+            .input
+            .get(self.pos..)
+            .and_then(|s| scan::find(s, b':'))?;
         self.input
             .get(self.pos + pos + 1..)
             .filter(|s| !s.is_empty())
@@ -89,7 +93,7 @@ impl<'a> Iterator for IrcParserIter<'a> {
             return None;
         }
 
-        let index = match self.data.get(self.pos..)?.find(CRLF) {
+        let index = match scan::find_pair(self.data.get(self.pos..)?, CRLF) {
             Some(index) => index + CRLF.len() + self.pos,
             None => {
                 let err = Err(MessageError::IncompleteMessage { pos: self.pos });
@@ -98,7 +102,7 @@ impl<'a> Iterator for IrcParserIter<'a> {
             }
         };
 
-        let pos = std::mem::replace(&mut self.pos, index);
+        let pos = core::mem::replace(&mut self.pos, index);
         self.data
             .get(pos..index)
             .map(MaybeOwned::from)
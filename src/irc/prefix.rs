@@ -6,8 +6,8 @@ pub struct Prefix<'a> {
     pub(crate) index: PrefixIndex,
 }
 
-impl<'a> std::fmt::Debug for Prefix<'a> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl<'a> core::fmt::Debug for Prefix<'a> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         self.data[self.index.as_index()].fmt(f)
     }
 }
@@ -0,0 +1,76 @@
+// byte searching for the parser. with the `simd` feature these use memchr, which scans many bytes at a time.
+// the needles are always ascii
+
+#[cfg(feature = "simd")]
+pub(crate) fn find(haystack: &str, needle: u8) -> Option<usize> {
+    memchr::memchr(needle, haystack.as_bytes())
+}
+
+#[cfg(not(feature = "simd"))]
+pub(crate) fn find(haystack: &str, needle: u8) -> Option<usize> {
+    haystack.find(char::from(needle))
+}
+
+// finds a two byte needle, e.g. "\r\n" or " :"
+#[cfg(feature = "simd")]
+pub(crate) fn find_pair(haystack: &str, needle: &str) -> Option<usize> {
+    let (haystack, needle) = (haystack.as_bytes(), needle.as_bytes());
+    memchr::memchr_iter(needle[0], haystack).find(|&pos| haystack.get(pos + 1) == Some(&needle[1]))
+}
+
+#[cfg(not(feature = "simd"))]
+pub(crate) fn find_pair(haystack: &str, needle: &str) -> Option<usize> {
+    haystack.find(needle)
+}
+
+// the positions of `needle` in the haystack, followed by the length of the haystack
+pub(crate) fn split_points(haystack: &str, needle: u8) -> impl Iterator<Item = usize> + '_ {
+    #[cfg(feature = "simd")]
+    let iter = memchr::memchr_iter(needle, haystack.as_bytes());
+    #[cfg(not(feature = "simd"))]
+    let iter = haystack
+        .match_indices(char::from(needle))
+        .map(|(pos, _)| pos);
+
+    iter.chain(core::iter::once(haystack.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INPUTS: &[&str] = &[
+        "",
+        " ",
+        "PING :foo\r\n",
+        "@badge-info=;badges=broadcaster/1;color=#FF69B4 :museun!museun@museun PRIVMSG #museun :hello :world\r\n",
+        "@display-name=月饼;foo=bar :a!a@a PRIVMSG #a :月饼 \r\r\n",
+    ];
+
+    #[test]
+    fn matches_str_find() {
+        for input in INPUTS {
+            for &needle in b" :;=!\r" {
+                assert_eq!(
+                    find(input, needle),
+                    input.find(needle as char),
+                    "{:?}",
+                    input
+                );
+            }
+            for needle in &["\r\n", " :"] {
+                assert_eq!(find_pair(input, needle), input.find(needle), "{:?}", input);
+            }
+
+            let mut start = 0;
+            let split = split_points(input, b';')
+                .map(|end| {
+                    let s = &input[start..end];
+                    start = end + 1;
+                    s
+                })
+                .collect::<Vec<_>>();
+            assert_eq!(split, input.split(';').collect::<Vec<_>>());
+        }
+    }
+}
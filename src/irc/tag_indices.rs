@@ -1,20 +1,21 @@
-use std::borrow::Cow;
+use crate::prelude::*;
 
-use crate::{maybe_owned::MaybeOwned, IntoOwned, MessageError};
+use crate::{maybe_owned::MaybeOwned, IntoOwned, MaybeOwnedIndex, MessageError};
 
 /// Pre-computed tag indices
 ///
 /// This type is only exposed for those wanting to extend/make custom types.
+///
+/// These are indices into the tags fragment they were built from, so looking up a tag doesn't allocate.
 #[derive(Default, Clone, PartialEq)]
 pub struct TagIndices {
-    // NOTE this is a hack to keep the semver stable, in v0.15 this'll go back to being borrowed.
-    pub(super) map: Box<[(Cow<'static, str>, Cow<'static, str>)]>,
+    pub(super) map: Box<[(MaybeOwnedIndex, MaybeOwnedIndex)]>,
 }
 
-impl std::fmt::Debug for TagIndices {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl core::fmt::Debug for TagIndices {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         f.debug_map()
-            .entries(self.map.iter().map(|(k, v)| (k, v)))
+            .entries(self.map.iter().map(|(k, v)| (k.as_range(), v.as_range())))
             .finish()
     }
 }
@@ -28,24 +29,44 @@ impl TagIndices {
             return Ok(Self::default());
         }
 
-        input[1..]
-            .split_terminator(';')
-            .enumerate()
-            .map(|(pos, input)| {
-                use MessageError::{MissingTagKey, MissingTagValue};
-                let expect = |s: Option<&str>, err: fn(usize) -> MessageError| {
-                    s.map(ToString::to_string)
-                        .map(Cow::from)
-                        .ok_or_else(|| err(pos))
-                };
-
-                let mut iter = input.split('=');
-                let key = expect(iter.next().filter(|s| !s.is_empty()), MissingTagKey)?;
-                let value = expect(iter.next(), MissingTagValue)?;
-                Ok((key, value))
-            })
-            .collect::<Result<_, _>>()
-            .map(|map| Self { map })
+        use super::scan;
+        use MessageError::{MissingTagKey, MissingTagValue};
+
+        let tags = &input[1..];
+        let mut start = 0;
+        let mut map = Vec::new();
+
+        for (i, end) in scan::split_points(tags, b';').enumerate() {
+            let tag = &tags[start..end];
+            // a trailing ';' doesn't start another tag
+            if tag.is_empty() && end == tags.len() {
+                break;
+            }
+
+            let key = scan::find(tag, b'=').unwrap_or(tag.len());
+            if key == 0 {
+                return Err(MissingTagKey(i));
+            }
+            if key == tag.len() {
+                return Err(MissingTagValue(i));
+            }
+
+            // anything after a second '=' isn't part of the value
+            let value = &tag[key + 1..];
+            let value = scan::find(value, b'=').unwrap_or(value.len());
+
+            // the indices are into the whole fragment, which starts with the '@'
+            let key_index = MaybeOwnedIndex::raw(1 + start, 1 + start + key);
+            let value_start = key_index.end as usize + 1;
+            map.push((
+                key_index,
+                MaybeOwnedIndex::raw(value_start, value_start + value),
+            ));
+
+            start = end + 1;
+        }
+
+        Ok(Self { map: map.into() })
     }
 
     /// Get the number of parsed tags
@@ -59,16 +80,20 @@ impl TagIndices {
     }
 
     // NOTE: this isn't public because they don't verify 'data' is the same as the built-indices data
-    pub(crate) fn get_unescaped<'a>(&'a self, key: &str) -> Option<MaybeOwned<'a>> {
-        self.get(key).map(crate::test::unescape_str)
+    pub(crate) fn get_unescaped<'a>(&self, data: &'a str, key: &str) -> Option<MaybeOwned<'a>> {
+        self.get(data, key).map(crate::irc::tags::unescape_str)
     }
 
     // NOTE: this isn't public because they don't verify 'data' is the same as the built-indices data
-    pub(crate) fn get<'a>(&'a self, key: &str) -> Option<&'a str> {
-        let key = crate::test::escape_str(key);
-        self.map
-            .iter()
-            .find_map(|(k, v)| if &key == k { Some(&**v) } else { None })
+    pub(crate) fn get<'a>(&self, data: &'a str, key: &str) -> Option<&'a str> {
+        let key = crate::irc::tags::escape_str(key);
+        self.map.iter().find_map(|(k, v)| {
+            if data.get(k.as_range())? == key {
+                data.get(v.as_range())
+            } else {
+                None
+            }
+        })
     }
 }
 
@@ -85,6 +110,9 @@ mod tests {
     #[test]
     fn utf8_tags() {
         let input = "@id=86293428;login=yuebing233;display_name=月饼;foo=bar";
-        TagIndices::build_indices(input).unwrap();
+        let indices = TagIndices::build_indices(input).unwrap();
+        assert_eq!(indices.get(input, "display_name"), Some("月饼"));
+        assert_eq!(indices.get(input, "foo"), Some("bar"));
+        assert_eq!(indices.get(input, "missing"), None);
     }
 }
@@ -1,5 +1,10 @@
-use crate::{irc::TagIndices, MaybeOwned};
-use std::{borrow::Borrow, str::FromStr};
+use crate::prelude::*;
+use crate::{
+    irc::{MessageError, TagIndices},
+    MaybeOwned, StdError,
+};
+use alloc::borrow::Cow;
+use core::{borrow::Borrow, str::FromStr, time::Duration};
 
 /// Tags are IRCv3 message tags. Twitch uses them extensively.
 ///
@@ -12,8 +17,8 @@ pub struct Tags<'a> {
     pub(crate) indices: &'a TagIndices,
 }
 
-impl<'a> std::fmt::Debug for Tags<'a> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl<'a> core::fmt::Debug for Tags<'a> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         f.debug_map().entries(self.iter()).finish()
     }
 }
@@ -47,7 +52,7 @@ impl<'a> Tags<'a> {
     where
         K: ?Sized + Borrow<str>,
     {
-        self.indices.get_unescaped(key.borrow())
+        self.indices.get_unescaped(self.data, key.borrow())
     }
 
     /// Tries to get this `key`
@@ -58,10 +63,10 @@ impl<'a> Tags<'a> {
     where
         K: ?Sized + Borrow<str>,
     {
-        self.indices.get(key.borrow())
+        self.indices.get(self.data, key.borrow())
     }
 
-    /** Tries to get the tag as a parsable [std::str::FromStr] type.
+    /** Tries to get the tag as a parsable [core::str::FromStr] type.
 
     This returns None if it cannot parse, or cannot find the tag
 
@@ -103,6 +108,114 @@ impl<'a> Tags<'a> {
             .flatten()
     }
 
+    /** Gets the tag as a parsable [core::str::FromStr] type, with an error saying why it couldn't.
+
+    This is like [Tags::get_parsed()], but returns [MessageError::ExpectedTag] if the tag is missing,
+    and [MessageError::CannotParseTag] (with the parse error) if it cannot be parsed.
+
+    ```rust
+    # use twitchchat::{irc::{TagIndices, Tags}, maybe_owned::MaybeOwned, MessageError};
+    let input: MaybeOwned<'_> = "@tmi-sent-ts=1601079032426;color=blue".into();
+    let indices = TagIndices::build_indices(&*input).unwrap();
+    let tags = Tags::from_data_indices(&input, &indices);
+
+    assert_eq!(tags.try_get_parsed::<_, u64>("tmi-sent-ts").unwrap(), 1601079032426);
+
+    let err = tags.try_get_parsed::<_, u64>("color").unwrap_err();
+    assert!(matches!(err, MessageError::CannotParseTag { .. }));
+
+    let err = tags.try_get_parsed::<_, u64>("room-id").unwrap_err();
+    assert!(matches!(err, MessageError::ExpectedTag { .. }));
+    ```
+    */
+    pub fn try_get_parsed<K, E>(&self, key: &K) -> Result<E, MessageError>
+    where
+        K: ?Sized + Borrow<str>,
+        E: FromStr,
+        E::Err: StdError + Send + Sync + 'static,
+    {
+        let key = key.borrow();
+        let value = self
+            .get_unescaped(key)
+            .ok_or_else(|| MessageError::ExpectedTag {
+                name: key.to_string(),
+            })?;
+        value.parse().map_err(|err| MessageError::CannotParseTag {
+            name: key.to_string(),
+            error: Box::new(err),
+        })
+    }
+
+    /** Gets the tag as a bool, with an error saying why it couldn't.
+
+    Twitch uses `1` and `0`, but `true` and `false` are also accepted. Unlike [Tags::get_as_bool()], a missing tag is an error.
+
+    ```rust
+    # use twitchchat::{irc::{TagIndices, Tags}, maybe_owned::MaybeOwned};
+    let input: MaybeOwned<'_> = "@emote-only=1;r9k=0;slow=30".into();
+    let indices = TagIndices::build_indices(&*input).unwrap();
+    let tags = Tags::from_data_indices(&input, &indices);
+
+    assert!(tags.get_bool("emote-only").unwrap());
+    assert!(!tags.get_bool("r9k").unwrap());
+    assert!(tags.get_bool("slow").is_err());
+    assert!(tags.get_bool("subs-only").is_err());
+    ```
+    */
+    pub fn get_bool<K>(&self, key: &K) -> Result<bool, MessageError>
+    where
+        K: ?Sized + Borrow<str>,
+    {
+        match self.get(key) {
+            Some("1") => Ok(true),
+            Some("0") => Ok(false),
+            _ => self.try_get_parsed(key),
+        }
+    }
+
+    /** Gets the tag as a duration in seconds, with an error saying why it couldn't.
+
+    ```rust
+    # use twitchchat::{irc::{TagIndices, Tags}, maybe_owned::MaybeOwned};
+    # use std::time::Duration;
+    let input: MaybeOwned<'_> = "@ban-duration=600;room-id=23196011".into();
+    let indices = TagIndices::build_indices(&*input).unwrap();
+    let tags = Tags::from_data_indices(&input, &indices);
+
+    assert_eq!(tags.get_duration("ban-duration").unwrap(), Duration::from_secs(600));
+    assert!(tags.get_duration("slow").is_err());
+    ```
+    */
+    pub fn get_duration<K>(&self, key: &K) -> Result<Duration, MessageError>
+    where
+        K: ?Sized + Borrow<str>,
+    {
+        self.try_get_parsed(key).map(Duration::from_secs)
+    }
+
+    /** Gets the tag as a timestamp in milliseconds since the unix epoch (e.g. `tmi-sent-ts`), with an error saying why it couldn't.
+
+    ```rust
+    # use twitchchat::{irc::{TagIndices, Tags}, maybe_owned::MaybeOwned};
+    # use std::time::{Duration, UNIX_EPOCH};
+    let input: MaybeOwned<'_> = "@tmi-sent-ts=1601079032426".into();
+    let indices = TagIndices::build_indices(&*input).unwrap();
+    let tags = Tags::from_data_indices(&input, &indices);
+
+    let sent_at = tags.get_timestamp("tmi-sent-ts").unwrap();
+    assert_eq!(sent_at, UNIX_EPOCH + Duration::from_millis(1601079032426));
+    ```
+    */
+    #[cfg(feature = "std")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+    pub fn get_timestamp<K>(&self, key: &K) -> Result<std::time::SystemTime, MessageError>
+    where
+        K: ?Sized + Borrow<str>,
+    {
+        self.try_get_parsed(key)
+            .map(|ms| std::time::UNIX_EPOCH + Duration::from_millis(ms))
+    }
+
     /** Tries to get the tag as a bool.
 
     If it wasn't found it'll return false
@@ -172,8 +285,8 @@ pub struct TagsIter<'a> {
     pos: usize,
 }
 
-impl<'a> std::fmt::Debug for TagsIter<'a> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl<'a> core::fmt::Debug for TagsIter<'a> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         f.debug_struct("TagsIter").finish()
     }
 }
@@ -188,8 +301,9 @@ impl<'a> Iterator for TagsIter<'a> {
         let pos = self.pos;
         self.pos += 1;
 
-        let (k, v) = &self.inner.indices.map.get(pos)?;
-        Some((&**k, &**v))
+        let data: &'a str = self.inner.data;
+        let (k, v) = self.inner.indices.map.get(pos)?;
+        Some((data.get(k.as_range())?, data.get(v.as_range())?))
     }
 }
 
@@ -231,7 +345,7 @@ impl<'a> ::serde::Serialize for Tags<'a> {
 ///
 /// [ref]: https://ircv3.net/specs/extensions/message-tags.html#escaping-values
 pub fn unescape_str(s: &str) -> MaybeOwned<'_> {
-    if !s.chars().any(|c| c == '\\') {
+    if !s.contains('\\') {
         return MaybeOwned::Borrowed(s);
     }
     let mut buf = String::with_capacity(s.len());
@@ -275,7 +389,7 @@ pub fn unescape_str(s: &str) -> MaybeOwned<'_> {
 /// | --          | the character itself |
 ///
 /// [ref]: https://ircv3.net/specs/extensions/message-tags.html#escaping-values
-pub fn escape_str(s: &str) -> std::borrow::Cow<'_, str> {
+pub fn escape_str(s: &str) -> Cow<'_, str> {
     const NEEDS_ESCAPE: [char; 5] = [';', ' ', '\\', '\n', '\r'];
     let n = s.chars().filter(|c| NEEDS_ESCAPE.contains(c)).count();
     if n == 0 {
@@ -300,7 +414,7 @@ pub fn escape_str(s: &str) -> std::borrow::Cow<'_, str> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::str::FromStr;
+    use core::str::FromStr;
 
     #[test]
     fn round_trip_escape() {
@@ -308,6 +422,22 @@ mod tests {
         assert_eq!(unescape_str(&*escape_str(s)), s);
     }
 
+    #[test]
+    fn typed_errors() {
+        let data = MaybeOwned::Borrowed("@slow=abc;ban-duration=-1");
+        let indices = TagIndices::build_indices(&data).unwrap();
+        let tags = Tags::from_data_indices(&data, &indices);
+
+        let err = tags.get_bool("slow").unwrap_err();
+        assert!(err.to_string().starts_with("cannot parse 'slow': "));
+
+        let err = tags.get_duration("ban-duration").unwrap_err();
+        assert!(err.to_string().starts_with("cannot parse 'ban-duration': "));
+
+        let err = tags.get_duration("missing").unwrap_err();
+        assert_eq!(err.to_string(), "expected tag 'missing'");
+    }
+
     #[test]
     fn escaped_tag() {
         let s = escape_str(r"@hello;world=abc\ndef");
@@ -358,7 +488,7 @@ mod tests {
         struct Badges(std::collections::HashMap<String, usize>);
 
         impl FromStr for Badges {
-            type Err = std::convert::Infallible;
+            type Err = core::convert::Infallible;
             fn from_str(s: &str) -> Result<Self, Self::Err> {
                 let iter = s.split_terminator(',').filter_map(|s| {
                     let mut iter = s.split('/');
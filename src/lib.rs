@@ -21,6 +21,7 @@
     unused_import_braces,
     unused_qualifications
 )]
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
 #![cfg_attr(docsrs, feature(doc_cfg))]
 #![cfg_attr(docsrs, feature(doc_alias))]
 #![cfg_attr(docsrs, feature(broken_intra_doc_links))]
@@ -42,6 +43,14 @@ To use the [AsyncRunner] (an async-event loop) and related helpers, you must abl
 ```toml
 twitchchat = { version = "0.14", features = ["async"] }
 ```
+
+Without the default `std` feature, the crate is `#![no_std]` and only needs `alloc`.
+This leaves the [irc] parser, the [messages] and the [twitch] types (and `serde` support for them), for use on embedded targets.
+The decoding, encoding and everything built on them needs `std`.
+
+```toml
+twitchchat = { version = "0.14", default-features = false }
+```
 ---
 
 For twitch types:
@@ -60,9 +69,79 @@ For just decoding messages:
 ---
 For just encoding messages:
 * [encoder]
+---
+For a blocking client, without an async runtime:
+* `sync`
+---
+For recording a session, and replaying it (e.g. in tests):
+* `recording`
+---
+For EventSub (channel points, polls, predictions, hype trains), enable the `eventsub` feature:
+* `eventsub`
+---
+For the chat-related Helix endpoints (bans, announcements, chat settings, ..), enable the `helix` feature:
+* `helix`
+---
+For validating and refreshing OAuth tokens, enable the `oauth` feature:
+* `oauth`
+* `validate_token`
+---
+For routing `!commands` to handlers (with arguments, permissions, cooldowns and a generated help), enable the `router` feature:
+* `router`
+---
+For detecting links and spam (caps, repeated characters, zalgo, ascii art, emote walls), enable the `filters` feature:
+* `filters`
+---
+For the URLs of emote images on Twitch's CDN (`twitch::TwitchCdn`) and the BTTV, FFZ and 7TV CDNs (`twitch::ThirdPartyCdn`), for `Privmsg::resolve_emotes`, enable the `emote-cdn` feature:
+* `twitch::TwitchCdn`
+* `twitch::ThirdPartyCdn`
+---
+For logging every message to rotating JSON-lines or text files, enable the `sinks` feature:
+* `sinks`
+---
+For archiving messages, users, badges and moderation events to a SQLite or PostgreSQL database, enable the `archive` feature:
+* `sinks::Archive`
+---
+For exporting the runner's metrics (connections, queue depths, parse errors, latency, per-channel message rates) in the Prometheus text format, enable the `prometheus` feature:
+* `observability::prometheus`
+---
+For sharing one connection (and its rate limits) between several local IRC clients, like a bouncer, enable the `relay` feature:
+* `relay`
+---
+For parsing chat in the browser (e.g. an overlay built for `wasm32-unknown-unknown`), enable the `browser` feature. This needs an [AsyncDecoder] and an [AsyncEncoder], the runner can't be used there:
+* `connector::browser`
+---
+For simulating a busy chat (messages, emotes, cheers, subscriptions and raids at the rates you choose), e.g. to load-test an overlay or a bot, enable the `testing` feature:
+* `simulate`
+---
+For log records of the connection's lifecycle, enable the `log-events` feature. This emits [log](https://docs.rs/log) records under these targets:
+* `twitchchat::connect` and `twitchchat::handshake`, at `INFO`, as the connection is established and registered
+* `twitchchat::reconnect`, at `INFO`, when a `Reconnect` waits and reconnects (and at `WARN` or `ERROR` when it disconnects or gives up)
+* `twitchchat::rate_limit`, at `INFO`, when a join or a write has to wait for the rate limit (and at `WARN` when the global rate limit is hit)
+* `twitchchat::read` and `twitchchat::write`, at `TRACE`, with every raw line
+---
+For [tracing](https://docs.rs/tracing) spans, enable the `tracing` feature. These are `INFO` spans, under the same targets:
+* `twitchchat::connect`, with the `name`, while connecting
+* `twitchchat::handshake`, while waiting for the connection to be ready
+* `twitchchat::reconnect`, with the `attempt`, around each of a `Reconnect`'s connection attempts
+* `twitchchat::rate_limit`, with the `channel`, while a join waits for the rate limit
+
+The records listed for `log-events` are also emitted as `tracing` events, in these spans.
+---
+For faster parsing of busy chats, enable the `simd` feature. This uses [memchr](https://docs.rs/memchr) to scan for the line endings and the tag separators.
 
 */
 
+macro_rules! cfg_std {
+    ($($item:item)*) => {
+        $(
+            #[cfg(feature = "std")]
+            #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+            $item
+        )*
+    };
+}
+
 macro_rules! cfg_async {
     ($($item:item)*) => {
         $(
@@ -92,20 +171,43 @@ pub const TWITCH_TLS_DOMAIN: &str = "irc.chat.twitch.tv";
 pub const ANONYMOUS_LOGIN: (&str, &str) = (JUSTINFAN1234, JUSTINFAN1234);
 pub(crate) const JUSTINFAN1234: &str = "justinfan1234";
 
+extern crate alloc;
+
+// the parts of `alloc` that `std` has in its prelude
+#[allow(unused_imports)]
+mod prelude {
+    pub(crate) use alloc::{
+        borrow::ToOwned,
+        boxed::Box,
+        format,
+        string::{String, ToString},
+        vec,
+        vec::Vec,
+    };
+}
+
+// `core::error::Error` needs a much newer compiler than the rest of the crate, so only use it without `std`
+#[cfg(not(feature = "std"))]
+use core::error::Error as StdError;
+#[cfg(feature = "std")]
+use std::error::Error as StdError;
+
 #[macro_use]
 #[allow(unused_macros)]
 mod macros;
 
-pub mod decoder;
-pub use decoder::{DecodeError, Decoder};
+cfg_std! { pub mod decoder; }
+cfg_std! { pub use decoder::{DecodeError, Decoder}; }
 cfg_async! { pub use decoder::AsyncDecoder; }
 
-pub mod encoder;
-pub use encoder::Encoder;
+cfg_std! { pub mod encoder; }
+cfg_std! { pub use encoder::Encoder; }
 cfg_async! { pub use encoder::AsyncEncoder; }
 
-/// A boxed `Future` that is `Send + Sync`
-pub type BoxedFuture<T> = std::pin::Pin<Box<dyn std::future::Future<Output = T> + Send + Sync>>;
+cfg_std! {
+    /// A boxed `Future` that is `Send + Sync`
+    pub type BoxedFuture<T> = std::pin::Pin<Box<dyn std::future::Future<Output = T> + Send + Sync>>;
+}
 
 cfg_async! {
     /// An AsyncWriter over an MpscWriter
@@ -113,32 +215,79 @@ cfg_async! {
 }
 
 cfg_async! { pub mod connector; }
+cfg_async! { pub mod recording; }
+cfg_async! { pub mod sync; }
 cfg_async! { pub mod writer; }
 cfg_async! { pub mod channel; }
 
-pub mod runner;
-pub use runner::{Error as RunnerError, Status};
+cfg_std! { pub mod runner; }
+cfg_std! { pub use runner::{Error, Error as RunnerError, Status}; }
 cfg_async! { pub use runner::AsyncRunner; }
 
-pub mod rate_limit;
+cfg_std! { pub mod rate_limit; }
+
+#[cfg(feature = "eventsub")]
+#[cfg_attr(docsrs, doc(cfg(feature = "eventsub")))]
+pub mod eventsub;
 
-pub mod commands;
+#[cfg(feature = "helix")]
+#[cfg_attr(docsrs, doc(cfg(feature = "helix")))]
+pub mod helix;
+
+#[cfg(feature = "oauth")]
+#[cfg_attr(docsrs, doc(cfg(feature = "oauth")))]
+pub mod oauth;
+
+#[cfg(feature = "oauth")]
+#[cfg_attr(docsrs, doc(cfg(feature = "oauth")))]
+pub use oauth::validate_token;
+
+#[cfg(feature = "router")]
+#[cfg_attr(docsrs, doc(cfg(feature = "router")))]
+pub mod router;
+
+#[cfg(feature = "filters")]
+#[cfg_attr(docsrs, doc(cfg(feature = "filters")))]
+pub mod filters;
+
+#[cfg(feature = "sinks")]
+#[cfg_attr(docsrs, doc(cfg(feature = "sinks")))]
+pub mod sinks;
+
+#[cfg(feature = "prometheus")]
+#[cfg_attr(docsrs, doc(cfg(feature = "prometheus")))]
+pub mod observability;
+
+#[cfg(feature = "relay")]
+#[cfg_attr(docsrs, doc(cfg(feature = "relay")))]
+pub mod relay;
+
+#[cfg(any(feature = "eventsub", feature = "helix", feature = "oauth"))]
+mod http;
+
+cfg_std! { pub mod commands; }
 pub mod messages;
 
 pub mod irc;
 pub use irc::{IrcMessage, MessageError};
 
-/// Helpful testing utilities
-pub mod test;
+cfg_std! {
+    /// Helpful testing utilities
+    pub mod test;
+}
+
+#[cfg(feature = "testing")]
+#[cfg_attr(docsrs, doc(cfg(feature = "testing")))]
+pub mod simulate;
 
 #[doc(inline)]
 pub use irc::{FromIrcMessage, IntoIrcMessage};
 
 pub mod twitch;
-pub use twitch::UserConfig;
+cfg_std! { pub use twitch::UserConfig; }
 
-mod encodable;
-pub use encodable::Encodable;
+cfg_std! { mod encodable; }
+cfg_std! { pub use encodable::Encodable; }
 
 pub mod maybe_owned;
 pub use maybe_owned::IntoOwned;
@@ -147,9 +296,9 @@ use maybe_owned::{MaybeOwned, MaybeOwnedIndex};
 mod validator;
 pub use validator::Validator;
 
-mod ext;
+cfg_std! { mod ext; }
 #[cfg(feature = "serde")]
 mod serde;
 mod util;
 
-pub use ext::PrivmsgExt;
+cfg_std! { pub use ext::PrivmsgExt; }
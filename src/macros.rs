@@ -46,8 +46,8 @@ macro_rules! serde_struct {
 
 macro_rules! impl_custom_debug {
     ($ty:ident { $($field:ident),* $(,)? }) => {
-        impl<'a> std::fmt::Debug for $ty<'a> {
-            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        impl<'a> core::fmt::Debug for $ty<'a> {
+            fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
                 f.debug_struct(stringify!($ty))
                     $( .field(stringify!($field), &self.$field()) )*
                 .finish()
@@ -115,3 +115,33 @@ macro_rules! opt_str_field {
         }
     };
 }
+
+// a log record for the connection's lifecycle, which is only emitted with the `log-events` feature.
+// with the `tracing` feature, this is also a `tracing` event (in the current span).
+//
+// the target is nested under `twitchchat::`, so each part can be filtered on its own
+macro_rules! event {
+    ($level:ident, $target:literal, $($arg:tt)+) => {{
+        // this is still type checked (and uses its arguments) without the feature
+        if cfg!(feature = "log-events") {
+            log::$level!(target: concat!("twitchchat::", $target), $($arg)+);
+        }
+        #[cfg(feature = "tracing")]
+        tracing::$level!(target: concat!("twitchchat::", $target), $($arg)+);
+    }};
+}
+
+// runs the future in an `INFO` span named after the target, with the `tracing` feature.
+// without it, this is just the future (and the fields aren't evaluated)
+macro_rules! in_span {
+    ($target:literal, $fut:expr $(, $($field:tt)+)?) => {{
+        #[cfg(feature = "tracing")]
+        let fut = tracing::Instrument::instrument(
+            $fut,
+            tracing::info_span!(target: concat!("twitchchat::", $target), $target $(, $($field)+)?),
+        );
+        #[cfg(not(feature = "tracing"))]
+        let fut = $fut;
+        fut
+    }};
+}
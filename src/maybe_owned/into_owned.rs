@@ -1,5 +1,6 @@
 use super::{MaybeOwned, MaybeOwnedIndex};
-use crate::twitch::{Color, UserConfig};
+use crate::prelude::*;
+use crate::twitch::Color;
 
 /// Converts a **borrowed** type into an owned type. e.g. `'a` to `'static`
 pub trait IntoOwned<'a> {
@@ -33,7 +34,8 @@ impl IntoOwned<'static> for Color {
     }
 }
 
-impl IntoOwned<'static> for UserConfig {
+#[cfg(feature = "std")]
+impl IntoOwned<'static> for crate::twitch::UserConfig {
     type Output = Self;
     fn into_owned(self) -> Self::Output {
         self
@@ -1,5 +1,5 @@
 use super::MaybeOwned;
-use std::ops::{Index, Range};
+use core::ops::{Index, Range};
 
 type IndexWidth = u16;
 
@@ -52,7 +52,7 @@ impl MaybeOwnedIndex {
 
     /// Replace this index with a new one start/ending at `pos`, returning the old index
     pub fn replace(&mut self, pos: usize) -> Self {
-        std::mem::replace(self, Self::new(pos))
+        core::mem::replace(self, Self::new(pos))
     }
 
     /// Checks whether this index is empty (e.g. start points to the dn)
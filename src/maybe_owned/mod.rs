@@ -3,7 +3,8 @@
 //! It is read-only unlike the std implementation.
 //!
 //! Its also specialized for just `str`
-use std::{fmt::Debug, ops::Deref};
+use crate::prelude::*;
+use core::{fmt::Debug, ops::Deref};
 
 mod into_owned;
 pub use into_owned::IntoOwned;
@@ -48,7 +49,7 @@ impl<'a> Clone for MaybeOwned<'a> {
 }
 
 impl<'a> Debug for MaybeOwned<'a> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         self.as_ref().fmt(f)
     }
 }
@@ -59,6 +60,14 @@ impl<'a> PartialEq for MaybeOwned<'a> {
     }
 }
 
+impl<'a> Eq for MaybeOwned<'a> {}
+
+impl<'a> core::hash::Hash for MaybeOwned<'a> {
+    fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
+        self.as_ref().hash(state)
+    }
+}
+
 impl<'a> PartialEq<str> for MaybeOwned<'a> {
     fn eq(&self, other: &str) -> bool {
         self.as_ref() == other
@@ -29,9 +29,58 @@
 //! assert!(matches!(all, Commands::Privmsg{..}));
 //! ```
 //!
+//! # Borrowing
+//!
+//! The messages borrow from the line they were parsed from (e.g. `Privmsg<'a>`), and their accessors (including the tags) are slices of that line.
+//! So parsing and filtering messages doesn't allocate a `String` per field.
+//!
+//! Use [IntoOwned](crate::IntoOwned) to turn one into a `'static` message, which copies the line once. The `AsyncRunner` hands out owned messages.
+//!
+//! ```
+//! use twitchchat::{FromIrcMessage, IntoOwned, messages::Privmsg};
+//!
+//! let input = "@display-name=Museun :museun!museun@museun PRIVMSG #museun :hello\r\n";
+//! let irc_msg = twitchchat::irc::parse(input).next().unwrap().unwrap();
+//!
+//! // this borrows from 'input'
+//! let pm: Privmsg<'_> = Privmsg::from_irc(irc_msg).unwrap();
+//! assert_eq!(pm.display_name().as_deref(), Some("Museun"));
+//!
+//! // keep it around after 'input' is gone
+//! let pm: Privmsg<'static> = pm.into_owned();
+//! ```
+//!
+//! # Building messages
+//!
+//! Some messages have a builder (e.g. [Privmsg::builder()]), for constructing them without writing the IRC line yourself -- e.g. to test your bot's handlers with.
+//!
+//! ```
+//! use twitchchat::{messages::Privmsg, twitch::BadgeKind};
+//!
+//! let pm = Privmsg::builder()
+//!     .channel("#museun")
+//!     .sender("museun")
+//!     .data("hello")
+//!     .badge(BadgeKind::Broadcaster)
+//!     .build()
+//!     .unwrap();
+//! assert!(pm.is_broadcaster());
+//! ```
+//!
+//! # Serde
+//!
+//! With the `serde` feature, the messages serialize as a map of their accessors, keyed by the accessor name (e.g. `display_name`, `badges`, `emotes`).
+//! The `raw` message is always included, and is the only field used when deserializing.
+//!
 
 mod commands;
-pub use commands::Commands;
+pub use commands::{Commands, FromCommands};
+
+mod builder;
+pub use builder::{
+    ClearChatBuilder, ClearMsgBuilder, JoinBuilder, NoticeBuilder, PartBuilder, PrivmsgBuilder,
+    WhisperBuilder,
+};
 
 mod irc_ready;
 pub use irc_ready::IrcReady;
@@ -57,8 +106,11 @@ pub use host_target::{HostTarget, HostTargetKind};
 mod join;
 pub use join::Join;
 
+mod moderation_action;
+pub use moderation_action::ModerationAction;
+
 mod notice;
-pub use notice::{MessageId, Notice};
+pub use notice::{MessageId, Notice, Severity};
 
 mod part;
 pub use part::Part;
@@ -70,16 +122,19 @@ mod pong;
 pub use pong::Pong;
 
 mod privmsg;
-pub use privmsg::Privmsg;
+pub use privmsg::{PaidMessage, Privmsg, ReplyParent, RewardRedemption};
 
 mod reconnect;
 pub use reconnect::Reconnect;
 
 mod room_state;
-pub use room_state::{FollowersOnly, RoomState};
+pub use room_state::{FollowersOnly, RoomSettings, RoomState};
 
 mod user_notice;
-pub use user_notice::{NoticeType, SubPlan, UserNotice};
+pub use user_notice::{
+    Announcement, BitsBadgeTier, GiftPaidUpgrade, MysteryGift, NoticeType, Raid, RewardGift,
+    Ritual, SubGift, SubPlan, Subscription, UserNotice, UserNoticeEvent, ViewerMilestone,
+};
 
 mod user_state;
 pub use user_state::UserState;
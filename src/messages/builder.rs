@@ -0,0 +1,673 @@
+use crate::{
+    irc::{tags::escape_str, IrcMessage},
+    prelude::*,
+    twitch::{color::RGB, BadgeKind},
+    FromIrcMessage, MaybeOwned, MessageError,
+};
+
+// the parts of a line, which the builders fill in
+#[derive(Debug, Default, Clone)]
+struct Line {
+    tags: Vec<(String, String)>,
+    // the nick of the user who sent it, otherwise it's from the server
+    sender: Option<String>,
+    args: Vec<String>,
+    data: Option<String>,
+}
+
+impl Line {
+    // a later tag replaces an earlier one
+    fn tag(&mut self, key: &str, value: impl ToString) {
+        let value = value.to_string();
+        match self.tags.iter_mut().find(|(k, _)| k == key) {
+            Some((_, v)) => *v = value,
+            None => self.tags.push((key.to_string(), value)),
+        }
+    }
+
+    // appends to a comma-separated tag, like `badges`
+    fn append_tag(&mut self, key: &str, value: &str) {
+        match self.tags.iter_mut().find(|(k, _)| k == key) {
+            Some((_, v)) if !v.is_empty() => {
+                v.push(',');
+                v.push_str(value)
+            }
+            Some((_, v)) => *v = value.to_string(),
+            None => self.tags.push((key.to_string(), value.to_string())),
+        }
+    }
+
+    fn encode(&self, command: &str) -> String {
+        let mut line = String::new();
+        if !self.tags.is_empty() {
+            line.push('@');
+            for (i, (key, value)) in self.tags.iter().enumerate() {
+                if i > 0 {
+                    line.push(';');
+                }
+                line.push_str(&escape_str(key));
+                line.push('=');
+                line.push_str(&escape_str(value));
+            }
+            line.push(' ');
+        }
+
+        match &self.sender {
+            Some(name) => line.push_str(&format!(":{0}!{0}@{0}.tmi.twitch.tv ", name)),
+            None => line.push_str(":tmi.twitch.tv "),
+        }
+        line.push_str(command);
+
+        for arg in &self.args {
+            line.push(' ');
+            line.push_str(arg);
+        }
+        if let Some(data) = &self.data {
+            line.push_str(" :");
+            line.push_str(data);
+        }
+        line.push_str("\r\n");
+        line
+    }
+
+    fn build<T>(&self, command: &str) -> Result<T, MessageError>
+    where
+        T: FromIrcMessage<'static, Error = MessageError>,
+    {
+        let msg = IrcMessage::parse(MaybeOwned::Owned(self.encode(command).into()))?;
+        T::from_irc(msg)
+    }
+}
+
+// twitch prefixes channels with a '#'
+fn channel(name: &str) -> String {
+    if name.starts_with('#') {
+        name.to_string()
+    } else {
+        format!("#{}", name)
+    }
+}
+
+fn badge_name(kind: &BadgeKind<'_>) -> String {
+    crate::twitch::Badge {
+        kind: kind.clone(),
+        data: "",
+    }
+    .kind_raw()
+    .to_string()
+}
+
+macro_rules! builder_setters {
+    () => {
+        /// Set this tag, replacing any previous value. The value is escaped.
+        pub fn tag(mut self, key: &str, value: impl ToString) -> Self {
+            self.line.tag(key, value);
+            self
+        }
+    };
+
+    (@user) => {
+        builder_setters!();
+
+        /// Add this badge, with version `1`
+        pub fn badge(self, kind: BadgeKind<'_>) -> Self {
+            self.badge_version(kind, "1")
+        }
+
+        /// Add this badge, with this version (e.g. the bits tier, or the subscriber badge's months)
+        pub fn badge_version(mut self, kind: BadgeKind<'_>, version: &str) -> Self {
+            let badge = format!("{}/{}", badge_name(&kind), version);
+            self.line.append_tag("badges", &badge);
+            self
+        }
+
+        /// The sender's color
+        pub fn color(mut self, color: RGB) -> Self {
+            self.line.tag("color", color);
+            self
+        }
+
+        /// The sender's display name
+        pub fn display_name(mut self, display_name: &str) -> Self {
+            self.line.tag("display-name", display_name);
+            self
+        }
+
+        /// The sender's user id
+        pub fn user_id(mut self, user_id: u64) -> Self {
+            self.line.tag("user-id", user_id);
+            self
+        }
+
+        /// When Twitch sent this, in milliseconds since the unix epoch
+        pub fn tmi_sent_ts(mut self, timestamp: u64) -> Self {
+            self.line.tag("tmi-sent-ts", timestamp);
+            self
+        }
+    };
+}
+
+/// A builder for a synthetic [Privmsg](super::Privmsg), e.g. to test your bot's handlers with, from [Privmsg::builder()](super::Privmsg::builder()).
+///
+/// ```
+/// # use twitchchat::{messages::Privmsg, twitch::BadgeKind};
+/// let msg = Privmsg::builder()
+///     .channel("#museun")
+///     .sender("shaken_bot")
+///     .data("hello Kappa")
+///     .badge(BadgeKind::Moderator)
+///     .emote(25, "Kappa")
+///     .build()
+///     .unwrap();
+///
+/// assert_eq!(msg.channel(), "#museun");
+/// assert_eq!(msg.name(), "shaken_bot");
+/// assert_eq!(msg.data(), "hello Kappa");
+/// assert!(msg.is_moderator());
+/// assert_eq!(msg.strip_emotes(), "hello");
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct PrivmsgBuilder {
+    line: Line,
+    channel: Option<String>,
+    data: String,
+    action: bool,
+    emotes: Vec<(usize, String)>,
+}
+
+impl super::Privmsg<'static> {
+    /// Create a builder for a synthetic message
+    pub fn builder() -> PrivmsgBuilder {
+        PrivmsgBuilder::default()
+    }
+}
+
+impl PrivmsgBuilder {
+    builder_setters!(@user);
+
+    /// The channel it was sent to. The `#` is added if it's missing
+    pub fn channel(mut self, name: &str) -> Self {
+        self.channel.replace(channel(name));
+        self
+    }
+
+    /// The name of the user who sent it
+    pub fn sender(mut self, name: &str) -> Self {
+        self.line.sender.replace(name.to_string());
+        self
+    }
+
+    /// The text of the message
+    pub fn data(mut self, data: &str) -> Self {
+        self.data = data.to_string();
+        self
+    }
+
+    /// Send it as an action (`/me`)
+    pub fn action(mut self, action: bool) -> Self {
+        self.action = action;
+        self
+    }
+
+    /// Mark every occurrence of this word, in the data, as this emote
+    pub fn emote(mut self, id: usize, name: &str) -> Self {
+        self.emotes.push((id, name.to_string()));
+        self
+    }
+
+    /// The id of the message
+    pub fn id(mut self, id: &str) -> Self {
+        self.line.tag("id", id);
+        self
+    }
+
+    /// The id of the channel
+    pub fn room_id(mut self, room_id: u64) -> Self {
+        self.line.tag("room-id", room_id);
+        self
+    }
+
+    /// How many bits were cheered with it
+    pub fn bits(mut self, bits: u64) -> Self {
+        self.line.tag("bits", bits);
+        self
+    }
+
+    /// Build the message.
+    ///
+    /// This fails if the channel or the sender is missing.
+    pub fn build(self) -> Result<super::Privmsg<'static>, MessageError> {
+        let Self {
+            mut line,
+            channel,
+            data,
+            action,
+            emotes,
+        } = self;
+
+        if !emotes.is_empty() {
+            line.tag("emotes", emote_ranges(&data, &emotes));
+        }
+        line.args.extend(channel);
+        line.data = Some(if action {
+            format!("\x01ACTION {}\x01", data)
+        } else {
+            data
+        });
+        line.build(IrcMessage::PRIVMSG)
+    }
+}
+
+// the `emotes` tag for each word in `data` which is one of the `emotes`
+fn emote_ranges(data: &str, emotes: &[(usize, String)]) -> String {
+    let mut words: Vec<(usize, usize)> = Vec::new();
+    let mut start = 0;
+    for (i, word) in data.split(' ').enumerate() {
+        if i > 0 {
+            start += 1;
+        }
+        let len = word.chars().count();
+        words.push((start, len));
+        start += len;
+    }
+
+    let words: Vec<_> = data.split(' ').zip(words).collect();
+    emotes
+        .iter()
+        .filter_map(|(id, name)| {
+            let ranges: Vec<_> = words
+                .iter()
+                .filter(|(word, _)| word == name)
+                // twitch uses an inclusive end, in characters
+                .map(|(_, (start, len))| format!("{}-{}", start, start + len - 1))
+                .collect();
+            if ranges.is_empty() {
+                return None;
+            }
+            Some(format!("{}:{}", id, ranges.join(",")))
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// A builder for a synthetic [Whisper](super::Whisper), from [Whisper::builder()](super::Whisper::builder()).
+///
+/// ```
+/// # use twitchchat::messages::Whisper;
+/// let msg = Whisper::builder()
+///     .to("shaken_bot")
+///     .sender("museun")
+///     .data("hello")
+///     .build()
+///     .unwrap();
+/// assert_eq!(msg.name(), "museun");
+/// assert_eq!(msg.data(), "hello");
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct WhisperBuilder {
+    line: Line,
+    to: Option<String>,
+}
+
+impl super::Whisper<'static> {
+    /// Create a builder for a synthetic message
+    pub fn builder() -> WhisperBuilder {
+        WhisperBuilder::default()
+    }
+}
+
+impl WhisperBuilder {
+    builder_setters!(@user);
+
+    /// The name of the user it was sent to
+    pub fn to(mut self, name: &str) -> Self {
+        self.to.replace(name.to_string());
+        self
+    }
+
+    /// The name of the user who sent it
+    pub fn sender(mut self, name: &str) -> Self {
+        self.line.sender.replace(name.to_string());
+        self
+    }
+
+    /// The text of the message
+    pub fn data(mut self, data: &str) -> Self {
+        self.line.data.replace(data.to_string());
+        self
+    }
+
+    /// Build the message.
+    ///
+    /// This fails if the recipient, the sender or the data is missing.
+    pub fn build(self) -> Result<super::Whisper<'static>, MessageError> {
+        let Self { mut line, to } = self;
+        line.args.extend(to);
+        line.build(IrcMessage::WHISPER)
+    }
+}
+
+macro_rules! membership_builder {
+    ($(#[$meta:meta])* $builder:ident => $ty:ident, $command:expr) => {
+        $(#[$meta])*
+        #[derive(Debug, Default, Clone)]
+        pub struct $builder {
+            line: Line,
+            channel: Option<String>,
+        }
+
+        impl super::$ty<'static> {
+            /// Create a builder for a synthetic message
+            pub fn builder() -> $builder {
+                $builder::default()
+            }
+        }
+
+        impl $builder {
+            /// The channel. The `#` is added if it's missing
+            pub fn channel(mut self, name: &str) -> Self {
+                self.channel.replace(channel(name));
+                self
+            }
+
+            /// The name of the user
+            pub fn name(mut self, name: &str) -> Self {
+                self.line.sender.replace(name.to_string());
+                self
+            }
+
+            /// Build the message.
+            ///
+            /// This fails if the channel or the name is missing.
+            pub fn build(self) -> Result<super::$ty<'static>, MessageError> {
+                let Self { mut line, channel } = self;
+                line.args.extend(channel);
+                line.build($command)
+            }
+        }
+    };
+}
+
+membership_builder!(
+    /// A builder for a synthetic [Join](super::Join), from [Join::builder()](super::Join::builder()).
+    JoinBuilder => Join, IrcMessage::JOIN
+);
+
+membership_builder!(
+    /// A builder for a synthetic [Part](super::Part), from [Part::builder()](super::Part::builder()).
+    PartBuilder => Part, IrcMessage::PART
+);
+
+/// A builder for a synthetic [Notice](super::Notice), from [Notice::builder()](super::Notice::builder()).
+///
+/// ```
+/// # use twitchchat::messages::{MessageId, Notice};
+/// let msg = Notice::builder()
+///     .channel("museun")
+///     .msg_id("msg_ratelimit")
+///     .message("Your message was not sent because you are sending messages too quickly.")
+///     .build()
+///     .unwrap();
+/// assert_eq!(msg.msg_id(), Some(MessageId::MsgRatelimit));
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct NoticeBuilder {
+    line: Line,
+    channel: Option<String>,
+}
+
+impl super::Notice<'static> {
+    /// Create a builder for a synthetic message
+    pub fn builder() -> NoticeBuilder {
+        NoticeBuilder::default()
+    }
+}
+
+impl NoticeBuilder {
+    builder_setters!();
+
+    /// The channel. The `#` is added if it's missing. Without one, it's a global notice (`*`)
+    pub fn channel(mut self, name: &str) -> Self {
+        self.channel.replace(channel(name));
+        self
+    }
+
+    /// The `msg-id`, e.g. `msg_ratelimit`
+    pub fn msg_id(mut self, msg_id: &str) -> Self {
+        self.line.tag("msg-id", msg_id);
+        self
+    }
+
+    /// The message from the server
+    pub fn message(mut self, message: &str) -> Self {
+        self.line.data.replace(message.to_string());
+        self
+    }
+
+    /// Build the message.
+    ///
+    /// This fails if the message is missing.
+    pub fn build(self) -> Result<super::Notice<'static>, MessageError> {
+        let Self { mut line, channel } = self;
+        line.args.push(channel.unwrap_or_else(|| "*".to_string()));
+        line.build(IrcMessage::NOTICE)
+    }
+}
+
+/// A builder for a synthetic [ClearChat](super::ClearChat), e.g. a timeout or a ban, from [ClearChat::builder()](super::ClearChat::builder()).
+///
+/// ```
+/// # use twitchchat::messages::ClearChat;
+/// let msg = ClearChat::builder()
+///     .channel("museun")
+///     .name("spammer")
+///     .ban_duration(600)
+///     .build()
+///     .unwrap();
+/// assert_eq!(msg.name(), Some("spammer"));
+/// assert_eq!(msg.ban_duration(), Some(600));
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct ClearChatBuilder {
+    line: Line,
+    channel: Option<String>,
+}
+
+impl super::ClearChat<'static> {
+    /// Create a builder for a synthetic message
+    pub fn builder() -> ClearChatBuilder {
+        ClearChatBuilder::default()
+    }
+}
+
+impl ClearChatBuilder {
+    builder_setters!();
+
+    /// The channel. The `#` is added if it's missing
+    pub fn channel(mut self, name: &str) -> Self {
+        self.channel.replace(channel(name));
+        self
+    }
+
+    /// The user who was timed out or banned. Without one, the whole chat was cleared
+    pub fn name(mut self, name: &str) -> Self {
+        self.line.data.replace(name.to_string());
+        self
+    }
+
+    /// How long the timeout is, in seconds. Without one, it's a ban
+    pub fn ban_duration(mut self, seconds: u64) -> Self {
+        self.line.tag("ban-duration", seconds);
+        self
+    }
+
+    /// The id of the channel
+    pub fn room_id(mut self, room_id: u64) -> Self {
+        self.line.tag("room-id", room_id);
+        self
+    }
+
+    /// The id of the user who was timed out or banned
+    pub fn target_user_id(mut self, user_id: u64) -> Self {
+        self.line.tag("target-user-id", user_id);
+        self
+    }
+
+    /// Build the message.
+    ///
+    /// This fails if the channel is missing.
+    pub fn build(self) -> Result<super::ClearChat<'static>, MessageError> {
+        let Self { mut line, channel } = self;
+        line.args.extend(channel);
+        line.build(IrcMessage::CLEAR_CHAT)
+    }
+}
+
+/// A builder for a synthetic [ClearMsg](super::ClearMsg), a deleted message, from [ClearMsg::builder()](super::ClearMsg::builder()).
+///
+/// ```
+/// # use twitchchat::messages::ClearMsg;
+/// let msg = ClearMsg::builder()
+///     .channel("museun")
+///     .login("spammer")
+///     .target_msg_id("abc-123")
+///     .message("buy followers")
+///     .build()
+///     .unwrap();
+/// assert_eq!(msg.login(), Some("spammer"));
+/// assert_eq!(msg.target_msg_id(), Some("abc-123"));
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct ClearMsgBuilder {
+    line: Line,
+    channel: Option<String>,
+}
+
+impl super::ClearMsg<'static> {
+    /// Create a builder for a synthetic message
+    pub fn builder() -> ClearMsgBuilder {
+        ClearMsgBuilder::default()
+    }
+}
+
+impl ClearMsgBuilder {
+    builder_setters!();
+
+    /// The channel. The `#` is added if it's missing
+    pub fn channel(mut self, name: &str) -> Self {
+        self.channel.replace(channel(name));
+        self
+    }
+
+    /// The name of the user whose message was deleted
+    pub fn login(mut self, login: &str) -> Self {
+        self.line.tag("login", login);
+        self
+    }
+
+    /// The id of the deleted message
+    pub fn target_msg_id(mut self, id: &str) -> Self {
+        self.line.tag("target-msg-id", id);
+        self
+    }
+
+    /// The text of the deleted message
+    pub fn message(mut self, message: &str) -> Self {
+        self.line.data.replace(message.to_string());
+        self
+    }
+
+    /// Build the message.
+    ///
+    /// This fails if the channel or the message is missing.
+    pub fn build(self) -> Result<super::ClearMsg<'static>, MessageError> {
+        let Self { mut line, channel } = self;
+        line.args.extend(channel);
+        line.build(IrcMessage::CLEAR_MSG)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messages::*;
+
+    #[test]
+    fn privmsg() {
+        let msg = Privmsg::builder()
+            .channel("museun")
+            .sender("shaken_bot")
+            .data("Kappa hello é Kappa")
+            .badge(BadgeKind::Moderator)
+            .badge_version(BadgeKind::Subscriber, "12")
+            .color(RGB(0xFF, 0, 0))
+            .display_name("Shaken Bot")
+            .user_id(42)
+            .emote(25, "Kappa")
+            .emote(1902, "Keepo")
+            .tag("custom", "a value; with spaces")
+            .build()
+            .unwrap();
+
+        assert_eq!(msg.channel(), "#museun");
+        assert_eq!(msg.name(), "shaken_bot");
+        assert_eq!(msg.data(), "Kappa hello é Kappa");
+        assert!(msg.is_moderator());
+        assert_eq!(msg.badges().len(), 2);
+        assert_eq!(msg.color().unwrap().rgb, RGB(0xFF, 0, 0));
+        assert_eq!(msg.display_name().as_deref(), Some("Shaken Bot"));
+        assert_eq!(msg.user_id(), Some(42));
+        assert_eq!(msg.tags().get("emotes"), Some("25:0-4,14-18"));
+        assert_eq!(msg.strip_emotes(), "hello é");
+        assert_eq!(
+            msg.tags().get_unescaped("custom").as_deref(),
+            Some("a value; with spaces")
+        );
+
+        let msg = Privmsg::builder()
+            .channel("#museun")
+            .sender("shaken_bot")
+            .data("waves")
+            .action(true)
+            .build()
+            .unwrap();
+        assert!(msg.is_action());
+        assert_eq!(msg.data(), "waves");
+
+        assert!(Privmsg::builder().sender("shaken_bot").build().is_err());
+        assert!(Privmsg::builder().channel("museun").build().is_err());
+    }
+
+    #[test]
+    fn others() {
+        let msg = Join::builder()
+            .channel("museun")
+            .name("shaken_bot")
+            .build()
+            .unwrap();
+        assert_eq!((msg.channel(), msg.name()), ("#museun", "shaken_bot"));
+
+        let msg = Part::builder()
+            .channel("museun")
+            .name("shaken_bot")
+            .build()
+            .unwrap();
+        assert_eq!((msg.channel(), msg.name()), ("#museun", "shaken_bot"));
+
+        let msg = Notice::builder().message("hello").build().unwrap();
+        assert_eq!((msg.channel(), msg.message()), ("*", "hello"));
+
+        let msg = ClearChat::builder().channel("museun").build().unwrap();
+        assert!(msg.name().is_none());
+        assert!(matches!(msg.action(), ModerationAction::ChatCleared { .. }));
+
+        let msg = Whisper::builder()
+            .to("shaken_bot")
+            .sender("museun")
+            .data("hi")
+            .badge(BadgeKind::Turbo)
+            .build()
+            .unwrap();
+        assert!(msg.is_turbo());
+    }
+}
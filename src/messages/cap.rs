@@ -36,6 +36,19 @@ impl<'a> Cap<'a> {
             Capability::NotAcknowledged(cap)
         }
     }
+
+    /// Each of the capabilities in this message.
+    ///
+    /// Twitch can acknowledge (or reject) several capabilities in a single message
+    pub fn capabilities(&self) -> impl Iterator<Item = Capability<'_>> + '_ {
+        let acknowledged = self.acknowledged;
+        self.raw[self.capability]
+            .split_whitespace()
+            .map(move |cap| match acknowledged {
+                true => Capability::Acknowledged(cap),
+                false => Capability::NotAcknowledged(cap),
+            })
+    }
 }
 
 impl<'a> FromIrcMessage<'a> for Cap<'a> {
@@ -96,6 +109,30 @@ mod tests {
         }
     }
 
+    #[test]
+    fn cap_many() {
+        let input = ":tmi.twitch.tv CAP * ACK :twitch.tv/tags twitch.tv/commands\r\n";
+        for msg in parse(input).map(|s| s.unwrap()) {
+            let msg = Cap::from_irc(msg).unwrap();
+            assert_eq!(
+                msg.capabilities().collect::<Vec<_>>(),
+                vec![
+                    Capability::Acknowledged("twitch.tv/tags"),
+                    Capability::Acknowledged("twitch.tv/commands"),
+                ]
+            );
+        }
+
+        let input = ":tmi.twitch.tv CAP * NAK :twitch.tv/foo\r\n";
+        for msg in parse(input).map(|s| s.unwrap()) {
+            let msg = Cap::from_irc(msg).unwrap();
+            assert_eq!(
+                msg.capabilities().collect::<Vec<_>>(),
+                vec![Capability::NotAcknowledged("twitch.tv/foo")]
+            );
+        }
+    }
+
     #[test]
     fn cap_failed() {
         let input = ":tmi.twitch.tv CAP * NAK :foobar\r\n";
@@ -33,6 +33,23 @@ impl<'a> ClearChat<'a> {
     pub fn room_id(&self) -> Option<&str> {
         self.tags().get("room-id")
     }
+
+    /// The id of the user that was being purged
+    pub fn target_user_id(&self) -> Option<u64> {
+        self.tags().get_parsed("target-user-id")
+    }
+
+    /// When this happened
+    #[cfg(feature = "std")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+    pub fn sent_at(&self) -> Option<std::time::SystemTime> {
+        self.tags().get_timestamp("tmi-sent-ts").ok()
+    }
+
+    /// The moderation action this message represents
+    pub fn action(&self) -> super::ModerationAction<'_> {
+        self.into()
+    }
 }
 
 impl<'a> FromIrcMessage<'a> for ClearChat<'a> {
@@ -74,7 +91,11 @@ serde_struct!(ClearChat {
     raw,
     tags,
     channel,
-    name
+    name,
+    room_id,
+    target_user_id,
+    ban_duration,
+    action,
 });
 
 #[cfg(test)]
@@ -32,6 +32,18 @@ impl<'a> ClearMsg<'a> {
     pub fn target_msg_id(&self) -> Option<&str> {
         self.tags().get("target-msg-id")
     }
+
+    /// When the message was deleted
+    #[cfg(feature = "std")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+    pub fn sent_at(&self) -> Option<std::time::SystemTime> {
+        self.tags().get_timestamp("tmi-sent-ts").ok()
+    }
+
+    /// The moderation action this message represents
+    pub fn action(&self) -> super::ModerationAction<'_> {
+        self.into()
+    }
 }
 
 impl<'a> FromIrcMessage<'a> for ClearMsg<'a> {
@@ -75,7 +87,8 @@ serde_struct!(ClearMsg {
     channel,
     message,
     login,
-    target_msg_id
+    target_msg_id,
+    action,
 });
 
 #[cfg(test)]
@@ -7,7 +7,10 @@ use {super::*, crate::*};
 #[derive(Debug, Clone, PartialEq)]
 #[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
 pub enum Commands<'a> {
-    /// An raw event occured
+    /// A message this crate doesn't have a type for.
+    ///
+    /// Nothing is dropped: new Twitch messages show up here, with all of their tags, prefix and args.
+    /// See [AsyncRunner::raw_events()](crate::AsyncRunner::raw_events()) to parse them into your own type.
     Raw(IrcMessage<'a>),
     /// A capabilities event occured
     IrcReady(IrcReady<'a>),
@@ -72,6 +75,31 @@ impl<'a> Commands<'a> {
             Self::Whisper(msg) => msg.raw(),
         }
     }
+
+    /// Get the channel this message was sent to, if it was sent to a channel
+    pub fn channel(&self) -> Option<&str> {
+        let channel = match self {
+            Self::Raw(msg) => msg.nth_arg(0)?,
+            Self::ClearChat(msg) => msg.channel(),
+            Self::ClearMsg(msg) => msg.channel(),
+            Self::HostTarget(msg) => msg.source(),
+            Self::Join(msg) => msg.channel(),
+            Self::Notice(msg) => msg.channel(),
+            Self::Part(msg) => msg.channel(),
+            Self::Privmsg(msg) => msg.channel(),
+            Self::RoomState(msg) => msg.channel(),
+            Self::UserNotice(msg) => msg.channel(),
+            Self::UserState(msg) => msg.channel(),
+            _ => return None,
+        };
+        // notices can be sent to '*'
+        Some(channel).filter(|s| s.starts_with('#'))
+    }
+
+    /// Get the channel this message was sent to as a [ChannelName](twitch::ChannelName), if it was sent to a channel
+    pub fn channel_name(&self) -> Option<twitch::ChannelName> {
+        self.channel().map(Into::into)
+    }
 }
 
 impl<'a> IntoOwned<'a> for Commands<'a> {
@@ -176,6 +204,35 @@ macro_rules! from_other {
 
 type Raw<'a> = IrcMessage<'a>;
 
+/// A message type that can be picked out of [Commands].
+///
+/// This is implemented for [Commands] itself and all of the message types it wraps.
+pub trait FromCommands<'a>: Sized {
+    /// Try to get this type out of a [Commands], cloning it.
+    ///
+    /// Returns `None` if the message was some other type.
+    fn from_commands(msg: &Commands<'a>) -> Option<Self>;
+}
+
+impl<'a> FromCommands<'a> for Commands<'a> {
+    fn from_commands(msg: &Commands<'a>) -> Option<Self> {
+        Some(msg.clone())
+    }
+}
+
+macro_rules! from_commands {
+    ($($ident:tt)*) => {
+        $(impl<'a> FromCommands<'a> for $ident<'a> {
+            fn from_commands(msg: &Commands<'a>) -> Option<Self> {
+                match msg {
+                    Commands::$ident(msg) => Some(msg.clone()),
+                    _ => None,
+                }
+            }
+        })*
+    };
+}
+
 from_other! {
     Raw
     IrcReady
@@ -198,6 +255,28 @@ from_other! {
     Whisper
 }
 
+from_commands! {
+    Raw
+    IrcReady
+    Ready
+    Cap
+    ClearChat
+    ClearMsg
+    GlobalUserState
+    HostTarget
+    Join
+    Notice
+    Part
+    Ping
+    Pong
+    Privmsg
+    Reconnect
+    RoomState
+    UserNotice
+    UserState
+    Whisper
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -217,4 +296,44 @@ mod tests {
         let all = Commands::from_irc(msg).unwrap();
         assert!(matches!(all, Commands::Privmsg { .. }));
     }
+
+    #[test]
+    fn from_commands() {
+        let input = ":test!test@test PRIVMSG #museun :this is a test\r\n";
+        let msg = IrcMessage::parse(MaybeOwned::Borrowed(input)).unwrap();
+        let all = Commands::from_irc(msg).unwrap();
+
+        let pm = Privmsg::from_commands(&all).unwrap();
+        assert_eq!(pm.data(), "this is a test");
+        assert!(Join::from_commands(&all).is_none());
+        assert_eq!(Commands::from_commands(&all).unwrap(), all);
+    }
+
+    #[test]
+    fn channel() {
+        let tests = &[
+            (
+                ":test!test@test PRIVMSG #museun :this is a test\r\n",
+                Some("#museun"),
+            ),
+            (":test!test@test JOIN #museun\r\n", Some("#museun")),
+            (
+                ":tmi.twitch.tv HOSTTARGET #museun :- 0\r\n",
+                Some("#museun"),
+            ),
+            (
+                ":tmi.twitch.tv NOTICE * :Login authentication failed\r\n",
+                None,
+            ),
+            (":tmi.twitch.tv FOO #museun :bar\r\n", Some("#museun")),
+            ("PING :1234567890\r\n", None),
+        ];
+
+        for (input, expected) in tests {
+            let msg = IrcMessage::parse(MaybeOwned::Borrowed(input)).unwrap();
+            let all = Commands::from_irc(msg).unwrap();
+            assert_eq!(all.channel(), *expected, "{}", input);
+            assert_eq!(all.channel_name(), expected.map(twitch::ChannelName::from));
+        }
+    }
 }
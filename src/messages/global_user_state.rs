@@ -1,3 +1,4 @@
+use crate::prelude::*;
 use crate::{irc::*, twitch::*, IntoOwned, MaybeOwned, Validator};
 
 /// Sent on successful login, if both **TAGS** and **COMMANDS** capabilities have been sent beforehand.
@@ -46,6 +47,24 @@ impl<'a> GlobalUserState<'a> {
             .unwrap_or_default()
     }
 
+    /// Metadata related to your badges
+    pub fn badge_info(&self) -> Vec<BadgeInfo<'_>> {
+        self.tags()
+            .get("badge-info")
+            .map(|s| s.split(',').filter_map(Badge::parse).collect())
+            .unwrap_or_default()
+    }
+
+    /// Any badges you have, with their versions and metadata parsed
+    pub fn typed_badges(&self) -> Vec<TypedBadge<'_>> {
+        parse_typed_badges(&self.tags())
+    }
+
+    /// Your user type, e.g. `staff`. This is empty for normal users
+    pub fn user_type(&self) -> Option<&str> {
+        self.tags().get("user-type")
+    }
+
     /// Your user-id -- only available if you have TAGs enabled
     pub fn user_id(&self) -> Option<&str> {
         self.user_id.as_deref()
@@ -80,14 +99,13 @@ impl<'a> FromIrcMessage<'a> for GlobalUserState<'a> {
             .map(MaybeOwned::into_owned);
 
         let display_name = tags
-            .get("display-name")
-            .map(MaybeOwned::from)
+            .get_unescaped("display-name")
             .map(MaybeOwned::into_owned);
 
         let color = tags
             .get("color")
             .filter(|s| !s.is_empty())
-            .map(std::str::FromStr::from_str)
+            .map(core::str::FromStr::from_str)
             .transpose()
             .map_err(|err| MessageError::CannotParseTag {
                 name: "color".into(),
@@ -135,6 +153,7 @@ serde_struct!(GlobalUserState {
     color,
     badges,
     emote_sets,
+    badge_info,
 });
 
 #[cfg(test)]
@@ -161,6 +180,22 @@ mod tests {
             assert_eq!(msg.color, color);
             assert_eq!(msg.color(), color);
             assert_eq!(msg.emote_sets(), vec!["0"]);
+            assert_eq!(msg.user_type(), Some(""));
+        }
+    }
+
+    #[test]
+    fn global_user_state_badges() {
+        let input = "@badge-info=subscriber/4;badges=subscriber/3,premium/1;color=;display-name=shaken_bot;emote-sets=0,33,50;user-id=241015868;user-type= :tmi.twitch.tv GLOBALUSERSTATE\r\n";
+        for msg in parse(input).map(|s| s.unwrap()) {
+            let msg = GlobalUserState::from_irc(msg).unwrap();
+            assert_eq!(msg.badges().len(), 2);
+            assert_eq!(msg.badge_info()[0].data, "4");
+            assert_eq!(
+                msg.typed_badges(),
+                vec![TypedBadge::Subscriber { months: 4 }, TypedBadge::Premium]
+            );
+            assert_eq!(msg.emote_sets(), vec!["0", "33", "50"]);
         }
     }
 
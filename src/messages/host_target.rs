@@ -34,6 +34,11 @@ impl<'a> HostTarget<'a> {
         self.viewers
     }
 
+    /// The channel being hosted (without its leading `#`), or `None` if this ends hosting
+    pub fn target(&self) -> Option<&str> {
+        self.target.map(|index| &self.raw[index])
+    }
+
     /// What kind of event this was. e.g. `Start` or `End`
     pub fn host_target_kind(&self) -> HostTargetKind<'_> {
         match self.target {
@@ -120,6 +125,7 @@ mod tests {
             let ht = HostTarget::from_irc(msg).unwrap();
             assert_eq!(ht.source(), "#shaken_bot");
             assert_eq!(ht.viewers().unwrap(), 1024);
+            assert_eq!(ht.target(), Some("museun"));
             assert_eq!(
                 ht.host_target_kind(),
                 HostTargetKind::Start { target: "museun" }
@@ -149,6 +155,18 @@ mod tests {
             assert_eq!(ht.source(), "#shaken_bot");
             assert_eq!(ht.viewers().unwrap(), 1024);
             assert_eq!(ht.host_target_kind(), HostTargetKind::End);
+            assert_eq!(ht.target(), None);
+        }
+    }
+
+    #[test]
+    fn host_target_end_without_viewers() {
+        let input = ":tmi.twitch.tv HOSTTARGET #shaken_bot :-\r\n";
+        for msg in parse(input).map(|s| s.unwrap()) {
+            let ht = HostTarget::from_irc(msg).unwrap();
+            assert_eq!(ht.source(), "#shaken_bot");
+            assert!(ht.viewers().is_none());
+            assert_eq!(ht.host_target_kind(), HostTargetKind::End);
         }
     }
 }
@@ -0,0 +1,202 @@
+use super::{ClearChat, ClearMsg, Commands};
+use core::time::Duration;
+
+/// A moderation event, from either a `CLEARCHAT` or a `CLEARMSG`
+///
+/// ```
+/// # use twitchchat::{FromIrcMessage as _, irc, messages::{ClearChat, ModerationAction}};
+/// let input = "@ban-duration=600;target-user-id=23196011 :tmi.twitch.tv CLEARCHAT #museun :shaken_bot\r\n";
+/// let msg = ClearChat::from_irc(irc::parse(input).next().unwrap().unwrap()).unwrap();
+/// match msg.action() {
+///     ModerationAction::TimedOut { login, duration, .. } => {
+///         assert_eq!(login, "shaken_bot");
+///         assert_eq!(duration.as_secs(), 600);
+///     }
+///     action => panic!("unexpected action: {:?}", action),
+/// }
+/// ```
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+pub enum ModerationAction<'a> {
+    /// A single message was deleted
+    MessageDeleted {
+        /// The channel the message was deleted from
+        channel: &'a str,
+        /// The login of the user who sent the message
+        login: Option<&'a str>,
+        /// The id of the message that was deleted
+        target_msg_id: Option<&'a str>,
+        /// The message that was deleted
+        message: Option<&'a str>,
+    },
+    /// A user was timed out
+    TimedOut {
+        /// The channel the user was timed out on
+        channel: &'a str,
+        /// The login of the user
+        login: &'a str,
+        /// The id of the user
+        target_user_id: Option<u64>,
+        /// How long the user was timed out for
+        duration: Duration,
+    },
+    /// A user was permanently banned
+    Banned {
+        /// The channel the user was banned from
+        channel: &'a str,
+        /// The login of the user
+        login: &'a str,
+        /// The id of the user
+        target_user_id: Option<u64>,
+    },
+    /// All of the messages in the channel were cleared
+    ChatCleared {
+        /// The channel that was cleared
+        channel: &'a str,
+    },
+}
+
+impl<'a> ModerationAction<'a> {
+    /// Get the moderation action from this message, if it is a `CLEARCHAT` or `CLEARMSG`
+    pub fn from_commands(msg: &'a Commands<'_>) -> Option<Self> {
+        match msg {
+            Commands::ClearChat(msg) => Some(msg.action()),
+            Commands::ClearMsg(msg) => Some(msg.action()),
+            _ => None,
+        }
+    }
+
+    /// The channel this action happened on
+    pub fn channel(&self) -> &'a str {
+        match self {
+            Self::MessageDeleted { channel, .. }
+            | Self::TimedOut { channel, .. }
+            | Self::Banned { channel, .. }
+            | Self::ChatCleared { channel } => channel,
+        }
+    }
+
+    /// The login of the user this action targeted, if any
+    pub fn login(&self) -> Option<&'a str> {
+        match self {
+            Self::MessageDeleted { login, .. } => *login,
+            Self::TimedOut { login, .. } | Self::Banned { login, .. } => Some(login),
+            Self::ChatCleared { .. } => None,
+        }
+    }
+}
+
+impl<'a> From<&'a ClearChat<'_>> for ModerationAction<'a> {
+    fn from(msg: &'a ClearChat<'_>) -> Self {
+        let channel = msg.channel();
+        let login = match msg.name() {
+            Some(login) => login,
+            None => return Self::ChatCleared { channel },
+        };
+
+        let target_user_id = msg.target_user_id();
+        match msg.ban_duration() {
+            Some(duration) => Self::TimedOut {
+                channel,
+                login,
+                target_user_id,
+                duration: Duration::from_secs(duration),
+            },
+            None => Self::Banned {
+                channel,
+                login,
+                target_user_id,
+            },
+        }
+    }
+}
+
+impl<'a> From<&'a ClearMsg<'_>> for ModerationAction<'a> {
+    fn from(msg: &'a ClearMsg<'_>) -> Self {
+        Self::MessageDeleted {
+            channel: msg.channel(),
+            login: msg.login(),
+            target_msg_id: msg.target_msg_id(),
+            message: msg.message(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{irc::parse, FromIrcMessage as _};
+
+    fn action(input: &str, check: impl Fn(Option<ModerationAction<'_>>)) {
+        let msg = Commands::from_irc(parse(input).next().unwrap().unwrap()).unwrap();
+        check(ModerationAction::from_commands(&msg))
+    }
+
+    #[test]
+    fn clear_chat() {
+        action(":tmi.twitch.tv CLEARCHAT #museun\r\n", |action| {
+            let action = action.unwrap();
+            assert_eq!(action, ModerationAction::ChatCleared { channel: "#museun" });
+            assert_eq!(action.login(), None);
+        });
+
+        action(
+            "@room-id=1;target-user-id=2 :tmi.twitch.tv CLEARCHAT #museun :shaken_bot\r\n",
+            |action| {
+                let action = action.unwrap();
+                assert_eq!(
+                    action,
+                    ModerationAction::Banned {
+                        channel: "#museun",
+                        login: "shaken_bot",
+                        target_user_id: Some(2)
+                    }
+                );
+                assert_eq!(action.login(), Some("shaken_bot"));
+            },
+        );
+
+        action(
+            "@ban-duration=30;room-id=1 :tmi.twitch.tv CLEARCHAT #museun :shaken_bot\r\n",
+            |action| {
+                assert_eq!(
+                    action.unwrap(),
+                    ModerationAction::TimedOut {
+                        channel: "#museun",
+                        login: "shaken_bot",
+                        target_user_id: None,
+                        duration: Duration::from_secs(30),
+                    }
+                );
+            },
+        );
+    }
+
+    #[test]
+    fn clear_msg() {
+        action(
+            "@login=shaken_bot;target-msg-id=abc-123 :tmi.twitch.tv CLEARMSG #museun :hello world\r\n",
+            |action| {
+                let action = action.unwrap();
+                assert_eq!(
+                    action,
+                    ModerationAction::MessageDeleted {
+                        channel: "#museun",
+                        login: Some("shaken_bot"),
+                        target_msg_id: Some("abc-123"),
+                        message: Some("hello world"),
+                    }
+                );
+                assert_eq!(action.channel(), "#museun");
+            },
+        );
+    }
+
+    #[test]
+    fn other() {
+        action(":test!user@host PRIVMSG #museun :hello\r\n", |action| {
+            assert_eq!(action, None)
+        });
+    }
+}
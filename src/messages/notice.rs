@@ -29,6 +29,13 @@ impl<'a> Notice<'a> {
     pub fn msg_id(&self) -> Option<MessageId<'_>> {
         self.tags().get("msg-id").map(MessageId::parse)
     }
+
+    /// How bad this notice is. This is [Severity::Unknown] if it doesn't have a `msg-id`
+    pub fn severity(&self) -> Severity {
+        self.msg_id()
+            .as_ref()
+            .map_or(Severity::Unknown, MessageId::severity)
+    }
 }
 
 impl<'a> FromIrcMessage<'a> for Notice<'a> {
@@ -161,6 +168,16 @@ pub enum MessageId<'a> {
     BadUnhostError,
     /// <user> is not a moderator of this channel.
     BadUnmodMod,
+    /// <user> is not a VIP of this channel.
+    BadUnvipGranteeNotVip,
+    /// You cannot add <user> as a VIP until you complete the VIP achievement.
+    BadVipAchievementIncomplete,
+    /// <user> is already a VIP of this channel.
+    BadVipGranteeAlreadyVip,
+    /// You cannot add <user> as a VIP, they are banned from this channel.
+    BadVipGranteeBanned,
+    /// Unable to add VIP. You've reached the maximum number of VIPs for this channel.
+    BadVipMaxVipsReached,
     /// <user> is now banned from this channel.
     BanSuccess,
     /// Commands available to you in this room (use /help <command> for
@@ -260,10 +277,16 @@ pub enum MessageId<'a> {
     /// This room requires a verified email address to chat. Please verify your
     /// email at https://www.twitch.tv/settings/profile.
     MsgVerifiedEmail,
+    /// This room requires a verified phone number to chat.
+    MsgRequiresVerifiedPhoneNumber,
+    /// You must acknowledge your warning before you can chat again.
+    MsgWarned,
     /// No help available.
     NoHelp,
     /// There are no moderators of this channel.
     NoMods,
+    /// This channel does not have any VIPs.
+    NoVips,
     /// No channel is currently being hosted.
     NotHosting,
     /// You don’t have permission to perform that action.
@@ -318,6 +341,10 @@ pub enum MessageId<'a> {
     UnraidErrorUnexpected,
     /// The raid has been cancelled.
     UnraidSuccess,
+    /// This command is not available.
+    UnavailableCommand,
+    /// You have removed <user> as a VIP of this channel.
+    UnvipSuccess,
     /// Unrecognized command: <command>
     UnrecognizedCmd,
     /// The command <command> cannot be used in a chatroom.
@@ -409,6 +436,10 @@ pub enum MessageId<'a> {
     UsageUnraid,
     /// Usage: “/raid <username>” - Removes a timeout on a user.
     UsageUntimeout,
+    /// You have added <user> as a VIP of this channel.
+    VipSuccess,
+    /// The VIPs of this channel are: <list of users>
+    VipsSuccess,
     /// You have been banned from sending whispers.
     WhisperBanned,
     /// That user has been banned from receiving whispers.
@@ -472,6 +503,11 @@ impl<'a> MessageId<'a> {
             "bad_unban_no_ban" => BadUnbanNoBan,
             "bad_unhost_error" => BadUnhostError,
             "bad_unmod_mod" => BadUnmodMod,
+            "bad_unvip_grantee_not_vip" => BadUnvipGranteeNotVip,
+            "bad_vip_achievement_incomplete" => BadVipAchievementIncomplete,
+            "bad_vip_grantee_already_vip" => BadVipGranteeAlreadyVip,
+            "bad_vip_grantee_banned" => BadVipGranteeBanned,
+            "bad_vip_max_vips_reached" => BadVipMaxVipsReached,
             "ban_success" => BanSuccess,
             "cmds_available" => CmdsAvailable,
             "color_changed" => ColorChanged,
@@ -511,8 +547,11 @@ impl<'a> MessageId<'a> {
             "msg_suspended" => MsgSuspended,
             "msg_timedout" => MsgTimedout,
             "msg_verified_email" => MsgVerifiedEmail,
+            "msg_requires_verified_phone_number" => MsgRequiresVerifiedPhoneNumber,
+            "msg_warned" => MsgWarned,
             "no_help" => NoHelp,
             "no_mods" => NoMods,
+            "no_vips" => NoVips,
             "not_hosting" => NotHosting,
             "no_permission" => NoPermission,
             "r9k_off" => R9kOff,
@@ -538,6 +577,8 @@ impl<'a> MessageId<'a> {
             "unraid_error_no_active_raid" => UnraidErrorNoActiveRaid,
             "unraid_error_unexpected" => UnraidErrorUnexpected,
             "unraid_success" => UnraidSuccess,
+            "unavailable_command" => UnavailableCommand,
+            "unvip_success" => UnvipSuccess,
             "unrecognized_cmd" => UnrecognizedCmd,
             "unsupported_chatrooms_cmd" => UnsupportedChatroomsCmd,
             "untimeout_banned" => UntimeoutBanned,
@@ -570,6 +611,8 @@ impl<'a> MessageId<'a> {
             "usage_unmod" => UsageUnmod,
             "usage_unraid" => UsageUnraid,
             "usage_untimeout" => UsageUntimeout,
+            "vip_success" => VipSuccess,
+            "vips_success" => VipsSuccess,
             "whisper_banned" => WhisperBanned,
             "whisper_banned_recipient" => WhisperBannedRecipient,
             "whisper_invalid_args" => WhisperInvalidArgs,
@@ -582,6 +625,144 @@ impl<'a> MessageId<'a> {
             _ => Unknown(input),
         }
     }
+
+    /// How bad this notice is. See [Severity]
+    ///
+    /// ```
+    /// # use twitchchat::{messages::{Notice, Severity}, irc, FromIrcMessage as _};
+    /// let input = "@msg-id=msg_ratelimit :tmi.twitch.tv NOTICE #museun :Your message was not sent because you are sending messages too quickly.\r\n";
+    /// let (_, msg) = irc::parse_one(input).unwrap();
+    /// let notice = Notice::from_irc(msg).unwrap();
+    /// assert_eq!(notice.msg_id().unwrap().severity(), Severity::Rejected);
+    /// ```
+    pub fn severity(&self) -> Severity {
+        use MessageId::*;
+        match self {
+            MsgBanned
+            | MsgBadCharacters
+            | MsgChannelBlocked
+            | MsgChannelSuspended
+            | MsgDuplicate
+            | MsgEmoteonly
+            | MsgFacebook
+            | MsgFollowersonly
+            | MsgFollowersonlyFollowed
+            | MsgFollowersonlyZero
+            | MsgR9k
+            | MsgRatelimit
+            | MsgRejected
+            | MsgRejectedMandatory
+            | MsgRequiresVerifiedPhoneNumber
+            | MsgRoomNotFound
+            | MsgSlowmode
+            | MsgSubsonly
+            | MsgSuspended
+            | MsgTimedout
+            | MsgVerifiedEmail
+            | MsgWarned
+            | WhisperBanned
+            | WhisperBannedRecipient
+            | WhisperInvalidLogin
+            | WhisperInvalidSelf
+            | WhisperLimitPerMin
+            | WhisperLimitPerSec
+            | WhisperRestricted
+            | WhisperRestrictedRecipient => Severity::Rejected,
+
+            AlreadyBanned
+            | AlreadyEmoteOnlyOff
+            | AlreadyEmoteOnlyOn
+            | AlreadyR9kOff
+            | AlreadyR9kOn
+            | AlreadySubsOff
+            | AlreadySubsOn
+            | BadBanAdmin
+            | BadBanAnon
+            | BadBanBroadcaster
+            | BadBanGlobalMod
+            | BadBanMod
+            | BadBanSelf
+            | BadBanStaff
+            | BadCommercialError
+            | BadDeleteMessageBroadcaster
+            | BadDeleteMessageMod
+            | BadHostError
+            | BadHostHosting
+            | BadHostRateExceeded
+            | BadHostRejected
+            | BadHostSelf
+            | BadMarkerClient
+            | BadModBanned
+            | BadModMod
+            | BadSlowDuration
+            | BadTimeoutAdmin
+            | BadTimeoutAnon
+            | BadTimeoutBroadcaster
+            | BadTimeoutDuration
+            | BadTimeoutGlobalMod
+            | BadTimeoutMod
+            | BadTimeoutSelf
+            | BadTimeoutStaff
+            | BadUnbanNoBan
+            | BadUnhostError
+            | BadUnmodMod
+            | BadUnvipGranteeNotVip
+            | BadVipAchievementIncomplete
+            | BadVipGranteeAlreadyVip
+            | BadVipGranteeBanned
+            | BadVipMaxVipsReached
+            | InvalidUser
+            | NoPermission
+            | RaidErrorAlreadyRaiding
+            | RaidErrorForbidden
+            | RaidErrorSelf
+            | RaidErrorTooManyViewers
+            | RaidErrorUnexpected
+            | TimeoutNoTimeout
+            | TosBan
+            | TurboOnlyColor
+            | UnavailableCommand
+            | UnraidErrorNoActiveRaid
+            | UnraidErrorUnexpected
+            | UnrecognizedCmd
+            | UnsupportedChatroomsCmd
+            | UntimeoutBanned
+            | WhisperInvalidArgs => Severity::Failed,
+
+            UsageBan | UsageClear | UsageColor | UsageCommercial | UsageDisconnect
+            | UsageEmoteOnlyOff | UsageEmoteOnlyOn | UsageFollowersOff | UsageFollowersOn
+            | UsageHelp | UsageHost | UsageMarker | UsageMe | UsageMod | UsageMods
+            | UsageR9kOff | UsageR9kOn | UsageRaid | UsageSlowOff | UsageSlowOn | UsageSubsOff
+            | UsageSubsOn | UsageTimeout | UsageUnban | UsageUnhost | UsageUnmod | UsageUnraid
+            | UsageUntimeout => Severity::Usage,
+
+            Unknown(..) => Severity::Unknown,
+
+            _ => Severity::Info,
+        }
+    }
+
+    /// Was this sent instead of delivering a message (or whisper) we sent? See [Severity::Rejected]
+    pub fn is_rejection(&self) -> bool {
+        self.severity() == Severity::Rejected
+    }
+}
+
+/// How bad a [Notice] is, grouping its [MessageId]s
+#[non_exhaustive]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+pub enum Severity {
+    /// Something happened, e.g. a room mode changed or a command succeeded
+    Info,
+    /// A command was used incorrectly, and Twitch replied with how to use it
+    Usage,
+    /// A command failed, e.g. we don't have permission to use it
+    Failed,
+    /// A message (or whisper) we sent wasn't delivered, e.g. we're rate limited or banned
+    Rejected,
+    /// The message id isn't known by this crate
+    Unknown,
 }
 
 #[cfg(test)]
@@ -596,6 +777,37 @@ mod tests {
         crate::serde::round_trip_rmp::<Notice>(input);
     }
 
+    #[test]
+    fn notice_severity() {
+        let tests = &[
+            ("msg_ratelimit", Severity::Rejected),
+            ("msg_followersonly", Severity::Rejected),
+            ("whisper_limit_per_sec", Severity::Rejected),
+            ("unrecognized_cmd", Severity::Failed),
+            ("no_permission", Severity::Failed),
+            ("usage_ban", Severity::Usage),
+            ("slow_on", Severity::Info),
+            ("vips_success", Severity::Info),
+            ("some_new_thing", Severity::Unknown),
+        ];
+        for (id, severity) in tests {
+            let input = format!("@msg-id={} :tmi.twitch.tv NOTICE #museun :hello\r\n", id);
+            for msg in parse(&input).map(|s| s.unwrap()) {
+                let msg = Notice::from_irc(msg).unwrap();
+                assert_eq!(msg.severity(), *severity, "{}", id);
+                assert_eq!(
+                    msg.msg_id().unwrap().is_rejection(),
+                    *severity == Severity::Rejected
+                );
+            }
+        }
+
+        let input = ":tmi.twitch.tv NOTICE #museun :hello\r\n";
+        for msg in parse(input).map(|s| s.unwrap()) {
+            assert_eq!(Notice::from_irc(msg).unwrap().severity(), Severity::Unknown);
+        }
+    }
+
     #[test]
     fn notice() {
         let input = ":tmi.twitch.tv NOTICE #museun :This room is no longer in slow mode.\r\n";
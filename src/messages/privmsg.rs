@@ -1,7 +1,10 @@
+use crate::prelude::*;
 use crate::{irc::*, MaybeOwned, MaybeOwnedIndex, Validator};
 
 use crate::twitch::{
-    parse_badges, parse_badges_iter, parse_emotes, Badge, BadgeInfo, BadgeKind, Color, Emotes,
+    parse_badges, parse_badges_iter, parse_emotes, parse_typed_badges, subscriber_months, Badge,
+    BadgeInfo, BadgeKind, ChatUser, Cheermotes, Color, EmoteRange, EmoteRanges, EmoteResolver,
+    Emotes, ResolvedEmote, Role, SharedChatSource, TypedBadge,
 };
 
 /// Some PRIVMSGs are considered 'CTCP' (client-to-client protocol)
@@ -30,10 +33,72 @@ pub struct Privmsg<'a> {
     ctcp: Option<MaybeOwnedIndex>,
 }
 
+/// The message a [Privmsg] was replying to
+///
+/// You can reply to a message with [commands::reply](crate::commands::reply)
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+pub struct ReplyParent<'a> {
+    /// The id of the parent message
+    pub msg_id: &'a str,
+    /// The id of the user who sent the parent message
+    pub user_id: Option<u64>,
+    /// The login of the user who sent the parent message
+    pub user_login: &'a str,
+    /// The (unescaped) display name of the user who sent the parent message
+    pub display_name: MaybeOwned<'a>,
+    /// The (unescaped) body of the parent message
+    pub msg_body: MaybeOwned<'a>,
+    /// The id of the message which started the thread, if known
+    pub thread_msg_id: Option<&'a str>,
+}
+
+/// A channel points reward that was redeemed with this [Privmsg]
+///
+/// Twitch only sends the reward's id over IRC; the reward's title and cost are in the matching
+/// EventSub notification (see `eventsub::events::Redemption::matches`, behind the `eventsub` feature).
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+pub struct RewardRedemption<'a> {
+    /// The id of the custom reward
+    pub reward_id: &'a str,
+    /// The login of the user who redeemed the reward
+    pub user_login: &'a str,
+    /// The id of the user who redeemed the reward
+    pub user_id: Option<u64>,
+    /// What the user entered for the reward
+    pub user_input: &'a str,
+}
+
+/// A paid message (a 'Hype Chat') attached to a [Privmsg]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+pub struct PaidMessage<'a> {
+    /// The amount paid, in the smallest unit of the currency (e.g. cents)
+    pub amount: u64,
+    /// The ISO 4217 code of the currency, e.g. `USD`
+    pub currency: &'a str,
+    /// The number of decimal places of the currency, e.g. `2` for USD
+    pub exponent: u32,
+    /// The level of the Hype Chat, from `ONE` to `TEN`
+    pub level: Option<&'a str>,
+    /// Whether the message was generated by Twitch, rather than sent by the user
+    pub is_system_message: bool,
+}
+
+impl<'a> PaidMessage<'a> {
+    /// The amount paid, in the currency's units. e.g. `1.5` (USD) for an `amount` of `150`
+    #[cfg(feature = "std")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+    pub fn value(&self) -> f64 {
+        self.amount as f64 / 10_f64.powi(self.exponent as i32)
+    }
+}
+
 /// An iterator over badges
 #[derive(Debug)]
 pub struct BadgesIter<'a> {
-    items: Option<std::str::Split<'a, char>>,
+    items: Option<core::str::Split<'a, char>>,
 }
 
 impl<'a> Iterator for BadgesIter<'a> {
@@ -51,7 +116,7 @@ impl<'a> Iterator for BadgesIter<'a> {
 /// An iterator over emotes
 #[derive(Debug)]
 pub struct EmotesIter<'a> {
-    items: Option<std::str::SplitTerminator<'a, char>>,
+    items: Option<core::str::SplitTerminator<'a, char>>,
 }
 
 impl<'a> Iterator for EmotesIter<'a> {
@@ -96,6 +161,43 @@ impl<'a> Privmsg<'a> {
         }
     }
 
+    /// Emotes attached to this message, resolved against the message's data
+    ///
+    /// Unlike `Privmsg::emotes()`, this yields each occurrence of an emote, in order, with its text
+    pub fn emote_ranges(&self) -> EmoteRanges<'_> {
+        EmoteRanges::new(self.data(), self.tags().get("emotes").unwrap_or_default())
+    }
+
+    /// The message's data with each emote replaced by the output of `replace`
+    pub fn replace_emotes<F, S>(&self, replace: F) -> String
+    where
+        F: FnMut(&EmoteRange<'_>) -> S,
+        S: AsRef<str>,
+    {
+        self.emote_ranges().replace(replace)
+    }
+
+    /// The message's data with the emotes removed
+    pub fn strip_emotes(&self) -> String {
+        self.emote_ranges().strip()
+    }
+
+    /// The emotes in this message, with the URLs of their images, in order.
+    ///
+    /// Like `Privmsg::emote_ranges()`, but this also finds third-party emotes (e.g. BTTV, FFZ or 7TV) in this channel. See [EmoteResolver].
+    /// Emotes which the resolver has no URL for are skipped.
+    pub fn resolve_emotes<R>(&self, resolver: &R) -> Vec<ResolvedEmote<'_>>
+    where
+        R: EmoteResolver + ?Sized,
+    {
+        crate::twitch::resolve_emotes(
+            self.data(),
+            self.tags().get("emotes").unwrap_or_default(),
+            self.channel(),
+            resolver,
+        )
+    }
+
     /// Gets the 'CTCP' kind associated with this message, if any
     pub fn ctcp(&self) -> Option<Ctcp<'_>> {
         const ACTION: &str = "ACTION";
@@ -131,16 +233,45 @@ impl<'a> Privmsg<'a> {
             .unwrap_or_default()
     }
 
+    /// Badges attached to this message, with their versions and metadata parsed
+    pub fn typed_badges(&'a self) -> Vec<TypedBadge<'a>> {
+        parse_typed_badges(&self.tags())
+    }
+
+    /// The number of months the user has been subscribed, if they are a subscriber
+    pub fn subscriber_months(&self) -> Option<u64> {
+        subscriber_months(&self.tags())
+    }
+
     /// How many bits were attached to this message
     pub fn bits(&self) -> Option<u64> {
         self.tags().get_parsed("bits")
     }
 
+    /// The cheermotes in this message, e.g. `Cheer100`
+    ///
+    /// This is empty if the `bits` tag isn't present
+    pub fn cheermotes(&self) -> Cheermotes<'_> {
+        let data = if self.bits().is_some() {
+            self.data()
+        } else {
+            ""
+        };
+        Cheermotes::new(data)
+    }
+
     /// The color of the user who sent this message, if set
     pub fn color(&self) -> Option<Color> {
         self.tags().get_parsed("color")
     }
 
+    /// The color of the user who sent this message, or the color Twitch shows for them if they haven't set one
+    ///
+    /// See [Color::fallback()]
+    pub fn color_or_fallback(&self) -> Color {
+        self.color().unwrap_or_else(|| Color::fallback(self.name()))
+    }
+
     /// Returns the display name of the user, if set.
     ///
     /// Users can changed the casing and encoding of their names, if they choose
@@ -150,8 +281,13 @@ impl<'a> Privmsg<'a> {
     /// their display name to **FOO** then this'll return that **FOO**.
     ///
     /// Otherwise it'll return `None`.
-    pub fn display_name(&'a self) -> Option<&str> {
-        self.tags().get("display-name")
+    pub fn display_name(&self) -> Option<MaybeOwned<'_>> {
+        self.tags().get_unescaped("display-name")
+    }
+
+    /// Who sent this message, with their display name and user id. See [ChatUser] for the name to show.
+    pub fn sender(&self) -> ChatUser<'_> {
+        ChatUser::from_parts(self.name(), self.display_name(), self.user_id())
     }
 
     /// Emotes attached to this message
@@ -162,6 +298,13 @@ impl<'a> Privmsg<'a> {
             .unwrap_or_default()
     }
 
+    /// The role the user sending this message has in this channel
+    ///
+    /// This checks the `vip` tag as well as the badges, as users can hide their vip badge
+    pub fn author_role(&self) -> Role {
+        Role::from_tags(&self.tags())
+    }
+
     /// Whether the user sending this message was a broadcaster
     pub fn is_broadcaster(&self) -> bool {
         self.contains_badge(BadgeKind::Broadcaster)
@@ -173,8 +316,22 @@ impl<'a> Privmsg<'a> {
     }
 
     /// Whether the user sending this message was a vip
+    ///
+    /// This checks the `vip` tag as well as the badges, as users can hide their vip badge
     pub fn is_vip(&self) -> bool {
-        self.contains_badge(BadgeKind::VIP)
+        self.tags().get("vip").is_some() || self.contains_badge(BadgeKind::VIP)
+    }
+
+    /// Whether this was the first message the user has sent in this channel
+    pub fn is_first_msg(&self) -> bool {
+        self.tags().get_as_bool("first-msg")
+    }
+
+    /// Whether the user is a returning chatter.
+    ///
+    /// Twitch sets this for users who have chatted in the channel a few times before, but not recently
+    pub fn is_returning_chatter(&self) -> bool {
+        self.tags().get_as_bool("returning-chatter")
     }
 
     /// Whether the user sending this message was a susbcriber
@@ -197,6 +354,11 @@ impl<'a> Privmsg<'a> {
         self.contains_badge(BadgeKind::GlobalMod)
     }
 
+    /// Where this message came from, if it was sent during a Shared Chat session
+    pub fn shared_chat_source(&'a self) -> Option<SharedChatSource<'a>> {
+        SharedChatSource::from_tags(&self.tags())
+    }
+
     /// The id of the room this message was sent to
     pub fn room_id(&self) -> Option<u64> {
         self.tags().get_parsed("room-id")
@@ -207,6 +369,20 @@ impl<'a> Privmsg<'a> {
         self.tags().get_parsed("tmi-sent-ts")
     }
 
+    /// When Twitch received this message
+    #[cfg(feature = "std")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+    pub fn sent_at(&self) -> Option<std::time::SystemTime> {
+        self.tags().get_timestamp("tmi-sent-ts").ok()
+    }
+
+    /// When Twitch's chat servers received this message, if known
+    #[cfg(feature = "std")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+    pub fn received_at(&self) -> Option<std::time::SystemTime> {
+        self.tags().get_timestamp("rm-received-ts").ok()
+    }
+
     /// The id of the user who sent this message
     pub fn user_id(&self) -> Option<u64> {
         self.tags().get_parsed("user-id")
@@ -221,6 +397,37 @@ impl<'a> Privmsg<'a> {
         self.tags().get("custom-reward-id")
     }
 
+    /// The custom channel points reward this message redeemed, if any
+    ///
+    /// ```
+    /// # use twitchchat::{messages::Privmsg, irc, FromIrcMessage as _};
+    /// let input = "@custom-reward-id=abc-123;user-id=1234 :museun!museun@museun PRIVMSG #museun :play my song\r\n";
+    /// let (_, msg) = irc::parse_one(input).unwrap();
+    /// let msg = Privmsg::from_irc(msg).unwrap();
+    ///
+    /// let redemption = msg.redemption().unwrap();
+    /// assert_eq!(redemption.reward_id, "abc-123");
+    /// assert_eq!(redemption.user_input, "play my song");
+    /// ```
+    pub fn redemption(&self) -> Option<RewardRedemption<'_>> {
+        Some(RewardRedemption {
+            reward_id: self.custom_reward_id()?,
+            user_login: self.name(),
+            user_id: self.user_id(),
+            user_input: self.data(),
+        })
+    }
+
+    /// Whether this message redeemed a custom channel points reward
+    pub fn is_redemption(&self) -> bool {
+        self.custom_reward_id().is_some()
+    }
+
+    /// The id of this message, e.g. for replying to it or deleting it
+    pub fn id(&self) -> Option<&str> {
+        self.tags().get("id")
+    }
+
     /// The name of the custom channel reward.
     ///
     /// For example, a highlighted message would be `highlighted-message`
@@ -230,6 +437,47 @@ impl<'a> Privmsg<'a> {
         self.tags().get("msg-id")
     }
 
+    /// The message this message was replying to, if any
+    pub fn reply_parent(&self) -> Option<ReplyParent<'_>> {
+        let tags = self.tags();
+        Some(ReplyParent {
+            msg_id: tags.get("reply-parent-msg-id")?,
+            user_id: tags.get_parsed("reply-parent-user-id"),
+            user_login: tags.get("reply-parent-user-login").unwrap_or_default(),
+            display_name: tags
+                .get_unescaped("reply-parent-display-name")
+                .unwrap_or(MaybeOwned::Borrowed("")),
+            msg_body: tags
+                .get_unescaped("reply-parent-msg-body")
+                .unwrap_or(MaybeOwned::Borrowed("")),
+            thread_msg_id: tags.get("reply-thread-parent-msg-id"),
+        })
+    }
+
+    /// The payment attached to this message, if it was a paid message (a 'Hype Chat')
+    pub fn paid_message(&self) -> Option<PaidMessage<'_>> {
+        let tags = self.tags();
+        Some(PaidMessage {
+            amount: tags.get_parsed("pinned-chat-paid-amount")?,
+            currency: tags.get("pinned-chat-paid-currency")?,
+            exponent: tags
+                .get_parsed("pinned-chat-paid-exponent")
+                .unwrap_or_default(),
+            level: tags.get("pinned-chat-paid-level"),
+            is_system_message: tags.get_as_bool("pinned-chat-paid-is-system-message"),
+        })
+    }
+
+    /// Whether this message was a paid message (a 'Hype Chat')
+    pub fn is_paid(&self) -> bool {
+        self.paid_message().is_some()
+    }
+
+    /// Whether this message was a reply to another message
+    pub fn is_reply(&self) -> bool {
+        self.tags().get("reply-parent-msg-id").is_some()
+    }
+
     fn contains_badge(&self, badge: BadgeKind<'_>) -> bool {
         self.tags()
             .get("badges")
@@ -250,7 +498,8 @@ impl<'a> FromIrcMessage<'a> for Privmsg<'a> {
         let mut ctcp = None;
 
         let data = &msg.raw[index];
-        if data.starts_with(CTCP_MARKER) && data.ends_with(CTCP_MARKER) {
+        // a lone marker is just data
+        if data.len() > 1 && data.starts_with(CTCP_MARKER) && data.ends_with(CTCP_MARKER) {
             let len = data.chars().map(char::len_utf8).sum::<usize>();
             match data[1..len - 1].find(' ') {
                 Some(pos) => {
@@ -327,6 +576,22 @@ serde_struct!(Privmsg {
     name,
     channel,
     data,
+    badge_info,
+    badges,
+    color,
+    display_name,
+    emotes,
+    user_id,
+    room_id,
+    id,
+    msg_id,
+    tmi_sent_ts,
+    bits,
+    ctcp,
+    reply_parent,
+    paid_message,
+    redemption,
+    shared_chat_source,
 });
 
 #[cfg(test)]
@@ -349,6 +614,26 @@ mod tests {
         }
     }
 
+    #[test]
+    #[cfg(feature = "serde")]
+    fn privmsg_serde_fields() {
+        let input = "@badge-info=subscriber/8;badges=subscriber/6;color=#59517B;display-name=Museun;emotes=25:0-4;room-id=23196011;user-id=12345 :museun!museun@museun PRIVMSG #museun :Kappa test\r\n";
+        let msg = Privmsg::from_irc(parse(input).next().unwrap().unwrap()).unwrap();
+
+        let json = serde_json::to_value(&msg).unwrap();
+        assert_eq!(json["name"], "museun");
+        assert_eq!(json["display_name"], "Museun");
+        assert_eq!(json["user_id"], 12345);
+        assert_eq!(json["room_id"], 23196011);
+        assert_eq!(json["badges"][0]["kind"], "Subscriber");
+        assert_eq!(json["badges"][0]["data"], "6");
+        assert_eq!(json["emotes"][0]["id"], 25);
+        assert_eq!(json["reply_parent"], serde_json::Value::Null);
+
+        let right = serde_json::from_value::<Privmsg>(json).unwrap();
+        assert_eq!(msg, right);
+    }
+
     #[test]
     fn privmsg() {
         let input = ":test!user@host PRIVMSG #museun :this is a test\r\n";
@@ -411,6 +696,264 @@ mod tests {
             assert_eq!(msg.data(), "Notice me!");
             assert_eq!(msg.custom_reward_id().unwrap(), "abc-123-foo");
             assert_eq!(msg.msg_id().unwrap(), "highlighted-message");
+
+            let redemption = msg.redemption().unwrap();
+            assert_eq!(redemption.reward_id, "abc-123-foo");
+            assert_eq!(redemption.user_login, "test");
+            assert_eq!(redemption.user_input, "Notice me!");
+            assert!(msg.is_redemption());
+        }
+
+        let input = ":test!user@host PRIVMSG #museun :Notice me!\r\n";
+        for msg in parse(input).map(|s| s.unwrap()) {
+            let msg = Privmsg::from_irc(msg).unwrap();
+            assert_eq!(msg.redemption(), None);
+            assert!(!msg.is_redemption());
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn privmsg_timestamps() {
+        use std::time::{Duration, UNIX_EPOCH};
+
+        let input = "@rm-received-ts=1601079032500;tmi-sent-ts=1601079032426 :test!user@host PRIVMSG #museun :hello\r\n";
+        for msg in parse(input).map(|s| s.unwrap()) {
+            let msg = Privmsg::from_irc(msg).unwrap();
+            assert_eq!(
+                msg.sent_at().unwrap(),
+                UNIX_EPOCH + Duration::from_millis(1601079032426)
+            );
+            assert_eq!(
+                msg.received_at().unwrap(),
+                UNIX_EPOCH + Duration::from_millis(1601079032500)
+            );
+        }
+
+        let input = "@tmi-sent-ts=soon :test!user@host PRIVMSG #museun :hello\r\n";
+        for msg in parse(input).map(|s| s.unwrap()) {
+            let msg = Privmsg::from_irc(msg).unwrap();
+            assert!(msg.sent_at().is_none());
+            assert!(msg.received_at().is_none());
+        }
+    }
+
+    #[test]
+    fn privmsg_color() {
+        let input = "@color=#0000FF :test!user@host PRIVMSG #museun :hello\r\n";
+        for msg in parse(input).map(|s| s.unwrap()) {
+            let msg = Privmsg::from_irc(msg).unwrap();
+            assert_eq!(
+                msg.color().unwrap().kind,
+                crate::twitch::color::TwitchColor::Blue
+            );
+            assert_eq!(msg.color_or_fallback(), msg.color().unwrap());
+        }
+
+        let input = "@color= :test!user@host PRIVMSG #museun :hello\r\n";
+        for msg in parse(input).map(|s| s.unwrap()) {
+            let msg = Privmsg::from_irc(msg).unwrap();
+            assert!(msg.color().is_none());
+            assert_eq!(msg.color_or_fallback(), Color::fallback("test"));
+        }
+    }
+
+    #[test]
+    fn privmsg_reply_parent() {
+        let input = "@reply-parent-display-name=Museun;reply-parent-msg-body=hello\\sworld;reply-parent-msg-id=abc-123;reply-parent-user-id=23196011;reply-parent-user-login=museun;reply-thread-parent-msg-id=def-456 :test!user@host PRIVMSG #museun :@Museun hi\r\n";
+        for msg in parse(input).map(|s| s.unwrap()) {
+            let msg = Privmsg::from_irc(msg).unwrap();
+            assert!(msg.is_reply());
+
+            let parent = msg.reply_parent().unwrap();
+            assert_eq!(parent.msg_id, "abc-123");
+            assert_eq!(parent.user_id, Some(23196011));
+            assert_eq!(parent.user_login, "museun");
+            assert_eq!(&*parent.display_name, "Museun");
+            assert_eq!(&*parent.msg_body, "hello world");
+            assert_eq!(parent.thread_msg_id, Some("def-456"));
+        }
+
+        let input = ":test!user@host PRIVMSG #museun :this is a test\r\n";
+        for msg in parse(input).map(|s| s.unwrap()) {
+            let msg = Privmsg::from_irc(msg).unwrap();
+            assert!(!msg.is_reply());
+            assert!(msg.reply_parent().is_none());
+        }
+    }
+
+    #[test]
+    fn privmsg_emote_ranges() {
+        let input = "@emotes=25:2-6/81274:8-13 :test!user@host PRIVMSG #museun :\u{1F468} Kappa VoHiYo !\r\n";
+        for msg in parse(input).map(|s| s.unwrap()) {
+            let msg = Privmsg::from_irc(msg).unwrap();
+            let emotes = msg.emote_ranges().map(|e| e.text).collect::<Vec<_>>();
+            assert_eq!(emotes, vec!["Kappa", "VoHiYo"]);
+            assert_eq!(
+                msg.replace_emotes(|e| e.id.to_string()),
+                "\u{1F468} 25 81274 !"
+            );
+            assert_eq!(msg.strip_emotes(), "\u{1F468} !");
+        }
+    }
+
+    #[test]
+    fn privmsg_sender() {
+        let input = "@display-name=\\s\u{30DF}\u{30E5}\u{30FC}\u{30BA};user-id=23196011 :museun!museun@museun PRIVMSG #museun :hi\r\n";
+        for msg in parse(input).map(|s| s.unwrap()) {
+            let msg = Privmsg::from_irc(msg).unwrap();
+            let sender = msg.sender();
+            assert_eq!(sender.name(), "museun");
+            assert_eq!(
+                sender.display_name(),
+                Some("\u{30DF}\u{30E5}\u{30FC}\u{30BA}")
+            );
+            assert_eq!(
+                sender.best_name(),
+                "\u{30DF}\u{30E5}\u{30FC}\u{30BA} (museun)"
+            );
+            assert!(sender.is_same_user(&ChatUser::new("renamed", None, Some(23196011))));
+        }
+
+        let input = "@display-name= :museun!museun@museun PRIVMSG #museun :hi\r\n";
+        for msg in parse(input).map(|s| s.unwrap()) {
+            let msg = Privmsg::from_irc(msg).unwrap();
+            assert_eq!(msg.sender().display_name(), None);
+            assert_eq!(msg.sender().best_name(), "museun");
+        }
+    }
+
+    #[test]
+    fn privmsg_resolve_emotes() {
+        struct Ids;
+        impl EmoteResolver for Ids {
+            fn twitch_emote(&self, id: usize, _name: &str) -> Option<String> {
+                Some(id.to_string())
+            }
+        }
+
+        let mut bttv = crate::twitch::EmoteMap::new();
+        bttv.insert("#museun", "catJAM", "bttv");
+
+        let input = "@emotes=25:2-6 :test!user@host PRIVMSG #museun :\u{1F468} Kappa catJAM !\r\n";
+        for msg in parse(input).map(|s| s.unwrap()) {
+            let msg = Privmsg::from_irc(msg).unwrap();
+            let emotes = msg.resolve_emotes(&(Ids, &bttv));
+            let emotes = emotes.iter().map(|e| (e.text, &*e.url)).collect::<Vec<_>>();
+            assert_eq!(emotes, vec![("Kappa", "25"), ("catJAM", "bttv")]);
+        }
+    }
+
+    #[test]
+    fn privmsg_cheermotes() {
+        let input = "@bits=150 :test!user@host PRIVMSG #museun :Cheer100 nice PogChamp50\r\n";
+        for msg in parse(input).map(|s| s.unwrap()) {
+            let msg = Privmsg::from_irc(msg).unwrap();
+            let cheers = msg
+                .cheermotes()
+                .map(|c| (c.prefix, c.amount, c.range))
+                .collect::<Vec<_>>();
+            assert_eq!(cheers, vec![("Cheer", 100, 0..8), ("PogChamp", 50, 14..24)]);
+        }
+
+        let input = ":test!user@host PRIVMSG #museun :Cheer100 nice PogChamp50\r\n";
+        for msg in parse(input).map(|s| s.unwrap()) {
+            let msg = Privmsg::from_irc(msg).unwrap();
+            assert_eq!(msg.cheermotes().next(), None);
+        }
+    }
+
+    #[test]
+    fn privmsg_shared_chat_source() {
+        let input = "@badges=subscriber/12;room-id=1;source-badge-info=subscriber/14;source-badges=moderator/1,subscriber/12;source-id=abc-123;source-only=0;source-room-id=2 :test!user@host PRIVMSG #museun :hello\r\n";
+        for msg in parse(input).map(|s| s.unwrap()) {
+            let msg = Privmsg::from_irc(msg).unwrap();
+            let source = msg.shared_chat_source().unwrap();
+            assert_eq!(source.room_id, 2);
+            assert_ne!(Some(source.room_id), msg.room_id());
+            assert_eq!(source.id, Some("abc-123"));
+            assert_eq!(source.badges.len(), 2);
+            assert_eq!(source.badges[0].kind, BadgeKind::Moderator);
+            assert_eq!(source.badge_info[0].data, "14");
+            assert!(!source.source_only);
+        }
+
+        let input = "@room-id=1 :test!user@host PRIVMSG #museun :hello\r\n";
+        for msg in parse(input).map(|s| s.unwrap()) {
+            let msg = Privmsg::from_irc(msg).unwrap();
+            assert_eq!(msg.shared_chat_source(), None);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn privmsg_paid_message() {
+        let input = "@pinned-chat-paid-amount=500;pinned-chat-paid-canonical-amount=5;pinned-chat-paid-currency=USD;pinned-chat-paid-exponent=2;pinned-chat-paid-is-system-message=0;pinned-chat-paid-level=ONE :test!user@host PRIVMSG #museun :hype!\r\n";
+        for msg in parse(input).map(|s| s.unwrap()) {
+            let msg = Privmsg::from_irc(msg).unwrap();
+            assert!(msg.is_paid());
+            let paid = msg.paid_message().unwrap();
+            assert_eq!(paid.amount, 500);
+            assert_eq!(paid.currency, "USD");
+            assert_eq!(paid.exponent, 2);
+            assert_eq!(paid.level, Some("ONE"));
+            assert!(!paid.is_system_message);
+            assert!((paid.value() - 5.0).abs() < f64::EPSILON);
+        }
+
+        let input = ":test!user@host PRIVMSG #museun :not hype\r\n";
+        for msg in parse(input).map(|s| s.unwrap()) {
+            let msg = Privmsg::from_irc(msg).unwrap();
+            assert!(!msg.is_paid());
+            assert_eq!(msg.paid_message(), None);
+        }
+    }
+
+    #[test]
+    fn privmsg_chatter_flags() {
+        let input = "@badges=;first-msg=1;returning-chatter=0;vip=1 :test!user@host PRIVMSG #museun :hello\r\n";
+        for msg in parse(input).map(|s| s.unwrap()) {
+            let msg = Privmsg::from_irc(msg).unwrap();
+            assert!(msg.is_first_msg());
+            assert!(!msg.is_returning_chatter());
+            assert!(msg.is_vip());
+        }
+
+        let input = "@badges=vip/1;first-msg=0;returning-chatter=1 :test!user@host PRIVMSG #museun :hello\r\n";
+        for msg in parse(input).map(|s| s.unwrap()) {
+            let msg = Privmsg::from_irc(msg).unwrap();
+            assert!(!msg.is_first_msg());
+            assert!(msg.is_returning_chatter());
+            assert!(msg.is_vip());
+        }
+
+        let input = ":test!user@host PRIVMSG #museun :hello\r\n";
+        for msg in parse(input).map(|s| s.unwrap()) {
+            let msg = Privmsg::from_irc(msg).unwrap();
+            assert!(!msg.is_first_msg());
+            assert!(!msg.is_returning_chatter());
+            assert!(!msg.is_vip());
+        }
+    }
+
+    #[test]
+    fn privmsg_typed_badges() {
+        let input = "@badge-info=subscriber/14;badges=moderator/1,subscriber/12;mod=1 :test!user@host PRIVMSG #museun :hello\r\n";
+        for msg in parse(input).map(|s| s.unwrap()) {
+            let msg = Privmsg::from_irc(msg).unwrap();
+            assert_eq!(
+                msg.typed_badges(),
+                vec![TypedBadge::Moderator, TypedBadge::Subscriber { months: 14 }]
+            );
+            assert!(msg.is_moderator());
+            assert_eq!(msg.subscriber_months(), Some(14));
+        }
+
+        let input = ":test!user@host PRIVMSG #museun :hello\r\n";
+        for msg in parse(input).map(|s| s.unwrap()) {
+            let msg = Privmsg::from_irc(msg).unwrap();
+            assert!(msg.typed_badges().is_empty());
+            assert_eq!(msg.subscriber_months(), None);
         }
     }
 
@@ -1,4 +1,5 @@
 use crate::{irc::*, MaybeOwned, MaybeOwnedIndex, Validator};
+use core::time::Duration;
 
 /// The parameters for a room being in follower-only mode
 #[derive(Debug, Copy, Clone, PartialEq)]
@@ -12,6 +13,51 @@ pub enum FollowersOnly {
     Limit(isize),
 }
 
+/// A channel's chat settings, built up from `ROOMSTATE` messages
+///
+/// Twitch sends every setting when you join a channel, and then only the settings which changed.
+/// Use [RoomSettings::update] to apply those changes.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+pub struct RoomSettings {
+    /// Whether chatters can only use emotes
+    pub emote_only: bool,
+    /// How long chatters must have followed the channel, if followers only mode is enabled
+    pub followers_only: Option<Duration>,
+    /// Whether chatters must send unique messages
+    pub r9k: bool,
+    /// How long chatters must wait between messages, if slow mode is enabled
+    pub slow: Option<Duration>,
+    /// Whether chatters must be subscribed
+    pub subs_only: bool,
+}
+
+impl RoomSettings {
+    /// Apply the settings which are present in this `ROOMSTATE`
+    pub fn update(&mut self, msg: &RoomState<'_>) {
+        let tags = msg.tags();
+        if tags.get("emote-only").is_some() {
+            self.emote_only = msg.is_emote_only();
+        }
+        if let Some(followers) = msg.is_followers_only() {
+            self.followers_only = match followers {
+                FollowersOnly::Disabled => None,
+                FollowersOnly::All => Some(Duration::from_secs(0)),
+                FollowersOnly::Limit(minutes) => Some(Duration::from_secs(minutes as u64 * 60)),
+            };
+        }
+        if tags.get("r9k").is_some() {
+            self.r9k = msg.is_r9k();
+        }
+        if let Some(slow) = tags.get_parsed::<_, u64>("slow") {
+            self.slow = Some(Duration::from_secs(slow)).filter(|_| slow > 0);
+        }
+        if tags.get("subs-only").is_some() {
+            self.subs_only = msg.is_subs_only();
+        }
+    }
+}
+
 /// Identifies the channel's chat settings (e.g., slow mode duration).
 #[derive(Clone, PartialEq)]
 pub struct RoomState<'a> {
@@ -45,6 +91,13 @@ impl<'a> RoomState<'a> {
         channel
     );
 
+    /// The settings in this message. Any settings which weren't sent are their defaults
+    pub fn settings(&self) -> RoomSettings {
+        let mut settings = RoomSettings::default();
+        settings.update(self);
+        settings
+    }
+
     /// Whether this room is in emote only mode
     pub fn is_emote_only(&self) -> bool {
         self.tags().get_as_bool("emote-only")
@@ -86,7 +139,13 @@ impl<'a> RoomState<'a> {
 
 into_owned!(RoomState { raw, tags, channel });
 impl_custom_debug!(RoomState { raw, tags, channel });
-serde_struct!(RoomState { raw, tags, channel });
+serde_struct!(RoomState {
+    raw,
+    tags,
+    channel,
+    room_id,
+    settings,
+});
 
 #[cfg(test)]
 mod tests {
@@ -108,4 +167,41 @@ mod tests {
             assert_eq!(msg.channel(), "#museun");
         }
     }
+
+    #[test]
+    fn room_settings() {
+        let input = "@emote-only=0;followers-only=10;r9k=0;room-id=23196011;slow=30;subs-only=0 :tmi.twitch.tv ROOMSTATE #museun\r\n";
+        let msg = parse(input).next().unwrap().unwrap();
+        let mut settings = RoomState::from_irc(msg).unwrap().settings();
+        assert_eq!(
+            settings,
+            RoomSettings {
+                emote_only: false,
+                followers_only: Some(Duration::from_secs(600)),
+                r9k: false,
+                slow: Some(Duration::from_secs(30)),
+                subs_only: false,
+            }
+        );
+
+        let input = "@emote-only=1;followers-only=-1;room-id=23196011;slow=0 :tmi.twitch.tv ROOMSTATE #museun\r\n";
+        let msg = parse(input).next().unwrap().unwrap();
+        settings.update(&RoomState::from_irc(msg).unwrap());
+        assert_eq!(
+            settings,
+            RoomSettings {
+                emote_only: true,
+                followers_only: None,
+                r9k: false,
+                slow: None,
+                subs_only: false,
+            }
+        );
+
+        let input = "@r9k=1;room-id=23196011 :tmi.twitch.tv ROOMSTATE #museun\r\n";
+        let msg = parse(input).next().unwrap().unwrap();
+        settings.update(&RoomState::from_irc(msg).unwrap());
+        assert!(settings.r9k);
+        assert!(settings.emote_only);
+    }
 }
@@ -1,6 +1,15 @@
-use crate::twitch::{parse_badges, parse_emotes, Badge, BadgeInfo, Color, Emotes};
+use crate::prelude::*;
+use crate::twitch::{
+    parse_badges, parse_emotes, Badge, BadgeInfo, ChatUser, Color, Emotes, SharedChatSource,
+};
 use crate::{irc::*, MaybeOwned, MaybeOwnedIndex, Validator};
 
+mod event;
+pub use event::{
+    Announcement, BitsBadgeTier, GiftPaidUpgrade, MysteryGift, Raid, RewardGift, Ritual, SubGift,
+    Subscription, UserNoticeEvent, ViewerMilestone,
+};
+
 /// A paid subscription ot the channel
 #[non_exhaustive]
 #[derive(Copy, Clone, Debug, PartialEq, Hash)]
@@ -18,6 +27,18 @@ pub enum SubPlan<'a> {
     Unknown(&'a str),
 }
 
+impl<'a> SubPlan<'a> {
+    fn parse(s: &'a str) -> Self {
+        match s {
+            "Prime" => Self::Prime,
+            "1000" | "Tier1" => Self::Tier1,
+            "2000" | "Tier2" => Self::Tier2,
+            "3000" | "Tier3" => Self::Tier3,
+            s => Self::Unknown(s),
+        }
+    }
+}
+
 /// The kind of notice it was, retrieved via [UserNotice::msg_id()]
 #[non_exhaustive]
 #[derive(Clone, Debug, PartialEq, Hash)]
@@ -47,6 +68,12 @@ pub enum NoticeType<'a> {
     Ritual,
     /// A the tier that the bits were part of
     BitsBadgeTier,
+    /// An announcement
+    Announcement,
+    /// A viewer milestone
+    ViewerMilestone,
+    /// An event from another channel in a shared chat session
+    SharedChatNotice,
     /// An unknown notice type (a catch-all)
     Unknown(&'a str),
 }
@@ -96,8 +123,8 @@ impl<'a> UserNotice<'a> {
     }
 
     /// The user's display name, if set
-    pub fn display_name(&self) -> Option<&str> {
-        self.tags().get("display-name")
+    pub fn display_name(&self) -> Option<MaybeOwned<'_>> {
+        self.tags().get_unescaped("display-name")
     }
 
     /// Emotes attached to this message
@@ -120,6 +147,16 @@ impl<'a> UserNotice<'a> {
         self.tags().get("login")
     }
 
+    /// Who sent this notice, with their display name and user id. See [ChatUser] for the name to show.
+    pub fn sender(&self) -> Option<ChatUser<'_>> {
+        let login = self.login()?;
+        Some(ChatUser::from_parts(
+            login,
+            self.display_name(),
+            self.user_id(),
+        ))
+    }
+
     /// Whether this user is a moderator
     pub fn is_moderator(&self) -> bool {
         self.tags().get_as_bool("mod")
@@ -141,11 +178,26 @@ impl<'a> UserNotice<'a> {
             "unraid" => NoticeType::Unraid,
             "ritual" => NoticeType::Ritual,
             "bitsbadgetier" => NoticeType::BitsBadgeTier,
+            "announcement" => NoticeType::Announcement,
+            "viewermilestone" => NoticeType::ViewerMilestone,
+            "sharedchatnotice" => NoticeType::SharedChatNotice,
             kind => NoticeType::Unknown(kind),
         }
         .into()
     }
 
+    /// The kind of notice this message is, with its `msg-param-*` tags parsed
+    pub fn event(&'a self) -> Option<UserNoticeEvent<'a>> {
+        let tags = self.tags();
+        let kind = tags.get("msg-id")?;
+        UserNoticeEvent::parse(kind, &tags).into()
+    }
+
+    /// Where this message came from, if it was sent during a Shared Chat session
+    pub fn shared_chat_source(&'a self) -> Option<SharedChatSource<'a>> {
+        SharedChatSource::from_tags(&self.tags())
+    }
+
     /// The id of the room for this notice
     pub fn room_id(&self) -> Option<u64> {
         self.tags().get_parsed("room-id")
@@ -156,21 +208,21 @@ impl<'a> UserNotice<'a> {
         self.tags().get_parsed("tmi-sent-ts")
     }
 
+    /// When Twitch received this message
+    #[cfg(feature = "std")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+    pub fn sent_at(&self) -> Option<std::time::SystemTime> {
+        self.tags().get_timestamp("tmi-sent-ts").ok()
+    }
+
     /// User id of the user who sent this notice
     pub fn user_id(&self) -> Option<u64> {
         self.tags().get_parsed("user-id")
     }
 
     /// The message printed in chat along with this notice
-    pub fn system_msg(&self) -> Option<String> {
-        self.tags()
-            .get("system-msg")?
-            .replace("\\s", " ")
-            .replace("\\r", "\r")
-            .replace("\\n", "\n")
-            .replace("\\\\", "\\")
-            .replace("\\:", ":")
-            .into()
+    pub fn system_msg(&self) -> Option<MaybeOwned<'_>> {
+        self.tags().get_unescaped("system-msg")
     }
 
     /// (Sent only on sub, resub) The total number of months the user has
@@ -184,8 +236,8 @@ impl<'a> UserNotice<'a> {
 
     /// (Sent only on raid) The display name of the source user raiding this
     /// channel.
-    pub fn msg_param_display_name(&self) -> Option<&str> {
-        self.tags().get("msg-param-displayName")
+    pub fn msg_param_display_name(&self) -> Option<MaybeOwned<'_>> {
+        self.tags().get_unescaped("msg-param-displayName")
     }
 
     /// (Sent on only raid) The name of the source user raiding this channel.
@@ -211,14 +263,15 @@ impl<'a> UserNotice<'a> {
 
     /// (Sent only on anongiftpaidupgrade, giftpaidupgrade) The subscriptions
     /// promo, if any, that is ongoing; e.g. Subtember 2018.
-    pub fn msg_param_promo_name(&self) -> Option<&str> {
-        self.tags().get("msg-param-promo-name")
+    pub fn msg_param_promo_name(&self) -> Option<MaybeOwned<'_>> {
+        self.tags().get_unescaped("msg-param-promo-name")
     }
 
     /// (Sent only on subgift, anonsubgift) The display name of the subscription
     /// gift recipient.
-    pub fn msg_param_recipient_display_name(&self) -> Option<&str> {
-        self.tags().get("msg-param-recipient-display-name")
+    pub fn msg_param_recipient_display_name(&self) -> Option<MaybeOwned<'_>> {
+        self.tags()
+            .get_unescaped("msg-param-recipient-display-name")
     }
 
     /// (Sent only on subgift, anonsubgift) The user ID of the subscription gift
@@ -241,8 +294,8 @@ impl<'a> UserNotice<'a> {
 
     /// (Sent only on giftpaidupgrade) The display name of the user who gifted
     /// the subscription.
-    pub fn msg_param_sender_name(&self) -> Option<&str> {
-        self.tags().get("msg-param-sender-name")
+    pub fn msg_param_sender_name(&self) -> Option<MaybeOwned<'_>> {
+        self.tags().get_unescaped("msg-param-sender-name")
     }
 
     /// (Sent only on sub, resub) Boolean indicating whether users want their
@@ -266,16 +319,7 @@ impl<'a> UserNotice<'a> {
     /// 3000 refer to the first, second, and third levels of paid subscriptions,
     /// respectively (currently $4.99, $9.99, and $24.99).
     pub fn msg_param_sub_plan(&'a self) -> Option<SubPlan<'a>> {
-        self.tags().get("msg-param-sub-plan").and_then(|s| {
-            match s {
-                "Prime" => SubPlan::Prime,
-                "Tier1" => SubPlan::Tier1,
-                "Tier2" => SubPlan::Tier2,
-                "Tier3" => SubPlan::Tier3,
-                s => SubPlan::Unknown(s),
-            }
-            .into()
-        })
+        self.tags().get("msg-param-sub-plan").map(SubPlan::parse)
     }
 
     /// (Sent only on sub, resub, subgift, anonsubgift) The display name of the
@@ -283,8 +327,8 @@ impl<'a> UserNotice<'a> {
     ///
     /// This may be a default name or one created by the
     /// channel owner.
-    pub fn msg_param_sub_plan_name(&self) -> Option<&str> {
-        self.tags().get("msg-param-sub-plan-name")
+    pub fn msg_param_sub_plan_name(&self) -> Option<MaybeOwned<'_>> {
+        self.tags().get_unescaped("msg-param-sub-plan-name")
     }
 
     /// (Sent only on raid) The number of viewers watching the source channel
@@ -293,6 +337,12 @@ impl<'a> UserNotice<'a> {
         self.tags().get_parsed("msg-param-viewerCount")
     }
 
+    /// (Sent only on raid) The profile image of the source user raiding this
+    /// channel.
+    pub fn msg_param_profile_image_url(&self) -> Option<MaybeOwned<'_>> {
+        self.tags().get_unescaped("msg-param-profileImageURL")
+    }
+
     /// (Sent only on ritual) The name of the ritual this notice is for. Valid
     /// value: new_chatter.
     pub fn msg_param_ritual_name(&self) -> Option<&str> {
@@ -344,6 +394,20 @@ serde_struct!(UserNotice {
     tags,
     channel,
     message,
+    badge_info,
+    badges,
+    color,
+    display_name,
+    emotes,
+    id,
+    login,
+    user_id,
+    room_id,
+    tmi_sent_ts,
+    system_msg,
+    msg_id,
+    event,
+    shared_chat_source,
 });
 
 #[cfg(test)]
@@ -365,6 +429,19 @@ mod tests {
         }
     }
 
+    #[test]
+    #[cfg(feature = "serde")]
+    fn user_notice_serde_event() {
+        let input = "@badges=subscriber/6;display-name=lllAirJordanlll;login=lllairjordanlll;msg-id=resub;msg-param-cumulative-months=8;msg-param-should-share-streak=0;msg-param-sub-plan=1000;room-id=22552479;user-id=44979519 :tmi.twitch.tv USERNOTICE #giantwaffle\r\n";
+        let msg = UserNotice::from_irc(parse(input).next().unwrap().unwrap()).unwrap();
+
+        let json = serde_json::to_value(&msg).unwrap();
+        assert_eq!(json["login"], "lllairjordanlll");
+        assert_eq!(json["msg_id"], "Resub");
+        assert_eq!(json["event"]["Resub"]["cumulative_months"], 8);
+        assert_eq!(json["event"]["Resub"]["sub_plan"], "Tier1");
+    }
+
     #[test]
     fn user_notice_message() {
         let input = ":tmi.twitch.tv USERNOTICE #museun :This room is no longer in slow mode.\r\n";
@@ -398,4 +475,131 @@ mod tests {
             assert_eq!(msg.tags().is_empty(), false);
         }
     }
+
+    #[test]
+    fn user_notice_unescaped_tags() {
+        let input = "@display-name=Museun\\s;msg-id=resub;msg-param-sub-plan-name=Channel\\sSubscription\\s(museun);system-msg=museun\\ssubscribed\\:\\sthanks\\\\ :tmi.twitch.tv USERNOTICE #museun\r\n";
+        for msg in parse(input).map(|s| s.unwrap()) {
+            let msg = UserNotice::from_irc(msg).unwrap();
+            assert_eq!(msg.display_name().unwrap(), "Museun ");
+            assert_eq!(msg.system_msg().unwrap(), "museun subscribed; thanks\\");
+            assert_eq!(
+                msg.msg_param_sub_plan_name().unwrap(),
+                "Channel Subscription (museun)"
+            );
+        }
+
+        let input = "@display-name=Museun :tmi.twitch.tv USERNOTICE #museun\r\n";
+        for msg in parse(input).map(|s| s.unwrap()) {
+            let msg = UserNotice::from_irc(msg).unwrap();
+            // nothing to unescape, so this borrows
+            assert!(matches!(
+                msg.display_name(),
+                Some(MaybeOwned::Borrowed("Museun"))
+            ));
+        }
+    }
+
+    #[test]
+    fn user_notice_event_resub() {
+        let input = "@badge-info=subscriber/8;badges=subscriber/6,bits/100;color=#59517B;display-name=lllAirJordanlll;emotes=;flags=;id=3198b02c-eaf4-4904-9b07-eb1b2b12ba50;login=lllairjordanlll;mod=0;msg-id=resub;msg-param-cumulative-months=8;msg-param-months=0;msg-param-should-share-streak=0;msg-param-sub-plan-name=Channel\\sSubscription\\s(giantwaffle);msg-param-sub-plan=1000;room-id=22552479;subscriber=1;system-msg=lllAirJordanlll\\ssubscribed\\sat\\sTier\\s1.\\sThey\'ve\\ssubscribed\\sfor\\s8\\smonths!;tmi-sent-ts=1580932171144;user-id=44979519;user-type= :tmi.twitch.tv USERNOTICE #giantwaffle\r\n";
+        for msg in parse(input).map(|s| s.unwrap()) {
+            let msg = UserNotice::from_irc(msg).unwrap();
+            assert_eq!(msg.msg_param_sub_plan(), Some(SubPlan::Tier1));
+
+            let sub = match msg.event().unwrap() {
+                UserNoticeEvent::Resub(sub) => sub,
+                event => panic!("unexpected event: {:?}", event),
+            };
+            assert_eq!(sub.cumulative_months, Some(8));
+            assert!(!sub.should_share_streak);
+            assert_eq!(sub.streak_months, None);
+            assert_eq!(sub.sub_plan, Some(SubPlan::Tier1));
+            assert_eq!(
+                sub.sub_plan_name.as_deref(),
+                Some("Channel Subscription (giantwaffle)")
+            );
+        }
+    }
+
+    #[test]
+    fn user_notice_event_raid() {
+        let input = "@login=museun;msg-id=raid;msg-param-displayName=Museun;msg-param-login=museun;msg-param-viewerCount=42;room-id=1 :tmi.twitch.tv USERNOTICE #shaken_bot\r\n";
+        for msg in parse(input).map(|s| s.unwrap()) {
+            let msg = UserNotice::from_irc(msg).unwrap();
+            assert_eq!(msg.msg_id(), Some(NoticeType::Raid));
+            let raid = match msg.event().unwrap() {
+                UserNoticeEvent::Raid(raid) => raid,
+                event => panic!("unexpected event: {:?}", event),
+            };
+            assert_eq!(raid.display_name.as_deref(), Some("Museun"));
+            assert_eq!(raid.login, Some("museun"));
+            assert_eq!(raid.viewer_count, Some(42));
+            assert_eq!(raid.profile_image_url, None);
+        }
+
+        let input = "@login=museun;msg-id=raid;msg-param-displayName=Museun;msg-param-login=museun;msg-param-profileImageURL=https://static-cdn.jtvnw.net/jtv_user_pictures/museun-profile_image-70x70.png;msg-param-viewerCount=7;room-id=1 :tmi.twitch.tv USERNOTICE #shaken_bot\r\n";
+        for msg in parse(input).map(|s| s.unwrap()) {
+            let msg = UserNotice::from_irc(msg).unwrap();
+            let url =
+                "https://static-cdn.jtvnw.net/jtv_user_pictures/museun-profile_image-70x70.png";
+            assert_eq!(msg.msg_param_profile_image_url().as_deref(), Some(url));
+            let raid = match msg.event().unwrap() {
+                UserNoticeEvent::Raid(raid) => raid,
+                event => panic!("unexpected event: {:?}", event),
+            };
+            assert_eq!(raid.viewer_count, Some(7));
+            assert_eq!(raid.profile_image_url.as_deref(), Some(url));
+        }
+    }
+
+    #[test]
+    fn user_notice_event_shared_chat() {
+        let input = "@msg-id=sharedchatnotice;source-msg-id=announcement;msg-param-color=BLUE;source-room-id=1 :tmi.twitch.tv USERNOTICE #museun :hello\r\n";
+        for msg in parse(input).map(|s| s.unwrap()) {
+            let msg = UserNotice::from_irc(msg).unwrap();
+            assert_eq!(msg.shared_chat_source().unwrap().room_id, 1);
+            assert_eq!(msg.msg_id(), Some(NoticeType::SharedChatNotice));
+            assert_eq!(
+                msg.event().unwrap(),
+                UserNoticeEvent::SharedChatNotice(Box::new(UserNoticeEvent::Announcement(
+                    Announcement {
+                        color: Some(crate::twitch::AnnouncementColor::Blue)
+                    }
+                )))
+            );
+        }
+    }
+
+    #[test]
+    fn user_notice_event_announcement() {
+        use crate::twitch::AnnouncementColor;
+        let input = "@msg-id=announcement;msg-param-color=PRIMARY;login=museun :tmi.twitch.tv USERNOTICE #museun :hello world\r\n";
+        for msg in parse(input).map(|s| s.unwrap()) {
+            let msg = UserNotice::from_irc(msg).unwrap();
+            assert_eq!(msg.msg_id(), Some(NoticeType::Announcement));
+            assert_eq!(msg.message(), Some("hello world"));
+            assert_eq!(
+                msg.event().unwrap(),
+                UserNoticeEvent::Announcement(Announcement {
+                    color: Some(AnnouncementColor::Primary)
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn user_notice_event_unknown() {
+        let input = "@msg-id=foobar :tmi.twitch.tv USERNOTICE #museun\r\n";
+        for msg in parse(input).map(|s| s.unwrap()) {
+            let msg = UserNotice::from_irc(msg).unwrap();
+            assert_eq!(msg.event(), Some(UserNoticeEvent::Unknown("foobar")));
+        }
+
+        let input = ":tmi.twitch.tv USERNOTICE #museun\r\n";
+        for msg in parse(input).map(|s| s.unwrap()) {
+            let msg = UserNotice::from_irc(msg).unwrap();
+            assert_eq!(msg.event(), None);
+        }
+    }
 }
@@ -0,0 +1,342 @@
+use super::SubPlan;
+use crate::prelude::*;
+use crate::{irc::Tags, twitch::AnnouncementColor, MaybeOwned};
+
+/// A `USERNOTICE`, with its `msg-param-*` tags parsed.
+///
+/// This is retrieved via [UserNotice::event()](super::UserNotice::event)
+#[non_exhaustive]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+pub enum UserNoticeEvent<'a> {
+    /// A user subscribed to the channel
+    Sub(Subscription<'a>),
+    /// A user re-subscribed to the channel
+    Resub(Subscription<'a>),
+    /// A user gifted a subscription to another user
+    SubGift(SubGift<'a>),
+    /// An anonymous user gifted a subscription to another user
+    AnonSubGift(SubGift<'a>),
+    /// A user gifted subscriptions to random users in the channel
+    SubMysteryGift(MysteryGift<'a>),
+    /// A user continued a subscription they were gifted
+    GiftPaidUpgrade(GiftPaidUpgrade<'a>),
+    /// A user continued a subscription they were gifted by an anonymous user
+    AnonGiftPaidUpgrade(GiftPaidUpgrade<'a>),
+    /// A user unlocked rewards for other users in the channel
+    RewardGift(RewardGift<'a>),
+    /// Another channel raided this channel
+    Raid(Raid<'a>),
+    /// A raid was canceled
+    Unraid,
+    /// A ritual, e.g. a new chatter
+    Ritual(Ritual<'a>),
+    /// A user earned a new bits badge tier
+    BitsBadgeTier(BitsBadgeTier),
+    /// A moderator sent an announcement
+    Announcement(Announcement),
+    /// A user reached a viewer milestone, e.g. a watch streak
+    ViewerMilestone(ViewerMilestone<'a>),
+    /// An event which happened in another channel in a shared chat session
+    SharedChatNotice(Box<UserNoticeEvent<'a>>),
+    /// An unknown notice type (a catch-all)
+    Unknown(&'a str),
+}
+
+impl<'a> UserNoticeEvent<'a> {
+    pub(super) fn parse(msg_id: &'a str, tags: &Tags<'a>) -> Self {
+        match msg_id {
+            "sub" => Self::Sub(Subscription::parse(tags)),
+            "resub" => Self::Resub(Subscription::parse(tags)),
+            "subgift" => Self::SubGift(SubGift::parse(tags)),
+            "anonsubgift" => Self::AnonSubGift(SubGift::parse(tags)),
+            "submysterygift" => Self::SubMysteryGift(MysteryGift::parse(tags)),
+            "giftpaidupgrade" => Self::GiftPaidUpgrade(GiftPaidUpgrade::parse(tags)),
+            "anongiftpaidupgrade" => Self::AnonGiftPaidUpgrade(GiftPaidUpgrade::parse(tags)),
+            "rewardgift" => Self::RewardGift(RewardGift::parse(tags)),
+            "raid" => Self::Raid(Raid::parse(tags)),
+            "unraid" => Self::Unraid,
+            "ritual" => Self::Ritual(Ritual::parse(tags)),
+            "bitsbadgetier" => Self::BitsBadgeTier(BitsBadgeTier::parse(tags)),
+            "announcement" => Self::Announcement(Announcement::parse(tags)),
+            "viewermilestone" => Self::ViewerMilestone(ViewerMilestone::parse(tags)),
+            // the actual event is in 'source-msg-id', but it can't be another shared chat notice
+            "sharedchatnotice" => match tags.get("source-msg-id") {
+                Some(kind) if kind != "sharedchatnotice" => {
+                    Self::SharedChatNotice(Box::new(Self::parse(kind, tags)))
+                }
+                _ => Self::Unknown(msg_id),
+            },
+            kind => Self::Unknown(kind),
+        }
+    }
+}
+
+/// A subscription (or re-subscription) to the channel
+#[non_exhaustive]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+pub struct Subscription<'a> {
+    /// The total number of months the user has subscribed
+    pub cumulative_months: Option<u64>,
+    /// Whether the user wants their streak to be shared
+    pub should_share_streak: bool,
+    /// The number of consecutive months the user has subscribed. This is `None` if the streak isn't shared
+    pub streak_months: Option<u64>,
+    /// The subscription plan
+    #[cfg_attr(feature = "serde", serde(borrow))]
+    pub sub_plan: Option<SubPlan<'a>>,
+    /// The display name of the subscription plan
+    pub sub_plan_name: Option<MaybeOwned<'a>>,
+    /// How many months the user subscribed for, in advance
+    pub multimonth_duration: Option<u64>,
+    /// How many months into the advance subscription the user is
+    pub multimonth_tenure: Option<u64>,
+}
+
+impl<'a> Subscription<'a> {
+    fn parse(tags: &Tags<'a>) -> Self {
+        let should_share_streak = tags.get_as_bool("msg-param-should-share-streak");
+        Self {
+            cumulative_months: tags.get_parsed("msg-param-cumulative-months"),
+            should_share_streak,
+            streak_months: tags
+                .get_parsed("msg-param-streak-months")
+                .filter(|_| should_share_streak),
+            sub_plan: sub_plan(tags),
+            sub_plan_name: tags.get_unescaped("msg-param-sub-plan-name"),
+            multimonth_duration: tags.get_parsed("msg-param-multimonth-duration"),
+            multimonth_tenure: tags.get_parsed("msg-param-multimonth-tenure"),
+        }
+    }
+}
+
+/// A subscription gifted to a specific user
+#[non_exhaustive]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+pub struct SubGift<'a> {
+    /// The total number of months the recipient has subscribed
+    pub months: Option<u64>,
+    /// The display name of the recipient
+    pub recipient_display_name: Option<MaybeOwned<'a>>,
+    /// The user id of the recipient
+    pub recipient_id: Option<u64>,
+    /// The login of the recipient
+    pub recipient_user_name: Option<&'a str>,
+    /// The subscription plan
+    #[cfg_attr(feature = "serde", serde(borrow))]
+    pub sub_plan: Option<SubPlan<'a>>,
+    /// The display name of the subscription plan
+    pub sub_plan_name: Option<MaybeOwned<'a>>,
+    /// How many months were gifted
+    pub gift_months: Option<u64>,
+    /// An id which ties this gift to a [MysteryGift], if it was part of one
+    pub origin_id: Option<MaybeOwned<'a>>,
+}
+
+impl<'a> SubGift<'a> {
+    fn parse(tags: &Tags<'a>) -> Self {
+        Self {
+            months: tags.get_parsed("msg-param-months"),
+            recipient_display_name: tags.get_unescaped("msg-param-recipient-display-name"),
+            recipient_id: tags.get_parsed("msg-param-recipient-id"),
+            recipient_user_name: tags.get("msg-param-recipient-user-name"),
+            sub_plan: sub_plan(tags),
+            sub_plan_name: tags.get_unescaped("msg-param-sub-plan-name"),
+            gift_months: tags.get_parsed("msg-param-gift-months"),
+            origin_id: tags.get_unescaped("msg-param-origin-id"),
+        }
+    }
+}
+
+/// Subscriptions gifted to random users in the channel
+#[non_exhaustive]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+pub struct MysteryGift<'a> {
+    /// How many subscriptions were gifted
+    pub mass_gift_count: Option<u64>,
+    /// The total number of subscriptions the user has gifted in the channel
+    pub sender_count: Option<u64>,
+    /// The subscription plan
+    #[cfg_attr(feature = "serde", serde(borrow))]
+    pub sub_plan: Option<SubPlan<'a>>,
+    /// An id which ties the individual [SubGift]s to this
+    pub origin_id: Option<MaybeOwned<'a>>,
+}
+
+impl<'a> MysteryGift<'a> {
+    fn parse(tags: &Tags<'a>) -> Self {
+        Self {
+            mass_gift_count: tags.get_parsed("msg-param-mass-gift-count"),
+            sender_count: tags.get_parsed("msg-param-sender-count"),
+            sub_plan: sub_plan(tags),
+            origin_id: tags.get_unescaped("msg-param-origin-id"),
+        }
+    }
+}
+
+/// A gifted subscription which was continued
+#[non_exhaustive]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+pub struct GiftPaidUpgrade<'a> {
+    /// The number of gifts the gifter has given during the promo
+    pub promo_gift_total: Option<u64>,
+    /// The subscription promo, if any, e.g. `Subtember 2018`
+    pub promo_name: Option<MaybeOwned<'a>>,
+    /// The login of the gifter. This is `None` if they were anonymous
+    pub sender_login: Option<&'a str>,
+    /// The display name of the gifter. This is `None` if they were anonymous
+    pub sender_name: Option<MaybeOwned<'a>>,
+}
+
+impl<'a> GiftPaidUpgrade<'a> {
+    fn parse(tags: &Tags<'a>) -> Self {
+        Self {
+            promo_gift_total: tags.get_parsed("msg-param-promo-gift-total"),
+            promo_name: tags.get_unescaped("msg-param-promo-name"),
+            sender_login: tags.get("msg-param-sender-login"),
+            sender_name: tags.get_unescaped("msg-param-sender-name"),
+        }
+    }
+}
+
+/// Rewards unlocked for other users in the channel
+#[non_exhaustive]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+pub struct RewardGift<'a> {
+    /// The domain of the reward, e.g. `pride_megacommerce_2020`
+    pub domain: Option<&'a str>,
+    /// How many users received the reward
+    pub selected_count: Option<u64>,
+    /// The total number of rewards the user has given
+    pub total_reward_count: Option<u64>,
+    /// The amount which triggered the reward
+    pub trigger_amount: Option<u64>,
+    /// What triggered the reward, e.g. `CHEER` or `SUBGIFT`
+    pub trigger_type: Option<&'a str>,
+}
+
+impl<'a> RewardGift<'a> {
+    fn parse(tags: &Tags<'a>) -> Self {
+        Self {
+            domain: tags.get("msg-param-domain"),
+            selected_count: tags.get_parsed("msg-param-selected-count"),
+            total_reward_count: tags.get_parsed("msg-param-total-reward-count"),
+            trigger_amount: tags.get_parsed("msg-param-trigger-amount"),
+            trigger_type: tags.get("msg-param-trigger-type"),
+        }
+    }
+}
+
+/// A raid from another channel
+#[non_exhaustive]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+pub struct Raid<'a> {
+    /// The display name of the raiding channel
+    pub display_name: Option<MaybeOwned<'a>>,
+    /// The login of the raiding channel
+    pub login: Option<&'a str>,
+    /// How many viewers came along with the raid
+    pub viewer_count: Option<u64>,
+    /// The profile image of the raiding channel
+    pub profile_image_url: Option<MaybeOwned<'a>>,
+}
+
+impl<'a> Raid<'a> {
+    fn parse(tags: &Tags<'a>) -> Self {
+        Self {
+            display_name: tags.get_unescaped("msg-param-displayName"),
+            login: tags.get("msg-param-login"),
+            viewer_count: tags.get_parsed("msg-param-viewerCount"),
+            profile_image_url: tags.get_unescaped("msg-param-profileImageURL"),
+        }
+    }
+}
+
+/// A ritual
+#[non_exhaustive]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+pub struct Ritual<'a> {
+    /// The name of the ritual, e.g. `new_chatter`
+    pub ritual_name: Option<&'a str>,
+}
+
+impl<'a> Ritual<'a> {
+    fn parse(tags: &Tags<'a>) -> Self {
+        Self {
+            ritual_name: tags.get("msg-param-ritual-name"),
+        }
+    }
+}
+
+/// A new bits badge tier
+#[non_exhaustive]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+pub struct BitsBadgeTier {
+    /// The tier of the bits badge, e.g. 100, 1000, 10000
+    pub threshold: Option<u64>,
+}
+
+impl BitsBadgeTier {
+    fn parse(tags: &Tags<'_>) -> Self {
+        Self {
+            threshold: tags.get_parsed("msg-param-threshold"),
+        }
+    }
+}
+
+/// An announcement sent by a moderator
+#[non_exhaustive]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+pub struct Announcement {
+    /// The color of the announcement. This is `None` if the color is unknown
+    pub color: Option<AnnouncementColor>,
+}
+
+impl Announcement {
+    fn parse(tags: &Tags<'_>) -> Self {
+        Self {
+            color: tags
+                .get("msg-param-color")
+                .and_then(AnnouncementColor::parse),
+        }
+    }
+}
+
+/// A viewer milestone
+#[non_exhaustive]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+pub struct ViewerMilestone<'a> {
+    /// The kind of milestone, e.g. `watch-streak`
+    pub category: Option<&'a str>,
+    /// The id of the milestone
+    pub id: Option<&'a str>,
+    /// The value of the milestone, e.g. the number of streams watched in a row
+    pub value: Option<u64>,
+    /// How many channel points were rewarded for this milestone
+    pub copo_reward: Option<u64>,
+}
+
+impl<'a> ViewerMilestone<'a> {
+    fn parse(tags: &Tags<'a>) -> Self {
+        Self {
+            category: tags.get("msg-param-category"),
+            id: tags.get("msg-param-id"),
+            value: tags.get_parsed("msg-param-value"),
+            copo_reward: tags.get_parsed("msg-param-copoReward"),
+        }
+    }
+}
+
+fn sub_plan<'a>(tags: &Tags<'a>) -> Option<SubPlan<'a>> {
+    tags.get("msg-param-sub-plan").map(SubPlan::parse)
+}
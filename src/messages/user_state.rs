@@ -1,4 +1,8 @@
-use crate::twitch::{parse_badges, parse_emotes, Badge, BadgeInfo, Color, Emotes};
+use crate::prelude::*;
+use crate::twitch::{
+    parse_badges, parse_badges_iter, parse_emotes, parse_typed_badges, subscriber_months, Badge,
+    BadgeInfo, BadgeKind, Color, Emotes, Role, TypedBadge,
+};
 use crate::{irc::*, MaybeOwned, MaybeOwnedIndex, Validator};
 
 /// Identifies a user's chat settings or properties (e.g., chat color)..
@@ -36,14 +40,24 @@ impl<'a> UserState<'a> {
             .unwrap_or_default()
     }
 
+    /// Badges attached to this message, with their versions and metadata parsed
+    pub fn typed_badges(&self) -> Vec<TypedBadge<'_>> {
+        parse_typed_badges(&self.tags())
+    }
+
+    /// The number of months the user has been subscribed, if they are a subscriber
+    pub fn subscriber_months(&self) -> Option<u64> {
+        subscriber_months(&self.tags())
+    }
+
     /// The user's color, if set
     pub fn color(&self) -> Option<Color> {
         self.tags().get_parsed("color")
     }
 
     /// The user's display name, if set
-    pub fn display_name(&self) -> Option<&str> {
-        self.tags().get("display-name")
+    pub fn display_name(&self) -> Option<MaybeOwned<'_>> {
+        self.tags().get_unescaped("display-name")
     }
 
     /// Emotes attached to this message
@@ -54,10 +68,50 @@ impl<'a> UserState<'a> {
             .unwrap_or_default()
     }
 
+    /// The id of the message we just sent, if this was sent in response to one
+    pub fn id(&self) -> Option<&str> {
+        self.tags().get("id")
+    }
+
+    /// The `client-nonce` of the message we just sent, if it had one
+    pub fn client_nonce(&self) -> Option<&str> {
+        self.tags().get("client-nonce")
+    }
+
+    /// The role this user has in this channel
+    pub fn role(&self) -> Role {
+        Role::from_tags(&self.tags())
+    }
+
     /// Whether this user is a moderator
     pub fn is_moderator(&self) -> bool {
         self.tags().get_as_bool("mod")
     }
+
+    /// Whether this user is the broadcaster
+    pub fn is_broadcaster(&self) -> bool {
+        self.contains_badge(BadgeKind::Broadcaster)
+    }
+
+    /// Whether this user is a vip
+    pub fn is_vip(&self) -> bool {
+        self.contains_badge(BadgeKind::VIP)
+    }
+
+    /// Whether this user gets the elevated (moderator) rate limit on this channel
+    ///
+    /// This is true for moderators, vips and the broadcaster.
+    pub fn is_elevated(&self) -> bool {
+        self.is_moderator() || self.is_broadcaster() || self.is_vip()
+    }
+
+    fn contains_badge(&self, badge: BadgeKind<'_>) -> bool {
+        self.tags()
+            .get("badges")
+            .into_iter()
+            .flat_map(parse_badges_iter)
+            .any(|x| x.kind == badge)
+    }
 }
 
 impl<'a> FromIrcMessage<'a> for UserState<'a> {
@@ -80,7 +134,16 @@ impl<'a> FromIrcMessage<'a> for UserState<'a> {
 
 into_owned!(UserState { raw, tags, channel });
 impl_custom_debug!(UserState { raw, tags, channel });
-serde_struct!(UserState { raw, tags, channel });
+serde_struct!(UserState {
+    raw,
+    tags,
+    channel,
+    badge_info,
+    badges,
+    color,
+    display_name,
+    emotes,
+});
 
 #[cfg(test)]
 mod tests {
@@ -100,6 +163,49 @@ mod tests {
         for msg in parse(input).map(|s| s.unwrap()) {
             let msg = UserState::from_irc(msg).unwrap();
             assert_eq!(msg.channel(), "#museun");
+            assert!(!msg.is_elevated());
+        }
+    }
+
+    #[test]
+    fn user_state_typed_badges() {
+        let input = "@badge-info=subscriber/3;badges=subscriber/3,bits/100;mod=0 :tmi.twitch.tv USERSTATE #museun\r\n";
+        for msg in parse(input).map(|s| s.unwrap()) {
+            let msg = UserState::from_irc(msg).unwrap();
+            assert_eq!(
+                msg.typed_badges(),
+                vec![
+                    TypedBadge::Subscriber { months: 3 },
+                    TypedBadge::Bits { tier: 100 }
+                ]
+            );
+            assert_eq!(msg.subscriber_months(), Some(3));
+        }
+    }
+
+    #[test]
+    fn user_state_elevated() {
+        let tests = &[
+            ("@badges=;mod=1 :tmi.twitch.tv USERSTATE #museun\r\n", true),
+            (
+                "@badges=broadcaster/1;mod=0 :tmi.twitch.tv USERSTATE #museun\r\n",
+                true,
+            ),
+            (
+                "@badges=vip/1;mod=0 :tmi.twitch.tv USERSTATE #museun\r\n",
+                true,
+            ),
+            (
+                "@badges=bits/1000;mod=0 :tmi.twitch.tv USERSTATE #museun\r\n",
+                false,
+            ),
+        ];
+
+        for (input, expected) in tests {
+            for msg in parse(input).map(|s| s.unwrap()) {
+                let msg = UserState::from_irc(msg).unwrap();
+                assert_eq!(msg.is_elevated(), *expected, "{}", input);
+            }
         }
     }
 }
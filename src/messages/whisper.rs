@@ -1,5 +1,6 @@
+use crate::prelude::*;
 use crate::twitch::{
-    parse_badges, parse_badges_iter, parse_emotes, Badge, BadgeKind, Color, Emotes,
+    parse_badges, parse_badges_iter, parse_emotes, Badge, BadgeKind, ChatUser, Color, Emotes,
 };
 use crate::{irc::*, MaybeOwned, MaybeOwnedIndex, Validator};
 
@@ -38,8 +39,13 @@ impl<'a> Whisper<'a> {
     /// their display name to **FOO** then this'll return that **FOO**.
     ///
     /// Otherwise it'll return `None`.
-    pub fn display_name(&'a self) -> Option<&'a str> {
-        self.tags().get("display-name")
+    pub fn display_name(&self) -> Option<MaybeOwned<'_>> {
+        self.tags().get_unescaped("display-name")
+    }
+
+    /// Who sent this message, with their display name and user id. See [ChatUser] for the name to show.
+    pub fn sender(&self) -> ChatUser<'_> {
+        ChatUser::from_parts(self.name(), self.display_name(), self.user_id())
     }
 
     /// Badges attached to this message
@@ -78,11 +84,30 @@ impl<'a> Whisper<'a> {
         self.tags().get_parsed("tmi-sent-ts")
     }
 
+    /// When Twitch received this message
+    #[cfg(feature = "std")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+    pub fn sent_at(&self) -> Option<std::time::SystemTime> {
+        self.tags().get_timestamp("tmi-sent-ts").ok()
+    }
+
     /// The id of the user who sent this message
     pub fn user_id(&self) -> Option<u64> {
         self.tags().get_parsed("user-id")
     }
 
+    /// The id of this whisper
+    pub fn message_id(&self) -> Option<&str> {
+        self.tags().get("message-id")
+    }
+
+    /// The id of the conversation this whisper is part of.
+    ///
+    /// This is the two user ids joined with an `_`, e.g. `1234_5678`
+    pub fn thread_id(&self) -> Option<&str> {
+        self.tags().get("thread-id")
+    }
+
     fn contains_badge(&self, badge: BadgeKind<'_>) -> bool {
         self.tags()
             .get("badges")
@@ -131,6 +156,13 @@ serde_struct!(Whisper {
     tags,
     name,
     data,
+    badges,
+    color,
+    display_name,
+    emotes,
+    user_id,
+    message_id,
+    thread_id,
 });
 
 #[cfg(test)]
@@ -155,4 +187,18 @@ mod tests {
             assert_eq!(msg.data(), "this is a test");
         }
     }
+
+    #[test]
+    fn whisper_tags() {
+        let input = "@badges=;color=#FF69B4;display-name=Test;emotes=;message-id=12;thread-id=1234_5678;turbo=0;user-id=1234;user-type= :test!test@test.tmi.twitch.tv WHISPER museun :hello\r\n";
+        for msg in parse(input).map(|s| s.unwrap()) {
+            let msg = Whisper::from_irc(msg).unwrap();
+
+            assert_eq!(msg.name(), "test");
+            assert_eq!(msg.display_name().as_deref(), Some("Test"));
+            assert_eq!(msg.user_id(), Some(1234));
+            assert_eq!(msg.message_id(), Some("12"));
+            assert_eq!(msg.thread_id(), Some("1234_5678"));
+        }
+    }
 }
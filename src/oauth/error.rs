@@ -0,0 +1,60 @@
+/// An error returned by the [oauth](super) helpers
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// An I/O error occured
+    Io(std::io::Error),
+    /// Twitch sent a response we could not deserialize
+    Json(serde_json::Error),
+    /// The access token is invalid (or has expired)
+    InvalidToken,
+    /// Twitch rejected the request (e.g. the refresh token is invalid)
+    Http {
+        /// The HTTP status code
+        status: u16,
+        /// The error message Twitch gave
+        message: String,
+    },
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "io error: {}", err),
+            Self::Json(err) => write!(f, "could not deserialize response: {}", err),
+            Self::InvalidToken => write!(f, "the access token is invalid or has expired"),
+            Self::Http { status, message } => write!(f, "http error ({}): {}", status, message),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            Self::Json(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        Self::Json(err)
+    }
+}
+
+impl From<Error> for std::io::Error {
+    fn from(err: Error) -> Self {
+        match err {
+            Error::Io(err) => err,
+            err => Self::new(std::io::ErrorKind::Other, err),
+        }
+    }
+}
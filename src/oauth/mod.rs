@@ -0,0 +1,424 @@
+//! Helpers for Twitch's [OAuth](https://dev.twitch.tv/docs/authentication) endpoints.
+//!
+//! User access tokens expire. If you have a ***refresh token*** (and your app's client id/secret) you can use a [RefreshingToken] to get fresh access tokens automatically.
+//!
+//! It implements [TokenProvider](crate::twitch::TokenProvider), so you can give it to [Reconnect::with_token_provider()](crate::runner::Reconnect::with_token_provider()) or [AsyncRunner::connect_with_token_provider()](crate::AsyncRunner::connect_with_token_provider()).
+//! The token is refreshed shortly before it expires, or when Twitch rejects it. Each reconnect re-registers (`PASS`/`NICK`) with the current token.
+//!
+//! You can check a token before connecting with [validate_token()], which will tell you who it belongs to and what scopes it has.
+//!
+//! # Example
+//! ```no_run
+//! # #[cfg(all(feature = "async-io", feature = "async-tls"))]
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use twitchchat::connector::async_io::ConnectorTls;
+//! use twitchchat::{oauth, runner::Reconnect, UserConfig};
+//!
+//! let id = ConnectorTls::custom(oauth::ID_ADDRESS, oauth::ID_HOST)?;
+//! let provider = oauth::RefreshingToken::new(id, "client-id", "client-secret", "refresh-token");
+//!
+//! // the token here is replaced with the one from the provider
+//! let user_config = UserConfig::builder()
+//!     .name("shaken_bot")
+//!     .token(format!("oauth:{}", "a".repeat(30)))
+//!     .build()?;
+//!
+//! let connector = ConnectorTls::twitch()?;
+//! let reconnect = Reconnect::new(connector, &user_config).with_token_provider(provider);
+//! # Ok(())
+//! # }
+//! # #[cfg(not(all(feature = "async-io", feature = "async-tls")))]
+//! # fn main() {}
+//! ```
+use crate::{connector::Connector, http, twitch::TokenProvider, BoxedFuture};
+
+use serde::Deserialize;
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+mod error;
+pub use error::Error;
+
+/// The host for the Twitch OAuth endpoints
+pub const ID_HOST: &str = "id.twitch.tv";
+
+/// The socket address for the Twitch OAuth endpoints (this requires TLS)
+pub const ID_ADDRESS: &str = "id.twitch.tv:443";
+
+// refresh the token if it expires within this window
+const REFRESH_MARGIN: Duration = Duration::from_secs(5 * 60);
+
+/// A token returned by Twitch
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[non_exhaustive]
+pub struct Token {
+    /// The access token
+    pub access_token: String,
+    /// The refresh token. Twitch may give you a new one, so you should store this
+    #[serde(default)]
+    pub refresh_token: Option<String>,
+    /// How many seconds until the access token expires
+    #[serde(default)]
+    pub expires_in: Option<u64>,
+    /// The scopes of the access token
+    #[serde(default)]
+    pub scope: Vec<String>,
+    /// The type of the token, generally `bearer`
+    #[serde(default)]
+    pub token_type: String,
+}
+
+/// Information about an access token, returned by [validate_token()]
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[non_exhaustive]
+pub struct Validation {
+    /// The client id the token was issued for
+    pub client_id: String,
+    /// The login of the user the token belongs to. This is `None` for app access tokens
+    #[serde(default)]
+    pub login: Option<String>,
+    /// The user id the token belongs to. This is `None` for app access tokens
+    #[serde(default)]
+    pub user_id: Option<String>,
+    /// The scopes of the token
+    #[serde(default)]
+    pub scopes: Vec<String>,
+    /// How many seconds until the token expires
+    #[serde(default)]
+    pub expires_in: u64,
+}
+
+impl Validation {
+    /// Does the token have this scope?
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scopes.iter().any(|s| s == scope)
+    }
+
+    /// Can this token be used to read from and write to chat? (e.g. has the `chat:read` and `chat:edit` scopes)
+    pub fn can_chat(&self) -> bool {
+        self.has_scope("chat:read") && self.has_scope("chat:edit")
+    }
+
+    /// How long until the token expires
+    pub fn expires_in(&self) -> Duration {
+        Duration::from_secs(self.expires_in)
+    }
+}
+
+/// Check that this access token is valid, returning who it belongs to and what scopes it has.
+///
+/// This lets you fail fast with a clear error, rather than Twitch closing the connection with `Login authentication failed`.
+///
+/// The token can be in the form used by [UserConfig](crate::UserConfig) (with an `oauth:` prefix), or without it.
+///
+/// The `connector` should be a TLS connector pointed at [ID_ADDRESS].
+///
+/// This returns [Error::InvalidToken] if the token is invalid or has expired.
+pub async fn validate_token<C>(connector: &mut C, token: &str) -> Result<Validation, Error>
+where
+    C: Connector,
+{
+    let token = token.strip_prefix("oauth:").unwrap_or(token);
+    let req = http::Request::new("GET", ID_HOST, "/oauth2/validate")
+        .header("Authorization", format!("OAuth {}", token));
+
+    let resp = http::send(connector, req).await?;
+    if resp.status == 401 {
+        return Err(Error::InvalidToken);
+    }
+    check(&resp)?;
+    resp.json().map_err(Into::into)
+}
+
+/// Use a refresh token to get a new access token.
+///
+/// The `connector` should be a TLS connector pointed at [ID_ADDRESS].
+pub async fn refresh_token<C>(
+    connector: &mut C,
+    client_id: &str,
+    client_secret: &str,
+    refresh_token: &str,
+) -> Result<Token, Error>
+where
+    C: Connector,
+{
+    let req = http::Request::new("POST", ID_HOST, "/oauth2/token").form(&[
+        ("grant_type", "refresh_token"),
+        ("refresh_token", refresh_token),
+        ("client_id", client_id),
+        ("client_secret", client_secret),
+    ]);
+
+    let resp = http::send(connector, req).await?;
+    check(&resp)?;
+    resp.json().map_err(Into::into)
+}
+
+// turns an error response into an Error
+fn check(resp: &http::Response) -> Result<(), Error> {
+    #[derive(Deserialize)]
+    struct ErrorBody {
+        message: String,
+    }
+
+    if resp.is_success() {
+        return Ok(());
+    }
+
+    let message = match resp.json::<ErrorBody>() {
+        Ok(ErrorBody { message }) => message,
+        Err(..) => String::from_utf8_lossy(&resp.body).into_owned(),
+    };
+    Err(Error::Http {
+        status: resp.status,
+        message,
+    })
+}
+
+#[derive(Debug)]
+struct State {
+    access_token: Option<String>,
+    refresh_token: String,
+    expires_at: Option<Instant>,
+}
+
+/// A [TokenProvider] which uses a refresh token (and your app's client id/secret) to keep the access token fresh.
+///
+/// Clones of this share the same tokens.
+#[derive(Clone)]
+pub struct RefreshingToken<C> {
+    connector: C,
+    client_id: String,
+    client_secret: String,
+    state: Arc<Mutex<State>>,
+}
+
+impl<C> std::fmt::Debug for RefreshingToken<C> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RefreshingToken")
+            .field("client_id", &self.client_id)
+            .finish()
+    }
+}
+
+impl<C> RefreshingToken<C>
+where
+    C: Connector + 'static,
+{
+    /// Create a new provider.
+    ///
+    /// The `connector` should be a TLS connector pointed at [ID_ADDRESS]. The first call to [TokenProvider::token()] will refresh the token.
+    pub fn new(
+        connector: C,
+        client_id: impl Into<String>,
+        client_secret: impl Into<String>,
+        refresh_token: impl Into<String>,
+    ) -> Self {
+        Self {
+            connector,
+            client_id: client_id.into(),
+            client_secret: client_secret.into(),
+            state: Arc::new(Mutex::new(State {
+                access_token: None,
+                refresh_token: refresh_token.into(),
+                expires_at: None,
+            })),
+        }
+    }
+
+    /// Use this access token until it expires (after `expires_in`), rather than refreshing right away.
+    pub fn with_access_token(
+        self,
+        access_token: impl Into<String>,
+        expires_in: impl Into<Option<Duration>>,
+    ) -> Self {
+        {
+            let mut state = self.state.lock().unwrap();
+            state.access_token.replace(access_token.into());
+            state.expires_at = expires_in.into().map(|d| Instant::now() + d);
+        }
+        self
+    }
+
+    /// Get the current refresh token.
+    ///
+    /// Twitch may give out a new refresh token when refreshing, so you should store this.
+    pub fn current_refresh_token(&self) -> String {
+        self.state.lock().unwrap().refresh_token.clone()
+    }
+
+    // the access token, if it isn't about to expire
+    fn current(&self) -> Option<String> {
+        let state = self.state.lock().unwrap();
+        match state.expires_at {
+            Some(at) if at <= Instant::now() + REFRESH_MARGIN => None,
+            _ => state.access_token.clone(),
+        }
+    }
+
+    async fn do_refresh(mut self) -> Result<String, Error> {
+        let refresh = self.current_refresh_token();
+        log::debug!("refreshing the access token");
+
+        let token = refresh_token(
+            &mut self.connector,
+            &self.client_id,
+            &self.client_secret,
+            &refresh,
+        )
+        .await?;
+
+        let mut state = self.state.lock().unwrap();
+        state.access_token.replace(token.access_token.clone());
+        state.expires_at = token
+            .expires_in
+            .map(|secs| Instant::now() + Duration::from_secs(secs));
+        if let Some(refresh) = token.refresh_token {
+            state.refresh_token = refresh;
+        }
+        Ok(token.access_token)
+    }
+}
+
+impl<C> TokenProvider for RefreshingToken<C>
+where
+    C: Connector + 'static,
+{
+    fn token(&mut self) -> BoxedFuture<std::io::Result<String>> {
+        let this = self.clone();
+        Box::pin(async move {
+            match this.current() {
+                Some(token) => Ok(token),
+                None => this.do_refresh().await.map_err(Into::into),
+            }
+        })
+    }
+
+    fn refresh(&mut self) -> BoxedFuture<std::io::Result<String>> {
+        let this = self.clone();
+        Box::pin(async move { this.do_refresh().await.map_err(Into::into) })
+    }
+}
+
+#[cfg(all(test, feature = "testing"))]
+mod tests {
+    use super::*;
+    use crate::test::TestConnector;
+    use futures_lite::future::block_on;
+
+    fn respond(connector: &TestConnector, status: &str, body: &str) {
+        let resp = format!(
+            "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        );
+        block_on(connector.conn.write_data(resp));
+    }
+
+    #[test]
+    fn validate() {
+        let connector = TestConnector::default();
+        respond(
+            &connector,
+            "200 OK",
+            r#"{
+                "client_id":"wbmytr93xzw8zbg0p1izqyzzc5mbiz",
+                "login":"twitchdev",
+                "scopes":["chat:read","chat:edit"],
+                "user_id":"141981764",
+                "expires_in":5520838
+            }"#,
+        );
+
+        let validation =
+            block_on(validate_token(&mut connector.clone(), "oauth:my-token")).unwrap();
+        assert_eq!(validation.login.as_deref(), Some("twitchdev"));
+        assert_eq!(validation.user_id.as_deref(), Some("141981764"));
+        assert_eq!(validation.expires_in(), Duration::from_secs(5520838));
+        assert!(validation.can_chat());
+        assert!(!validation.has_scope("channel:moderate"));
+
+        let lines = block_on(connector.conn.read_all_lines()).unwrap();
+        assert_eq!(lines[0], "GET /oauth2/validate HTTP/1.1\r\n");
+        assert!(lines.contains(&"Authorization: OAuth my-token\r\n".to_string()));
+    }
+
+    #[test]
+    fn validate_invalid_token() {
+        let connector = TestConnector::default();
+        respond(
+            &connector,
+            "401 Unauthorized",
+            r#"{"status":401,"message":"invalid access token"}"#,
+        );
+
+        let err = block_on(validate_token(&mut connector.clone(), "my-token")).unwrap_err();
+        assert!(matches!(err, Error::InvalidToken));
+    }
+
+    #[test]
+    fn refreshing_token() {
+        let connector = TestConnector::default();
+        respond(
+            &connector,
+            "200 OK",
+            r#"{
+                "access_token":"new-access",
+                "refresh_token":"new-refresh",
+                "expires_in":14400,
+                "scope":["chat:read","chat:edit"],
+                "token_type":"bearer"
+            }"#,
+        );
+
+        let mut provider = RefreshingToken::new(connector.clone(), "id", "secret", "old-refresh");
+        assert_eq!(block_on(provider.token()).unwrap(), "new-access");
+        assert_eq!(provider.current_refresh_token(), "new-refresh");
+
+        let lines = block_on(connector.conn.read_all_lines()).unwrap();
+        assert_eq!(lines[0], "POST /oauth2/token HTTP/1.1\r\n");
+        assert_eq!(
+            lines.last().unwrap(),
+            "grant_type=refresh_token&refresh_token=old-refresh&client_id=id&client_secret=secret\r\n"
+        );
+
+        // this is still valid, so it shouldn't be refreshed
+        assert_eq!(block_on(provider.token()).unwrap(), "new-access");
+        assert!(block_on(connector.conn.read_all_lines())
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn refresh_expiring_token() {
+        let connector = TestConnector::default();
+        respond(&connector, "200 OK", r#"{"access_token":"new-access"}"#);
+
+        let mut provider = RefreshingToken::new(connector, "id", "secret", "refresh")
+            .with_access_token("old-access", Duration::from_secs(60));
+        assert_eq!(block_on(provider.token()).unwrap(), "new-access");
+        // twitch didn't give us a new refresh token
+        assert_eq!(provider.current_refresh_token(), "refresh");
+    }
+
+    #[test]
+    fn invalid_refresh_token() {
+        let connector = TestConnector::default();
+        respond(
+            &connector,
+            "400 Bad Request",
+            r#"{"status":400,"message":"Invalid refresh token"}"#,
+        );
+
+        let mut provider = RefreshingToken::new(connector, "id", "secret", "refresh")
+            .with_access_token("old-access", None);
+        // this token never expires, so it is used until Twitch rejects it
+        assert_eq!(block_on(provider.token()).unwrap(), "old-access");
+
+        let err = block_on(provider.refresh()).unwrap_err();
+        assert_eq!(err.to_string(), "http error (400): Invalid refresh token");
+    }
+}
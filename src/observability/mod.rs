@@ -0,0 +1,4 @@
+//! Exporters for the runner's [Metrics](crate::runner::Metrics).
+//!
+//! * [prometheus] renders them in the Prometheus text format, for a `/metrics` endpoint
+pub mod prometheus;
@@ -0,0 +1,315 @@
+//! [Metrics] for the runner, in the Prometheus text format.
+//!
+//! Give a [Registry] to the runner, and serve [Registry::render()] from your `/metrics` endpoint:
+//! ```no_run
+//! # use twitchchat::observability::prometheus::Registry;
+//! # fn demo(runner: &mut twitchchat::AsyncRunner) {
+//! let registry = Registry::new();
+//! runner.set_metrics(registry.clone());
+//!
+//! // in your http handler
+//! let body = registry.render();
+//! # }
+//! ```
+//!
+//! These are exported:
+//!
+//! | name | type | |
+//! | --- | --- | --- |
+//! | `twitchchat_connections` | gauge | runners which are connected |
+//! | `twitchchat_reconnects_total` | counter | times a `Reconnect` re-established its connection |
+//! | `twitchchat_messages_total` | counter | messages read and parsed |
+//! | `twitchchat_parse_errors_total` | counter | lines read which couldn't be parsed |
+//! | `twitchchat_read_bytes_total` | counter | bytes read |
+//! | `twitchchat_written_bytes_total` | counter | bytes written |
+//! | `twitchchat_writer_queue_depth` | gauge | messages waiting to be sent |
+//! | `twitchchat_throttled_total` | counter | times sending was held back by a rate limit |
+//! | `twitchchat_throttled_seconds_total` | counter | how long sending was held back for |
+//! | `twitchchat_latency_seconds` | histogram | round-trip time of the keep-alive `PING` |
+//! | `twitchchat_chat_messages_total{channel}` | counter | chat messages read, per channel (use `rate()` for the message rate) |
+use crate::runner::Metrics;
+
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    sync::{
+        atomic::{AtomicI64, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+// the upper bounds of the latency histogram, in seconds
+const LATENCY_BUCKETS: &[f64] = &[0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+#[derive(Default)]
+struct Histogram {
+    // the count in each bucket, with the last one for everything above the largest bound
+    buckets: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn observe(&mut self, value: f64) {
+        if self.buckets.is_empty() {
+            self.buckets = vec![0; LATENCY_BUCKETS.len() + 1];
+        }
+        let index = LATENCY_BUCKETS
+            .iter()
+            .position(|&bound| value <= bound)
+            .unwrap_or(LATENCY_BUCKETS.len());
+        self.buckets[index] += 1;
+        self.sum += value;
+        self.count += 1;
+    }
+}
+
+/// The metrics the runner reports into, rendered in the Prometheus text format. See the [module docs](self).
+///
+/// This can be shared by several runners (e.g. a [ShardManager](crate::runner::ShardManager)), the counters are their totals.
+#[derive(Default)]
+pub struct Registry {
+    connections: AtomicI64,
+    reconnects: AtomicU64,
+    messages: AtomicU64,
+    parse_errors: AtomicU64,
+    read_bytes: AtomicU64,
+    written_bytes: AtomicU64,
+    queue_depth: AtomicU64,
+    throttled: AtomicU64,
+    throttled_micros: AtomicU64,
+    latency: Mutex<Histogram>,
+    channels: Mutex<BTreeMap<String, u64>>,
+}
+
+impl std::fmt::Debug for Registry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Registry")
+            .field("connections", &self.connections)
+            .field("messages", &self.messages)
+            .finish()
+    }
+}
+
+impl Registry {
+    /// Create an empty registry
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self::default())
+    }
+
+    /// Render every metric, in the Prometheus text format
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        let load = |n: &AtomicU64| n.load(Ordering::Relaxed);
+
+        let mut metric = |name: &str, kind: &str, help: &str, value: &dyn std::fmt::Display| {
+            let _ = writeln!(out, "# HELP {} {}", name, help);
+            let _ = writeln!(out, "# TYPE {} {}", name, kind);
+            let _ = writeln!(out, "{} {}", name, value);
+        };
+
+        metric(
+            "twitchchat_connections",
+            "gauge",
+            "Runners which are connected",
+            &self.connections.load(Ordering::Relaxed),
+        );
+        metric(
+            "twitchchat_reconnects_total",
+            "counter",
+            "Times a Reconnect re-established its connection",
+            &load(&self.reconnects),
+        );
+        metric(
+            "twitchchat_messages_total",
+            "counter",
+            "Messages read and parsed",
+            &load(&self.messages),
+        );
+        metric(
+            "twitchchat_parse_errors_total",
+            "counter",
+            "Lines read which couldn't be parsed",
+            &load(&self.parse_errors),
+        );
+        metric(
+            "twitchchat_read_bytes_total",
+            "counter",
+            "Bytes read",
+            &load(&self.read_bytes),
+        );
+        metric(
+            "twitchchat_written_bytes_total",
+            "counter",
+            "Bytes written",
+            &load(&self.written_bytes),
+        );
+        metric(
+            "twitchchat_writer_queue_depth",
+            "gauge",
+            "Messages waiting to be sent",
+            &load(&self.queue_depth),
+        );
+        metric(
+            "twitchchat_throttled_total",
+            "counter",
+            "Times sending was held back by a rate limit",
+            &load(&self.throttled),
+        );
+        metric(
+            "twitchchat_throttled_seconds_total",
+            "counter",
+            "How long sending was held back by a rate limit",
+            &(load(&self.throttled_micros) as f64 / 1e6),
+        );
+
+        {
+            let latency = self.latency.lock().unwrap();
+            let _ = writeln!(
+                out,
+                "# HELP twitchchat_latency_seconds Round-trip time of the keep-alive PING"
+            );
+            let _ = writeln!(out, "# TYPE twitchchat_latency_seconds histogram");
+            let mut cumulative = 0;
+            for (i, bound) in LATENCY_BUCKETS.iter().enumerate() {
+                cumulative += latency.buckets.get(i).copied().unwrap_or_default();
+                let _ = writeln!(
+                    out,
+                    "twitchchat_latency_seconds_bucket{{le=\"{}\"}} {}",
+                    bound, cumulative
+                );
+            }
+            let _ = writeln!(
+                out,
+                "twitchchat_latency_seconds_bucket{{le=\"+Inf\"}} {}",
+                latency.count
+            );
+            let _ = writeln!(out, "twitchchat_latency_seconds_sum {}", latency.sum);
+            let _ = writeln!(out, "twitchchat_latency_seconds_count {}", latency.count);
+        }
+
+        let _ = writeln!(
+            out,
+            "# HELP twitchchat_chat_messages_total Chat messages read, per channel"
+        );
+        let _ = writeln!(out, "# TYPE twitchchat_chat_messages_total counter");
+        for (channel, count) in &*self.channels.lock().unwrap() {
+            let _ = writeln!(
+                out,
+                "twitchchat_chat_messages_total{{channel=\"{}\"}} {}",
+                escape(channel),
+                count
+            );
+        }
+
+        out
+    }
+}
+
+impl Metrics for Registry {
+    fn message_parsed(&self) {
+        self.messages.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn parse_failed(&self) {
+        self.parse_errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn bytes_read(&self, bytes: usize) {
+        self.read_bytes.fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    fn bytes_written(&self, bytes: usize) {
+        self.written_bytes
+            .fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    fn writer_queue_depth(&self, depth: usize) {
+        self.queue_depth.store(depth as u64, Ordering::Relaxed);
+    }
+
+    fn throttled(&self, delay: Duration) {
+        self.throttled.fetch_add(1, Ordering::Relaxed);
+        self.throttled_micros
+            .fetch_add(delay.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    fn reconnected(&self) {
+        self.reconnects.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn connected(&self) {
+        self.connections.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn disconnected(&self) {
+        self.connections.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    fn latency(&self, latency: Duration) {
+        self.latency.lock().unwrap().observe(latency.as_secs_f64());
+    }
+
+    fn chat_message(&self, channel: &str) {
+        let mut channels = self.channels.lock().unwrap();
+        match channels.get_mut(channel) {
+            Some(count) => *count += 1,
+            None => {
+                channels.insert(channel.to_string(), 1);
+            }
+        }
+    }
+}
+
+// label values escape backslashes, quotes and newlines
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render() {
+        let registry = Registry::new();
+        registry.connected();
+        registry.message_parsed();
+        registry.message_parsed();
+        registry.parse_failed();
+        registry.throttled(Duration::from_millis(1500));
+        registry.latency(Duration::from_millis(40));
+        registry.latency(Duration::from_secs(20));
+        registry.chat_message("#museun");
+        registry.chat_message("#museun");
+        registry.chat_message("#shaken_bot");
+
+        let out = registry.render();
+        for line in &[
+            "# TYPE twitchchat_connections gauge",
+            "twitchchat_connections 1",
+            "twitchchat_messages_total 2",
+            "twitchchat_parse_errors_total 1",
+            "twitchchat_throttled_total 1",
+            "twitchchat_throttled_seconds_total 1.5",
+            "# TYPE twitchchat_latency_seconds histogram",
+            "twitchchat_latency_seconds_bucket{le=\"0.025\"} 0",
+            "twitchchat_latency_seconds_bucket{le=\"0.05\"} 1",
+            "twitchchat_latency_seconds_bucket{le=\"10\"} 1",
+            "twitchchat_latency_seconds_bucket{le=\"+Inf\"} 2",
+            "twitchchat_latency_seconds_count 2",
+            "twitchchat_chat_messages_total{channel=\"#museun\"} 2",
+            "twitchchat_chat_messages_total{channel=\"#shaken_bot\"} 1",
+        ] {
+            assert!(out.lines().any(|l| l == *line), "{}\n{}", line, out);
+        }
+
+        registry.disconnected();
+        assert!(registry.render().contains("twitchchat_connections 0\n"));
+        assert_eq!(escape("a\"b\\c"), "a\\\"b\\\\c");
+    }
+}
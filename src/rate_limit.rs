@@ -43,6 +43,32 @@ impl RateClass {
     }
 }
 
+/// A preset number of `JOIN` tokens as described by Twitch
+#[non_exhaustive]
+#[derive(Copy, Clone, Debug, Default)]
+pub enum JoinRateClass {
+    /// `20` per `10` seconds
+    #[default]
+    Regular,
+    /// `2000` per `10` seconds
+    Verified,
+}
+
+impl JoinRateClass {
+    /// Number of tickets available for this class
+    pub fn tickets(self) -> u64 {
+        match self {
+            Self::Regular => 20,
+            Self::Verified => 2000,
+        }
+    }
+
+    /// Period specified by Twitch
+    pub const fn period() -> Duration {
+        Duration::from_secs(10)
+    }
+}
+
 /// A leaky-bucket style token-based rate limiter
 #[derive(Debug, Clone)]
 pub struct RateLimit {
@@ -82,6 +108,11 @@ impl RateLimit {
         Self::full(rate_class.tickets(), RateClass::period())
     }
 
+    /// Create a rate limit from a JoinRateClass
+    pub fn from_join_class(join_rate_class: JoinRateClass) -> Self {
+        Self::full(join_rate_class.tickets(), JoinRateClass::period())
+    }
+
     /// Create a new rate limiter of `capacity` with an `initial` number of
     /// token and the `period` between refills
     pub fn new(cap: u64, initial: u64, period: Duration) -> Self {
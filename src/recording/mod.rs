@@ -0,0 +1,46 @@
+//! Record the raw messages from a connection, and replay them later.
+//!
+//! Wrap any [`Connector`](crate::connector::Connector) with a [`RecordingConnector`] and every line Twitch sends is appended to a file, prefixed with when it was received.
+//! A [`ReplayConnector`] reads that file and feeds the lines back through the [`AsyncRunner`](crate::AsyncRunner), at their original pace or faster.
+//!
+//! This is useful for regression-testing bot logic against real chat traffic.
+//!
+//! # Format
+//! Each inbound line is written as the number of milliseconds since the unix epoch, a space, then the raw line (including its `\r\n`):
+//! ```text
+//! 1612051832992 :tmi.twitch.tv 001 shaken_bot :Welcome, GLHF!
+//! 1612051833431 @badge-info=;badges=;color=#FF69B4;display-name=museun :museun!museun@museun.tmi.twitch.tv PRIVMSG #museun :hello
+//! ```
+//!
+//! # Example
+//! ```no_run
+//! # use twitchchat::recording::{RecordingConnector, ReplayConnector};
+//! # fn main() -> std::io::Result<()> {
+//! # let connector = twitchchat::connector::from_fn(|| async { Ok(futures_lite::io::Cursor::new(Vec::<u8>::new())) });
+//! // record a session
+//! let connector = RecordingConnector::new(connector, "session.log");
+//!
+//! // then later, replay it 10x faster
+//! let connector = ReplayConnector::from_file("session.log")?.with_speed(10.0);
+//! # Ok(())
+//! # }
+//! ```
+
+mod record;
+pub use record::{RecordingConnector, RecordingStream};
+
+mod replay;
+pub use replay::{ReplayConnector, ReplayStream};
+
+#[derive(Debug, Clone, PartialEq)]
+struct Entry {
+    // milliseconds since the unix epoch
+    at: u64,
+    line: String,
+}
+
+fn now_millis() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64)
+}
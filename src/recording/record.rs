@@ -0,0 +1,192 @@
+use crate::BoxedFuture;
+
+use futures_lite::{AsyncRead, AsyncWrite};
+use std::{
+    fs::{File, OpenOptions},
+    io::{Result, Write},
+    path::PathBuf,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+/// A [`Connector`](crate::connector::Connector) which records every line read from the `inner` connector.
+///
+/// Each connection appends to the same file, so a [`Reconnect`](crate::runner::Reconnect)ing session is recorded as one long session.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordingConnector<C> {
+    inner: C,
+    path: PathBuf,
+}
+
+impl<C> RecordingConnector<C>
+where
+    C: crate::connector::Connector,
+{
+    /// Create a new recording connector, over the `inner` connector, which appends to the file at `path`.
+    ///
+    /// The file is created if it doesn't exist.
+    pub fn new<P>(inner: C, path: P) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        Self {
+            inner,
+            path: path.into(),
+        }
+    }
+}
+
+impl<C> crate::connector::Connector for RecordingConnector<C>
+where
+    C: crate::connector::Connector + 'static,
+{
+    type Output = async_dup::Mutex<RecordingStream<C::Output, File>>;
+
+    fn connect(&mut self) -> BoxedFuture<Result<Self::Output>> {
+        let mut this = self.clone();
+        let fut = async move {
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&this.path)?;
+            let stream = this.inner.connect().await?;
+            Ok(async_dup::Mutex::new(RecordingStream::new(stream, file)))
+        };
+        Box::pin(fut)
+    }
+}
+
+/// A stream which writes every line read from it to a `writer`, in the [recording format](super#format).
+///
+/// Writes to the stream are passed through and aren't recorded.
+///
+/// If the recording can't be written, the read fails with that error.
+#[derive(Debug)]
+pub struct RecordingStream<S, W> {
+    stream: S,
+    writer: W,
+    partial: Vec<u8>,
+}
+
+impl<S, W> RecordingStream<S, W>
+where
+    W: Write,
+{
+    /// Create a new recording stream, recording lines read from `stream` to `writer`
+    pub fn new(stream: S, writer: W) -> Self {
+        Self {
+            stream,
+            writer,
+            partial: Vec::new(),
+        }
+    }
+
+    /// Consumes the recording stream, returning the stream and the writer
+    pub fn into_inner(self) -> (S, W) {
+        (self.stream, self.writer)
+    }
+
+    fn record(&mut self, data: &[u8]) -> Result<()> {
+        self.partial.extend_from_slice(data);
+
+        let mut wrote = false;
+        while let Some(pos) = self.partial.iter().position(|&c| c == b'\n') {
+            write!(self.writer, "{} ", super::now_millis())?;
+            self.writer.write_all(&self.partial[..=pos])?;
+            self.partial.drain(..=pos);
+            wrote = true;
+        }
+
+        if wrote {
+            self.writer.flush()?;
+        }
+        Ok(())
+    }
+}
+
+impl<S, W> AsyncRead for RecordingStream<S, W>
+where
+    S: AsyncRead + Unpin,
+    W: Write + Unpin,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<Result<usize>> {
+        let this = self.get_mut();
+        let n = futures_lite::ready!(Pin::new(&mut this.stream).poll_read(cx, buf))?;
+        this.record(&buf[..n])?;
+        Poll::Ready(Ok(n))
+    }
+}
+
+impl<S, W> AsyncWrite for RecordingStream<S, W>
+where
+    S: AsyncWrite + Unpin,
+    W: Unpin,
+{
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<Result<usize>> {
+        Pin::new(&mut self.get_mut().stream).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        Pin::new(&mut self.get_mut().stream).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        Pin::new(&mut self.get_mut().stream).poll_close(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_lite::{io::Cursor, AsyncReadExt as _, AsyncWriteExt as _};
+
+    #[test]
+    fn assert_connector_trait_is_fulfilled() {
+        use crate::connector::testing::*;
+
+        let inner = crate::connector::from_fn(|| async { Ok(Cursor::new(Vec::<u8>::new())) });
+        assert_obj_is_sane(RecordingConnector::new(inner, "session.log"));
+    }
+
+    #[test]
+    fn records_lines() {
+        let input =
+            b"PING :foo\r\n:tmi.twitch.tv 001 shaken_bot :Welcome, GLHF!\r\nPING :b".to_vec();
+        let mut stream = RecordingStream::new(Cursor::new(input), Vec::new());
+
+        futures_lite::future::block_on(async {
+            // read it in small pieces, so lines are split between reads
+            let mut buf = [0_u8; 7];
+            while stream.read(&mut buf).await.unwrap() > 0 {}
+            stream.write_all(b"PONG :foo\r\n").await.unwrap();
+        });
+
+        let (_, recording) = stream.into_inner();
+        let recording = String::from_utf8(recording).unwrap();
+        assert_eq!(
+            crate::recording::ReplayConnector::new(&recording)
+                .unwrap()
+                .len(),
+            2
+        );
+
+        let lines = recording
+            .split_terminator('\n')
+            .map(|line| line.split_at(line.find(' ').unwrap()))
+            .collect::<Vec<_>>();
+
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].1, " PING :foo\r");
+        assert_eq!(
+            lines[1].1,
+            " :tmi.twitch.tv 001 shaken_bot :Welcome, GLHF!\r"
+        );
+        for (at, _) in lines {
+            assert!(at.parse::<u64>().unwrap() > 0);
+        }
+    }
+}
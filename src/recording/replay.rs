@@ -0,0 +1,227 @@
+use super::Entry;
+use crate::BoxedFuture;
+
+use futures_lite::{AsyncRead, AsyncWrite};
+use futures_timer::Delay;
+use std::{
+    future::Future,
+    io::{Error, ErrorKind, Result},
+    path::Path,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+/// A [`Connector`](crate::connector::Connector) which replays a [recording](super#format).
+///
+/// Every connection replays the recording from the start. Once all of the lines have been read, the stream is at its end (so the runner sees an EOF).
+///
+/// Anything written to the stream is discarded.
+///
+/// By default, the lines are replayed at the pace they were recorded. See [ReplayConnector::with_speed()] to change that.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplayConnector {
+    entries: Arc<[Entry]>,
+    speed: f64,
+}
+
+impl ReplayConnector {
+    /// Create a replay connector from a recording, that was read to a string
+    pub fn new(recording: &str) -> Result<Self> {
+        let entries = recording
+            .split_terminator('\n')
+            .filter(|line| !line.trim().is_empty())
+            .enumerate()
+            .map(|(n, line)| {
+                let (at, line) = line.split_at(line.find(' ').unwrap_or(0));
+                let at = at.parse().map_err(|_| {
+                    Error::new(
+                        ErrorKind::InvalidData,
+                        format!("line {} does not start with a timestamp", n + 1),
+                    )
+                })?;
+                Ok(Entry {
+                    at,
+                    line: format!("{}\n", &line[1..]),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            entries: entries.into(),
+            speed: 1.0,
+        })
+    }
+
+    /// Create a replay connector from the recording at `path`
+    pub fn from_file<P>(path: P) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        std::fs::read_to_string(path).and_then(|recording| Self::new(&recording))
+    }
+
+    /// Replay the recording `speed` times faster than it was recorded. e.g. `2.0` is twice as fast.
+    ///
+    /// Use `f64::INFINITY` to replay it without any delays.
+    pub fn with_speed(mut self, speed: f64) -> Self {
+        self.speed = speed;
+        self
+    }
+
+    /// How many lines are in the recording
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether the recording is empty
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl crate::connector::Connector for ReplayConnector {
+    type Output = async_dup::Mutex<ReplayStream>;
+
+    fn connect(&mut self) -> BoxedFuture<Result<Self::Output>> {
+        let stream = ReplayStream {
+            entries: Arc::clone(&self.entries),
+            speed: self.speed,
+            pos: 0,
+            offset: 0,
+            delay: None,
+        };
+        Box::pin(async move { Ok(async_dup::Mutex::new(stream)) })
+    }
+}
+
+/// A stream which replays a recording, produced by a [ReplayConnector]
+pub struct ReplayStream {
+    entries: Arc<[Entry]>,
+    speed: f64,
+    pos: usize,
+    offset: usize,
+    delay: Option<Delay>,
+}
+
+impl std::fmt::Debug for ReplayStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ReplayStream")
+            .field("speed", &self.speed)
+            .field("pos", &self.pos)
+            .field("len", &self.entries.len())
+            .finish()
+    }
+}
+
+impl ReplayStream {
+    // the delay between the previous line and the current one
+    fn next_delay(&self) -> Option<Delay> {
+        let prev = self.entries.get(self.pos.checked_sub(1)?)?;
+        let next = self.entries.get(self.pos)?;
+
+        let gap = next.at.saturating_sub(prev.at) as f64 / self.speed;
+        if !gap.is_finite() || gap < 1.0 {
+            return None;
+        }
+        Some(Delay::new(Duration::from_millis(gap as u64)))
+    }
+}
+
+impl AsyncRead for ReplayStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<Result<usize>> {
+        let this = self.get_mut();
+
+        if let Some(delay) = &mut this.delay {
+            futures_lite::ready!(Pin::new(delay).poll(cx));
+            this.delay.take();
+        }
+
+        let line = match this.entries.get(this.pos) {
+            Some(entry) => &entry.line.as_bytes()[this.offset..],
+            None => return Poll::Ready(Ok(0)),
+        };
+
+        let n = line.len().min(buf.len());
+        buf[..n].copy_from_slice(&line[..n]);
+        this.offset += n;
+
+        if n == line.len() {
+            this.pos += 1;
+            this.offset = 0;
+            this.delay = this.next_delay();
+        }
+
+        Poll::Ready(Ok(n))
+    }
+}
+
+impl AsyncWrite for ReplayStream {
+    fn poll_write(self: Pin<&mut Self>, _cx: &mut Context<'_>, buf: &[u8]) -> Poll<Result<usize>> {
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::connector::Connector as _;
+    use futures_lite::AsyncReadExt as _;
+
+    const RECORDING: &str = "\
+        1000 PING :foo\r\n\
+        1000 :tmi.twitch.tv 001 shaken_bot :Welcome, GLHF!\r\n\
+        1050 PING :bar\r\n\
+    ";
+
+    #[test]
+    fn assert_connector_trait_is_fulfilled() {
+        use crate::connector::testing::*;
+        assert_obj_is_sane(ReplayConnector::new(RECORDING).unwrap());
+    }
+
+    #[test]
+    fn invalid_recording() {
+        let err = ReplayConnector::new("1000 PING :foo\r\nPING :bar\r\n").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn replay() {
+        let replay = ReplayConnector::new(RECORDING).unwrap();
+        assert_eq!(replay.len(), 3);
+
+        for speed in &[f64::INFINITY, 1.0] {
+            let mut connector = replay.clone().with_speed(*speed);
+            let start = std::time::Instant::now();
+
+            let out = futures_lite::future::block_on(async {
+                let stream = connector.connect().await.unwrap();
+                let mut out = String::new();
+                (&stream).read_to_string(&mut out).await.unwrap();
+                out
+            });
+
+            assert_eq!(
+                out,
+                "PING :foo\r\n:tmi.twitch.tv 001 shaken_bot :Welcome, GLHF!\r\nPING :bar\r\n"
+            );
+            if *speed == 1.0 {
+                assert!(start.elapsed() >= Duration::from_millis(50));
+            }
+        }
+    }
+}
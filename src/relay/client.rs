@@ -0,0 +1,359 @@
+use super::{strip_tags, Caps, Client, RelayHandle, Room};
+use crate::{channel::Receiver, commands};
+
+use futures_lite::{
+    io::BufReader, AsyncBufRead, AsyncBufReadExt as _, AsyncRead, AsyncWrite, AsyncWriteExt as _,
+};
+use std::{collections::BTreeSet, io};
+
+const CAPABILITIES: &str = "twitch.tv/commands twitch.tv/membership twitch.tv/tags";
+
+impl RelayHandle {
+    /// Serve this client (e.g. a connection accepted from a `TcpListener`), until it disconnects or the [Relay](super::Relay) stops.
+    ///
+    /// The client registers like it would with Twitch (`CAP REQ`, `PASS` and `NICK`), but with the relay's [password](super::Relay::password()).
+    /// Whatever `NICK` it uses, it's given the bot's name.
+    ///
+    /// This returns a [PermissionDenied](io::ErrorKind::PermissionDenied) error if the password was wrong
+    /// (after sending the client the `NOTICE` Twitch would have).
+    pub async fn serve<IO>(&self, io: IO) -> io::Result<()>
+    where
+        IO: AsyncRead + AsyncWrite + Send + Unpin,
+    {
+        let (read, mut write) = futures_lite::io::split(io);
+        let mut read = BufReader::new(read);
+
+        let caps = self.register(&mut read, &mut write).await?;
+
+        let (tx, rx) = crate::channel::unbounded();
+        let id = {
+            let mut state = self.shared.state.lock().unwrap();
+            let id = state.next_id;
+            state.next_id += 1;
+            let client = Client {
+                tx,
+                caps,
+                channels: BTreeSet::new(),
+            };
+            state.clients.insert(id, client);
+            id
+        };
+        log::debug!("relay client {} registered", id);
+
+        let res =
+            futures_lite::future::or(self.read_client(id, read), write_client(rx, write)).await;
+        self.disconnect(id).await;
+        log::debug!("relay client {} disconnected", id);
+        res
+    }
+
+    async fn register<R, W>(&self, read: &mut R, write: &mut W) -> io::Result<Caps>
+    where
+        R: AsyncBufRead + Unpin,
+        W: AsyncWrite + Unpin,
+    {
+        let mut caps = Caps::default();
+        let mut pass = None;
+
+        loop {
+            let line = read_line(read).await?.ok_or_else(|| {
+                io::Error::new(io::ErrorKind::UnexpectedEof, "the client disconnected")
+            })?;
+            let (command, rest) = split_command(&line);
+
+            match &*command {
+                "CAP" => {
+                    if let Some(reply) = cap(rest, &mut caps) {
+                        write.write_all(reply.as_bytes()).await?;
+                    }
+                }
+                "PASS" => pass = Some(rest.to_string()),
+                "NICK" => break,
+                "PING" => write.write_all(pong(rest).as_bytes()).await?,
+                _ => {}
+            }
+            write.flush().await?;
+        }
+
+        let password = self.shared.state.lock().unwrap().password.clone();
+        if let Some(password) = password {
+            let without_prefix = |s: &str| s.strip_prefix("oauth:").unwrap_or(s).to_string();
+            if pass.as_deref().map(without_prefix) != Some(without_prefix(&password)) {
+                write
+                    .write_all(b":tmi.twitch.tv NOTICE * :Login authentication failed\r\n")
+                    .await?;
+                write.flush().await?;
+                return Err(io::Error::new(
+                    io::ErrorKind::PermissionDenied,
+                    "the client's password was wrong",
+                ));
+            }
+        }
+
+        let name = &self.shared.name;
+        for (numeric, text) in &[
+            ("001", "Welcome, GLHF!"),
+            ("002", "Your host is tmi.twitch.tv"),
+            ("003", "This server is rather new"),
+            ("004", "-"),
+            ("375", "-"),
+            ("372", "You are in a maze of twisty passages, all alike."),
+            ("376", ">"),
+        ] {
+            let line = format!(":tmi.twitch.tv {} {} :{}\r\n", numeric, name, text);
+            write.write_all(line.as_bytes()).await?;
+        }
+        write.flush().await?;
+
+        Ok(caps)
+    }
+
+    async fn read_client<R>(&self, id: usize, mut read: R) -> io::Result<()>
+    where
+        R: AsyncBufRead + Unpin,
+    {
+        let mut writer = self.shared.writer.clone();
+
+        while let Some(line) = read_line(&mut read).await? {
+            let (command, rest) = split_command(&line);
+            match &*command {
+                "PING" => {
+                    self.shared.send_to(id, pong(rest));
+                }
+                "CAP" => {
+                    let mut caps = self.caps(id);
+                    if let Some(reply) = cap(rest, &mut caps) {
+                        self.shared.send_to(id, reply);
+                    }
+                    if let Some(client) = self.shared.state.lock().unwrap().clients.get_mut(&id) {
+                        client.caps = caps;
+                    }
+                }
+                "QUIT" => break,
+                "PONG" | "PASS" | "NICK" | "USER" => {}
+                "JOIN" => {
+                    for channel in rest.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+                        self.join(id, channel).await?;
+                    }
+                }
+                "PART" => {
+                    let channels = rest.split_whitespace().next().unwrap_or_default();
+                    for channel in channels.split(',').filter(|s| !s.is_empty()) {
+                        self.part(id, channel).await?;
+                    }
+                }
+                "PRIVMSG" => {
+                    writer.encode(format!("{}\r\n", line)).await?;
+                    self.echo(id, rest);
+                }
+                _ => writer.encode(format!("{}\r\n", line)).await?,
+            }
+
+            // the relay stopped
+            if !self.shared.state.lock().unwrap().clients.contains_key(&id) {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    fn caps(&self, id: usize) -> Caps {
+        let state = self.shared.state.lock().unwrap();
+        state.clients.get(&id).map(|c| c.caps).unwrap_or_default()
+    }
+
+    async fn join(&self, id: usize, channel: &str) -> io::Result<()> {
+        let channel = commands::Channel::new(channel).to_string();
+
+        let needs_join = {
+            let mut state = self.shared.state.lock().unwrap();
+            let state = &mut *state;
+            let client = match state.clients.get_mut(&id) {
+                Some(client) => client,
+                None => return Ok(()),
+            };
+            if !client.channels.insert(channel.clone()) {
+                return Ok(());
+            }
+
+            match state.rooms.get(&channel) {
+                // it's already joined, so this is what the client would have been sent
+                Some(room) => {
+                    let caps = client.caps;
+                    let join = self.shared.bot_line(&format!("JOIN {}", channel));
+                    let _ = client.tx.try_send(join);
+                    if caps.commands {
+                        for line in room.room_state.iter().chain(&room.user_state) {
+                            let _ = client.tx.try_send(caps.line(line).to_string());
+                        }
+                    }
+                    false
+                }
+                None => {
+                    state.rooms.insert(channel.clone(), Room::default());
+                    true
+                }
+            }
+        };
+
+        if !needs_join {
+            return Ok(());
+        }
+
+        loop {
+            let consumed = self.shared.state.lock().unwrap().join_rate_limit.consume(1);
+            match consumed {
+                Ok(..) => break,
+                Err(delay) => {
+                    log::debug!("relay join rate limit hit, waiting {:.2?}", delay);
+                    futures_timer::Delay::new(delay).await;
+                }
+            }
+        }
+
+        log::debug!("relay joining '{}'", channel);
+        self.shared
+            .writer
+            .clone()
+            .encode(commands::join(&channel))
+            .await
+    }
+
+    async fn part(&self, id: usize, channel: &str) -> io::Result<()> {
+        let channel = commands::Channel::new(channel).to_string();
+
+        let was_on = {
+            let mut state = self.shared.state.lock().unwrap();
+            match state.clients.get_mut(&id) {
+                Some(client) => client.channels.remove(&channel),
+                None => false,
+            }
+        };
+        if !was_on {
+            return Ok(());
+        }
+
+        // Twitch's PART only goes to the clients still on the channel, so this one is told here
+        let part = self.shared.bot_line(&format!("PART {}", channel));
+        self.shared.send_to(id, part);
+
+        self.part_if_unused(&channel).await
+    }
+
+    async fn part_if_unused(&self, channel: &str) -> io::Result<()> {
+        let unused = {
+            let mut state = self.shared.state.lock().unwrap();
+            let used = state.clients.values().any(|c| c.channels.contains(channel));
+            let keep = state.rooms.get(channel).map_or(true, |room| room.keep);
+            if !used && !keep {
+                state.rooms.remove(channel);
+            }
+            !used && !keep
+        };
+
+        if unused {
+            log::debug!("relay leaving '{}'", channel);
+            self.shared
+                .writer
+                .clone()
+                .encode(commands::part(channel))
+                .await?;
+        }
+        Ok(())
+    }
+
+    // the other clients on the channel see what this one sent, like they would on IRC
+    fn echo(&self, id: usize, rest: &str) {
+        let channel = match rest.split_whitespace().next() {
+            Some(channel) => commands::Channel::new(channel).to_string(),
+            None => return,
+        };
+
+        let line = self.shared.bot_line(&format!("PRIVMSG {}", rest));
+        let state = self.shared.state.lock().unwrap();
+        for (_, client) in state.clients.iter().filter(|(&other, _)| other != id) {
+            if client.channels.contains(&channel) {
+                let _ = client.tx.try_send(line.clone());
+            }
+        }
+    }
+
+    async fn disconnect(&self, id: usize) {
+        let client = self.shared.state.lock().unwrap().clients.remove(&id);
+        for channel in client.into_iter().flat_map(|c| c.channels) {
+            if let Err(err) = self.part_if_unused(&channel).await {
+                log::warn!("relay could not leave '{}': {}", channel, err);
+            }
+        }
+    }
+}
+
+async fn write_client<W>(rx: Receiver<String>, mut write: W) -> io::Result<()>
+where
+    W: AsyncWrite + Unpin,
+{
+    while let Some(line) = rx.recv().await {
+        write.write_all(line.as_bytes()).await?;
+        // write everything that's ready before flushing
+        while let Some(line) = rx.try_recv() {
+            write.write_all(line.as_bytes()).await?;
+        }
+        write.flush().await?;
+    }
+    Ok(())
+}
+
+// the next non-empty line, without its line ending
+async fn read_line<R>(read: &mut R) -> io::Result<Option<String>>
+where
+    R: AsyncBufRead + Unpin,
+{
+    loop {
+        let mut line = String::new();
+        if read.read_line(&mut line).await? == 0 {
+            return Ok(None);
+        }
+        let len = line.trim_end().len();
+        line.truncate(len);
+        if !line.is_empty() {
+            return Ok(Some(line));
+        }
+    }
+}
+
+// the uppercased command, and everything after it
+fn split_command(line: &str) -> (String, &str) {
+    let line = strip_tags(line);
+    let (command, rest) = line.split_at(line.find(' ').unwrap_or(line.len()));
+    (command.to_ascii_uppercase(), rest.trim_start())
+}
+
+fn pong(token: &str) -> String {
+    format!(":tmi.twitch.tv PONG tmi.twitch.tv {}\r\n", token)
+}
+
+// the reply to a `CAP` command, updating the requested capabilities
+pub(super) fn cap(rest: &str, caps: &mut Caps) -> Option<String> {
+    let (sub, args) = split_command(rest);
+    let args = args.trim_start_matches(':');
+    match &*sub {
+        "LS" => Some(format!(":tmi.twitch.tv CAP * LS :{}\r\n", CAPABILITIES)),
+        "REQ" => {
+            let known = args
+                .split_whitespace()
+                .all(|cap| CAPABILITIES.split(' ').any(|c| c == cap));
+            if !known {
+                return Some(format!(":tmi.twitch.tv CAP * NAK :{}\r\n", args));
+            }
+            for cap in args.split_whitespace() {
+                match cap {
+                    "twitch.tv/commands" => caps.commands = true,
+                    "twitch.tv/membership" => caps.membership = true,
+                    _ => caps.tags = true,
+                }
+            }
+            Some(format!(":tmi.twitch.tv CAP * ACK :{}\r\n", args))
+        }
+        _ => None,
+    }
+}
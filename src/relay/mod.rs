@@ -0,0 +1,514 @@
+//! A relay which shares one connection to Twitch between many local IRC clients -- like a bouncer (e.g. ZNC), specialized for Twitch.
+//!
+//! This lets several tools (e.g. a bot, a chat overlay and a plain IRC client) use the same bot identity, without each of them logging in:
+//! * the clients [register](RelayHandle::serve()) with a password you choose, instead of the OAuth token
+//! * a channel is joined once, when the first client joins it, and parted when the last one leaves (or disconnects)
+//! * the clients only get the messages for the channels they're on, and only with the capabilities (tags, commands, membership) they requested
+//! * everything they send is written to the one connection, so they share its global rate limit (and the `JOIN` rate limit)
+//!
+//! The relay doesn't listen for connections itself: accept them with your runtime's `TcpListener`, and [serve](RelayHandle::serve()) each one (e.g. on its own task).
+//! Only expose it to clients you trust (e.g. bind it to `localhost`): they can do anything the bot can.
+//!
+//! ```no_run
+//! # use futures_lite::{AsyncRead, AsyncWrite};
+//! # use twitchchat::{relay::Relay, AsyncRunner};
+//! async fn relay<C>(runner: AsyncRunner, client: C) -> Result<(), twitchchat::Error>
+//! where
+//!     C: AsyncRead + AsyncWrite + Send + Unpin,
+//! {
+//!     let relay = Relay::new(runner).password("hunter2");
+//!     let handle = relay.handle();
+//!
+//!     // e.g. for each connection your TcpListener accepts, on its own task
+//!     let serving = async move { handle.serve(client).await.map_err(Into::into) };
+//!
+//!     futures_lite::future::or(relay.run(), serving).await
+//! }
+//! ```
+use crate::{
+    channel::Sender,
+    irc::IrcMessage,
+    messages::{Commands, MessageId},
+    rate_limit::{JoinRateClass, RateLimit},
+    runner::{AsyncRunner, Error, Reader, Status, Writer},
+    Encodable as _,
+};
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::{Arc, Mutex},
+};
+
+mod client;
+
+/// Relays one [AsyncRunner]'s connection to local IRC clients.
+///
+/// This drives the runner: [Relay::run()] reads the messages from Twitch, and sends them on to the clients [served](RelayHandle::serve()) by its [handles](Relay::handle()).
+pub struct Relay {
+    reader: Reader,
+    shared: Arc<Shared>,
+}
+
+impl std::fmt::Debug for Relay {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Relay")
+            .field("name", &self.shared.name)
+            .field("clients", &self.shared.state.lock().unwrap().clients.len())
+            .finish()
+    }
+}
+
+impl Relay {
+    /// Relay this runner's connection.
+    ///
+    /// The channels it's already on stay joined, even when no client is on them.
+    pub fn new(runner: AsyncRunner) -> Self {
+        let rooms = runner
+            .channel_names()
+            .map(|channel| {
+                let room = Room {
+                    keep: true,
+                    ..Room::default()
+                };
+                (channel.to_string(), room)
+            })
+            .collect();
+
+        let state = State {
+            password: None,
+            join_rate_limit: RateLimit::from_join_class(JoinRateClass::default()),
+            next_id: 0,
+            clients: BTreeMap::new(),
+            rooms,
+        };
+
+        let name = runner.identity.username().to_string();
+        // this writes directly to the connection, only waiting for the global rate limit
+        let (reader, writer) = runner.split();
+        let shared = Shared {
+            name,
+            writer,
+            state: Mutex::new(state),
+        };
+
+        Self {
+            reader,
+            shared: Arc::new(shared),
+        }
+    }
+
+    /// The password the clients must register with (their `PASS`). Without one, every client is accepted.
+    ///
+    /// An `oauth:` prefix is ignored, as some clients add it.
+    pub fn password(self, password: &str) -> Self {
+        self.shared.state.lock().unwrap().password = Some(password.to_string());
+        self
+    }
+
+    /// The `JOIN` rate limit for the channels the clients join. This should match the runner's.
+    pub fn join_rate_class(self, join_rate_class: JoinRateClass) -> Self {
+        self.shared.state.lock().unwrap().join_rate_limit =
+            RateLimit::from_join_class(join_rate_class);
+        self
+    }
+
+    /// Get a handle for serving clients. This can be cloned, e.g. for each task which accepts connections.
+    pub fn handle(&self) -> RelayHandle {
+        RelayHandle {
+            shared: Arc::clone(&self.shared),
+        }
+    }
+
+    /// Get the runner, e.g. to subscribe to its events before it's run
+    pub fn runner_mut(&mut self) -> &mut AsyncRunner {
+        self.reader.runner_mut()
+    }
+
+    /// Read the messages from Twitch, and relay them to the clients, until the connection ends.
+    ///
+    /// The clients are disconnected when this returns.
+    pub async fn run(mut self) -> Result<(), Error> {
+        let res = self.relay().await;
+        // dropping their senders ends their connections
+        self.shared.state.lock().unwrap().clients.clear();
+        res
+    }
+
+    async fn relay(&mut self) -> Result<(), Error> {
+        loop {
+            match self.reader.next_message().await? {
+                Status::Message(msg) => self.shared.dispatch(&msg),
+                Status::Invalid(..) => {}
+                Status::Quit | Status::Stopped | Status::Eof => return Ok(()),
+            }
+        }
+    }
+}
+
+/// A handle to a [Relay], for [serving](RelayHandle::serve()) its clients
+#[derive(Clone)]
+pub struct RelayHandle {
+    shared: Arc<Shared>,
+}
+
+impl std::fmt::Debug for RelayHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RelayHandle")
+            .field("name", &self.shared.name)
+            .finish()
+    }
+}
+
+impl RelayHandle {
+    /// How many clients are connected (and registered)
+    pub fn clients(&self) -> usize {
+        self.shared.state.lock().unwrap().clients.len()
+    }
+
+    /// The channels the relay is on, for its clients (or because the runner was already on them)
+    pub fn channels(&self) -> Vec<String> {
+        let state = self.shared.state.lock().unwrap();
+        state.rooms.keys().cloned().collect()
+    }
+}
+
+// this is shared between the relay and all of its handles
+struct Shared {
+    // the bot's name, which the clients are given
+    name: String,
+    writer: Writer,
+    state: Mutex<State>,
+}
+
+struct State {
+    password: Option<String>,
+    join_rate_limit: RateLimit,
+    next_id: usize,
+    clients: BTreeMap<usize, Client>,
+    // the channels which are joined, or being joined
+    rooms: BTreeMap<String, Room>,
+}
+
+struct Client {
+    tx: Sender<String>,
+    caps: Caps,
+    channels: BTreeSet<String>,
+}
+
+#[derive(Default)]
+struct Room {
+    // the runner was on it before the relay, so it isn't parted
+    keep: bool,
+    // the latest of these, for the clients which join it later
+    room_state: Option<String>,
+    user_state: Option<String>,
+}
+
+// the capabilities a client requested
+#[derive(Default, Debug, Copy, Clone)]
+struct Caps {
+    tags: bool,
+    commands: bool,
+    membership: bool,
+}
+
+impl Caps {
+    // whether Twitch would have sent this to a client with these capabilities
+    fn wants(self, msg: &Commands<'_>, name: &str) -> bool {
+        use Commands::*;
+        match msg {
+            Join(msg) => self.membership || msg.name() == name,
+            Part(msg) => self.membership || msg.name() == name,
+            Raw(msg) if is_names(msg) => self.membership,
+            ClearChat(..) | ClearMsg(..) | GlobalUserState(..) | HostTarget(..) | Reconnect(..)
+            | RoomState(..) | UserNotice(..) | UserState(..) | Whisper(..) => self.commands,
+            _ => true,
+        }
+    }
+
+    // the line, as a client with these capabilities would get it
+    fn line(self, line: &str) -> &str {
+        if self.tags {
+            line
+        } else {
+            strip_tags(line)
+        }
+    }
+}
+
+fn is_names(msg: &IrcMessage<'_>) -> bool {
+    matches!(
+        msg.get_command(),
+        IrcMessage::NAMES | IrcMessage::END_OF_NAMES
+    )
+}
+
+fn strip_tags(line: &str) -> &str {
+    match line.strip_prefix('@') {
+        Some(rest) => rest.find(' ').map_or("", |pos| rest[pos..].trim_start()),
+        None => line,
+    }
+}
+
+impl Shared {
+    // a line from the bot, as Twitch would echo it
+    fn bot_line(&self, rest: &str) -> String {
+        format!(":{0}!{0}@{0}.tmi.twitch.tv {1}\r\n", self.name, rest)
+    }
+
+    fn dispatch(&self, msg: &Commands<'_>) {
+        let mut data = Vec::new();
+        if msg.encode(&mut data).is_err() {
+            return;
+        }
+        let line = String::from_utf8_lossy(&data);
+
+        let mut state = self.state.lock().unwrap();
+        let channel = match msg {
+            // these are for the relay's connection, which it handles itself
+            Commands::IrcReady(..) | Commands::Ready(..) | Commands::Cap(..) => return,
+            Commands::Ping(..) | Commands::Pong(..) => return,
+
+            Commands::Join(msg) if msg.name() == self.name => {
+                state.rooms.entry(msg.channel().to_string()).or_default();
+                Some(msg.channel())
+            }
+            Commands::Part(msg) if msg.name() == self.name => {
+                state.rooms.remove(msg.channel());
+                Some(msg.channel())
+            }
+            Commands::RoomState(msg) => {
+                if let Some(room) = state.rooms.get_mut(msg.channel()) {
+                    room.room_state.replace(line.to_string());
+                }
+                Some(msg.channel())
+            }
+            Commands::UserState(msg) => {
+                if let Some(room) = state.rooms.get_mut(msg.channel()) {
+                    room.user_state.replace(line.to_string());
+                }
+                Some(msg.channel())
+            }
+            // so the channel can be joined again later
+            Commands::Notice(msg)
+                if matches!(
+                    msg.msg_id(),
+                    Some(MessageId::MsgBanned)
+                        | Some(MessageId::MsgChannelSuspended)
+                        | Some(MessageId::MsgChannelBlocked)
+                ) =>
+            {
+                state.rooms.remove(msg.channel());
+                Some(msg.channel()).filter(|ch| ch.starts_with('#'))
+            }
+            // :name.tmi.twitch.tv 353 name = #channel :user1 user2 user3
+            Commands::Raw(raw) if raw.get_command() == IrcMessage::NAMES => raw.nth_arg(2),
+            // :name.tmi.twitch.tv 366 name #channel :End of /NAMES list
+            Commands::Raw(raw) if raw.get_command() == IrcMessage::END_OF_NAMES => raw.nth_arg(1),
+            msg => msg.channel(),
+        };
+
+        for client in state.clients.values() {
+            if channel.map_or(false, |ch| !client.channels.contains(ch)) {
+                continue;
+            }
+            if !client.caps.wants(msg, &self.name) {
+                continue;
+            }
+            let _ = client.tx.try_send(client.caps.line(&line).to_string());
+        }
+    }
+
+    // send this line to the client, if it's still connected
+    fn send_to(&self, id: usize, line: String) -> bool {
+        let state = self.state.lock().unwrap();
+        match state.clients.get(&id) {
+            Some(client) => client.tx.try_send(line).is_ok(),
+            None => false,
+        }
+    }
+}
+
+#[cfg(all(test, feature = "testing"))]
+mod tests {
+    use super::*;
+    use crate::{
+        connector::Connector as _,
+        test::{MockConnection, MockServer},
+        UserConfig,
+    };
+    use futures_lite::future::{block_on, or, zip};
+    use std::io;
+
+    async fn upstream(server: &MockServer) -> (AsyncRunner, MockConnection) {
+        let user_config = UserConfig::builder()
+            .name("shaken_bot")
+            .token(format!("oauth:{}", "a".repeat(30)))
+            .enable_all_capabilities()
+            .build()
+            .unwrap();
+
+        let (runner, conn) = zip(
+            AsyncRunner::connect(server.clone(), &user_config),
+            server.accept(),
+        )
+        .await;
+        (runner.unwrap(), conn.unwrap())
+    }
+
+    // the lines the client reads until (and including) the one starting with `until`
+    async fn read_until(client: &mut MockConnection, until: &str) -> Vec<String> {
+        let mut lines = vec![];
+        loop {
+            let line = client.read_line().await.unwrap();
+            let done = line.starts_with(until);
+            lines.push(line);
+            if done {
+                break lines;
+            }
+        }
+    }
+
+    async fn register(client: &mut MockConnection, caps: &str) {
+        client.send(format!("CAP REQ :{}", caps)).unwrap();
+        client.send("PASS oauth:hunter2").unwrap();
+        client.send("NICK whoever").unwrap();
+        let lines = read_until(client, ":tmi.twitch.tv 376").await;
+        assert!(lines[0].starts_with(":tmi.twitch.tv CAP * ACK"));
+        assert!(lines
+            .iter()
+            .any(|l| l == ":tmi.twitch.tv 001 shaken_bot :Welcome, GLHF!\r\n"));
+    }
+
+    #[test]
+    fn relays_to_clients() {
+        let (server, local) = (MockServer::new(), MockServer::new());
+
+        block_on(async {
+            let (runner, mut upstream) = upstream(&server).await;
+            let relay = Relay::new(runner).password("hunter2");
+            let handle = relay.handle();
+
+            let (a, alice) = zip(local.clone().connect(), local.accept_raw()).await;
+            let (b, bob) = zip(local.clone().connect(), local.accept_raw()).await;
+            let serving = zip(handle.serve(a.unwrap()), handle.serve(b.unwrap()));
+            let (mut alice, mut bob) = (alice.unwrap(), bob.unwrap());
+
+            let script = async {
+                register(&mut alice, "twitch.tv/tags twitch.tv/commands").await;
+                register(&mut bob, "twitch.tv/commands").await;
+                assert_eq!(handle.clients(), 2);
+
+                // the first client to join a channel joins it
+                alice.send("JOIN #museun").unwrap();
+                assert_eq!(upstream.read_line().await.unwrap(), "JOIN #museun\r\n");
+                let lines = read_until(&mut alice, "@emote-only").await;
+                assert_eq!(
+                    lines[0],
+                    ":shaken_bot!shaken_bot@shaken_bot.tmi.twitch.tv JOIN #museun\r\n"
+                );
+
+                // the second is told it joined, with what the first was sent
+                bob.send("join museun").unwrap();
+                let lines = read_until(&mut bob, ":tmi.twitch.tv ROOMSTATE").await;
+                assert_eq!(
+                    lines[0],
+                    ":shaken_bot!shaken_bot@shaken_bot.tmi.twitch.tv JOIN #museun\r\n"
+                );
+                assert_eq!(handle.channels(), vec!["#museun"]);
+
+                // messages go to the clients on the channel, with the tags they asked for
+                upstream
+                    .send("@id=1 :museun!museun@museun.tmi.twitch.tv PRIVMSG #other :not relayed")
+                    .unwrap();
+                upstream
+                    .send("@id=2 :museun!museun@museun.tmi.twitch.tv PRIVMSG #museun :hello")
+                    .unwrap();
+                assert_eq!(
+                    alice.read_line().await.unwrap(),
+                    "@id=2 :museun!museun@museun.tmi.twitch.tv PRIVMSG #museun :hello\r\n"
+                );
+                assert_eq!(
+                    bob.read_line().await.unwrap(),
+                    ":museun!museun@museun.tmi.twitch.tv PRIVMSG #museun :hello\r\n"
+                );
+
+                // what a client sends goes to Twitch, and to the other clients
+                alice.send("PRIVMSG #museun :hi").unwrap();
+                assert_eq!(
+                    upstream.read_line().await.unwrap(),
+                    "PRIVMSG #museun :hi\r\n"
+                );
+                assert_eq!(
+                    bob.read_line().await.unwrap(),
+                    ":shaken_bot!shaken_bot@shaken_bot.tmi.twitch.tv PRIVMSG #museun :hi\r\n"
+                );
+
+                // PINGs are answered by the relay
+                bob.send("PING :1234").unwrap();
+                assert_eq!(
+                    bob.read_line().await.unwrap(),
+                    ":tmi.twitch.tv PONG tmi.twitch.tv :1234\r\n"
+                );
+
+                // the channel is parted when the last client leaves
+                alice.send("PART #museun").unwrap();
+                assert!(alice
+                    .read_line()
+                    .await
+                    .unwrap()
+                    .ends_with("PART #museun\r\n"));
+                bob.send("QUIT").unwrap();
+                assert_eq!(upstream.read_line().await.unwrap(), "PART #museun\r\n");
+                assert!(handle.channels().is_empty());
+            };
+
+            or(script, async {
+                let _ = zip(relay.run(), serving).await;
+                panic!("the relay stopped")
+            })
+            .await;
+        });
+    }
+
+    #[test]
+    fn wrong_password() {
+        let (server, local) = (MockServer::new(), MockServer::new());
+
+        block_on(async {
+            let (runner, _upstream) = upstream(&server).await;
+            let relay = Relay::new(runner).password("hunter2");
+            let handle = relay.handle();
+
+            let (stream, client) = zip(local.clone().connect(), local.accept_raw()).await;
+            let mut client = client.unwrap();
+            client.send("PASS oauth:hunter3").unwrap();
+            client.send("NICK whoever").unwrap();
+
+            let err = handle.serve(stream.unwrap()).await.unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+            assert_eq!(
+                client.read_line().await.unwrap(),
+                ":tmi.twitch.tv NOTICE * :Login authentication failed\r\n"
+            );
+            assert_eq!(handle.clients(), 0);
+        });
+    }
+
+    #[test]
+    fn capabilities() {
+        let caps = Caps::default();
+        let line = "@badges= :tmi.twitch.tv ROOMSTATE #museun\r\n";
+        assert_eq!(caps.line(line), ":tmi.twitch.tv ROOMSTATE #museun\r\n");
+        assert_eq!(strip_tags(":tmi.twitch.tv PING"), ":tmi.twitch.tv PING");
+
+        let mut caps = Caps::default();
+        assert!(client::cap("REQ :twitch.tv/foo", &mut caps)
+            .unwrap()
+            .contains(" NAK "));
+        assert!(client::cap("REQ :twitch.tv/membership", &mut caps)
+            .unwrap()
+            .contains(" ACK "));
+        assert!(caps.membership && !caps.tags && !caps.commands);
+    }
+}
@@ -0,0 +1,221 @@
+use crate::messages::Privmsg;
+
+use super::Permission;
+
+/// Why the arguments of a command couldn't be extracted
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ArgError {
+    /// This argument wasn't given
+    Missing {
+        /// The position of the argument, starting at `0`
+        index: usize,
+    },
+    /// This argument couldn't be parsed
+    Invalid {
+        /// The position of the argument, starting at `0`
+        index: usize,
+        /// What was given
+        value: String,
+        /// Why it couldn't be parsed
+        reason: String,
+    },
+    /// More arguments were given than the command takes
+    Unexpected {
+        /// The first argument that wasn't expected
+        value: String,
+    },
+}
+
+impl std::fmt::Display for ArgError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Missing { index } => write!(f, "argument {} is missing", index + 1),
+            Self::Invalid {
+                index,
+                value,
+                reason,
+            } => write!(
+                f,
+                "argument {} ('{}') is invalid: {}",
+                index + 1,
+                value,
+                reason
+            ),
+            Self::Unexpected { value } => write!(f, "unexpected argument: '{}'", value),
+        }
+    }
+}
+
+impl std::error::Error for ArgError {}
+
+/// The arguments given to a command, split on whitespace.
+///
+/// A `"quoted argument"` is kept together, without its quotes.
+#[derive(Debug, Clone)]
+pub struct Args<'a> {
+    rest: &'a str,
+    index: usize,
+}
+
+impl<'a> Args<'a> {
+    pub(crate) fn new(input: &'a str) -> Self {
+        Self {
+            rest: input.trim(),
+            index: 0,
+        }
+    }
+
+    /// Take the next argument, parsed as `T`
+    pub fn parse<T>(&mut self) -> Result<T, ArgError>
+    where
+        T: std::str::FromStr,
+        T::Err: std::fmt::Display,
+    {
+        let index = self.index;
+        match self.optional()? {
+            Some(value) => Ok(value),
+            None => Err(ArgError::Missing { index }),
+        }
+    }
+
+    /// Take the next argument if there is one, parsed as `T`
+    pub fn optional<T>(&mut self) -> Result<Option<T>, ArgError>
+    where
+        T: std::str::FromStr,
+        T::Err: std::fmt::Display,
+    {
+        let index = self.index;
+        let value = match self.next_str() {
+            Some(value) => value,
+            None => return Ok(None),
+        };
+        value
+            .parse()
+            .map(Some)
+            .map_err(|err: T::Err| ArgError::Invalid {
+                index,
+                value: value.to_string(),
+                reason: err.to_string(),
+            })
+    }
+
+    /// Take the next argument, unparsed
+    pub fn next_str(&mut self) -> Option<&'a str> {
+        if self.rest.is_empty() {
+            return None;
+        }
+
+        let (arg, rest) = match self.rest.strip_prefix('"') {
+            Some(quoted) => match quoted.find('"') {
+                Some(end) => (&quoted[..end], &quoted[end + 1..]),
+                // an unclosed quote takes the rest
+                None => (quoted, ""),
+            },
+            None => {
+                let end = self
+                    .rest
+                    .find(char::is_whitespace)
+                    .unwrap_or(self.rest.len());
+                (&self.rest[..end], &self.rest[end..])
+            }
+        };
+
+        self.rest = rest.trim_start();
+        self.index += 1;
+        Some(arg)
+    }
+
+    /// Take everything that's left, as one string
+    pub fn rest(&mut self) -> &'a str {
+        let rest = std::mem::take(&mut self.rest);
+        if !rest.is_empty() {
+            self.index += 1;
+        }
+        rest
+    }
+
+    /// Check that every argument was taken
+    pub fn finish(&self) -> Result<(), ArgError> {
+        match self.clone().next_str() {
+            Some(value) => Err(ArgError::Unexpected {
+                value: value.to_string(),
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+/// A command being run, given to its handler
+#[derive(Debug)]
+pub struct Invocation<'a> {
+    pub(crate) msg: &'a Privmsg<'a>,
+    pub(crate) name: &'a str,
+    pub(crate) alias: &'a str,
+    pub(crate) permission: Permission,
+    /// The arguments given to the command
+    pub args: Args<'a>,
+}
+
+impl<'a> Invocation<'a> {
+    /// The message which ran the command
+    pub fn msg(&self) -> &'a Privmsg<'a> {
+        self.msg
+    }
+
+    /// The name of the command
+    pub fn name(&self) -> &'a str {
+        self.name
+    }
+
+    /// The name (or alias) the command was run with
+    pub fn alias(&self) -> &'a str {
+        self.alias
+    }
+
+    /// The channel the command was run in
+    pub fn channel(&self) -> &'a str {
+        self.msg.channel()
+    }
+
+    /// The user who ran the command
+    pub fn user(&self) -> &'a str {
+        self.msg.name()
+    }
+
+    /// The permission the user has
+    pub fn permission(&self) -> Permission {
+        self.permission
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn args() {
+        let mut args = Args::new(r#" 6  "hello world"  extra stuff "#);
+        assert_eq!(args.parse::<u8>().unwrap(), 6);
+        assert_eq!(args.next_str(), Some("hello world"));
+        assert!(args.finish().is_err());
+        assert_eq!(args.rest(), "extra stuff");
+        assert!(args.finish().is_ok());
+        assert_eq!(args.optional::<u8>().unwrap(), None);
+        assert_eq!(
+            args.parse::<u8>().unwrap_err(),
+            ArgError::Missing { index: 3 }
+        );
+
+        let err = Args::new("six").parse::<u8>().unwrap_err();
+        assert!(matches!(err, ArgError::Invalid { index: 0, .. }));
+        assert_eq!(
+            err.to_string(),
+            "argument 1 ('six') is invalid: invalid digit found in string"
+        );
+
+        let mut args = Args::new(r#""unclosed quote"#);
+        assert_eq!(args.next_str(), Some("unclosed quote"));
+        assert_eq!(args.next_str(), None);
+    }
+}
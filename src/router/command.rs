@@ -0,0 +1,121 @@
+use super::{ArgError, Cooldowns, Invocation, Permission};
+
+use std::time::Duration;
+
+type Handler = Box<dyn FnMut(&mut Invocation<'_>) -> Result<Option<String>, ArgError> + Send>;
+
+/// A command for a [Router](super::Router), e.g. `!dice`.
+///
+/// The handler returns what to reply with (if anything), or an [ArgError] if the arguments were wrong
+/// (in which case the usage is replied with).
+pub struct Command {
+    pub(crate) name: String,
+    pub(crate) aliases: Vec<String>,
+    pub(crate) usage: Option<String>,
+    pub(crate) description: Option<String>,
+    pub(crate) permission: Permission,
+    pub(crate) cooldowns: Cooldowns,
+    pub(crate) hidden: bool,
+    pub(crate) handler: Handler,
+}
+
+impl std::fmt::Debug for Command {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Command")
+            .field("name", &self.name)
+            .field("aliases", &self.aliases)
+            .field("permission", &self.permission)
+            .finish()
+    }
+}
+
+impl Command {
+    /// Create a command called `name` (without the prefix), handled by `handler`
+    pub fn new<F>(name: impl Into<String>, handler: F) -> Self
+    where
+        F: FnMut(&mut Invocation<'_>) -> Result<Option<String>, ArgError> + Send + 'static,
+    {
+        Self {
+            name: name.into(),
+            aliases: Vec::new(),
+            usage: None,
+            description: None,
+            permission: Permission::Everyone,
+            cooldowns: Cooldowns::new(),
+            hidden: false,
+            handler: Box::new(handler),
+        }
+    }
+
+    /// Another name this command can be run with
+    pub fn alias(mut self, alias: impl Into<String>) -> Self {
+        self.aliases.push(alias.into());
+        self
+    }
+
+    /// The arguments this command takes, for the help (e.g. `<sides>`)
+    pub fn usage(mut self, usage: impl Into<String>) -> Self {
+        self.usage.replace(usage.into());
+        self
+    }
+
+    /// What this command does, for the help
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description.replace(description.into());
+        self
+    }
+
+    /// Who can use this command. This defaults to [Permission::Everyone]
+    pub fn permission(mut self, permission: Permission) -> Self {
+        self.permission = permission;
+        self
+    }
+
+    /// How long each user has to wait between uses, in each channel
+    pub fn user_cooldown(mut self, cooldown: Duration) -> Self {
+        self.cooldowns = self.cooldowns.user(cooldown);
+        self
+    }
+
+    /// How long everyone has to wait between uses, in each channel
+    pub fn channel_cooldown(mut self, cooldown: Duration) -> Self {
+        self.cooldowns = self.cooldowns.channel(cooldown);
+        self
+    }
+
+    /// How long everyone has to wait between uses, across every channel
+    pub fn global_cooldown(mut self, cooldown: Duration) -> Self {
+        self.cooldowns = self.cooldowns.global(cooldown);
+        self
+    }
+
+    /// Use these cooldowns, e.g. to change who skips them. Moderators (and the broadcaster) skip them by default
+    pub fn cooldowns(mut self, cooldowns: Cooldowns) -> Self {
+        self.cooldowns = cooldowns;
+        self
+    }
+
+    /// Leave this command out of the list of commands in the help
+    pub fn hidden(mut self) -> Self {
+        self.hidden = true;
+        self
+    }
+
+    /// The name of this command
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub(crate) fn matches(&self, alias: &str) -> bool {
+        std::iter::once(&self.name)
+            .chain(&self.aliases)
+            .any(|name| name.eq_ignore_ascii_case(alias))
+    }
+
+    pub(crate) fn usage_line(&self, prefix: &str) -> String {
+        match &self.usage {
+            Some(usage) => format!("usage: {}{} {}", prefix, self.name, usage),
+            None => format!("usage: {}{}", prefix, self.name),
+        }
+    }
+}
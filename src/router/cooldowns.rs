@@ -0,0 +1,314 @@
+use super::Permission;
+
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+// how many slots the wheel has, and how long each one is
+const SLOTS: usize = 64;
+const SLOT_WIDTH: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Key {
+    Global,
+    Channel(String),
+    User(String, String),
+}
+
+/// Global, per-channel and per-user cooldowns. This is used by each [Command](super::Command), but it also works on its own.
+///
+/// Users with at least the bypass permission (the moderators, by default) skip the cooldowns.
+///
+/// Expired cooldowns are forgotten as time passes, so this stays small no matter how many users there are.
+///
+/// # Example
+/// ```
+/// # use twitchchat::router::{Cooldowns, Permission};
+/// # use std::time::Duration;
+/// let mut cooldowns = Cooldowns::new()
+///     .user(Duration::from_secs(30))
+///     .bypass(Some(Permission::Vip));
+///
+/// assert!(cooldowns.try_use("#museun", "shaken_bot", Permission::Everyone).is_ok());
+/// assert!(cooldowns.try_use("#museun", "shaken_bot", Permission::Everyone).is_err());
+/// assert!(cooldowns.try_use("#museun", "museun", Permission::Everyone).is_ok());
+/// assert!(cooldowns.try_use("#museun", "shaken_bot", Permission::Vip).is_ok());
+/// ```
+#[derive(Clone)]
+pub struct Cooldowns {
+    global: Option<Duration>,
+    channel: Option<Duration>,
+    user: Option<Duration>,
+    bypass: Option<Permission>,
+    // when each cooldown ends
+    expires: HashMap<Key, Instant>,
+    wheel: Wheel,
+}
+
+impl std::fmt::Debug for Cooldowns {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Cooldowns")
+            .field("global", &self.global)
+            .field("channel", &self.channel)
+            .field("user", &self.user)
+            .field("bypass", &self.bypass)
+            .field("active", &self.expires.len())
+            .finish()
+    }
+}
+
+impl Default for Cooldowns {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Cooldowns {
+    /// Create cooldowns which don't limit anything, until they're configured
+    pub fn new() -> Self {
+        Self {
+            global: None,
+            channel: None,
+            user: None,
+            bypass: Some(Permission::Moderator),
+            expires: HashMap::new(),
+            wheel: Wheel::new(Instant::now()),
+        }
+    }
+
+    /// How long everyone has to wait between uses, across every channel
+    pub fn global(mut self, cooldown: Duration) -> Self {
+        self.global.replace(cooldown);
+        self
+    }
+
+    /// How long everyone has to wait between uses, in each channel
+    pub fn channel(mut self, cooldown: Duration) -> Self {
+        self.channel.replace(cooldown);
+        self
+    }
+
+    /// How long each user has to wait between uses, in each channel
+    pub fn user(mut self, cooldown: Duration) -> Self {
+        self.user.replace(cooldown);
+        self
+    }
+
+    /// Users with at least this permission skip the cooldowns, or nobody does with `None`.
+    ///
+    /// This defaults to [Permission::Moderator] (which includes the broadcaster).
+    pub fn bypass(mut self, permission: Option<Permission>) -> Self {
+        self.bypass = permission;
+        self
+    }
+
+    /// Is anything configured?
+    pub fn is_limited(&self) -> bool {
+        self.global.is_some() || self.channel.is_some() || self.user.is_some()
+    }
+
+    /// Check whether this user can use it now, or how long until they can. This doesn't start the cooldowns.
+    pub fn check(
+        &mut self,
+        channel: &str,
+        user: &str,
+        permission: Permission,
+    ) -> Result<(), Duration> {
+        if self.bypasses(permission) {
+            return Ok(());
+        }
+
+        let now = Instant::now();
+        self.wheel.advance(now, &mut self.expires);
+
+        let remaining = self
+            .keys(channel, user)
+            .filter_map(|(key, _)| self.expires.get(&key))
+            .filter_map(|expires| expires.checked_duration_since(now))
+            .filter(|remaining| *remaining > Duration::from_secs(0))
+            .max();
+        match remaining {
+            Some(remaining) => Err(remaining),
+            None => Ok(()),
+        }
+    }
+
+    /// Start the cooldowns for this use
+    pub fn trigger(&mut self, channel: &str, user: &str) {
+        let now = Instant::now();
+        let keys = self.keys(channel, user).collect::<Vec<_>>();
+        for (key, cooldown) in keys {
+            let expires = now + cooldown;
+            // a key is in the wheel once, and it's moved along when it's found to have been extended
+            if self.expires.insert(key.clone(), expires).is_none() {
+                self.wheel.insert(key, expires);
+            }
+        }
+    }
+
+    /// [Cooldowns::check()], and then [Cooldowns::trigger()] if the user can use it now
+    pub fn try_use(
+        &mut self,
+        channel: &str,
+        user: &str,
+        permission: Permission,
+    ) -> Result<(), Duration> {
+        self.check(channel, user, permission)?;
+        if !self.bypasses(permission) {
+            self.trigger(channel, user);
+        }
+        Ok(())
+    }
+
+    /// Forget every cooldown
+    pub fn reset(&mut self) {
+        self.expires.clear();
+        self.wheel = Wheel::new(Instant::now());
+    }
+
+    /// How many cooldowns are running
+    pub fn active(&self) -> usize {
+        self.expires.len()
+    }
+
+    pub(crate) fn bypasses(&self, permission: Permission) -> bool {
+        self.bypass.map_or(false, |bypass| permission >= bypass)
+    }
+
+    fn keys(&self, channel: &str, user: &str) -> impl Iterator<Item = (Key, Duration)> {
+        let channel = channel.to_ascii_lowercase();
+        let user = user.to_ascii_lowercase();
+        let global = self.global.map(|d| (Key::Global, d));
+        let per_channel = self.channel.map(|d| (Key::Channel(channel.clone()), d));
+        let per_user = self.user.map(|d| (Key::User(channel, user), d));
+        global.into_iter().chain(per_channel).chain(per_user)
+    }
+}
+
+// a timing wheel, so expired cooldowns are found without scanning all of them
+#[derive(Clone)]
+struct Wheel {
+    start: Instant,
+    // the last tick that was processed
+    tick: u64,
+    slots: Vec<Vec<Key>>,
+}
+
+impl Wheel {
+    fn new(start: Instant) -> Self {
+        Self {
+            start,
+            tick: 0,
+            slots: vec![Vec::new(); SLOTS],
+        }
+    }
+
+    fn ticks(&self, at: Instant) -> u64 {
+        let elapsed = at.saturating_duration_since(self.start);
+        (elapsed.as_nanos() / SLOT_WIDTH.as_nanos()) as u64
+    }
+
+    fn insert(&mut self, key: Key, expires: Instant) {
+        // it's looked at in the first tick after it expires
+        let tick = std::cmp::max(self.ticks(expires) + 1, self.tick + 1);
+        self.slots[tick as usize % SLOTS].push(key);
+    }
+
+    fn advance(&mut self, now: Instant, expires: &mut HashMap<Key, Instant>) {
+        let target = self.ticks(now);
+        // each slot only has to be looked at once
+        self.tick = std::cmp::max(self.tick, target.saturating_sub(SLOTS as u64));
+
+        while self.tick < target {
+            self.tick += 1;
+            let slot = std::mem::take(&mut self.slots[self.tick as usize % SLOTS]);
+            for key in slot {
+                match expires.get(&key).copied() {
+                    Some(at) if at <= now => {
+                        expires.remove(&key);
+                    }
+                    // it's further away than the wheel goes, or it was extended
+                    Some(at) => self.insert(key, at),
+                    None => {}
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scopes() {
+        let mut cooldowns = Cooldowns::new()
+            .channel(Duration::from_secs(10))
+            .bypass(None);
+        assert!(cooldowns
+            .try_use("#museun", "a", Permission::Everyone)
+            .is_ok());
+        let remaining = cooldowns
+            .try_use("#museun", "b", Permission::Broadcaster)
+            .unwrap_err();
+        assert!(remaining <= Duration::from_secs(10));
+        assert!(cooldowns
+            .try_use("#shaken_bot", "a", Permission::Everyone)
+            .is_ok());
+
+        let mut cooldowns = Cooldowns::new().global(Duration::from_secs(10));
+        assert!(cooldowns
+            .try_use("#museun", "a", Permission::Everyone)
+            .is_ok());
+        assert!(cooldowns
+            .try_use("#shaken_bot", "b", Permission::Everyone)
+            .is_err());
+        // moderators skip them by default, and don't start them
+        assert!(cooldowns
+            .try_use("#shaken_bot", "b", Permission::Moderator)
+            .is_ok());
+        assert_eq!(cooldowns.active(), 1);
+
+        // nothing is configured
+        let mut cooldowns = Cooldowns::new();
+        assert!(!cooldowns.is_limited());
+        assert!(cooldowns
+            .try_use("#museun", "a", Permission::Everyone)
+            .is_ok());
+        assert!(cooldowns
+            .try_use("#museun", "a", Permission::Everyone)
+            .is_ok());
+    }
+
+    #[test]
+    fn expiry() {
+        let start = Instant::now();
+        let mut wheel = Wheel::new(start);
+        let mut expires = HashMap::new();
+
+        let soon = start + Duration::from_millis(1500);
+        // further away than the wheel goes
+        let later = start + SLOT_WIDTH * (SLOTS as u32 + 10);
+        for (key, at) in [(Key::Global, soon), (Key::Channel("#museun".into()), later)] {
+            expires.insert(key.clone(), at);
+            wheel.insert(key, at);
+        }
+
+        wheel.advance(start + Duration::from_secs(1), &mut expires);
+        assert_eq!(expires.len(), 2);
+
+        wheel.advance(start + Duration::from_secs(3), &mut expires);
+        assert_eq!(expires.len(), 1);
+        assert!(expires.contains_key(&Key::Channel("#museun".into())));
+
+        // it's moved along as the wheel turns, until it expires
+        for secs in (4..SLOTS as u64 + 10).step_by(7) {
+            wheel.advance(start + Duration::from_secs(secs), &mut expires);
+            assert_eq!(expires.len(), 1);
+        }
+        wheel.advance(later + SLOT_WIDTH, &mut expires);
+        assert!(expires.is_empty());
+        assert!(wheel.slots.iter().all(Vec::is_empty));
+    }
+}
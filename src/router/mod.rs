@@ -0,0 +1,441 @@
+//! A command router for chat bots.
+//!
+//! Register [Command]s (e.g. `!dice 20`) on a [Router], and give it every [Privmsg]. It finds the command,
+//! checks its [Permission] and cooldowns, and runs its handler with the (typed) [Args].
+//!
+//! A `help` command is generated from the commands' descriptions, unless you register your own.
+//!
+//! # Example
+//! ```
+//! # use twitchchat::{router::{Command, Outcome, Permission, Router}, messages::Privmsg, FromIrcMessage};
+//! let mut router = Router::new("!");
+//! router.add(
+//!     Command::new("dice", |inv| {
+//!         let sides: u32 = inv.args.parse()?;
+//!         Ok(Some(format!("{} rolls a d{}", inv.user(), sides)))
+//!     })
+//!     .usage("<sides>")
+//!     .description("roll a dice")
+//!     .alias("roll"),
+//! );
+//! router.add(
+//!     Command::new("shutdown", |_| Ok(None))
+//!         .description("stop the bot")
+//!         .permission(Permission::Broadcaster),
+//! );
+//!
+//! # let input = ":museun!museun@museun PRIVMSG #museun :!roll 20\r\n";
+//! # let msg = Privmsg::from_irc(twitchchat::irc::parse_one(input).unwrap().1).unwrap();
+//! // with `msg` being "!roll 20" from museun
+//! let outcome = router.route(&msg).unwrap();
+//! assert_eq!(outcome.reply(), Some("museun rolls a d20"));
+//!
+//! # let input = ":museun!museun@museun PRIVMSG #museun :!help\r\n";
+//! # let msg = Privmsg::from_irc(twitchchat::irc::parse_one(input).unwrap().1).unwrap();
+//! // with `msg` being "!help"
+//! let outcome = router.route(&msg).unwrap();
+//! assert_eq!(outcome.reply(), Some("commands: !dice"));
+//! ```
+use crate::{messages::Privmsg, twitch::Role};
+
+use std::time::Duration;
+
+mod args;
+pub use args::{ArgError, Args, Invocation};
+
+mod command;
+pub use command::Command;
+
+mod cooldowns;
+pub use cooldowns::Cooldowns;
+
+/// What a user is allowed to do, from their [Role]. These are ordered, so a moderator is `>= Permission::Vip`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum Permission {
+    /// Anyone
+    #[default]
+    Everyone,
+    /// Subscribers (and founders)
+    Subscriber,
+    /// VIPs
+    Vip,
+    /// Moderators
+    Moderator,
+    /// The broadcaster
+    Broadcaster,
+}
+
+impl Permission {
+    /// The permission of the user who sent this message
+    pub fn of(msg: &Privmsg<'_>) -> Self {
+        msg.author_role().into()
+    }
+}
+
+impl From<Role> for Permission {
+    fn from(role: Role) -> Self {
+        match role {
+            Role::Everyone => Self::Everyone,
+            Role::Subscriber { .. } => Self::Subscriber,
+            Role::Vip => Self::Vip,
+            Role::Moderator => Self::Moderator,
+            Role::Broadcaster => Self::Broadcaster,
+        }
+    }
+}
+
+/// What happened when a message was routed to a command
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum Outcome {
+    /// The command ran, and this is its reply (if it had one)
+    Handled {
+        /// The name of the command
+        command: String,
+        /// What it replied with
+        reply: Option<String>,
+    },
+    /// The command is on cooldown (for this user, or this channel)
+    Cooldown {
+        /// The name of the command
+        command: String,
+        /// When it can be used again
+        remaining: Duration,
+    },
+    /// The user isn't allowed to use the command
+    NotPermitted {
+        /// The name of the command
+        command: String,
+        /// What they need
+        required: Permission,
+    },
+    /// The arguments were wrong
+    BadArguments {
+        /// The name of the command
+        command: String,
+        /// What was wrong
+        error: ArgError,
+        /// How the command is used, e.g. `usage: !dice <sides>`
+        usage: String,
+    },
+    /// The generated help
+    Help(String),
+}
+
+impl Outcome {
+    /// What the bot should say back, if anything.
+    ///
+    /// Cooldowns and missing permissions are silent, so they can't be used to spam the chat.
+    pub fn reply(&self) -> Option<&str> {
+        match self {
+            Self::Handled { reply, .. } => reply.as_deref(),
+            Self::BadArguments { usage, .. } => Some(usage),
+            Self::Help(help) => Some(help),
+            Self::Cooldown { .. } | Self::NotPermitted { .. } => None,
+        }
+    }
+}
+
+/// Routes messages to [Command]s. See the [module docs](self) for an example.
+pub struct Router {
+    prefix: String,
+    commands: Vec<Command>,
+}
+
+impl std::fmt::Debug for Router {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Router")
+            .field("prefix", &self.prefix)
+            .field("commands", &self.commands.len())
+            .finish()
+    }
+}
+
+impl Default for Router {
+    fn default() -> Self {
+        Self::new("!")
+    }
+}
+
+impl Router {
+    /// Create a router for commands starting with `prefix` (e.g. `!`)
+    pub fn new(prefix: impl Into<String>) -> Self {
+        Self {
+            prefix: prefix.into(),
+            commands: Vec::new(),
+        }
+    }
+
+    /// The prefix commands start with
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    /// Add a command. This replaces an existing command with the same name
+    pub fn add(&mut self, command: Command) -> &mut Self {
+        match self.commands.iter().position(|c| c.name == command.name) {
+            Some(pos) => self.commands[pos] = command,
+            None => self.commands.push(command),
+        }
+        self
+    }
+
+    /// Remove the command with this name, returning whether it existed
+    pub fn remove(&mut self, name: &str) -> bool {
+        let before = self.commands.len();
+        self.commands.retain(|c| !c.name.eq_ignore_ascii_case(name));
+        before != self.commands.len()
+    }
+
+    /// Route this message to its command, if it's a command for this router.
+    pub fn route(&mut self, msg: &Privmsg<'_>) -> Option<Outcome> {
+        let input = msg.data().strip_prefix(self.prefix.as_str())?;
+        let (alias, rest) = match input.find(char::is_whitespace) {
+            Some(pos) => (&input[..pos], &input[pos..]),
+            None => (input, ""),
+        };
+        if alias.is_empty() {
+            return None;
+        }
+
+        let permission = Permission::of(msg);
+        let index = match self.commands.iter().position(|c| c.matches(alias)) {
+            Some(index) => index,
+            None if alias.eq_ignore_ascii_case("help") => {
+                return Some(Outcome::Help(
+                    self.help(Args::new(rest).next_str(), permission),
+                ))
+            }
+            None => return None,
+        };
+
+        let command = &mut self.commands[index];
+        if permission < command.permission {
+            return Some(Outcome::NotPermitted {
+                command: command.name.clone(),
+                required: command.permission,
+            });
+        }
+
+        if let Err(remaining) = command
+            .cooldowns
+            .check(msg.channel(), msg.name(), permission)
+        {
+            return Some(Outcome::Cooldown {
+                command: command.name.clone(),
+                remaining,
+            });
+        }
+
+        let mut invocation = Invocation {
+            msg,
+            name: &command.name,
+            alias,
+            permission,
+            args: Args::new(rest),
+        };
+
+        let outcome = match (command.handler)(&mut invocation) {
+            Ok(reply) => Outcome::Handled {
+                command: command.name.clone(),
+                reply,
+            },
+            Err(error) => {
+                return Some(Outcome::BadArguments {
+                    command: command.name.clone(),
+                    error,
+                    usage: command.usage_line(&self.prefix),
+                })
+            }
+        };
+
+        if !command.cooldowns.bypasses(permission) {
+            command.cooldowns.trigger(msg.channel(), msg.name());
+        }
+        Some(outcome)
+    }
+
+    /// The help for the commands a user with this permission can use.
+    ///
+    /// With a `command`, this is the usage and description of just that one.
+    pub fn help(&self, command: Option<&str>, permission: Permission) -> String {
+        let prefix = &self.prefix;
+        if let Some(name) = command {
+            let name = name.strip_prefix(prefix.as_str()).unwrap_or(name);
+            return match self.commands.iter().find(|c| c.matches(name)) {
+                Some(command) => match &command.description {
+                    Some(description) => {
+                        format!("{} - {}", command.usage_line(prefix), description)
+                    }
+                    None => command.usage_line(prefix),
+                },
+                None => format!("unknown command: {}{}", prefix, name),
+            };
+        }
+
+        let mut names = self
+            .commands
+            .iter()
+            .filter(|c| c.permission <= permission && !c.hidden)
+            .map(|c| format!("{}{}", prefix, c.name))
+            .collect::<Vec<_>>();
+        names.sort();
+        format!("commands: {}", names.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FromIrcMessage as _;
+
+    fn privmsg(input: &str) -> Privmsg<'_> {
+        Privmsg::from_irc(crate::irc::parse_one(input).unwrap().1).unwrap()
+    }
+
+    fn router() -> Router {
+        let mut router = Router::new("!");
+        router
+            .add(
+                Command::new("add", |inv| {
+                    let (a, b) = (inv.args.parse::<i32>()?, inv.args.parse::<i32>()?);
+                    inv.args.finish()?;
+                    Ok(Some((a + b).to_string()))
+                })
+                .usage("<a> <b>")
+                .description("add two numbers")
+                .alias("plus"),
+            )
+            .add(
+                Command::new("so", |inv| {
+                    Ok(Some(format!("go follow {}", inv.args.rest())))
+                })
+                .permission(Permission::Moderator),
+            )
+            .add(
+                Command::new("hug", |inv| Ok(Some(format!("{} hugs chat", inv.user()))))
+                    .user_cooldown(Duration::from_secs(30)),
+            );
+        router
+    }
+
+    #[test]
+    fn routing() {
+        let mut router = router();
+        let outcome = router
+            .route(&privmsg(
+                ":museun!museun@museun PRIVMSG #museun :!PLUS 1 2\r\n",
+            ))
+            .unwrap();
+        assert_eq!(outcome.reply(), Some("3"));
+
+        let outcome = router
+            .route(&privmsg(
+                ":museun!museun@museun PRIVMSG #museun :!add 1 two\r\n",
+            ))
+            .unwrap();
+        assert!(matches!(
+            &outcome,
+            Outcome::BadArguments {
+                error: ArgError::Invalid { index: 1, .. },
+                ..
+            }
+        ));
+        assert_eq!(outcome.reply(), Some("usage: !add <a> <b>"));
+
+        for input in &[
+            ":museun!museun@museun PRIVMSG #museun :hello\r\n",
+            ":museun!museun@museun PRIVMSG #museun :!\r\n",
+            ":museun!museun@museun PRIVMSG #museun :!unknown\r\n",
+        ] {
+            assert_eq!(router.route(&privmsg(input)), None);
+        }
+    }
+
+    #[test]
+    fn permissions() {
+        let mut router = router();
+        let outcome = router
+            .route(&privmsg(
+                ":museun!museun@museun PRIVMSG #museun :!so shaken_bot\r\n",
+            ))
+            .unwrap();
+        assert_eq!(
+            outcome,
+            Outcome::NotPermitted {
+                command: "so".into(),
+                required: Permission::Moderator
+            }
+        );
+
+        let outcome = router
+            .route(&privmsg(
+                "@badges=moderator/1 :museun!museun@museun PRIVMSG #museun :!so shaken_bot\r\n",
+            ))
+            .unwrap();
+        assert_eq!(outcome.reply(), Some("go follow shaken_bot"));
+
+        let msg = privmsg(
+            "@badges=broadcaster/1,subscriber/12 :museun!museun@museun PRIVMSG #museun :hi\r\n",
+        );
+        assert_eq!(Permission::of(&msg), Permission::Broadcaster);
+        let msg = privmsg("@badges=founder/0 :museun!museun@museun PRIVMSG #museun :hi\r\n");
+        assert_eq!(Permission::of(&msg), Permission::Subscriber);
+    }
+
+    #[test]
+    fn cooldowns() {
+        let mut router = router();
+        let hug = ":museun!museun@museun PRIVMSG #museun :!hug\r\n";
+        assert!(router.route(&privmsg(hug)).unwrap().reply().is_some());
+        assert!(matches!(
+            router.route(&privmsg(hug)).unwrap(),
+            Outcome::Cooldown { .. }
+        ));
+
+        // other users (and channels) have their own cooldowns
+        let other = ":shaken_bot!shaken_bot@shaken_bot PRIVMSG #museun :!hug\r\n";
+        assert!(router.route(&privmsg(other)).unwrap().reply().is_some());
+        let other = ":museun!museun@museun PRIVMSG #shaken_bot :!hug\r\n";
+        assert!(router.route(&privmsg(other)).unwrap().reply().is_some());
+
+        // and moderators skip them
+        let moderator = "@badges=moderator/1 :museun!museun@museun PRIVMSG #museun :!hug\r\n";
+        assert!(router.route(&privmsg(moderator)).unwrap().reply().is_some());
+    }
+
+    #[test]
+    fn help() {
+        let mut router = router();
+        let outcome = router
+            .route(&privmsg(":museun!museun@museun PRIVMSG #museun :!help\r\n"))
+            .unwrap();
+        assert_eq!(outcome, Outcome::Help("commands: !add, !hug".into()));
+
+        let outcome = router
+            .route(&privmsg(
+                ":museun!museun@museun PRIVMSG #museun :!help !plus\r\n",
+            ))
+            .unwrap();
+        assert_eq!(
+            outcome.reply(),
+            Some("usage: !add <a> <b> - add two numbers")
+        );
+
+        assert_eq!(
+            router.help(None, Permission::Moderator),
+            "commands: !add, !hug, !so"
+        );
+        assert_eq!(
+            router.help(Some("nope"), Permission::Everyone),
+            "unknown command: !nope"
+        );
+
+        // unless there's a help command
+        router.add(Command::new("help", |_| Ok(Some("no help for you".into()))));
+        let outcome = router
+            .route(&privmsg(":museun!museun@museun PRIVMSG #museun :!help\r\n"))
+            .unwrap();
+        assert_eq!(outcome.reply(), Some("no help for you"));
+    }
+}
@@ -3,26 +3,34 @@ use crate::{
     channel::Receiver,
     commands,
     connector::Connector,
-    encoder::AsyncEncoder,
-    messages::{Capability, Commands, MessageId},
-    rate_limit::{RateClass, RateLimit},
-    twitch::UserConfig,
+    encoder::{AsyncEncoder, FlushThreshold},
+    messages::{Commands, FromCommands, MessageId, RoomSettings},
+    rate_limit::{JoinRateClass, RateClass, RateLimit},
+    twitch::{ChannelName, Role, TokenProvider, UserConfig},
     util::{Notify, NotifyHandle},
-    writer::{AsyncWriter, MpscWriter},
+    writer::{AsyncWriter, MpscWriter, Priority},
+    decoder::InvalidMessage,
     AsyncDecoder, DecodeError, Encodable, FromIrcMessage, IrcMessage,
 };
 
 use super::{
     channel::Channels,
-    timeout::{TimeoutState, RATE_LIMIT_WINDOW, TIMEOUT, WINDOW},
-    Capabilities, Channel, Error, Identity, Status, StepResult,
+    broadcast,
+    dispatcher::{Dispatcher, EventStream},
+    Handshake,
+    timeout::{
+        KeepAlive, TimeoutState, JOIN_RETRIES, JOIN_RETRY_DELAY, RATE_LIMIT_WINDOW,
+    },
+    Channel, Error, Flow, Identity, Metrics, Middleware, Names, NamesBuffer, NoMetrics, Overflow, Presence, Reader, ShutdownHandle, Status, StepResult, Subscription, Writer,
 };
 
 use futures_lite::{AsyncRead, AsyncWrite, AsyncWriteExt, Stream};
 use std::{
-    collections::{HashSet, VecDeque},
+    collections::{BTreeSet, VecDeque},
     pin::Pin,
+    sync::{Arc, Mutex},
     task::{Context, Poll},
+    time::{Duration, Instant},
 };
 
 /// An asynchronous runner
@@ -39,15 +47,74 @@ pub struct AsyncRunner {
     // why don't we use this?
     notify_handle: NotifyHandle,
 
+    shutdown: Notify,
+    shutdown_handle: ShutdownHandle,
+
     timeout_state: TimeoutState,
+    keep_alive: KeepAlive,
+    last_ping: Instant,
+    latency: Option<Duration>,
 
     decoder: AsyncDecoder<Box<dyn AsyncRead + Send + Sync + Unpin>>,
-    encoder: AsyncEncoder<Box<dyn AsyncWrite + Send + Sync + Unpin>>,
+    // this is shared with the Writer, if the runner is split
+    encoder: SharedEncoder,
+    flush_threshold: FlushThreshold,
 
     writer: AsyncWriter<MpscWriter>,
-    global_rate_limit: RateLimit,
+    global_rate_limit: Arc<Mutex<RateLimit>>,
+    join_rate_limit: RateLimit,
+    // lines from the writer which aren't for a channel, e.g. JOINs and PARTs
+    queued_commands: VecDeque<Box<[u8]>>,
 
     missed_messages: VecDeque<Commands<'static>>,
+
+    dispatcher: Dispatcher,
+    // the names are aggregated until the end of each channel's list
+    names: NamesBuffer,
+    names_dispatcher: Dispatcher<Names>,
+
+    metrics: Arc<dyn Metrics>,
+
+    middleware: Vec<Arc<Mutex<dyn Middleware>>>,
+}
+
+pub(crate) type SharedEncoder = Arc<async_mutex::Mutex<AsyncEncoder<Box<dyn AsyncWrite + Send + Sync + Unpin>>>>;
+
+// These outlive a single connection, so writers and quit handles can be reused across reconnects
+#[derive(Clone)]
+pub(crate) struct Handles {
+    pub(crate) writer: AsyncWriter<MpscWriter>,
+    pub(crate) writer_rx: Receiver<Box<[u8]>>,
+    pub(crate) activity_rx: Receiver<()>,
+    pub(crate) notify: Notify,
+    pub(crate) notify_handle: NotifyHandle,
+    pub(crate) shutdown: Notify,
+    pub(crate) shutdown_handle: ShutdownHandle,
+}
+
+impl Handles {
+    pub(crate) fn new() -> Self {
+        let (writer_tx, writer_rx) = crate::channel::unbounded();
+        let (notify, notify_handle) = Notify::new();
+        let (shutdown, shutdown_notify) = Notify::new();
+        let (activity_tx, activity_rx) = crate::channel::bounded(32);
+
+        let writer = AsyncWriter::new(MpscWriter::new(writer_tx), activity_tx);
+        let shutdown_handle = ShutdownHandle {
+            writer: writer.clone(),
+            notify: shutdown_notify,
+        };
+
+        Self {
+            writer,
+            writer_rx,
+            activity_rx,
+            notify,
+            notify_handle,
+            shutdown,
+            shutdown_handle,
+        }
+    }
 }
 
 impl std::fmt::Debug for AsyncRunner {
@@ -56,6 +123,12 @@ impl std::fmt::Debug for AsyncRunner {
     }
 }
 
+impl Drop for AsyncRunner {
+    fn drop(&mut self) {
+        self.metrics.disconnected();
+    }
+}
+
 impl AsyncRunner {
     /// Connect with the provided connector and the provided UserConfig
     ///
@@ -65,15 +138,69 @@ impl AsyncRunner {
         C: Connector,
         for<'a> &'a C::Output: AsyncRead + AsyncWrite + Send + Sync + Unpin,
     {
-        log::debug!("connecting");
-        let mut stream = { connector }.connect().await?;
-        log::debug!("connection established");
+        Self::connect_with_handles(connector, user_config, Handles::new()).await
+    }
+
+    /// Connect with the provided connector, getting the OAuth token from the [TokenProvider].
+    ///
+    /// The token in your UserConfig is replaced with the one from the provider.
+    /// If Twitch rejects the token, the provider is asked to [refresh](TokenProvider::refresh()) it and the connection is tried again (once).
+    pub async fn connect_with_token_provider<C>(
+        connector: C,
+        user_config: &UserConfig,
+        provider: &mut dyn TokenProvider,
+    ) -> Result<Self, Error>
+    where
+        C: Connector,
+        for<'a> &'a C::Output: AsyncRead + AsyncWrite + Send + Sync + Unpin,
+    {
+        Self::connect_with_handles_and_provider(connector, user_config, Handles::new(), provider)
+            .await
+    }
+
+    pub(crate) async fn connect_with_handles_and_provider<C>(
+        connector: C,
+        user_config: &UserConfig,
+        handles: Handles,
+        provider: &mut dyn TokenProvider,
+    ) -> Result<Self, Error>
+    where
+        C: Connector,
+        for<'a> &'a C::Output: AsyncRead + AsyncWrite + Send + Sync + Unpin,
+    {
+        let mut user_config = user_config.clone();
+        user_config.token = oauth_token(provider.token().await?);
+
+        match Self::connect_with_handles(connector.clone(), &user_config, handles.clone()).await {
+            Err(Error::AuthenticationFailed) => {
+                log::warn!("authentication failed, refreshing the token");
+                user_config.token = oauth_token(provider.refresh().await?);
+                Self::connect_with_handles(connector, &user_config, handles).await
+            }
+            res => res,
+        }
+    }
+
+    pub(crate) async fn connect_with_handles<C>(
+        connector: C,
+        user_config: &UserConfig,
+        handles: Handles,
+    ) -> Result<Self, Error>
+    where
+        C: Connector,
+        for<'a> &'a C::Output: AsyncRead + AsyncWrite + Send + Sync + Unpin,
+    {
+        event!(info, "connect", "connecting as '{}'", user_config.name);
+        let mut stream = in_span!("connect", { connector }.connect(), name = %user_config.name)
+            .await
+            .map_err(Error::connecting)?;
+        event!(info, "connect", "connection established");
 
         log::debug!("registering");
         let mut buf = vec![];
         commands::register(user_config).encode(&mut buf)?;
         stream.write_all(&buf).await?;
-        log::debug!("registered");
+        event!(info, "handshake", "registered, waiting for the connection to be ready");
 
         let read = async_dup::Arc::new(stream);
         let write = read.clone();
@@ -81,30 +208,44 @@ impl AsyncRunner {
         let read: Box<dyn AsyncRead + Send + Sync + Unpin> = Box::new(read);
         let write: Box<dyn AsyncWrite + Send + Sync + Unpin> = Box::new(write);
 
-        let mut decoder = AsyncDecoder::new(read);
+        // a weird line from Twitch shouldn't end the connection
+        let mut decoder = AsyncDecoder::new(read).lossy(true);
         let mut encoder = AsyncEncoder::new(write);
 
         log::debug!("waiting for the connection to be ready");
         let mut missed_messages = VecDeque::new();
-        let identity = Self::wait_for_ready(
+        let ready = Self::wait_for_ready(
             &mut decoder,
             &mut encoder,
             user_config,
             &mut missed_messages,
-        )
-        .await?;
-        log::debug!("connection is ready: {:?}", identity);
+        );
+        let login_timeout = async {
+            futures_timer::Delay::new(user_config.login_timeout).await;
+            log::warn!("timed out waiting for the connection to be ready");
+            Err(Error::LoginTimedOut)
+        };
+        let identity = in_span!("handshake", futures_lite::future::or(ready, login_timeout)).await?;
+        event!(info, "handshake", "connection is ready as '{}'", identity.username());
 
-        let (writer_tx, writer_rx) = crate::channel::unbounded();
-        let (notify, notify_handle) = Notify::new();
-        let (activity_tx, activity_rx) = crate::channel::bounded(32);
+        // Twitch ignores anything an anonymous user sends
+        handles.writer.set_read_only(user_config.is_anonymous());
 
-        let writer = AsyncWriter::new(MpscWriter::new(writer_tx), activity_tx);
+        let Handles {
+            writer,
+            writer_rx,
+            activity_rx,
+            notify,
+            notify_handle,
+            shutdown,
+            shutdown_handle,
+        } = handles;
 
         let timeout_state = TimeoutState::Start;
         let channels = Channels::default();
 
         let global_rate_limit = RateLimit::from_class(RateClass::Regular);
+        let join_rate_limit = RateLimit::from_join_class(JoinRateClass::Regular);
 
         Ok(Self {
             identity,
@@ -116,31 +257,284 @@ impl AsyncRunner {
             notify,
             notify_handle,
 
+            shutdown,
+            shutdown_handle,
+
             timeout_state,
+            keep_alive: KeepAlive::default(),
+            last_ping: Instant::now(),
+            latency: None,
 
             decoder,
-            encoder,
+            encoder: Arc::new(async_mutex::Mutex::new(encoder)),
+            flush_threshold: FlushThreshold::default(),
 
             writer,
-            global_rate_limit,
+            global_rate_limit: Arc::new(Mutex::new(global_rate_limit)),
+            join_rate_limit,
+            queued_commands: VecDeque::new(),
 
             missed_messages,
+
+            dispatcher: Dispatcher::default(),
+            names: NamesBuffer::default(),
+            names_dispatcher: Dispatcher::default(),
+
+            metrics: Arc::new(NoMetrics),
+
+            middleware: Vec::new(),
         })
     }
 
+    // the names of the channels we're currently on
+    pub(crate) fn channel_names(&self) -> impl Iterator<Item = &str> + '_ {
+        self.channels.map.keys().map(|s| &**s)
+    }
+
     /// Check whether you're on this channel
-    pub fn is_on_channel(&self, channel: &str) -> bool {
-        self.channels.is_on(channel)
+    pub fn is_on_channel(&self, channel: impl Into<ChannelName>) -> bool {
+        self.channels.is_on(&channel.into())
+    }
+
+    /// The chat settings for this channel, if you're on it.
+    ///
+    /// This is kept up to date as Twitch sends `ROOMSTATE` messages
+    pub fn room_state(&self, channel: impl Into<ChannelName>) -> Option<&RoomSettings> {
+        self.channels.map.get(&*channel.into()).map(Channel::room_settings)
+    }
+
+    /// Our role on this channel, if you're on it.
+    ///
+    /// This is kept up to date as Twitch sends `USERSTATE` messages (after joining, and after each message we send)
+    pub fn role(&self, channel: impl Into<ChannelName>) -> Option<Role> {
+        self.channels.map.get(&*channel.into()).map(Channel::role)
+    }
+
+    /// The users on this channel, if you're on it.
+    ///
+    /// This is kept up to date from `JOIN`, `PART` and `NAMES` messages, so it requires the `membership` capability.
+    pub fn chatters(&self, channel: impl Into<ChannelName>) -> Option<&BTreeSet<String>> {
+        self.channels.map.get(&*channel.into()).map(Channel::chatters)
+    }
+
+    /// The channel this channel is hosting, if you're on it and it's hosting one.
+    ///
+    /// This is kept up to date as Twitch sends `HOSTTARGET` messages
+    pub fn hosting(&self, channel: impl Into<ChannelName>) -> Option<&str> {
+        self.channels
+            .map
+            .get(&*channel.into())
+            .and_then(Channel::hosting)
     }
 
     /// Get a specific channel.
     ///
     /// This is useful for changing the rate limit/state manually.
-    pub fn get_channel_mut(&mut self, channel: &str) -> Option<&mut Channel> {
-        self.channels.get_mut(channel)
+    pub fn get_channel_mut(&mut self, channel: impl Into<ChannelName>) -> Option<&mut Channel> {
+        self.channels.get_mut(&channel.into())
+    }
+
+    /// Set the [JoinRateClass] used to throttle joins.
+    ///
+    /// Only use [JoinRateClass::Verified] if your bot is actually verified.
+    pub fn set_join_rate_class(&mut self, join_rate_class: JoinRateClass) {
+        self.join_rate_limit = RateLimit::from_join_class(join_rate_class);
+    }
+
+    /// Set when the messages the runner sends together are written.
+    ///
+    /// The queued messages which the rate limits allow are batched, and written with a single vectored write
+    /// once there are this many bytes (or messages), rather than a write per line. By default, this is 16 KiB.
+    pub fn set_flush_threshold(&mut self, threshold: FlushThreshold) {
+        self.flush_threshold = threshold;
+    }
+
+    /// Set the [KeepAlive] policy used to detect dead connections.
+    pub fn set_keep_alive(&mut self, keep_alive: KeepAlive) {
+        self.keep_alive = keep_alive;
+    }
+
+    /// Report what the runner does into these [Metrics].
+    pub fn set_metrics(&mut self, metrics: Arc<dyn Metrics>) {
+        self.metrics.disconnected();
+        self.metrics = metrics;
+        self.metrics.connected();
+    }
+
+    /// Add a [Middleware], which sees every message (after the ones already added) before it's produced.
+    pub fn add_middleware<M>(&mut self, middleware: M)
+    where
+        M: Middleware + 'static,
+    {
+        self.middleware.push(Arc::new(Mutex::new(middleware)));
+    }
+
+    // these are shared with the reconnect, so they outlive this connection
+    pub(crate) fn add_shared_middleware(&mut self, middleware: Arc<Mutex<dyn Middleware>>) {
+        self.middleware.push(middleware);
+    }
+
+    /// The round-trip time of the last `PING` the runner sent.
+    ///
+    /// This is `None` until the first `PONG` arrives.
+    pub fn latency(&self) -> Option<Duration> {
+        self.latency
+    }
+
+    /// Subscribe to a specific message type.
+    ///
+    /// e.g. `runner.events::<Privmsg>()` gives you a stream of just the `Privmsg`s.
+    ///
+    /// Each message produced by [AsyncRunner::next_message()] is cloned to every subscriber.
+    /// Each subscriber has its own buffer, so you must still drive the runner for these to produce messages.
+    pub fn events<T>(&mut self) -> EventStream<T>
+    where
+        T: FromCommands<'static> + Send + Sync + 'static,
+    {
+        self.dispatcher.subscribe()
+    }
+
+    /// Subscribe to a specific message type, buffering at most `capacity` messages.
+    ///
+    /// Like [AsyncRunner::events()], but a slow consumer can't make the buffer grow without bound (e.g. during a raid).
+    /// Once it's full, new messages are handled by the [Overflow] policy, and [EventStream::dropped()] counts what was dropped.
+    pub fn bounded_events<T>(&mut self, capacity: usize, overflow: Overflow) -> EventStream<T>
+    where
+        T: FromCommands<'static> + Send + Sync + 'static,
+    {
+        self.dispatcher.subscribe_bounded(capacity, overflow)
+    }
+
+    /// Subscribe to every message, with broadcast semantics.
+    ///
+    /// Clone the [Subscription] for more subscribers: each one sees every message produced by [AsyncRunner::next_message()],
+    /// so e.g. a GUI, a logger and the bot logic can each read the whole stream without a fanout task of their own.
+    ///
+    /// The runner keeps the last 1024 messages for the slowest subscriber.
+    /// A subscriber which falls further behind gets a [Lagged](super::Lagged) error, instead of making the runner buffer without bound.
+    ///
+    /// ```no_run
+    /// # use futures_lite::StreamExt as _;
+    /// # async fn demo(mut runner: twitchchat::AsyncRunner) {
+    /// let mut logger = runner.subscribe();
+    /// let mut bot = logger.clone();
+    ///
+    /// // e.g. in another task
+    /// while let Some(msg) = logger.next().await {
+    ///     match msg {
+    ///         Ok(msg) => println!("{}", msg.raw().trim_end()),
+    ///         Err(lagged) => eprintln!("missed {} messages", lagged.missed),
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    pub fn subscribe(&mut self) -> Subscription {
+        self.dispatcher.subscribe_broadcast(broadcast::CAPACITY)
+    }
+
+    // for subscribers which aren't just a message type (e.g. the merged eventsub stream)
+    #[cfg(feature = "eventsub")]
+    pub(crate) fn dispatcher(&mut self) -> &mut Dispatcher {
+        &mut self.dispatcher
+    }
+
+    /// Subscribe to a command this crate doesn't have a type for, parsing it into your own type.
+    ///
+    /// Messages with this `command` (which are otherwise produced as [Commands::Raw]) are parsed with `T::from_irc()`.
+    /// Messages which fail to parse are skipped. Use [IrcMessage](crate::irc::IrcMessage) for `T` to get them unparsed.
+    ///
+    /// ```no_run
+    /// # use twitchchat::{irc::IrcMessage, maybe_owned::MaybeOwned, FromIrcMessage, MessageError};
+    /// # async fn demo(mut runner: twitchchat::AsyncRunner) {
+    /// struct NewCommand {
+    ///     raw: MaybeOwned<'static>,
+    /// }
+    ///
+    /// impl FromIrcMessage<'static> for NewCommand {
+    ///     type Error = MessageError;
+    ///
+    ///     fn from_irc(msg: IrcMessage<'static>) -> Result<Self, Self::Error> {
+    ///         Ok(Self { raw: msg.into_inner() })
+    ///     }
+    ///
+    ///     fn into_inner(self) -> MaybeOwned<'static> {
+    ///         self.raw
+    ///     }
+    /// }
+    ///
+    /// let events = runner.raw_events::<NewCommand>("NEWCOMMAND");
+    /// # }
+    /// ```
+    pub fn raw_events<T>(&mut self, command: &str) -> EventStream<T>
+    where
+        T: FromIrcMessage<'static> + Send + Sync + 'static,
+    {
+        self.dispatcher.subscribe_raw(command)
+    }
+
+    /// Get a stream of all of the messages sent to `channel`.
+    ///
+    /// Like [AsyncRunner::events()], you must still drive the runner for this to produce messages.
+    pub fn channel_events(
+        &mut self,
+        channel: impl Into<ChannelName>,
+    ) -> EventStream<Commands<'static>> {
+        let channel = channel.into();
+        self.dispatcher
+            .subscribe_filter(move |msg| {
+                msg.channel()
+                    .filter(|ch| ch.eq_ignore_ascii_case(&channel))
+                    .is_some()
+            })
+    }
+
+    /// Get a stream of users joining and leaving channels.
+    ///
+    /// Like [AsyncRunner::events()], you must still drive the runner for this to produce messages.
+    pub fn presence_events(&mut self) -> EventStream<Presence> {
+        self.dispatcher.subscribe_map(Presence::from_commands)
+    }
+
+    /// Get a stream of the users on each channel, as Twitch lists them after we join it.
+    ///
+    /// Twitch splits the list over several `353` lines, which are collected into one [Names] once the `366` line ends it.
+    /// Like [AsyncRunner::events()], you must still drive the runner for this to produce messages.
+    pub fn names_events(&mut self) -> EventStream<Names> {
+        self.names_dispatcher
+            .subscribe_map(|names: &Names| Some(names.clone()))
+    }
+
+    /// Split the runner into a [Reader] and a [Writer], which can live on different tasks (or threads).
+    ///
+    /// The [Reader] is driven like the runner, and the [Writer] writes directly to the connection,
+    /// so it works even while nothing is driving the [Reader] (and after it's dropped).
+    ///
+    /// ```no_run
+    /// # use twitchchat::commands;
+    /// # async fn demo(runner: twitchchat::AsyncRunner) {
+    /// let (mut reader, mut writer) = runner.split();
+    ///
+    /// // e.g. in another task
+    /// writer.encode(commands::privmsg("#museun", "hello")).await.unwrap();
+    ///
+    /// while let Ok(status) = reader.next_message().await {
+    ///     // ..
+    /// }
+    /// # }
+    /// ```
+    pub fn split(self) -> (Reader, Writer) {
+        let writer = Writer::new(
+            Arc::clone(&self.encoder),
+            Arc::clone(&self.global_rate_limit),
+            self.writer.clone(),
+            Arc::clone(&self.metrics),
+        );
+        (Reader::new(self), writer)
     }
 
     /// Get a clonable writer you can use
+    ///
+    /// If you connected anonymously, this writer is read-only (it'll return an [ReadOnly](crate::writer::ReadOnly) error).
     pub fn writer(&self) -> AsyncWriter<MpscWriter> {
         self.writer.clone()
     }
@@ -152,18 +546,39 @@ impl AsyncRunner {
         self.notify_handle.clone()
     }
 
+    /// Get a handle that you can use to gracefully shut down the runner.
+    ///
+    /// See [ShutdownHandle] for how this differs from the [quit handle](AsyncRunner::quit_handle()).
+    pub fn shutdown_handle(&self) -> ShutdownHandle {
+        self.shutdown_handle.clone()
+    }
+
     /// Join `channel` and wait for it to complete
-    pub async fn join(&mut self, channel: &str) -> Result<(), Error> {
-        if self.is_on_channel(channel) {
+    ///
+    /// This will wait if the JOIN rate limit has been reached.
+    pub async fn join(&mut self, channel: impl Into<ChannelName>) -> Result<(), Error> {
+        let channel = channel.into();
+        if self.is_on_channel(&channel) {
             return Err(Error::AlreadyOnChannel {
                 channel: channel.to_string(),
             });
         }
 
+        while let Err(delay) = self.join_rate_limit.consume(1) {
+            event!(info, "rate_limit", "waiting {:.2?} to join '{}'", delay, channel);
+            self.metrics.throttled(delay);
+            in_span!(
+                "rate_limit",
+                futures_timer::Delay::new(delay),
+                channel = %channel,
+                wait = ?delay
+            )
+            .await;
+        }
+
         log::debug!("joining '{}'", channel);
-        self.encoder.encode(commands::join(channel)).await?;
+        self.encode(commands::join(&channel)).await?;
 
-        let channel = crate::commands::Channel::new(channel).to_string();
         log::debug!("waiting for a response");
 
         let mut queue = VecDeque::new();
@@ -182,36 +597,91 @@ impl AsyncRunner {
                     })
                 }
 
+                // check to see if the channel can't be joined right now
+                Commands::Notice(msg)
+                    if msg.channel() == channel
+                        && matches!(
+                            msg.msg_id(),
+                            Some(MessageId::MsgChannelSuspended)
+                                | Some(MessageId::MsgChannelBlocked)
+                        ) =>
+                {
+                    Err(Error::ChannelUnavailable {
+                        channel: msg.channel().to_string(),
+                    })
+                }
+
                 _ => Ok(false),
             })
-            .await?;
-
-        if let Some(status) = status {
-            match status {
-                Status::Quit | Status::Eof => return Err(Error::UnexpectedEof),
-                _ => unimplemented!(),
-            }
-        }
+            .await;
 
+        // don't lose any messages that were read while waiting
         self.missed_messages.extend(queue);
 
+        // this only returns a status when the connection ended
+        if status?.is_some() {
+            return Err(Error::UnexpectedEof);
+        }
+
         log::debug!("joined '{}'", channel);
 
         Ok(())
     }
 
+    /// Join all of these `channels`, waiting for each to complete
+    ///
+    /// The joins are spread out to respect the JOIN rate limit. Joins that fail because the channel is
+    /// unavailable are retried a few times. Channels you're already on are skipped.
+    ///
+    /// This returns the errors for the channels that could not be joined, the outer error is for connection failures.
+    pub async fn join_many<I, S>(&mut self, channels: I) -> Result<Vec<Error>, Error>
+    where
+        I: IntoIterator<Item = S> + Send,
+        S: AsRef<str>,
+    {
+        let mut queue = channels
+            .into_iter()
+            .map(|ch| (ChannelName::from(ch.as_ref()), 0, None))
+            .collect::<VecDeque<(_, _, Option<Instant>)>>();
+
+        let mut failed = vec![];
+
+        while let Some((channel, attempt, retry_at)) = queue.pop_front() {
+            if let Some(delay) = retry_at.and_then(|dt| dt.checked_duration_since(Instant::now())) {
+                futures_timer::Delay::new(delay).await;
+            }
+
+            match self.join(&channel).await {
+                Ok(()) | Err(Error::AlreadyOnChannel { .. }) => {}
+                Err(Error::ChannelUnavailable { .. }) if attempt < JOIN_RETRIES => {
+                    log::debug!("'{}' is unavailable, trying again later", channel);
+                    let retry_at = Instant::now() + JOIN_RETRY_DELAY;
+                    queue.push_back((channel, attempt + 1, Some(retry_at)));
+                }
+                Err(err @ Error::ChannelUnavailable { .. })
+                | Err(err @ Error::BannedFromChannel { .. }) => {
+                    log::warn!("could not join: {}", err);
+                    failed.push(err);
+                }
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(failed)
+    }
+
     /// Part `channel` and wait for it to complete
-    pub async fn part(&mut self, channel: &str) -> Result<(), Error> {
-        if !self.is_on_channel(channel) {
+    pub async fn part(&mut self, channel: impl Into<ChannelName>) -> Result<(), Error> {
+        let channel = channel.into();
+        if !self.is_on_channel(&channel) {
             return Err(Error::NotOnChannel {
                 channel: channel.to_string(),
             });
         }
 
         log::debug!("leaving '{}'", channel);
-        self.encoder.encode(commands::part(channel)).await?;
+        self.encode(commands::part(&channel)).await?;
 
-        let channel = crate::commands::Channel::new(channel).to_string();
         log::debug!("waiting for a response");
 
         let mut queue = VecDeque::new();
@@ -226,11 +696,9 @@ impl AsyncRunner {
             })
             .await?;
 
-        if let Some(status) = status {
-            match status {
-                Status::Quit | Status::Eof => return Err(Error::UnexpectedEof),
-                _ => unimplemented!(),
-            }
+        // this only returns a status when the connection ended
+        if status.is_some() {
+            return Err(Error::UnexpectedEof);
         }
         log::debug!("left '{}'", channel);
 
@@ -243,6 +711,9 @@ impl AsyncRunner {
     pub async fn next_message(&mut self) -> Result<Status<'static>, Error> {
         use crate::util::{Either::*, FutExt as _};
 
+        // a subscriber with `Overflow::Block` pauses the runner until it has room
+        self.dispatcher.ready().await;
+
         loop {
             match self.step().await? {
                 StepResult::Nothing => continue,
@@ -255,17 +726,36 @@ impl AsyncRunner {
                         // and then drain any remaining items
                         while self.available_queued_messages() > 0 {
                             self.drain_queued_messages().await?;
-                            futures_lite::future::yield_now().await;
+                            if self.available_queued_messages() > 0 {
+                                // wait for the rate limit to refill
+                                futures_timer::Delay::new(Duration::from_millis(100)).await;
+                            }
                         }
 
                         // and finally send the quit
-                        self.encoder.encode(commands::raw("QUIT\r\n")).await?;
+                        self.encode(commands::raw("QUIT\r\n")).await?;
 
                         // and signal that we've quit
                         break Ok(Status::Quit);
                     }
                 }
-                StepResult::Status(status) => break Ok(status),
+                StepResult::Status(Status::Stopped) => {
+                    self.stop().await?;
+                    break Ok(Status::Stopped);
+                }
+                StepResult::Status(mut status) => {
+                    if let Status::Message(msg) = &mut status {
+                        let dropped = self
+                            .middleware
+                            .iter()
+                            .any(|m| m.lock().unwrap().on_message(msg) == Flow::Drop);
+                        if dropped {
+                            continue;
+                        }
+                        self.dispatcher.dispatch(msg);
+                    }
+                    break Ok(status);
+                }
             }
         }
     }
@@ -279,13 +769,14 @@ impl AsyncRunner {
             return Ok(StepResult::Status(Status::Message(msg)));
         }
 
+        let next_check = self.next_check();
         let select = self
             .decoder
             .read_message()
             .either(self.activity_rx.recv())
             .either(self.writer_rx.recv())
-            .either(self.notify.wait())
-            .either(super::timeout::next_delay())
+            .either(self.notify.wait().either(self.shutdown.wait()))
+            .either(super::timeout::next_delay(next_check))
             .await;
 
         match select {
@@ -295,59 +786,59 @@ impl AsyncRunner {
                         log::info!("got an EOF, exiting main loop");
                         return Ok(StepResult::Status(Status::Eof));
                     }
+                    Err(DecodeError::Invalid(invalid)) => {
+                        log::warn!("skipping an invalid message: {}", invalid);
+                        self.metrics.parse_failed();
+                        self.timeout_state.mark_activity();
+                        return Ok(StepResult::Status(Status::Invalid(invalid)));
+                    }
                     Err(err) => {
                         log::warn!("read an error: {}", err);
+                        if !matches!(err, DecodeError::Io(..)) {
+                            self.metrics.parse_failed();
+                        }
                         return Err(err.into());
                     }
                     Ok(msg) => msg,
                 };
 
-                self.timeout_state = TimeoutState::activity();
+                self.timeout_state.mark_activity();
+
+                // a known command can still be missing something (e.g. a PRIVMSG without a channel)
+                let all = match Commands::from_irc(msg.clone()) {
+                    Ok(all) => all.into_owned(),
+                    Err(err) => {
+                        let invalid = InvalidMessage::parse_error(msg.get_raw(), err);
+                        log::warn!("skipping an invalid message: {}", invalid);
+                        self.metrics.parse_failed();
+                        return Ok(StepResult::Status(Status::Invalid(invalid)));
+                    }
+                };
 
-                let all = Commands::from_irc(msg) //
-                    .expect("msg identity conversion should be upheld")
-                    .into_owned();
+                self.metrics.message_parsed();
+                self.metrics.bytes_read(all.raw().len());
+                if let Commands::Privmsg(msg) = &all {
+                    self.metrics.chat_message(msg.channel());
+                }
 
                 self.check_messages(&all).await?;
 
                 return Ok(StepResult::Status(Status::Message(all)));
             }
 
-            Left(Left(Left(Right(Some(_activity))))) => {
-                self.timeout_state = TimeoutState::activity();
-            }
+            Left(Left(Left(Right(Some(_activity))))) => self.timeout_state.mark_activity(),
 
             Left(Left(Right(Some(write_data)))) => {
-                // TODO provide a 'bytes' flavored parser
-                let msg = std::str::from_utf8(&*write_data).map_err(Error::InvalidUtf8)?;
-                let res = crate::irc::parse_one(msg) //
-                    .expect("encoder should produce valid IRC messages");
-                let msg = res.1;
-
-                if let crate::irc::IrcMessage::PRIVMSG = msg.get_command() {
-                    if let Some(ch) = msg.nth_arg(0) {
-                        if !self.channels.is_on(ch) {
-                            self.channels.add(ch)
-                        }
-
-                        let ch = self.channels.get_mut(ch).unwrap();
-                        if ch.rated_limited_at.map(|s| s.elapsed()) > Some(RATE_LIMIT_WINDOW) {
-                            ch.reset_rate_limit();
-                        }
-
-                        ch.rate_limited.enqueue(write_data)
-                    }
-                }
+                self.enqueue_write(write_data)?;
+                self.metrics.writer_queue_depth(self.writer_rx.len() + self.available_queued_messages());
             }
 
-            Left(Right(_notified)) => return Ok(StepResult::Status(Status::Quit)),
+            Left(Right(Left(_notified))) => return Ok(StepResult::Status(Status::Quit)),
 
-            Right(_timeout) => {
-                log::info!("idle connection detected, sending a ping");
-                let ts = timestamp().to_string();
-                self.encoder.encode(commands::ping(&ts)).await?;
-                self.timeout_state = TimeoutState::waiting_for_pong();
-            }
+            Left(Right(Right(_shutdown))) => return Ok(StepResult::Status(Status::Stopped)),
+
+            // the keep-alive is checked below
+            Right(_timeout) => {}
 
             _ => {
                 return Ok(StepResult::Status(Status::Eof));
@@ -356,20 +847,20 @@ impl AsyncRunner {
 
         match self.timeout_state {
             TimeoutState::WaitingForPong(dt) => {
-                if dt.elapsed() > TIMEOUT {
+                if dt.elapsed() >= self.keep_alive.get_timeout() {
                     log::warn!("PING timeout detected, exiting");
                     return Err(Error::TimedOut);
                 }
             }
-            TimeoutState::Activity(dt) => {
-                if dt.elapsed() > WINDOW {
-                    log::warn!("idle connectiond detected, sending a PING");
+            TimeoutState::Activity(..) | TimeoutState::Start => {
+                if self.last_ping.elapsed() >= self.keep_alive.get_interval() {
+                    log::debug!("sending a keep-alive PING");
                     let ts = timestamp().to_string();
-                    self.encoder.encode(crate::commands::ping(&ts)).await?;
+                    self.encode(commands::ping(&ts)).await?;
                     self.timeout_state = TimeoutState::waiting_for_pong();
+                    self.last_ping = Instant::now();
                 }
             }
-            TimeoutState::Start => {}
         }
 
         log::trace!("draining messages");
@@ -383,40 +874,108 @@ impl AsyncRunner {
 
         log::trace!("< {}", all.raw().escape_debug());
 
+        if let Some(names) = self.names.update(all) {
+            self.names_dispatcher.dispatch(&names);
+        }
+
         match &all {
-            Ping(msg) => {
+            Ping(msg) if self.keep_alive.get_auto_pong() => {
                 let token = msg.token();
                 log::debug!(
                     "got a ping from the server. responding with token '{}'",
                     token
                 );
-                self.encoder.encode(commands::pong(token)).await?;
-                self.timeout_state = TimeoutState::activity();
+                self.encode(commands::pong(token)).await?;
             }
 
-            Pong(..) if matches!(self.timeout_state, WaitingForPong {..}) => {
-                self.timeout_state = TimeoutState::activity()
+            Pong(..) => {
+                if let WaitingForPong(dt) = self.timeout_state {
+                    let latency = dt.elapsed();
+                    log::trace!("got a pong, latency: {:.2?}", latency);
+                    self.latency.replace(latency);
+                    self.metrics.latency(latency);
+                    self.timeout_state = TimeoutState::activity();
+                }
             }
 
-            Join(msg) if msg.name() == self.identity.username() => {
-                log::debug!("starting tracking channel for '{}'", msg.channel());
-                self.channels.add(msg.channel());
+            Join(msg) => {
+                if msg.name() == self.identity.username() {
+                    log::debug!("starting tracking channel for '{}'", msg.channel());
+                    self.channels.add(msg.channel());
+                    self.writer.confirmations().membership_changed(msg.channel(), true);
+                }
+                if let Some(ch) = self.channels.get_mut(msg.channel()) {
+                    ch.chatters.insert(msg.name().to_string());
+                }
             }
 
             Part(msg) if msg.name() == self.identity.username() => {
                 log::debug!("stopping tracking of channel '{}'", msg.channel());
                 self.channels.remove(msg.channel());
+                self.update_global_rate_limit();
+                self.writer.confirmations().membership_changed(msg.channel(), false);
+                self.writer.restrictions().remove(msg.channel());
+            }
+
+            Part(msg) => {
+                if let Some(ch) = self.channels.get_mut(msg.channel()) {
+                    ch.chatters.remove(msg.name());
+                }
+            }
+
+            // :name.tmi.twitch.tv 353 name = #channel :user1 user2 user3
+            Raw(msg) if msg.get_command() == IrcMessage::NAMES => {
+                let ch = msg.nth_arg(2).and_then(|ch| self.channels.get_mut(ch));
+                if let (Some(ch), Some(names)) = (ch, msg.get_data()) {
+                    ch.chatters.extend(names.split_whitespace().map(ToString::to_string));
+                }
+            }
+
+            UserState(msg) => {
+                self.writer.confirmations().accept(msg);
+                let role = msg.role();
+                let elevated = role.is_elevated();
+                if let Some(ch) = self.channels.get_mut(msg.channel()) {
+                    self.writer.restrictions().set_role(msg.channel(), role);
+                    ch.role = role;
+                    // Twitch sends this after every message we send, so only update on changes
+                    if ch.is_moderator != elevated {
+                        log::debug!("updating rate limit for '{}', elevated: {}", msg.channel(), elevated);
+                        ch.is_moderator = elevated;
+                        ch.update_slow_mode();
+                        self.writer.set_moderator(msg.channel(), elevated);
+                        ch.set_rate_class(if elevated {
+                            RateClass::Moderator
+                        } else {
+                            RateClass::Regular
+                        });
+                        self.update_global_rate_limit();
+                    }
+                }
             }
 
             RoomState(msg) => {
-                if let Some(dur) = msg.is_slow_mode() {
-                    if let Some(ch) = self.channels.get_mut(msg.channel()) {
+                if let Some(ch) = self.channels.get_mut(msg.channel()) {
+                    ch.room_settings.update(msg);
+                    ch.update_slow_mode();
+                    self.writer
+                        .restrictions()
+                        .set_room_settings(msg.channel(), &ch.room_settings);
+                    if let Some(dur) = msg.is_slow_mode() {
                         ch.enable_slow_mode(dur)
                     }
                 }
             }
 
+            HostTarget(msg) => {
+                if let Some(ch) = self.channels.get_mut(msg.source()) {
+                    ch.hosting = msg.target().map(|target| ChannelName::from(target).into());
+                }
+            }
+
             Notice(msg) => {
+                self.writer.reject(msg);
+                self.writer.confirmations().reject_membership(msg);
                 let ch = self.channels.get_mut(msg.channel());
                 match (msg.msg_id(), ch) {
                     // we should enable slow mode
@@ -426,7 +985,10 @@ impl AsyncRunner {
                     // we've been rate limited on the channel
                     (Some(MessageId::MsgRatelimit), Some(ch)) => ch.set_rate_limited(),
                     // we cannot join/send to the channel because we're banned
-                    (Some(MessageId::MsgBanned), ..) => self.channels.remove(msg.channel()),
+                    (Some(MessageId::MsgBanned), ..) => {
+                        self.channels.remove(msg.channel());
+                        self.update_global_rate_limit();
+                    }
                     _ => {}
                 }
             }
@@ -457,62 +1019,202 @@ impl AsyncRunner {
                     }
                     missed.push_back(msg);
                 }
+                // these were already logged, keep waiting
+                StepResult::Status(Status::Invalid(..)) => continue,
                 StepResult::Status(d) => return Ok(Some(d)),
                 StepResult::Nothing => continue,
             }
         }
     }
 
+    // this writes directly to the connection, skipping the rate limiting
+    async fn encode<M>(&mut self, msg: M) -> std::io::Result<()>
+    where
+        M: Encodable + Send + Sync,
+    {
+        let mut data = self.writer.pool().take();
+        msg.encode(&mut data)?;
+        self.metrics.bytes_written(data.len());
+        let res = self.encoder.lock().await.encode_bytes(&data).await;
+        self.writer.pool().give(data);
+        res
+    }
+
+    fn enqueue_write(&mut self, write_data: Box<[u8]>) -> Result<(), Error> {
+        // a coalescing writer sends many lines at once
+        if write_data.iter().filter(|&&c| c == b'\n').nth(1).is_some() {
+            for line in write_data.split_inclusive(|&c| c == b'\n') {
+                self.enqueue_line(line.into())?;
+            }
+            return Ok(());
+        }
+        self.enqueue_line(write_data)
+    }
+
+    fn enqueue_line(&mut self, write_data: Box<[u8]>) -> Result<(), Error> {
+        let (priority, write_data) = Priority::take(write_data);
+
+        // TODO provide a 'bytes' flavored parser
+        let msg = std::str::from_utf8(&*write_data).map_err(Error::InvalidUtf8)?;
+        let res = crate::irc::parse_one(msg) //
+            .expect("encoder should produce valid IRC messages");
+        let msg = res.1;
+
+        match (msg.get_command(), msg.nth_arg(0)) {
+            (IrcMessage::PRIVMSG, Some(ch)) => {
+                if !self.channels.is_on(ch) {
+                    self.channels.add(ch)
+                }
+
+                let ch = self.channels.get_mut(ch).unwrap();
+                if ch.rated_limited_at.map(|s| s.elapsed()) > Some(RATE_LIMIT_WINDOW) {
+                    ch.reset_rate_limit();
+                }
+
+                ch.rate_limited.enqueue(priority, write_data)
+            }
+            _ => self.queued_commands.push_back(write_data),
+        }
+
+        Ok(())
+    }
+
+    async fn stop(&mut self) -> Result<(), Error> {
+        log::debug!("shutting down, flushing queued messages");
+
+        // stop accepting new writes, but keep everything already written
+        self.writer.set_shut_down();
+        self.writer_rx.close();
+        while let Some(write_data) = self.writer_rx.try_recv() {
+            self.enqueue_write(write_data)?;
+        }
+
+        while self.available_queued_messages() > 0 {
+            self.drain_queued_messages().await?;
+            if self.available_queued_messages() > 0 {
+                // wait for the rate limit to refill
+                futures_timer::Delay::new(Duration::from_millis(100)).await;
+            }
+        }
+
+        self.encode(commands::raw("QUIT\r\n")).await?;
+        log::debug!("shut down");
+        Ok(())
+    }
+
+    // when to wake up, for the keep-alive or for messages held back by slow mode
+    fn next_check(&self) -> Duration {
+        let mut keep_alive = self.keep_alive.next_check(self.timeout_state, self.last_ping);
+        if !self.queued_commands.is_empty() {
+            // these are only held back by the rate limits
+            keep_alive = keep_alive.min(Duration::from_millis(100));
+        }
+        self.channels
+            .map
+            .values()
+            .filter_map(|ch| ch.rate_limited.next_send())
+            .fold(keep_alive, std::cmp::min)
+    }
+
+    // every channel is held to its own rate class, the global limit is the highest one in use
+    fn update_global_rate_limit(&mut self) {
+        let class = if self.channels.map.values().any(|ch| ch.is_moderator) {
+            RateClass::Moderator
+        } else {
+            RateClass::Regular
+        };
+        let mut global = self.global_rate_limit.lock().unwrap();
+        if global.get_cap() != class.tickets() {
+            log::debug!("updating the global rate limit to {:?}", class);
+            global.set_cap(class.tickets());
+        }
+    }
+
     fn available_queued_messages(&self) -> usize {
         self.channels
             .map
             .values()
             .map(|s| s.rate_limited.queue.len())
-            .sum()
+            .sum::<usize>()
+            + self.queued_commands.len()
     }
 
     async fn drain_queued_messages(&mut self) -> std::io::Result<()> {
-        let enc = &mut self.encoder;
-        let limit = &mut self.global_rate_limit.get_available_tokens();
-
-        let start = *limit;
-
-        // for each channel, try to take up to 'limit' tokens
-        for channel in self.channels.map.values_mut() {
-            if channel.rated_limited_at.map(|s| s.elapsed()) > Some(RATE_LIMIT_WINDOW) {
-                channel.reset_rate_limit();
+        let mut enc = self.encoder.lock().await;
+        let enc = &mut *enc;
+        enc.set_flush_threshold(self.flush_threshold);
+        let mut available = self.global_rate_limit.lock().unwrap().get_available_tokens();
+        let limit = &mut available;
+
+        // commands which aren't for a channel go first. JOINs only wait for the join rate limit, and PARTs aren't limited
+        while let Some(data) = self.queued_commands.pop_front() {
+            if data.starts_with(b"JOIN ") {
+                if let Err(delay) = self.join_rate_limit.consume(1) {
+                    log::debug!("join rate limit hit, waiting {:.2?}", delay);
+                    self.metrics.throttled(delay);
+                    self.queued_commands.push_front(data);
+                    break;
+                }
+            } else if !data.starts_with(b"PART ") {
+                if *limit == 0 {
+                    self.queued_commands.push_front(data);
+                    break;
+                }
+                if let Err(delay) = self.global_rate_limit.lock().unwrap().consume(1) {
+                    self.metrics.throttled(delay);
+                    self.queued_commands.push_front(data);
+                    break;
+                }
+                *limit -= 1;
             }
+            self.metrics.bytes_written(data.len());
+            enc.batch_bytes(data).await?;
+        }
 
-            // drain until we're out of messages, or tokens
-            channel
-                .rate_limited
-                .drain_until_blocked(&channel.name, limit, enc)
-                .await?;
+        // the high priority messages of every channel go first
+        'passes: for &min in &[Priority::High, Priority::Low] {
+            let start = *limit;
 
-            let left = std::cmp::max(start, *limit);
-            let right = std::cmp::min(start, *limit);
+            // for each channel, try to take up to 'limit' tokens
+            for channel in self.channels.map.values_mut() {
+                if channel.rated_limited_at.map(|s| s.elapsed()) > Some(RATE_LIMIT_WINDOW) {
+                    channel.reset_rate_limit();
+                }
 
-            let diff = left - right;
+                // drain until we're out of messages, or tokens
+                channel
+                    .rate_limited
+                    .drain_until_blocked(&channel.name, limit, enc, &*self.metrics, min)
+                    .await?;
 
-            if *limit == 0 {
-                log::warn!(target: "twitchchat::rate_limit", "global rate limit hit while draining '{}'", &channel.name);
-                break;
-            }
+                let left = std::cmp::max(start, *limit);
+                let right = std::cmp::min(start, *limit);
 
-            // and throttle the global one
-            match self.global_rate_limit.consume(diff) {
-                // use the new remaining amount of tokens
-                Ok(rem) => *limit = rem,
+                let diff = left - right;
 
-                // we're globally rate limited, so just return
-                Err(..) => {
-                    log::warn!(target: "twitchchat::rate_limit", "global rate limit hit while draining '{}'", &channel.name);
-                    break;
+                if *limit == 0 {
+                    event!(warn, "rate_limit", "global rate limit hit while draining '{}'", &channel.name);
+                    break 'passes;
+                }
+
+                // and throttle the global one
+                let consumed = self.global_rate_limit.lock().unwrap().consume(diff);
+                match consumed {
+                    // use the new remaining amount of tokens
+                    Ok(rem) => *limit = rem,
+
+                    // we're globally rate limited, so just return
+                    Err(delay) => {
+                        self.metrics.throttled(delay);
+                        event!(warn, "rate_limit", "global rate limit hit while draining '{}'", &channel.name);
+                        break 'passes;
+                    }
                 }
             }
         }
 
-        Ok(())
+        // everything which was drained goes out together
+        enc.flush_batch().await
     }
 
     async fn wait_for_ready<R, W>(
@@ -527,140 +1229,49 @@ impl AsyncRunner {
     {
         use crate::IntoOwned as _;
 
-        let is_anonymous = user_config.is_anonymous();
-
-        let mut looking_for: HashSet<_> = user_config.capabilities.iter().collect();
-        let mut caps = Capabilities::default();
-        let mut our_name = None;
-
-        use crate::twitch::Capability as TwitchCap;
-        // Twitch says we'll be getting a GlobalUserState if we just send the
-        // Tags capability
-        //
-        // This is false. Twitch will only send GlobalUserState if we've sent
-        // the Commands capability and atleast 1 other capability.
-        //
-        // That other capability doesn't have to be Tags, interestingly enough.
-        // So a combination of both 'Commands' and 'Membership' will produce an
-        // empty GlobalUserState
-        //
-        // We'll check for both Tags and Commands
-        //
-        let will_be_getting_global_user_state_hopefully =
-            user_config.capabilities.contains(&TwitchCap::Tags) &&
-            user_config.capabilities.contains(&TwitchCap::Commands);
+        let mut handshake = Handshake::new(user_config);
 
         let identity = loop {
-            let msg: IrcMessage<'_> = decoder.read_message().await?;
+            let msg: IrcMessage<'_> = match decoder.read_message().await {
+                Err(DecodeError::Invalid(invalid)) => {
+                    log::warn!("skipping an invalid message: {}", invalid);
+                    continue;
+                }
+                msg => msg?,
+            };
 
             // this should always be infallible. its not marked infallible
             // because of the 'non-exhaustive' attribute
-            use Commands::*;
             let commands = Commands::from_irc(msg)?;
 
             // this is the simpliest way. and this'll only clone like 9 messages
             missed_messages.push_back(commands.clone().into_owned());
 
-            match commands {
-                Ready(msg) => {
-                    our_name.replace(msg.username().to_string());
-
-                    // if we aren't going to be receiving tags, then we
-                    // won't be looking for any more messages
-
-                    // if we're anonymous, we won't get GLOBALUSERSTATE even
-                    // if we do send Tags
-                    if is_anonymous {
-                        break Identity::Anonymous { caps };
-                    }
-
-                    // if we're not looking for any more caps and we won't be
-                    // getting a GlobalUserState just give them the basic
-                    // Identity
-                    if looking_for.is_empty() && !will_be_getting_global_user_state_hopefully {
-                        break Identity::Basic {
-                            name: our_name.take().unwrap(),
-                            caps,
-                        };
-                    }
-                }
-
-                Cap(msg) => match msg.capability() {
-                    Capability::Acknowledged(name) => {
-                        use crate::twitch::Capability as Cap;
-
-                        let cap = match Cap::maybe_from_str(name) {
-                            Some(cap) => cap,
-                            // Twitch sent us an unknown capability
-                            None => {
-                                caps.unknown.insert(name.to_string());
-                                continue;
-                            }
-                        };
-
-                        *match cap {
-                            Cap::Tags => &mut caps.tags,
-                            Cap::Membership => &mut caps.membership,
-                            Cap::Commands => &mut caps.commands,
-                        } = true;
-
-                        looking_for.remove(&cap);
-                    }
+            // Reply to any PINGs while waiting. Although Twitch doesn't
+            // currently send a PING for spoof detection on initial
+            // handshake, one day they may. Most IRC servers do this
+            // already
+            if let Commands::Ping(msg) = &commands {
+                encoder.encode(commands::pong(msg.token())).await?;
+            }
 
-                    Capability::NotAcknowledged(name) => {
-                        return Err(Error::InvalidCap {
-                            cap: name.to_string(),
-                        })
-                    }
-                },
-
-                // NOTE: This will only be sent when there's both Commands and atleast one other CAP requested
-                GlobalUserState(msg) => {
-                    // TODO: this is so shitty.
-                    let id = match msg.user_id {
-                        Some(id) => id.parse().unwrap(),
-                        // XXX: we can get this message without any tags
-                        None => {
-                            break Identity::Basic {
-                                name: our_name.take().unwrap(),
-                                caps,
-                            };
-                        }
-                    };
-
-                    break Identity::Full {
-                        // these unwraps should be safe because we'll have all of the TAGs here
-                        name: our_name.unwrap(),
-                        user_id: id,
-                        display_name: msg.display_name.map(|s| s.to_string()),
-                        color: msg.color,
-                        caps,
-                    };
-
-                }
-
-                // Reply to any PINGs while waiting. Although Twitch doesn't
-                // currently send a PING for spoof detection on initial
-                // handshake, one day they may. Most IRC servers do this
-                // already
-                Ping(msg) => encoder.encode(commands::pong(msg.token())).await?,
-
-                _ => {
-                    // we have our name, but we won't be getting GlobalUserState and we've got all of our Caps
-                    if our_name.is_some() && !will_be_getting_global_user_state_hopefully && looking_for.is_empty() {
-                        break Identity::Basic {
-                            name: our_name.take().unwrap(),
-                            caps,
-                        };
-                    }
-                }
-            };
+            if let Some(identity) = handshake.step(&commands)? {
+                break identity;
+            }
         };
 
         Ok(identity)
     }
 }
 
+// formats this token the way the PASS command wants it
+fn oauth_token(token: String) -> String {
+    if token.starts_with("oauth:") {
+        return token;
+    }
+    format!("oauth:{}", token)
+}
+
 impl Stream for AsyncRunner {
     type Item = Commands<'static>;
 
@@ -672,10 +1283,192 @@ impl Stream for AsyncRunner {
         match futures_lite::ready!(fut.poll(ctx)) {
             Ok(status) => match status {
                 Status::Message(msg) => Poll::Ready(Some(msg)),
-                Status::Quit | Status::Eof => Poll::Ready(None),
+                // skip it, and try again
+                Status::Invalid(..) => {
+                    ctx.waker().wake_by_ref();
+                    Poll::Pending
+                }
+                Status::Quit | Status::Stopped | Status::Eof => Poll::Ready(None),
             },
             Err(..) => Poll::Ready(None),
         }
     }
 }
 }
+
+#[cfg(all(test, feature = "testing"))]
+mod tests {
+    use crate::{
+        commands,
+        messages::Commands,
+        test::{user_config, MockServer},
+        Status, UserConfig,
+    };
+    use futures_lite::future::{block_on, or, zip};
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn elevated_rate_limit() {
+        let server = MockServer::new();
+        block_on(async {
+            let (mut runner, conn) = server.connect_runner(&user_config()).await.unwrap();
+
+            conn.send(":shaken_bot!shaken_bot@shaken_bot.tmi.twitch.tv JOIN #museun")
+                .unwrap();
+            conn.send("@badge-info=;badges=moderator/1;color=;display-name=shaken_bot;emote-sets=0;mod=1;subscriber=0;user-type=mod :tmi.twitch.tv USERSTATE #museun").unwrap();
+            while !matches!(
+                runner.next_message().await.unwrap(),
+                Status::Message(Commands::UserState(..))
+            ) {}
+
+            conn.send(":shaken_bot!shaken_bot@shaken_bot.tmi.twitch.tv JOIN #shaken_bot")
+                .unwrap();
+            while !matches!(
+                runner.next_message().await.unwrap(),
+                Status::Message(Commands::Join(..))
+            ) {}
+
+            // only the channel we moderate gets the higher limit, the global one follows it
+            let channel = runner.get_channel_mut("museun").unwrap();
+            assert!(channel.is_moderator());
+            assert_eq!(channel.rate_limited.rate_limit.get_cap(), 100);
+            let channel = runner.get_channel_mut("shaken_bot").unwrap();
+            assert_eq!(channel.rate_limited.rate_limit.get_cap(), 20);
+            assert_eq!(runner.global_rate_limit.lock().unwrap().get_cap(), 100);
+
+            // and goes back down once we leave it
+            conn.send(":shaken_bot!shaken_bot@shaken_bot.tmi.twitch.tv PART #museun")
+                .unwrap();
+            while !matches!(
+                runner.next_message().await.unwrap(),
+                Status::Message(Commands::Part(..))
+            ) {}
+            assert_eq!(runner.global_rate_limit.lock().unwrap().get_cap(), 20);
+        });
+    }
+
+    #[test]
+    fn membership_isnt_rate_limited() {
+        let server = MockServer::new();
+        block_on(async {
+            let (mut runner, mut conn) = server.connect_runner(&user_config()).await.unwrap();
+            let available = runner
+                .global_rate_limit
+                .lock()
+                .unwrap()
+                .get_available_tokens();
+
+            let mut writer = runner.writer();
+            writer.encode(commands::join("museun")).await.unwrap();
+            writer.encode(commands::part("museun")).await.unwrap();
+
+            let lines = async { (conn.read_line().await, conn.read_line().await) };
+            let (join, part) = or(lines, async {
+                loop {
+                    runner.step().await.unwrap();
+                }
+            })
+            .await;
+            assert_eq!(join.unwrap(), "JOIN #museun\r\n");
+            assert_eq!(part.unwrap(), "PART #museun\r\n");
+
+            // only the join rate limit was used
+            let global = runner.global_rate_limit.lock().unwrap();
+            assert_eq!(global.get_available_tokens(), available);
+        });
+    }
+
+    #[test]
+    fn hosting() {
+        let server = MockServer::new();
+        block_on(async {
+            let (mut runner, conn) = server.connect_runner(&user_config()).await.unwrap();
+
+            conn.send(":shaken_bot!shaken_bot@shaken_bot.tmi.twitch.tv JOIN #museun")
+                .unwrap();
+            conn.send(":tmi.twitch.tv HOSTTARGET #museun :Shaken_Bot 42")
+                .unwrap();
+            while !matches!(
+                runner.next_message().await.unwrap(),
+                Status::Message(Commands::HostTarget(..))
+            ) {}
+            assert_eq!(runner.hosting("museun"), Some("#shaken_bot"));
+
+            conn.send(":tmi.twitch.tv HOSTTARGET #museun :- 0").unwrap();
+            while !matches!(
+                runner.next_message().await.unwrap(),
+                Status::Message(Commands::HostTarget(..))
+            ) {}
+            assert_eq!(runner.hosting("museun"), None);
+        });
+    }
+    #[test]
+    fn invalid_while_joining() {
+        let server = MockServer::new();
+        block_on(async {
+            let (mut runner, mut conn) = server.connect_runner(&user_config()).await.unwrap();
+
+            // a PING without a token can't be parsed
+            conn.send(":tmi.twitch.tv PING").unwrap();
+            let (joined, line) = zip(runner.join("museun"), conn.read_line()).await;
+            joined.unwrap();
+            assert_eq!(line.unwrap(), "JOIN #museun\r\n");
+
+            conn.send(":tmi.twitch.tv PING").unwrap();
+            let (parted, line) = zip(runner.part("museun"), conn.read_line()).await;
+            parted.unwrap();
+            assert_eq!(line.unwrap(), "PART #museun\r\n");
+        });
+    }
+
+    #[test]
+    fn skips_invalid_messages() {
+        let server = MockServer::new();
+        block_on(async {
+            let (mut runner, conn) = server.connect_runner(&user_config()).await.unwrap();
+
+            conn.send(":tmi.twitch.tv PING").unwrap();
+            conn.send(":museun!museun@museun.tmi.twitch.tv PRIVMSG")
+                .unwrap();
+            conn.send(":museun!museun@museun.tmi.twitch.tv PRIVMSG #museun :still here")
+                .unwrap();
+
+            let mut invalid = Vec::new();
+            let msg = loop {
+                match runner.next_message().await.unwrap() {
+                    Status::Message(Commands::Privmsg(msg)) => break msg,
+                    Status::Invalid(msg) => invalid.push(msg),
+                    _ => {}
+                }
+            };
+            assert_eq!(msg.data(), "still here");
+
+            assert_eq!(invalid.len(), 2);
+            assert_eq!(invalid[0].raw, ":tmi.twitch.tv PING\r\n");
+            for invalid in invalid {
+                // the end of the line, where something was missing
+                assert_eq!(invalid.offset, invalid.raw.len() - 2);
+            }
+        });
+    }
+
+    #[test]
+    fn latency() {
+        let server = MockServer::new().with_latency(Duration::from_millis(50));
+        let user_config = UserConfig::builder().anonymous().build().unwrap();
+        block_on(async {
+            let (mut runner, conn) = server.connect_runner(&user_config).await.unwrap();
+
+            let start = Instant::now();
+            conn.send("PING :1234").unwrap();
+            conn.close();
+
+            // skip the messages from the handshake
+            while !matches!(
+                runner.next_message().await.unwrap(),
+                Status::Message(Commands::Ping(..))
+            ) {}
+            assert!(start.elapsed() >= Duration::from_millis(50));
+        });
+    }
+}
@@ -0,0 +1,266 @@
+use crate::messages::Commands;
+
+use futures_lite::Stream;
+use std::{
+    collections::VecDeque,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll, Waker},
+};
+
+// how many messages are kept for the slowest subscriber, before it lags
+pub(crate) const CAPACITY: usize = 1024;
+
+/// A [Subscription] fell behind, so it missed some messages.
+///
+/// The subscription skips ahead to the oldest message it still has, so the next read continues from there.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Lagged {
+    /// How many messages were missed
+    pub missed: u64,
+}
+
+impl std::fmt::Display for Lagged {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "the subscriber fell behind, and missed {} messages",
+            self.missed
+        )
+    }
+}
+
+impl std::error::Error for Lagged {}
+
+pub(crate) struct State<T> {
+    buffer: VecDeque<T>,
+    // the position of the first buffered message, in the whole stream
+    head: u64,
+    capacity: usize,
+    receivers: usize,
+    // the runner was dropped
+    finished: bool,
+    wakers: Vec<Waker>,
+}
+
+impl<T> State<T> {
+    fn tail(&self) -> u64 {
+        self.head + self.buffer.len() as u64
+    }
+
+    pub(crate) fn has_receivers(&self) -> bool {
+        self.receivers > 0
+    }
+}
+
+pub(crate) struct BroadcastSender<T> {
+    state: Arc<Mutex<State<T>>>,
+}
+
+impl<T: Clone> BroadcastSender<T> {
+    // returns false once every subscription was dropped
+    pub(crate) fn send(&self, item: &T) -> bool {
+        let mut state = self.state.lock().unwrap();
+        if !state.has_receivers() {
+            return false;
+        }
+        state.buffer.push_back(item.clone());
+        if state.buffer.len() > state.capacity {
+            state.buffer.pop_front();
+            state.head += 1;
+        }
+        state.wakers.drain(..).for_each(Waker::wake);
+        true
+    }
+}
+
+impl<T> Drop for BroadcastSender<T> {
+    fn drop(&mut self) {
+        let mut state = self.state.lock().unwrap();
+        state.finished = true;
+        state.wakers.drain(..).for_each(Waker::wake);
+    }
+}
+
+/// A stream of every message produced by the [AsyncRunner](super::AsyncRunner), from [AsyncRunner::subscribe()](super::AsyncRunner::subscribe()).
+///
+/// Cloning a subscription gives another independent subscriber, which starts at the same position.
+/// Every subscriber sees every message, so e.g. a GUI, a logger and the bot logic can each read the whole stream.
+///
+/// The runner keeps the most recent messages for the subscribers to read, up to a limit.
+/// A subscriber which falls further behind than that gets a [Lagged] error with how many messages it missed, and continues from the oldest one still kept.
+///
+/// The runner must still be driven (e.g. by calling [next_message](super::AsyncRunner::next_message()) in a loop) for messages to arrive.
+/// This stream ends when the runner is dropped.
+pub struct Subscription<T = Commands<'static>> {
+    state: Arc<Mutex<State<T>>>,
+    // the position of the next message to read
+    next: u64,
+    lagged: u64,
+}
+
+impl<T> std::fmt::Debug for Subscription<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Subscription")
+            .field("lagged", &self.lagged)
+            .finish()
+    }
+}
+
+impl<T> Subscription<T> {
+    // a new subscriber, which starts with the next message sent
+    pub(crate) fn new(state: &Arc<Mutex<State<T>>>) -> Self {
+        let mut guard = state.lock().unwrap();
+        guard.receivers += 1;
+        let next = guard.tail();
+        drop(guard);
+        Self {
+            state: Arc::clone(state),
+            next,
+            lagged: 0,
+        }
+    }
+
+    /// How many messages this subscriber missed in total, because it fell behind.
+    pub fn lagged(&self) -> u64 {
+        self.lagged
+    }
+}
+
+impl<T: Clone> Subscription<T> {
+    /// Try to get the next message without waiting.
+    ///
+    /// This returns `None` if there are no new messages.
+    pub fn try_next(&mut self) -> Option<Result<T, Lagged>> {
+        let state = self.state.lock().unwrap();
+        if self.next < state.head {
+            let missed = state.head - self.next;
+            self.next = state.head;
+            self.lagged += missed;
+            return Some(Err(Lagged { missed }));
+        }
+
+        let item = state.buffer.get((self.next - state.head) as usize)?.clone();
+        self.next += 1;
+        Some(Ok(item))
+    }
+}
+
+impl<T> Clone for Subscription<T> {
+    fn clone(&self) -> Self {
+        self.state.lock().unwrap().receivers += 1;
+        Self {
+            state: Arc::clone(&self.state),
+            next: self.next,
+            lagged: 0,
+        }
+    }
+}
+
+impl<T> Drop for Subscription<T> {
+    fn drop(&mut self) {
+        let mut state = self.state.lock().unwrap();
+        state.receivers -= 1;
+        // nothing can read these any more
+        if !state.has_receivers() {
+            state.head = state.tail();
+            state.buffer.clear();
+        }
+    }
+}
+
+impl<T: Clone> Stream for Subscription<T> {
+    type Item = Result<T, Lagged>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        if let Some(item) = this.try_next() {
+            return Poll::Ready(Some(item));
+        }
+
+        let mut state = this.state.lock().unwrap();
+        if state.finished {
+            return Poll::Ready(None);
+        }
+        if !state.wakers.iter().any(|w| w.will_wake(cx.waker())) {
+            state.wakers.push(cx.waker().clone());
+        }
+        Poll::Pending
+    }
+}
+
+pub(crate) fn channel<T>(capacity: usize) -> (BroadcastSender<T>, Arc<Mutex<State<T>>>) {
+    let state = Arc::new(Mutex::new(State {
+        buffer: VecDeque::with_capacity(capacity.max(1)),
+        head: 0,
+        capacity: capacity.max(1),
+        receivers: 0,
+        finished: false,
+        wakers: Vec::new(),
+    }));
+    (
+        BroadcastSender {
+            state: Arc::clone(&state),
+        },
+        state,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_lite::{future::block_on, StreamExt as _};
+
+    #[test]
+    fn every_subscriber_sees_every_message() {
+        let (tx, state) = channel(4);
+        let mut gui = Subscription::new(&state);
+        let mut bot = gui.clone();
+
+        assert!(tx.send(&1));
+        let mut logger = bot.clone();
+        assert!(tx.send(&2));
+
+        assert_eq!(gui.try_next(), Some(Ok(1)));
+        assert_eq!(gui.try_next(), Some(Ok(2)));
+        assert_eq!(gui.try_next(), None);
+
+        assert_eq!(bot.try_next(), Some(Ok(1)));
+        // cloned before reading, so it starts at the same position
+        assert_eq!(logger.try_next(), Some(Ok(1)));
+        assert_eq!(logger.try_next(), Some(Ok(2)));
+
+        // a new subscriber starts with the next message
+        let mut late = Subscription::new(&state);
+        assert!(tx.send(&3));
+        assert_eq!(late.try_next(), Some(Ok(3)));
+
+        drop(tx);
+        assert_eq!(block_on(bot.next()), Some(Ok(2)));
+        assert_eq!(block_on(bot.next()), Some(Ok(3)));
+        assert_eq!(block_on(bot.next()), None);
+    }
+
+    #[test]
+    fn slow_subscribers_lag() {
+        let (tx, state) = channel(2);
+        let mut fast = Subscription::new(&state);
+        let mut slow = Subscription::new(&state);
+
+        for i in 0..5 {
+            assert!(tx.send(&i));
+            assert_eq!(fast.try_next(), Some(Ok(i)));
+        }
+
+        assert_eq!(slow.try_next(), Some(Err(Lagged { missed: 3 })));
+        assert_eq!(slow.try_next(), Some(Ok(3)));
+        assert_eq!(slow.try_next(), Some(Ok(4)));
+        assert_eq!(slow.try_next(), None);
+        assert_eq!(slow.lagged(), 3);
+        assert_eq!(fast.lagged(), 0);
+
+        drop(fast);
+        drop(slow);
+        assert!(!tx.send(&5));
+    }
+}
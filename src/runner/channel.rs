@@ -1,8 +1,10 @@
 cfg_async! {
 use super::rate_limit::{PreviousRate, RateLimitedEncoder};
+use crate::messages::RoomSettings;
 use crate::rate_limit::{RateClass, RateLimit};
+use crate::twitch::Role;
 use std::{
-    collections::{HashMap, VecDeque},
+    collections::{BTreeSet, HashMap, VecDeque},
     time::Duration,
 };
 
@@ -20,6 +22,11 @@ pub struct Channel {
     pub(crate) rate_limited: RateLimitedEncoder,
     pub(crate) previous: Option<PreviousRate>,
     pub(crate) rated_limited_at: Option<std::time::Instant>,
+    pub(crate) is_moderator: bool,
+    pub(crate) role: Role,
+    pub(crate) room_settings: RoomSettings,
+    pub(crate) chatters: BTreeSet<String>,
+    pub(crate) hosting: Option<String>,
 }
 
 impl std::fmt::Debug for Channel {
@@ -34,15 +41,61 @@ impl Channel {
         let rate_limited = RateLimitedEncoder {
             rate_limit,
             queue: VecDeque::new(),
+            slow: None,
+            last_sent: None,
         };
         Self {
             name,
             rate_limited,
             previous: None,
             rated_limited_at: None,
+            is_moderator: false,
+            role: Role::Everyone,
+            room_settings: RoomSettings::default(),
+            chatters: BTreeSet::new(),
+            hosting: None,
         }
     }
 
+    /// Whether Twitch told us (via `USERSTATE`) that we're a moderator, vip or the broadcaster on this channel
+    pub fn is_moderator(&self) -> bool {
+        self.is_moderator
+    }
+
+    /// Our role on this channel, as of the last `USERSTATE`
+    pub fn role(&self) -> Role {
+        self.role
+    }
+
+    /// The chat settings for this channel, as of the last `ROOMSTATE`
+    pub fn room_settings(&self) -> &RoomSettings {
+        &self.room_settings
+    }
+
+    /// The users on this channel, from `JOIN`, `PART` and `NAMES` messages.
+    ///
+    /// This requires the `membership` capability. Twitch only sends these for channels with less than 1000 chatters
+    pub fn chatters(&self) -> &BTreeSet<String> {
+        &self.chatters
+    }
+
+    /// The channel this channel is hosting, as of the last `HOSTTARGET`
+    pub fn hosting(&self) -> Option<&str> {
+        self.hosting.as_deref()
+    }
+
+    /// How long until slow mode lets us send the next message on this channel.
+    ///
+    /// Moderators (and vips and the broadcaster) aren't affected by slow mode, so this is always `None` for them.
+    pub fn slow_mode_delay(&self) -> Option<Duration> {
+        self.rate_limited.slow_mode_delay()
+    }
+
+    // slow mode doesn't apply to moderators
+    pub(crate) fn update_slow_mode(&mut self) {
+        self.rate_limited.slow = self.room_settings.slow.filter(|_| !self.is_moderator);
+    }
+
     /// Set the [RateClass] for this channel
     pub fn set_rate_class(&mut self, rate_class: RateClass) {
         self.rate_limited.rate_limit = RateLimit::from_class(rate_class);
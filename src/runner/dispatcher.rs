@@ -0,0 +1,372 @@
+use super::{
+    broadcast::{self, Subscription},
+    overflow::{self, BoundedReceiver, Overflow},
+};
+use crate::{
+    channel::{Receiver, Sender},
+    messages::{Commands, FromCommands},
+    FromIrcMessage,
+};
+
+use futures_lite::Stream;
+use std::{
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+};
+
+type Subscriber<M> = Box<dyn Fn(&M) -> bool + Send + Sync>;
+
+// a bounded subscriber which makes the runner wait for room
+type Waiter = Box<dyn Fn(&mut Context<'_>) -> Poll<bool> + Send + Sync>;
+
+/// Fans out messages to typed subscribers.
+///
+/// Each subscriber has its own buffer, so a slow subscriber doesn't affect the others
+/// (unless it's bounded with [Overflow::Block]).
+pub(crate) struct Dispatcher<M = Commands<'static>> {
+    subscribers: Vec<Subscriber<M>>,
+    waiters: Vec<Waiter>,
+    // shared by every broadcast subscription, while there are any
+    broadcast: Option<Arc<Mutex<broadcast::State<M>>>>,
+}
+
+impl<M> Default for Dispatcher<M> {
+    fn default() -> Self {
+        Self {
+            subscribers: Vec::new(),
+            waiters: Vec::new(),
+            broadcast: None,
+        }
+    }
+}
+
+impl<M> std::fmt::Debug for Dispatcher<M> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Dispatcher")
+            .field("subscribers", &self.subscribers.len())
+            .finish()
+    }
+}
+
+impl Dispatcher {
+    pub(crate) fn subscribe<T>(&mut self) -> EventStream<T>
+    where
+        T: FromCommands<'static> + Send + Sync + 'static,
+    {
+        self.subscribe_filter(|_| true)
+    }
+
+    pub(crate) fn subscribe_filter<T, F>(&mut self, filter: F) -> EventStream<T>
+    where
+        T: FromCommands<'static> + Send + Sync + 'static,
+        F: Fn(&Commands<'static>) -> bool + Send + Sync + 'static,
+    {
+        self.subscribe_map(move |msg| T::from_commands(msg).filter(|_| filter(msg)))
+    }
+
+    pub(crate) fn subscribe_bounded<T>(
+        &mut self,
+        capacity: usize,
+        overflow: Overflow,
+    ) -> EventStream<T>
+    where
+        T: FromCommands<'static> + Send + Sync + 'static,
+    {
+        let (tx, rx) = overflow::bounded(capacity, overflow);
+        let tx = Arc::new(tx);
+        if tx.is_blocking() {
+            let tx = Arc::downgrade(&tx);
+            // this is removed once the subscriber is
+            self.waiters.push(Box::new(move |cx| match tx.upgrade() {
+                Some(tx) => tx.poll_ready(cx).map(|_| true),
+                None => Poll::Ready(false),
+            }));
+        }
+
+        self.subscribers
+            .push(Box::new(move |msg| match T::from_commands(msg) {
+                Some(item) => tx.send(item, msg),
+                None => true,
+            }));
+        EventStream {
+            inner: Inner::Bounded { rx },
+        }
+    }
+
+    pub(crate) fn subscribe_raw<T>(&mut self, command: &str) -> EventStream<T>
+    where
+        T: FromIrcMessage<'static> + Send + Sync + 'static,
+    {
+        let command = command.to_string();
+        self.subscribe_map(move |msg| match msg {
+            Commands::Raw(msg) if msg.get_command().eq_ignore_ascii_case(&command) => {
+                T::from_irc(msg.clone()).ok()
+            }
+            _ => None,
+        })
+    }
+}
+
+impl<M> Dispatcher<M> {
+    pub(crate) fn subscribe_map<T, F>(&mut self, map: F) -> EventStream<T>
+    where
+        T: Send + Sync + 'static,
+        F: Fn(&M) -> Option<T> + Send + Sync + 'static,
+    {
+        use crate::channel::TrySendError;
+
+        let (tx, rx): (Sender<T>, _) = crate::channel::unbounded();
+        self.subscribers.push(Box::new(move |msg| {
+            match map(msg) {
+                // if the receiver was dropped, we'll remove this subscriber
+                Some(msg) => !matches!(tx.try_send(msg), Err(TrySendError::Closed(..))),
+                None => true,
+            }
+        }));
+        EventStream {
+            inner: Inner::Channel { rx },
+        }
+    }
+
+    pub(crate) fn subscribe_broadcast(&mut self, capacity: usize) -> Subscription<M>
+    where
+        M: Clone + Send + 'static,
+    {
+        if let Some(state) = &self.broadcast {
+            if state.lock().unwrap().has_receivers() {
+                return Subscription::new(state);
+            }
+        }
+
+        let (tx, state) = broadcast::channel(capacity);
+        // this is removed once every subscription is dropped
+        self.subscribers.push(Box::new(move |msg| tx.send(msg)));
+        let subscription = Subscription::new(&state);
+        self.broadcast.replace(state);
+        subscription
+    }
+
+    pub(crate) fn dispatch(&mut self, msg: &M) {
+        self.subscribers.retain(|sub| sub(msg));
+    }
+
+    // waits until every subscriber bounded with `Overflow::Block` has room for another message
+    pub(crate) async fn ready(&mut self) {
+        if self.waiters.is_empty() {
+            return;
+        }
+
+        let waiters = &mut self.waiters;
+        futures_lite::future::poll_fn(|cx| {
+            let mut pending = false;
+            waiters.retain(|waiter| match waiter(cx) {
+                Poll::Ready(alive) => alive,
+                Poll::Pending => {
+                    pending = true;
+                    true
+                }
+            });
+            if pending {
+                Poll::Pending
+            } else {
+                Poll::Ready(())
+            }
+        })
+        .await
+    }
+}
+
+pin_project_lite::pin_project! {
+    /// A stream of a specific message type, produced by the [AsyncRunner](super::AsyncRunner) (or the `EventSub` client).
+    ///
+    /// The runner (or client) must still be driven (e.g. by calling [next_message](super::AsyncRunner::next_message()) in a loop) for messages to arrive.
+    ///
+    /// This stream ends when the runner is dropped.
+    pub struct EventStream<T> {
+        #[pin]
+        inner: Inner<T>,
+    }
+}
+
+pin_project_lite::pin_project! {
+    #[project = InnerProj]
+    enum Inner<T> {
+        Channel { #[pin] rx: Receiver<T> },
+        Bounded { rx: BoundedReceiver<T> },
+    }
+}
+
+impl<T> std::fmt::Debug for EventStream<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EventStream").finish()
+    }
+}
+
+impl<T> EventStream<T> {
+    /// Try to get the next message without waiting.
+    pub fn try_next(&self) -> Option<T> {
+        match &self.inner {
+            Inner::Channel { rx } => rx.try_recv(),
+            Inner::Bounded { rx } => rx.try_recv(),
+        }
+    }
+
+    /// How many messages were dropped because the buffer was full.
+    ///
+    /// This is always `0` unless the stream was created with [AsyncRunner::bounded_events()](super::AsyncRunner::bounded_events()).
+    pub fn dropped(&self) -> u64 {
+        match &self.inner {
+            Inner::Channel { .. } => 0,
+            Inner::Bounded { rx } => rx.dropped(),
+        }
+    }
+}
+
+impl<T> Stream for EventStream<T> {
+    type Item = T;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        match self.project().inner.project() {
+            InnerProj::Channel { rx } => rx.poll_next(cx),
+            InnerProj::Bounded { rx } => rx.poll_recv(cx),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{messages::*, IntoOwned as _};
+
+    fn commands(input: &str) -> Commands<'static> {
+        let msg = crate::irc::parse(input).next().unwrap().unwrap();
+        Commands::from_irc(msg).unwrap().into_owned()
+    }
+
+    #[test]
+    fn typed_subscribers() {
+        let mut dispatcher = Dispatcher::default();
+
+        let privmsgs = dispatcher.subscribe::<Privmsg>();
+        let joins = dispatcher.subscribe::<Join>();
+        let all = dispatcher.subscribe::<Commands>();
+
+        dispatcher.dispatch(&commands(":test!test@test PRIVMSG #museun :hello\r\n"));
+        dispatcher.dispatch(&commands(":test!test@test JOIN #museun\r\n"));
+        dispatcher.dispatch(&commands(":test!test@test PRIVMSG #museun :world\r\n"));
+
+        assert_eq!(privmsgs.try_next().unwrap().data(), "hello");
+        assert_eq!(privmsgs.try_next().unwrap().data(), "world");
+        assert!(privmsgs.try_next().is_none());
+
+        assert_eq!(joins.try_next().unwrap().channel(), "#museun");
+        assert!(joins.try_next().is_none());
+
+        assert_eq!(std::iter::from_fn(|| all.try_next()).count(), 3);
+    }
+
+    #[test]
+    fn dropped_subscribers() {
+        let mut dispatcher = Dispatcher::default();
+
+        let privmsgs = dispatcher.subscribe::<Privmsg>();
+        let joins = dispatcher.subscribe::<Join>();
+        drop(joins);
+
+        dispatcher.dispatch(&commands(":test!test@test JOIN #museun\r\n"));
+        assert_eq!(dispatcher.subscribers.len(), 1);
+
+        dispatcher.dispatch(&commands(":test!test@test PRIVMSG #museun :hello\r\n"));
+        assert_eq!(privmsgs.try_next().unwrap().data(), "hello");
+    }
+
+    #[test]
+    fn filtered_subscribers() {
+        let mut dispatcher = Dispatcher::default();
+
+        let museun =
+            dispatcher.subscribe_filter::<Commands, _>(|msg| msg.channel() == Some("#museun"));
+
+        dispatcher.dispatch(&commands(":test!test@test PRIVMSG #museun :hello\r\n"));
+        dispatcher.dispatch(&commands(":test!test@test PRIVMSG #shaken_bot :world\r\n"));
+        dispatcher.dispatch(&commands(":test!test@test JOIN #museun\r\n"));
+
+        assert!(matches!(museun.try_next().unwrap(), Commands::Privmsg(..)));
+        assert!(matches!(museun.try_next().unwrap(), Commands::Join(..)));
+        assert!(museun.try_next().is_none());
+    }
+
+    #[test]
+    fn bounded_subscribers() {
+        use futures_lite::future::{block_on, poll_once};
+
+        let mut dispatcher = Dispatcher::default();
+        let dropping = dispatcher.subscribe_bounded::<Privmsg>(1, Overflow::DropNewest);
+        let blocking = dispatcher.subscribe_bounded::<Privmsg>(1, Overflow::Block);
+
+        dispatcher.dispatch(&commands(":test!test@test PRIVMSG #museun :hello\r\n"));
+        dispatcher.dispatch(&commands(":test!test@test PRIVMSG #museun :world\r\n"));
+        assert_eq!(dropping.try_next().unwrap().data(), "hello");
+        assert_eq!(dropping.dropped(), 1);
+
+        // the blocking subscriber is full, until it's read
+        assert!(block_on(poll_once(dispatcher.ready())).is_none());
+        assert_eq!(blocking.try_next().unwrap().data(), "hello");
+        assert_eq!(blocking.try_next().unwrap().data(), "world");
+        assert!(block_on(poll_once(dispatcher.ready())).is_some());
+
+        // and it stops blocking once it's dropped
+        dispatcher.dispatch(&commands(":test!test@test PRIVMSG #museun :again\r\n"));
+        drop(blocking);
+        dispatcher.dispatch(&commands(":test!test@test PRIVMSG #museun :again\r\n"));
+        assert!(block_on(poll_once(dispatcher.ready())).is_some());
+        assert!(dispatcher.waiters.is_empty());
+    }
+
+    #[test]
+    fn broadcast_subscribers() {
+        let mut dispatcher = Dispatcher::default();
+
+        let mut first = dispatcher.subscribe_broadcast(8);
+        dispatcher.dispatch(&commands(":test!test@test PRIVMSG #museun :hello\r\n"));
+        let mut second = dispatcher.subscribe_broadcast(8);
+        dispatcher.dispatch(&commands(":test!test@test JOIN #museun\r\n"));
+
+        assert!(matches!(first.try_next(), Some(Ok(Commands::Privmsg(..)))));
+        assert!(matches!(first.try_next(), Some(Ok(Commands::Join(..)))));
+        assert!(matches!(second.try_next(), Some(Ok(Commands::Join(..)))));
+        assert!(second.try_next().is_none());
+
+        // removed once every subscription is dropped
+        drop((first, second));
+        dispatcher.dispatch(&commands(":test!test@test JOIN #museun\r\n"));
+        assert!(dispatcher.subscribers.is_empty());
+
+        let mut again = dispatcher.subscribe_broadcast(8);
+        dispatcher.dispatch(&commands(":test!test@test PART #museun\r\n"));
+        assert!(matches!(again.try_next(), Some(Ok(Commands::Part(..)))));
+    }
+
+    #[test]
+    fn raw_subscribers() {
+        let mut dispatcher = Dispatcher::default();
+
+        let raw = dispatcher.subscribe_raw::<IrcMessage>("newcommand");
+        let privmsgs = dispatcher.subscribe_raw::<IrcMessage>("PRIVMSG");
+
+        dispatcher.dispatch(&commands(
+            "@id=1 :tmi.twitch.tv NEWCOMMAND #museun :hello\r\n",
+        ));
+        dispatcher.dispatch(&commands(":tmi.twitch.tv OTHERCOMMAND #museun :world\r\n"));
+        dispatcher.dispatch(&commands(":test!test@test PRIVMSG #museun :hello\r\n"));
+
+        let msg = raw.try_next().unwrap();
+        assert_eq!(msg.get_tag("id"), Some("1"));
+        assert_eq!(msg.nth_arg(0), Some("#museun"));
+        assert!(raw.try_next().is_none());
+
+        // known commands are never raw
+        assert!(privmsgs.try_next().is_none());
+    }
+}
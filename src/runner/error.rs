@@ -1,14 +1,23 @@
-use crate::{DecodeError, MessageError};
+use crate::{decoder::InvalidMessage, DecodeError, MessageError};
 
-/// An error returned by a Runner
+/// An error returned by a Runner.
+///
+/// This is also the crate-level [Error](crate::Error). The [source()](std::error::Error::source()) of an error wrapping another one is that error.
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum Error {
     /// An I/O error occured
     Io(std::io::Error),
+    /// The connection to Twitch (or to the proxy) couldn't be established
+    Connect(std::io::Error),
+    /// TLS couldn't be set up, e.g. the handshake failed or a root certificate was invalid (see [TlsError](crate::connector::TlsError))
+    Tls(std::io::Error),
     /// Invalid utf-8 was parsed (either you sent invalid utf-8, or Twitch did and we read it).
     InvalidUtf8(std::str::Utf8Error),
     /// We could not parse a message -- this should never happen
     ParsingFailure(MessageError),
+    /// Twitch sent a line which couldn't be decoded. This has the raw line, and where the problem was found.
+    InvalidMessage(InvalidMessage),
     /// You requested a capability and Twitch rejected it
     InvalidCap {
         /// The capability name
@@ -29,8 +38,49 @@ pub enum Error {
         /// The channel name
         channel: String,
     },
+    /// You could not join this channel, it is currently unavailable (e.g. it was suspended).
+    ChannelUnavailable {
+        /// The channel name
+        channel: String,
+    },
+    /// Twitch rejected your login (e.g. your OAuth token expired or was revoked).
+    AuthenticationFailed,
+    /// Twitch couldn't make sense of your OAuth token (e.g. it isn't a token at all).
+    InvalidToken,
+    /// You tried to write on a read-only (anonymous) connection.
+    ReadOnly,
+    /// You tried to write after the connection was shut down.
+    ShutDown,
+    /// You tried to write a message that Twitch would drop as a duplicate (with `DuplicatePolicy::Reject`).
+    Duplicate,
+    /// A message wasn't sent because the rate limit was hit (see [RateLimited](crate::writer::RateLimited)).
+    RateLimited {
+        /// How long to wait before the message can be sent
+        retry_after: std::time::Duration,
+    },
+    /// You tried to write a message that Twitch would reject because of the channel's chat mode (with `RestrictionPolicy::Reject`, see [WouldBeRejected](crate::writer::WouldBeRejected)).
+    #[cfg(feature = "async")]
+    WouldBeRejected {
+        /// The channel the message was for
+        channel: String,
+        /// Why Twitch would reject it
+        reason: crate::writer::RejectReason,
+    },
+    /// You tried to write after the runner was dropped (see [Closed](crate::writer::Closed)).
+    ChannelClosed,
     /// Your connection timed out.
     TimedOut,
+    /// The TCP connection to Twitch (or to the proxy) wasn't established in time.
+    ConnectTimedOut,
+    /// The TLS handshake with Twitch didn't finish in time.
+    TlsTimedOut,
+    /// Twitch didn't finish the login in time (see [UserConfig::login_timeout](crate::UserConfig::login_timeout)).
+    LoginTimedOut,
+    /// Twitch didn't confirm joining (or parting) this channel in time (see [MembershipConfirmation](crate::writer::MembershipConfirmation)).
+    MembershipTimedOut {
+        /// The channel name
+        channel: String,
+    },
     /// Twitch restarted the server, you should reconnect.
     ShouldReconnect,
     /// An unexpected EOF was found -- this means the connectionc losed abnormally.
@@ -41,15 +91,48 @@ impl std::fmt::Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::Io(err) => write!(f, "io error: {}", err),
+            Self::Connect(err) => write!(f, "could not connect: {}", err),
+            Self::Tls(err) => write!(f, "could not set up tls: {}", err),
             Self::InvalidUtf8(err) => write!(f, "invalid utf-8 while parsing: {}", err),
             Self::ParsingFailure(err) => write!(f, "could not parse message: {}", err),
+            Self::InvalidMessage(err) => write!(f, "could not decode message: {}", err),
             Self::InvalidCap { cap } => {
                 write!(f, "request capability '{}' was not acknowledged", cap)
             }
             Self::AlreadyOnChannel { channel } => write!(f, "already on channel '{}'", channel),
             Self::NotOnChannel { channel } => write!(f, "not on channel '{}'", channel),
             Self::BannedFromChannel { channel } => write!(f, "banned from channel '{}'", channel),
+            Self::ChannelUnavailable { channel } => {
+                write!(f, "channel '{}' is currently unavailable", channel)
+            }
+            Self::AuthenticationFailed => write!(f, "login authentication failed"),
+            Self::InvalidToken => write!(f, "Twitch could not make sense of the oauth token"),
+            Self::ReadOnly => write!(f, "cannot write on a read-only (anonymous) connection"),
+            Self::ShutDown => write!(f, "cannot write after the connection was shut down"),
+            Self::Duplicate => write!(f, "Twitch would drop this message as a duplicate"),
+            Self::RateLimited { retry_after } => {
+                write!(f, "rate limited, retry after {:.2?}", retry_after)
+            }
+            #[cfg(feature = "async")]
+            Self::WouldBeRejected { channel, reason } => {
+                write!(
+                    f,
+                    "Twitch would reject this message for '{}': {}",
+                    channel, reason
+                )
+            }
+            Self::ChannelClosed => write!(f, "cannot write after the runner was dropped"),
             Self::TimedOut => write!(f, "your connection timed out"),
+            Self::ConnectTimedOut => write!(f, "timed out connecting to Twitch"),
+            Self::TlsTimedOut => write!(f, "timed out during the TLS handshake with Twitch"),
+            Self::LoginTimedOut => write!(f, "timed out waiting for Twitch to finish the login"),
+            Self::MembershipTimedOut { channel } => {
+                write!(
+                    f,
+                    "timed out waiting for Twitch to confirm joining (or parting) '{}'",
+                    channel
+                )
+            }
             Self::ShouldReconnect => write!(f, "you should reconnect. Twitch restarted the server"),
             Self::UnexpectedEof => write!(f, "reached an unexpected EOF"),
         }
@@ -59,9 +142,10 @@ impl std::fmt::Display for Error {
 impl std::error::Error for Error {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match self {
-            Self::Io(err) => Some(err),
+            Self::Io(err) | Self::Connect(err) | Self::Tls(err) => Some(err),
             Self::InvalidUtf8(err) => Some(err),
             Self::ParsingFailure(err) => Some(err),
+            Self::InvalidMessage(err) => Some(err),
             _ => None,
         }
     }
@@ -74,16 +158,64 @@ impl From<DecodeError> for Error {
             DecodeError::InvalidUtf8(err) => Self::InvalidUtf8(err),
             DecodeError::ParseError(err) => Self::ParsingFailure(err),
             DecodeError::Eof => Self::UnexpectedEof,
+            DecodeError::Invalid(err) => Self::InvalidMessage(err),
         }
     }
 }
 
 impl From<std::io::Error> for Error {
     fn from(err: std::io::Error) -> Self {
+        #[cfg(feature = "async")]
+        {
+            if crate::writer::ReadOnly::is_read_only(&err) {
+                return Self::ReadOnly;
+            }
+            if crate::writer::ShutDown::is_shut_down(&err) {
+                return Self::ShutDown;
+            }
+            if crate::writer::Duplicate::is_duplicate(&err) {
+                return Self::Duplicate;
+            }
+            if let Some(crate::writer::WouldBeRejected { channel, reason }) =
+                crate::writer::WouldBeRejected::of(&err)
+            {
+                return Self::WouldBeRejected {
+                    channel: channel.clone(),
+                    reason: *reason,
+                };
+            }
+            if crate::writer::Closed::is_closed(&err) {
+                return Self::ChannelClosed;
+            }
+            if let Some(crate::writer::RateLimited { retry_after }) =
+                crate::writer::RateLimited::of(&err)
+            {
+                return Self::RateLimited { retry_after };
+            }
+            match crate::connector::ConnectTimeout::of(&err) {
+                Some(crate::connector::ConnectTimeout::Connect) => return Self::ConnectTimedOut,
+                Some(crate::connector::ConnectTimeout::TlsHandshake) => return Self::TlsTimedOut,
+                _ => {}
+            }
+            if crate::connector::TlsError::is_tls_error(&err) {
+                return Self::Tls(err);
+            }
+        }
         Self::Io(err)
     }
 }
 
+impl Error {
+    // an error from the connector, which is a `Connect` error unless it's more specific
+    #[cfg(feature = "async")]
+    pub(crate) fn connecting(err: std::io::Error) -> Self {
+        match Self::from(err) {
+            Self::Io(err) => Self::Connect(err),
+            err => err,
+        }
+    }
+}
+
 impl From<MessageError> for Error {
     fn from(err: MessageError) -> Self {
         Self::ParsingFailure(err)
@@ -0,0 +1,163 @@
+use super::{Capabilities, Error, Identity};
+use crate::{
+    messages::{Capability, Commands},
+    twitch::{Capability as TwitchCap, UserConfig},
+};
+
+use std::collections::HashSet;
+
+/// Tracks the messages Twitch sends after registering, until we know our [Identity].
+///
+/// This doesn't do any I/O, so both the async and the blocking clients can drive it.
+pub(crate) struct Handshake {
+    is_anonymous: bool,
+    looking_for: HashSet<TwitchCap>,
+    caps: Capabilities,
+    our_name: Option<String>,
+    will_be_getting_global_user_state_hopefully: bool,
+}
+
+impl Handshake {
+    pub(crate) fn new(user_config: &UserConfig) -> Self {
+        // Twitch says we'll be getting a GlobalUserState if we just send the
+        // Tags capability
+        //
+        // This is false. Twitch will only send GlobalUserState if we've sent
+        // the Commands capability and atleast 1 other capability.
+        //
+        // That other capability doesn't have to be Tags, interestingly enough.
+        // So a combination of both 'Commands' and 'Membership' will produce an
+        // empty GlobalUserState
+        //
+        // We'll check for both Tags and Commands
+        //
+        let will_be_getting_global_user_state_hopefully =
+            user_config.capabilities.contains(&TwitchCap::Tags)
+                && user_config.capabilities.contains(&TwitchCap::Commands);
+
+        Self {
+            is_anonymous: user_config.is_anonymous(),
+            looking_for: user_config.capabilities.iter().copied().collect(),
+            caps: Capabilities::default(),
+            our_name: None,
+            will_be_getting_global_user_state_hopefully,
+        }
+    }
+
+    /// Look at the next message. This returns the [Identity] once the connection is ready.
+    pub(crate) fn step(&mut self, commands: &Commands<'_>) -> Result<Option<Identity>, Error> {
+        use Commands::*;
+
+        match commands {
+            Ready(msg) => {
+                self.our_name.replace(msg.username().to_string());
+
+                // if we aren't going to be receiving tags, then we
+                // won't be looking for any more messages
+
+                // if we're anonymous, we won't get GLOBALUSERSTATE even
+                // if we do send Tags
+                if self.is_anonymous {
+                    return Ok(Some(Identity::Anonymous {
+                        name: self.our_name.take().unwrap(),
+                        caps: self.caps.clone(),
+                    }));
+                }
+
+                // if we're not looking for any more caps and we won't be
+                // getting a GlobalUserState just give them the basic
+                // Identity
+                if self.looking_for.is_empty() && !self.will_be_getting_global_user_state_hopefully
+                {
+                    return Ok(Some(self.basic()));
+                }
+            }
+
+            Cap(msg) => {
+                for capability in msg.capabilities() {
+                    let name = match capability {
+                        Capability::Acknowledged(name) => name,
+                        Capability::NotAcknowledged(name) => {
+                            return Err(Error::InvalidCap {
+                                cap: name.to_string(),
+                            })
+                        }
+                    };
+
+                    let cap = match TwitchCap::maybe_from_str(name) {
+                        Some(cap) => cap,
+                        // Twitch sent us an unknown capability
+                        None => {
+                            self.caps.unknown.insert(name.to_string());
+                            continue;
+                        }
+                    };
+
+                    *match cap {
+                        TwitchCap::Tags => &mut self.caps.tags,
+                        TwitchCap::Membership => &mut self.caps.membership,
+                        TwitchCap::Commands => &mut self.caps.commands,
+                    } = true;
+
+                    self.looking_for.remove(&cap);
+                }
+            }
+
+            // NOTE: This will only be sent when there's both Commands and atleast one other CAP requested
+            GlobalUserState(msg) => {
+                // this is sent after all of the ACKs, so anything missing was silently dropped
+                if let Some(cap) = self.looking_for.iter().next() {
+                    return Err(Error::InvalidCap {
+                        cap: cap.as_str().to_string(),
+                    });
+                }
+
+                // TODO: this is so shitty.
+                let id = match &msg.user_id {
+                    Some(id) => id.parse().unwrap(),
+                    // XXX: we can get this message without any tags
+                    None => return Ok(Some(self.basic())),
+                };
+
+                return Ok(Some(Identity::Full {
+                    // these unwraps should be safe because we'll have all of the TAGs here
+                    name: self.our_name.take().unwrap(),
+                    user_id: id,
+                    display_name: msg.display_name.as_ref().map(|s| s.to_string()),
+                    color: msg.color,
+                    caps: self.caps.clone(),
+                }));
+            }
+
+            // Twitch sends this (and then closes the connection) if the PASS was rejected
+            Notice(msg) if msg.message() == "Login authentication failed" => {
+                return Err(Error::AuthenticationFailed)
+            }
+            Notice(msg) if msg.message() == "Improperly formatted auth" => {
+                return Err(Error::InvalidToken)
+            }
+
+            // the caller replies to these
+            Ping(..) => {}
+
+            _ => {
+                // we have our name, but we won't be getting GlobalUserState and we've got all of our Caps
+                if self.our_name.is_some()
+                    && !self.will_be_getting_global_user_state_hopefully
+                    && self.looking_for.is_empty()
+                {
+                    return Ok(Some(self.basic()));
+                }
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn basic(&mut self) -> Identity {
+        Identity::Basic {
+            name: self.our_name.take().unwrap(),
+            caps: self.caps.clone(),
+        }
+    }
+}
@@ -7,6 +7,8 @@ use crate::{runner::Capabilities, twitch::Color};
 pub enum Identity {
     /// An anonymous identity.
     Anonymous {
+        /// Your username, e.g. `justinfan1234`
+        name: String,
         /// The capabilities you'll have
         caps: Capabilities,
     },
@@ -41,12 +43,12 @@ pub enum Identity {
 impl Identity {
     /// Get your username from this identity
     ///
-    /// If its anonymous, it'll be `justinfan1234`
+    /// If its anonymous, it'll be something like `justinfan1234`
     pub fn username(&self) -> &str {
-        let (nick, _) = crate::ANONYMOUS_LOGIN;
         match self {
-            Self::Anonymous { .. } => nick,
-            Self::Basic { name, .. } | Self::Full { name, .. } => &*name,
+            Self::Anonymous { name, .. } | Self::Basic { name, .. } | Self::Full { name, .. } => {
+                name
+            }
         }
     }
 }
@@ -0,0 +1,143 @@
+use std::time::Duration;
+
+/// Callbacks that the [AsyncRunner](super::AsyncRunner) (and the [Reconnect](super::Reconnect)) report into.
+///
+/// Every method does nothing by default, so only implement the ones you're interested in.
+/// These are called from the runner's loop, so they should be cheap -- e.g. bumping an atomic counter.
+///
+/// ```
+/// # use twitchchat::runner::Metrics;
+/// # use std::sync::atomic::{AtomicUsize, Ordering};
+/// #[derive(Default)]
+/// struct Counters {
+///     messages: AtomicUsize,
+///     bytes_read: AtomicUsize,
+/// }
+///
+/// impl Metrics for Counters {
+///     fn message_parsed(&self) {
+///         self.messages.fetch_add(1, Ordering::Relaxed);
+///     }
+///
+///     fn bytes_read(&self, bytes: usize) {
+///         self.bytes_read.fetch_add(bytes, Ordering::Relaxed);
+///     }
+/// }
+/// ```
+pub trait Metrics: Send + Sync {
+    /// A message was read and parsed
+    fn message_parsed(&self) {}
+
+    /// A line was read, but it couldn't be parsed
+    fn parse_failed(&self) {}
+
+    /// This many bytes were read from the connection
+    fn bytes_read(&self, bytes: usize) {
+        let _ = bytes;
+    }
+
+    /// This many bytes were written to the connection
+    fn bytes_written(&self, bytes: usize) {
+        let _ = bytes;
+    }
+
+    /// How many messages are waiting to be sent, after one was written to the [writer](super::AsyncRunner::writer())
+    fn writer_queue_depth(&self, depth: usize) {
+        let _ = depth;
+    }
+
+    /// Sending was held back by a rate limit, for about this long
+    fn throttled(&self, delay: Duration) {
+        let _ = delay;
+    }
+
+    /// The connection was re-established by a [Reconnect](super::Reconnect)
+    fn reconnected(&self) {}
+
+    /// A connected runner started reporting into these metrics
+    fn connected(&self) {}
+
+    /// A runner reporting into these metrics was dropped, so its connection was closed
+    fn disconnected(&self) {}
+
+    /// A `PONG` arrived for the runner's `PING`, after this long
+    fn latency(&self, latency: Duration) {
+        let _ = latency;
+    }
+
+    /// A chat message (a `PRIVMSG`) was read on this channel
+    fn chat_message(&self, channel: &str) {
+        let _ = channel;
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct NoMetrics;
+
+impl Metrics for NoMetrics {}
+
+#[cfg(all(test, feature = "testing"))]
+mod tests {
+    use super::*;
+    use crate::{messages::Commands, runner::AsyncRunner, test::MockServer, Status, UserConfig};
+    use futures_lite::future::{block_on, zip};
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    #[derive(Default)]
+    struct Counters {
+        parsed: AtomicUsize,
+        read: AtomicUsize,
+        written: AtomicUsize,
+    }
+
+    impl Metrics for Counters {
+        fn message_parsed(&self) {
+            self.parsed.fetch_add(1, Ordering::SeqCst);
+        }
+
+        fn bytes_read(&self, bytes: usize) {
+            self.read.fetch_add(bytes, Ordering::SeqCst);
+        }
+
+        fn bytes_written(&self, bytes: usize) {
+            self.written.fetch_add(bytes, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn reports_reads_and_writes() {
+        let server = MockServer::new();
+        let user_config = UserConfig::builder().anonymous().build().unwrap();
+        let counters = Arc::new(Counters::default());
+
+        block_on(async {
+            let (runner, conn) = zip(
+                AsyncRunner::connect(server.clone(), &user_config),
+                server.accept(),
+            )
+            .await;
+            let (mut runner, mut conn) = (runner.unwrap(), conn.unwrap());
+            runner.set_metrics(counters.clone());
+
+            let (joined, line) = zip(runner.join("museun"), conn.read_line()).await;
+            joined.unwrap();
+            assert_eq!(line.unwrap(), "JOIN #museun\r\n");
+
+            while !matches!(
+                runner.next_message().await.unwrap(),
+                Status::Message(Commands::RoomState(..))
+            ) {}
+        });
+
+        // the JOIN echo and the ROOMSTATE
+        assert_eq!(counters.parsed.load(Ordering::SeqCst), 2);
+        assert!(counters.read.load(Ordering::SeqCst) > 0);
+        assert_eq!(
+            counters.written.load(Ordering::SeqCst),
+            "JOIN #museun\r\n".len()
+        );
+    }
+}
@@ -0,0 +1,103 @@
+use crate::messages::Commands;
+
+/// What to do with a message after a [Middleware] has seen it
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Flow {
+    /// Pass the message on, to the next middleware and then to the caller (and the subscribers)
+    Continue,
+    /// Drop the message. Nothing after this middleware sees it
+    Drop,
+}
+
+/// A hook which sees (and can change or drop) every message before [AsyncRunner::next_message()](super::AsyncRunner::next_message()) produces it.
+///
+/// This is for things every bot needs, like ignore lists, spam filters, logging or metrics.
+/// Install these with [AsyncRunner::add_middleware()](super::AsyncRunner::add_middleware()) (or [Reconnect::with_middleware()](super::Reconnect::with_middleware())).
+/// They run in the order they were added.
+///
+/// Closures can be used as middleware:
+/// ```no_run
+/// # use twitchchat::{messages::Commands, runner::Flow};
+/// # fn demo(runner: &mut twitchchat::AsyncRunner) {
+/// // ignore everything from other bots
+/// runner.add_middleware(|msg: &mut Commands<'static>| match msg {
+///     Commands::Privmsg(msg) if msg.name().ends_with("bot") => Flow::Drop,
+///     _ => Flow::Continue,
+/// });
+/// # }
+/// ```
+pub trait Middleware: Send {
+    /// Called for each message, in the order they were read
+    fn on_message(&mut self, msg: &mut Commands<'static>) -> Flow;
+}
+
+impl<F> Middleware for F
+where
+    F: FnMut(&mut Commands<'static>) -> Flow + Send,
+{
+    fn on_message(&mut self, msg: &mut Commands<'static>) -> Flow {
+        (self)(msg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FromIrcMessage as _, IntoOwned as _};
+
+    #[test]
+    fn closures() {
+        let mut seen = 0;
+        let mut middleware = |msg: &mut Commands<'static>| {
+            seen += 1;
+            match msg {
+                Commands::Ping(..) => Flow::Drop,
+                _ => Flow::Continue,
+            }
+        };
+
+        let msg = crate::irc::parse_one("PING :1234\r\n").unwrap().1;
+        let mut msg = Commands::from_irc(msg).unwrap().into_owned();
+        assert_eq!(
+            Middleware::on_message(&mut middleware, &mut msg),
+            Flow::Drop
+        );
+        assert_eq!(seen, 1);
+    }
+
+    #[test]
+    #[cfg(feature = "testing")]
+    fn middleware() {
+        use crate::test::{user_config, MockServer};
+        use crate::Status;
+        use futures_lite::future::block_on;
+
+        let server = MockServer::new();
+        block_on(async {
+            let (mut runner, conn) = server.connect_runner(&user_config()).await.unwrap();
+
+            let privmsgs = runner.events::<crate::messages::Privmsg>();
+            runner.add_middleware(|msg: &mut Commands<'static>| match msg {
+                Commands::Privmsg(msg) if msg.name() == "spammer" => Flow::Drop,
+                _ => Flow::Continue,
+            });
+
+            conn.send(":spammer!spammer@spammer.tmi.twitch.tv PRIVMSG #museun :buy followers")
+                .unwrap();
+            conn.send(":museun!museun@museun.tmi.twitch.tv PRIVMSG #museun :hello")
+                .unwrap();
+            let msg = loop {
+                if let Status::Message(Commands::Privmsg(msg)) =
+                    runner.next_message().await.unwrap()
+                {
+                    break msg;
+                }
+            };
+            assert_eq!(msg.name(), "museun");
+
+            // and the subscribers never see it either
+            assert_eq!(privmsgs.try_next().unwrap().name(), "museun");
+            assert!(privmsgs.try_next().is_none());
+        });
+    }
+}
@@ -9,6 +9,8 @@
 //!     1. write messages with the [AsyncWriter](crate::writer::AsyncWriter) provided by [AsyncRunner::writer()].
 //!     1. signal you want to quit with the [AsyncRunner::quit_handle()]
 //!
+//! If you want the connection to be re-established automatically, wrap it in a [Reconnect].
+//!
 
 mod status;
 pub use status::{Status, StepResult};
@@ -24,21 +26,100 @@ pub use error::Error;
 
 #[allow(dead_code)]
 mod timeout;
+pub use timeout::KeepAlive;
 
 cfg_async! {
     mod rate_limit;
 }
 
+cfg_async! {
+    mod middleware;
+    pub use middleware::{Flow, Middleware};
+}
+
+cfg_async! {
+    mod user_filter;
+    pub use user_filter::{FilterMode, UserFilter, UserFilterHandle};
+}
+
+cfg_async! {
+    mod metrics;
+    pub use metrics::Metrics;
+    pub(crate) use metrics::NoMetrics;
+}
+
+cfg_async! {
+    mod handshake;
+    pub(crate) use handshake::Handshake;
+}
+
+cfg_async! {
+    mod shutdown;
+    pub use shutdown::ShutdownHandle;
+}
+
+cfg_async! {
+    mod presence;
+    pub use presence::Presence;
+}
+
+cfg_async! {
+    mod names;
+    pub use names::Names;
+    pub(crate) use names::NamesBuffer;
+}
+
 cfg_async! {
     mod channel;
     pub use channel::Channel;
 }
 
+cfg_async! {
+    mod overflow;
+    pub use overflow::Overflow;
+}
+
+cfg_async! {
+    mod broadcast;
+    pub use broadcast::{Lagged, Subscription};
+}
+
+cfg_async! {
+    mod dispatcher;
+    pub(crate) use dispatcher::Dispatcher;
+    pub use dispatcher::EventStream;
+}
+
 cfg_async! {
     mod async_runner;
     pub use async_runner::AsyncRunner;
 }
 
+cfg_async! {
+    mod split;
+    pub use split::{Reader, Writer};
+}
+
+cfg_async! {
+    mod reconnect;
+    pub use reconnect::{Backoff, Reconciliation, Reconnect, ReconnectStatus};
+}
+
+cfg_async! {
+    mod shard;
+    pub use shard::{ShardManager, ShardStatus, ShardWriter};
+}
+
+cfg_async! {
+    mod multi;
+    pub use multi::{MultiClient, MultiStatus};
+}
+
+cfg_async! {
+    mod pool;
+    pub use pool::{PoolStrategy, PoolWriter, WriterPool};
+}
+
 cfg_async! {
     #[doc(inline)]
     pub use crate::util::NotifyHandle;
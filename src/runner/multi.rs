@@ -0,0 +1,478 @@
+use super::{
+    shard::{Polled, Shard, State},
+    Backoff, Dispatcher, Error, EventStream, Reconnect, ReconnectStatus,
+};
+use crate::{
+    connector::Connector,
+    messages::FromCommands,
+    twitch::{ChannelName, UserConfig},
+    util::NotifyHandle,
+    writer::{AsyncWriter, MpscWriter},
+};
+
+use futures_lite::{AsyncRead, AsyncWrite};
+use std::{
+    collections::{BTreeSet, VecDeque},
+    task::Poll,
+};
+
+struct Identity<C> {
+    name: String,
+    shard: Shard<C>,
+    writer: AsyncWriter<MpscWriter>,
+    quit_handle: NotifyHandle,
+    channels: BTreeSet<String>,
+}
+
+/// Status produced by [MultiClient::next_message()]
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum MultiStatus {
+    /// This identity produced a status
+    Identity {
+        /// The name the identity was added with
+        identity: String,
+        /// What it produced
+        status: ReconnectStatus,
+    },
+    /// This identity couldn't join a channel
+    JoinFailed {
+        /// The name the identity was added with
+        identity: String,
+        /// Why it couldn't join
+        error: Error,
+    },
+    /// This identity gave up reconnecting
+    Died {
+        /// The name the identity was added with
+        identity: String,
+        /// Why it gave up
+        error: Error,
+        /// The channels it was on
+        channels: Vec<String>,
+    },
+    /// Every identity has died or quit
+    Closed,
+}
+
+/// Runs several accounts (e.g. your bot and a moderator account) together, each on its own connection which transparently reconnects (see [Reconnect]).
+///
+/// Each identity is added with a name of your choosing, and has its own channels and writer.
+/// Twitch rate limits each account separately, so each writer has its own rate limit -- and it's raised for the channels where that account is a moderator.
+///
+/// * [join](MultiClient::join()) adds a channel to an identity.
+/// * [next_message](MultiClient::next_message()) drives every identity, and produces their messages as they arrive.
+/// * [writer](MultiClient::writer()) gets the writer for an identity.
+///
+/// If several identities join the same channel, each of them produces its messages.
+///
+/// # Example
+/// ```no_run
+/// # use twitchchat::{commands, connector::Connector, runner::{MultiClient, MultiStatus, ReconnectStatus}, messages::Commands, UserConfig};
+/// # async fn demo<C>(connector: C, bot: &UserConfig, moderator: &UserConfig) -> std::io::Result<()>
+/// # where
+/// #    C: Connector + 'static,
+/// #    for<'a> &'a C::Output: futures_lite::AsyncRead + futures_lite::AsyncWrite + Send + Sync + Unpin,
+/// # {
+/// let mut client = MultiClient::new(connector);
+/// client.add_identity("bot", bot);
+/// client.add_identity("moderator", moderator);
+///
+/// // the bot talks, while the moderator account only reads
+/// client.join("bot", "museun");
+/// client.join("moderator", "museun");
+///
+/// let mut bot = client.writer("bot").unwrap();
+/// let mut moderator = client.writer("moderator").unwrap();
+/// loop {
+///     match client.next_message().await {
+///         MultiStatus::Identity { identity, status: ReconnectStatus::Message(Commands::Privmsg(msg)) } if identity == "moderator" => {
+///             if msg.data().contains("bad word") {
+///                 moderator.encode(commands::timeout(msg.channel(), msg.name(), "10m", "no")).await?;
+///                 bot.encode(commands::privmsg(msg.channel(), "please don't")).await?;
+///             }
+///         }
+///         MultiStatus::Closed => break,
+///         _ => {}
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct MultiClient<C> {
+    connector: C,
+    backoff: Option<Backoff>,
+    identities: Vec<Identity<C>>,
+    statuses: VecDeque<MultiStatus>,
+    dispatcher: Dispatcher,
+    // the identity to poll first, so a busy identity doesn't starve the others
+    next: usize,
+}
+
+impl<C> std::fmt::Debug for MultiClient<C> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MultiClient")
+            .field(
+                "identities",
+                &self.identities.iter().map(|i| &i.name).collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+impl<C> MultiClient<C>
+where
+    C: Connector + 'static,
+    for<'a> &'a C::Output: AsyncRead + AsyncWrite + Send + Sync + Unpin,
+{
+    /// Create a client without any identities. Each identity uses this connector
+    pub fn new(connector: C) -> Self {
+        Self {
+            connector,
+            backoff: None,
+            identities: Vec::new(),
+            statuses: VecDeque::new(),
+            dispatcher: Dispatcher::default(),
+            next: 0,
+        }
+    }
+
+    /// Use this backoff policy for the identities added (with [MultiClient::add_identity()]) after this
+    pub fn with_backoff(mut self, backoff: Backoff) -> Self {
+        self.backoff.replace(backoff);
+        self
+    }
+
+    /// Add an identity, which logs in with this [UserConfig].
+    ///
+    /// This doesn't connect until [MultiClient::next_message()] is called. If there's already an identity with this name, this does nothing and returns `false`.
+    pub fn add_identity(&mut self, name: impl Into<String>, user_config: &UserConfig) -> bool {
+        let mut reconnect = Reconnect::new(self.connector.clone(), user_config);
+        if let Some(backoff) = self.backoff {
+            reconnect = reconnect.with_backoff(backoff);
+        }
+        self.add_reconnect(name, reconnect)
+    }
+
+    /// Add an identity which uses this (already configured) [Reconnect], e.g. one with a [TokenProvider](crate::twitch::TokenProvider).
+    ///
+    /// If there's already an identity with this name, this does nothing and returns `false`.
+    pub fn add_reconnect(&mut self, name: impl Into<String>, reconnect: Reconnect<C>) -> bool {
+        let name = name.into();
+        if self.identity(&name).is_some() {
+            return false;
+        }
+
+        self.identities.push(Identity {
+            name,
+            writer: reconnect.writer(),
+            quit_handle: reconnect.quit_handle(),
+            shard: Shard::new(reconnect),
+            channels: BTreeSet::new(),
+        });
+        true
+    }
+
+    /// The names of the identities, in the order they were added. This includes the dead ones
+    pub fn identities(&self) -> impl Iterator<Item = &str> + '_ {
+        self.identities
+            .iter()
+            .map(|identity| identity.name.as_str())
+    }
+
+    /// The channels this identity is on
+    pub fn channels(&self, name: &str) -> Vec<String> {
+        self.identity(name)
+            .map(|identity| identity.channels.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Join `channel` with this identity.
+    ///
+    /// The join happens when [MultiClient::next_message()] is next called. This returns `false` if there's no identity with this name, or it has died.
+    pub fn join(&mut self, name: &str, channel: impl Into<ChannelName>) -> bool {
+        let channel = String::from(channel.into());
+        let identity = match self.live_identity_mut(name) {
+            Some(identity) => identity,
+            None => return false,
+        };
+
+        if identity.channels.insert(channel.clone()) {
+            identity.shard.joins.push(channel);
+            identity.shard.wake();
+        }
+        true
+    }
+
+    /// Part `channel` with this identity, returning whether it was on that channel.
+    pub fn part(&mut self, name: &str, channel: impl Into<ChannelName>) -> bool {
+        let channel = String::from(channel.into());
+        let identity = match self.live_identity_mut(name) {
+            Some(identity) => identity,
+            None => return false,
+        };
+
+        if !identity.channels.remove(&channel) {
+            return false;
+        }
+
+        let joins = &mut identity.shard.joins;
+        match joins.iter().position(|ch| *ch == channel) {
+            // it was never joined
+            Some(pos) => drop(joins.remove(pos)),
+            None => {
+                identity.shard.parts.push(channel);
+                identity.shard.wake();
+            }
+        }
+        true
+    }
+
+    /// Get the writer for this identity. This stays valid across reconnects
+    pub fn writer(&self, name: &str) -> Option<AsyncWriter<MpscWriter>> {
+        self.identity(name).map(|identity| identity.writer.clone())
+    }
+
+    /// Get a handle that makes this identity 'quit'. The others keep running
+    pub fn quit_handle(&self, name: &str) -> Option<NotifyHandle> {
+        self.identity(name)
+            .map(|identity| identity.quit_handle.clone())
+    }
+
+    /// Subscribe to a specific message type, from every identity.
+    ///
+    /// Like [AsyncRunner::events()](super::AsyncRunner::events()), you must still drive the client for these to produce messages.
+    pub fn events<T>(&mut self) -> EventStream<T>
+    where
+        T: FromCommands<'static> + Send + Sync + 'static,
+    {
+        self.dispatcher.subscribe()
+    }
+
+    /// Get the next message (or state change) from any of the identities. You'll usually want to call this in a loop
+    pub async fn next_message(&mut self) -> MultiStatus {
+        loop {
+            if let Some(status) = self.statuses.pop_front() {
+                return status;
+            }
+
+            for identity in &mut self.identities {
+                if let State::Idle(..) = identity.shard.state {
+                    identity.shard.start();
+                }
+            }
+
+            if self
+                .identities
+                .iter()
+                .all(|identity| matches!(identity.shard.state, State::Dead))
+            {
+                return MultiStatus::Closed;
+            }
+
+            let (index, polled) = self.poll_identities().await;
+            self.handle(index, polled);
+        }
+    }
+
+    async fn poll_identities(&mut self) -> (usize, Polled<C>) {
+        let identities = &mut self.identities;
+        let next = &mut self.next;
+        futures_lite::future::poll_fn(move |cx| {
+            let len = identities.len();
+            for index in (0..len).map(|i| (*next + i) % len) {
+                if let State::Running(fut) = &mut identities[index].shard.state {
+                    if let Poll::Ready(polled) = fut.as_mut().poll(cx) {
+                        *next = (index + 1) % len;
+                        return Poll::Ready((index, polled));
+                    }
+                }
+            }
+            Poll::Pending
+        })
+        .await
+    }
+
+    fn handle(&mut self, index: usize, polled: Polled<C>) {
+        let Polled {
+            reconnect,
+            join_errors,
+            status,
+        } = polled;
+
+        let identity = &mut self.identities[index];
+        for error in join_errors {
+            if let Error::BannedFromChannel { channel } | Error::ChannelUnavailable { channel } =
+                &error
+            {
+                identity.channels.remove(channel);
+            }
+            self.statuses.push_back(MultiStatus::JoinFailed {
+                identity: identity.name.clone(),
+                error,
+            });
+        }
+
+        let status = match status {
+            Some(Ok(status)) => status,
+            None => {
+                identity.shard.state = State::Idle(reconnect);
+                return;
+            }
+            Some(Err(error)) => {
+                log::warn!("identity '{}' died: {}", identity.name, error);
+                identity.shard.state = State::Dead;
+                identity.shard.joins.clear();
+                identity.shard.parts.clear();
+                self.statuses.push_back(MultiStatus::Died {
+                    identity: identity.name.clone(),
+                    error,
+                    channels: std::mem::take(&mut identity.channels).into_iter().collect(),
+                });
+                return;
+            }
+        };
+
+        identity.shard.state = match status {
+            ReconnectStatus::Quit | ReconnectStatus::Stopped => State::Dead,
+            _ => State::Idle(reconnect),
+        };
+
+        if let ReconnectStatus::Message(msg) = &status {
+            self.dispatcher.dispatch(msg);
+        }
+        self.statuses.push_back(MultiStatus::Identity {
+            identity: identity.name.clone(),
+            status,
+        });
+    }
+
+    fn identity(&self, name: &str) -> Option<&Identity<C>> {
+        self.identities
+            .iter()
+            .find(|identity| identity.name == name)
+    }
+
+    fn live_identity_mut(&mut self, name: &str) -> Option<&mut Identity<C>> {
+        self.identities
+            .iter_mut()
+            .find(|identity| identity.name == name)
+            .filter(|identity| !matches!(identity.shard.state, State::Dead))
+    }
+}
+
+#[cfg(all(test, feature = "testing"))]
+mod tests {
+    use super::*;
+    use crate::{
+        commands,
+        messages::Commands,
+        test::{user_config, MockServer},
+    };
+    use futures_lite::future::{block_on, zip};
+
+    #[test]
+    fn multiple_identities() {
+        let moderator = UserConfig::builder()
+            .name("museun_mod")
+            .token(format!("oauth:{}", "b".repeat(30)))
+            .enable_all_capabilities()
+            .build()
+            .unwrap();
+
+        let server = MockServer::new();
+        let mut client = MultiClient::new(server.clone());
+        assert!(client.add_identity("bot", &user_config()));
+        assert!(client.add_identity("moderator", &moderator));
+        assert!(!client.add_identity("bot", &moderator));
+        assert_eq!(
+            client.identities().collect::<Vec<_>>(),
+            ["bot", "moderator"]
+        );
+
+        assert!(client.join("bot", "museun"));
+        assert!(client.join("bot", "#shaken_bot"));
+        assert!(client.join("moderator", "Museun"));
+        assert!(client.part("bot", "shaken_bot"));
+        assert!(!client.part("bot", "shaken_bot"));
+        assert!(!client.join("nobody", "museun"));
+        assert_eq!(client.channels("bot"), ["#museun"]);
+        assert_eq!(client.channels("moderator"), ["#museun"]);
+        assert!(client.writer("nobody").is_none());
+
+        let mut bot = client.writer("bot").unwrap();
+        let mut moderator = client.writer("moderator").unwrap();
+        block_on(async {
+            let connected = async {
+                let mut connected = vec![];
+                while connected.len() < 2 {
+                    if let MultiStatus::Identity {
+                        identity,
+                        status: ReconnectStatus::Connected,
+                    } = client.next_message().await
+                    {
+                        connected.push(identity);
+                    }
+                }
+                connected.sort();
+                connected
+            };
+            let accepted = async {
+                let mut conns = vec![];
+                for _ in 0..2 {
+                    let mut conn = server.accept().await.unwrap();
+                    assert_eq!(conn.read_line().await.unwrap(), "JOIN #museun\r\n");
+                    conns.push(conn);
+                }
+                conns.sort_by(|a, b| a.name().cmp(b.name()));
+                conns
+            };
+            let (connected, mut conns) = zip(connected, accepted).await;
+            assert_eq!(connected, ["bot", "moderator"]);
+            let (mut moderator_conn, mut bot_conn) = (conns.remove(0), conns.remove(0));
+            assert_eq!(bot_conn.name(), "shaken_bot");
+
+            // each writer sends on its own connection
+            bot.encode(commands::privmsg("museun", "hello"))
+                .await
+                .unwrap();
+            moderator
+                .encode(commands::privmsg("museun", "/timeout someone 10"))
+                .await
+                .unwrap();
+            let sent = async {
+                let bot_line = loop {
+                    let line = bot_conn.read_line().await.unwrap();
+                    if line.starts_with("PRIVMSG") {
+                        break line;
+                    }
+                };
+                let moderator_line = loop {
+                    let line = moderator_conn.read_line().await.unwrap();
+                    if line.starts_with("PRIVMSG") {
+                        break line;
+                    }
+                };
+                moderator_conn
+                    .send(":museun!museun@museun.tmi.twitch.tv PRIVMSG #museun :hi")
+                    .unwrap();
+                (bot_line, moderator_line)
+            };
+            let received = async {
+                loop {
+                    if let MultiStatus::Identity {
+                        identity,
+                        status: ReconnectStatus::Message(Commands::Privmsg(msg)),
+                    } = client.next_message().await
+                    {
+                        break (identity, msg);
+                    }
+                }
+            };
+            let ((bot_line, moderator_line), (identity, msg)) = zip(sent, received).await;
+            assert_eq!(bot_line, "PRIVMSG #museun :hello\r\n");
+            assert_eq!(moderator_line, "PRIVMSG #museun :/timeout someone 10\r\n");
+            assert_eq!((identity.as_str(), msg.data()), ("moderator", "hi"));
+        });
+    }
+}
@@ -0,0 +1,130 @@
+use crate::{irc::IrcMessage, messages::Commands};
+
+use std::collections::HashMap;
+
+/// The users on a channel, as Twitch listed them after we joined it.
+///
+/// These are produced by [AsyncRunner::names_events()](super::AsyncRunner::names_events()), and require the `membership` capability.
+///
+/// Twitch splits the list over several `353` (`RPL_NAMREPLY`) lines, so this is produced once the `366` (`RPL_ENDOFNAMES`) line ends it.
+/// For channels with more than 1000 chatters, Twitch only lists the moderators.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Names {
+    /// The channel
+    pub channel: String,
+    /// The names of the users, in the order Twitch listed them
+    pub users: Vec<String>,
+}
+
+// collects the names for each channel until the end of its list
+#[derive(Debug, Default)]
+pub(crate) struct NamesBuffer {
+    pending: HashMap<String, Vec<String>>,
+}
+
+impl NamesBuffer {
+    pub(crate) fn update(&mut self, msg: &Commands<'_>) -> Option<Names> {
+        let msg = match msg {
+            Commands::Raw(msg) => msg,
+            _ => return None,
+        };
+
+        match msg.get_command() {
+            // :name.tmi.twitch.tv 353 name = #channel :user1 user2 user3
+            IrcMessage::NAMES => {
+                let channel = msg.nth_arg(2)?;
+                let names = msg.get_data()?.split_whitespace().map(ToString::to_string);
+                self.pending
+                    .entry(channel.to_string())
+                    .or_default()
+                    .extend(names);
+                None
+            }
+            // :name.tmi.twitch.tv 366 name #channel :End of /NAMES list
+            IrcMessage::END_OF_NAMES => {
+                let channel = msg.nth_arg(1)?;
+                Some(Names {
+                    users: self.pending.remove(channel).unwrap_or_default(),
+                    channel: channel.to_string(),
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{irc::parse, FromIrcMessage as _};
+
+    #[test]
+    fn names() {
+        let input = ":shaken_bot.tmi.twitch.tv 353 shaken_bot = #museun :shaken_bot museun\r\n\
+                     :shaken_bot.tmi.twitch.tv 353 shaken_bot = #other :someone\r\n\
+                     :shaken_bot.tmi.twitch.tv 353 shaken_bot = #museun :test\r\n\
+                     :shaken_bot.tmi.twitch.tv 366 shaken_bot #museun :End of /NAMES list\r\n\
+                     :shaken_bot.tmi.twitch.tv 366 shaken_bot #empty :End of /NAMES list\r\n";
+
+        let mut buffer = NamesBuffer::default();
+        let names = parse(input)
+            .map(|msg| Commands::from_irc(msg.unwrap()).unwrap())
+            .filter_map(|msg| buffer.update(&msg))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            names,
+            vec![
+                Names {
+                    channel: "#museun".into(),
+                    users: vec!["shaken_bot".into(), "museun".into(), "test".into()],
+                },
+                Names {
+                    channel: "#empty".into(),
+                    users: vec![],
+                },
+            ]
+        );
+
+        // #other hasn't ended yet
+        assert_eq!(buffer.pending["#other"], vec!["someone".to_string()]);
+    }
+
+    #[test]
+    #[cfg(feature = "testing")]
+    fn names_events() {
+        use crate::{
+            test::{user_config, MockServer},
+            Status,
+        };
+        use futures_lite::future::block_on;
+
+        let server = MockServer::new();
+        block_on(async {
+            let (mut runner, conn) = server.connect_runner(&user_config()).await.unwrap();
+
+            let names = runner.names_events();
+            conn.send(":shaken_bot!shaken_bot@shaken_bot.tmi.twitch.tv JOIN #museun")
+                .unwrap();
+            conn.send(":shaken_bot.tmi.twitch.tv 353 shaken_bot = #museun :shaken_bot museun")
+                .unwrap();
+            conn.send(":shaken_bot.tmi.twitch.tv 353 shaken_bot = #museun :test")
+                .unwrap();
+            conn.send(":shaken_bot.tmi.twitch.tv 366 shaken_bot #museun :End of /NAMES list")
+                .unwrap();
+            loop {
+                if let Status::Message(Commands::Raw(msg)) = runner.next_message().await.unwrap() {
+                    if msg.get_command() == IrcMessage::END_OF_NAMES {
+                        break;
+                    }
+                    assert!(names.try_next().is_none());
+                }
+            }
+
+            let names = names.try_next().unwrap();
+            assert_eq!(names.channel, "#museun");
+            assert_eq!(names.users, vec!["shaken_bot", "museun", "test"]);
+            assert_eq!(runner.chatters("museun").unwrap().len(), 3);
+        });
+    }
+}
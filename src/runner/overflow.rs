@@ -0,0 +1,281 @@
+use crate::messages::Commands;
+
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+    task::{Context, Poll, Waker},
+};
+
+/// What a bounded [EventStream](super::EventStream) does with a new message when its buffer is full.
+///
+/// Use these with [AsyncRunner::bounded_events()](super::AsyncRunner::bounded_events()).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Overflow {
+    /// Wait for room, which pauses the runner until the stream is read.
+    ///
+    /// The stream must be read somewhere other than the loop which drives the runner, or the runner never makes progress.
+    Block,
+    /// Drop the oldest buffered message to make room
+    DropOldest,
+    /// Drop the new message
+    DropNewest,
+    /// Make room by coalescing `JOIN`s and `PART`s, for the floods of them during raids.
+    ///
+    /// A new `JOIN` (or `PART`) replaces any buffered one for the same user and channel.
+    /// Otherwise the oldest buffered `JOIN` (or `PART`) is dropped, and if there are none, the oldest message is.
+    CoalescePresence,
+}
+
+// the user and channel of a JOIN or PART
+type PresenceKey = (String, String);
+
+fn presence_key(msg: &Commands<'_>) -> Option<PresenceKey> {
+    match msg {
+        Commands::Join(msg) => Some((msg.channel().to_string(), msg.name().to_string())),
+        Commands::Part(msg) => Some((msg.channel().to_string(), msg.name().to_string())),
+        _ => None,
+    }
+}
+
+struct State<T> {
+    queue: VecDeque<(T, Option<PresenceKey>)>,
+    capacity: usize,
+    overflow: Overflow,
+    // the stream, waiting for a message
+    reader: Option<Waker>,
+    // the runner, waiting for room
+    writer: Option<Waker>,
+    // the stream was dropped
+    closed: bool,
+    // the runner was dropped
+    finished: bool,
+    dropped: u64,
+}
+
+impl<T> State<T> {
+    fn push(&mut self, item: T, key: Option<PresenceKey>) {
+        if self.queue.len() < self.capacity || self.overflow == Overflow::Block {
+            self.queue.push_back((item, key));
+            return;
+        }
+
+        self.dropped += 1;
+        match self.overflow {
+            Overflow::DropNewest => return,
+            Overflow::CoalescePresence => {
+                let same = key
+                    .as_ref()
+                    .and_then(|key| self.queue.iter().position(|(_, k)| k.as_ref() == Some(key)));
+                let pos = same
+                    .or_else(|| self.queue.iter().position(|(_, k)| k.is_some()))
+                    .unwrap_or(0);
+                self.queue.remove(pos);
+            }
+            _ => {
+                self.queue.pop_front();
+            }
+        }
+        self.queue.push_back((item, key));
+    }
+}
+
+pub(crate) struct BoundedSender<T> {
+    state: Arc<Mutex<State<T>>>,
+}
+
+impl<T> BoundedSender<T> {
+    // returns false if the stream was dropped
+    pub(crate) fn send(&self, item: T, msg: &Commands<'_>) -> bool {
+        let mut state = self.state.lock().unwrap();
+        if state.closed {
+            return false;
+        }
+        let key = match state.overflow {
+            Overflow::CoalescePresence => presence_key(msg),
+            _ => None,
+        };
+        state.push(item, key);
+        if let Some(waker) = state.reader.take() {
+            waker.wake()
+        }
+        true
+    }
+
+    // ready once there's room (or the stream was dropped)
+    pub(crate) fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<()> {
+        let mut state = self.state.lock().unwrap();
+        if state.closed || state.queue.len() < state.capacity {
+            return Poll::Ready(());
+        }
+        state.writer.replace(cx.waker().clone());
+        Poll::Pending
+    }
+
+    pub(crate) fn is_blocking(&self) -> bool {
+        self.state.lock().unwrap().overflow == Overflow::Block
+    }
+}
+
+impl<T> Drop for BoundedSender<T> {
+    fn drop(&mut self) {
+        let mut state = self.state.lock().unwrap();
+        state.finished = true;
+        if let Some(waker) = state.reader.take() {
+            waker.wake()
+        }
+    }
+}
+
+pub(crate) struct BoundedReceiver<T> {
+    state: Arc<Mutex<State<T>>>,
+}
+
+impl<T> BoundedReceiver<T> {
+    pub(crate) fn try_recv(&self) -> Option<T> {
+        let mut state = self.state.lock().unwrap();
+        let (item, _) = state.queue.pop_front()?;
+        if let Some(waker) = state.writer.take() {
+            waker.wake()
+        }
+        Some(item)
+    }
+
+    pub(crate) fn poll_recv(&self, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        if let Some(item) = self.try_recv() {
+            return Poll::Ready(Some(item));
+        }
+        let mut state = self.state.lock().unwrap();
+        if state.finished {
+            return Poll::Ready(None);
+        }
+        state.reader.replace(cx.waker().clone());
+        Poll::Pending
+    }
+
+    pub(crate) fn dropped(&self) -> u64 {
+        self.state.lock().unwrap().dropped
+    }
+}
+
+impl<T> Drop for BoundedReceiver<T> {
+    fn drop(&mut self) {
+        let mut state = self.state.lock().unwrap();
+        state.closed = true;
+        state.queue.clear();
+        if let Some(waker) = state.writer.take() {
+            waker.wake()
+        }
+    }
+}
+
+pub(crate) fn bounded<T>(
+    capacity: usize,
+    overflow: Overflow,
+) -> (BoundedSender<T>, BoundedReceiver<T>) {
+    let state = Arc::new(Mutex::new(State {
+        queue: VecDeque::with_capacity(capacity.max(1)),
+        capacity: capacity.max(1),
+        overflow,
+        reader: None,
+        writer: None,
+        closed: false,
+        finished: false,
+        dropped: 0,
+    }));
+    (
+        BoundedSender {
+            state: Arc::clone(&state),
+        },
+        BoundedReceiver { state },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FromIrcMessage as _, IntoOwned as _};
+
+    fn commands(input: &str) -> Commands<'static> {
+        let msg = crate::irc::parse(input).next().unwrap().unwrap();
+        Commands::from_irc(msg).unwrap().into_owned()
+    }
+
+    fn send_all(overflow: Overflow, inputs: &[&str]) -> Vec<String> {
+        let (tx, rx) = bounded(3, overflow);
+        for input in inputs {
+            let msg = commands(input);
+            assert!(tx.send(msg.raw().trim_end().to_string(), &msg));
+        }
+        std::iter::from_fn(|| rx.try_recv()).collect()
+    }
+
+    const MESSAGES: &[&str] = &[
+        ":a!a@a PRIVMSG #museun :1\r\n",
+        ":a!a@a PRIVMSG #museun :2\r\n",
+        ":a!a@a PRIVMSG #museun :3\r\n",
+        ":a!a@a PRIVMSG #museun :4\r\n",
+    ];
+
+    #[test]
+    fn drop_oldest_and_newest() {
+        assert_eq!(
+            send_all(Overflow::DropOldest, MESSAGES),
+            [
+                ":a!a@a PRIVMSG #museun :2",
+                ":a!a@a PRIVMSG #museun :3",
+                ":a!a@a PRIVMSG #museun :4"
+            ]
+        );
+        assert_eq!(
+            send_all(Overflow::DropNewest, MESSAGES),
+            [
+                ":a!a@a PRIVMSG #museun :1",
+                ":a!a@a PRIVMSG #museun :2",
+                ":a!a@a PRIVMSG #museun :3"
+            ]
+        );
+    }
+
+    #[test]
+    fn coalesce_presence() {
+        let inputs = &[
+            ":a!a@a JOIN #museun\r\n",
+            ":b!b@b JOIN #museun\r\n",
+            ":a!a@a PRIVMSG #museun :hello\r\n",
+            // replaces the JOIN from 'b'
+            ":b!b@b PART #museun\r\n",
+            // drops the oldest JOIN (from 'a')
+            ":c!c@c PRIVMSG #museun :world\r\n",
+        ];
+        assert_eq!(
+            send_all(Overflow::CoalescePresence, inputs),
+            [
+                ":a!a@a PRIVMSG #museun :hello",
+                ":b!b@b PART #museun",
+                ":c!c@c PRIVMSG #museun :world"
+            ]
+        );
+    }
+
+    #[test]
+    fn block_waits_for_room() {
+        let (tx, rx) = bounded(1, Overflow::Block);
+        let msg = commands(MESSAGES[0]);
+        let waker = futures_lite::future::block_on(futures_lite::future::poll_fn(|cx| {
+            Poll::Ready(cx.waker().clone())
+        }));
+        let mut cx = Context::from_waker(&waker);
+
+        assert!(tx.poll_ready(&mut cx).is_ready());
+        assert!(tx.send(1, &msg));
+        assert!(tx.poll_ready(&mut cx).is_pending());
+
+        assert_eq!(rx.try_recv(), Some(1));
+        assert!(tx.poll_ready(&mut cx).is_ready());
+        assert_eq!(rx.dropped(), 0);
+
+        drop(rx);
+        assert!(!tx.send(2, &msg));
+    }
+}
@@ -0,0 +1,360 @@
+use super::{AsyncRunner, Error, Status};
+use crate::{
+    connector::Connector,
+    rate_limit::{RateClass, RateLimit},
+    twitch::UserConfig,
+    writer::{AsyncWriter, MpscWriter},
+    Encodable,
+};
+
+use futures_lite::{AsyncRead, AsyncWrite};
+use std::{
+    future::Future,
+    io,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::Poll,
+};
+
+/// How a [PoolWriter] picks the connection for each message
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum PoolStrategy {
+    /// Use each connection in turn
+    #[default]
+    RoundRobin,
+    /// Use the connection which has sent the fewest messages recently
+    LeastLoaded,
+}
+
+// this is shared between all of the clones of a pool's writer
+struct PoolState {
+    strategy: PoolStrategy,
+    next: usize,
+    // the limit for the account, across every connection
+    shared: RateLimit,
+    // what each connection has sent recently, for the least loaded strategy
+    connections: Vec<RateLimit>,
+}
+
+impl PoolState {
+    fn new(connections: usize) -> Self {
+        Self {
+            strategy: PoolStrategy::default(),
+            next: 0,
+            shared: RateLimit::from_class(RateClass::Regular),
+            connections: (0..connections)
+                .map(|_| RateLimit::from_class(RateClass::Regular))
+                .collect(),
+        }
+    }
+
+    // the connection to send the next message on, or how long to wait for the shared rate limit
+    fn pick(&mut self) -> Result<usize, std::time::Duration> {
+        self.shared.consume(1)?;
+
+        let index = match self.strategy {
+            PoolStrategy::RoundRobin => {
+                let index = self.next % self.connections.len();
+                self.next = index + 1;
+                index
+            }
+            PoolStrategy::LeastLoaded => self
+                .connections
+                .iter_mut()
+                // this refills the bucket, without taking anything
+                .map(|limit| limit.consume(0).unwrap_or_default())
+                .enumerate()
+                .max_by_key(|&(index, tokens)| (tokens, std::cmp::Reverse(index)))
+                .map(|(index, _)| index)
+                .unwrap_or_default(),
+        };
+
+        let _ = self.connections[index].consume(1);
+        Ok(index)
+    }
+}
+
+/// Several extra connections used only for sending messages, for bots that need more throughput than one connection allows.
+///
+/// Messages are read on your own [AsyncRunner] as usual. The connections in this pool join no channels,
+/// and what they read (other than `PING`s, which are answered) is discarded.
+///
+/// Messages are sent with a [PoolWriter], which spreads them across the connections (see [PoolStrategy])
+/// while keeping every connection under one shared rate limit (see [WriterPool::with_rate_class()]).
+///
+/// The pool must be driven with [WriterPool::run()] for its messages to be sent.
+///
+/// # Example
+/// ```no_run
+/// # use twitchchat::{commands, connector::Connector, runner::{PoolStrategy, WriterPool}, rate_limit::RateClass, AsyncRunner, UserConfig};
+/// # async fn demo<C>(connector: C, user_config: &UserConfig, runner: AsyncRunner) -> Result<(), twitchchat::runner::Error>
+/// # where
+/// #    C: Connector,
+/// #    for<'a> &'a C::Output: futures_lite::AsyncRead + futures_lite::AsyncWrite + Send + Sync + Unpin,
+/// # {
+/// let mut pool = WriterPool::connect(connector, user_config, 4)
+///     .await?
+///     .with_strategy(PoolStrategy::LeastLoaded)
+///     .with_rate_class(RateClass::Verified);
+///
+/// let mut writer = pool.writer();
+/// let sending = async move {
+///     for n in 0..1000 {
+///         writer.send(commands::privmsg("museun", &n.to_string())).await?;
+///     }
+///     Ok::<_, std::io::Error>(())
+/// };
+///
+/// // keep reading on the runner (e.g. in a loop elsewhere), and drive the pool while sending
+/// futures_lite::future::or(async { sending.await.map_err(Into::into) }, async { pool.run().await.map(drop) }).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct WriterPool {
+    connections: Vec<AsyncRunner>,
+    writer: PoolWriter,
+}
+
+impl std::fmt::Debug for WriterPool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WriterPool")
+            .field("connections", &self.connections.len())
+            .finish()
+    }
+}
+
+impl WriterPool {
+    /// Connect this many (at least one) connections, each authenticated as `user_config`.
+    pub async fn connect<C>(
+        connector: C,
+        user_config: &UserConfig,
+        connections: usize,
+    ) -> Result<Self, Error>
+    where
+        C: Connector,
+        for<'a> &'a C::Output: AsyncRead + AsyncWrite + Send + Sync + Unpin,
+    {
+        let mut runners = Vec::with_capacity(connections.max(1));
+        for _ in 0..connections.max(1) {
+            let runner = AsyncRunner::connect(connector.clone(), user_config).await?;
+            if runner.writer().is_read_only() {
+                log::warn!("an anonymous connection cannot send messages");
+            }
+            runners.push(runner);
+        }
+        Ok(Self::from_runners(runners))
+    }
+
+    /// Make a pool from these (already connected) runners.
+    ///
+    /// # Panics
+    /// This panics if `runners` is empty.
+    pub fn from_runners(runners: Vec<AsyncRunner>) -> Self {
+        assert!(!runners.is_empty(), "a pool needs at least one connection");
+
+        let writers = runners.iter().map(AsyncRunner::writer).collect();
+        let writer = PoolWriter {
+            state: Arc::new(Mutex::new(PoolState::new(runners.len()))),
+            writers,
+        };
+        Self {
+            connections: runners,
+            writer,
+        }
+    }
+
+    /// Use this strategy to pick the connection for each message. This defaults to [PoolStrategy::RoundRobin].
+    pub fn with_strategy(self, strategy: PoolStrategy) -> Self {
+        self.writer.state.lock().unwrap().strategy = strategy;
+        self
+    }
+
+    /// Use this rate limit for the whole account, shared by every connection. This defaults to [RateClass::Regular].
+    ///
+    /// Only use [RateClass::Verified] if your bot is actually verified.
+    pub fn with_rate_class(self, rate_class: RateClass) -> Self {
+        self.writer.state.lock().unwrap().shared = RateLimit::from_class(rate_class);
+        self
+    }
+
+    /// How many connections are in the pool
+    pub fn len(&self) -> usize {
+        self.connections.len()
+    }
+
+    /// Is the pool empty? (this is never true)
+    pub fn is_empty(&self) -> bool {
+        self.connections.is_empty()
+    }
+
+    /// Get a clonable writer which sends on this pool's connections
+    pub fn writer(&self) -> PoolWriter {
+        self.writer.clone()
+    }
+
+    /// Drive every connection, sending what was written to the [PoolWriter].
+    ///
+    /// This returns once any connection ends, with the [Status] (or the error) it ended with.
+    pub async fn run(&mut self) -> Result<Status<'static>, Error> {
+        type Running<'a> =
+            Pin<Box<dyn Future<Output = Result<Status<'static>, Error>> + Send + 'a>>;
+
+        let mut running = self
+            .connections
+            .iter_mut()
+            .map(|runner| -> Running<'_> {
+                Box::pin(async move {
+                    loop {
+                        match runner.next_message().await? {
+                            Status::Message(..) | Status::Invalid(..) => continue,
+                            status => break Ok(status),
+                        }
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+
+        futures_lite::future::poll_fn(|cx| {
+            for fut in &mut running {
+                if let Poll::Ready(res) = fut.as_mut().poll(cx) {
+                    return Poll::Ready(res);
+                }
+            }
+            Poll::Pending
+        })
+        .await
+    }
+}
+
+/// A writer which spreads messages across the connections of a [WriterPool]
+#[derive(Clone)]
+pub struct PoolWriter {
+    state: Arc<Mutex<PoolState>>,
+    writers: Arc<[AsyncWriter<MpscWriter>]>,
+}
+
+impl std::fmt::Debug for PoolWriter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PoolWriter")
+            .field("connections", &self.writers.len())
+            .finish()
+    }
+}
+
+impl PoolWriter {
+    /// Encode this message on the next connection.
+    ///
+    /// This waits if the shared rate limit has been reached. Like the [AsyncWriter], only `PRIVMSG`s are sent.
+    pub async fn send<M>(&mut self, msg: M) -> io::Result<()>
+    where
+        M: Encodable + Send + Sync,
+    {
+        let index = loop {
+            // the lock can't be held across the delay
+            let picked = self.state.lock().unwrap().pick();
+            match picked {
+                Ok(index) => break index,
+                Err(delay) => {
+                    log::debug!("pool rate limit hit, waiting {:.2?}", delay);
+                    futures_timer::Delay::new(delay).await
+                }
+            }
+        };
+        self.writers[index].clone().encode(msg).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_robin() {
+        let mut state = PoolState::new(3);
+        let picked = (0..5).map(|_| state.pick().unwrap()).collect::<Vec<_>>();
+        assert_eq!(picked, [0, 1, 2, 0, 1]);
+    }
+
+    #[test]
+    fn least_loaded() {
+        let mut state = PoolState::new(3);
+        state.strategy = PoolStrategy::LeastLoaded;
+        for _ in 0..3 {
+            state.connections[0].consume(1).unwrap();
+        }
+        state.connections[2].consume(1).unwrap();
+
+        let picked = (0..4).map(|_| state.pick().unwrap()).collect::<Vec<_>>();
+        assert_eq!(picked, [1, 1, 2, 1]);
+    }
+
+    #[test]
+    fn shared_rate_limit() {
+        let mut state = PoolState::new(2);
+        state.shared = RateLimit::full(3, std::time::Duration::from_secs(30));
+        for _ in 0..3 {
+            state.pick().unwrap();
+        }
+        assert!(state.pick().is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "testing")]
+    fn pooled_writes() {
+        use crate::{
+            commands,
+            test::{user_config, MockConnection, MockServer},
+        };
+        use futures_lite::future::{block_on, zip};
+
+        let server = MockServer::new();
+        block_on(async {
+            let accepted = async {
+                (
+                    server.accept().await.unwrap(),
+                    server.accept().await.unwrap(),
+                )
+            };
+            let (pool, (mut first, mut second)) = zip(
+                WriterPool::connect(server.clone(), &user_config(), 2),
+                accepted,
+            )
+            .await;
+            let mut pool = pool.unwrap();
+            assert_eq!(pool.len(), 2);
+
+            let mut writer = pool.writer();
+            for msg in &["1", "2", "3"] {
+                writer.send(commands::privmsg("museun", msg)).await.unwrap();
+            }
+
+            let read = async {
+                let read = |conn: &mut MockConnection, count| {
+                    let mut lines = vec![];
+                    while lines.len() < count {
+                        match conn.try_read_line().unwrap() {
+                            Some(line) => lines.push(line),
+                            None => break,
+                        }
+                    }
+                    lines
+                };
+                let (mut a, mut b) = (vec![], vec![]);
+                while a.len() < 2 || b.is_empty() {
+                    a.extend(read(&mut first, 2 - a.len()));
+                    b.extend(read(&mut second, 1 - b.len()));
+                    futures_lite::future::yield_now().await;
+                }
+                (a, b)
+            };
+            let (a, b) = futures_lite::future::or(read, async {
+                let status = pool.run().await;
+                panic!("the pool ended: {:?}", status)
+            })
+            .await;
+            assert_eq!(a, ["PRIVMSG #museun :1\r\n", "PRIVMSG #museun :3\r\n"]);
+            assert_eq!(b, ["PRIVMSG #museun :2\r\n"]);
+        });
+    }
+}
@@ -0,0 +1,90 @@
+use crate::messages::Commands;
+
+/// A user joined or left a channel.
+///
+/// These are produced by [AsyncRunner::presence_events()](super::AsyncRunner::presence_events()), and require the `membership` capability.
+///
+/// Twitch batches these up, so they can arrive a few seconds late.
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Presence {
+    /// A user joined a channel
+    Joined {
+        /// The channel they joined
+        channel: String,
+        /// The name of the user
+        name: String,
+    },
+    /// A user left a channel
+    Parted {
+        /// The channel they left
+        channel: String,
+        /// The name of the user
+        name: String,
+    },
+}
+
+impl Presence {
+    pub(crate) fn from_commands(msg: &Commands<'_>) -> Option<Self> {
+        match msg {
+            Commands::Join(msg) => Some(Self::Joined {
+                channel: msg.channel().to_string(),
+                name: msg.name().to_string(),
+            }),
+            Commands::Part(msg) => Some(Self::Parted {
+                channel: msg.channel().to_string(),
+                name: msg.name().to_string(),
+            }),
+            _ => None,
+        }
+    }
+
+    /// The channel this happened on
+    pub fn channel(&self) -> &str {
+        match self {
+            Self::Joined { channel, .. } | Self::Parted { channel, .. } => channel,
+        }
+    }
+
+    /// The name of the user
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Joined { name, .. } | Self::Parted { name, .. } => name,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{irc::parse, FromIrcMessage as _};
+
+    #[test]
+    fn presence() {
+        let input = ":test!test@test.tmi.twitch.tv JOIN #museun\r\n\
+                     :test!test@test.tmi.twitch.tv PART #museun\r\n\
+                     :test!user@host PRIVMSG #museun :hello\r\n";
+
+        let presence = parse(input)
+            .map(|msg| Commands::from_irc(msg.unwrap()).unwrap())
+            .map(|msg| Presence::from_commands(&msg))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            presence,
+            vec![
+                Some(Presence::Joined {
+                    channel: "#museun".into(),
+                    name: "test".into()
+                }),
+                Some(Presence::Parted {
+                    channel: "#museun".into(),
+                    name: "test".into()
+                }),
+                None,
+            ]
+        );
+        assert_eq!(presence[0].as_ref().unwrap().name(), "test");
+        assert_eq!(presence[1].as_ref().unwrap().channel(), "#museun");
+    }
+}
@@ -1,10 +1,22 @@
-use crate::rate_limit::{RateClass, RateLimit};
-use futures_lite::{AsyncWrite, AsyncWriteExt};
-use std::{collections::VecDeque, time::Duration};
+use super::Metrics;
+use crate::{
+    encoder::AsyncEncoder,
+    rate_limit::{RateClass, RateLimit},
+    writer::Priority,
+};
+use futures_lite::AsyncWrite;
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
 
 pub struct RateLimitedEncoder {
     pub(crate) rate_limit: RateLimit,
-    pub(crate) queue: VecDeque<Box<[u8]>>,
+    // this is kept in priority order
+    pub(crate) queue: VecDeque<(Priority, Box<[u8]>)>,
+    // the slow mode delay, if it applies to us
+    pub(crate) slow: Option<Duration>,
+    pub(crate) last_sent: Option<Instant>,
 }
 
 impl RateLimitedEncoder {
@@ -12,12 +24,32 @@ impl RateLimitedEncoder {
         &mut self,
         name: &str,
         limit: &mut u64,
-        sink: &mut W,
+        sink: &mut AsyncEncoder<W>,
+        metrics: &dyn Metrics,
+        min: Priority,
     ) -> std::io::Result<()>
     where
-        W: AsyncWrite + Send + Sync + Unpin + ?Sized,
+        W: AsyncWrite + Send + Sync + Unpin,
     {
-        while let Some(data) = self.queue.pop_front() {
+        while let Some((priority, data)) = self.queue.pop_front() {
+            if priority < min {
+                self.queue.push_front((priority, data));
+                break;
+            }
+
+            // Twitch drops messages sent too soon in slow mode, so wait for it
+            if let Some(delay) = self.slow_mode_delay() {
+                log::trace!(
+                    target: "twitchchat::rate_limit",
+                    "slow mode for '{}', waiting {:.2?}",
+                    name,
+                    delay
+                );
+                metrics.throttled(delay);
+                self.queue.push_front((priority, data));
+                break;
+            }
+
             match self.rate_limit.consume(1) {
                 Ok(..) => {
                     *limit = limit.saturating_sub(1);
@@ -26,14 +58,21 @@ impl RateLimitedEncoder {
                         "> {}",
                         std::str::from_utf8(&*data).unwrap().escape_debug()
                     );
-                    sink.write_all(&*data).await?;
+                    metrics.bytes_written(data.len());
+                    // these are written together, once the caller is done draining
+                    sink.batch_bytes(data).await?;
+                    self.last_sent.replace(Instant::now());
                 }
-                Err(..) => {
-                    log::warn!(
-                        target: "twitchchat::rate_limit",
-                        "local rate limit for '{}' hit",
+                Err(delay) => {
+                    event!(
+                        info,
+                        "rate_limit",
+                        "waiting for the local rate limit for '{}'",
                         name
                     );
+                    metrics.throttled(delay);
+                    // keep its place, it'll be sent once the rate limit refills
+                    self.queue.push_front((priority, data));
                     break;
                 }
             }
@@ -45,8 +84,29 @@ impl RateLimitedEncoder {
         Ok(())
     }
 
-    pub fn enqueue(&mut self, msg: Box<[u8]>) {
-        self.queue.push_back(msg);
+    // how long until slow mode lets us send the next message
+    pub fn slow_mode_delay(&self) -> Option<Duration> {
+        let (slow, last_sent) = (self.slow?, self.last_sent?);
+        slow.checked_sub(last_sent.elapsed())
+            .filter(|delay| *delay > Duration::from_secs(0))
+    }
+
+    // how long until a queued message can be sent, if slow mode is holding it back
+    pub fn next_send(&self) -> Option<Duration> {
+        if self.queue.is_empty() {
+            return None;
+        }
+        self.slow_mode_delay()
+    }
+
+    pub fn enqueue(&mut self, priority: Priority, msg: Box<[u8]>) {
+        // after everything with the same (or a higher) priority
+        let pos = self
+            .queue
+            .iter()
+            .position(|(p, _)| *p < priority)
+            .unwrap_or(self.queue.len());
+        self.queue.insert(pos, (priority, msg));
     }
 }
 
@@ -63,3 +123,87 @@ impl Default for PreviousRate {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::NoMetrics;
+
+    #[test]
+    fn drains_in_priority_order() {
+        let mut encoder = RateLimitedEncoder {
+            rate_limit: RateLimit::from_class(RateClass::Regular),
+            queue: VecDeque::new(),
+            slow: None,
+            last_sent: None,
+        };
+
+        let tests = &[
+            (Priority::Low, "PRIVMSG #museun :low\r\n"),
+            (Priority::Normal, "PRIVMSG #museun :normal 1\r\n"),
+            (Priority::High, "PRIVMSG #museun :/ban someone\r\n"),
+            (Priority::Normal, "PRIVMSG #museun :normal 2\r\n"),
+        ];
+        for (priority, msg) in tests {
+            encoder.enqueue(*priority, msg.as_bytes().into());
+        }
+
+        let mut limit = 100;
+        let mut sink = AsyncEncoder::new(Vec::new());
+        futures_lite::future::block_on(async {
+            encoder
+                .drain_until_blocked("#museun", &mut limit, &mut sink, &NoMetrics, Priority::High)
+                .await
+                .unwrap();
+            sink.flush_batch().await.unwrap();
+            assert_eq!(sink.writer, b"PRIVMSG #museun :/ban someone\r\n");
+
+            encoder
+                .drain_until_blocked("#museun", &mut limit, &mut sink, &NoMetrics, Priority::Low)
+                .await
+                .unwrap();
+            // these are batched together
+            assert_eq!(sink.batched(), 3);
+            sink.flush_batch().await.unwrap();
+        });
+
+        assert_eq!(
+            std::str::from_utf8(&sink.writer).unwrap(),
+            "PRIVMSG #museun :/ban someone\r\n\
+             PRIVMSG #museun :normal 1\r\n\
+             PRIVMSG #museun :normal 2\r\n\
+             PRIVMSG #museun :low\r\n"
+        );
+        assert!(encoder.queue.is_empty());
+    }
+
+    #[test]
+    fn waits_for_slow_mode() {
+        let mut encoder = RateLimitedEncoder {
+            rate_limit: RateLimit::from_class(RateClass::Regular),
+            queue: VecDeque::new(),
+            slow: Some(Duration::from_secs(30)),
+            last_sent: None,
+        };
+        assert!(encoder.next_send().is_none());
+
+        encoder.enqueue(Priority::Normal, b"PRIVMSG #museun :1\r\n"[..].into());
+        encoder.enqueue(Priority::Normal, b"PRIVMSG #museun :2\r\n"[..].into());
+
+        let mut limit = 100;
+        let mut sink = AsyncEncoder::new(Vec::new());
+        futures_lite::future::block_on(async {
+            encoder
+                .drain_until_blocked("#museun", &mut limit, &mut sink, &NoMetrics, Priority::Low)
+                .await
+                .unwrap();
+            sink.flush_batch().await.unwrap();
+        });
+
+        // only the first one was sent, the second one waits for slow mode
+        assert_eq!(sink.writer, b"PRIVMSG #museun :1\r\n");
+        assert_eq!(encoder.queue.len(), 1);
+        let delay = encoder.next_send().unwrap();
+        assert!(delay > Duration::from_secs(29) && delay <= Duration::from_secs(30));
+    }
+}
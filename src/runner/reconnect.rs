@@ -0,0 +1,873 @@
+use super::{
+    async_runner::Handles, AsyncRunner, Error, KeepAlive, Metrics, Middleware, ShutdownHandle,
+    Status,
+};
+use crate::{
+    connector::Connector,
+    decoder::InvalidMessage,
+    messages::Commands,
+    twitch::{ChannelName, TokenProvider, UserConfig},
+    util::{Either::*, FutExt as _, NotifyHandle},
+    writer::{AsyncWriter, MpscWriter},
+};
+
+use futures_lite::{AsyncRead, AsyncWrite};
+use std::{
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+// a connection which drops sooner than this counts as a failed attempt
+const STABLE_CONNECTION: Duration = Duration::from_secs(30);
+
+/// The backoff policy used by [Reconnect] between failed connection attempts.
+///
+/// The delay for attempt `n` is `initial * factor^(n - 1)`, capped at `max`, plus a random amount up to `jitter`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Backoff {
+    initial: Duration,
+    max: Duration,
+    jitter: Duration,
+    factor: u32,
+    max_attempts: Option<usize>,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self {
+            initial: Duration::from_secs(1),
+            max: Duration::from_secs(60),
+            jitter: Duration::from_millis(500),
+            factor: 2,
+            max_attempts: None,
+        }
+    }
+}
+
+impl Backoff {
+    /// Create a new backoff policy with the provided initial and max delays
+    pub fn new(initial: Duration, max: Duration) -> Self {
+        Self {
+            initial,
+            max,
+            ..Self::default()
+        }
+    }
+
+    /// Set the upper bound of the random jitter added to each delay
+    pub fn jitter(mut self, jitter: Duration) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Set the multiplier applied to the delay after each failed attempt
+    pub fn factor(mut self, factor: u32) -> Self {
+        self.factor = factor.max(1);
+        self
+    }
+
+    /// Give up after this many consecutive failed attempts.
+    ///
+    /// By default, this will retry forever.
+    pub fn max_attempts(mut self, attempts: impl Into<Option<usize>>) -> Self {
+        self.max_attempts = attempts.into();
+        self
+    }
+
+    /// Get the delay for this (1-based) attempt
+    pub fn delay(&self, attempt: usize) -> Duration {
+        let exp = attempt.saturating_sub(1).min(u32::MAX as usize) as u32;
+        let delay = self
+            .factor
+            .checked_pow(exp)
+            .and_then(|mul| self.initial.checked_mul(mul))
+            .unwrap_or(self.max)
+            .min(self.max);
+
+        let jitter = self.jitter.as_millis() as u64;
+        if jitter == 0 {
+            return delay;
+        }
+        delay + Duration::from_millis(fastrand::u64(..=jitter))
+    }
+}
+
+/// Status produced by the [Reconnect] loop
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ReconnectStatus {
+    /// A message was produced
+    Message(Commands<'static>),
+    /// A line was read that couldn't be decoded. It was skipped
+    Invalid(InvalidMessage),
+    /// A connection was established, and the previously joined channels were rejoined.
+    ///
+    /// With [Reconnect::set_channels()], they're joined after this instead (see [ReconnectStatus::Reconciled]).
+    Connected,
+    /// The connection was lost. This contains the reason, if there was one.
+    Disconnected(Option<Error>),
+    /// A connection attempt failed. The next attempt will happen after `delay`
+    Reconnecting {
+        /// How many attempts have failed so far
+        attempt: usize,
+        /// How long until the next attempt
+        delay: Duration,
+        /// Why the attempt failed
+        error: Error,
+    },
+    /// The user quit the loop
+    Quit,
+    /// A [ShutdownHandle](super::ShutdownHandle) was triggered.
+    ///
+    /// If this happened while connected, everything queued was sent first.
+    Stopped,
+    /// A channel was joined (or parted) to match the channels given to [Reconnect::set_channels()]
+    Reconciled(Reconciliation),
+}
+
+/// What [Reconnect] did to match the channels given to [Reconnect::set_channels()]
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Reconciliation {
+    /// This channel was joined
+    Joined {
+        /// The channel name
+        channel: String,
+    },
+    /// This channel was parted
+    Parted {
+        /// The channel name
+        channel: String,
+    },
+    /// This channel couldn't be joined
+    JoinFailed {
+        /// The channel name
+        channel: String,
+        /// Why it couldn't be joined
+        error: Error,
+        /// How many times joining it has failed
+        attempt: usize,
+        /// How long until it's tried again (using the [Backoff] policy). This is `None` if [Backoff::max_attempts()] was reached, and the channel was removed from the set
+        retry_in: Option<Duration>,
+    },
+}
+
+/// A wrapper around an [AsyncRunner] which transparently reconnects.
+///
+/// When the connection is lost (or Twitch asks us to `RECONNECT`) this will connect again, re-register with your [UserConfig] and rejoin the channels you were on.
+///
+/// The [writer](Reconnect::writer()) and [quit handle](Reconnect::quit_handle()) stay valid across reconnects.
+///
+/// Failed attempts are retried using the [Backoff] policy, and a connection which drops within 30 seconds counts as a failed attempt.
+/// Twitch rejecting the login (e.g. the token expired) isn't retried: [Reconnect::next_message()] returns that error.
+pub struct Reconnect<C> {
+    connector: C,
+    user_config: UserConfig,
+    backoff: Backoff,
+    keep_alive: KeepAlive,
+    token_provider: Option<Box<dyn TokenProvider>>,
+    metrics: Option<Arc<dyn Metrics>>,
+    middleware: Vec<Arc<Mutex<dyn Middleware>>>,
+    handles: Handles,
+    runner: Option<AsyncRunner>,
+    channels: BTreeSet<String>,
+    // the channels given to set_channels, which are kept in sync with the connection
+    desired: Option<BTreeSet<String>>,
+    // the desired channels that couldn't be joined: how many times they failed, and when to try again
+    retries: BTreeMap<String, (usize, Instant)>,
+    reconcile: bool,
+    statuses: VecDeque<ReconnectStatus>,
+    attempt: usize,
+    delay: Option<Duration>,
+    // when the current connection was established
+    connected_at: Option<Instant>,
+    quit: bool,
+    connected_before: bool,
+}
+
+impl<C> std::fmt::Debug for Reconnect<C> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Reconnect").finish()
+    }
+}
+
+impl<C> Reconnect<C>
+where
+    C: Connector,
+    for<'a> &'a C::Output: AsyncRead + AsyncWrite + Send + Sync + Unpin,
+{
+    /// Create a new reconnecting runner. This doesn't connect until [Reconnect::next_message()] is called.
+    pub fn new(connector: C, user_config: &UserConfig) -> Self {
+        Self {
+            connector,
+            user_config: user_config.clone(),
+            backoff: Backoff::default(),
+            keep_alive: KeepAlive::default(),
+            token_provider: None,
+            metrics: None,
+            middleware: Vec::new(),
+            handles: Handles::new(),
+            runner: None,
+            channels: BTreeSet::new(),
+            desired: None,
+            retries: BTreeMap::new(),
+            reconcile: false,
+            statuses: VecDeque::new(),
+            attempt: 0,
+            delay: None,
+            connected_at: None,
+            quit: false,
+            connected_before: false,
+        }
+    }
+
+    /// Use this backoff policy between failed connection attempts
+    pub fn with_backoff(mut self, backoff: Backoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Use this [KeepAlive] policy for each connection
+    pub fn with_keep_alive(mut self, keep_alive: KeepAlive) -> Self {
+        self.keep_alive = keep_alive;
+        self
+    }
+
+    /// Get the OAuth token from this [TokenProvider] before each connection attempt.
+    ///
+    /// If Twitch rejects the token, it'll be refreshed and the connection is tried again.
+    pub fn with_token_provider<P>(mut self, provider: P) -> Self
+    where
+        P: TokenProvider + 'static,
+    {
+        self.token_provider.replace(Box::new(provider));
+        self
+    }
+
+    /// Report into these [Metrics], across every connection
+    pub fn with_metrics(mut self, metrics: Arc<dyn Metrics>) -> Self {
+        self.metrics.replace(metrics);
+        self
+    }
+
+    /// Add a [Middleware] to every connection. It's kept across reconnects.
+    pub fn with_middleware<M>(mut self, middleware: M) -> Self
+    where
+        M: Middleware + 'static,
+    {
+        self.middleware.push(Arc::new(Mutex::new(middleware)));
+        self
+    }
+
+    /// Get the current runner, if connected
+    pub fn runner(&self) -> Option<&AsyncRunner> {
+        self.runner.as_ref()
+    }
+
+    /// Get the current runner mutably, if connected
+    pub fn runner_mut(&mut self) -> Option<&mut AsyncRunner> {
+        self.runner.as_mut()
+    }
+
+    /// Get a clonable writer you can use. This stays valid across reconnects
+    pub fn writer(&self) -> AsyncWriter<MpscWriter> {
+        self.handles.writer.clone()
+    }
+
+    /// Get a handle that you can trigger a normal 'quit'.
+    pub fn quit_handle(&self) -> NotifyHandle {
+        self.handles.notify_handle.clone()
+    }
+
+    /// Get a handle that you can use to gracefully shut down. This stays valid across reconnects
+    pub fn shutdown_handle(&self) -> ShutdownHandle {
+        self.handles.shutdown_handle.clone()
+    }
+
+    /// Use these credentials for the next connection, e.g. after rotating the OAuth token.
+    ///
+    /// The current connection (and the channels, writer and handles) are kept -- these are used when it reconnects. Call [Reconnect::reconnect_now()] to use them right away.
+    ///
+    /// If a [TokenProvider] is used, its token still replaces the one in `user_config`.
+    pub fn update_credentials(&mut self, user_config: UserConfig) {
+        log::debug!("updated the credentials for '{}'", user_config.name);
+        self.user_config = user_config;
+    }
+
+    /// Drop the current connection, so [Reconnect::next_message()] connects again without waiting.
+    ///
+    /// The channels are rejoined, and the writer and handles stay valid.
+    pub fn reconnect_now(&mut self) {
+        if let Some(runner) = self.runner.take() {
+            self.channels = runner.channel_names().map(ToString::to_string).collect();
+            log::debug!("reconnecting now");
+        }
+        self.connected_at.take();
+        self.delay.take();
+    }
+
+    /// Keep the connection on exactly these channels.
+    ///
+    /// [Reconnect::next_message()] joins the missing channels and parts the extra ones, and does this again after each reconnect. Each change is produced as a [ReconnectStatus::Reconciled].
+    ///
+    /// A channel that can't be joined (e.g. we're banned, or it's suspended) is tried again later, using the [Backoff] policy.
+    pub fn set_channels<I, C2>(&mut self, channels: I)
+    where
+        I: IntoIterator<Item = C2>,
+        C2: Into<ChannelName>,
+    {
+        let desired = channels
+            .into_iter()
+            .map(|channel| String::from(channel.into()))
+            .collect::<BTreeSet<_>>();
+        self.retries.retain(|channel, _| desired.contains(channel));
+        self.desired.replace(desired);
+        self.reconcile = true;
+    }
+
+    /// Join `channel`.
+    ///
+    /// If not connected, this'll be joined once the connection is established.
+    pub async fn join(&mut self, channel: impl Into<ChannelName>) -> Result<(), Error> {
+        let channel: ChannelName = channel.into();
+        if let Some(desired) = &mut self.desired {
+            desired.insert(channel.to_string());
+        }
+        if let Some(runner) = &mut self.runner {
+            runner.join(&channel).await?;
+        }
+        self.channels.insert(channel.into());
+        Ok(())
+    }
+
+    /// Part `channel`.
+    ///
+    /// This channel will no longer be rejoined.
+    pub async fn part(&mut self, channel: impl Into<ChannelName>) -> Result<(), Error> {
+        let channel: ChannelName = channel.into();
+        if let Some(desired) = &mut self.desired {
+            desired.remove(channel.as_str());
+            self.retries.remove(channel.as_str());
+        }
+        self.channels.remove(channel.as_str());
+        match &mut self.runner {
+            Some(runner) => runner.part(&channel).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Get the next message or connection state change. You'll usually want to call this in a loop
+    pub async fn next_message(&mut self) -> Result<ReconnectStatus, Error> {
+        loop {
+            if self.quit {
+                return Ok(ReconnectStatus::Quit);
+            }
+
+            if let Some(status) = self.statuses.pop_front() {
+                return Ok(status);
+            }
+
+            if self.runner.is_none() {
+                return self.reconnect().await;
+            }
+
+            let now = Instant::now();
+            if self.reconcile || self.retries.values().any(|&(_, at)| at <= now) {
+                if let Err(err) = self.reconcile().await {
+                    return Ok(self.disconnected(Some(err)));
+                }
+                continue;
+            }
+
+            // a failed join is retried while waiting for messages
+            let retry = self.retries.values().map(|&(_, at)| at - now).min();
+            let runner = self.runner.as_mut().expect("connected");
+            let status = match retry {
+                Some(delay) => {
+                    match runner
+                        .next_message()
+                        .either(futures_timer::Delay::new(delay))
+                        .await
+                    {
+                        Left(status) => status,
+                        Right(_retry) => continue,
+                    }
+                }
+                None => runner.next_message().await,
+            };
+
+            let error = match status {
+                Ok(Status::Message(msg)) => return Ok(ReconnectStatus::Message(msg)),
+                Ok(Status::Invalid(invalid)) => return Ok(ReconnectStatus::Invalid(invalid)),
+                Ok(Status::Quit) => {
+                    self.quit = true;
+                    return Ok(ReconnectStatus::Quit);
+                }
+                Ok(Status::Stopped) => {
+                    self.quit = true;
+                    return Ok(ReconnectStatus::Stopped);
+                }
+                Ok(Status::Eof) => None,
+                Err(err) => Some(err),
+            };
+
+            return Ok(self.disconnected(error));
+        }
+    }
+
+    fn disconnected(&mut self, error: Option<Error>) -> ReconnectStatus {
+        if let Some(runner) = self.runner.take() {
+            self.channels = runner.channel_names().map(ToString::to_string).collect();
+        }
+
+        match &error {
+            Some(err) => event!(warn, "reconnect", "disconnected: {}", err),
+            None => event!(warn, "reconnect", "disconnected"),
+        }
+
+        match self.connected_at.take() {
+            Some(at) if at.elapsed() >= STABLE_CONNECTION => self.attempt = 0,
+            // it was dropped right away, so back off before connecting again
+            Some(..) => {
+                self.attempt += 1;
+                self.delay.replace(self.backoff.delay(self.attempt));
+            }
+            None => {}
+        }
+
+        ReconnectStatus::Disconnected(error)
+    }
+
+    // joins (and parts) channels to match the desired set
+    async fn reconcile(&mut self) -> Result<(), Error> {
+        self.reconcile = false;
+        let Self {
+            desired,
+            runner,
+            retries,
+            statuses,
+            backoff,
+            ..
+        } = self;
+        let (desired, runner) = match (desired, runner) {
+            (Some(desired), Some(runner)) => (desired, runner),
+            _ => return Ok(()),
+        };
+
+        let extra = runner
+            .channel_names()
+            .filter(|channel| !desired.contains(*channel))
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        for channel in extra {
+            match runner.part(&channel).await {
+                Ok(()) | Err(Error::NotOnChannel { .. }) => {}
+                Err(err) => return Err(err),
+            }
+            log::debug!("parted '{}' to match the channel set", channel);
+            statuses.push_back(ReconnectStatus::Reconciled(Reconciliation::Parted {
+                channel,
+            }));
+        }
+
+        let now = Instant::now();
+        let missing = desired
+            .iter()
+            .filter(|&channel| !runner.is_on_channel(channel.as_str()))
+            .filter(|&channel| retries.get(channel).map_or(true, |&(_, at)| at <= now))
+            .cloned()
+            .collect::<Vec<_>>();
+        for channel in missing {
+            let error = match runner.join(&channel).await {
+                Ok(()) | Err(Error::AlreadyOnChannel { .. }) => {
+                    retries.remove(&channel);
+                    log::debug!("joined '{}' to match the channel set", channel);
+                    statuses.push_back(ReconnectStatus::Reconciled(Reconciliation::Joined {
+                        channel,
+                    }));
+                    continue;
+                }
+                Err(err @ Error::BannedFromChannel { .. })
+                | Err(err @ Error::ChannelUnavailable { .. }) => err,
+                Err(err) => return Err(err),
+            };
+
+            let attempt = retries.get(&channel).map_or(0, |&(attempt, _)| attempt) + 1;
+            let retry_in = match backoff.max_attempts {
+                Some(max) if attempt >= max => {
+                    log::warn!("giving up on joining '{}': {}", channel, error);
+                    retries.remove(&channel);
+                    desired.remove(&channel);
+                    None
+                }
+                _ => {
+                    let delay = backoff.delay(attempt);
+                    log::warn!(
+                        "cannot join '{}': {}. retrying in {:.2?}",
+                        channel,
+                        error,
+                        delay
+                    );
+                    retries.insert(channel.clone(), (attempt, now + delay));
+                    Some(delay)
+                }
+            };
+            statuses.push_back(ReconnectStatus::Reconciled(Reconciliation::JoinFailed {
+                channel,
+                error,
+                attempt,
+                retry_in,
+            }));
+        }
+        Ok(())
+    }
+
+    async fn reconnect(&mut self) -> Result<ReconnectStatus, Error> {
+        if let Some(delay) = self.delay.take() {
+            event!(
+                info,
+                "reconnect",
+                "waiting {:.2?} before reconnecting",
+                delay
+            );
+            let delay = futures_timer::Delay::new(delay);
+            let handles = &mut self.handles;
+            match delay
+                .either(handles.notify.wait().either(handles.shutdown.wait()))
+                .await
+            {
+                Left(_delay) => {}
+                Right(Left(_notified)) => {
+                    self.quit = true;
+                    return Ok(ReconnectStatus::Quit);
+                }
+                Right(Right(_shutdown)) => {
+                    self.quit = true;
+                    return Ok(ReconnectStatus::Stopped);
+                }
+            }
+        }
+
+        let attempt = self.attempt + 1;
+        let error = match in_span!("reconnect", self.try_connect(), attempt).await {
+            Ok(()) => {
+                event!(info, "reconnect", "connected after {} attempt(s)", attempt);
+                self.connected_at.replace(Instant::now());
+                return Ok(ReconnectStatus::Connected);
+            }
+            // retrying with the same credentials won't help
+            Err(err @ Error::AuthenticationFailed) | Err(err @ Error::InvalidToken) => {
+                log::error!("cannot reconnect: {}", err);
+                return Err(err);
+            }
+            Err(err) => err,
+        };
+
+        self.attempt += 1;
+        if let Some(max) = self.backoff.max_attempts {
+            if self.attempt >= max {
+                event!(
+                    error,
+                    "reconnect",
+                    "giving up after {} attempts: {}",
+                    self.attempt,
+                    error
+                );
+                return Err(error);
+            }
+        }
+
+        let delay = self.backoff.delay(self.attempt);
+        log::warn!(
+            "connection attempt {} failed: {}. retrying in {:.2?}",
+            self.attempt,
+            error,
+            delay
+        );
+        self.delay.replace(delay);
+
+        Ok(ReconnectStatus::Reconnecting {
+            attempt: self.attempt,
+            delay,
+            error,
+        })
+    }
+
+    async fn try_connect(&mut self) -> Result<(), Error> {
+        let connector = self.connector.clone();
+        let handles = self.handles.clone();
+        let mut runner = match &mut self.token_provider {
+            Some(provider) => {
+                AsyncRunner::connect_with_handles_and_provider(
+                    connector,
+                    &self.user_config,
+                    handles,
+                    &mut **provider,
+                )
+                .await?
+            }
+            None => {
+                AsyncRunner::connect_with_handles(connector, &self.user_config, handles).await?
+            }
+        };
+
+        runner.set_keep_alive(self.keep_alive);
+        if let Some(metrics) = &self.metrics {
+            if self.connected_before {
+                metrics.reconnected();
+            }
+            runner.set_metrics(Arc::clone(metrics));
+        }
+        for middleware in &self.middleware {
+            runner.add_shared_middleware(Arc::clone(middleware));
+        }
+        self.connected_before = true;
+
+        // the desired channels are joined (and retried) by reconciling
+        if self.desired.is_some() {
+            self.retries.clear();
+            self.reconcile = true;
+            self.runner.replace(runner);
+            return Ok(());
+        }
+
+        let channels = self.channels.iter().cloned().collect::<Vec<_>>();
+        for err in runner.join_many(channels).await? {
+            if let Error::BannedFromChannel { channel } | Error::ChannelUnavailable { channel } =
+                err
+            {
+                log::warn!("cannot rejoin '{}'", channel);
+                self.channels.remove(&channel);
+            }
+        }
+
+        self.runner.replace(runner);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_delay() {
+        let backoff = Backoff::new(Duration::from_secs(1), Duration::from_secs(10))
+            .jitter(Duration::from_secs(0));
+
+        let delays = (1..=6).map(|n| backoff.delay(n)).collect::<Vec<_>>();
+        assert_eq!(
+            delays,
+            [1, 2, 4, 8, 10, 10]
+                .iter()
+                .copied()
+                .map(Duration::from_secs)
+                .collect::<Vec<_>>()
+        );
+
+        // this shouldn't overflow
+        assert_eq!(backoff.delay(usize::MAX), Duration::from_secs(10));
+    }
+
+    #[test]
+    fn backoff_jitter() {
+        let backoff = Backoff::new(Duration::from_secs(1), Duration::from_secs(10))
+            .jitter(Duration::from_millis(100));
+
+        for _ in 0..100 {
+            let delay = backoff.delay(1);
+            assert!(delay >= Duration::from_secs(1));
+            assert!(delay <= Duration::from_millis(1100));
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "testing")]
+    fn authentication_failure_is_fatal() {
+        use crate::test::{user_config, MockServer};
+
+        let server = MockServer::new();
+        let mut reconnect = Reconnect::new(server.clone(), &user_config());
+        futures_lite::future::block_on(async {
+            let rejected = async {
+                let conn = server.accept_raw().await.unwrap();
+                conn.send(":tmi.twitch.tv NOTICE * :Login authentication failed")
+                    .unwrap();
+                conn
+            };
+            let (status, _conn) =
+                futures_lite::future::zip(reconnect.next_message(), rejected).await;
+            assert!(matches!(status, Err(Error::AuthenticationFailed)));
+        });
+    }
+
+    #[test]
+    #[cfg(feature = "testing")]
+    fn dropped_connection_backs_off() {
+        use crate::test::{user_config, MockServer};
+
+        let server = MockServer::new();
+        let backoff = Backoff::new(Duration::from_millis(200), Duration::from_secs(1))
+            .jitter(Duration::from_secs(0));
+        let mut reconnect = Reconnect::new(server.clone(), &user_config()).with_backoff(backoff);
+        futures_lite::future::block_on(async {
+            for _ in 0..2 {
+                let (status, conn) =
+                    futures_lite::future::zip(reconnect.next_message(), server.accept()).await;
+                assert!(matches!(status.unwrap(), ReconnectStatus::Connected));
+
+                // the server drops it right away
+                conn.unwrap().close();
+                while !matches!(
+                    reconnect.next_message().await.unwrap(),
+                    ReconnectStatus::Disconnected(..)
+                ) {}
+
+                let start = Instant::now();
+                let (status, _conn) =
+                    futures_lite::future::zip(reconnect.next_message(), server.accept()).await;
+                assert!(matches!(status.unwrap(), ReconnectStatus::Connected));
+                assert!(start.elapsed() >= Duration::from_millis(200));
+                reconnect.reconnect_now();
+            }
+        });
+    }
+
+    #[test]
+    #[cfg(feature = "testing")]
+    fn updated_credentials() {
+        use crate::{
+            commands,
+            test::{user_config, MockServer},
+        };
+        use futures_lite::future::{block_on, zip};
+
+        let server = MockServer::new();
+        let mut reconnect = Reconnect::new(server.clone(), &user_config());
+        let mut writer = reconnect.writer();
+        block_on(async {
+            let accepted = async {
+                let mut conn = server.accept().await.unwrap();
+                assert_eq!(conn.read_line().await.unwrap(), "JOIN #museun\r\n");
+                conn
+            };
+            let (status, conn) = zip(
+                async {
+                    reconnect.join("museun").await.unwrap();
+                    reconnect.next_message().await.unwrap()
+                },
+                accepted,
+            )
+            .await;
+            assert!(matches!(status, ReconnectStatus::Connected));
+            assert_eq!(conn.password(), format!("oauth:{}", "a".repeat(30)));
+
+            let rotated = UserConfig::builder()
+                .name("shaken_bot")
+                .token(format!("oauth:{}", "b".repeat(30)))
+                .enable_all_capabilities()
+                .build()
+                .unwrap();
+            reconnect.update_credentials(rotated);
+            reconnect.reconnect_now();
+
+            let accepted = async {
+                let mut conn = server.accept().await.unwrap();
+                assert_eq!(conn.read_line().await.unwrap(), "JOIN #museun\r\n");
+                conn
+            };
+            let (status, mut conn) = zip(reconnect.next_message(), accepted).await;
+            assert!(matches!(status.unwrap(), ReconnectStatus::Connected));
+            assert_eq!(conn.password(), format!("oauth:{}", "b".repeat(30)));
+
+            // the writer is still usable
+            writer
+                .encode(commands::privmsg("museun", "hello"))
+                .await
+                .unwrap();
+            let disconnected = async {
+                loop {
+                    if let ReconnectStatus::Disconnected(..) =
+                        reconnect.next_message().await.unwrap()
+                    {
+                        break;
+                    }
+                }
+            };
+            let (_, line) = zip(disconnected, async {
+                let line = conn.read_line().await.unwrap();
+                conn.close();
+                line
+            })
+            .await;
+            assert_eq!(line, "PRIVMSG #museun :hello\r\n");
+        });
+    }
+
+    #[test]
+    #[cfg(feature = "testing")]
+    fn reconciled_channels() {
+        use crate::test::{user_config, MockServer};
+        use futures_lite::future::{block_on, zip};
+
+        async fn reconciled(
+            reconnect: &mut Reconnect<MockServer>,
+            count: usize,
+        ) -> Vec<Reconciliation> {
+            let mut reconciled = vec![];
+            while reconciled.len() < count {
+                if let ReconnectStatus::Reconciled(r) = reconnect.next_message().await.unwrap() {
+                    reconciled.push(r);
+                }
+            }
+            reconciled
+        }
+
+        let server = MockServer::new();
+        let backoff = Backoff::new(Duration::from_millis(10), Duration::from_millis(10))
+            .jitter(Duration::from_millis(0));
+        let mut reconnect = Reconnect::new(server.clone(), &user_config()).with_backoff(backoff);
+        reconnect.set_channels(vec!["museun", "#Shaken_Bot"]);
+
+        block_on(async {
+            let accepted = async {
+                let mut conn = server.accept().await.unwrap();
+                conn.read_line().await.unwrap();
+                conn.read_line().await.unwrap();
+                conn
+            };
+            let (joined, mut conn) = zip(reconciled(&mut reconnect, 2), accepted).await;
+            assert!(matches!(
+                &joined[..],
+                [Reconciliation::Joined { channel: a }, Reconciliation::Joined { channel: b }]
+                    if a == "#museun" && b == "#shaken_bot"
+            ));
+
+            reconnect.set_channels(vec!["museun", "banned"]);
+            conn.send("@msg-id=msg_banned :tmi.twitch.tv NOTICE #banned :You are permanently banned from talking in banned.").unwrap();
+            let lines = async {
+                let part = conn.read_line().await.unwrap();
+                // this JOIN is never answered
+                let join = conn.read_line_raw().await.unwrap();
+                (part, join)
+            };
+            let (changed, (part, join)) = zip(reconciled(&mut reconnect, 2), lines).await;
+            assert_eq!(part, "PART #shaken_bot\r\n");
+            assert_eq!(join, "JOIN #banned\r\n");
+            assert!(matches!(
+                &changed[..],
+                [
+                    Reconciliation::Parted { channel: a },
+                    Reconciliation::JoinFailed { channel: b, error: Error::BannedFromChannel { .. }, attempt: 1, retry_in: Some(..) },
+                ] if a == "#shaken_bot" && b == "#banned"
+            ));
+
+            // it's tried again after the backoff
+            let (retried, _) = zip(reconciled(&mut reconnect, 1), conn.read_line()).await;
+            assert!(
+                matches!(&retried[..], [Reconciliation::Joined { channel }] if channel == "#banned")
+            );
+        });
+    }
+}
@@ -0,0 +1,576 @@
+use super::{Backoff, Dispatcher, Error, EventStream, Reconnect, ReconnectStatus};
+use crate::{
+    channel::{Receiver, Sender},
+    connector::Connector,
+    messages::FromCommands,
+    twitch::{ChannelName, UserConfig},
+    util::{Either::*, FutExt as _},
+    writer::{AsyncWriter, MpscWriter},
+    Encodable,
+};
+
+use futures_lite::{AsyncRead, AsyncWrite};
+use std::{
+    collections::{HashMap, VecDeque},
+    future::Future,
+    io,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::Poll,
+};
+
+type Routes = Arc<Mutex<HashMap<String, usize>>>;
+
+// what a shard's future gives back, including the shard itself
+pub(super) struct Polled<C> {
+    pub(super) reconnect: Box<Reconnect<C>>,
+    pub(super) join_errors: Vec<Error>,
+    // this is None if the shard was woken up to join (or part) channels
+    pub(super) status: Option<Result<ReconnectStatus, Error>>,
+}
+
+type ShardFuture<C> = Pin<Box<dyn Future<Output = Polled<C>> + Send>>;
+
+pub(super) enum State<C> {
+    Idle(Box<Reconnect<C>>),
+    // the shard is owned by its future, so it's never dropped part way through reading (or reconnecting)
+    Running(ShardFuture<C>),
+    Dead,
+}
+
+// this is also used by the MultiClient, which runs a shard for each identity
+pub(super) struct Shard<C> {
+    pub(super) state: State<C>,
+    pub(super) joins: Vec<String>,
+    pub(super) parts: Vec<String>,
+    wake_tx: Sender<()>,
+    wake_rx: Receiver<()>,
+}
+
+/// Status produced by [ShardManager::next_message()]
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ShardStatus {
+    /// This shard produced a status
+    Shard {
+        /// The index of the shard
+        shard: usize,
+        /// What it produced
+        status: ReconnectStatus,
+    },
+    /// This shard couldn't join a channel
+    JoinFailed {
+        /// The index of the shard
+        shard: usize,
+        /// Why it couldn't join
+        error: Error,
+    },
+    /// This shard gave up reconnecting, and its channels were moved to the other shards
+    Died {
+        /// The index of the shard
+        shard: usize,
+        /// Why it gave up
+        error: Error,
+        /// The channels that were moved
+        moved: Vec<String>,
+    },
+    /// Every shard has died or quit
+    Closed,
+}
+
+/// Spreads many channels across several connections, each of which transparently reconnects (see [Reconnect]).
+///
+/// Twitch limits how many channels a single connection should be on, so very large bots should use a few connections.
+///
+/// * [join](ShardManager::join()) adds a channel to the shard with the fewest channels.
+/// * [next_message](ShardManager::next_message()) drives every shard, and produces their messages as they arrive.
+/// * the [writer](ShardManager::writer()) sends each message on the shard that joined its channel.
+///
+/// If a shard gives up reconnecting (see [Backoff::max_attempts()]), its channels are moved to the other shards.
+///
+/// # Example
+/// ```no_run
+/// # use twitchchat::{commands, connector::Connector, runner::{ShardManager, ShardStatus, ReconnectStatus}, messages::Commands, UserConfig};
+/// # async fn demo<C>(connector: C, user_config: &UserConfig, channels: &[&str]) -> std::io::Result<()>
+/// # where
+/// #    C: Connector + 'static,
+/// #    for<'a> &'a C::Output: futures_lite::AsyncRead + futures_lite::AsyncWrite + Send + Sync + Unpin,
+/// # {
+/// let mut manager = ShardManager::new(connector, user_config, 4);
+/// for channel in channels {
+///     manager.join(channel);
+/// }
+///
+/// let mut writer = manager.writer();
+/// loop {
+///     match manager.next_message().await {
+///         ShardStatus::Shard { status: ReconnectStatus::Message(Commands::Privmsg(msg)), .. } => {
+///             if msg.data() == "!hello" {
+///                 writer.send(msg.channel(), commands::privmsg(msg.channel(), "hi")).await?;
+///             }
+///         }
+///         ShardStatus::Closed => break,
+///         _ => {}
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct ShardManager<C> {
+    shards: Vec<Shard<C>>,
+    routes: Routes,
+    writers: Arc<[AsyncWriter<MpscWriter>]>,
+    statuses: VecDeque<ShardStatus>,
+    dispatcher: Dispatcher,
+    // the shard to poll first, so a busy shard doesn't starve the others
+    next: usize,
+}
+
+impl<C> std::fmt::Debug for ShardManager<C> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ShardManager")
+            .field("shards", &self.shards.len())
+            .field("channels", &self.routes.lock().unwrap().len())
+            .finish()
+    }
+}
+
+impl<C> ShardManager<C>
+where
+    C: Connector + 'static,
+    for<'a> &'a C::Output: AsyncRead + AsyncWrite + Send + Sync + Unpin,
+{
+    /// Create a manager with this many shards (at least one). These don't connect until [ShardManager::next_message()] is called.
+    pub fn new(connector: C, user_config: &UserConfig, shards: usize) -> Self {
+        let reconnects = (0..shards.max(1))
+            .map(|_| Reconnect::new(connector.clone(), user_config))
+            .collect::<Vec<_>>();
+
+        let writers = reconnects.iter().map(Reconnect::writer).collect();
+        let shards = reconnects.into_iter().map(Shard::new).collect();
+
+        Self {
+            shards,
+            routes: Routes::default(),
+            writers,
+            statuses: VecDeque::new(),
+            dispatcher: Dispatcher::default(),
+            next: 0,
+        }
+    }
+
+    /// Use this backoff policy for every shard.
+    ///
+    /// This only affects the shards which haven't started yet, so it should be called before [ShardManager::next_message()].
+    pub fn with_backoff(mut self, backoff: Backoff) -> Self {
+        for shard in &mut self.shards {
+            if let State::Idle(reconnect) = std::mem::replace(&mut shard.state, State::Dead) {
+                shard.state = State::Idle(Box::new(reconnect.with_backoff(backoff)));
+            }
+        }
+        self
+    }
+
+    /// How many shards there are, including the dead ones
+    pub fn shard_count(&self) -> usize {
+        self.shards.len()
+    }
+
+    /// The shard that `channel` is on, if it was joined
+    pub fn shard_of(&self, channel: impl Into<ChannelName>) -> Option<usize> {
+        let channel: ChannelName = channel.into();
+        self.routes.lock().unwrap().get(channel.as_str()).copied()
+    }
+
+    /// The channels on this shard
+    pub fn channels(&self, shard: usize) -> Vec<String> {
+        let mut channels = self
+            .routes
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, &s)| s == shard)
+            .map(|(channel, _)| channel.clone())
+            .collect::<Vec<_>>();
+        channels.sort();
+        channels
+    }
+
+    /// Join `channel` on the shard with the fewest channels, returning that shard.
+    ///
+    /// The join happens when [ShardManager::next_message()] is next called. This returns `None` if every shard has died.
+    pub fn join(&mut self, channel: impl Into<ChannelName>) -> Option<usize> {
+        let channel = String::from(channel.into());
+        if let Some(shard) = self.shard_of(&channel) {
+            return Some(shard);
+        }
+
+        let shard = self.least_loaded()?;
+        self.routes.lock().unwrap().insert(channel.clone(), shard);
+        self.shards[shard].joins.push(channel);
+        self.shards[shard].wake();
+        Some(shard)
+    }
+
+    /// Part `channel`, returning the shard it was on.
+    pub fn part(&mut self, channel: impl Into<ChannelName>) -> Option<usize> {
+        let channel = String::from(channel.into());
+        let shard = self.routes.lock().unwrap().remove(&channel)?;
+
+        let joins = &mut self.shards[shard].joins;
+        match joins.iter().position(|ch| *ch == channel) {
+            // it was never joined
+            Some(pos) => drop(joins.remove(pos)),
+            None => {
+                self.shards[shard].parts.push(channel);
+                self.shards[shard].wake();
+            }
+        }
+        Some(shard)
+    }
+
+    /// Get a writer which sends each message on the shard that joined its channel. This stays valid across reconnects (and rebalancing)
+    pub fn writer(&self) -> ShardWriter {
+        ShardWriter {
+            routes: Arc::clone(&self.routes),
+            writers: Arc::clone(&self.writers),
+        }
+    }
+
+    /// Subscribe to a specific message type, from every shard.
+    ///
+    /// Like [AsyncRunner::events()](super::AsyncRunner::events()), you must still drive the manager for these to produce messages.
+    pub fn events<T>(&mut self) -> EventStream<T>
+    where
+        T: FromCommands<'static> + Send + Sync + 'static,
+    {
+        self.dispatcher.subscribe()
+    }
+
+    /// Get the next message (or state change) from any of the shards. You'll usually want to call this in a loop
+    pub async fn next_message(&mut self) -> ShardStatus {
+        loop {
+            if let Some(status) = self.statuses.pop_front() {
+                return status;
+            }
+
+            for shard in &mut self.shards {
+                if let State::Idle(..) = shard.state {
+                    shard.start();
+                }
+            }
+
+            if self
+                .shards
+                .iter()
+                .all(|shard| matches!(shard.state, State::Dead))
+            {
+                return ShardStatus::Closed;
+            }
+
+            let (index, polled) = self.poll_shards().await;
+            self.handle(index, polled);
+        }
+    }
+
+    async fn poll_shards(&mut self) -> (usize, Polled<C>) {
+        let shards = &mut self.shards;
+        let next = &mut self.next;
+        futures_lite::future::poll_fn(move |cx| {
+            let len = shards.len();
+            for index in (0..len).map(|i| (*next + i) % len) {
+                if let State::Running(fut) = &mut shards[index].state {
+                    if let Poll::Ready(polled) = fut.as_mut().poll(cx) {
+                        *next = (index + 1) % len;
+                        return Poll::Ready((index, polled));
+                    }
+                }
+            }
+            Poll::Pending
+        })
+        .await
+    }
+
+    fn handle(&mut self, shard: usize, polled: Polled<C>) {
+        let Polled {
+            reconnect,
+            join_errors,
+            status,
+        } = polled;
+
+        for error in join_errors {
+            if let Error::BannedFromChannel { channel } | Error::ChannelUnavailable { channel } =
+                &error
+            {
+                self.routes.lock().unwrap().remove(channel);
+            }
+            self.statuses
+                .push_back(ShardStatus::JoinFailed { shard, error });
+        }
+
+        let status = match status {
+            Some(Ok(status)) => status,
+            None => {
+                self.shards[shard].state = State::Idle(reconnect);
+                return;
+            }
+            Some(Err(error)) => {
+                log::warn!("shard {} died: {}", shard, error);
+                self.shards[shard].state = State::Dead;
+                let moved = self.rebalance(shard);
+                self.statuses.push_back(ShardStatus::Died {
+                    shard,
+                    error,
+                    moved,
+                });
+                return;
+            }
+        };
+
+        self.shards[shard].state = match status {
+            ReconnectStatus::Quit | ReconnectStatus::Stopped => State::Dead,
+            _ => State::Idle(reconnect),
+        };
+
+        if let ReconnectStatus::Message(msg) = &status {
+            self.dispatcher.dispatch(msg);
+        }
+        self.statuses
+            .push_back(ShardStatus::Shard { shard, status });
+    }
+
+    // moves the channels of a dead shard to the live ones
+    fn rebalance(&mut self, dead: usize) -> Vec<String> {
+        let channels = self.channels(dead);
+        self.shards[dead].joins.clear();
+        self.shards[dead].parts.clear();
+
+        let mut moved = Vec::with_capacity(channels.len());
+        for channel in channels {
+            self.routes.lock().unwrap().remove(&channel);
+            if let Some(shard) = self.join(&channel) {
+                log::debug!("moved '{}' from shard {} to shard {}", channel, dead, shard);
+                moved.push(channel);
+            }
+        }
+        moved
+    }
+
+    fn least_loaded(&self) -> Option<usize> {
+        let routes = self.routes.lock().unwrap();
+        let mut counts = vec![0_usize; self.shards.len()];
+        for &shard in routes.values() {
+            counts[shard] += 1;
+        }
+
+        counts
+            .into_iter()
+            .enumerate()
+            .filter(|&(index, _)| !matches!(self.shards[index].state, State::Dead))
+            .min_by_key(|&(_, count)| count)
+            .map(|(index, _)| index)
+    }
+}
+
+impl<C> Shard<C>
+where
+    C: Connector + 'static,
+    for<'a> &'a C::Output: AsyncRead + AsyncWrite + Send + Sync + Unpin,
+{
+    pub(super) fn new(reconnect: Reconnect<C>) -> Self {
+        let (wake_tx, wake_rx) = crate::channel::bounded(1);
+        Self {
+            state: State::Idle(Box::new(reconnect)),
+            joins: Vec::new(),
+            parts: Vec::new(),
+            wake_tx,
+            wake_rx,
+        }
+    }
+
+    pub(super) fn start(&mut self) {
+        let mut reconnect = match std::mem::replace(&mut self.state, State::Dead) {
+            State::Idle(reconnect) => reconnect,
+            state => {
+                self.state = state;
+                return;
+            }
+        };
+
+        let joins = std::mem::take(&mut self.joins);
+        let parts = std::mem::take(&mut self.parts);
+        let wake_rx = self.wake_rx.clone();
+        // anything queued before now is handled below
+        while wake_rx.try_recv().is_some() {}
+
+        self.state = State::Running(Box::pin(async move {
+            let mut join_errors = Vec::new();
+            for channel in parts {
+                if let Err(err) = reconnect.part(&channel).await {
+                    log::warn!("cannot part '{}': {}", channel, err);
+                }
+            }
+            for channel in joins {
+                if let Err(err) = reconnect.join(&channel).await {
+                    join_errors.push(err);
+                }
+            }
+
+            // only a connected shard is interrupted, so a reconnect (and its backoff) isn't restarted
+            let status = if reconnect.runner().is_some() {
+                match reconnect.next_message().either(wake_rx.recv()).await {
+                    Left(status) => Some(status),
+                    Right(..) => None,
+                }
+            } else {
+                Some(reconnect.next_message().await)
+            };
+
+            Polled {
+                reconnect,
+                join_errors,
+                status,
+            }
+        }));
+    }
+}
+
+impl<C> Shard<C> {
+    // a running shard joins (and parts) channels after it produces its next message, so this interrupts it
+    pub(super) fn wake(&self) {
+        let _ = self.wake_tx.try_send(());
+    }
+}
+
+/// A writer which sends each message on the shard of a [ShardManager] that joined its channel.
+#[derive(Clone)]
+pub struct ShardWriter {
+    routes: Routes,
+    writers: Arc<[AsyncWriter<MpscWriter>]>,
+}
+
+impl std::fmt::Debug for ShardWriter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ShardWriter").finish()
+    }
+}
+
+impl ShardWriter {
+    /// Get the writer of the shard that joined `channel`
+    pub fn writer_for(&self, channel: impl Into<ChannelName>) -> Option<AsyncWriter<MpscWriter>> {
+        let channel: ChannelName = channel.into();
+        let shard = *self.routes.lock().unwrap().get(channel.as_str())?;
+        self.writers.get(shard).cloned()
+    }
+
+    /// Encode this message on the shard that joined `channel`.
+    ///
+    /// This returns an error with the `NotFound` kind if `channel` wasn't joined.
+    pub async fn send<M>(&mut self, channel: impl Into<ChannelName>, msg: M) -> io::Result<()>
+    where
+        M: Encodable + Send + Sync,
+    {
+        let channel: ChannelName = channel.into();
+        let mut writer = self.writer_for(&channel).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("'{}' wasn't joined by any shard", channel),
+            )
+        })?;
+        writer.encode(msg).await
+    }
+}
+
+#[cfg(all(test, feature = "testing"))]
+mod tests {
+    use super::*;
+    use crate::{
+        commands,
+        messages::Commands,
+        test::{user_config, MockServer},
+    };
+    use futures_lite::future::{block_on, zip};
+    use std::io::ErrorKind;
+
+    #[test]
+    fn sharded_channels() {
+        let server = MockServer::new();
+        let mut manager = ShardManager::new(server.clone(), &user_config(), 2);
+        assert_eq!(manager.join("museun"), Some(0));
+        assert_eq!(manager.join("#shaken_bot"), Some(1));
+        assert_eq!(manager.join("justinfan1234"), Some(0));
+        assert_eq!(manager.join("museun"), Some(0));
+        assert_eq!(manager.shard_of("#shaken_bot"), Some(1));
+        assert_eq!(manager.channels(0), ["#justinfan1234", "#museun"]);
+
+        let mut writer = manager.writer();
+        block_on(async {
+            let connected = async {
+                let mut connected = 0;
+                while connected < 2 {
+                    if let ShardStatus::Shard {
+                        status: ReconnectStatus::Connected,
+                        ..
+                    } = manager.next_message().await
+                    {
+                        connected += 1;
+                    }
+                }
+            };
+            let accepted = async {
+                let mut conns = vec![None, None];
+                for _ in 0..2 {
+                    let mut conn = server.accept().await.unwrap();
+                    // the connection for the second shard only joins one channel
+                    let shard = match conn.read_line().await.unwrap().as_str() {
+                        "JOIN #shaken_bot\r\n" => 1,
+                        _ => {
+                            conn.read_line().await.unwrap();
+                            0
+                        }
+                    };
+                    conns[shard] = Some(conn);
+                }
+                conns
+            };
+            let (_, conns) = zip(connected, accepted).await;
+            let mut conn = conns.into_iter().nth(1).flatten().unwrap();
+
+            writer
+                .send("shaken_bot", commands::privmsg("shaken_bot", "hello"))
+                .await
+                .unwrap();
+            let sent = async {
+                let line = loop {
+                    let line = conn.read_line().await.unwrap();
+                    if line.starts_with("PRIVMSG") {
+                        break line;
+                    }
+                };
+                conn.send(":museun!museun@museun.tmi.twitch.tv PRIVMSG #shaken_bot :hi")
+                    .unwrap();
+                line
+            };
+            let received = async {
+                loop {
+                    if let ShardStatus::Shard {
+                        shard,
+                        status: ReconnectStatus::Message(Commands::Privmsg(msg)),
+                    } = manager.next_message().await
+                    {
+                        break (shard, msg);
+                    }
+                }
+            };
+            let (line, (shard, msg)) = zip(sent, received).await;
+            assert_eq!(line, "PRIVMSG #shaken_bot :hello\r\n");
+            assert_eq!((shard, msg.data()), (1, "hi"));
+
+            let err = writer
+                .send("#not_joined", commands::privmsg("not_joined", "hello"))
+                .await
+                .unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::NotFound);
+        });
+    }
+}
@@ -0,0 +1,34 @@
+use crate::{
+    util::NotifyHandle,
+    writer::{AsyncWriter, MpscWriter},
+};
+
+/// A handle for gracefully shutting down a runner.
+///
+/// Unlike the [quit handle](super::AsyncRunner::quit_handle()), this guarantees everything that was already written is sent:
+/// 1. the [writer](super::AsyncRunner::writer()) stops accepting new messages (they'll return a [ShutDown](crate::writer::ShutDown) error)
+/// 1. everything still queued is flushed, respecting the rate limits
+/// 1. a `QUIT` is sent
+/// 1. the runner produces [Status::Stopped](super::Status::Stopped)
+///
+/// This can be cloned and triggered from anywhere, only the first trigger has an effect.
+#[derive(Clone, Debug)]
+pub struct ShutdownHandle {
+    pub(crate) writer: AsyncWriter<MpscWriter>,
+    pub(crate) notify: NotifyHandle,
+}
+
+impl ShutdownHandle {
+    /// Request a graceful shutdown.
+    ///
+    /// Returns false if the runner wasn't around any more
+    pub fn shutdown(&self) -> bool {
+        self.writer.set_shut_down();
+        self.notify.try_notify()
+    }
+
+    /// Has a shutdown been requested?
+    pub fn is_shut_down(&self) -> bool {
+        self.writer.is_shut_down()
+    }
+}
@@ -0,0 +1,288 @@
+use super::{async_runner::SharedEncoder, AsyncRunner, Error, Metrics, Status};
+use crate::{
+    rate_limit::RateLimit,
+    writer::{AsyncWriter, MpscWriter, Priority, RateLimited},
+    Encodable,
+};
+
+use futures_lite::Stream;
+use std::{
+    borrow::Cow,
+    io,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+};
+
+/// The reading half of a split [AsyncRunner], from [AsyncRunner::split()].
+///
+/// This drives the connection: it reads the messages, and replies to Twitch's `PING`s (and sends its own).
+/// It still drains the queue of the [AsyncWriter]s from [AsyncRunner::writer()], but the [Writer] doesn't need it to be driven.
+pub struct Reader {
+    runner: AsyncRunner,
+}
+
+impl std::fmt::Debug for Reader {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Reader").finish()
+    }
+}
+
+impl Reader {
+    pub(crate) fn new(runner: AsyncRunner) -> Self {
+        Self { runner }
+    }
+
+    /// Get the next message. You'll usually want to call this in a loop
+    pub async fn next_message(&mut self) -> Result<Status<'static>, Error> {
+        self.runner.next_message().await
+    }
+
+    /// Get the runner, e.g. to [join](AsyncRunner::join()) a channel or to subscribe to its [events](AsyncRunner::events())
+    pub fn runner(&self) -> &AsyncRunner {
+        &self.runner
+    }
+
+    /// Get the runner mutably
+    pub fn runner_mut(&mut self) -> &mut AsyncRunner {
+        &mut self.runner
+    }
+
+    /// Get the runner back. The [Writer] keeps working.
+    pub fn into_inner(self) -> AsyncRunner {
+        self.runner
+    }
+}
+
+impl Stream for Reader {
+    type Item = <AsyncRunner as Stream>::Item;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.get_mut().runner).poll_next(cx)
+    }
+}
+
+/// The writing half of a split [AsyncRunner], from [AsyncRunner::split()].
+///
+/// This writes directly to the connection, so it doesn't need the [Reader] to be driven -- it can be used from another task or thread, and it keeps working after the [Reader] is dropped.
+/// It waits for the global rate limit it shares with the [Reader] before each message.
+///
+/// Unlike the [AsyncWriter], this doesn't queue messages per channel, so it doesn't know about slow mode, duplicates or priorities.
+#[derive(Clone)]
+pub struct Writer {
+    encoder: SharedEncoder,
+    rate_limit: Arc<Mutex<RateLimit>>,
+    // for the read-only and shut down flags, which are shared with the reader
+    writer: AsyncWriter<MpscWriter>,
+    metrics: Arc<dyn Metrics>,
+}
+
+impl std::fmt::Debug for Writer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Writer").finish()
+    }
+}
+
+impl Writer {
+    pub(crate) fn new(
+        encoder: SharedEncoder,
+        rate_limit: Arc<Mutex<RateLimit>>,
+        writer: AsyncWriter<MpscWriter>,
+        metrics: Arc<dyn Metrics>,
+    ) -> Self {
+        Self {
+            encoder,
+            rate_limit,
+            writer,
+            metrics,
+        }
+    }
+
+    /// Is this writer read-only? (e.g. the connection is anonymous)
+    pub fn is_read_only(&self) -> bool {
+        self.writer.is_read_only()
+    }
+
+    /// Encode this message, and write it to the connection once the rate limit allows it.
+    ///
+    /// This returns a [ReadOnly](crate::writer::ReadOnly) error on an anonymous connection,
+    /// and a [ShutDown](crate::writer::ShutDown) error after a [ShutdownHandle](super::ShutdownHandle) was triggered.
+    pub async fn encode<M>(&mut self, msg: M) -> io::Result<()>
+    where
+        M: Encodable + Send + Sync,
+    {
+        let (data, lines) = self.prepare(msg)?;
+        loop {
+            let consumed = self.rate_limit.lock().unwrap().consume(lines);
+            match consumed {
+                Ok(..) => break,
+                Err(delay) => {
+                    event!(warn, "rate_limit", "global rate limit hit while writing");
+                    self.metrics.throttled(delay);
+                    futures_timer::Delay::new(delay).await;
+                }
+            }
+        }
+        self.write(&data).await
+    }
+
+    /// Encode this message, and write it to the connection if the rate limit allows it right now.
+    ///
+    /// Unlike [Writer::encode()], this doesn't wait for the rate limit: it returns a [RateLimited](crate::writer::RateLimited) error, with how long to wait.
+    pub async fn try_encode<M>(&mut self, msg: M) -> io::Result<()>
+    where
+        M: Encodable + Send + Sync,
+    {
+        let (data, lines) = self.prepare(msg)?;
+        let consumed = self.rate_limit.lock().unwrap().consume(lines);
+        if let Err(retry_after) = consumed {
+            self.metrics.throttled(retry_after);
+            return Err(io::Error::new(
+                io::ErrorKind::WouldBlock,
+                RateLimited { retry_after },
+            ));
+        }
+        self.write(&data).await
+    }
+
+    // encodes the message, and counts how many tokens it costs
+    fn prepare<M>(&self, msg: M) -> io::Result<(Vec<u8>, u64)>
+    where
+        M: Encodable + Send + Sync,
+    {
+        self.writer.check_writable()?;
+
+        let mut data = Vec::new();
+        msg.encode(&mut data)?;
+        // this doesn't queue by priority
+        if let Cow::Owned(stripped) = Priority::strip(&data) {
+            data = stripped;
+        }
+
+        // each line costs a token
+        let lines = data.iter().filter(|&&c| c == b'\n').count().max(1) as u64;
+        Ok((data, lines))
+    }
+
+    async fn write(&self, data: &[u8]) -> io::Result<()> {
+        self.metrics.bytes_written(data.len());
+        self.encoder.lock().await.encode_bytes(data).await
+    }
+}
+
+#[cfg(all(test, feature = "testing"))]
+mod tests {
+    use crate::{
+        commands, messages::Commands, runner::AsyncRunner, test::MockServer, Status, UserConfig,
+    };
+    use futures_lite::future::{block_on, zip};
+
+    #[test]
+    fn writer_without_the_reader() {
+        let server = MockServer::new();
+        let user_config = UserConfig::builder()
+            .name("museun")
+            .token(format!("oauth:{}", "a".repeat(30)))
+            .build()
+            .unwrap();
+
+        block_on(async {
+            let (runner, conn) = zip(
+                AsyncRunner::connect(server.clone(), &user_config),
+                server.accept(),
+            )
+            .await;
+            let (runner, mut conn) = (runner.unwrap(), conn.unwrap());
+            let (mut reader, mut writer) = runner.split();
+
+            // the reader isn't driven for this
+            writer
+                .encode(commands::privmsg("#museun", "hello"))
+                .await
+                .unwrap();
+            assert_eq!(
+                conn.read_line().await.unwrap(),
+                "PRIVMSG #museun :hello\r\n"
+            );
+
+            // but it still replies to PINGs
+            conn.send("PING :1234").unwrap();
+            // skip the messages from the handshake
+            while !matches!(
+                reader.next_message().await.unwrap(),
+                Status::Message(Commands::Ping(..))
+            ) {}
+            assert_eq!(conn.read_line().await.unwrap(), "PONG :1234\r\n");
+
+            // and the writer outlives it
+            drop(reader);
+            writer
+                .encode(commands::privmsg("#museun", "world"))
+                .await
+                .unwrap();
+            assert_eq!(
+                conn.read_line().await.unwrap(),
+                "PRIVMSG #museun :world\r\n"
+            );
+        });
+    }
+
+    #[test]
+    fn anonymous_writer_is_read_only() {
+        let server = MockServer::new();
+        let user_config = UserConfig::builder().anonymous().build().unwrap();
+
+        block_on(async {
+            let (runner, conn) = zip(
+                AsyncRunner::connect(server.clone(), &user_config),
+                server.accept(),
+            )
+            .await;
+            let _conn = conn.unwrap();
+            let (_reader, mut writer) = runner.unwrap().split();
+
+            assert!(writer.is_read_only());
+            let err = writer
+                .encode(commands::privmsg("#museun", "hello"))
+                .await
+                .unwrap_err();
+            assert!(crate::writer::ReadOnly::is_read_only(&err));
+        });
+    }
+
+    #[test]
+    fn try_encode_is_rate_limited() {
+        let server = MockServer::new();
+        let user_config = UserConfig::builder()
+            .name("museun")
+            .token(format!("oauth:{}", "a".repeat(30)))
+            .build()
+            .unwrap();
+
+        block_on(async {
+            let (runner, conn) = zip(
+                AsyncRunner::connect(server.clone(), &user_config),
+                server.accept(),
+            )
+            .await;
+            let _conn = conn.unwrap();
+            let (_reader, mut writer) = runner.unwrap().split();
+
+            // it doesn't wait for the rate limit, so this runs out
+            let err = loop {
+                if let Err(err) = writer
+                    .try_encode(commands::privmsg("#museun", "hello"))
+                    .await
+                {
+                    break err;
+                }
+            };
+            let limited = crate::writer::RateLimited::of(&err).unwrap();
+            assert!(limited.retry_after > std::time::Duration::from_secs(0));
+            assert!(matches!(
+                crate::Error::from(err),
+                crate::Error::RateLimited { .. }
+            ));
+        });
+    }
+}
@@ -1,4 +1,4 @@
-use crate::messages::Commands;
+use crate::{decoder::InvalidMessage, messages::Commands};
 
 /// Result of a single step of the loop
 #[derive(Debug)]
@@ -11,11 +11,16 @@ pub enum StepResult<'a> {
 
 /// Status produced by the loop
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum Status<'a> {
     /// A message was produced
     Message(Commands<'a>),
+    /// A line was read that couldn't be decoded. It was skipped, and the loop carries on
+    Invalid(InvalidMessage),
     /// The user quit the loop
     Quit,
+    /// A [ShutdownHandle](super::ShutdownHandle) was triggered, and everything queued was sent
+    Stopped,
     /// Loop run to completion
     Eof,
 }
@@ -15,14 +15,137 @@ impl TimeoutState {
     pub fn waiting_for_pong() -> Self {
         Self::WaitingForPong(Instant::now())
     }
+
+    // only a PONG ends the wait for one
+    pub fn mark_activity(&mut self) {
+        if !matches!(self, Self::WaitingForPong(..)) {
+            *self = Self::activity();
+        }
+    }
 }
 
 pub const WINDOW: Duration = Duration::from_secs(45);
 pub const TIMEOUT: Duration = Duration::from_secs(10);
 pub const RATE_LIMIT_WINDOW: Duration = Duration::from_secs(30);
+pub const JOIN_RETRIES: usize = 3;
+pub const JOIN_RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// How the [AsyncRunner](super::AsyncRunner) keeps the connection alive.
+///
+/// The runner sends a `PING` every `interval`, and if no `PONG` arrives within `timeout` the connection is considered dead and [Error::TimedOut](super::Error::TimedOut) is returned.
+///
+/// By default, this pings every 45 seconds, waits 10 seconds for the `PONG` and responds to the server's `PING`s.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct KeepAlive {
+    interval: Duration,
+    timeout: Duration,
+    auto_pong: bool,
+}
+
+impl Default for KeepAlive {
+    fn default() -> Self {
+        Self {
+            interval: WINDOW,
+            timeout: TIMEOUT,
+            auto_pong: true,
+        }
+    }
+}
+
+impl KeepAlive {
+    /// Set how often a `PING` is sent
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Set how long to wait for a `PONG` before timing out
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Set whether the server's `PING` should be responded to automatically.
+    ///
+    /// If you disable this, you must send the `PONG` yourself or Twitch will disconnect you.
+    pub fn auto_pong(mut self, auto_pong: bool) -> Self {
+        self.auto_pong = auto_pong;
+        self
+    }
+
+    /// How often a `PING` is sent
+    pub fn get_interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// How long to wait for a `PONG` before timing out
+    pub fn get_timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Whether the server's `PING` is responded to automatically
+    pub fn get_auto_pong(&self) -> bool {
+        self.auto_pong
+    }
+
+    // how long until the next check should happen
+    pub(crate) fn next_check(&self, state: TimeoutState, last_ping: Instant) -> Duration {
+        match state {
+            TimeoutState::WaitingForPong(dt) => self.timeout.checked_sub(dt.elapsed()),
+            _ => self.interval.checked_sub(last_ping.elapsed()),
+        }
+        .unwrap_or_default()
+    }
+}
 
 cfg_async! {
-    pub async fn next_delay() {
-        futures_timer::Delay::new(WINDOW).await
+    pub async fn next_delay(delay: Duration) {
+        futures_timer::Delay::new(delay).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keep_alive_next_check() {
+        let keep_alive = KeepAlive::default()
+            .interval(Duration::from_secs(60))
+            .timeout(Duration::from_secs(5));
+
+        let now = Instant::now();
+        let next = keep_alive.next_check(TimeoutState::Start, now);
+        assert!(next <= Duration::from_secs(60) && next > Duration::from_secs(55));
+
+        let next = keep_alive.next_check(TimeoutState::WaitingForPong(now), now);
+        assert!(next <= Duration::from_secs(5));
+
+        let old = now - Duration::from_secs(120);
+        let next = keep_alive.next_check(TimeoutState::activity(), old);
+        assert_eq!(next, Duration::from_secs(0));
+    }
+
+    #[test]
+    #[cfg(feature = "testing")]
+    fn login_timeout() {
+        use crate::{runner::Error as RunnerError, test::MockServer, AsyncRunner, UserConfig};
+        use futures_lite::future::{block_on, zip};
+
+        let server = MockServer::new();
+        let user_config = UserConfig::builder()
+            .anonymous()
+            .login_timeout(Duration::from_millis(50))
+            .build()
+            .unwrap();
+        block_on(async {
+            // the server never finishes the login
+            let (runner, _conn) = zip(
+                AsyncRunner::connect(server.clone(), &user_config),
+                server.accept_raw(),
+            )
+            .await;
+            assert!(matches!(runner, Err(RunnerError::LoginTimedOut)));
+        });
     }
 }
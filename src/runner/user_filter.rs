@@ -0,0 +1,220 @@
+use super::{Flow, Middleware};
+use crate::messages::Commands;
+
+use std::{
+    collections::HashSet,
+    sync::{Arc, Mutex},
+};
+
+/// Whether a [UserFilter] drops the users on its list, or everyone else
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FilterMode {
+    /// Drop messages from the users on the list
+    Ignore,
+    /// Only pass messages from the users on the list
+    Allow,
+}
+
+#[derive(Debug)]
+struct Lists {
+    mode: FilterMode,
+    logins: HashSet<String>,
+    user_ids: HashSet<u64>,
+}
+
+impl Lists {
+    fn contains(&self, login: Option<&str>, user_id: Option<u64>) -> bool {
+        login.map_or(false, |login| {
+            self.logins.contains(&login.to_ascii_lowercase())
+        }) || user_id.map_or(false, |id| self.user_ids.contains(&id))
+    }
+}
+
+/// A [Middleware] which drops messages from ignored users (e.g. other bots), or from everyone who isn't allowed.
+///
+/// Users are matched by their login or by their user-id. Messages which aren't from a user (e.g. `PING` or `ROOMSTATE`) are always passed on.
+///
+/// The lists can be changed while the runner is running, with a [UserFilterHandle].
+///
+/// # Example
+/// ```no_run
+/// # use twitchchat::runner::UserFilter;
+/// # fn demo(runner: &mut twitchchat::AsyncRunner) {
+/// let filter = UserFilter::ignore()
+///     .login("nightbot")
+///     .login("streamelements");
+/// let handle = filter.handle();
+/// runner.add_middleware(filter);
+///
+/// // later on
+/// handle.add_login("moobot");
+/// # }
+/// ```
+pub struct UserFilter {
+    lists: Arc<Mutex<Lists>>,
+}
+
+impl std::fmt::Debug for UserFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UserFilter").finish()
+    }
+}
+
+impl UserFilter {
+    /// Create a filter with this mode, and empty lists
+    pub fn new(mode: FilterMode) -> Self {
+        Self {
+            lists: Arc::new(Mutex::new(Lists {
+                mode,
+                logins: HashSet::new(),
+                user_ids: HashSet::new(),
+            })),
+        }
+    }
+
+    /// Create a filter which drops messages from the users on its list
+    pub fn ignore() -> Self {
+        Self::new(FilterMode::Ignore)
+    }
+
+    /// Create a filter which only passes messages from the users on its list
+    pub fn allow() -> Self {
+        Self::new(FilterMode::Allow)
+    }
+
+    /// Add this login to the list
+    pub fn login(self, login: impl Into<String>) -> Self {
+        self.handle().add_login(login);
+        self
+    }
+
+    /// Add this user-id to the list
+    pub fn user_id(self, user_id: u64) -> Self {
+        self.handle().add_user_id(user_id);
+        self
+    }
+
+    /// Get a handle for changing the lists while this filter is running
+    pub fn handle(&self) -> UserFilterHandle {
+        UserFilterHandle {
+            lists: Arc::clone(&self.lists),
+        }
+    }
+}
+
+impl Middleware for UserFilter {
+    fn on_message(&mut self, msg: &mut Commands<'static>) -> Flow {
+        let (login, user_id) = match msg {
+            Commands::Privmsg(msg) => (Some(msg.name()), msg.user_id()),
+            Commands::Whisper(msg) => (Some(msg.name()), msg.user_id()),
+            Commands::UserNotice(msg) => (msg.login(), msg.user_id()),
+            Commands::Join(msg) => (Some(msg.name()), None),
+            Commands::Part(msg) => (Some(msg.name()), None),
+            _ => return Flow::Continue,
+        };
+
+        let lists = self.lists.lock().unwrap();
+        let listed = lists.contains(login, user_id);
+        match (lists.mode, listed) {
+            (FilterMode::Ignore, true) | (FilterMode::Allow, false) => Flow::Drop,
+            _ => Flow::Continue,
+        }
+    }
+}
+
+/// A handle for changing the lists of a [UserFilter] while it's running
+#[derive(Clone)]
+pub struct UserFilterHandle {
+    lists: Arc<Mutex<Lists>>,
+}
+
+impl std::fmt::Debug for UserFilterHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UserFilterHandle").finish()
+    }
+}
+
+impl UserFilterHandle {
+    /// Add this login to the list
+    pub fn add_login(&self, login: impl Into<String>) {
+        let login = login.into().to_ascii_lowercase();
+        self.lists.lock().unwrap().logins.insert(login);
+    }
+
+    /// Remove this login from the list, returning whether it was on it
+    pub fn remove_login(&self, login: &str) -> bool {
+        let login = login.to_ascii_lowercase();
+        self.lists.lock().unwrap().logins.remove(&login)
+    }
+
+    /// Add this user-id to the list
+    pub fn add_user_id(&self, user_id: u64) {
+        self.lists.lock().unwrap().user_ids.insert(user_id);
+    }
+
+    /// Remove this user-id from the list, returning whether it was on it
+    pub fn remove_user_id(&self, user_id: u64) -> bool {
+        self.lists.lock().unwrap().user_ids.remove(&user_id)
+    }
+
+    /// Whether this login (or user-id) is on the list
+    pub fn contains(&self, login: Option<&str>, user_id: Option<u64>) -> bool {
+        self.lists.lock().unwrap().contains(login, user_id)
+    }
+
+    /// The current mode
+    pub fn mode(&self) -> FilterMode {
+        self.lists.lock().unwrap().mode
+    }
+
+    /// Change the mode. The lists are kept
+    pub fn set_mode(&self, mode: FilterMode) {
+        self.lists.lock().unwrap().mode = mode;
+    }
+
+    /// Empty the lists
+    pub fn clear(&self) {
+        let mut lists = self.lists.lock().unwrap();
+        lists.logins.clear();
+        lists.user_ids.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FromIrcMessage as _, IntoOwned as _};
+
+    fn parse(input: &str) -> Commands<'static> {
+        let msg = crate::irc::parse_one(input).unwrap().1;
+        Commands::from_irc(msg).unwrap().into_owned()
+    }
+
+    #[test]
+    fn lists() {
+        let mut filter = UserFilter::ignore().login("NightBot").user_id(1234);
+        let handle = filter.handle();
+
+        let mut bot = parse(":nightbot!nightbot@nightbot PRIVMSG #museun :hello\r\n");
+        let mut renamed = parse("@user-id=1234 :somebot!somebot@somebot PRIVMSG #museun :hi\r\n");
+        let mut user = parse("@user-id=42 :museun!museun@museun PRIVMSG #museun :hi\r\n");
+        let mut ping = parse("PING :1234\r\n");
+
+        assert_eq!(filter.on_message(&mut bot), Flow::Drop);
+        assert_eq!(filter.on_message(&mut renamed), Flow::Drop);
+        assert_eq!(filter.on_message(&mut user), Flow::Continue);
+        assert_eq!(filter.on_message(&mut ping), Flow::Continue);
+
+        assert!(handle.remove_login("nightbot"));
+        assert_eq!(filter.on_message(&mut bot), Flow::Continue);
+
+        handle.set_mode(FilterMode::Allow);
+        handle.add_login("museun");
+        assert_eq!(filter.on_message(&mut user), Flow::Continue);
+        assert_eq!(filter.on_message(&mut bot), Flow::Drop);
+        assert_eq!(filter.on_message(&mut ping), Flow::Continue);
+
+        handle.clear();
+        assert_eq!(filter.on_message(&mut user), Flow::Drop);
+    }
+}
@@ -1,3 +1,4 @@
+use crate::prelude::*;
 use crate::{FromIrcMessage, IrcMessage, MaybeOwned};
 
 use serde::{
@@ -5,7 +6,7 @@ use serde::{
     Deserialize, Deserializer,
 };
 
-use std::marker::PhantomData;
+use core::marker::PhantomData;
 
 impl<'de, 'a> Deserialize<'de> for MaybeOwned<'a> {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
@@ -16,6 +17,31 @@ impl<'de, 'a> Deserialize<'de> for MaybeOwned<'a> {
     }
 }
 
+const RAW: &str = "raw";
+
+// keys can be borrowed or owned (e.g. from a `serde_json::Value`), only the `raw` one matters
+struct IsRaw(bool);
+
+impl<'de> Deserialize<'de> for IsRaw {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct KeyVisitor;
+        impl<'de> Visitor<'de> for KeyVisitor {
+            type Value = IsRaw;
+            fn expecting(&self, formatter: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+                write!(formatter, "a field name")
+            }
+
+            fn visit_str<E: Error>(self, v: &str) -> Result<Self::Value, E> {
+                Ok(IsRaw(v == RAW))
+            }
+        }
+        deserializer.deserialize_identifier(KeyVisitor)
+    }
+}
+
 pub struct RawVisitor<'a, T>(PhantomData<&'a T>);
 
 impl<'a, T> Default for RawVisitor<'a, T> {
@@ -27,10 +53,10 @@ impl<'a, T> Default for RawVisitor<'a, T> {
 impl<'de, 'a, T> Visitor<'de> for RawVisitor<'a, T>
 where
     T: FromIrcMessage<'a>,
-    T::Error: std::error::Error,
+    T::Error: crate::StdError,
 {
     type Value = T;
-    fn expecting(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn expecting(&self, formatter: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         write!(formatter, "map")
     }
 
@@ -38,11 +64,9 @@ where
     where
         A: MapAccess<'de>,
     {
-        const RAW: &str = "raw";
-
         let mut raw = None;
-        while let Some(key) = map.next_key::<&str>()? {
-            if key != RAW {
+        while let Some(IsRaw(is_raw)) = map.next_key()? {
+            if !is_raw {
                 map.next_value::<serde::de::IgnoredAny>()?;
                 continue;
             }
@@ -56,7 +80,7 @@ where
         let irc = IrcMessage::parse(raw).map_err(|err| {
             A::Error::custom(format!(
                 "cannot parse '{}' from the irc message: {}",
-                std::any::type_name::<T>(),
+                core::any::type_name::<T>(),
                 err
             ))
         })?;
@@ -64,7 +88,7 @@ where
         T::from_irc(irc).map_err(|err| {
             A::Error::custom(format!(
                 "cannot parse '{}' because: {}",
-                std::any::type_name::<T>(),
+                core::any::type_name::<T>(),
                 err
             ))
         })
@@ -74,8 +98,8 @@ where
 #[cfg(test)]
 pub(crate) fn round_trip_json<'a, T>(input: &'a str)
 where
-    T: FromIrcMessage<'a> + PartialEq + std::fmt::Debug,
-    T::Error: std::fmt::Debug,
+    T: FromIrcMessage<'a> + PartialEq + core::fmt::Debug,
+    T::Error: core::fmt::Debug,
     for<'de> T: ::serde::Serialize + ::serde::Deserialize<'de>,
 {
     let (_, msg) = crate::irc::parse_one(input).unwrap();
@@ -88,8 +112,8 @@ where
 #[cfg(test)]
 pub(crate) fn round_trip_rmp<'a, T>(input: &'a str)
 where
-    T: FromIrcMessage<'a> + PartialEq + std::fmt::Debug,
-    T::Error: std::fmt::Debug,
+    T: FromIrcMessage<'a> + PartialEq + core::fmt::Debug,
+    T::Error: core::fmt::Debug,
     for<'de> T: ::serde::Serialize + ::serde::Deserialize<'de>,
 {
     let (_, msg) = crate::irc::parse_one(input).unwrap();
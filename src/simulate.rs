@@ -0,0 +1,718 @@
+//! Synthesize realistic chat, e.g. to load-test an overlay or a bot's pipeline.
+//!
+//! A [Simulation] generates a stream of messages for a channel -- chat (with emotes), cheers, subscriptions and raids -- at the rates you configure.
+//! They're shaped like Twitch's (with the same tags), so they go through the same parsing as the real thing.
+//!
+//! Use them:
+//! * as a [Stream] of [Commands], paced at the configured rate, in place of the runner's [events](crate::AsyncRunner::events())
+//! * [fed](Simulation::feed()) to a [MockServer](crate::test::MockServer)'s connection, so they go through a real [AsyncRunner](crate::AsyncRunner) (and its event streams)
+//! * one at a time, with [Simulation::next_line()] or [Simulation::next_message()], without waiting
+//!
+//! This is seeded, so a run can be reproduced.
+//!
+//! ```
+//! # use twitchchat::{messages::Commands, simulate::Simulation};
+//! let mut sim = Simulation::builder("#museun")
+//!     .seed(42)
+//!     .messages_per_second(50.0)
+//!     .emote_density(0.3)
+//!     .subs_per_minute(10.0)
+//!     .raids_per_minute(1.0)
+//!     .build();
+//!
+//! for _ in 0..100 {
+//!     match sim.next_message() {
+//!         Commands::Privmsg(msg) => assert_eq!(msg.channel(), "#museun"),
+//!         Commands::UserNotice(msg) => assert!(msg.event().is_some()),
+//!         msg => panic!("unexpected message: {:?}", msg),
+//!     }
+//! }
+//! ```
+use crate::{
+    irc::tags::escape_str,
+    messages::{Commands, Privmsg},
+    test::MockConnection,
+    twitch::{color::RGB, BadgeKind},
+    FromIrcMessage as _, IntoOwned as _,
+};
+
+use futures_lite::Stream;
+use std::{
+    future::Future,
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+const WORDS: &[&str] = &[
+    "hello", "hi", "lol", "gg", "nice", "the", "is", "this", "that", "what", "why", "chat",
+    "stream", "play", "game", "boss", "again", "wow", "no", "yes", "clip", "it", "so", "good",
+    "bad", "first", "time", "here", "love", "you", "we", "are", "back", "go", "run", "!uptime",
+    "?", "omg", "pog", "based", "true", "wait", "how", "did", "he", "she", "they", "do",
+];
+
+// some of Twitch's global emotes, with their ids
+const EMOTES: &[(usize, &str)] = &[
+    (25, "Kappa"),
+    (88, "PogChamp"),
+    (425618, "LUL"),
+    (41, "Kreygasm"),
+    (354, "4Head"),
+    (1902, "Keepo"),
+    (28087, "WutFace"),
+    (30259, "HeyGuys"),
+    (81274, "VoHiYo"),
+    (86, "BibleThump"),
+    (58765, "NotLikeThis"),
+    (22639, "BabyRage"),
+];
+
+const USER_PARTS: &[&str] = &[
+    "dark", "pixel", "lazy", "happy", "frog", "cat", "ninja", "gamer", "sleepy", "cosmic", "turbo",
+    "waffle", "moon", "salty", "potato", "dragon", "shadow", "noodle",
+];
+
+// the simulated timestamps start here, so a seed reproduces them
+const EPOCH_MS: u64 = 1_600_000_000_000;
+
+#[derive(Debug, Clone)]
+struct Chatter {
+    name: String,
+    display_name: String,
+    user_id: u64,
+    color: Option<RGB>,
+    months: u32,
+    moderator: bool,
+}
+
+/// Configuration for a [Simulation], from [Simulation::builder()]
+#[derive(Debug, Clone)]
+pub struct SimulationBuilder {
+    channel: String,
+    seed: Option<u64>,
+    messages_per_second: f64,
+    emote_density: f64,
+    chatters: usize,
+    cheers_per_minute: f64,
+    subs_per_minute: f64,
+    raids_per_minute: f64,
+}
+
+impl SimulationBuilder {
+    /// The seed for the random numbers. The same seed (and configuration) generates the same messages.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed.replace(seed);
+        self
+    }
+
+    /// How many chat messages are sent a second, on average. The default is `5`
+    pub fn messages_per_second(mut self, rate: f64) -> Self {
+        self.messages_per_second = rate.max(0.0);
+        self
+    }
+
+    /// The fraction of the words in chat messages which are emotes, from `0` to `1`. The default is `0.15`
+    pub fn emote_density(mut self, density: f64) -> Self {
+        self.emote_density = density.clamp(0.0, 1.0);
+        self
+    }
+
+    /// How many different users send the messages. The default is `100`
+    pub fn chatters(mut self, chatters: usize) -> Self {
+        self.chatters = chatters.max(1);
+        self
+    }
+
+    /// How many messages with bits are sent a minute, on average. The default is `0`
+    pub fn cheers_per_minute(mut self, rate: f64) -> Self {
+        self.cheers_per_minute = rate.max(0.0);
+        self
+    }
+
+    /// How many subscriptions (and resubscriptions and gifts) happen a minute, on average. The default is `0`
+    pub fn subs_per_minute(mut self, rate: f64) -> Self {
+        self.subs_per_minute = rate.max(0.0);
+        self
+    }
+
+    /// How many raids happen a minute, on average. The default is `0`
+    pub fn raids_per_minute(mut self, rate: f64) -> Self {
+        self.raids_per_minute = rate.max(0.0);
+        self
+    }
+
+    /// Build the simulation
+    pub fn build(self) -> Simulation {
+        let rng = match self.seed {
+            Some(seed) => fastrand::Rng::with_seed(seed),
+            None => fastrand::Rng::new(),
+        };
+
+        let chatters = (0..self.chatters)
+            .map(|i| {
+                let name = format!(
+                    "{}{}{}",
+                    USER_PARTS[rng.usize(..USER_PARTS.len())],
+                    USER_PARTS[rng.usize(..USER_PARTS.len())],
+                    // so the names are unique
+                    i
+                );
+                let display_name = match rng.u8(..3) {
+                    0 => name.to_uppercase(),
+                    _ => name.clone(),
+                };
+                Chatter {
+                    display_name,
+                    user_id: 100_000 + i as u64,
+                    color: Some(RGB(rng.u8(..), rng.u8(..), rng.u8(..)))
+                        .filter(|_| rng.u8(..5) > 0),
+                    months: if rng.bool() { rng.u32(1..60) } else { 0 },
+                    moderator: rng.u8(..30) == 0,
+                    name,
+                }
+            })
+            .collect();
+
+        Simulation {
+            rng,
+            channel: crate::commands::Channel::new(&self.channel).to_string(),
+            room_id: 12_345_678,
+            chatters,
+            config: self,
+            now_ms: EPOCH_MS,
+            next_id: 0,
+        }
+    }
+}
+
+/// A simulated chat, from [Simulation::builder()]. See the [module](self) docs.
+pub struct Simulation {
+    rng: fastrand::Rng,
+    channel: String,
+    room_id: u64,
+    chatters: Vec<Chatter>,
+    config: SimulationBuilder,
+    // the simulated time, for the `tmi-sent-ts` tags
+    now_ms: u64,
+    next_id: u64,
+}
+
+impl std::fmt::Debug for Simulation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Simulation")
+            .field("channel", &self.channel)
+            .field("config", &self.config)
+            .finish()
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+enum Kind {
+    Chat,
+    Cheer,
+    Sub,
+    Raid,
+}
+
+impl Simulation {
+    /// Configure a simulation of this channel
+    pub fn builder(channel: &str) -> SimulationBuilder {
+        SimulationBuilder {
+            channel: channel.to_string(),
+            seed: None,
+            messages_per_second: 5.0,
+            emote_density: 0.15,
+            chatters: 100,
+            cheers_per_minute: 0.0,
+            subs_per_minute: 0.0,
+            raids_per_minute: 0.0,
+        }
+    }
+
+    /// The channel being simulated, with its leading `#`
+    pub fn channel(&self) -> &str {
+        &self.channel
+    }
+
+    // events a second, of each kind
+    fn rates(&self) -> [(Kind, f64); 4] {
+        let config = &self.config;
+        [
+            (Kind::Chat, config.messages_per_second),
+            (Kind::Cheer, config.cheers_per_minute / 60.0),
+            (Kind::Sub, config.subs_per_minute / 60.0),
+            (Kind::Raid, config.raids_per_minute / 60.0),
+        ]
+    }
+
+    /// How long until the next message, at the configured rates. This advances the simulated clock.
+    ///
+    /// The messages arrive randomly (a Poisson process), so they're sometimes bursty, like a real chat.
+    /// With every rate at `0`, this is an hour.
+    pub fn next_delay(&mut self) -> Duration {
+        let total: f64 = self.rates().iter().map(|(_, rate)| rate).sum();
+        if total <= 0.0 {
+            return Duration::from_secs(60 * 60);
+        }
+        // exponentially distributed, for the gaps between events
+        let uniform = 1.0 - self.rng.f64();
+        let delay = Duration::from_secs_f64((-uniform.ln() / total).min(60.0 * 60.0));
+        self.now_ms += delay.as_millis() as u64;
+        delay
+    }
+
+    /// The next message, as the raw line Twitch would send (ending with `\r\n`)
+    pub fn next_line(&mut self) -> String {
+        match self.next_kind() {
+            Kind::Chat => self.chat(0),
+            Kind::Cheer => {
+                let bits = [1, 10, 100, 500, 1000][self.rng.usize(..5)];
+                self.chat(bits)
+            }
+            Kind::Sub => self.sub(),
+            Kind::Raid => self.raid(),
+        }
+    }
+
+    /// The next message, parsed
+    pub fn next_message(&mut self) -> Commands<'static> {
+        let line = self.next_line();
+        let (_, msg) = crate::irc::parse_one(&line).expect("simulated lines should be valid");
+        Commands::from_irc(msg)
+            .expect("simulated messages should be valid")
+            .into_owned()
+    }
+
+    /// Send messages to this [MockConnection] (e.g. to a runner connected to the [MockServer](crate::test::MockServer)) at the configured rates, for this long.
+    ///
+    /// This returns how many were sent.
+    pub async fn feed(&mut self, conn: &MockConnection, duration: Duration) -> io::Result<usize> {
+        let start = Instant::now();
+        let mut next = start;
+        let mut sent = 0;
+        loop {
+            next += self.next_delay();
+            if next.duration_since(start) > duration {
+                break Ok(sent);
+            }
+
+            // the delays are from when each message was due, so slow sends don't lower the rate
+            if let Some(delay) = next.checked_duration_since(Instant::now()) {
+                futures_timer::Delay::new(delay).await;
+            }
+            conn.send(self.next_line())?;
+            sent += 1;
+        }
+    }
+
+    fn next_kind(&mut self) -> Kind {
+        let rates = self.rates();
+        let total: f64 = rates.iter().map(|(_, rate)| rate).sum();
+        if total <= 0.0 {
+            return Kind::Chat;
+        }
+
+        let mut pick = self.rng.f64() * total;
+        for &(kind, rate) in &rates {
+            if pick < rate {
+                return kind;
+            }
+            pick -= rate;
+        }
+        Kind::Chat
+    }
+
+    fn chatter(&mut self) -> Chatter {
+        let index = self.rng.usize(..self.chatters.len());
+        self.chatters[index].clone()
+    }
+
+    fn id(&mut self) -> String {
+        self.next_id += 1;
+        format!(
+            "{:08x}-{:04x}-{:04x}-{:04x}-{:012x}",
+            self.rng.u32(..),
+            self.rng.u16(..),
+            self.rng.u16(..),
+            self.rng.u16(..),
+            self.next_id
+        )
+    }
+
+    fn chat(&mut self, bits: u64) -> String {
+        let chatter = self.chatter();
+
+        let mut words = vec![];
+        if bits > 0 {
+            words.push(format!("Cheer{}", bits));
+        }
+        let mut emotes = vec![];
+        for _ in 0..self.rng.usize(1..12) {
+            if self.rng.f64() < self.config.emote_density {
+                let (id, name) = EMOTES[self.rng.usize(..EMOTES.len())];
+                if !emotes.contains(&(id, name)) {
+                    emotes.push((id, name));
+                }
+                words.push(name.to_string());
+            } else {
+                words.push(WORDS[self.rng.usize(..WORDS.len())].to_string());
+            }
+        }
+
+        let id = self.id();
+        let mut builder = Privmsg::builder()
+            .channel(&self.channel)
+            .sender(&chatter.name)
+            .data(&words.join(" "))
+            .display_name(&chatter.display_name)
+            .user_id(chatter.user_id)
+            .room_id(self.room_id)
+            .id(&id)
+            .tmi_sent_ts(self.now_ms);
+
+        if chatter.moderator {
+            builder = builder.badge(BadgeKind::Moderator);
+        }
+        if chatter.months > 0 {
+            builder = builder
+                .badge_version(BadgeKind::Subscriber, &chatter.months.to_string())
+                .tag("badge-info", format!("subscriber/{}", chatter.months));
+        }
+        if let Some(color) = chatter.color {
+            builder = builder.color(color);
+        }
+        if bits > 0 {
+            builder = builder.bits(bits);
+        }
+        for (id, name) in emotes {
+            builder = builder.emote(id, name);
+        }
+
+        let msg = builder.build().expect("simulated messages should be valid");
+        msg.raw().to_string()
+    }
+
+    fn sub(&mut self) -> String {
+        let mut chatter = self.chatter();
+
+        let (msg_id, system_msg, extra) = match self.rng.u8(..4) {
+            0 => {
+                let recipient = self.chatter();
+                let system_msg = format!(
+                    "{} gifted a Tier 1 sub to {}!",
+                    chatter.display_name, recipient.display_name
+                );
+                let extra = vec![
+                    ("msg-param-months", "1".to_string()),
+                    ("msg-param-recipient-display-name", recipient.display_name),
+                    ("msg-param-recipient-id", recipient.user_id.to_string()),
+                    ("msg-param-recipient-user-name", recipient.name),
+                ];
+                ("subgift", system_msg, extra)
+            }
+            1 if chatter.months > 0 => {
+                chatter.months += 1;
+                let system_msg = format!(
+                    "{} subscribed at Tier 1. They've subscribed for {} months!",
+                    chatter.display_name, chatter.months
+                );
+                let extra = vec![
+                    ("msg-param-cumulative-months", chatter.months.to_string()),
+                    ("msg-param-should-share-streak", "0".to_string()),
+                ];
+                ("resub", system_msg, extra)
+            }
+            _ => {
+                chatter.months = 1;
+                let system_msg = format!("{} subscribed at Tier 1.", chatter.display_name);
+                let extra = vec![
+                    ("msg-param-cumulative-months", "1".to_string()),
+                    ("msg-param-should-share-streak", "0".to_string()),
+                ];
+                ("sub", system_msg, extra)
+            }
+        };
+
+        let plan = ["Prime", "1000", "1000", "2000", "3000"][self.rng.usize(..5)];
+        let id = self.id();
+        let mut tags = vec![
+            ("badge-info", format!("subscriber/{}", chatter.months)),
+            ("badges", format!("subscriber/{}", chatter.months)),
+            ("display-name", chatter.display_name.clone()),
+            ("emotes", String::new()),
+            ("id", id),
+            ("login", chatter.name.clone()),
+            ("mod", (chatter.moderator as u8).to_string()),
+            ("msg-id", msg_id.to_string()),
+            ("msg-param-sub-plan", plan.to_string()),
+            ("room-id", self.room_id.to_string()),
+            ("system-msg", system_msg),
+            ("tmi-sent-ts", self.now_ms.to_string()),
+            ("user-id", chatter.user_id.to_string()),
+        ];
+        tags.extend(extra);
+
+        // most resubs come with a message
+        let data = match msg_id {
+            "resub" if self.rng.u8(..4) > 0 => Some(WORDS[self.rng.usize(..WORDS.len())]),
+            _ => None,
+        };
+        self.user_notice(tags, data)
+    }
+
+    fn raid(&mut self) -> String {
+        let raider = self.chatter();
+        let viewers = self.rng.u32(1..5000);
+
+        let id = self.id();
+        let tags = vec![
+            ("badge-info", String::new()),
+            ("badges", String::new()),
+            ("display-name", raider.display_name.clone()),
+            ("id", id),
+            ("login", raider.name.clone()),
+            ("msg-id", "raid".to_string()),
+            ("msg-param-displayName", raider.display_name.clone()),
+            ("msg-param-login", raider.name.clone()),
+            ("msg-param-viewerCount", viewers.to_string()),
+            ("room-id", self.room_id.to_string()),
+            (
+                "system-msg",
+                format!(
+                    "{} raiders from {} have joined!",
+                    viewers, raider.display_name
+                ),
+            ),
+            ("tmi-sent-ts", self.now_ms.to_string()),
+            ("user-id", raider.user_id.to_string()),
+        ];
+        self.user_notice(tags, None)
+    }
+
+    fn user_notice(&self, tags: Vec<(&str, String)>, data: Option<&str>) -> String {
+        let tags = tags
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, escape_str(value)))
+            .collect::<Vec<_>>()
+            .join(";");
+
+        match data {
+            Some(data) => format!(
+                "@{} :tmi.twitch.tv USERNOTICE {} :{}\r\n",
+                tags, self.channel, data
+            ),
+            None => format!("@{} :tmi.twitch.tv USERNOTICE {}\r\n", tags, self.channel),
+        }
+    }
+
+    /// Turn this into a [Stream] of messages, paced at the configured rates
+    pub fn into_stream(self) -> SimulationStream {
+        SimulationStream {
+            simulation: self,
+            delay: None,
+        }
+    }
+}
+
+/// A [Stream] of simulated messages, from [Simulation::into_stream()].
+///
+/// This never ends.
+pub struct SimulationStream {
+    simulation: Simulation,
+    delay: Option<futures_timer::Delay>,
+}
+
+impl std::fmt::Debug for SimulationStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SimulationStream")
+            .field("simulation", &self.simulation)
+            .finish()
+    }
+}
+
+impl SimulationStream {
+    /// Get the simulation back
+    pub fn into_inner(self) -> Simulation {
+        self.simulation
+    }
+}
+
+impl Stream for SimulationStream {
+    type Item = Commands<'static>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        let delay = match &mut this.delay {
+            Some(delay) => delay,
+            None => {
+                let next = this.simulation.next_delay();
+                this.delay.get_or_insert(futures_timer::Delay::new(next))
+            }
+        };
+
+        futures_lite::ready!(Pin::new(delay).poll(cx));
+        this.delay.take();
+        Poll::Ready(Some(this.simulation.next_message()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{messages::UserNoticeEvent, test::MockServer, AsyncRunner, UserConfig};
+    use futures_lite::{
+        future::{block_on, zip},
+        StreamExt as _,
+    };
+
+    #[test]
+    fn seeded() {
+        let build = || {
+            Simulation::builder("museun")
+                .seed(7)
+                .subs_per_minute(600.0)
+                .build()
+        };
+        let (mut a, mut b) = (build(), build());
+        for _ in 0..50 {
+            assert_eq!(a.next_delay(), b.next_delay());
+            assert_eq!(a.next_line(), b.next_line());
+        }
+    }
+
+    #[test]
+    fn messages() {
+        let mut sim = Simulation::builder("museun")
+            .seed(1)
+            .messages_per_second(1.0)
+            .cheers_per_minute(60.0)
+            .subs_per_minute(60.0)
+            .raids_per_minute(60.0)
+            .build();
+
+        let (mut chat, mut cheers, mut subs, mut raids) = (0, 0, 0, 0);
+        for _ in 0..1000 {
+            match sim.next_message() {
+                Commands::Privmsg(msg) if msg.bits().is_some() => {
+                    assert!(msg.data().starts_with("Cheer"));
+                    cheers += 1
+                }
+                Commands::Privmsg(msg) => {
+                    assert_eq!(msg.channel(), "#museun");
+                    assert!(msg.display_name().is_some());
+                    chat += 1
+                }
+                Commands::UserNotice(msg) => match msg.event().unwrap() {
+                    UserNoticeEvent::Raid(raid) => {
+                        assert!(raid.viewer_count.unwrap() > 0);
+                        raids += 1
+                    }
+                    UserNoticeEvent::Sub(..)
+                    | UserNoticeEvent::Resub(..)
+                    | UserNoticeEvent::SubGift(..) => subs += 1,
+                    event => panic!("unexpected event: {:?}", event),
+                },
+                msg => panic!("unexpected message: {:?}", msg),
+            }
+        }
+
+        // each is a quarter of them
+        for count in &[chat, cheers, subs, raids] {
+            assert!(
+                (150..350).contains(count),
+                "{:?}",
+                (chat, cheers, subs, raids)
+            );
+        }
+    }
+
+    #[test]
+    fn emote_density() {
+        let mut sim = Simulation::builder("museun")
+            .seed(2)
+            .emote_density(1.0)
+            .build();
+        for _ in 0..100 {
+            match sim.next_message() {
+                Commands::Privmsg(msg) => {
+                    assert_eq!(msg.emote_ranges().count(), msg.data().split(' ').count());
+                    assert_eq!(msg.strip_emotes(), "");
+                }
+                msg => panic!("unexpected message: {:?}", msg),
+            }
+        }
+
+        let mut sim = Simulation::builder("museun")
+            .seed(2)
+            .emote_density(0.0)
+            .build();
+        for _ in 0..100 {
+            match sim.next_message() {
+                Commands::Privmsg(msg) => assert!(msg.emotes().is_empty()),
+                msg => panic!("unexpected message: {:?}", msg),
+            }
+        }
+    }
+
+    #[test]
+    fn rates() {
+        let mut sim = Simulation::builder("museun")
+            .seed(3)
+            .messages_per_second(100.0)
+            .build();
+        let total: Duration = (0..10_000).map(|_| sim.next_delay()).sum();
+        // about 100 seconds
+        assert!((90..110).contains(&total.as_secs()), "{:?}", total);
+
+        let mut sim = Simulation::builder("museun")
+            .messages_per_second(0.0)
+            .build();
+        assert_eq!(sim.next_delay(), Duration::from_secs(60 * 60));
+    }
+
+    #[test]
+    fn stream() {
+        let sim = Simulation::builder("museun")
+            .seed(4)
+            .messages_per_second(1000.0)
+            .build();
+        let msgs = block_on(sim.into_stream().take(20).collect::<Vec<_>>());
+        assert_eq!(msgs.len(), 20);
+    }
+
+    #[test]
+    fn feed_a_runner() {
+        let server = MockServer::new();
+        let user_config = UserConfig::builder().anonymous().build().unwrap();
+
+        block_on(async {
+            let (runner, conn) = zip(
+                AsyncRunner::connect(server.clone(), &user_config),
+                server.accept(),
+            )
+            .await;
+            let (mut runner, conn) = (runner.unwrap(), conn.unwrap());
+            let mut events = runner.events::<Privmsg>();
+
+            let mut sim = Simulation::builder("museun")
+                .seed(5)
+                .messages_per_second(500.0)
+                .build();
+            let sent = sim.feed(&conn, Duration::from_millis(100)).await.unwrap();
+            assert!(sent > 0);
+            conn.close();
+
+            while let Ok(status) = runner.next_message().await {
+                if matches!(status, crate::Status::Eof) {
+                    break;
+                }
+            }
+            let mut received = 0;
+            while let Some(Some(..)) = futures_lite::future::poll_once(events.next()).await {
+                received += 1;
+            }
+            assert_eq!(received, sent);
+        });
+    }
+}
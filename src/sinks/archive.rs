@@ -0,0 +1,740 @@
+use crate::{
+    messages::{Commands, ModerationAction},
+    runner::{Flow, Middleware},
+    BoxedFuture,
+};
+
+use futures_lite::future;
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+const SCHEMA: &str = "\
+CREATE TABLE IF NOT EXISTS users (
+    user_id BIGINT PRIMARY KEY,
+    login TEXT NOT NULL,
+    display_name TEXT,
+    last_seen BIGINT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS messages (
+    id TEXT PRIMARY KEY,
+    channel TEXT NOT NULL,
+    room_id BIGINT,
+    user_id BIGINT,
+    login TEXT NOT NULL,
+    text TEXT NOT NULL,
+    bits BIGINT,
+    sent_at BIGINT NOT NULL,
+    raw TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS messages_channel_sent_at ON messages (channel, sent_at);
+CREATE TABLE IF NOT EXISTS badges (
+    message_id TEXT NOT NULL,
+    badge TEXT NOT NULL,
+    version TEXT NOT NULL,
+    PRIMARY KEY (message_id, badge)
+);
+CREATE TABLE IF NOT EXISTS moderation_events (
+    channel TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    login TEXT,
+    target_user_id BIGINT,
+    target_msg_id TEXT,
+    duration_secs BIGINT,
+    at BIGINT NOT NULL
+);
+";
+
+/// The SQL dialect the statements are written in
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Dialect {
+    /// SQLite (3.24 or newer), with `?` placeholders
+    Sqlite,
+    /// PostgreSQL, with `$1` placeholders
+    Postgres,
+}
+
+impl Dialect {
+    /// The statements which create the tables (if they don't exist), separated by `;`
+    ///
+    /// * `users`: `user_id`, `login`, `display_name`, `last_seen`
+    /// * `messages`: `id`, `channel`, `room_id`, `user_id`, `login`, `text`, `bits`, `sent_at`, `raw`
+    /// * `badges`: `message_id`, `badge`, `version`
+    /// * `moderation_events`: `channel`, `kind` (`deleted`, `timeout`, `ban` or `clear`), `login`, `target_user_id`, `target_msg_id`, `duration_secs`, `at`
+    ///
+    /// The times are unix milliseconds.
+    pub fn schema(self) -> &'static str {
+        // the types are the same for both
+        SCHEMA
+    }
+
+    fn placeholder(self, n: usize) -> String {
+        match self {
+            Self::Sqlite => "?".to_string(),
+            Self::Postgres => format!("${}", n),
+        }
+    }
+}
+
+/// A value bound to a [Statement]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    /// `NULL`
+    Null,
+    /// A `BIGINT`
+    Int(i64),
+    /// A `TEXT`
+    Text(String),
+}
+
+impl From<Option<u64>> for Value {
+    fn from(value: Option<u64>) -> Self {
+        value.map_or(Self::Null, |v| Self::Int(v as i64))
+    }
+}
+
+impl From<Option<&str>> for Value {
+    fn from(value: Option<&str>) -> Self {
+        value.map_or(Self::Null, |v| Self::Text(v.to_string()))
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Self::Text(value.to_string())
+    }
+}
+
+/// An `INSERT`, with the values for its placeholders
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Statement {
+    /// The SQL
+    pub sql: String,
+    /// The values, in the order of the placeholders
+    pub params: Vec<Value>,
+}
+
+/// Somewhere an [Archiver] can store its batches.
+///
+/// This is implemented for your database library, e.g. with `sqlx`:
+/// ```ignore
+/// struct Sqlite(sqlx::SqlitePool);
+///
+/// impl Database for Sqlite {
+///     fn execute(&mut self, statements: Vec<Statement>) -> BoxedFuture<std::io::Result<()>> {
+///         let pool = self.0.clone();
+///         Box::pin(async move {
+///             let mut tx = pool.begin().await.map_err(to_io)?;
+///             for statement in &statements {
+///                 let mut query = sqlx::query(&statement.sql);
+///                 for param in &statement.params {
+///                     query = match param {
+///                         Value::Null => query.bind(None::<i64>),
+///                         Value::Int(n) => query.bind(*n),
+///                         Value::Text(s) => query.bind(s.as_str()),
+///                     };
+///                 }
+///                 query.execute(&mut *tx).await.map_err(to_io)?;
+///             }
+///             tx.commit().await.map_err(to_io)
+///         })
+///     }
+/// }
+/// ```
+pub trait Database: Send {
+    /// Run these statements, ideally in one transaction
+    fn execute(&mut self, statements: Vec<Statement>) -> BoxedFuture<std::io::Result<()>>;
+}
+
+impl<T> Database for Box<T>
+where
+    T: Database + ?Sized,
+{
+    fn execute(&mut self, statements: Vec<Statement>) -> BoxedFuture<std::io::Result<()>> {
+        (**self).execute(statements)
+    }
+}
+
+/// Configuration for an [Archive]
+#[derive(Debug, Copy, Clone)]
+pub struct ArchiveBuilder {
+    dialect: Dialect,
+    batch_size: usize,
+    flush_interval: Duration,
+    capacity: usize,
+}
+
+impl ArchiveBuilder {
+    /// How many messages are put in each batch. This defaults to `100`
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// How long a batch waits to be filled before it's stored anyway. This defaults to 1 second
+    pub fn flush_interval(mut self, flush_interval: Duration) -> Self {
+        self.flush_interval = flush_interval;
+        self
+    }
+
+    /// How many messages can be waiting to be stored. Once this is full, new messages are dropped (and counted) instead of waiting.
+    ///
+    /// This defaults to `10000`
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity.max(1);
+        self
+    }
+
+    /// Create the [Archive] (for the runner) and the [Archiver] which stores its batches in `database`
+    pub fn build<D: Database>(self, database: D) -> (Archive, Archiver<D>) {
+        let (tx, rx) = async_channel::bounded(self.capacity);
+        let stats = Arc::new(Stats::default());
+        let archive = Archive {
+            tx,
+            stats: Arc::clone(&stats),
+        };
+        let archiver = Archiver {
+            rx,
+            database,
+            dialect: self.dialect,
+            batch_size: self.batch_size,
+            flush_interval: self.flush_interval,
+            stats,
+        };
+        (archive, archiver)
+    }
+}
+
+/// A [Middleware] which archives chat messages, users (and their badges) and moderation events to a [Database].
+///
+/// The messages are batched up and stored by an [Archiver], which you run (e.g. spawn) alongside the runner.
+/// If it falls behind, messages are dropped (see [ArchiveHandle::dropped()]) so archiving never stalls the runner.
+///
+/// Messages are keyed by their `id` tag, so this needs the `tags` capability.
+///
+/// # Example
+/// ```no_run
+/// # use twitchchat::sinks::{Archive, Database, Dialect};
+/// # async fn demo(runner: &mut twitchchat::AsyncRunner, database: impl Database + 'static) -> std::io::Result<()> {
+/// // create the tables first, using `Dialect::Sqlite.schema()`
+/// let (archive, archiver) = Archive::builder(Dialect::Sqlite).batch_size(500).build(database);
+/// runner.add_middleware(archive);
+/// // this runs until the archive is dropped, or the database returns an error
+/// archiver.run().await?;
+/// # Ok(()) }
+/// ```
+pub struct Archive {
+    tx: async_channel::Sender<Record>,
+    stats: Arc<Stats>,
+}
+
+impl std::fmt::Debug for Archive {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Archive")
+            .field("queued", &self.tx.len())
+            .finish()
+    }
+}
+
+impl Archive {
+    /// Configure an archive for this [Dialect]
+    pub fn builder(dialect: Dialect) -> ArchiveBuilder {
+        ArchiveBuilder {
+            dialect,
+            batch_size: 100,
+            flush_interval: Duration::from_secs(1),
+            capacity: 10_000,
+        }
+    }
+
+    /// Get a handle for checking on this archive, after it's been given to the runner
+    pub fn handle(&self) -> ArchiveHandle {
+        ArchiveHandle {
+            stats: Arc::clone(&self.stats),
+        }
+    }
+
+    /// Archive a message, if it's something which is archived. This doesn't wait if the [Archiver] is behind
+    pub fn archive(&mut self, msg: &Commands<'_>) {
+        if let Some(record) = Record::from_commands(msg, unix_millis(SystemTime::now())) {
+            // this is full when the archiver is behind, or closed when it stopped
+            if self.tx.try_send(record).is_err() {
+                self.stats.dropped.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+impl Middleware for Archive {
+    fn on_message(&mut self, msg: &mut Commands<'static>) -> Flow {
+        self.archive(msg);
+        Flow::Continue
+    }
+}
+
+/// A handle for checking on an [Archive]
+#[derive(Clone)]
+pub struct ArchiveHandle {
+    stats: Arc<Stats>,
+}
+
+impl std::fmt::Debug for ArchiveHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ArchiveHandle")
+            .field("archived", &self.archived())
+            .field("dropped", &self.dropped())
+            .finish()
+    }
+}
+
+impl ArchiveHandle {
+    /// How many messages (and moderation events) have been stored
+    pub fn archived(&self) -> u64 {
+        self.stats.archived.load(Ordering::Relaxed)
+    }
+
+    /// How many were dropped, because the [Archiver] was behind (or had stopped)
+    pub fn dropped(&self) -> u64 {
+        self.stats.dropped.load(Ordering::Relaxed)
+    }
+}
+
+/// Stores the batches from an [Archive] in a [Database]
+pub struct Archiver<D> {
+    rx: async_channel::Receiver<Record>,
+    database: D,
+    dialect: Dialect,
+    batch_size: usize,
+    flush_interval: Duration,
+    stats: Arc<Stats>,
+}
+
+impl<D> std::fmt::Debug for Archiver<D> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Archiver")
+            .field("dialect", &self.dialect)
+            .field("batch_size", &self.batch_size)
+            .field("flush_interval", &self.flush_interval)
+            .finish()
+    }
+}
+
+impl<D: Database> Archiver<D> {
+    /// Store batches until the [Archive] is dropped (after storing what's left), or the database returns an error
+    pub async fn run(mut self) -> std::io::Result<()> {
+        while let Ok(first) = self.rx.recv().await {
+            let mut batch = vec![first];
+            let mut deadline = futures_timer::Delay::new(self.flush_interval);
+            while batch.len() < self.batch_size {
+                let next = future::or(async { self.rx.recv().await.ok() }, async {
+                    (&mut deadline).await;
+                    None
+                });
+                match next.await {
+                    Some(record) => batch.push(record),
+                    None => break,
+                }
+            }
+
+            let count = batch.len() as u64;
+            self.database
+                .execute(statements(self.dialect, &batch))
+                .await?;
+            self.stats.archived.fetch_add(count, Ordering::Relaxed);
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+struct Stats {
+    archived: AtomicU64,
+    dropped: AtomicU64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Record {
+    Message {
+        id: String,
+        channel: String,
+        room_id: Option<u64>,
+        user_id: Option<u64>,
+        login: String,
+        display_name: Option<String>,
+        text: String,
+        bits: Option<u64>,
+        sent_at: u64,
+        badges: Vec<(String, String)>,
+        raw: String,
+    },
+    Moderation {
+        channel: String,
+        kind: &'static str,
+        login: Option<String>,
+        target_user_id: Option<u64>,
+        target_msg_id: Option<String>,
+        duration: Option<u64>,
+        at: u64,
+    },
+}
+
+impl Record {
+    fn from_commands(msg: &Commands<'_>, now: u64) -> Option<Self> {
+        if let Commands::Privmsg(msg) = msg {
+            let sent_at = msg.sent_at().map(unix_millis).unwrap_or(now);
+            return Some(Self::Message {
+                id: msg.id()?.to_string(),
+                channel: msg.channel().to_string(),
+                room_id: msg.room_id(),
+                user_id: msg.user_id(),
+                login: msg.name().to_string(),
+                display_name: msg.display_name().map(|s| s.to_string()),
+                text: msg.data().to_string(),
+                bits: msg.bits(),
+                sent_at,
+                badges: msg
+                    .badges()
+                    .iter()
+                    .map(|badge| (badge.kind_raw().to_string(), badge.data.to_string()))
+                    .collect(),
+                raw: msg.raw().trim_end().to_string(),
+            });
+        }
+
+        let at = match msg {
+            Commands::ClearChat(msg) => msg.sent_at(),
+            Commands::ClearMsg(msg) => msg.sent_at(),
+            _ => None,
+        }
+        .map(unix_millis)
+        .unwrap_or(now);
+
+        let record = match ModerationAction::from_commands(msg)? {
+            ModerationAction::MessageDeleted {
+                channel,
+                login,
+                target_msg_id,
+                ..
+            } => Self::moderation(channel, "deleted", login, None, target_msg_id, None, at),
+            ModerationAction::TimedOut {
+                channel,
+                login,
+                target_user_id,
+                duration,
+            } => Self::moderation(
+                channel,
+                "timeout",
+                Some(login),
+                target_user_id,
+                None,
+                Some(duration.as_secs()),
+                at,
+            ),
+            ModerationAction::Banned {
+                channel,
+                login,
+                target_user_id,
+            } => Self::moderation(channel, "ban", Some(login), target_user_id, None, None, at),
+            ModerationAction::ChatCleared { channel } => {
+                Self::moderation(channel, "clear", None, None, None, None, at)
+            }
+        };
+        Some(record)
+    }
+
+    fn moderation(
+        channel: &str,
+        kind: &'static str,
+        login: Option<&str>,
+        target_user_id: Option<u64>,
+        target_msg_id: Option<&str>,
+        duration: Option<u64>,
+        at: u64,
+    ) -> Self {
+        Self::Moderation {
+            channel: channel.to_string(),
+            kind,
+            login: login.map(ToString::to_string),
+            target_user_id,
+            target_msg_id: target_msg_id.map(ToString::to_string),
+            duration,
+            at,
+        }
+    }
+}
+
+// a multi-row INSERT, with its placeholders numbered for the dialect
+fn insert(
+    dialect: Dialect,
+    head: &str,
+    columns: usize,
+    rows: Vec<Vec<Value>>,
+    tail: &str,
+) -> Option<Statement> {
+    if rows.is_empty() {
+        return None;
+    }
+
+    let mut sql = head.to_string();
+    let mut params = Vec::with_capacity(rows.len() * columns);
+    for (i, row) in rows.into_iter().enumerate() {
+        sql.push_str(if i == 0 { " VALUES (" } else { ", (" });
+        for (j, value) in row.into_iter().enumerate() {
+            if j > 0 {
+                sql.push_str(", ");
+            }
+            params.push(value);
+            sql.push_str(&dialect.placeholder(params.len()));
+        }
+        sql.push(')');
+    }
+    sql.push(' ');
+    sql.push_str(tail);
+    Some(Statement { sql, params })
+}
+
+fn statements(dialect: Dialect, batch: &[Record]) -> Vec<Statement> {
+    let mut messages = Vec::new();
+    let mut badges = Vec::new();
+    let mut events = Vec::new();
+    // an upsert can't change the same row twice, so only the latest for each user is kept
+    let mut users = HashMap::new();
+
+    for record in batch {
+        match record {
+            Record::Message {
+                id,
+                channel,
+                room_id,
+                user_id,
+                login,
+                display_name,
+                text,
+                bits,
+                sent_at,
+                badges: message_badges,
+                raw,
+            } => {
+                messages.push(vec![
+                    id.as_str().into(),
+                    channel.as_str().into(),
+                    (*room_id).into(),
+                    (*user_id).into(),
+                    login.as_str().into(),
+                    text.as_str().into(),
+                    (*bits).into(),
+                    Value::Int(*sent_at as i64),
+                    raw.as_str().into(),
+                ]);
+                for (badge, version) in message_badges {
+                    badges.push(vec![
+                        id.as_str().into(),
+                        badge.as_str().into(),
+                        version.as_str().into(),
+                    ]);
+                }
+                if let Some(user_id) = user_id {
+                    users.insert(
+                        *user_id,
+                        vec![
+                            Value::Int(*user_id as i64),
+                            login.as_str().into(),
+                            display_name.as_deref().into(),
+                            Value::Int(*sent_at as i64),
+                        ],
+                    );
+                }
+            }
+            Record::Moderation {
+                channel,
+                kind,
+                login,
+                target_user_id,
+                target_msg_id,
+                duration,
+                at,
+            } => events.push(vec![
+                channel.as_str().into(),
+                (*kind).into(),
+                login.as_deref().into(),
+                (*target_user_id).into(),
+                target_msg_id.as_deref().into(),
+                (*duration).into(),
+                Value::Int(*at as i64),
+            ]),
+        }
+    }
+
+    let mut users = users.into_iter().collect::<Vec<_>>();
+    users.sort_by_key(|(id, _)| *id);
+    let users = users.into_iter().map(|(_, row)| row).collect();
+
+    vec![
+        insert(
+            dialect,
+            "INSERT INTO users (user_id, login, display_name, last_seen)",
+            4,
+            users,
+            "ON CONFLICT (user_id) DO UPDATE SET login = excluded.login, display_name = excluded.display_name, last_seen = excluded.last_seen",
+        ),
+        insert(
+            dialect,
+            "INSERT INTO messages (id, channel, room_id, user_id, login, text, bits, sent_at, raw)",
+            9,
+            messages,
+            "ON CONFLICT (id) DO NOTHING",
+        ),
+        insert(
+            dialect,
+            "INSERT INTO badges (message_id, badge, version)",
+            3,
+            badges,
+            "ON CONFLICT (message_id, badge) DO NOTHING",
+        ),
+        insert(
+            dialect,
+            "INSERT INTO moderation_events (channel, kind, login, target_user_id, target_msg_id, duration_secs, at)",
+            7,
+            events,
+            "",
+        ),
+    ]
+    .into_iter()
+    .flatten()
+    .map(|mut statement| {
+        let len = statement.sql.trim_end().len();
+        statement.sql.truncate(len);
+        statement
+    })
+    .collect()
+}
+
+fn unix_millis(ts: SystemTime) -> u64 {
+    ts.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FromIrcMessage as _, IntoOwned as _};
+    use std::sync::Mutex;
+
+    fn parse(input: &str) -> Commands<'static> {
+        let msg = crate::irc::parse_one(input).unwrap().1;
+        Commands::from_irc(msg).unwrap().into_owned()
+    }
+
+    #[test]
+    fn batches() {
+        let batch = [
+            "@badges=subscriber/6;display-name=Museun;id=a;room-id=1;tmi-sent-ts=1000;user-id=2 :museun!museun@museun PRIVMSG #museun :hello\r\n",
+            "@badges=;id=b;room-id=1;tmi-sent-ts=2000;user-id=2 :museun!museun@museun PRIVMSG #museun :world\r\n",
+            "@ban-duration=600;target-user-id=3;tmi-sent-ts=3000 :tmi.twitch.tv CLEARCHAT #museun :shaken_bot\r\n",
+        ]
+        .iter()
+        .filter_map(|input| Record::from_commands(&parse(input), 0))
+        .collect::<Vec<_>>();
+        // without an id, messages aren't archived
+        assert!(
+            Record::from_commands(&parse(":museun!museun@museun PRIVMSG #museun :hi\r\n"), 0)
+                .is_none()
+        );
+        assert!(Record::from_commands(&parse("PING :1234\r\n"), 0).is_none());
+
+        let statements = statements(Dialect::Postgres, &batch);
+        assert_eq!(statements.len(), 4);
+
+        assert_eq!(
+            statements[0].sql,
+            "INSERT INTO users (user_id, login, display_name, last_seen) VALUES ($1, $2, $3, $4) ON CONFLICT (user_id) DO UPDATE SET login = excluded.login, display_name = excluded.display_name, last_seen = excluded.last_seen"
+        );
+        // the latest message wins
+        assert_eq!(
+            statements[0].params,
+            vec![
+                Value::Int(2),
+                "museun".into(),
+                Value::Null,
+                Value::Int(2000)
+            ]
+        );
+
+        assert!(statements[1]
+            .sql
+            .ends_with("VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9), ($10, $11, $12, $13, $14, $15, $16, $17, $18) ON CONFLICT (id) DO NOTHING"));
+        assert_eq!(statements[1].params.len(), 18);
+        assert_eq!(statements[1].params[5], "hello".into());
+
+        assert_eq!(
+            statements[2].params,
+            vec!["a".into(), "subscriber".into(), "6".into()]
+        );
+
+        assert_eq!(
+            statements[3].sql,
+            "INSERT INTO moderation_events (channel, kind, login, target_user_id, target_msg_id, duration_secs, at) VALUES ($1, $2, $3, $4, $5, $6, $7)"
+        );
+        assert_eq!(
+            statements[3].params,
+            vec![
+                "#museun".into(),
+                "timeout".into(),
+                "shaken_bot".into(),
+                Value::Int(3),
+                Value::Null,
+                Value::Int(600),
+                Value::Int(3000)
+            ]
+        );
+
+        let statements = super::statements(Dialect::Sqlite, &batch[2..]);
+        assert!(statements[0].sql.ends_with("VALUES (?, ?, ?, ?, ?, ?, ?)"));
+    }
+
+    #[derive(Clone, Default)]
+    struct Recorded(Arc<Mutex<Vec<Vec<Statement>>>>);
+
+    impl Database for Recorded {
+        fn execute(&mut self, statements: Vec<Statement>) -> BoxedFuture<std::io::Result<()>> {
+            self.0.lock().unwrap().push(statements);
+            Box::pin(async { Ok(()) })
+        }
+    }
+
+    #[test]
+    fn archiver() {
+        let database = Recorded::default();
+        let (mut archive, archiver) = Archive::builder(Dialect::Sqlite)
+            .batch_size(2)
+            .flush_interval(Duration::from_millis(10))
+            .build(database.clone());
+        let handle = archive.handle();
+
+        for id in 0..3 {
+            let mut msg = parse(&format!(
+                "@id={};user-id=2 :museun!museun@museun PRIVMSG #museun :hello\r\n",
+                id
+            ));
+            assert_eq!(archive.on_message(&mut msg), Flow::Continue);
+        }
+        drop(archive);
+
+        future::block_on(archiver.run()).unwrap();
+        assert_eq!(handle.archived(), 3);
+        assert_eq!(handle.dropped(), 0);
+
+        // two messages, and then what was left
+        let batches = database.0.lock().unwrap();
+        assert_eq!(batches.len(), 2);
+        assert_eq!(batches[0][1].params.len(), 18);
+        assert_eq!(batches[1][1].params.len(), 9);
+    }
+}
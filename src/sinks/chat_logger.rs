@@ -0,0 +1,572 @@
+use crate::{
+    messages::Commands,
+    runner::{Flow, Middleware},
+};
+
+use std::{
+    fs::{File, OpenOptions},
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc::{sync_channel, Receiver, SyncSender},
+        Arc, Mutex,
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+type Filter = Box<dyn FnMut(&Commands<'static>) -> bool + Send>;
+
+/// How a [ChatLogger] writes each message
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum LogFormat {
+    /// One JSON object per line, with `ts` (unix milliseconds), `command`, `channel`, `user`, `text` and `raw`
+    JsonLines,
+    /// `[2020-09-26 00:10:32] #museun <museun> hello`, or the raw line for anything that isn't a message
+    Text,
+}
+
+impl LogFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            Self::JsonLines => "jsonl",
+            Self::Text => "log",
+        }
+    }
+}
+
+/// Configuration for a [ChatLogger]
+pub struct ChatLoggerBuilder {
+    dir: PathBuf,
+    prefix: String,
+    format: LogFormat,
+    daily: bool,
+    max_size: Option<u64>,
+    capacity: usize,
+    filter: Option<Filter>,
+}
+
+impl std::fmt::Debug for ChatLoggerBuilder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ChatLoggerBuilder")
+            .field("dir", &self.dir)
+            .field("prefix", &self.prefix)
+            .field("format", &self.format)
+            .field("daily", &self.daily)
+            .field("max_size", &self.max_size)
+            .field("capacity", &self.capacity)
+            .finish()
+    }
+}
+
+impl ChatLoggerBuilder {
+    /// The start of each file name. This defaults to `chat`
+    pub fn prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = prefix.into();
+        self
+    }
+
+    /// How each message is written. This defaults to [LogFormat::JsonLines]
+    pub fn format(mut self, format: LogFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Start a new file each day (in UTC). This is the default
+    pub fn daily(mut self, daily: bool) -> Self {
+        self.daily = daily;
+        self
+    }
+
+    /// Start a new file once the current one is this many bytes
+    pub fn max_size(mut self, bytes: u64) -> Self {
+        self.max_size.replace(bytes);
+        self
+    }
+
+    /// How many lines can be waiting to be written. Once this is full, new lines are dropped (and counted) instead of waiting.
+    ///
+    /// This defaults to `4096`
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// Only log the messages this returns `true` for
+    pub fn filter<F>(mut self, filter: F) -> Self
+    where
+        F: FnMut(&Commands<'static>) -> bool + Send + 'static,
+    {
+        self.filter.replace(Box::new(filter));
+        self
+    }
+
+    /// Create the directory (if needed), open the first file and start the thread which writes to it
+    pub fn build(self) -> std::io::Result<ChatLogger> {
+        std::fs::create_dir_all(&self.dir)?;
+
+        let stats = Arc::new(Stats::default());
+        let mut files = Files {
+            dir: self.dir,
+            prefix: self.prefix,
+            format: self.format,
+            daily: self.daily,
+            max_size: self.max_size,
+            current: None,
+            stats: Arc::clone(&stats),
+        };
+        files.rotate(SystemTime::now())?;
+
+        let (tx, rx) = sync_channel(self.capacity);
+        std::thread::Builder::new()
+            .name("twitchchat-logger".into())
+            .spawn(move || files.run(rx))?;
+
+        Ok(ChatLogger {
+            tx,
+            format: self.format,
+            filter: self.filter,
+            stats,
+        })
+    }
+}
+
+/// A [Middleware] which logs every message (or the ones you've filtered) to files, rotating them by day or by size.
+///
+/// The files are written on their own thread. If it falls behind, lines are dropped (see [ChatLoggerHandle::dropped()]) so logging never stalls the runner.
+///
+/// # Example
+/// ```no_run
+/// # use twitchchat::{messages::Commands, sinks::{ChatLogger, LogFormat}};
+/// # fn demo(runner: &mut twitchchat::AsyncRunner) -> std::io::Result<()> {
+/// let logger = ChatLogger::builder("logs")
+///     .format(LogFormat::Text)
+///     .max_size(64 * 1024 * 1024)
+///     .filter(|msg| matches!(msg, Commands::Privmsg(..)))
+///     .build()?;
+/// let handle = logger.handle();
+/// runner.add_middleware(logger);
+/// # Ok(()) }
+/// ```
+pub struct ChatLogger {
+    tx: SyncSender<Line>,
+    format: LogFormat,
+    filter: Option<Filter>,
+    stats: Arc<Stats>,
+}
+
+impl std::fmt::Debug for ChatLogger {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ChatLogger")
+            .field("format", &self.format)
+            .finish()
+    }
+}
+
+impl ChatLogger {
+    /// Configure a logger which writes its files into `dir`
+    pub fn builder(dir: impl AsRef<Path>) -> ChatLoggerBuilder {
+        ChatLoggerBuilder {
+            dir: dir.as_ref().to_path_buf(),
+            prefix: "chat".to_string(),
+            format: LogFormat::JsonLines,
+            daily: true,
+            max_size: None,
+            capacity: 4096,
+            filter: None,
+        }
+    }
+
+    /// Get a handle for checking on this logger, after it's been given to the runner
+    pub fn handle(&self) -> ChatLoggerHandle {
+        ChatLoggerHandle {
+            stats: Arc::clone(&self.stats),
+        }
+    }
+
+    /// Log a message. This doesn't wait if the writer thread is behind
+    pub fn log(&mut self, msg: &Commands<'static>) {
+        if let Some(filter) = &mut self.filter {
+            if !filter(msg) {
+                return;
+            }
+        }
+
+        let ts = SystemTime::now();
+        let text = match self.format {
+            LogFormat::JsonLines => json_line(ts, msg),
+            LogFormat::Text => text_line(ts, msg),
+        };
+
+        // this is full when the thread is behind, or disconnected when it stopped (and its error was recorded)
+        if self.tx.try_send(Line { ts, text }).is_err() {
+            self.stats.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+impl Middleware for ChatLogger {
+    fn on_message(&mut self, msg: &mut Commands<'static>) -> Flow {
+        self.log(msg);
+        Flow::Continue
+    }
+}
+
+/// A handle for checking on a [ChatLogger]
+#[derive(Clone)]
+pub struct ChatLoggerHandle {
+    stats: Arc<Stats>,
+}
+
+impl std::fmt::Debug for ChatLoggerHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ChatLoggerHandle")
+            .field("written", &self.written())
+            .field("dropped", &self.dropped())
+            .finish()
+    }
+}
+
+impl ChatLoggerHandle {
+    /// How many lines have been written
+    pub fn written(&self) -> u64 {
+        self.stats.written.load(Ordering::Relaxed)
+    }
+
+    /// How many lines were dropped, because the writer thread was behind (or had stopped)
+    pub fn dropped(&self) -> u64 {
+        self.stats.dropped.load(Ordering::Relaxed)
+    }
+
+    /// The file currently being written to
+    pub fn current_file(&self) -> Option<PathBuf> {
+        self.stats.current.lock().unwrap().clone()
+    }
+
+    /// The error which stopped the writer thread, if it stopped
+    pub fn error(&self) -> Option<String> {
+        self.stats.error.lock().unwrap().clone()
+    }
+}
+
+#[derive(Default)]
+struct Stats {
+    written: AtomicU64,
+    dropped: AtomicU64,
+    current: Mutex<Option<PathBuf>>,
+    error: Mutex<Option<String>>,
+}
+
+struct Line {
+    ts: SystemTime,
+    text: String,
+}
+
+struct Current {
+    file: BufWriter<File>,
+    day: u64,
+    // the number of the file, for this day
+    index: usize,
+    size: u64,
+}
+
+struct Files {
+    dir: PathBuf,
+    prefix: String,
+    format: LogFormat,
+    daily: bool,
+    max_size: Option<u64>,
+    current: Option<Current>,
+    stats: Arc<Stats>,
+}
+
+impl Files {
+    fn run(mut self, rx: Receiver<Line>) {
+        // this ends when the logger is dropped
+        while let Ok(line) = rx.recv() {
+            let mut next = Some(line);
+            while let Some(line) = next.take() {
+                if let Err(err) = self.write(&line) {
+                    self.stats.error.lock().unwrap().replace(err.to_string());
+                    return;
+                }
+                next = rx.try_recv().ok();
+            }
+
+            // flush once we've caught up
+            if let Some(current) = &mut self.current {
+                if let Err(err) = current.file.flush() {
+                    self.stats.error.lock().unwrap().replace(err.to_string());
+                    return;
+                }
+            }
+        }
+    }
+
+    fn write(&mut self, line: &Line) -> std::io::Result<()> {
+        let day = days(line.ts);
+        let full = match &self.current {
+            Some(current) => {
+                (self.daily && current.day != day)
+                    || self
+                        .max_size
+                        .map_or(false, |max| current.size + line.text.len() as u64 + 1 > max)
+            }
+            None => true,
+        };
+        if full {
+            self.rotate(line.ts)?;
+        }
+
+        let current = self.current.as_mut().expect("file should be open");
+        current.file.write_all(line.text.as_bytes())?;
+        current.file.write_all(b"\n")?;
+        current.size += line.text.len() as u64 + 1;
+        self.stats.written.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn rotate(&mut self, ts: SystemTime) -> std::io::Result<()> {
+        let day = days(ts);
+        // a full file is followed by the next one, a new day starts from the first one
+        let mut index = match self.current.take() {
+            Some(mut current) => {
+                current.file.flush()?;
+                if current.day == day || !self.daily {
+                    current.index + 1
+                } else {
+                    0
+                }
+            }
+            None => 0,
+        };
+
+        let (year, month, date) = civil(day);
+        let stem = if self.daily {
+            format!("{}-{:04}-{:02}-{:02}", self.prefix, year, month, date)
+        } else {
+            self.prefix.clone()
+        };
+
+        // files from earlier runs are appended to, unless they're full
+        let (path, size) = loop {
+            let name = match index {
+                0 => format!("{}.{}", stem, self.format.extension()),
+                n => format!("{}.{}.{}", stem, n, self.format.extension()),
+            };
+            let path = self.dir.join(name);
+            let size = std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+            if self.max_size.map_or(true, |max| size < max) {
+                break (path, size);
+            }
+            index += 1;
+        };
+
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        self.stats.current.lock().unwrap().replace(path);
+        self.current = Some(Current {
+            file: BufWriter::new(file),
+            day,
+            index,
+            size,
+        });
+        Ok(())
+    }
+}
+
+fn days(ts: SystemTime) -> u64 {
+    ts.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() / 86400)
+        .unwrap_or_default()
+}
+
+// the (year, month, day) of the days since the unix epoch, in the proleptic gregorian calendar
+fn civil(days: u64) -> (u64, u64, u64) {
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + (month <= 2) as u64;
+    (year, month, day)
+}
+
+fn command(raw: &str) -> &str {
+    raw.split(' ')
+        .find(|part| !part.is_empty() && !part.starts_with('@') && !part.starts_with(':'))
+        .map(str::trim_end)
+        .unwrap_or_default()
+}
+
+fn user_and_text<'a>(msg: &'a Commands<'static>) -> (Option<&'a str>, Option<&'a str>) {
+    match msg {
+        Commands::Privmsg(msg) => (Some(msg.name()), Some(msg.data())),
+        Commands::Whisper(msg) => (Some(msg.name()), Some(msg.data())),
+        Commands::UserNotice(msg) => (msg.login(), msg.message()),
+        Commands::Join(msg) => (Some(msg.name()), None),
+        Commands::Part(msg) => (Some(msg.name()), None),
+        _ => (None, None),
+    }
+}
+
+fn json_line(ts: SystemTime, msg: &Commands<'static>) -> String {
+    let millis = ts
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or_default();
+    let raw = msg.raw().trim_end();
+    let (user, text) = user_and_text(msg);
+
+    let mut out = format!("{{\"ts\":{},\"command\":", millis);
+    json_string(&mut out, command(raw));
+    for (key, value) in [("channel", msg.channel()), ("user", user), ("text", text)] {
+        out.push_str(",\"");
+        out.push_str(key);
+        out.push_str("\":");
+        match value {
+            Some(value) => json_string(&mut out, value),
+            None => out.push_str("null"),
+        }
+    }
+    out.push_str(",\"raw\":");
+    json_string(&mut out, raw);
+    out.push('}');
+    out
+}
+
+fn json_string(out: &mut String, s: &str) {
+    use std::fmt::Write as _;
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+fn text_line(ts: SystemTime, msg: &Commands<'static>) -> String {
+    let secs = ts
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let (year, month, day) = civil(secs / 86400);
+    let time = format!(
+        "[{:04}-{:02}-{:02} {:02}:{:02}:{:02}]",
+        year,
+        month,
+        day,
+        secs % 86400 / 3600,
+        secs % 3600 / 60,
+        secs % 60
+    );
+
+    match (msg.channel(), user_and_text(msg)) {
+        (Some(channel), (Some(user), Some(text))) => {
+            format!("{} {} <{}> {}", time, channel, user, text)
+        }
+        (None, (Some(user), Some(text))) => format!("{} <{}> {}", time, user, text),
+        _ => format!("{} {}", time, msg.raw().trim_end()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FromIrcMessage as _, IntoOwned as _};
+    use std::time::Duration;
+
+    fn parse(input: &str) -> Commands<'static> {
+        let msg = crate::irc::parse_one(input).unwrap().1;
+        Commands::from_irc(msg).unwrap().into_owned()
+    }
+
+    #[test]
+    fn formats() {
+        assert_eq!(civil(0), (1970, 1, 1));
+        assert_eq!(civil(18_531), (2020, 9, 26));
+        assert_eq!(civil(19_416), (2023, 2, 28));
+        assert_eq!(civil(19_783), (2024, 3, 1));
+
+        let ts = UNIX_EPOCH + Duration::from_millis(1_601_079_032_426);
+        let msg = parse("@badges= :museun!museun@museun PRIVMSG #museun :a \"quote\"\r\n");
+        assert_eq!(
+            json_line(ts, &msg),
+            r##"{"ts":1601079032426,"command":"PRIVMSG","channel":"#museun","user":"museun","text":"a \"quote\"","raw":"@badges= :museun!museun@museun PRIVMSG #museun :a \"quote\""}"##
+        );
+        assert_eq!(
+            text_line(ts, &msg),
+            r##"[2020-09-26 00:10:32] #museun <museun> a "quote""##
+        );
+
+        let msg = parse("PING :1234\r\n");
+        assert_eq!(
+            json_line(ts, &msg),
+            r##"{"ts":1601079032426,"command":"PING","channel":null,"user":null,"text":null,"raw":"PING :1234"}"##
+        );
+        assert_eq!(text_line(ts, &msg), "[2020-09-26 00:10:32] PING :1234");
+    }
+
+    #[test]
+    fn rotation() {
+        let dir = std::env::temp_dir().join(format!(
+            "twitchchat-logger-{}-{}",
+            std::process::id(),
+            UNIX_EPOCH.elapsed().unwrap().as_nanos()
+        ));
+
+        let mut logger = ChatLogger::builder(&dir)
+            .prefix("test")
+            .format(LogFormat::Text)
+            .daily(false)
+            .max_size(110)
+            .filter(|msg| matches!(msg, Commands::Privmsg(..)))
+            .build()
+            .unwrap();
+        let handle = logger.handle();
+
+        let mut msg = parse(":museun!museun@museun PRIVMSG #museun :hello world\r\n");
+        let mut ping = parse("PING :1234\r\n");
+        for _ in 0..5 {
+            assert_eq!(logger.on_message(&mut msg), Flow::Continue);
+            assert_eq!(logger.on_message(&mut ping), Flow::Continue);
+        }
+
+        // the thread finishes writing once the logger is dropped
+        drop(logger);
+        let start = std::time::Instant::now();
+        while handle.written() < 5 && start.elapsed() < Duration::from_secs(5) {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(handle.written(), 5);
+        assert_eq!(handle.dropped(), 0);
+        assert!(handle.error().is_none());
+
+        // each line is 51 bytes, so that's 2 per file
+        let mut files = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect::<Vec<_>>();
+        files.sort();
+        assert_eq!(files, vec!["test.1.log", "test.2.log", "test.log"]);
+        assert_eq!(handle.current_file(), Some(dir.join("test.2.log")));
+
+        let contents = std::fs::read_to_string(dir.join("test.log")).unwrap();
+        assert_eq!(contents.lines().count(), 2);
+        assert!(contents.ends_with("#museun <museun> hello world\n"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
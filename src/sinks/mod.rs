@@ -0,0 +1,15 @@
+//! Sinks which can be attached to the runner (as [Middleware](crate::runner::Middleware)), to store the messages it reads.
+//!
+//! * [ChatLogger] writes them to rotating files, as JSON-lines or plain text
+//! * `Archive` (behind the `archive` feature) stores them in a SQLite or PostgreSQL database
+mod chat_logger;
+pub use chat_logger::{ChatLogger, ChatLoggerBuilder, ChatLoggerHandle, LogFormat};
+
+#[cfg(feature = "archive")]
+#[cfg_attr(docsrs, doc(cfg(feature = "archive")))]
+mod archive;
+#[cfg(feature = "archive")]
+#[cfg_attr(docsrs, doc(cfg(feature = "archive")))]
+pub use archive::{
+    Archive, ArchiveBuilder, ArchiveHandle, Archiver, Database, Dialect, Statement, Value,
+};
@@ -0,0 +1,388 @@
+//! A blocking client, for when you don't want an async runtime.
+//!
+//! This reads messages on the current thread with a [Decoder](crate::Decoder) and writes with an [Encoder](crate::Encoder).
+//!
+//! Messages written to the [MpscWriter] from [Client::writer()] are queued and sent before the next read (or when you call [Client::flush()]),
+//! so they can be written from other threads.
+//!
+//! While the client is blocked on a read, the queued messages are sent whenever the read times out.
+//! [Client::connect()] sets a short read timeout on its socket, so they're sent even when the chat is quiet.
+//! With [Client::from_stream()], set a read timeout on your own stream (e.g. with [TcpStream::set_read_timeout()]) for this,
+//! otherwise they wait until the next message arrives.
+//!
+//! **NOTE** Unlike the [AsyncRunner](crate::AsyncRunner), this doesn't rate limit anything you send.
+//!
+//! ```no_run
+//! # use twitchchat::{sync::Client, messages::Commands, commands, UserConfig};
+//! # fn main() -> Result<(), twitchchat::RunnerError> {
+//! let user_config = UserConfig::builder().anonymous().build().unwrap();
+//!
+//! let mut client = Client::connect(&user_config)?;
+//! client.join("#museun")?;
+//!
+//! for msg in &mut client {
+//!     if let Commands::Privmsg(msg) = msg? {
+//!         println!("{}: {}", msg.name(), msg.data());
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use crate::{
+    channel::Receiver,
+    commands,
+    messages::Commands,
+    runner::{Error, Handshake, Identity},
+    twitch::ChannelName,
+    writer::{MpscWriter, Priority},
+    DecodeError, Decoder, Encodable, Encoder, FromIrcMessage as _, IntoOwned as _, UserConfig,
+};
+
+use std::{
+    collections::VecDeque,
+    io::{Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    time::Duration,
+};
+
+// how often the messages queued by the writer are sent, while waiting for a message
+const WRITE_INTERVAL: Duration = Duration::from_millis(50);
+
+/// A blocking client.
+///
+/// This responds to `PING`s for you. See the [module docs](self) for an example.
+///
+/// The messages queued by its [writer](Client::writer()) are only sent while this is being read from (or [flushed](Client::flush())), and a read
+/// only stops to send them if the stream has a read timeout. See the [module docs](self) for more.
+pub struct Client {
+    identity: Identity,
+    decoder: Decoder<Box<dyn Read + Send>>,
+    encoder: Encoder<Box<dyn Write + Send>>,
+    writer: MpscWriter,
+    writer_rx: Receiver<Box<[u8]>>,
+    missed_messages: VecDeque<Commands<'static>>,
+}
+
+impl std::fmt::Debug for Client {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Client")
+            .field("identity", &self.identity)
+            .finish()
+    }
+}
+
+impl Client {
+    /// Connect to the ***default Twitch*** address (without TLS), blocking until the connection is ready
+    pub fn connect(user_config: &UserConfig) -> Result<Self, Error> {
+        Self::connect_to(crate::TWITCH_IRC_ADDRESS, user_config)
+    }
+
+    /// Connect to a custom address (without TLS), blocking until the connection is ready
+    ///
+    /// This sets a short read timeout on the socket, so the messages queued by the [writer](Client::writer()) are sent while waiting for a message.
+    pub fn connect_to<A>(addrs: A, user_config: &UserConfig) -> Result<Self, Error>
+    where
+        A: ToSocketAddrs,
+    {
+        let write = TcpStream::connect(addrs)?;
+        let read = write.try_clone()?;
+        read.set_read_timeout(Some(WRITE_INTERVAL))?;
+        Self::from_stream(read, write, user_config)
+    }
+
+    /// Register over an already connected stream, blocking until the connection is ready.
+    ///
+    /// Use this for your own transport, e.g. a TLS stream.
+    ///
+    /// A read which times out (with a `WouldBlock` or `TimedOut` error) is retried after sending the messages queued by the [writer](Client::writer()),
+    /// so give `read` a read timeout to have them sent while the chat is quiet.
+    pub fn from_stream<R, W>(read: R, write: W, user_config: &UserConfig) -> Result<Self, Error>
+    where
+        R: Read + Send + 'static,
+        W: Write + Send + 'static,
+    {
+        let read: Box<dyn Read + Send> = Box::new(read);
+        let write: Box<dyn Write + Send> = Box::new(write);
+
+        let mut decoder = Decoder::new(read);
+        let mut encoder = Encoder::new(write);
+
+        log::debug!("registering");
+        encoder.encode(commands::register(user_config))?;
+
+        let mut handshake = Handshake::new(user_config);
+        let mut missed_messages = VecDeque::new();
+
+        let identity = loop {
+            let msg = match decoder.read_message() {
+                Err(err) if is_timeout(&err) => continue,
+                res => Commands::from_irc(res?)?.into_owned(),
+            };
+            if let Commands::Ping(msg) = &msg {
+                encoder.encode(commands::pong(msg.token()))?;
+            }
+
+            let identity = handshake.step(&msg)?;
+            missed_messages.push_back(msg);
+            if let Some(identity) = identity {
+                break identity;
+            }
+        };
+        log::debug!("connection is ready: {:?}", identity);
+
+        let (writer_tx, writer_rx) = crate::channel::unbounded();
+
+        Ok(Self {
+            identity,
+            decoder,
+            encoder,
+            writer: MpscWriter::new(writer_tx),
+            writer_rx,
+            missed_messages,
+        })
+    }
+
+    /// Your identity, as Twitch sees it
+    pub fn identity(&self) -> &Identity {
+        &self.identity
+    }
+
+    /// Get a clonable writer. What's written to this is sent before the next read, or when [Client::flush()] is called.
+    pub fn writer(&self) -> MpscWriter {
+        self.writer.clone()
+    }
+
+    /// Send this message right away
+    pub fn encode<M>(&mut self, msg: M) -> Result<(), Error>
+    where
+        M: Encodable,
+    {
+        self.encoder.encode(msg).map_err(Into::into)
+    }
+
+    /// Join `channel`.
+    ///
+    /// This doesn't wait for Twitch to confirm it.
+    pub fn join(&mut self, channel: impl Into<ChannelName>) -> Result<(), Error> {
+        let channel: ChannelName = channel.into();
+        self.encode(commands::join(&channel))
+    }
+
+    /// Part `channel`.
+    ///
+    /// This doesn't wait for Twitch to confirm it.
+    pub fn part(&mut self, channel: impl Into<ChannelName>) -> Result<(), Error> {
+        let channel: ChannelName = channel.into();
+        self.encode(commands::part(&channel))
+    }
+
+    /// Send everything that was queued by the [writer](Client::writer())
+    pub fn flush(&mut self) -> Result<(), Error> {
+        while let Some(data) = self.writer_rx.try_recv() {
+            // this doesn't queue by priority
+            self.encoder.write_all(&Priority::strip(&data))?;
+        }
+        self.encoder.flush().map_err(Into::into)
+    }
+
+    /// Block until the next message arrives.
+    ///
+    /// This returns `None` when the connection was closed.
+    pub fn next_message(&mut self) -> Result<Option<Commands<'static>>, Error> {
+        if let Some(msg) = self.missed_messages.pop_front() {
+            return Ok(Some(msg));
+        }
+
+        let msg = loop {
+            // this is also done each time the read times out
+            self.flush()?;
+
+            match self.decoder.read_message() {
+                Err(DecodeError::Eof) => return Ok(None),
+                Err(err) if is_timeout(&err) => continue,
+                Err(err) => return Err(err.into()),
+                Ok(msg) => break Commands::from_irc(msg)?.into_owned(),
+            }
+        };
+
+        match &msg {
+            Commands::Ping(msg) => self.encoder.encode(commands::pong(msg.token()))?,
+            Commands::Reconnect(..) => return Err(Error::ShouldReconnect),
+            _ => {}
+        }
+
+        Ok(Some(msg))
+    }
+
+    /// Send a `QUIT`, after everything that was queued
+    pub fn quit(mut self) -> Result<(), Error> {
+        self.flush()?;
+        self.encode(commands::raw("QUIT"))
+    }
+}
+
+// the read timed out, so it can be tried again (the decoder keeps what it already read)
+fn is_timeout(err: &DecodeError) -> bool {
+    matches!(
+        err,
+        DecodeError::Io(err)
+            if matches!(err.kind(), std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut)
+    )
+}
+
+impl Iterator for Client {
+    type Item = Result<Commands<'static>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_message().transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Clone, Default)]
+    struct Output(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Output {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Output {
+        fn take(&self) -> String {
+            String::from_utf8(std::mem::take(&mut *self.0.lock().unwrap())).unwrap()
+        }
+    }
+
+    fn client(input: &'static str) -> (Client, Output) {
+        let user_config = UserConfig::builder().anonymous().build().unwrap();
+        let output = Output::default();
+        let client = Client::from_stream(input.as_bytes(), output.clone(), &user_config).unwrap();
+        (client, output)
+    }
+
+    #[test]
+    fn register() {
+        let (client, output) = client(":tmi.twitch.tv 376 justinfan1234 :>\r\n");
+        assert_eq!(client.identity().username(), "justinfan1234");
+        assert!(output.take().contains("NICK justinfan1234\r\n"));
+    }
+
+    #[test]
+    fn messages() {
+        let (mut client, output) = client(
+            ":tmi.twitch.tv 376 justinfan1234 :>\r\n\
+             PING :1234\r\n\
+             :test!test@test PRIVMSG #museun :hello\r\n",
+        );
+        output.take();
+
+        let mut writer = client.writer();
+        writer.encode(commands::privmsg("#museun", "hi")).unwrap();
+
+        // the 376 was read while registering
+        assert!(matches!(
+            client.next_message(),
+            Ok(Some(Commands::Ready(..)))
+        ));
+        assert!(matches!(
+            client.next_message(),
+            Ok(Some(Commands::Ping(..)))
+        ));
+        assert_eq!(output.take(), "PRIVMSG #museun :hi\r\nPONG :1234\r\n");
+
+        let msg = match client.next().unwrap().unwrap() {
+            Commands::Privmsg(msg) => msg,
+            e => panic!("unexpected message: {:?}", e),
+        };
+        assert_eq!(msg.data(), "hello");
+
+        assert!(client.next().is_none());
+
+        client.quit().unwrap();
+        assert_eq!(output.take(), "QUIT\r\n");
+    }
+
+    #[test]
+    fn writes_when_the_read_times_out() {
+        // a read from the script only times out while the writer is waiting for it
+        struct Timeout {
+            script: VecDeque<&'static [u8]>,
+            writer: Arc<Mutex<Option<MpscWriter>>>,
+        }
+
+        impl Read for Timeout {
+            fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+                match self.script.pop_front() {
+                    Some(b"") => {
+                        // something else wrote while this was blocked
+                        let mut writer = self.writer.lock().unwrap().take().unwrap();
+                        writer.encode(commands::privmsg("#museun", "hi")).unwrap();
+                        Err(std::io::ErrorKind::WouldBlock.into())
+                    }
+                    Some(data) => {
+                        buf[..data.len()].copy_from_slice(data);
+                        Ok(data.len())
+                    }
+                    None => Ok(0),
+                }
+            }
+        }
+
+        let writer = Arc::new(Mutex::new(None));
+        let read = Timeout {
+            script: vec![
+                &b":tmi.twitch.tv 376 justinfan1234 :>\r\n"[..],
+                b":test!test@test PRIVMSG #mus",
+                b"",
+                b"eun :hello\r\n",
+            ]
+            .into(),
+            writer: Arc::clone(&writer),
+        };
+
+        let user_config = UserConfig::builder().anonymous().build().unwrap();
+        let output = Output::default();
+        let mut client = Client::from_stream(read, output.clone(), &user_config).unwrap();
+        writer.lock().unwrap().replace(client.writer());
+        output.take();
+
+        assert!(matches!(
+            client.next_message(),
+            Ok(Some(Commands::Ready(..)))
+        ));
+        // the partial line from before the timeout isn't lost
+        let msg = match client.next_message().unwrap().unwrap() {
+            Commands::Privmsg(msg) => msg,
+            e => panic!("unexpected message: {:?}", e),
+        };
+        assert_eq!(msg.data(), "hello");
+        assert_eq!(output.take(), "PRIVMSG #museun :hi\r\n");
+    }
+
+    #[test]
+    fn authentication_failed() {
+        let user_config = UserConfig::builder()
+            .name("museun")
+            .token(format!("oauth:{}", "a".repeat(30)))
+            .build()
+            .unwrap();
+        let input = ":tmi.twitch.tv NOTICE * :Login authentication failed\r\n";
+        let err =
+            Client::from_stream(input.as_bytes(), Output::default(), &user_config).unwrap_err();
+        assert!(matches!(err, Error::AuthenticationFailed));
+
+        let input = ":tmi.twitch.tv NOTICE * :Improperly formatted auth\r\n";
+        let err =
+            Client::from_stream(input.as_bytes(), Output::default(), &user_config).unwrap_err();
+        assert!(matches!(err, Error::InvalidToken));
+    }
+}
@@ -0,0 +1,407 @@
+//! Random, Twitch-like lines for property tests and fuzzing.
+//!
+//! This doesn't implement `arbitrary::Arbitrary` (or `proptest`'s `Arbitrary`): the crate doesn't depend on either, and an arbitrary byte string rarely looks enough like a Twitch line to get past the parser.
+//! Instead, the [Generator] is driven by a `u64` seed, which both of them can produce -- e.g. `fuzz_target!(|seed: u64| ..)` with `cargo-fuzz`, or `any::<u64>()` with `proptest`.
+
+use crate::{messages::Commands, FromIrcMessage as _, IntoOwned as _};
+
+// commands Twitch sends, and some it doesn't
+const COMMANDS: &[&str] = &[
+    "PRIVMSG",
+    "WHISPER",
+    "NOTICE",
+    "USERNOTICE",
+    "CLEARCHAT",
+    "CLEARMSG",
+    "ROOMSTATE",
+    "USERSTATE",
+    "GLOBALUSERSTATE",
+    "HOSTTARGET",
+    "JOIN",
+    "PART",
+    "PING",
+    "PONG",
+    "CAP",
+    "RECONNECT",
+    "001",
+    "353",
+    "366",
+    "372",
+    "375",
+    "376",
+    "421",
+    "FOOBAR",
+];
+
+// tags Twitch sends, which are parsed further than a string
+const TAG_KEYS: &[&str] = &[
+    "badge-info",
+    "badges",
+    "bits",
+    "color",
+    "display-name",
+    "emote-only",
+    "emote-sets",
+    "emotes",
+    "first-msg",
+    "followers-only",
+    "id",
+    "login",
+    "mod",
+    "msg-id",
+    "msg-param-cumulative-months",
+    "msg-param-sub-plan",
+    "r9k",
+    "reply-parent-msg-id",
+    "room-id",
+    "slow",
+    "subs-only",
+    "target-msg-id",
+    "tmi-sent-ts",
+    "user-id",
+    "user-type",
+    "vip",
+];
+
+/// Generates random, Twitch-like IRC lines and tags, for property tests and fuzzing.
+///
+/// The lines look enough like Twitch's to reach the parsers for each message and tag, but their values are often nonsense (e.g. emote ranges past the end of the message, or negative counts).
+/// [Generator::mangled_line()] breaks them further, and [exercise()] decodes data and uses every part of every message, the way a handler would.
+///
+/// This is seeded, so a failure can be reproduced.
+/// It doesn't implement `arbitrary::Arbitrary`, take the seed from `arbitrary` or `proptest` instead:
+/// `Generator::with_seed(u64::arbitrary(..)?)`.
+///
+/// ```
+/// # use twitchchat::test::{exercise, Generator};
+/// let mut gen = Generator::with_seed(42);
+/// for _ in 0..100 {
+///     exercise(gen.line().as_bytes());
+///     exercise(&gen.mangled_line());
+/// }
+/// ```
+pub struct Generator {
+    rng: fastrand::Rng,
+}
+
+impl std::fmt::Debug for Generator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Generator").finish()
+    }
+}
+
+impl Default for Generator {
+    fn default() -> Self {
+        Self::with_seed(fastrand::u64(..))
+    }
+}
+
+impl Generator {
+    /// Create a generator with a random seed
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a generator with this seed. The same seed generates the same lines.
+    pub fn with_seed(seed: u64) -> Self {
+        Self {
+            rng: fastrand::Rng::with_seed(seed),
+        }
+    }
+
+    /// A tag key, usually one that Twitch sends
+    pub fn tag_key(&mut self) -> String {
+        if self.rng.u8(..10) == 0 {
+            return self.word(0..8);
+        }
+        TAG_KEYS[self.rng.usize(..TAG_KEYS.len())].to_string()
+    }
+
+    /// An escaped value for this tag, shaped like Twitch's (e.g. emote ranges for `emotes`) but not always valid
+    pub fn tag_value(&mut self, key: &str) -> String {
+        match (key, self.rng.u8(..4)) {
+            (_, 0) => self.text(0..12),
+            ("badges", _) | ("badge-info", _) => {
+                self.list(',', |gen| format!("{}/{}", gen.word(0..10), gen.number()))
+            }
+            ("emotes", _) => self.list('/', |gen| {
+                let ranges = gen.list(',', |gen| format!("{}-{}", gen.number(), gen.number()));
+                format!("{}:{}", gen.number(), ranges)
+            }),
+            ("emote-sets", _) => self.list(',', Self::number),
+            ("color", _) => match self.rng.u8(..3) {
+                0 => format!("#{:06X}", self.rng.u32(..0x0100_0000)),
+                1 => format!("#{}", self.word(0..8)),
+                _ => String::new(),
+            },
+            ("bits", _)
+            | ("emote-only", _)
+            | ("first-msg", _)
+            | ("followers-only", _)
+            | ("mod", _)
+            | ("msg-param-cumulative-months", _)
+            | ("r9k", _)
+            | ("room-id", _)
+            | ("slow", _)
+            | ("subs-only", _)
+            | ("tmi-sent-ts", _)
+            | ("user-id", _)
+            | ("vip", _) => self.number(),
+            _ => self.text(0..12),
+        }
+    }
+
+    /// A set of tags, with their values escaped
+    pub fn tags(&mut self) -> Vec<(String, String)> {
+        (0..self.rng.usize(0..8))
+            .map(|_| {
+                let key = self.tag_key();
+                let value = self.tag_value(&key);
+                (key, value)
+            })
+            .collect()
+    }
+
+    /// A line, ending with `\r\n`
+    pub fn line(&mut self) -> String {
+        let mut line = String::new();
+
+        let tags = self.tags();
+        if !tags.is_empty() {
+            line.push('@');
+            for (i, (key, value)) in tags.iter().enumerate() {
+                if i > 0 {
+                    line.push(';');
+                }
+                line.push_str(key);
+                if !value.is_empty() || self.rng.bool() {
+                    line.push('=');
+                    line.push_str(value);
+                }
+            }
+            line.push(' ');
+        }
+
+        match self.rng.u8(..4) {
+            0 => {}
+            1 => line.push_str(":tmi.twitch.tv "),
+            _ => {
+                let name = self.word(0..10);
+                line.push_str(&format!(":{0}!{0}@{0}.tmi.twitch.tv ", name));
+            }
+        }
+
+        line.push_str(COMMANDS[self.rng.usize(..COMMANDS.len())]);
+
+        for _ in 0..self.rng.usize(0..3) {
+            line.push(' ');
+            match self.rng.u8(..3) {
+                0 => line.push('*'),
+                1 => line.push_str(&self.word(0..10)),
+                _ => {
+                    line.push('#');
+                    line.push_str(&self.word(0..10))
+                }
+            }
+        }
+
+        if self.rng.bool() {
+            line.push_str(" :");
+            let text = self.text(0..30);
+            // sometimes as a CTCP action
+            if self.rng.u8(..4) == 0 {
+                line.push_str(&format!("\x01ACTION {}\x01", text));
+            } else {
+                line.push_str(&text);
+            }
+        }
+
+        line.push_str("\r\n");
+        line
+    }
+
+    /// A [line](Generator::line()), with some of its bytes changed, removed or repeated. This is often not UTF-8.
+    pub fn mangled_line(&mut self) -> Vec<u8> {
+        let mut line = self.line().into_bytes();
+        // without the line ending, and added back after
+        line.truncate(line.len() - 2);
+
+        for _ in 0..self.rng.usize(1..4) {
+            if line.is_empty() {
+                break;
+            }
+            let pos = self.rng.usize(..line.len());
+            match self.rng.u8(..4) {
+                0 => line[pos] = self.rng.u8(..),
+                1 => {
+                    line.remove(pos);
+                }
+                2 => line.truncate(pos),
+                _ => {
+                    let byte = b" :;=@!#,-/\\"[self.rng.usize(..11)];
+                    line.insert(pos, byte)
+                }
+            }
+        }
+
+        line.extend_from_slice(b"\r\n");
+        line
+    }
+
+    /// A message which parses, e.g. to test your handlers with
+    pub fn message(&mut self) -> Commands<'static> {
+        loop {
+            let line = self.line();
+            let msg = match crate::irc::parse_one(&line) {
+                Ok((_, msg)) => msg,
+                Err(..) => continue,
+            };
+            if let Ok(msg) = Commands::from_irc(msg) {
+                break msg.into_owned();
+            }
+        }
+    }
+
+    fn number(&mut self) -> String {
+        match self.rng.u8(..8) {
+            0 => String::new(),
+            1 => format!("-{}", self.rng.u32(..)),
+            2 => self.rng.u64(..).to_string(),
+            _ => self.rng.u32(..100).to_string(),
+        }
+    }
+
+    fn word(&mut self, len: std::ops::Range<usize>) -> String {
+        (0..self.rng.usize(len))
+            .map(|_| self.rng.alphanumeric().to_ascii_lowercase())
+            .collect()
+    }
+
+    // printable text, with some multi-byte characters. For a tag value, this is escaped
+    fn text(&mut self, len: std::ops::Range<usize>) -> String {
+        (0..self.rng.usize(len))
+            .map(|_| match self.rng.u8(..16) {
+                0 => "\\s".to_string(),
+                1 => "\\:".to_string(),
+                2 => "é".to_string(),
+                3 => "🦀".to_string(),
+                4 => " ".to_string(),
+                _ => self.rng.alphanumeric().to_string(),
+            })
+            .collect()
+    }
+
+    fn list(&mut self, sep: char, mut item: impl FnMut(&mut Self) -> String) -> String {
+        let items: Vec<_> = (0..self.rng.usize(0..4)).map(|_| item(self)).collect();
+        items.join(&sep.to_string())
+    }
+}
+
+/// Decode this data, and use every part of every message in it -- as a fuzz target would.
+///
+/// This shouldn't panic, whatever the data is. If it does, that's a bug in this crate.
+///
+/// ```
+/// twitchchat::test::exercise(b"@emotes=25:0-999 PRIVMSG #museun :Kappa\r\n");
+/// ```
+pub fn exercise(data: &[u8]) {
+    use crate::{DecodeError, Decoder};
+
+    let mut decoder = Decoder::new(data).lossy(true);
+    loop {
+        let msg = match decoder.read_message() {
+            Ok(msg) => msg,
+            Err(DecodeError::Eof) => break,
+            Err(err) => {
+                let _ = err.to_string();
+                continue;
+            }
+        };
+
+        let msg = match Commands::from_irc(msg) {
+            Ok(msg) => msg,
+            Err(err) => {
+                let _ = err.to_string();
+                continue;
+            }
+        };
+        let _ = format!("{:?}", msg);
+
+        if let Commands::Privmsg(msg) = &msg {
+            let _ = msg.iter_badges().count();
+            let _ = msg.iter_emotes().count();
+            let _ = msg.emote_ranges().count();
+            for (key, _) in msg.tags().iter() {
+                let _ = msg.tags().get_unescaped(key);
+            }
+            let _ = msg.replace_emotes(|emote| emote.text.to_uppercase());
+            let _ = msg.strip_emotes();
+            let _ = msg.ctcp();
+            let _ = msg.badge_info();
+            let _ = msg.typed_badges();
+            let _ = msg.cheermotes().count();
+            let _ = msg.color_or_fallback();
+            let _ = msg.display_name();
+            let _ = msg.emotes();
+            let _ = msg.author_role();
+            let _ = msg.shared_chat_source();
+            let _ = msg.sent_at();
+            let _ = msg.redemption();
+            let _ = msg.reply_parent();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn regressions() {
+        // lines which used to panic
+        for line in &[
+            " @first-ms=1 :tmi.twitch.tv NOTICE #museun :é\r\n",
+            ":tmi.twitch.tv 376 a🦀b c\r\n",
+            "@color=🦀NZ :museun!museun@museun.tmi.twitch.tv GLOBALUSERSTATE\r\n",
+            ":museun!museun@museun.tmi.twitch.tv PRIVMSG #museun :\x01\r\n",
+        ] {
+            exercise(line.as_bytes());
+        }
+    }
+
+    #[test]
+    fn exercise_generated_lines() {
+        let mut gen = Generator::with_seed(0x7477_6974_6368);
+        for _ in 0..20_000 {
+            let line = gen.line();
+            let mangled = gen.mangled_line();
+            let res = std::panic::catch_unwind(|| {
+                exercise(line.as_bytes());
+                exercise(&mangled);
+            });
+            assert!(
+                res.is_ok(),
+                "panicked on {:?} or {:?}",
+                line,
+                String::from_utf8_lossy(&mangled)
+            );
+        }
+    }
+
+    #[test]
+    fn generated_messages_parse() {
+        let mut gen = Generator::with_seed(1);
+        for _ in 0..100 {
+            let _ = gen.message();
+        }
+    }
+
+    #[test]
+    fn seeded() {
+        let (mut a, mut b) = (Generator::with_seed(7), Generator::with_seed(7));
+        for _ in 0..10 {
+            assert_eq!(a.line(), b.line());
+            assert_eq!(a.tags(), b.tags());
+        }
+    }
+}
@@ -0,0 +1,501 @@
+use std::{
+    future::Future,
+    io::{Error, ErrorKind, Result},
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use futures_lite::{AsyncRead, AsyncWrite, Stream};
+use futures_timer::Delay;
+
+use crate::{
+    channel::{Receiver, Sender},
+    connector::Connector,
+    AsyncRunner, UserConfig,
+};
+
+type Chunk = (Instant, Vec<u8>);
+
+/// An in-memory Twitch server, for end-to-end tests.
+///
+/// This is a [Connector]: give a clone of it to the [AsyncRunner](crate::AsyncRunner) (or a [Reconnect](crate::runner::Reconnect)) and [accept](MockServer::accept()) its connections.
+///
+/// ```
+/// # use twitchchat::{test::MockServer, messages::Commands, AsyncRunner, Status, UserConfig, commands};
+/// # use futures_lite::future::{block_on, zip};
+/// # block_on(async {
+/// let server = MockServer::new();
+/// let user_config = UserConfig::builder()
+///     .name("shaken_bot")
+///     .token(format!("oauth:{}", "a".repeat(30)))
+///     .enable_all_capabilities()
+///     .build()
+///     .unwrap();
+///
+/// let (mut runner, mut conn) = server.connect_runner(&user_config).await.unwrap();
+///
+/// // JOINs are answered like Twitch would
+/// let (joined, line) = zip(runner.join("museun"), conn.read_line()).await;
+/// joined.unwrap();
+/// assert_eq!(line.unwrap(), "JOIN #museun\r\n");
+///
+/// // script what Twitch sends, and check what the client sends
+/// conn.send(":museun!museun@museun.tmi.twitch.tv PRIVMSG #museun :hello").unwrap();
+/// // (the messages from the handshake are returned first)
+/// while !matches!(runner.next_message().await.unwrap(), Status::Message(Commands::Privmsg(..))) {}
+///
+/// runner.writer().encode(commands::privmsg("museun", "hi")).await.unwrap();
+/// // the runner sends what was written as it is stepped
+/// let line = loop {
+///     runner.step().await.unwrap();
+///     if let Some(line) = conn.try_read_line().unwrap() {
+///         break line;
+///     }
+/// };
+/// assert_eq!(line, "PRIVMSG #museun :hi\r\n");
+/// # });
+/// ```
+#[derive(Clone)]
+pub struct MockServer {
+    incoming: Arc<(Sender<MockConnection>, Receiver<MockConnection>)>,
+    latency: Duration,
+}
+
+impl std::fmt::Debug for MockServer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MockServer")
+            .field("latency", &self.latency)
+            .finish()
+    }
+}
+
+impl Default for MockServer {
+    fn default() -> Self {
+        Self {
+            incoming: Arc::new(crate::channel::unbounded()),
+            latency: Duration::from_secs(0),
+        }
+    }
+}
+
+impl MockServer {
+    /// Create a new mock server
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Delay every message sent to the client by `latency`
+    pub fn with_latency(mut self, latency: Duration) -> Self {
+        self.latency = latency;
+        self
+    }
+
+    /// Wait for the next connection and do the `CAP`/`PASS`/`NICK` registration with it.
+    ///
+    /// Every requested capability is acknowledged. If both `tags` and `commands` are requested (and the user isn't anonymous), a `GLOBALUSERSTATE` is sent.
+    pub async fn accept(&self) -> Result<MockConnection> {
+        let mut conn = self.accept_raw().await?;
+        conn.handshake().await?;
+        Ok(conn)
+    }
+
+    /// Connect an [AsyncRunner] to this server with `user_config`, and [accept](MockServer::accept()) its connection.
+    pub async fn connect_runner(
+        &self,
+        user_config: &UserConfig,
+    ) -> std::result::Result<(AsyncRunner, MockConnection), crate::runner::Error> {
+        let (runner, conn) = futures_lite::future::zip(
+            AsyncRunner::connect(self.clone(), user_config),
+            self.accept(),
+        )
+        .await;
+        Ok((runner?, conn?))
+    }
+
+    /// Wait for the next connection, without doing the registration.
+    ///
+    /// Use this to script your own handshake, e.g. to reject the login.
+    pub async fn accept_raw(&self) -> Result<MockConnection> {
+        self.incoming
+            .1
+            .recv()
+            .await
+            .ok_or_else(|| Error::new(ErrorKind::ConnectionAborted, "the server was closed"))
+    }
+}
+
+impl Connector for MockServer {
+    type Output = async_dup::Mutex<MockStream>;
+
+    fn connect(&mut self) -> crate::BoxedFuture<Result<Self::Output>> {
+        let (to_client, from_server) = crate::channel::unbounded();
+        let (to_server, from_client) = crate::channel::unbounded();
+
+        let conn = MockConnection {
+            to_client: Mutex::new(Some(to_client)),
+            from_client,
+            buf: Vec::new(),
+            name: String::new(),
+            password: String::new(),
+            capabilities: Vec::new(),
+        };
+
+        let stream = MockStream {
+            from_server,
+            to_server,
+            latency: self.latency,
+            delay: None,
+            pending: None,
+            buf: Vec::new(),
+        };
+
+        let incoming = Arc::clone(&self.incoming);
+        Box::pin(async move {
+            incoming
+                .0
+                .send(conn)
+                .await
+                .map_err(|_| Error::new(ErrorKind::ConnectionRefused, "the server was closed"))?;
+            Ok(async_dup::Mutex::new(stream))
+        })
+    }
+}
+
+/// The server's side of a connection to the [MockServer]
+pub struct MockConnection {
+    to_client: Mutex<Option<Sender<Chunk>>>,
+    from_client: Receiver<Vec<u8>>,
+    buf: Vec<u8>,
+    name: String,
+    password: String,
+    capabilities: Vec<String>,
+}
+
+impl std::fmt::Debug for MockConnection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MockConnection")
+            .field("name", &self.name)
+            .field("capabilities", &self.capabilities)
+            .finish()
+    }
+}
+
+impl MockConnection {
+    /// The name the client registered with (the `NICK`)
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The password the client registered with (the `PASS`)
+    pub fn password(&self) -> &str {
+        &self.password
+    }
+
+    /// The capabilities the client requested
+    pub fn capabilities(&self) -> &[String] {
+        &self.capabilities
+    }
+
+    /// Send a line to the client. The `\r\n` is added, if it's missing.
+    pub fn send(&self, line: impl AsRef<str>) -> Result<()> {
+        let line = line.as_ref();
+        let mut data = line.as_bytes().to_vec();
+        if !line.ends_with("\r\n") {
+            data.extend_from_slice(b"\r\n");
+        }
+
+        let to_client = self.to_client.lock().unwrap();
+        to_client
+            .as_ref()
+            .ok_or_else(|| Error::new(ErrorKind::NotConnected, "the connection was closed"))?
+            .try_send((Instant::now(), data))
+            .map_err(|_| Error::new(ErrorKind::BrokenPipe, "the client disconnected"))
+    }
+
+    /// Send a `RECONNECT` to the client and then close the connection, like Twitch does when it restarts a server
+    pub fn reconnect(&self) -> Result<()> {
+        self.send(":tmi.twitch.tv RECONNECT")?;
+        self.close();
+        Ok(())
+    }
+
+    /// Close the connection. The client reads an EOF once it has read everything sent before this.
+    pub fn close(&self) {
+        self.to_client.lock().unwrap().take();
+    }
+
+    /// Read the next line the client sent, including its `\r\n`.
+    ///
+    /// Like Twitch, a `JOIN` is answered with a `JOIN` and a `ROOMSTATE`, a `PART` with a `PART` and a `PING` with a `PONG`.
+    pub async fn read_line(&mut self) -> Result<String> {
+        let line = self.read_line_raw().await?;
+        self.respond(&line)?;
+        Ok(line)
+    }
+
+    /// Read the next line the client sent, if it has already sent one.
+    ///
+    /// Like [MockConnection::read_line()], this answers `JOIN`s, `PART`s and `PING`s.
+    pub fn try_read_line(&mut self) -> Result<Option<String>> {
+        while let Some(data) = self.from_client.try_recv() {
+            self.buf.extend_from_slice(&data);
+        }
+
+        let line = self.take_line()?;
+        if let Some(line) = &line {
+            self.respond(line)?;
+        }
+        Ok(line)
+    }
+
+    fn take_line(&mut self) -> Result<Option<String>> {
+        let pos = match self.buf.windows(2).position(|w| w == b"\r\n") {
+            Some(pos) => pos,
+            None => return Ok(None),
+        };
+        let line = self.buf.drain(..pos + 2).collect();
+        String::from_utf8(line)
+            .map(Some)
+            .map_err(|err| Error::new(ErrorKind::InvalidData, err))
+    }
+
+    /// Read the next line the client sent, without answering it.
+    pub async fn read_line_raw(&mut self) -> Result<String> {
+        loop {
+            if let Some(line) = self.take_line()? {
+                return Ok(line);
+            }
+
+            match self.from_client.recv().await {
+                Some(data) => self.buf.extend_from_slice(&data),
+                None => {
+                    return Err(Error::new(
+                        ErrorKind::UnexpectedEof,
+                        "the client disconnected",
+                    ))
+                }
+            }
+        }
+    }
+
+    fn respond(&self, line: &str) -> Result<()> {
+        let line = line.trim_end();
+        let name = &self.name;
+
+        if let Some(channels) = line.strip_prefix("JOIN ") {
+            for channel in channels.split(',') {
+                self.send(format!(
+                    ":{0}!{0}@{0}.tmi.twitch.tv JOIN {1}",
+                    name, channel
+                ))?;
+                self.send(format!(
+                    "@emote-only=0;followers-only=-1;r9k=0;room-id=1;slow=0;subs-only=0 :tmi.twitch.tv ROOMSTATE {}",
+                    channel
+                ))?;
+            }
+        } else if let Some(channel) = line.strip_prefix("PART ") {
+            self.send(format!(
+                ":{0}!{0}@{0}.tmi.twitch.tv PART {1}",
+                name, channel
+            ))?;
+        } else if let Some(token) = line.strip_prefix("PING ") {
+            self.send(format!(":tmi.twitch.tv PONG tmi.twitch.tv {}", token))?;
+        }
+        Ok(())
+    }
+
+    async fn handshake(&mut self) -> Result<()> {
+        while self.name.is_empty() {
+            let line = self.read_line_raw().await?;
+            let line = line.trim_end();
+
+            if let Some(caps) = line.strip_prefix("CAP REQ :") {
+                self.capabilities
+                    .extend(caps.split_whitespace().map(ToString::to_string));
+            } else if let Some(pass) = line.strip_prefix("PASS ") {
+                self.password = pass.to_string();
+            } else if let Some(name) = line.strip_prefix("NICK ") {
+                self.name = name.to_string();
+            }
+        }
+
+        for cap in &self.capabilities {
+            self.send(format!(":tmi.twitch.tv CAP * ACK :{}", cap))?;
+        }
+
+        let name = &self.name;
+        self.send(format!(":tmi.twitch.tv 001 {} :Welcome, GLHF!", name))?;
+        self.send(format!(":tmi.twitch.tv 376 {} :>", name))?;
+
+        let has_cap = |cap: &str| self.capabilities.iter().any(|c| c == cap);
+        if !name.starts_with("justinfan")
+            && has_cap("twitch.tv/tags")
+            && has_cap("twitch.tv/commands")
+        {
+            self.send(format!(
+                "@badge-info=;badges=;color=;display-name={};emote-sets=0;user-id=1234;user-type= :tmi.twitch.tv GLOBALUSERSTATE",
+                name
+            ))?;
+        }
+        Ok(())
+    }
+}
+
+/// The client's side of a connection to the [MockServer]
+pub struct MockStream {
+    from_server: Receiver<Chunk>,
+    to_server: Sender<Vec<u8>>,
+    latency: Duration,
+    delay: Option<Delay>,
+    pending: Option<Vec<u8>>,
+    buf: Vec<u8>,
+}
+
+impl std::fmt::Debug for MockStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MockStream")
+            .field("latency", &self.latency)
+            .finish()
+    }
+}
+
+impl AsyncRead for MockStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<Result<usize>> {
+        let this = self.get_mut();
+
+        loop {
+            if !this.buf.is_empty() {
+                let n = this.buf.len().min(buf.len());
+                buf[..n].copy_from_slice(&this.buf[..n]);
+                this.buf.drain(..n);
+                return Poll::Ready(Ok(n));
+            }
+
+            if let Some(delay) = &mut this.delay {
+                futures_lite::ready!(Pin::new(delay).poll(cx));
+                this.delay.take();
+                this.buf = this.pending.take().unwrap_or_default();
+                continue;
+            }
+
+            let (sent, data) =
+                match futures_lite::ready!(Pin::new(&mut this.from_server).poll_next(cx)) {
+                    Some(chunk) => chunk,
+                    None => return Poll::Ready(Ok(0)),
+                };
+
+            match (sent + this.latency).checked_duration_since(Instant::now()) {
+                Some(delay) if delay > Duration::from_secs(0) => {
+                    this.delay.replace(Delay::new(delay));
+                    this.pending.replace(data);
+                }
+                _ => this.buf = data,
+            }
+        }
+    }
+}
+
+impl AsyncWrite for MockStream {
+    fn poll_write(self: Pin<&mut Self>, _cx: &mut Context<'_>, buf: &[u8]) -> Poll<Result<usize>> {
+        let res = self
+            .to_server
+            .try_send(buf.to_vec())
+            .map(|_| buf.len())
+            .map_err(|_| Error::new(ErrorKind::BrokenPipe, "the server closed the connection"));
+        Poll::Ready(res)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<()>> {
+        self.from_server.close();
+        Poll::Ready(Ok(()))
+    }
+}
+
+// the user the tests log in as
+#[cfg(test)]
+pub(crate) fn user_config() -> UserConfig {
+    UserConfig::builder()
+        .name("shaken_bot")
+        .token(format!("oauth:{}", "a".repeat(30)))
+        .enable_all_capabilities()
+        .build()
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{commands, messages::Commands, runner::Error as RunnerError, Status};
+    use futures_lite::future::{block_on, zip};
+
+    #[test]
+    fn assert_connector_trait_is_fulfilled() {
+        use crate::connector::testing::*;
+        assert_obj_is_sane(MockServer::new());
+    }
+
+    #[test]
+    fn handshake_and_script() {
+        let server = MockServer::new();
+        block_on(async {
+            let (mut runner, mut conn) = server.connect_runner(&user_config()).await.unwrap();
+
+            assert_eq!(conn.name(), "shaken_bot");
+            assert!(conn.password().starts_with("oauth:"));
+            assert_eq!(conn.capabilities().len(), 3);
+            assert_eq!(runner.identity.username(), "shaken_bot");
+
+            let (joined, line) = zip(runner.join("museun"), conn.read_line()).await;
+            joined.unwrap();
+            assert_eq!(line.unwrap(), "JOIN #museun\r\n");
+            assert!(runner.is_on_channel("#museun"));
+            // without the `#`, or with the wrong case
+            assert!(runner.is_on_channel("Museun"));
+            assert!(runner.room_state("museun").is_some());
+            assert!(runner
+                .role(crate::twitch::ChannelName::from("museun"))
+                .is_some());
+
+            conn.send(":museun!museun@museun.tmi.twitch.tv PRIVMSG #museun :hello")
+                .unwrap();
+            // the messages from the handshake (and the join) are returned first
+            let msg = loop {
+                if let Status::Message(Commands::Privmsg(msg)) =
+                    runner.next_message().await.unwrap()
+                {
+                    break msg;
+                }
+            };
+            assert_eq!(msg.data(), "hello");
+
+            runner
+                .writer()
+                .encode(commands::privmsg("museun", "hi"))
+                .await
+                .unwrap();
+            let line = loop {
+                runner.step().await.unwrap();
+                if let Some(line) = conn.try_read_line().unwrap() {
+                    break line;
+                }
+            };
+            assert_eq!(line, "PRIVMSG #museun :hi\r\n");
+
+            conn.reconnect().unwrap();
+            assert!(matches!(
+                runner.next_message().await,
+                Err(RunnerError::ShouldReconnect)
+            ));
+        });
+    }
+}
@@ -0,0 +1,189 @@
+use crate::{
+    messages::Commands,
+    writer::{MessageSink, SinkFuture},
+    Encodable, FromIrcMessage as _, IntoOwned as _, MessageError,
+};
+
+use std::{
+    io,
+    sync::{Arc, Mutex},
+};
+
+/// A writer which records the messages sent to it, instead of sending them anywhere.
+///
+/// This lets you test your bot's logic without a connection: give it one of these instead of a writer, and check what it would have sent.
+/// It implements [MessageSink], so code written against that works with it unchanged. Clones share the same record.
+///
+/// ```
+/// # use twitchchat::{commands, test::TestWriter, writer::MessageSink};
+/// async fn greet(sink: &mut impl MessageSink, channel: &str) -> std::io::Result<()> {
+///     sink.send(commands::privmsg(channel, "hello")).await
+/// }
+///
+/// # futures_lite::future::block_on(async {
+/// let mut writer = TestWriter::new();
+/// greet(&mut writer, "museun").await.unwrap();
+///
+/// assert_eq!(writer.sent(), vec!["PRIVMSG #museun :hello"]);
+/// assert_eq!(writer.messages_to("#museun"), vec!["hello"]);
+/// # });
+/// ```
+#[derive(Clone)]
+pub struct TestWriter {
+    name: Arc<str>,
+    sent: Arc<Mutex<Vec<String>>>,
+}
+
+impl std::fmt::Debug for TestWriter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TestWriter")
+            .field("name", &self.name)
+            .field("sent", &self.sent.lock().unwrap().len())
+            .finish()
+    }
+}
+
+impl Default for TestWriter {
+    fn default() -> Self {
+        Self::with_name("test_bot")
+    }
+}
+
+impl TestWriter {
+    /// Create a new, empty TestWriter
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a new, empty TestWriter, for a bot with this name.
+    ///
+    /// The name is only used by [TestWriter::commands()], as the sender of the messages.
+    pub fn with_name(name: &str) -> Self {
+        Self {
+            name: name.into(),
+            sent: Arc::default(),
+        }
+    }
+
+    /// Encode this message, and record it
+    pub async fn encode<M>(&mut self, msg: M) -> io::Result<()>
+    where
+        M: Encodable + Send + Sync,
+    {
+        let mut data = Vec::new();
+        msg.encode(&mut data)?;
+        self.record(&data)
+    }
+
+    /// Every line recorded so far, without the trailing `\r\n`
+    pub fn sent(&self) -> Vec<String> {
+        self.sent.lock().unwrap().clone()
+    }
+
+    /// Take every line recorded so far, leaving the writer empty
+    pub fn take(&self) -> Vec<String> {
+        std::mem::take(&mut *self.sent.lock().unwrap())
+    }
+
+    /// The text of every `PRIVMSG` sent to this channel (with or without its leading `#`)
+    pub fn messages_to(&self, channel: &str) -> Vec<String> {
+        let channel = channel.trim_start_matches('#');
+        self.sent
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(|line| {
+                let rest = line.strip_prefix("PRIVMSG #")?;
+                let (target, data) = rest.split_at(rest.find(" :")?);
+                if !target.eq_ignore_ascii_case(channel) {
+                    return None;
+                }
+                Some(data[2..].to_string())
+            })
+            .collect()
+    }
+
+    /// Parse every line recorded so far back into [Commands], as Twitch would echo them from this bot.
+    pub fn commands(&self) -> Result<Vec<Commands<'static>>, MessageError> {
+        let name = &*self.name;
+        self.sent
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|line| {
+                let line = format!(":{0}!{0}@{0}.tmi.twitch.tv {1}\r\n", name, line);
+                let (_, msg) = crate::irc::parse_one(&line)?;
+                Commands::from_irc(msg).map(|msg| msg.into_owned())
+            })
+            .collect()
+    }
+
+    fn record(&self, data: &[u8]) -> io::Result<()> {
+        let data = std::str::from_utf8(data)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        self.sent.lock().unwrap().extend(
+            data.split("\r\n")
+                .filter(|line| !line.is_empty())
+                .map(ToString::to_string),
+        );
+        Ok(())
+    }
+}
+
+impl MessageSink for TestWriter {
+    fn send_bytes<'a>(&'a mut self, data: &'a [u8]) -> SinkFuture<'a> {
+        let res = self.record(data);
+        Box::pin(async move { res })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands;
+    use futures_lite::future::block_on;
+
+    #[test]
+    fn records_messages() {
+        let mut writer = TestWriter::with_name("shaken_bot");
+        let mut clone = writer.clone();
+
+        block_on(async {
+            writer.encode(commands::join("museun")).await.unwrap();
+            clone
+                .send(commands::privmsg("#museun", "hello"))
+                .await
+                .unwrap();
+            writer
+                .encode(commands::privmsg("shaken_bot", "world"))
+                .await
+                .unwrap();
+        });
+
+        assert_eq!(
+            writer.sent(),
+            vec![
+                "JOIN #museun",
+                "PRIVMSG #museun :hello",
+                "PRIVMSG #shaken_bot :world"
+            ]
+        );
+        assert_eq!(writer.messages_to("museun"), vec!["hello"]);
+        assert_eq!(writer.messages_to("#shaken_bot"), vec!["world"]);
+        assert!(writer.messages_to("#other").is_empty());
+
+        let commands = writer.commands().unwrap();
+        assert!(matches!(&commands[0], Commands::Join(msg) if msg.name() == "shaken_bot"));
+        match &commands[1] {
+            Commands::Privmsg(msg) => {
+                assert_eq!(msg.name(), "shaken_bot");
+                assert_eq!(msg.channel(), "#museun");
+                assert_eq!(msg.data(), "hello");
+            }
+            msg => panic!("unexpected message: {:?}", msg),
+        }
+
+        assert_eq!(clone.take().len(), 3);
+        assert!(writer.sent().is_empty());
+    }
+}
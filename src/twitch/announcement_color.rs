@@ -0,0 +1,64 @@
+/// The color used to highlight an announcement
+#[non_exhaustive]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+pub enum AnnouncementColor {
+    /// Use the channel's accent color
+    #[default]
+    Primary,
+    /// Blue
+    Blue,
+    /// Green
+    Green,
+    /// Orange
+    Orange,
+    /// Purple
+    Purple,
+}
+
+impl AnnouncementColor {
+    /// Parse a color, ignoring case. e.g. `PRIMARY` (from the `msg-param-color` tag) or `blue`
+    pub fn parse(input: &str) -> Option<Self> {
+        const COLORS: [AnnouncementColor; 5] = [
+            AnnouncementColor::Primary,
+            AnnouncementColor::Blue,
+            AnnouncementColor::Green,
+            AnnouncementColor::Orange,
+            AnnouncementColor::Purple,
+        ];
+        COLORS
+            .iter()
+            .copied()
+            .find(|color| color.as_str().eq_ignore_ascii_case(input))
+    }
+
+    /// The name of this color, as used by the Helix API. e.g. `primary`
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Primary => "primary",
+            Self::Blue => "blue",
+            Self::Green => "green",
+            Self::Orange => "orange",
+            Self::Purple => "purple",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        assert_eq!(
+            AnnouncementColor::parse("PRIMARY"),
+            Some(AnnouncementColor::Primary)
+        );
+        assert_eq!(
+            AnnouncementColor::parse("purple"),
+            Some(AnnouncementColor::Purple)
+        );
+        assert_eq!(AnnouncementColor::parse("red"), None);
+        assert_eq!(AnnouncementColor::parse(""), None);
+    }
+}
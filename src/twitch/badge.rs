@@ -1,3 +1,5 @@
+use crate::prelude::*;
+
 /// The kind of the [badges] that are associated with messages.
 ///
 /// Any unknown (e.g. custom badges/sub events, etc) are placed into the [Unknown] variant.
@@ -30,6 +32,8 @@ pub enum BadgeKind<'a> {
     VIP,
     /// Partner badge
     Partner,
+    /// Founder badge
+    Founder,
     /// Unknown badge. Likely a custom badge
     Unknown(&'a str),
 }
@@ -67,6 +71,7 @@ impl<'a> Badge<'a> {
             "premium" => Premium,
             "vip" => VIP,
             "partner" => Partner,
+            "founder" => Founder,
             badge => Unknown(badge),
         };
 
@@ -90,6 +95,7 @@ impl<'a> Badge<'a> {
             Premium => "premium",
             VIP => "vip",
             Partner => "partner",
+            Founder => "founder",
             Unknown(s) => s,
         }
     }
@@ -98,6 +104,111 @@ impl<'a> Badge<'a> {
 /// Metadata to the chat badges
 pub type BadgeInfo<'a> = Badge<'a>;
 
+/// A badge with its version, and any metadata from `badge-info`, parsed
+///
+/// Use [TypedBadge::parse_all] to parse the `badges` and `badge-info` tags together.
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+pub enum TypedBadge<'a> {
+    /// Broadcaster badge
+    Broadcaster,
+    /// Moderator badge
+    Moderator,
+    /// Subscriber badge
+    Subscriber {
+        /// The exact number of months the user has been subscribed.
+        ///
+        /// If there was no `badge-info`, this is the number of months of the badge
+        months: u64,
+    },
+    /// VIP badge
+    Vip,
+    /// Partner badge
+    Partner,
+    /// Bits badge
+    Bits {
+        /// The bits tier, e.g. `100` or `1000`
+        tier: u64,
+    },
+    /// Founder badge
+    Founder {
+        /// The number of months the user has been subscribed, if known
+        months: Option<u64>,
+    },
+    /// Staff badge
+    Staff,
+    /// Admin badge
+    Admin,
+    /// GlobalMod badge
+    GlobalMod,
+    /// Turbo badge
+    Turbo,
+    /// Premium badge
+    Premium,
+    /// Any other badge (e.g. custom badges), or a known badge with an invalid version
+    Unknown {
+        /// The badge set, e.g. `sub-gifter`
+        set: &'a str,
+        /// The version of the badge
+        version: &'a str,
+    },
+}
+
+impl<'a> TypedBadge<'a> {
+    /// Parse the `badges` tag, using the `badge-info` tag for the metadata
+    pub fn parse_all(badges: &'a str, badge_info: &'a str) -> Vec<Self> {
+        let info = badge_info
+            .split(',')
+            .filter_map(Badge::parse)
+            .collect::<Vec<_>>();
+
+        badges
+            .split(',')
+            .filter_map(Badge::parse)
+            .map(|badge| Self::from_badge(&badge, &info))
+            .collect()
+    }
+
+    /// Convert a [Badge], using the (optional) `badge-info` metadata
+    pub fn from_badge(badge: &Badge<'a>, info: &[BadgeInfo<'a>]) -> Self {
+        let months = || {
+            info.iter()
+                .find(|info| info.kind == badge.kind)
+                .and_then(|info| info.data.parse().ok())
+        };
+        let unknown = || Self::Unknown {
+            set: badge.kind_raw(),
+            version: badge.data,
+        };
+
+        match badge.kind {
+            BadgeKind::Broadcaster => Self::Broadcaster,
+            BadgeKind::Moderator => Self::Moderator,
+            BadgeKind::VIP => Self::Vip,
+            BadgeKind::Partner => Self::Partner,
+            BadgeKind::Staff => Self::Staff,
+            BadgeKind::Admin => Self::Admin,
+            BadgeKind::GlobalMod => Self::GlobalMod,
+            BadgeKind::Turbo => Self::Turbo,
+            BadgeKind::Premium => Self::Premium,
+            BadgeKind::Founder => Self::Founder { months: months() },
+            // tier 2 and 3 badges are offset by 2000 and 3000
+            BadgeKind::Subscriber => match months().or_else(|| badge.data.parse().ok()) {
+                Some(months) => Self::Subscriber {
+                    months: months % 1000,
+                },
+                None => unknown(),
+            },
+            BadgeKind::Bits => match badge.data.parse() {
+                Ok(tier) => Self::Bits { tier },
+                Err(..) => unknown(),
+            },
+            BadgeKind::Unknown(..) => unknown(),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -117,6 +228,7 @@ mod tests {
             ("premium", BadgeKind::Premium),
             ("vip", BadgeKind::VIP),
             ("partner", BadgeKind::Partner),
+            ("founder", BadgeKind::Founder),
             ("unknown", BadgeKind::Unknown("unknown")),
         ];
 
@@ -151,4 +263,40 @@ mod tests {
         let badge = Badge::parse(badge_str);
         assert_eq!(badge, None)
     }
+
+    #[test]
+    fn parse_typed() {
+        let badges = TypedBadge::parse_all(
+            "broadcaster/1,subscriber/3012,bits/1000,sub-gifter/50,vip/1",
+            "subscriber/14",
+        );
+        assert_eq!(
+            badges,
+            vec![
+                TypedBadge::Broadcaster,
+                TypedBadge::Subscriber { months: 14 },
+                TypedBadge::Bits { tier: 1000 },
+                TypedBadge::Unknown {
+                    set: "sub-gifter",
+                    version: "50"
+                },
+                TypedBadge::Vip,
+            ]
+        );
+
+        let badges = TypedBadge::parse_all("subscriber/2006,founder/0,bits/foo", "founder/3");
+        assert_eq!(
+            badges,
+            vec![
+                TypedBadge::Subscriber { months: 6 },
+                TypedBadge::Founder { months: Some(3) },
+                TypedBadge::Unknown {
+                    set: "bits",
+                    version: "foo"
+                },
+            ]
+        );
+
+        assert!(TypedBadge::parse_all("", "").is_empty());
+    }
 }
@@ -29,6 +29,15 @@ impl Capability {
         }
     }
 
+    /// The name of this capability, e.g. `twitch.tv/tags`
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Membership => "twitch.tv/membership",
+            Self::Tags => "twitch.tv/tags",
+            Self::Commands => "twitch.tv/commands",
+        }
+    }
+
     /// Attempts to 'parse' this capability from a string
     ///
     /// This will take the form of `twitch.tv/$tag` and produce a [Capability]
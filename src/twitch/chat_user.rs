@@ -0,0 +1,163 @@
+use super::{Login, UserId};
+use crate::prelude::*;
+use crate::MaybeOwned;
+
+/**
+Who sent a message: their login, their display name and their user id.
+
+Twitch users can change their display name's casing (`museun` to `MuseUn`), or use a localized (e.g. CJK) name which has nothing in common with their login.
+The tag is also sometimes empty, or has trailing whitespace. [ChatUser::best_name] handles these the way Twitch's own chat does.
+
+```rust
+# use twitchchat::twitch::ChatUser;
+let user = ChatUser::new("museun", Some("MuseUn"), Some(23196011));
+assert_eq!(user.best_name(), "MuseUn");
+
+let user = ChatUser::new("museun", Some("ミューズ"), Some(23196011));
+assert_eq!(user.best_name(), "ミューズ (museun)");
+
+let user = ChatUser::new("museun", Some(""), Some(23196011));
+assert_eq!(user.best_name(), "museun");
+```
+*/
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ChatUser<'a> {
+    login: &'a str,
+    display_name: Option<MaybeOwned<'a>>,
+    user_id: Option<u64>,
+}
+
+impl<'a> ChatUser<'a> {
+    /// Create a user from their login and (if known) display name and user id
+    pub fn new(
+        login: &'a str,
+        display_name: impl Into<Option<&'a str>>,
+        user_id: impl Into<Option<u64>>,
+    ) -> Self {
+        Self::from_parts(
+            login,
+            display_name.into().map(MaybeOwned::Borrowed),
+            user_id.into(),
+        )
+    }
+
+    pub(crate) fn from_parts(
+        login: &'a str,
+        display_name: Option<MaybeOwned<'a>>,
+        user_id: Option<u64>,
+    ) -> Self {
+        let display_name = display_name
+            .map(|name| match name {
+                MaybeOwned::Borrowed(name) => MaybeOwned::Borrowed(name.trim()),
+                MaybeOwned::Owned(name) => MaybeOwned::Owned(name.trim().into()),
+            })
+            .filter(|name| !name.is_empty());
+
+        Self {
+            login,
+            display_name,
+            user_id,
+        }
+    }
+
+    /// The user's login, e.g. `museun`. This is always lowercase ASCII
+    pub fn name(&self) -> &str {
+        self.login
+    }
+
+    /// The user's display name, if they have one. An empty `display-name` tag is `None`
+    pub fn display_name(&self) -> Option<&str> {
+        self.display_name.as_deref()
+    }
+
+    /// The user's id, if it's known
+    pub fn user_id(&self) -> Option<u64> {
+        self.user_id
+    }
+
+    /// The user's login, as a [Login]
+    pub fn login(&self) -> Login {
+        Login::from(self.login)
+    }
+
+    /// The user's id as a [UserId], if it's known
+    pub fn id(&self) -> Option<UserId> {
+        self.user_id.map(UserId::new)
+    }
+
+    /// Whether the display name is localized -- more than a change of casing of the login
+    pub fn is_localized(&self) -> bool {
+        self.display_name()
+            .map_or(false, |name| !name.eq_ignore_ascii_case(self.login))
+    }
+
+    /// The name to show for this user.
+    ///
+    /// This is their display name, unless it's empty (then it's their login). A [localized](ChatUser::is_localized) display name is followed by the login, as `display name (login)`.
+    pub fn best_name(&self) -> MaybeOwned<'_> {
+        match self.display_name() {
+            Some(name) if self.is_localized() => format!("{} ({})", name, self.login).into(),
+            Some(name) => name.into(),
+            None => self.login.into(),
+        }
+    }
+
+    /// Whether this is the same user as `other`.
+    ///
+    /// This compares their user ids, as users can change their login. If either id isn't known, the logins are compared (ignoring their case).
+    pub fn is_same_user(&self, other: &ChatUser<'_>) -> bool {
+        match (self.user_id, other.user_id) {
+            (Some(left), Some(right)) => left == right,
+            _ => self.login.eq_ignore_ascii_case(other.login),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn best_name() {
+        let cases = &[
+            (Some("MuseUn"), "MuseUn", false),
+            (Some("museun"), "museun", false),
+            (Some("  MuseUn "), "MuseUn", false),
+            (Some(""), "museun", false),
+            (Some(" "), "museun", false),
+            (None, "museun", false),
+            (Some("뮤즈"), "뮤즈 (museun)", true),
+            (Some("museun_"), "museun_ (museun)", true),
+        ];
+        for (display_name, expected, localized) in cases {
+            let user = ChatUser::new("museun", *display_name, None);
+            assert_eq!(user.best_name(), *expected, "{:?}", display_name);
+            assert_eq!(user.is_localized(), *localized, "{:?}", display_name);
+        }
+
+        let user = ChatUser::from_parts("museun", Some(MaybeOwned::Owned(" MuseUn ".into())), None);
+        assert_eq!(user.display_name(), Some("MuseUn"));
+    }
+
+    #[test]
+    fn is_same_user() {
+        let a = ChatUser::new("museun", None, Some(1));
+        let renamed = ChatUser::new("not_museun", None, Some(1));
+        let other = ChatUser::new("museun", None, Some(2));
+        assert!(a.is_same_user(&renamed));
+        assert!(!a.is_same_user(&other));
+
+        // without an id, by login
+        let no_id = ChatUser::new("MUSEUN", None, None);
+        assert!(a.is_same_user(&no_id));
+        assert!(!renamed.is_same_user(&no_id));
+    }
+
+    #[test]
+    fn typed_names() {
+        let user = ChatUser::new("museun", Some("MuseUn"), Some(23196011));
+        assert_eq!(user.login(), "museun");
+        assert_eq!(user.id(), Some(UserId::new(23196011)));
+        assert_eq!(ChatUser::new("museun", None, None).id(), None);
+    }
+}
@@ -0,0 +1,157 @@
+use core::ops::Range;
+
+/// A cheermote found in a message, e.g. `Cheer100`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+pub struct Cheermote<'a> {
+    /// The prefix of the cheermote, e.g. `Cheer`
+    pub prefix: &'a str,
+    /// How many bits were cheered with this cheermote
+    pub amount: u64,
+    /// The byte range in the message where this cheermote is found
+    pub range: Range<usize>,
+}
+
+impl<'a> Cheermote<'a> {
+    /// Try to parse a single word as a cheermote.
+    ///
+    /// A cheermote is a prefix (which is alphanumeric and contains at least one letter) immediately followed by a non-zero amount.
+    pub fn parse(word: &'a str) -> Option<Self> {
+        let split = word.trim_end_matches(|c: char| c.is_ascii_digit()).len();
+        let (prefix, amount) = word.split_at(split);
+        if amount.is_empty()
+            || amount.starts_with('0')
+            || !prefix.chars().all(|c| c.is_ascii_alphanumeric())
+            || !prefix.chars().any(|c| c.is_ascii_alphabetic())
+        {
+            return None;
+        }
+
+        Some(Self {
+            prefix,
+            amount: amount.parse().ok()?,
+            range: 0..word.len(),
+        })
+    }
+}
+
+/**
+An iterator over the cheermotes in a message
+
+Twitch only uses cheermotes when the `bits` tag is present, see `Privmsg::cheermotes()`.
+
+Channels can have their own cheermotes, so by default any word which looks like a cheermote is yielded. Use [Cheermotes::with_prefixes] to only match specific prefixes (e.g. ones fetched from the Helix API).
+
+# example:
+```rust
+# use twitchchat::twitch::Cheermotes;
+let data = "Cheer100 great stream! PogChamp1000 4Head50";
+let amounts = Cheermotes::new(data).map(|c| (c.prefix, c.amount)).collect::<Vec<_>>();
+assert_eq!(amounts, vec![("Cheer", 100), ("PogChamp", 1000), ("4Head", 50)]);
+
+let cheers = Cheermotes::with_prefixes(data, &["cheer"]).collect::<Vec<_>>();
+assert_eq!(cheers.len(), 1);
+assert_eq!(&data[cheers[0].range.clone()], "Cheer100");
+```
+*/
+#[derive(Debug, Clone)]
+pub struct Cheermotes<'a> {
+    data: &'a str,
+    pos: usize,
+    prefixes: Option<&'a [&'a str]>,
+}
+
+impl<'a> Cheermotes<'a> {
+    /// Find anything which looks like a cheermote in `data`
+    pub fn new(data: &'a str) -> Self {
+        Self {
+            data,
+            pos: 0,
+            prefixes: None,
+        }
+    }
+
+    /// Find cheermotes in `data` which use one of these `prefixes`.
+    ///
+    /// The prefixes are matched case-insensitively, like Twitch does.
+    pub fn with_prefixes(data: &'a str, prefixes: &'a [&'a str]) -> Self {
+        Self {
+            data,
+            pos: 0,
+            prefixes: Some(prefixes),
+        }
+    }
+
+    /// The total amount of bits of the cheermotes in this message
+    pub fn total(self) -> u64 {
+        self.map(|c| c.amount).sum()
+    }
+
+    fn is_known(&self, prefix: &str) -> bool {
+        self.prefixes.map_or(true, |prefixes| {
+            prefixes.iter().any(|p| p.eq_ignore_ascii_case(prefix))
+        })
+    }
+}
+
+impl<'a> Iterator for Cheermotes<'a> {
+    type Item = Cheermote<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.pos < self.data.len() {
+            let rest = &self.data[self.pos..];
+            let start = self.pos + (rest.len() - rest.trim_start().len());
+            let word = &self.data[start..];
+            let len = word.find(char::is_whitespace).unwrap_or(word.len());
+            self.pos = start + len;
+
+            match Cheermote::parse(&word[..len]) {
+                Some(cheer) if self.is_known(cheer.prefix) => {
+                    return Some(Cheermote {
+                        range: start..start + len,
+                        ..cheer
+                    });
+                }
+                _ => continue,
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let cheer = Cheermote::parse("Cheer100").unwrap();
+        assert_eq!(cheer.prefix, "Cheer");
+        assert_eq!(cheer.amount, 100);
+
+        for bad in &["Cheer", "100", "Cheer0", "Cheer010", "Che-er100", "", "!1"] {
+            assert_eq!(Cheermote::parse(bad), None, "{}", bad);
+        }
+    }
+
+    #[test]
+    fn iter() {
+        let data = "  \u{1F468} Cheer1  hello  cheerwhal100 Kappa\tuni5";
+        let cheers = Cheermotes::new(data).collect::<Vec<_>>();
+        let texts = cheers
+            .iter()
+            .map(|c| &data[c.range.clone()])
+            .collect::<Vec<_>>();
+        assert_eq!(texts, vec!["Cheer1", "cheerwhal100", "uni5"]);
+        assert_eq!(Cheermotes::new(data).total(), 106);
+
+        let cheers = Cheermotes::with_prefixes(data, &["CHEER", "uni"]);
+        assert_eq!(
+            cheers.map(|c| c.prefix).collect::<Vec<_>>(),
+            vec!["Cheer", "uni"]
+        );
+
+        assert_eq!(Cheermotes::new("").next(), None);
+        assert_eq!(Cheermotes::new("   ").next(), None);
+    }
+}
@@ -6,7 +6,7 @@ Both an RGB triplet and named Colors are provided.
 # RGB
 ## Try to parse a RGB from a #RRGGBB or RRGGBB
 ```
-# use std::str::FromStr as _;
+# use core::str::FromStr as _;
 # use twitchchat::twitch::color::RGB;
 let rgb: RGB = "#00FF19".parse().unwrap();
 assert_eq!(rgb.red(), 0x00);
@@ -22,7 +22,7 @@ assert_eq!(rgb.blue(), 0x19);
 
 ## Turning it back into a string
 ```
-# use std::str::FromStr as _;
+# use core::str::FromStr as _;
 # use twitchchat::twitch::color::RGB;
 let input = "#00FF19";
 let rgb: RGB = input.parse().unwrap();
@@ -32,7 +32,7 @@ assert_eq!(rgb.to_string(), input);
 # Color
 ## Try to parse a Color from a named color
 ```
-# use std::str::FromStr as _;
+# use core::str::FromStr as _;
 # use twitchchat::twitch::color::*;
 let input = "Blue Violet";
 let color: Color = input.parse().unwrap();
@@ -40,6 +40,24 @@ assert_eq!(color.rgb, RGB(0x8A, 0x2B, 0xE2));
 assert_eq!(color.kind, TwitchColor::BlueViolet);
 ```
 
+## Named colors are recognized from their RGB
+```
+# use twitchchat::twitch::color::*;
+let color: Color = "#0000FF".parse().unwrap();
+assert_eq!(color.kind, TwitchColor::Blue);
+
+// the RGB is always displayed as hex
+assert_eq!(color.rgb.to_string(), "#0000FF");
+```
+
+## Users without a color
+```
+# use twitchchat::twitch::color::*;
+// Twitch picks a default color from the user's name
+let color = Color::fallback("museun");
+assert_eq!(color, Color::fallback("museun"));
+```
+
 # Conversions
 ```
 # use twitchchat::twitch::color::*;
@@ -57,7 +75,8 @@ let rgb: RGB = twitch_color.into();
 ```
 */
 
-use std::{convert::TryFrom, str::FromStr};
+use crate::prelude::*;
+use core::{convert::TryFrom, str::FromStr};
 
 /// An error returned when trying to parse a string as an RGB triplet
 #[non_exhaustive]
@@ -69,8 +88,8 @@ pub enum ParseError {
     UnknownColor,
 }
 
-impl std::fmt::Display for ParseError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl core::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match self {
             Self::InvalidHexString => f.write_str("invalid hex string"),
             Self::UnknownColor => f.write_str("unknown color"),
@@ -78,7 +97,7 @@ impl std::fmt::Display for ParseError {
     }
 }
 
-impl std::error::Error for ParseError {}
+impl crate::StdError for ParseError {}
 
 impl FromStr for RGB {
     type Err = ParseError;
@@ -114,8 +133,8 @@ impl Default for RGB {
     }
 }
 
-impl std::fmt::Display for RGB {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl core::fmt::Display for RGB {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         let Self(r, g, b) = self;
         write!(f, "#{:02X}{:02X}{:02X}", r, g, b)
     }
@@ -198,7 +217,7 @@ These can be [parsed] from their **name** in
 - `"snake_case"`
 - `"lower case"`
 
-[parsed]: std::str::FromStr
+[parsed]: core::str::FromStr
 */
 #[derive(Debug, Copy, Clone, PartialEq, PartialOrd, Ord, Eq, Hash)]
 #[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
@@ -220,17 +239,15 @@ impl FromStr for Color {
 
         let mut s = input.replace(' ', "_");
         if !s.contains('_') {
-            if let Some(pos) = s
-                .chars()
+            if let Some((pos, _)) = s
+                .char_indices()
                 .skip(1)
-                .position(|d| d.is_ascii_uppercase())
-                .map(|d| d + 1)
+                .find(|(_, d)| d.is_ascii_uppercase())
             {
                 s.insert(pos, '_');
             }
         }
 
-        // TODO this isn't correct, its always parsing stuff as 'Turbo'
         let s = s.to_ascii_lowercase();
         let (kind, rgb) = match s.as_str() {
             "blue" => find(Blue),
@@ -248,13 +265,65 @@ impl FromStr for Color {
             "sea_green" => find(SeaGreen),
             "spring_green" => find(SpringGreen),
             "yellow_green" => find(YellowGreen),
-            _ => (Turbo, input.parse()?),
+            _ => {
+                // Twitch sends the named colors as hex, too
+                let rgb: RGB = input.parse()?;
+                (rgb.into(), rgb)
+            }
         };
 
         Ok(Self { kind, rgb })
     }
 }
 
+impl Color {
+    /// The color Twitch shows for a user who hasn't set one.
+    ///
+    /// Twitch picks one of its named colors from the first and last characters of the `login`, so this is always the same for the same user.
+    ///
+    /// ```
+    /// # use twitchchat::twitch::color::*;
+    /// let color = Color::fallback("museun");
+    /// assert_eq!(color, Color::fallback("Museun"));
+    /// assert_ne!(color.kind, TwitchColor::Turbo);
+    /// ```
+    pub fn fallback(login: &str) -> Self {
+        use TwitchColor::*;
+        // the order Twitch uses for its default colors
+        const DEFAULTS: [TwitchColor; 15] = [
+            Red,
+            Blue,
+            Green,
+            Firebrick,
+            Coral,
+            YellowGreen,
+            OrangeRed,
+            SeaGreen,
+            GoldenRod,
+            Chocolate,
+            CadetBlue,
+            DodgerBlue,
+            HotPink,
+            BlueViolet,
+            SpringGreen,
+        ];
+
+        let login = login.as_bytes();
+        let n = match (login.first(), login.last()) {
+            (Some(first), Some(last)) => {
+                first.to_ascii_lowercase() as usize + last.to_ascii_lowercase() as usize
+            }
+            _ => 0,
+        };
+
+        let kind = DEFAULTS[n % DEFAULTS.len()];
+        Self {
+            kind,
+            rgb: kind.into(),
+        }
+    }
+}
+
 impl Default for Color {
     /// Defaults to having a kind of [Turbo] and RGB of #FFFFFF (white)
     ///
@@ -267,8 +336,8 @@ impl Default for Color {
     }
 }
 
-impl std::fmt::Display for Color {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl core::fmt::Display for Color {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         use TwitchColor::*;
         let name = match self.kind {
             Blue => "Blue",
@@ -474,6 +543,30 @@ mod tests {
         assert_eq!(color.to_string(), "#FAFAFA");
     }
 
+    #[test]
+    fn parse_named_hex_color() {
+        for (kind, rgb) in twitch_colors().iter() {
+            let color: Color = rgb.to_string().parse().unwrap();
+            assert_eq!(color.kind, *kind);
+            assert_eq!(color.rgb, *rgb);
+            assert_eq!(color.rgb.to_string(), rgb.to_string());
+        }
+    }
+
+    #[test]
+    fn fallback_color() {
+        // 'm' + 'n' (109 + 110) % 15 == 9
+        assert_eq!(Color::fallback("museun").kind, TwitchColor::Chocolate);
+        assert_eq!(Color::fallback("MUSEUN"), Color::fallback("museun"));
+        assert_eq!(Color::fallback("").kind, TwitchColor::Red);
+
+        for name in &["a", "shaken_bot", "justinfan1234"] {
+            let color = Color::fallback(name);
+            assert_ne!(color.kind, TwitchColor::Turbo);
+            assert_eq!(color.rgb, RGB::from(color.kind));
+        }
+    }
+
     #[test]
     fn fields() {
         let rgb = RGB(0x27, 255, 82);
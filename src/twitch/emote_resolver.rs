@@ -0,0 +1,530 @@
+use super::{EmoteRange, EmoteRanges};
+use crate::prelude::*;
+
+use alloc::collections::BTreeMap;
+
+/// An emote found in a message, with the URL of its image
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+pub struct ResolvedEmote<'a> {
+    /// The Twitch emote id, or `None` for a third-party emote
+    pub id: Option<usize>,
+    /// The index of the first character of this emote
+    pub start: usize,
+    /// The index one past the last character of this emote
+    pub end: usize,
+    /// The text of the emote, e.g. `Kappa`
+    pub text: &'a str,
+    /// The URL of the emote's image
+    pub url: String,
+}
+
+/**
+Resolves the emotes in a message to the URLs of their images, e.g. for an overlay to render them.
+
+Twitch's own emotes come from the message's `emotes` tag. Third-party emotes (e.g. from BTTV, FFZ or 7TV) are only words in the message, so every other word is offered to [EmoteResolver::third_party_emote].
+
+Both methods return `None` by default, so a resolver only implements what it knows. Resolvers can be combined with a tuple (or a `Vec`), and the first one to return a URL is used:
+
+```rust
+# use twitchchat::twitch::{EmoteMap, EmoteResolver};
+struct MyTwitchEmotes;
+impl EmoteResolver for MyTwitchEmotes {
+    fn twitch_emote(&self, id: usize, _name: &str) -> Option<String> {
+        Some(format!("https://example.com/{}.png", id))
+    }
+}
+
+let mut bttv = EmoteMap::new();
+bttv.insert_global("catJAM", "https://cdn.betterttv.net/emote/5f1b0186cf6d2144653d2970/3x");
+
+let resolver = (MyTwitchEmotes, bttv);
+assert_eq!(resolver.twitch_emote(25, "Kappa").as_deref(), Some("https://example.com/25.png"));
+assert!(resolver.third_party_emote("#museun", "catJAM").is_some());
+```
+
+See [resolve_emotes] (or `Privmsg::resolve_emotes()`) to resolve a message.
+*/
+pub trait EmoteResolver {
+    /// The image URL of this Twitch emote (by its id from the `emotes` tag, and its text)
+    fn twitch_emote(&self, id: usize, name: &str) -> Option<String> {
+        let _ = (id, name);
+        None
+    }
+
+    /// The image URL of this word, if it's a third-party emote in this channel (with its leading `#`)
+    fn third_party_emote(&self, channel: &str, word: &str) -> Option<String> {
+        let _ = (channel, word);
+        None
+    }
+}
+
+impl<T> EmoteResolver for &T
+where
+    T: EmoteResolver + ?Sized,
+{
+    fn twitch_emote(&self, id: usize, name: &str) -> Option<String> {
+        (**self).twitch_emote(id, name)
+    }
+
+    fn third_party_emote(&self, channel: &str, word: &str) -> Option<String> {
+        (**self).third_party_emote(channel, word)
+    }
+}
+
+impl<T> EmoteResolver for Box<T>
+where
+    T: EmoteResolver + ?Sized,
+{
+    fn twitch_emote(&self, id: usize, name: &str) -> Option<String> {
+        (**self).twitch_emote(id, name)
+    }
+
+    fn third_party_emote(&self, channel: &str, word: &str) -> Option<String> {
+        (**self).third_party_emote(channel, word)
+    }
+}
+
+impl<T> EmoteResolver for Vec<T>
+where
+    T: EmoteResolver,
+{
+    fn twitch_emote(&self, id: usize, name: &str) -> Option<String> {
+        self.iter().find_map(|r| r.twitch_emote(id, name))
+    }
+
+    fn third_party_emote(&self, channel: &str, word: &str) -> Option<String> {
+        self.iter().find_map(|r| r.third_party_emote(channel, word))
+    }
+}
+
+macro_rules! tuple_resolver {
+    ($($ty:ident)*) => {
+        #[allow(non_snake_case)]
+        impl<$($ty: EmoteResolver),*> EmoteResolver for ($($ty,)*) {
+            fn twitch_emote(&self, id: usize, name: &str) -> Option<String> {
+                let ($($ty,)*) = self;
+                None$(.or_else(|| $ty.twitch_emote(id, name)))*
+            }
+
+            fn third_party_emote(&self, channel: &str, word: &str) -> Option<String> {
+                let ($($ty,)*) = self;
+                None$(.or_else(|| $ty.third_party_emote(channel, word)))*
+            }
+        }
+    };
+}
+
+tuple_resolver!(A B);
+tuple_resolver!(A B C);
+tuple_resolver!(A B C D);
+
+/**
+A set of third-party emotes (by their names), global or for specific channels.
+
+This is where BTTV, FFZ or 7TV emotes are plugged in: fetch them from the provider's API, and insert their URLs (with the `emote-cdn` feature, `ThirdPartyCdn::url` builds them for the provider's CDN).
+
+```rust
+# use twitchchat::twitch::{EmoteMap, EmoteResolver};
+let mut emotes = EmoteMap::new();
+emotes.insert_global("catJAM", "https://example.com/catJAM");
+emotes.insert("#museun", "museunHi", "https://example.com/museunHi");
+
+assert!(emotes.third_party_emote("#museun", "catJAM").is_some());
+assert!(emotes.third_party_emote("museun", "museunHi").is_some());
+assert!(emotes.third_party_emote("#shaken_bot", "museunHi").is_none());
+```
+*/
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EmoteMap {
+    global: BTreeMap<String, String>,
+    channels: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+impl EmoteMap {
+    /// Create an empty set of emotes
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an emote which is used in every channel
+    pub fn insert_global(&mut self, name: impl Into<String>, url: impl Into<String>) {
+        self.global.insert(name.into(), url.into());
+    }
+
+    /// Add an emote which is only used in this channel. A channel's emote is used over a global one with the same name.
+    pub fn insert(&mut self, channel: &str, name: impl Into<String>, url: impl Into<String>) {
+        self.channels
+            .entry(channel_key(channel))
+            .or_default()
+            .insert(name.into(), url.into());
+    }
+
+    /// Remove every emote for this channel (e.g. before inserting the updated ones)
+    pub fn clear_channel(&mut self, channel: &str) {
+        self.channels.remove(&channel_key(channel));
+    }
+
+    /// How many emotes there are, global and for every channel
+    pub fn len(&self) -> usize {
+        self.global.len() + self.channels.values().map(BTreeMap::len).sum::<usize>()
+    }
+
+    /// Whether there are no emotes
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl EmoteResolver for EmoteMap {
+    fn third_party_emote(&self, channel: &str, word: &str) -> Option<String> {
+        self.channels
+            .get(&channel_key(channel))
+            .and_then(|emotes| emotes.get(word))
+            .or_else(|| self.global.get(word))
+            .cloned()
+    }
+}
+
+fn channel_key(channel: &str) -> String {
+    channel.trim_start_matches('#').to_ascii_lowercase()
+}
+
+/// Resolve the emotes in this message `data` (with its `emotes` tag) in this channel, in the order they appear.
+///
+/// Emotes which the resolver has no URL for are skipped.
+///
+/// ```rust
+/// # use twitchchat::twitch::{resolve_emotes, EmoteMap, EmoteResolver};
+/// struct Twitch;
+/// impl EmoteResolver for Twitch {
+///     fn twitch_emote(&self, id: usize, _name: &str) -> Option<String> {
+///         Some(id.to_string())
+///     }
+/// }
+///
+/// let mut third_party = EmoteMap::new();
+/// third_party.insert_global("catJAM", "catJAM");
+///
+/// let emotes = resolve_emotes("Kappa hello catJAM", "25:0-4", "#museun", &(Twitch, third_party));
+/// let emotes = emotes.iter().map(|e| (e.id, e.text, &*e.url)).collect::<Vec<_>>();
+/// assert_eq!(emotes, vec![(Some(25), "Kappa", "25"), (None, "catJAM", "catJAM")]);
+/// ```
+pub fn resolve_emotes<'a, R>(
+    data: &'a str,
+    emotes: &str,
+    channel: &str,
+    resolver: &R,
+) -> Vec<ResolvedEmote<'a>>
+where
+    R: EmoteResolver + ?Sized,
+{
+    let chars = data.char_indices().map(|(i, _)| i).collect::<Vec<_>>();
+    let byte_offset = |pos: usize| chars.get(pos).copied().unwrap_or(data.len());
+
+    let mut out = vec![];
+    let third_party = |start: usize, end: usize| {
+        let text = &data[byte_offset(start)..byte_offset(end)];
+        resolver
+            .third_party_emote(channel, text)
+            .map(|url| ResolvedEmote {
+                id: None,
+                start,
+                end,
+                text,
+                url,
+            })
+    };
+
+    let mut twitch = EmoteRanges::new(data, emotes).peekable();
+    let mut word = None;
+    let mut pos = 0;
+    while pos <= chars.len() {
+        let at_emote = twitch.peek().filter(|emote| emote.start == pos).is_some();
+        let at_space = pos == chars.len()
+            || data[byte_offset(pos)..]
+                .chars()
+                .next()
+                .map_or(true, char::is_whitespace);
+
+        if at_emote || at_space {
+            if let Some(start) = word.take() {
+                out.extend(third_party(start, pos));
+            }
+        }
+
+        if at_emote {
+            let EmoteRange {
+                id,
+                start,
+                end,
+                text,
+                ..
+            } = twitch.next().expect("peeked");
+            out.extend(resolver.twitch_emote(id, text).map(|url| ResolvedEmote {
+                id: Some(id),
+                start,
+                end,
+                text,
+                url,
+            }));
+            pos = end;
+            continue;
+        }
+
+        if !at_space && word.is_none() {
+            word.replace(pos);
+        }
+        pos += 1;
+    }
+
+    out
+}
+
+/// The size of an emote's image on Twitch's CDN
+#[cfg(feature = "emote-cdn")]
+#[cfg_attr(docsrs, doc(cfg(feature = "emote-cdn")))]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum EmoteScale {
+    /// 28x28 pixels
+    Small,
+    /// 56x56 pixels
+    Medium,
+    /// 112x112 pixels
+    Large,
+}
+
+/// The background an emote's image is for, on Twitch's CDN
+#[cfg(feature = "emote-cdn")]
+#[cfg_attr(docsrs, doc(cfg(feature = "emote-cdn")))]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum EmoteTheme {
+    /// A light background
+    Light,
+    /// A dark background
+    Dark,
+}
+
+/// The format of an emote's image on Twitch's CDN
+#[cfg(feature = "emote-cdn")]
+#[cfg_attr(docsrs, doc(cfg(feature = "emote-cdn")))]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum EmoteFormat {
+    /// Animated, if the emote is animated. Otherwise static
+    Default,
+    /// Always static
+    Static,
+    /// Always animated. Twitch has no image for emotes which aren't animated
+    Animated,
+}
+
+/**
+Resolves Twitch emotes to their images on Twitch's CDN (`static-cdn.jtvnw.net`).
+
+```rust
+# use twitchchat::twitch::{EmoteResolver, EmoteScale, EmoteTheme, TwitchCdn};
+let cdn = TwitchCdn::new().scale(EmoteScale::Large).theme(EmoteTheme::Light);
+assert_eq!(
+    cdn.twitch_emote(25, "Kappa").unwrap(),
+    "https://static-cdn.jtvnw.net/emoticons/v2/25/default/light/3.0"
+);
+```
+*/
+#[cfg(feature = "emote-cdn")]
+#[cfg_attr(docsrs, doc(cfg(feature = "emote-cdn")))]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct TwitchCdn {
+    scale: EmoteScale,
+    theme: EmoteTheme,
+    format: EmoteFormat,
+}
+
+#[cfg(feature = "emote-cdn")]
+impl Default for TwitchCdn {
+    fn default() -> Self {
+        Self {
+            scale: EmoteScale::Small,
+            theme: EmoteTheme::Dark,
+            format: EmoteFormat::Default,
+        }
+    }
+}
+
+#[cfg(feature = "emote-cdn")]
+impl TwitchCdn {
+    /// The small, dark, (possibly) animated images
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Use images of this size
+    pub fn scale(mut self, scale: EmoteScale) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    /// Use images for this background
+    pub fn theme(mut self, theme: EmoteTheme) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    /// Use images in this format
+    pub fn format(mut self, format: EmoteFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// The URL of this emote's image
+    pub fn url(&self, id: usize) -> String {
+        let format = match self.format {
+            EmoteFormat::Default => "default",
+            EmoteFormat::Static => "static",
+            EmoteFormat::Animated => "animated",
+        };
+        let theme = match self.theme {
+            EmoteTheme::Light => "light",
+            EmoteTheme::Dark => "dark",
+        };
+        let scale = match self.scale {
+            EmoteScale::Small => "1.0",
+            EmoteScale::Medium => "2.0",
+            EmoteScale::Large => "3.0",
+        };
+        format!(
+            "https://static-cdn.jtvnw.net/emoticons/v2/{}/{}/{}/{}",
+            id, format, theme, scale
+        )
+    }
+}
+
+#[cfg(feature = "emote-cdn")]
+impl EmoteResolver for TwitchCdn {
+    fn twitch_emote(&self, id: usize, _name: &str) -> Option<String> {
+        Some(self.url(id))
+    }
+}
+
+/// The CDNs of the third-party emote providers, for the URLs of the emotes in an [EmoteMap]
+///
+/// ```rust
+/// # use twitchchat::twitch::ThirdPartyCdn;
+/// assert_eq!(
+///     ThirdPartyCdn::Bttv.url("5f1b0186cf6d2144653d2970"),
+///     "https://cdn.betterttv.net/emote/5f1b0186cf6d2144653d2970/3x"
+/// );
+/// ```
+#[cfg(feature = "emote-cdn")]
+#[cfg_attr(docsrs, doc(cfg(feature = "emote-cdn")))]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ThirdPartyCdn {
+    /// BetterTTV
+    Bttv,
+    /// FrankerFaceZ
+    Ffz,
+    /// 7TV
+    SevenTv,
+}
+
+#[cfg(feature = "emote-cdn")]
+impl ThirdPartyCdn {
+    /// The URL of the largest image of the emote with this (provider's) id
+    pub fn url(&self, id: &str) -> String {
+        match self {
+            Self::Bttv => format!("https://cdn.betterttv.net/emote/{}/3x", id),
+            Self::Ffz => format!("https://cdn.frankerfacez.com/emote/{}/4", id),
+            Self::SevenTv => format!("https://cdn.7tv.app/emote/{}/4x.webp", id),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Ids;
+    impl EmoteResolver for Ids {
+        fn twitch_emote(&self, id: usize, _name: &str) -> Option<String> {
+            Some(id.to_string())
+        }
+    }
+
+    fn resolve(
+        data: &str,
+        emotes: &str,
+        resolver: &dyn EmoteResolver,
+    ) -> Vec<(usize, usize, String)> {
+        resolve_emotes(data, emotes, "#museun", resolver)
+            .into_iter()
+            .map(|e| (e.start, e.end, e.url))
+            .collect()
+    }
+
+    #[test]
+    fn twitch_and_third_party() {
+        let mut map = EmoteMap::new();
+        map.insert_global("catJAM", "cat");
+        map.insert("museun", "hi", "hi");
+        let resolver = (Ids, map);
+
+        let data = "\u{1F642} Kappa catJAM  hi hello Kappa";
+        assert_eq!(
+            resolve(data, "25:2-6,24-28", &resolver),
+            vec![
+                (2, 7, "25".to_string()),
+                (8, 14, "cat".to_string()),
+                (16, 18, "hi".to_string()),
+                (24, 29, "25".to_string()),
+            ]
+        );
+
+        // without a `emotes` tag, and only third-party emotes
+        assert_eq!(
+            resolve("catJAM", "", &resolver),
+            vec![(0, 6, "cat".to_string())]
+        );
+        // a Twitch emote ends the word before it
+        assert_eq!(
+            resolve("catJAMKappa", "25:6-10", &resolver),
+            vec![(0, 6, "cat".to_string()), (6, 11, "25".to_string())]
+        );
+        // emotes nothing resolves are skipped
+        assert!(resolve("Kappa catJAM", "25:0-4", &EmoteMap::new()).is_empty());
+    }
+
+    #[test]
+    fn channel_emotes() {
+        let mut map = EmoteMap::new();
+        map.insert_global("hi", "global");
+        map.insert("#MUSEUN", "hi", "channel");
+        assert_eq!(map.third_party_emote("#museun", "hi").unwrap(), "channel");
+        assert_eq!(map.third_party_emote("#other", "hi").unwrap(), "global");
+        assert_eq!(map.len(), 2);
+
+        map.clear_channel("museun");
+        assert_eq!(map.third_party_emote("#museun", "hi").unwrap(), "global");
+    }
+
+    #[test]
+    #[cfg(feature = "emote-cdn")]
+    fn cdn() {
+        let cdn = TwitchCdn::new();
+        assert_eq!(
+            resolve("Kappa", "25:0-4", &cdn)[0].2,
+            "https://static-cdn.jtvnw.net/emoticons/v2/25/default/dark/1.0"
+        );
+        let cdn = cdn.format(EmoteFormat::Static).scale(EmoteScale::Medium);
+        assert_eq!(
+            cdn.url(25),
+            "https://static-cdn.jtvnw.net/emoticons/v2/25/static/dark/2.0"
+        );
+        assert_eq!(
+            ThirdPartyCdn::SevenTv.url("abc"),
+            "https://cdn.7tv.app/emote/abc/4x.webp"
+        );
+    }
+}
@@ -1,4 +1,5 @@
-use std::ops::Range;
+use crate::prelude::*;
+use core::ops::Range;
 
 /**
 Emotes are little pictograms used in-line in Twitch messages
@@ -39,6 +40,125 @@ impl Emotes {
     }
 }
 
+/// An emote found in a message, resolved against the message text
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+pub struct EmoteRange<'a> {
+    /// This emote id, e.g. `Kappa = 25`
+    pub id: usize,
+    /// The index of the first character of this emote
+    pub start: usize,
+    /// The index one past the last character of this emote
+    pub end: usize,
+    /// The text of the emote, e.g. `Kappa`
+    pub text: &'a str,
+    bytes: Range<usize>,
+}
+
+/**
+An iterator over the emotes in a message, ordered by their position
+
+Twitch gives the positions as *character* offsets, rather than byte offsets, so slicing the message with them directly breaks on multi-byte UTF-8. This resolves them to the correct substrings.
+
+# example:
+```rust
+# use twitchchat::twitch::EmoteRanges;
+let emotes = EmoteRanges::new("\u{1F642} Kappa testing Kappa", "25:2-6,16-20");
+assert_eq!(emotes.clone().map(|e| e.text).collect::<Vec<_>>(), vec!["Kappa", "Kappa"]);
+assert_eq!(emotes.clone().replace(|_| ":)"), "\u{1F642} :) testing :)");
+assert_eq!(emotes.strip(), "\u{1F642} testing");
+```
+*/
+#[derive(Debug, Clone)]
+pub struct EmoteRanges<'a> {
+    data: &'a str,
+    inner: vec::IntoIter<EmoteRange<'a>>,
+}
+
+impl<'a> EmoteRanges<'a> {
+    /// Resolve the `emotes` tag against the message `data`.
+    ///
+    /// Ranges which are out of bounds, or which overlap a previous emote, are skipped.
+    pub fn new(data: &'a str, emotes: &str) -> Self {
+        let offsets = data
+            .char_indices()
+            .map(|(i, _)| i)
+            .chain(core::iter::once(data.len()))
+            .collect::<Vec<_>>();
+
+        let mut ranges = Emotes::parse(emotes)
+            .flat_map(|Emotes { id, ranges }| ranges.into_iter().map(move |r| (id, r)))
+            .filter_map(|(id, range)| {
+                // twitch uses an inclusive end
+                let (start, end) = (range.start as usize, range.end as usize + 1);
+                let bytes = *offsets.get(start)?..*offsets.get(end)?;
+                Some(EmoteRange {
+                    id,
+                    start,
+                    end,
+                    text: data.get(bytes.clone())?,
+                    bytes,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        ranges.sort_by_key(|r| r.start);
+        let mut last = 0;
+        ranges.retain(|r| {
+            let keep = r.start >= last;
+            if keep {
+                last = r.end;
+            }
+            keep
+        });
+
+        Self {
+            data,
+            inner: ranges.into_iter(),
+        }
+    }
+
+    /// Produce the message with each (remaining) emote replaced by the output of `replace`
+    pub fn replace<F, S>(self, mut replace: F) -> String
+    where
+        F: FnMut(&EmoteRange<'a>) -> S,
+        S: AsRef<str>,
+    {
+        let data = self.data;
+        let mut out = String::with_capacity(data.len());
+        let mut pos = 0;
+        for emote in self {
+            out.push_str(&data[pos..emote.bytes.start]);
+            out.push_str(replace(&emote).as_ref());
+            pos = emote.bytes.end;
+        }
+        out.push_str(&data[pos..]);
+        out
+    }
+
+    /// Produce the message with each (remaining) emote removed.
+    ///
+    /// This also collapses the whitespace left behind.
+    pub fn strip(self) -> String {
+        self.replace(|_| "")
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+impl<'a> Iterator for EmoteRanges<'a> {
+    type Item = EmoteRange<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
 #[inline]
 fn get_parts(input: &str, sep: char) -> Option<(&str, &str)> {
     let mut split = input.split_terminator(sep);
@@ -103,4 +223,38 @@ mod tests {
             assert_eq!(emotes, *expect);
         }
     }
+
+    #[test]
+    fn ranges() {
+        let data = "Kappa \u{1F468}\u{1F469} VoHiYo Kappa";
+        let emotes = EmoteRanges::new(data, "25:0-4,16-20/81274:9-14");
+        let emotes = emotes.map(|e| (e.id, e.start, e.end, e.text));
+        assert_eq!(
+            emotes.collect::<Vec<_>>(),
+            vec![
+                (25, 0, 5, "Kappa"),
+                (81274, 9, 15, "VoHiYo"),
+                (25, 16, 21, "Kappa"),
+            ]
+        );
+    }
+
+    #[test]
+    fn ranges_out_of_bounds() {
+        let emotes = EmoteRanges::new("Kappa", "25:0-4,6-10/33:2-3");
+        assert_eq!(emotes.map(|e| e.text).collect::<Vec<_>>(), vec!["Kappa"]);
+    }
+
+    #[test]
+    fn replace_and_strip() {
+        let data = "\u{FFFD}Kappa hello  VoHiYo";
+        let emotes = EmoteRanges::new(data, "25:1-5/81274:14-19");
+        assert_eq!(
+            emotes.clone().replace(|e| format!("[{}]", e.id)),
+            "\u{FFFD}[25] hello  [81274]"
+        );
+        assert_eq!(emotes.strip(), "\u{FFFD} hello");
+
+        assert_eq!(EmoteRanges::new("hello", "").replace(|_| "x"), "hello");
+    }
 }
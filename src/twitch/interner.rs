@@ -0,0 +1,107 @@
+use std::{
+    collections::HashSet,
+    sync::{Arc, Mutex},
+};
+
+/// An interner for strings that are repeated across many messages, e.g. channel names, user logins and badge names.
+///
+/// Each distinct string is stored once, and shared as an `Arc<str>`. This is useful when keeping a history of messages around:
+/// intern the fields you keep, rather than holding on to the whole (owned) message.
+///
+/// The interner is shared between its clones.
+///
+/// ```
+/// use twitchchat::{FromIrcMessage, messages::Privmsg, twitch::Interner};
+/// use std::sync::Arc;
+///
+/// struct Line {
+///     channel: Arc<str>,
+///     login: Arc<str>,
+///     text: String,
+/// }
+///
+/// let interner = Interner::default();
+/// let mut history = Vec::new();
+///
+/// let input = ":museun!museun@museun PRIVMSG #museun :hello\r\n:museun!museun@museun PRIVMSG #museun :world\r\n";
+/// for msg in twitchchat::irc::parse(input) {
+///     let pm = Privmsg::from_irc(msg.unwrap()).unwrap();
+///     history.push(Line {
+///         channel: interner.intern(pm.channel()),
+///         login: interner.intern(pm.name()),
+///         text: pm.data().to_string(),
+///     });
+/// }
+///
+/// // both lines share the same allocation for the channel and the login
+/// assert!(Arc::ptr_eq(&history[0].channel, &history[1].channel));
+/// assert_eq!(interner.len(), 2);
+///
+/// assert_eq!(history[0].text, "hello");
+/// assert_eq!(history[1].text, "world");
+/// ```
+#[derive(Default, Clone)]
+pub struct Interner {
+    strings: Arc<Mutex<HashSet<Arc<str>>>>,
+}
+
+impl std::fmt::Debug for Interner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Interner")
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+impl Interner {
+    /// Get the shared copy of this string, storing it if it hasn't been seen before
+    pub fn intern(&self, s: &str) -> Arc<str> {
+        let mut strings = self.strings.lock().unwrap();
+        if let Some(s) = strings.get(s) {
+            return Arc::clone(s);
+        }
+        let s: Arc<str> = s.into();
+        strings.insert(Arc::clone(&s));
+        s
+    }
+
+    /// How many distinct strings are stored
+    pub fn len(&self) -> usize {
+        self.strings.lock().unwrap().len()
+    }
+
+    /// Are there no strings stored?
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Forget the strings that are only held by the interner, e.g. after old messages were dropped from a history
+    pub fn shrink(&self) {
+        self.strings
+            .lock()
+            .unwrap()
+            .retain(|s| Arc::strong_count(s) > 1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn intern() {
+        let interner = Interner::default();
+
+        let a = interner.intern("museun");
+        let b = interner.clone().intern("museun");
+        assert!(Arc::ptr_eq(&a, &b));
+
+        let c = interner.intern("shaken_bot");
+        assert_eq!(interner.len(), 2);
+
+        drop((a, b));
+        interner.shrink();
+        assert_eq!(interner.len(), 1);
+        assert!(Arc::ptr_eq(&c, &interner.intern("shaken_bot")));
+    }
+}
@@ -1,16 +1,55 @@
 //! Common Twitch types
 
+use crate::prelude::*;
+
 mod capability;
 pub use capability::Capability;
 
-mod userconfig;
-pub use userconfig::{UserConfig, UserConfigBuilder, UserConfigError};
+cfg_std! {
+    mod userconfig;
+    pub use userconfig::{UserConfig, UserConfigBuilder, UserConfigError};
+}
+
+cfg_std! {
+    mod token_provider;
+    pub use token_provider::TokenProvider;
+}
+
+cfg_std! {
+    mod interner;
+    pub use interner::Interner;
+}
+
+mod announcement_color;
+pub use announcement_color::AnnouncementColor;
 
 mod emotes;
-pub use emotes::Emotes;
+pub use emotes::{EmoteRange, EmoteRanges, Emotes};
+
+mod emote_resolver;
+pub use emote_resolver::{resolve_emotes, EmoteMap, EmoteResolver, ResolvedEmote};
+
+#[cfg(feature = "emote-cdn")]
+#[cfg_attr(docsrs, doc(cfg(feature = "emote-cdn")))]
+pub use emote_resolver::{EmoteFormat, EmoteScale, EmoteTheme, ThirdPartyCdn, TwitchCdn};
+
+mod cheermotes;
+pub use cheermotes::{Cheermote, Cheermotes};
+
+mod shared_chat;
+pub use shared_chat::SharedChatSource;
 
 mod badge;
-pub use badge::{Badge, BadgeInfo, BadgeKind};
+pub use badge::{Badge, BadgeInfo, BadgeKind, TypedBadge};
+
+mod role;
+pub use role::Role;
+
+mod chat_user;
+pub use chat_user::ChatUser;
+
+mod names;
+pub use names::{ChannelName, Login, NameError, UserId};
 
 pub mod color;
 #[doc(inline)]
@@ -26,6 +65,22 @@ pub(crate) fn parse_badges(input: &str) -> Vec<Badge<'_>> {
     input.split(',').filter_map(Badge::parse).collect()
 }
 
+#[allow(dead_code)]
+pub(crate) fn parse_typed_badges<'a>(tags: &crate::irc::Tags<'a>) -> Vec<TypedBadge<'a>> {
+    let badges = tags.get("badges").unwrap_or_default();
+    TypedBadge::parse_all(badges, tags.get("badge-info").unwrap_or_default())
+}
+
+#[allow(dead_code)]
+pub(crate) fn subscriber_months(tags: &crate::irc::Tags<'_>) -> Option<u64> {
+    parse_typed_badges(tags)
+        .into_iter()
+        .find_map(|badge| match badge {
+            TypedBadge::Subscriber { months } => Some(months),
+            _ => None,
+        })
+}
+
 #[allow(dead_code)]
 pub(crate) fn parse_emotes_iter(input: &str) -> impl Iterator<Item = Emotes> + '_ {
     Emotes::parse(input)
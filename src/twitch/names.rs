@@ -0,0 +1,398 @@
+use crate::prelude::*;
+
+use core::{fmt, ops::Deref, str::FromStr};
+
+// the longest login Twitch allows
+const MAX_LOGIN_LENGTH: usize = 25;
+
+/// An error returned when a [ChannelName] or [Login] isn't a valid Twitch name
+#[non_exhaustive]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum NameError {
+    /// The name was empty
+    Empty,
+    /// The name was longer than Twitch allows (25 characters)
+    TooLong,
+    /// The name contained a character Twitch doesn't allow (only ASCII letters, digits and `_` are allowed)
+    InvalidCharacter(char),
+}
+
+impl fmt::Display for NameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => f.write_str("the name was empty"),
+            Self::TooLong => write!(
+                f,
+                "the name was longer than {} characters",
+                MAX_LOGIN_LENGTH
+            ),
+            Self::InvalidCharacter(ch) => {
+                write!(f, "the name contained an invalid character: {:?}", ch)
+            }
+        }
+    }
+}
+
+impl crate::StdError for NameError {}
+
+fn validate(name: &str) -> Result<(), NameError> {
+    if name.is_empty() {
+        return Err(NameError::Empty);
+    }
+    if let Some(ch) = name
+        .chars()
+        .find(|&ch| !ch.is_ascii_alphanumeric() && ch != '_')
+    {
+        return Err(NameError::InvalidCharacter(ch));
+    }
+    if name.len() > MAX_LOGIN_LENGTH {
+        return Err(NameError::TooLong);
+    }
+    Ok(())
+}
+
+macro_rules! name_impls {
+    ($ty:ident) => {
+        impl Deref for $ty {
+            type Target = str;
+            fn deref(&self) -> &Self::Target {
+                &self.0
+            }
+        }
+
+        impl AsRef<str> for $ty {
+            fn as_ref(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl core::borrow::Borrow<str> for $ty {
+            fn borrow(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl fmt::Display for $ty {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(&self.0)
+            }
+        }
+
+        impl From<&str> for $ty {
+            fn from(name: &str) -> Self {
+                Self::normalize(name)
+            }
+        }
+
+        impl From<&&str> for $ty {
+            fn from(name: &&str) -> Self {
+                Self::normalize(name)
+            }
+        }
+
+        impl From<&String> for $ty {
+            fn from(name: &String) -> Self {
+                Self::normalize(name)
+            }
+        }
+
+        impl From<String> for $ty {
+            fn from(name: String) -> Self {
+                Self::normalize(&name)
+            }
+        }
+
+        impl From<&$ty> for $ty {
+            fn from(name: &$ty) -> Self {
+                name.clone()
+            }
+        }
+
+        impl From<$ty> for String {
+            fn from(name: $ty) -> Self {
+                name.0
+            }
+        }
+
+        impl FromStr for $ty {
+            type Err = NameError;
+            fn from_str(name: &str) -> Result<Self, Self::Err> {
+                Self::new(name)
+            }
+        }
+
+        // these compare the normalized names, so `"Museun"` is the same as `#museun`
+        impl PartialEq<str> for $ty {
+            fn eq(&self, other: &str) -> bool {
+                self.0 == Self::normalize(other).0
+            }
+        }
+
+        impl PartialEq<&str> for $ty {
+            fn eq(&self, other: &&str) -> bool {
+                self == *other
+            }
+        }
+
+        impl PartialEq<$ty> for str {
+            fn eq(&self, other: &$ty) -> bool {
+                other == self
+            }
+        }
+
+        impl PartialEq<$ty> for &str {
+            fn eq(&self, other: &$ty) -> bool {
+                other == *self
+            }
+        }
+    };
+}
+
+/**
+A channel name, which always has its leading `#` and is lowercase.
+
+Twitch only knows `#museun`, so sending to (or looking up) `museun` or `#Museun` silently does nothing. Every API which takes a channel accepts anything which converts into this, and `From<&str>` adds the `#` and lowercases it.
+
+[ChannelName::new] also validates the name:
+
+```rust
+# use twitchchat::twitch::{ChannelName, NameError};
+let channel = ChannelName::from("Museun");
+assert_eq!(channel.as_str(), "#museun");
+assert_eq!(channel.name(), "museun");
+assert_eq!(channel, "museun");
+
+assert_eq!(ChannelName::new("#museun").unwrap(), channel);
+assert_eq!(ChannelName::new("#museun, #other").unwrap_err(), NameError::InvalidCharacter(','));
+```
+*/
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(
+    feature = "serde",
+    derive(::serde::Serialize, ::serde::Deserialize),
+    serde(from = "String", into = "String")
+)]
+pub struct ChannelName(String);
+
+impl ChannelName {
+    /// Create a channel, with or without its leading `#`.
+    ///
+    /// This returns an error if the name (without its `#`) isn't a valid Twitch login.
+    pub fn new(channel: &str) -> Result<Self, NameError> {
+        let this = Self::normalize(channel);
+        validate(this.name())?;
+        Ok(this)
+    }
+
+    /// The channel, with its leading `#`
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// The channel's name, without its leading `#`. This is the broadcaster's login
+    pub fn name(&self) -> &str {
+        &self.0[1..]
+    }
+
+    /// The broadcaster's login
+    pub fn login(&self) -> Login {
+        Login(self.name().to_string())
+    }
+
+    fn normalize(channel: &str) -> Self {
+        let channel = channel.trim();
+        let name = channel.strip_prefix('#').unwrap_or(channel);
+        Self(format!("#{}", name.to_lowercase()))
+    }
+}
+
+name_impls!(ChannelName);
+
+impl From<Login> for ChannelName {
+    fn from(login: Login) -> Self {
+        Self(format!("#{}", login.0))
+    }
+}
+
+impl From<&Login> for ChannelName {
+    fn from(login: &Login) -> Self {
+        Self(format!("#{}", login.0))
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'a> From<&'a ChannelName> for crate::commands::Channel<'a> {
+    fn from(channel: &'a ChannelName) -> Self {
+        Self::new(channel.as_str())
+    }
+}
+
+/**
+A user's login, which is lowercase and has no leading `#` (or `@`).
+
+Unlike their display name, this is what Twitch uses in commands (e.g. `/ban museun`). [Login::new] also validates the name.
+
+```rust
+# use twitchchat::twitch::{ChannelName, Login};
+let login = Login::from("@Museun");
+assert_eq!(login.as_str(), "museun");
+assert_eq!(ChannelName::from(&login), "#museun");
+
+assert!(Login::new("museun").is_ok());
+assert!(Login::new("").is_err());
+```
+*/
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(
+    feature = "serde",
+    derive(::serde::Serialize, ::serde::Deserialize),
+    serde(from = "String", into = "String")
+)]
+pub struct Login(String);
+
+impl Login {
+    /// Create a login, with or without a leading `@` (or `#`).
+    ///
+    /// This returns an error if it isn't a valid Twitch login.
+    pub fn new(login: &str) -> Result<Self, NameError> {
+        let this = Self::normalize(login);
+        validate(&this.0)?;
+        Ok(this)
+    }
+
+    /// The login
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    fn normalize(login: &str) -> Self {
+        let login = login.trim();
+        let login = login.trim_start_matches(['@', '#']);
+        Self(login.to_lowercase())
+    }
+}
+
+name_impls!(Login);
+
+/**
+A user's id.
+
+Users can change their login, but not their id, so this is what to compare (and store) users by.
+
+```rust
+# use twitchchat::twitch::UserId;
+let id: UserId = "23196011".parse().unwrap();
+assert_eq!(id, UserId::new(23196011));
+assert_eq!(id.to_string(), "23196011");
+assert!("museun".parse::<UserId>().is_err());
+```
+*/
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(
+    feature = "serde",
+    derive(::serde::Serialize, ::serde::Deserialize),
+    serde(transparent)
+)]
+pub struct UserId(u64);
+
+impl UserId {
+    /// Create a user id
+    pub const fn new(id: u64) -> Self {
+        Self(id)
+    }
+
+    /// The id, as a number
+    pub const fn get(self) -> u64 {
+        self.0
+    }
+}
+
+impl From<u64> for UserId {
+    fn from(id: u64) -> Self {
+        Self(id)
+    }
+}
+
+impl From<UserId> for u64 {
+    fn from(id: UserId) -> Self {
+        id.0
+    }
+}
+
+impl FromStr for UserId {
+    type Err = core::num::ParseIntError;
+    fn from_str(id: &str) -> Result<Self, Self::Err> {
+        id.trim().parse().map(Self)
+    }
+}
+
+impl fmt::Display for UserId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl PartialEq<u64> for UserId {
+    fn eq(&self, other: &u64) -> bool {
+        self.0 == *other
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn channel() {
+        for input in &["museun", "#museun", "MUSEUN", " #Museun "] {
+            let channel = ChannelName::from(*input);
+            assert_eq!(channel.as_str(), "#museun");
+            assert_eq!(channel.name(), "museun");
+            assert_eq!(ChannelName::new(input).unwrap(), channel);
+            assert_eq!(channel, *input);
+            assert_eq!(&*channel.login(), "museun");
+        }
+
+        assert_eq!(ChannelName::new("#").unwrap_err(), NameError::Empty);
+        assert_eq!(ChannelName::new("").unwrap_err(), NameError::Empty);
+        assert_eq!(
+            ChannelName::new("mu seun").unwrap_err(),
+            NameError::InvalidCharacter(' ')
+        );
+        assert_eq!(
+            ChannelName::new(&"a".repeat(26)).unwrap_err(),
+            NameError::TooLong
+        );
+        assert!("#museun".parse::<ChannelName>().is_ok());
+
+        // it's still a str
+        let channel = ChannelName::from("museun");
+        assert!(channel.starts_with('#'));
+        #[cfg(feature = "std")]
+        assert_eq!(
+            crate::commands::Channel::from(&channel).to_string(),
+            "#museun"
+        );
+    }
+
+    #[test]
+    fn login() {
+        for input in &["museun", "@museun", "#museun", "MuseUn"] {
+            assert_eq!(Login::from(*input).as_str(), "museun");
+            assert_eq!(Login::new(input).unwrap(), "museun");
+        }
+        assert_eq!(Login::new("@").unwrap_err(), NameError::Empty);
+        assert_eq!(
+            Login::new("ミューズ").unwrap_err(),
+            NameError::InvalidCharacter('ミ')
+        );
+        assert_eq!(ChannelName::from(Login::from("museun")), "#museun");
+    }
+
+    #[test]
+    fn user_id() {
+        assert_eq!(" 42 ".parse::<UserId>().unwrap(), 42);
+        assert_eq!(u64::from(UserId::new(42)), 42);
+        assert!("-1".parse::<UserId>().is_err());
+    }
+}
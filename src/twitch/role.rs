@@ -0,0 +1,169 @@
+use super::{parse_badges_iter, BadgeKind};
+use crate::irc::Tags;
+
+/// The role a user has in a channel, from their badges.
+///
+/// These are ordered, so permission checks can be written as comparisons:
+/// ```
+/// # use twitchchat::twitch::Role;
+/// let role = Role::parse("moderator/1,subscriber/3012", "subscriber/14");
+/// assert!(role >= Role::Moderator);
+/// assert!(role > Role::Vip);
+/// # assert!(role < Role::Broadcaster);
+/// // any subscriber is above everyone, and below a vip
+/// assert!(Role::Subscriber { tier: 1, months: 1 } > Role::Everyone);
+/// assert!(Role::Subscriber { tier: 3, months: 48 } < Role::Vip);
+/// ```
+///
+/// Subscribers are ordered by their tier, and then by how long they've been subscribed.
+#[non_exhaustive]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+pub enum Role {
+    /// Anyone
+    #[default]
+    Everyone,
+    /// A subscriber (or a founder)
+    Subscriber {
+        /// The tier, `1`, `2` or `3`
+        tier: u8,
+        /// How many months they've been subscribed, if Twitch said
+        months: u64,
+    },
+    /// A vip
+    Vip,
+    /// A moderator
+    Moderator,
+    /// The broadcaster
+    Broadcaster,
+}
+
+impl Role {
+    /// Parse the role from the `badges` tag, using the `badge-info` tag for how long they've been subscribed
+    pub fn parse(badges: &str, badge_info: &str) -> Self {
+        let badges = parse_badges_iter(badges);
+        let months = |kind: BadgeKind<'_>| {
+            parse_badges_iter(badge_info)
+                .find(|info| info.kind == kind)
+                .and_then(|info| info.data.parse::<u64>().ok())
+        };
+
+        badges
+            .filter_map(|badge| {
+                let role = match badge.kind {
+                    BadgeKind::Broadcaster => Self::Broadcaster,
+                    BadgeKind::Moderator => Self::Moderator,
+                    BadgeKind::VIP => Self::Vip,
+                    // tier 2 and 3 badges are offset by 2000 and 3000
+                    BadgeKind::Subscriber => {
+                        let version = badge.data.parse::<u64>().unwrap_or_default();
+                        Self::Subscriber {
+                            tier: core::cmp::max(1, (version / 1000) as u8),
+                            months: months(BadgeKind::Subscriber).unwrap_or(version % 1000),
+                        }
+                    }
+                    BadgeKind::Founder => Self::Subscriber {
+                        tier: 1,
+                        months: months(BadgeKind::Founder).unwrap_or_default(),
+                    },
+                    _ => return None,
+                };
+                Some(role)
+            })
+            .max()
+            .unwrap_or_default()
+    }
+
+    // users can hide their vip badge, and USERSTATE has a `mod` tag, so those are checked as well
+    pub(crate) fn from_tags(tags: &Tags<'_>) -> Self {
+        let role = Self::parse(
+            tags.get("badges").unwrap_or_default(),
+            tags.get("badge-info").unwrap_or_default(),
+        );
+        let role = if tags.get_as_bool("mod") {
+            core::cmp::max(role, Self::Moderator)
+        } else {
+            role
+        };
+        if tags.get("vip").is_some() {
+            core::cmp::max(role, Self::Vip)
+        } else {
+            role
+        }
+    }
+
+    /// Whether this role gets the elevated (moderator) rate limit
+    ///
+    /// This is true for moderators, vips and the broadcaster.
+    pub fn is_elevated(&self) -> bool {
+        *self >= Self::Vip
+    }
+
+    /// Whether this role can moderate the channel (moderators and the broadcaster)
+    pub fn is_moderator(&self) -> bool {
+        *self >= Self::Moderator
+    }
+
+    /// Whether this role is a subscriber, or higher
+    pub fn is_subscriber(&self) -> bool {
+        *self >= Self::Subscriber { tier: 0, months: 0 }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        assert_eq!(Role::parse("", ""), Role::Everyone);
+        assert_eq!(Role::parse("bits/100,turbo/1", ""), Role::Everyone);
+        assert_eq!(
+            Role::parse("subscriber/3012", "subscriber/14"),
+            Role::Subscriber {
+                tier: 3,
+                months: 14
+            }
+        );
+        assert_eq!(
+            Role::parse("subscriber/6", ""),
+            Role::Subscriber { tier: 1, months: 6 }
+        );
+        assert_eq!(
+            Role::parse("founder/0", "founder/3"),
+            Role::Subscriber { tier: 1, months: 3 }
+        );
+        assert_eq!(
+            Role::parse("subscriber/2024,vip/1", "subscriber/24"),
+            Role::Vip
+        );
+        assert_eq!(
+            Role::parse("broadcaster/1,moderator/1", ""),
+            Role::Broadcaster
+        );
+
+        assert!(
+            Role::Subscriber { tier: 2, months: 1 }
+                > Role::Subscriber {
+                    tier: 1,
+                    months: 48
+                }
+        );
+        assert!(Role::Subscriber { tier: 1, months: 0 }.is_subscriber());
+        assert!(!Role::Everyone.is_subscriber());
+        assert!(Role::Vip.is_elevated() && !Role::Vip.is_moderator());
+    }
+
+    #[test]
+    fn tags() {
+        use crate::{messages::*, FromIrcMessage as _};
+
+        let input = "@badges=;mod=1 :tmi.twitch.tv USERSTATE #museun\r\n";
+        let msg = crate::irc::parse_one(input).unwrap().1;
+        assert_eq!(UserState::from_irc(msg).unwrap().role(), Role::Moderator);
+
+        let input = "@badges=subscriber/6;vip=1 :museun!museun@museun PRIVMSG #museun :hi\r\n";
+        let msg = crate::irc::parse_one(input).unwrap().1;
+        assert_eq!(Privmsg::from_irc(msg).unwrap().author_role(), Role::Vip);
+    }
+}
@@ -0,0 +1,43 @@
+use super::{parse_badges, Badge, BadgeInfo};
+use crate::irc::Tags;
+use crate::prelude::*;
+
+/// Where a message in a Shared Chat session came from.
+///
+/// During a Shared Chat session, messages from every participating channel are sent to each of them. These tags describe the channel that the message was originally sent to.
+///
+/// If `room_id` is the same as the message's `room-id` then the message was sent to this channel.
+#[non_exhaustive]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+pub struct SharedChatSource<'a> {
+    /// The id of the room the message was sent to
+    pub room_id: u64,
+    /// The id of the message in the source room
+    pub id: Option<&'a str>,
+    /// The badges the user has in the source room
+    pub badges: Vec<Badge<'a>>,
+    /// The badge metadata the user has in the source room
+    pub badge_info: Vec<BadgeInfo<'a>>,
+    /// Whether the message was only sent to the source room
+    pub source_only: bool,
+}
+
+impl<'a> SharedChatSource<'a> {
+    /// Parse the `source-*` tags, this is `None` if there is no `source-room-id`
+    pub fn from_tags(tags: &Tags<'a>) -> Option<Self> {
+        Some(Self {
+            room_id: tags.get_parsed("source-room-id")?,
+            id: tags.get("source-id"),
+            badges: tags
+                .get("source-badges")
+                .map(parse_badges)
+                .unwrap_or_default(),
+            badge_info: tags
+                .get("source-badge-info")
+                .map(parse_badges)
+                .unwrap_or_default(),
+            source_only: tags.get_as_bool("source-only"),
+        })
+    }
+}
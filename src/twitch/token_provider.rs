@@ -0,0 +1,32 @@
+use crate::BoxedFuture;
+use std::io::Result;
+
+/// A source of OAuth tokens, used to (re)register with Twitch.
+///
+/// Implement this if you want to store (and refresh) your tokens somewhere specific, e.g. a database or a secrets manager.
+///
+/// The token can be returned with or without the `oauth:` prefix, it'll be added when registering.
+///
+/// See `oauth::RefreshingToken` (behind the `oauth` feature) for an implementation using a refresh token.
+pub trait TokenProvider: Send + Sync {
+    /// Get the current access token.
+    ///
+    /// This is called before every connection attempt. If the token is about to expire, this should refresh it.
+    fn token(&mut self) -> BoxedFuture<Result<String>>;
+
+    /// Twitch rejected the current access token, get a new one.
+    fn refresh(&mut self) -> BoxedFuture<Result<String>>;
+}
+
+impl<T> TokenProvider for Box<T>
+where
+    T: TokenProvider + ?Sized,
+{
+    fn token(&mut self) -> BoxedFuture<Result<String>> {
+        (**self).token()
+    }
+
+    fn refresh(&mut self) -> BoxedFuture<Result<String>> {
+        (**self).refresh()
+    }
+}
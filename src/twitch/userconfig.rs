@@ -1,5 +1,13 @@
 use crate::twitch::Capability;
-use std::collections::BTreeSet;
+use std::{collections::BTreeSet, time::Duration};
+
+// how long Twitch gets to finish the login, by default
+const LOGIN_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[cfg(feature = "serde")]
+const fn login_timeout() -> Duration {
+    LOGIN_TIMEOUT
+}
 
 /**
 User configuration for 'registering' with Twitch
@@ -15,6 +23,8 @@ It contains your user name, your OAuth token and the capabilities you want to re
 # std::env::set_var("TWITCH_TOKEN", format!("oauth:{}", "a".repeat(30)));
 // as anonymous
 let config = UserConfig::builder().anonymous().build().unwrap();
+// or as anonymous, with a random name
+let config = UserConfig::anonymous();
 // or with a name/token
 let name = std::env::var("TWITCH_NAME").unwrap();
 let token = std::env::var("TWITCH_TOKEN").unwrap();
@@ -35,6 +45,11 @@ pub struct UserConfig {
     pub token: String,
     /// Capabilities to be requested from the server
     pub capabilities: Vec<Capability>,
+    /// How long Twitch gets to finish the login (acknowledging the capabilities, and sending `GLOBALUSERSTATE` or the end of the MOTD) after connecting.
+    ///
+    /// If it takes longer, connecting fails with [LoginTimedOut](crate::runner::Error::LoginTimedOut). This defaults to 30 seconds.
+    #[cfg_attr(feature = "serde", serde(default = "login_timeout"))]
+    pub login_timeout: Duration,
 }
 
 impl UserConfig {
@@ -43,12 +58,49 @@ impl UserConfig {
         UserConfigBuilder::default()
     }
 
+    /// Create an anonymous (read-only) config, with a random `justinfanNNNN` name.
+    ///
+    /// No `PASS` is sent when registering with this config. Twitch ignores anything an anonymous user sends, so the [AsyncRunner](crate::AsyncRunner)'s writer will refuse to send messages.
+    ///
+    /// No capabilities are requested, but you can add them to [capabilities](UserConfig::capabilities).
+    pub fn anonymous() -> Self {
+        Self {
+            name: format!("justinfan{}", random_suffix()),
+            token: String::new(),
+            capabilities: Vec::new(),
+            login_timeout: LOGIN_TIMEOUT,
+        }
+    }
+
     /// Determines whether this config was requested as anonymous
     pub fn is_anonymous(&self) -> bool {
-        self.name == crate::JUSTINFAN1234 && self.token == crate::JUSTINFAN1234
+        is_anonymous_name(&self.name)
+            && (self.token.is_empty()
+                || self.token == self.name
+                || self.token == crate::JUSTINFAN1234)
     }
 }
 
+// 'justinfan' followed by only digits
+fn is_anonymous_name(name: &str) -> bool {
+    name.strip_prefix("justinfan")
+        .filter(|s| !s.is_empty() && s.bytes().all(|c| c.is_ascii_digit()))
+        .is_some()
+}
+
+// a random number between 10000 and 99999
+fn random_suffix() -> u64 {
+    use std::hash::{BuildHasher as _, Hasher as _};
+    let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+    // there is no clock on wasm32-unknown-unknown, `SystemTime::now` panics there
+    if cfg!(not(all(target_arch = "wasm32", target_os = "unknown"))) {
+        if let Ok(now) = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH) {
+            hasher.write_u128(now.as_nanos());
+        }
+    }
+    10_000 + hasher.finish() % 90_000
+}
+
 /// User config error returned by the [UserConfigBuilder]
 #[non_exhaustive]
 #[derive(Debug, Copy, Clone)]
@@ -84,6 +136,7 @@ pub struct UserConfigBuilder {
     capabilities: BTreeSet<Capability>,
     name: Option<String>,
     token: Option<String>,
+    login_timeout: Option<Duration>,
 }
 
 impl UserConfigBuilder {
@@ -114,11 +167,18 @@ impl UserConfigBuilder {
 
     /// Capabilities to enable
     ///
+    /// When connecting, the runner checks that Twitch acknowledged each of these and returns an error if it didn't.
     pub fn capabilities(mut self, caps: &[Capability]) -> Self {
         self.capabilities.extend(caps);
         self
     }
 
+    /// A capability to enable
+    pub fn capability(mut self, cap: Capability) -> Self {
+        self.capabilities.insert(cap);
+        self
+    }
+
     /// Enables all of the capabilities.
     ///
     /// This is just a shortcut for enabling all of the Capabilities listed [here].
@@ -132,6 +192,14 @@ impl UserConfigBuilder {
         ])
     }
 
+    /// How long Twitch gets to finish the login after connecting. This defaults to 30 seconds.
+    ///
+    /// See [UserConfig::login_timeout]
+    pub fn login_timeout(mut self, timeout: Duration) -> Self {
+        self.login_timeout.replace(timeout);
+        self
+    }
+
     /// Tries to build the UserConfig
     ///
     /// This returns an error if the name or token are invalid
@@ -164,6 +232,7 @@ impl UserConfigBuilder {
             name,
             token,
             capabilities: self.capabilities.into_iter().collect(),
+            login_timeout: self.login_timeout.unwrap_or(LOGIN_TIMEOUT),
         })
     }
 }
@@ -199,6 +268,7 @@ mod tests {
                 name: "foo".to_string(),
                 token: format!("oauth:{}", "a".repeat(30)),
                 capabilities: vec![],
+                login_timeout: LOGIN_TIMEOUT,
             }
         )
     }
@@ -219,10 +289,28 @@ mod tests {
                 name: "foo".to_string(),
                 token: format!("oauth:{}", "a".repeat(30)),
                 capabilities: vec![Capability::Membership, Capability::Tags,],
+                login_timeout: LOGIN_TIMEOUT,
             }
         )
     }
 
+    #[test]
+    fn valid_user_config_capability() {
+        let config = UserConfig::builder()
+            .name("foo")
+            .token(format!("oauth:{}", "a".repeat(30)))
+            .capability(Capability::Commands)
+            .capability(Capability::Tags)
+            .capability(Capability::Commands)
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            config.capabilities,
+            vec![Capability::Tags, Capability::Commands]
+        );
+    }
+
     #[test]
     fn valid_user_config_anonymous() {
         let config = UserConfig::builder().anonymous().build().unwrap();
@@ -233,12 +321,33 @@ mod tests {
                 name: crate::JUSTINFAN1234.to_string(),
                 token: crate::JUSTINFAN1234.to_string(),
                 capabilities: vec![],
+                login_timeout: LOGIN_TIMEOUT,
             }
         );
 
         assert!(config.is_anonymous());
     }
 
+    #[test]
+    fn random_anonymous() {
+        let config = UserConfig::anonymous();
+        assert!(config.is_anonymous());
+        assert!(config.token.is_empty());
+        assert!(config.capabilities.is_empty());
+
+        let suffix = config.name.strip_prefix("justinfan").unwrap();
+        assert_eq!(suffix.len(), 5);
+        assert!(suffix.parse::<u64>().is_ok());
+
+        let config = UserConfig {
+            name: "justinfanfoo".to_string(),
+            token: String::new(),
+            capabilities: vec![],
+            login_timeout: LOGIN_TIMEOUT,
+        };
+        assert!(!config.is_anonymous());
+    }
+
     #[test]
     fn invalid_name_missing() {
         let err = UserConfig::builder().build().unwrap_err();
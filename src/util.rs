@@ -1,3 +1,4 @@
+#[cfg(feature = "std")]
 #[allow(dead_code)]
 pub fn timestamp() -> u64 {
     std::time::SystemTime::now()
@@ -14,7 +15,7 @@ use std::{
     task::{Context, Poll},
 };
 
-// TODO if we make this clonable it can be used in the Writers
+#[derive(Clone)]
 pub struct Notify {
     rx: crate::channel::Receiver<()>,
     triggered: bool,
@@ -67,6 +68,52 @@ impl NotifyHandle {
     pub async fn notify(self) -> bool {
         self.tx.send(()).await.is_ok()
     }
+
+    // this can be called from many clones, only the first one is needed
+    pub(crate) fn try_notify(&self) -> bool {
+        !matches!(
+            self.tx.try_send(()),
+            Err(crate::channel::TrySendError::Closed(..))
+        )
+    }
+}
+
+// buffers bigger than this aren't kept, so one huge message doesn't pin its memory forever
+const MAX_POOLED_CAPACITY: usize = 4096;
+const MAX_POOLED_BUFFERS: usize = 16;
+
+/// A pool of byte buffers, shared between its clones.
+///
+/// This is used on the write path so that encoding a message reuses an old buffer instead of allocating a new one.
+#[derive(Clone, Default)]
+pub struct BufferPool {
+    buffers: std::sync::Arc<std::sync::Mutex<Vec<Vec<u8>>>>,
+}
+
+impl std::fmt::Debug for BufferPool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BufferPool").finish()
+    }
+}
+
+impl BufferPool {
+    /// Take an empty buffer out of the pool, or a new one if the pool is empty.
+    pub fn take(&self) -> Vec<u8> {
+        self.buffers.lock().unwrap().pop().unwrap_or_default()
+    }
+
+    /// Give a buffer back to the pool
+    pub fn give(&self, mut buf: Vec<u8>) {
+        if buf.capacity() == 0 || buf.capacity() > MAX_POOLED_CAPACITY {
+            return;
+        }
+
+        let mut buffers = self.buffers.lock().unwrap();
+        if buffers.len() < MAX_POOLED_BUFFERS {
+            buf.clear();
+            buffers.push(buf);
+        }
+    }
 }
 
 pub enum Either<L, R> {
@@ -180,4 +227,28 @@ where
         Poll::Pending
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn buffer_pool() {
+        let pool = BufferPool::default();
+
+        let mut buf = pool.take();
+        buf.extend_from_slice(b"PRIVMSG #museun :hello\r\n");
+        let ptr = buf.as_ptr();
+        pool.give(buf);
+
+        // the same allocation is handed out again, empty
+        let buf = pool.clone().take();
+        assert!(buf.is_empty());
+        assert_eq!(buf.as_ptr(), ptr);
+
+        // huge buffers aren't kept
+        pool.give(Vec::with_capacity(MAX_POOLED_CAPACITY + 1));
+        assert!(pool.take().capacity() == 0);
+    }
+}
 }
@@ -1,3 +1,4 @@
+use crate::prelude::*;
 use crate::{
     irc::{IrcMessage, MessageError, TagIndices},
     MaybeOwnedIndex,
@@ -1,16 +1,253 @@
+use super::{
+    Confirmation, Confirmations, DuplicatePolicy, Duplicates, MembershipConfirmation, MpscWriter,
+    Prioritized, Priority, RestrictionPolicy, Restrictions, SendRejected,
+};
 use crate::channel::Sender;
 use crate::encoder::AsyncEncoder;
+use crate::messages::Notice;
+use crate::runner::{Dispatcher, EventStream};
+use crate::twitch::ChannelName;
+use crate::util::BufferPool;
 use crate::Encodable;
+use crate::IntoOwned as _;
 
 use futures_lite::AsyncWrite;
 use io::Write;
-use std::io::{self};
+use std::{
+    io::{self},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+};
+
+/// The error returned when writing to a read-only (anonymous) [AsyncWriter].
+///
+/// This is wrapped in an `std::io::Error` with the `PermissionDenied` kind. Use [ReadOnly::is_read_only()] to check for it.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ReadOnly;
+
+impl ReadOnly {
+    /// Was this error caused by writing to a read-only writer?
+    pub fn is_read_only(err: &io::Error) -> bool {
+        err.get_ref().map_or(false, |err| err.is::<Self>())
+    }
+}
+
+impl std::fmt::Display for ReadOnly {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("cannot write on a read-only (anonymous) connection")
+    }
+}
+
+impl std::error::Error for ReadOnly {}
+
+/// The error returned when writing to an [AsyncWriter] after a [ShutdownHandle](crate::runner::ShutdownHandle) was triggered.
+///
+/// This is wrapped in an `std::io::Error` with the `BrokenPipe` kind. Use [ShutDown::is_shut_down()] to check for it.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ShutDown;
+
+impl ShutDown {
+    /// Was this error caused by writing after a shutdown was requested?
+    pub fn is_shut_down(err: &io::Error) -> bool {
+        err.get_ref().map_or(false, |err| err.is::<Self>())
+    }
+}
+
+impl std::fmt::Display for ShutDown {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("cannot write after the connection was shut down")
+    }
+}
+
+impl std::error::Error for ShutDown {}
+
+/// The error returned when writing to a writer after its runner was dropped, so nothing reads its messages any more.
+///
+/// This is wrapped in an `std::io::Error` with the `UnexpectedEof` kind. Use [Closed::is_closed()] to check for it.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Closed;
+
+impl Closed {
+    /// Was this error caused by writing after the runner was dropped?
+    pub fn is_closed(err: &io::Error) -> bool {
+        err.get_ref().map_or(false, |err| err.is::<Self>())
+    }
+
+    pub(crate) fn error() -> io::Error {
+        io::Error::new(io::ErrorKind::UnexpectedEof, Self)
+    }
+}
+
+impl std::fmt::Display for Closed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("the runner was dropped, so the writer was closed")
+    }
+}
+
+impl std::error::Error for Closed {}
+
+/// The error returned when a message wasn't sent because the rate limit was hit, e.g. by [Writer::try_encode()](crate::runner::Writer::try_encode()).
+///
+/// This is wrapped in an `std::io::Error` with the `WouldBlock` kind. Use [RateLimited::of()] to get it back.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct RateLimited {
+    /// How long to wait before the message can be sent
+    pub retry_after: std::time::Duration,
+}
+
+impl RateLimited {
+    /// Get this from the error, if it was caused by the rate limit
+    pub fn of(err: &io::Error) -> Option<Self> {
+        err.get_ref()
+            .and_then(|err| err.downcast_ref::<Self>())
+            .copied()
+    }
+}
+
+impl std::fmt::Display for RateLimited {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "rate limited, retry after {:.2?}", self.retry_after)
+    }
+}
+
+impl std::error::Error for RateLimited {}
 
 /// An asynchronous writer.
 #[derive(Clone)]
 pub struct AsyncWriter<W> {
     inner: AsyncEncoder<W>,
     activity_tx: Sender<()>,
+    read_only: Arc<AtomicBool>,
+    shut_down: Arc<AtomicBool>,
+    duplicates: Arc<Mutex<Duplicates>>,
+    restrictions: Arc<Mutex<Restrictions>>,
+    confirmations: Arc<Mutex<Confirmations>>,
+    rejections: Arc<Mutex<Dispatcher<SendRejected>>>,
+    pool: BufferPool,
+}
+
+impl<W> AsyncWriter<W> {
+    /// Is this writer read-only? (e.g. the connection is anonymous)
+    pub fn is_read_only(&self) -> bool {
+        self.read_only.load(Ordering::SeqCst)
+    }
+
+    // this is shared between all of the clones
+    pub(crate) fn set_read_only(&self, read_only: bool) {
+        self.read_only.store(read_only, Ordering::SeqCst)
+    }
+
+    /// Has a shutdown been requested? If so, this writer no longer accepts messages.
+    pub fn is_shut_down(&self) -> bool {
+        self.shut_down.load(Ordering::SeqCst)
+    }
+
+    // this is shared between all of the clones
+    pub(crate) fn set_shut_down(&self) {
+        self.shut_down.store(true, Ordering::SeqCst)
+    }
+
+    /// Set what to do with a message that Twitch would drop as a duplicate of the previous one. See [DuplicatePolicy].
+    ///
+    /// This is shared between all of the clones of this writer.
+    pub fn set_duplicate_policy(&self, policy: DuplicatePolicy) {
+        self.duplicates.lock().unwrap().policy = policy;
+    }
+
+    // moderators can send duplicate messages
+    pub(crate) fn set_moderator(&self, channel: &str, is_moderator: bool) {
+        self.duplicates
+            .lock()
+            .unwrap()
+            .set_moderator(channel, is_moderator)
+    }
+
+    /// Set what to do with a message that Twitch would reject because of the channel's chat mode (followers-only or subscribers-only). See [RestrictionPolicy].
+    ///
+    /// This is shared between all of the clones of this writer.
+    pub fn set_restriction_policy(&self, policy: RestrictionPolicy) {
+        self.restrictions.lock().unwrap().policy = policy;
+    }
+
+    /// Set whether we follow this channel, for its followers-only mode.
+    ///
+    /// Twitch doesn't say whether we follow a channel, so until this is set (or Twitch rejects a message because we don't follow it) messages are sent. See [RestrictionPolicy].
+    pub fn set_following(&self, channel: impl Into<ChannelName>, following: bool) {
+        self.restrictions()
+            .set_following(&channel.into(), following)
+    }
+
+    // the runner updates the chat modes and our roles as ROOMSTATEs and USERSTATEs arrive
+    pub(crate) fn restrictions(&self) -> std::sync::MutexGuard<'_, Restrictions> {
+        self.restrictions.lock().unwrap()
+    }
+
+    // the runner resolves the confirmations as USERSTATEs and NOTICEs arrive
+    pub(crate) fn confirmations(&self) -> std::sync::MutexGuard<'_, Confirmations> {
+        self.confirmations.lock().unwrap()
+    }
+
+    /// Subscribe to the messages that Twitch rejected, e.g. because we're rate limited, banned, or the channel is in followers-only mode.
+    ///
+    /// This includes the messages sent by every clone of this writer. The runner must still be driven for these to arrive.
+    ///
+    /// ```no_run
+    /// # use twitchchat::{commands, AsyncRunner, writer::SendRejected};
+    /// # use futures_lite::StreamExt as _;
+    /// # async fn demo(runner: &mut AsyncRunner) -> std::io::Result<()> {
+    /// let mut writer = runner.writer();
+    /// let mut rejections = writer.rejections();
+    ///
+    /// writer.encode(commands::privmsg("museun", "hello")).await?;
+    /// while let Some(rejected) = rejections.next().await {
+    ///     if rejected.is_temporary() {
+    ///         // try again later
+    ///     }
+    ///     eprintln!("{} rejected our message: {:?}", rejected.channel(), rejected.msg_id());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn rejections(&self) -> EventStream<SendRejected> {
+        self.rejections
+            .lock()
+            .unwrap()
+            .subscribe_map(|msg: &SendRejected| Some(msg.clone()))
+    }
+
+    // the runner gives every NOTICE to the writer, which resolves confirmations and notifies the subscribers of rejections
+    pub(crate) fn reject(&self, notice: &Notice<'_>) {
+        if notice.severity() != crate::messages::Severity::Rejected {
+            return;
+        }
+        self.restrictions().learn(notice);
+        let nonce = self.confirmations().reject(notice);
+        self.rejections.lock().unwrap().dispatch(&SendRejected {
+            notice: notice.clone().into_owned(),
+            nonce,
+        });
+    }
+
+    pub(crate) fn check_writable(&self) -> io::Result<()> {
+        if self.is_read_only() {
+            return Err(io::Error::new(io::ErrorKind::PermissionDenied, ReadOnly));
+        }
+        if self.is_shut_down() {
+            return Err(io::Error::new(io::ErrorKind::BrokenPipe, ShutDown));
+        }
+        Ok(())
+    }
+}
+
+impl AsyncWriter<MpscWriter> {
+    /// Merge the messages that are flushed together into one item on the channel. See [MpscWriter::coalescing()].
+    ///
+    /// This only affects this writer, not its clones.
+    pub fn set_coalescing(&mut self, coalesce: bool) {
+        self.inner.writer.set_coalescing(coalesce);
+    }
 }
 
 impl<W> std::fmt::Debug for AsyncWriter<W> {
@@ -28,6 +265,7 @@ where
     where
         M: Encodable + Send + Sync,
     {
+        self.check_writable()?;
         self.inner.encode_sync(msg)
     }
 }
@@ -37,6 +275,7 @@ where
     W: Write + Send + Sync,
 {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.check_writable()?;
         self.inner.write(buf)
     }
 
@@ -53,34 +292,279 @@ where
         Self {
             inner: AsyncEncoder::new(inner),
             activity_tx,
+            read_only: Arc::new(AtomicBool::new(false)),
+            shut_down: Arc::new(AtomicBool::new(false)),
+            duplicates: Arc::default(),
+            restrictions: Arc::default(),
+            confirmations: Arc::default(),
+            rejections: Arc::default(),
+            pool: BufferPool::default(),
         }
     }
 
+    // the buffers are shared between all of the clones (and the runner)
+    pub(crate) fn pool(&self) -> &BufferPool {
+        &self.pool
+    }
+
     /// Encode this [Encodable] message to the writer.
     pub async fn encode<M>(&mut self, msg: M) -> io::Result<()>
     where
         M: Encodable + Send + Sync,
     {
-        self.inner.encode(msg).await?;
+        self.check_writable()?;
+
+        let mut data = self.pool.take();
+        msg.encode(&mut data)?;
+        loop {
+            // this doesn't hold the lock while waiting
+            let wait = self.restrictions().check(&data)?;
+            match wait {
+                Some(wait) => drop(wait.recv().await),
+                None => break,
+            }
+        }
+        let delay = self.duplicates.lock().unwrap().check(&mut data)?;
+        if let Some(delay) = delay {
+            futures_timer::Delay::new(delay).await;
+        }
+
+        let res = self.inner.encode_bytes(&data).await;
+        self.pool.give(data);
+        res?;
         if self.activity_tx.send(()).await.is_err() {
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::UnexpectedEof,
-                "Runner has closed its receiver",
-            ));
+            return Err(Closed::error());
         }
         Ok(())
     }
 
+    /// Encode this [Encodable] message to the writer, with a [Priority].
+    ///
+    /// The [AsyncRunner](crate::AsyncRunner) sends higher priority messages first, e.g. use [Priority::High] for moderation actions.
+    /// The message carries its priority in a client-only `+twitchchat/priority` tag, which is removed before it's sent to Twitch.
+    pub async fn encode_with_priority<M>(&mut self, msg: M, priority: Priority) -> io::Result<()>
+    where
+        M: Encodable + Send + Sync,
+    {
+        self.encode(Prioritized { msg, priority }).await
+    }
+
+    /// Encode this `PRIVMSG` to the writer, with a `client-nonce` so you can find out whether Twitch accepted it.
+    ///
+    /// The returned [Confirmation] resolves when the [AsyncRunner](crate::AsyncRunner) reads the `USERSTATE` Twitch echoes back, or the `NOTICE` it sends instead.
+    ///
+    /// This returns an `InvalidInput` error if `msg` isn't exactly one `PRIVMSG`.
+    ///
+    /// ```no_run
+    /// # use twitchchat::{commands, writer::Confirmed};
+    /// # async fn demo(mut writer: twitchchat::Writer) -> std::io::Result<()> {
+    /// let confirmation = writer.encode_confirmed(commands::privmsg("museun", "hello")).await?;
+    /// // the runner must be driven (e.g. on another task) for this to resolve
+    /// match confirmation.await {
+    ///     Some(Confirmed::Accepted(state)) => println!("sent: {:?}", state.id()),
+    ///     Some(Confirmed::Rejected(notice)) => println!("rejected: {}", notice.message()),
+    ///     None => println!("Twitch never answered"),
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn encode_confirmed<M>(&mut self, msg: M) -> io::Result<Confirmation>
+    where
+        M: Encodable + Send + Sync,
+    {
+        let mut data = Vec::new();
+        msg.encode(&mut data)?;
+        let channel = super::duplicates::privmsg_channel(&data).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "only a single PRIVMSG can be confirmed",
+            )
+        })?;
+
+        let nonce = Confirmations::next_nonce();
+        let confirmation = self.confirmations().register(nonce.clone(), &channel);
+
+        let tagged = crate::commands::tagged(&data[..]).tag("client-nonce", &nonce);
+        if let Err(err) = self.encode(tagged).await {
+            self.confirmations().forget(&nonce);
+            return Err(err);
+        }
+        Ok(confirmation)
+    }
+
+    /// Join `channel`, returning a [MembershipConfirmation] which resolves once Twitch confirms it.
+    ///
+    /// The [AsyncRunner](crate::AsyncRunner) sends the `JOIN` within the JOIN rate limit, and resolves the confirmation when it reads our own `JOIN` echoed back.
+    ///
+    /// ```no_run
+    /// # async fn demo(mut writer: twitchchat::Writer) -> Result<(), twitchchat::Error> {
+    /// // the runner must be driven (e.g. on another task) for this to resolve
+    /// match writer.join_confirmed("museun").await?.await {
+    ///     Ok(()) => println!("joined"),
+    ///     Err(twitchchat::Error::BannedFromChannel { .. }) => println!("we're banned"),
+    ///     Err(err) => println!("couldn't join: {}", err),
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn join_confirmed(
+        &mut self,
+        channel: impl Into<ChannelName>,
+    ) -> io::Result<MembershipConfirmation> {
+        let channel: ChannelName = channel.into();
+        let confirmation = self.confirmations().register_membership(&channel, true);
+        self.encode(crate::commands::join(&channel)).await?;
+        Ok(confirmation)
+    }
+
+    /// Part `channel`, returning a [MembershipConfirmation] which resolves once Twitch confirms it.
+    pub async fn part_confirmed(
+        &mut self,
+        channel: impl Into<ChannelName>,
+    ) -> io::Result<MembershipConfirmation> {
+        let channel: ChannelName = channel.into();
+        let confirmation = self.confirmations().register_membership(&channel, false);
+        self.encode(crate::commands::part(&channel)).await?;
+        Ok(confirmation)
+    }
+
     /// Encode a slice of [Encodable] messages to the writer.
+    ///
+    /// These are flushed together, so a [coalescing](MpscWriter::coalescing()) writer sends them as one item.
     pub async fn encode_many<'a, I, M>(&mut self, msgs: I) -> io::Result<()>
     where
         I: IntoIterator<Item = &'a M> + Send + Sync + 'a,
         I::IntoIter: Send + Sync,
         M: Encodable + Send + Sync + 'a,
     {
+        let mut data = self.pool.take();
         for msg in msgs {
-            self.encode(msg).await?;
+            msg.encode(&mut data)?;
         }
-        Ok(())
+        if data.is_empty() {
+            return Ok(());
+        }
+        let res = self.encode(&data).await;
+        self.pool.give(data);
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::writer::Duplicate;
+
+    #[test]
+    fn read_only() {
+        let (tx, rx) = crate::channel::unbounded();
+        let (activity_tx, _activity_rx) = crate::channel::unbounded();
+        let mut writer = AsyncWriter::new(MpscWriter::new(tx), activity_tx);
+
+        let other = writer.clone();
+        other.set_read_only(true);
+        assert!(writer.is_read_only());
+
+        let err = futures_lite::future::block_on(writer.encode(crate::commands::ping("foo")))
+            .unwrap_err();
+        assert!(ReadOnly::is_read_only(&err));
+        assert!(matches!(
+            crate::runner::Error::from(err),
+            crate::runner::Error::ReadOnly
+        ));
+
+        let err = writer.write_all(b"PING :foo\r\n").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+        assert!(rx.try_recv().is_none());
+
+        other.set_read_only(false);
+        futures_lite::future::block_on(writer.encode(crate::commands::ping("foo"))).unwrap();
+        assert_eq!(&*rx.try_recv().unwrap(), b"PING foo\r\n");
+    }
+
+    #[test]
+    fn shut_down() {
+        let (tx, rx) = crate::channel::unbounded();
+        let (activity_tx, _activity_rx) = crate::channel::unbounded();
+        let mut writer = AsyncWriter::new(MpscWriter::new(tx), activity_tx);
+
+        futures_lite::future::block_on(writer.encode(crate::commands::ping("foo"))).unwrap();
+
+        writer.clone().set_shut_down();
+        assert!(writer.is_shut_down());
+
+        let err = futures_lite::future::block_on(writer.encode(crate::commands::ping("bar")))
+            .unwrap_err();
+        assert!(ShutDown::is_shut_down(&err));
+        assert!(matches!(
+            crate::runner::Error::from(err),
+            crate::runner::Error::ShutDown
+        ));
+
+        // what was already written is still queued
+        assert_eq!(&*rx.try_recv().unwrap(), b"PING foo\r\n");
+        assert!(rx.try_recv().is_none());
+    }
+
+    #[test]
+    fn duplicates() {
+        let (tx, rx) = crate::channel::unbounded();
+        let (activity_tx, _activity_rx) = crate::channel::unbounded();
+        let mut writer = AsyncWriter::new(MpscWriter::new(tx), activity_tx);
+
+        let recv = || String::from_utf8(rx.try_recv().unwrap().into()).unwrap();
+
+        futures_lite::future::block_on(async {
+            writer.clone().set_duplicate_policy(DuplicatePolicy::Reject);
+            writer
+                .encode(crate::commands::privmsg("museun", "hi"))
+                .await
+                .unwrap();
+            let err = writer
+                .encode(crate::commands::privmsg("museun", "hi"))
+                .await
+                .unwrap_err();
+            assert!(Duplicate::is_duplicate(&err));
+            assert!(matches!(
+                crate::runner::Error::from(err),
+                crate::runner::Error::Duplicate
+            ));
+
+            // other channels, and other messages, are fine
+            writer
+                .encode(crate::commands::privmsg("shaken_bot", "hi"))
+                .await
+                .unwrap();
+            writer
+                .encode(crate::commands::privmsg("museun", "hello"))
+                .await
+                .unwrap();
+
+            // and moderators aren't affected
+            writer.set_moderator("#museun", true);
+            writer
+                .encode(crate::commands::privmsg("museun", "hello"))
+                .await
+                .unwrap();
+            writer.set_moderator("#museun", false);
+
+            writer.set_duplicate_policy(DuplicatePolicy::invisible_suffix());
+            writer
+                .encode(crate::commands::privmsg("shaken_bot", "hi"))
+                .await
+                .unwrap();
+            writer
+                .encode(crate::commands::privmsg("shaken_bot", "hi"))
+                .await
+                .unwrap();
+        });
+
+        assert_eq!(recv(), "PRIVMSG #museun :hi\r\n");
+        assert_eq!(recv(), "PRIVMSG #shaken_bot :hi\r\n");
+        assert_eq!(recv(), "PRIVMSG #museun :hello\r\n");
+        assert_eq!(recv(), "PRIVMSG #museun :hello\r\n");
+        assert_eq!(recv(), "PRIVMSG #shaken_bot :hi \u{E0000}\r\n");
+        // alternating, so neither is a duplicate of the previous one
+        assert_eq!(recv(), "PRIVMSG #shaken_bot :hi\r\n");
     }
 }
@@ -0,0 +1,505 @@
+use crate::{
+    channel::{Receiver, Sender},
+    messages::{MessageId, Notice, UserState},
+    runner::Error,
+    IntoOwned as _,
+};
+
+use futures_lite::Stream;
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::atomic::{AtomicU64, Ordering},
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+// Twitch answers within a few seconds, anything older than this is forgotten
+const PENDING_WINDOW: Duration = Duration::from_secs(60);
+
+// how long a join (or part) waits for Twitch, unless it's changed
+const MEMBERSHIP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// What Twitch did with a message sent with [AsyncWriter::encode_confirmed()](super::AsyncWriter::encode_confirmed()).
+#[derive(Debug, Clone, PartialEq)]
+pub enum Confirmed {
+    /// Twitch accepted the message, and echoed this `USERSTATE` back with its `client-nonce`
+    Accepted(UserState<'static>),
+    /// Twitch rejected the message with this `NOTICE`, e.g. because we're rate limited or the channel is in followers-only mode
+    Rejected(Notice<'static>),
+}
+
+impl Confirmed {
+    /// Was the message accepted?
+    pub fn is_accepted(&self) -> bool {
+        matches!(self, Self::Accepted(..))
+    }
+}
+
+pin_project_lite::pin_project! {
+    /// A future which resolves when Twitch accepts or rejects a message sent with [AsyncWriter::encode_confirmed()](super::AsyncWriter::encode_confirmed()).
+    ///
+    /// The runner must still be driven for this to resolve.
+    /// This resolves to `None` if Twitch never answered (within a minute), or the runner was dropped.
+    #[must_use = "futures do nothing unless polled"]
+    pub struct Confirmation {
+        nonce: String,
+        #[pin]
+        rx: Receiver<Confirmed>,
+    }
+}
+
+impl Confirmation {
+    /// The `client-nonce` that was attached to the message
+    pub fn nonce(&self) -> &str {
+        &self.nonce
+    }
+}
+
+impl std::fmt::Debug for Confirmation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Confirmation")
+            .field("nonce", &self.nonce)
+            .finish()
+    }
+}
+
+impl Future for Confirmation {
+    type Output = Option<Confirmed>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.project().rx.poll_next(cx)
+    }
+}
+
+pin_project_lite::pin_project! {
+    /// A future which resolves when Twitch confirms a `JOIN` (or `PART`) sent with [AsyncWriter::join_confirmed()](super::AsyncWriter::join_confirmed()) (or [AsyncWriter::part_confirmed()](super::AsyncWriter::part_confirmed())).
+    ///
+    /// The runner must still be driven for this to resolve. It resolves when the runner reads our own `JOIN` (or `PART`) for the channel, or with an error if:
+    /// * we're banned from the channel: [BannedFromChannel](Error::BannedFromChannel)
+    /// * the channel was suspended (or blocked): [ChannelUnavailable](Error::ChannelUnavailable)
+    /// * Twitch didn't answer within 10 seconds (see [MembershipConfirmation::timeout()]): [MembershipTimedOut](Error::MembershipTimedOut)
+    #[must_use = "futures do nothing unless polled"]
+    pub struct MembershipConfirmation {
+        channel: String,
+        #[pin]
+        rx: Receiver<Result<(), Error>>,
+        #[pin]
+        timeout: futures_timer::Delay,
+    }
+}
+
+impl MembershipConfirmation {
+    /// The channel that was joined (or parted)
+    pub fn channel(&self) -> &str {
+        &self.channel
+    }
+
+    /// Wait this long for Twitch, instead of 10 seconds
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = futures_timer::Delay::new(timeout);
+        self
+    }
+}
+
+impl std::fmt::Debug for MembershipConfirmation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MembershipConfirmation")
+            .field("channel", &self.channel)
+            .finish()
+    }
+}
+
+impl Future for MembershipConfirmation {
+    type Output = Result<(), Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        if let Poll::Ready(res) = this.rx.poll_next(cx) {
+            // the runner (and every writer) was dropped
+            return Poll::Ready(res.unwrap_or(Err(Error::ChannelClosed)));
+        }
+        let channel = this.channel;
+        this.timeout.poll(cx).map(|_| {
+            Err(Error::MembershipTimedOut {
+                channel: channel.clone(),
+            })
+        })
+    }
+}
+
+struct PendingMembership {
+    channel: String,
+    join: bool,
+    tx: Sender<Result<(), Error>>,
+}
+
+struct Pending {
+    nonce: String,
+    channel: String,
+    sent_at: Instant,
+    tx: Sender<Confirmed>,
+}
+
+// this is shared between all of the clones of a writer, and the runner
+#[derive(Default)]
+pub(crate) struct Confirmations {
+    // in the order they were sent, so a rejection goes to the oldest message on that channel
+    pending: Vec<Pending>,
+    memberships: Vec<PendingMembership>,
+}
+
+impl std::fmt::Debug for Confirmations {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Confirmations")
+            .field("pending", &self.pending.len())
+            .field("memberships", &self.memberships.len())
+            .finish()
+    }
+}
+
+impl Confirmations {
+    pub(crate) fn next_nonce() -> String {
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+        format!(
+            "{:x}{:04x}",
+            crate::util::timestamp(),
+            COUNTER.fetch_add(1, Ordering::Relaxed) & 0xFFFF
+        )
+    }
+
+    pub(crate) fn register(&mut self, nonce: String, channel: &str) -> Confirmation {
+        let now = Instant::now();
+        self.pending
+            .retain(|pending| now.duration_since(pending.sent_at) < PENDING_WINDOW);
+
+        let (tx, rx) = crate::channel::bounded(1);
+        self.pending.push(Pending {
+            nonce: nonce.clone(),
+            channel: channel.to_string(),
+            sent_at: now,
+            tx,
+        });
+        Confirmation { nonce, rx }
+    }
+
+    // the message was never sent
+    pub(crate) fn forget(&mut self, nonce: &str) {
+        self.pending.retain(|pending| pending.nonce != nonce)
+    }
+
+    pub(crate) fn accept(&mut self, msg: &UserState<'_>) {
+        let nonce = match msg.client_nonce() {
+            Some(nonce) => nonce,
+            None => return,
+        };
+        if let Some(pos) = self.pending.iter().position(|p| p.nonce == nonce) {
+            let pending = self.pending.remove(pos);
+            let _ = pending
+                .tx
+                .try_send(Confirmed::Accepted(msg.clone().into_owned()));
+        }
+    }
+
+    pub(crate) fn register_membership(
+        &mut self,
+        channel: &str,
+        join: bool,
+    ) -> MembershipConfirmation {
+        // the ones that timed out (or were dropped) can't be resolved anymore
+        self.memberships.retain(|pending| !pending.tx.is_closed());
+
+        let (tx, rx) = crate::channel::bounded(1);
+        self.memberships.push(PendingMembership {
+            channel: channel.to_string(),
+            join,
+            tx,
+        });
+        MembershipConfirmation {
+            channel: channel.to_string(),
+            rx,
+            timeout: futures_timer::Delay::new(MEMBERSHIP_TIMEOUT),
+        }
+    }
+
+    // we joined (or parted) this channel
+    pub(crate) fn membership_changed(&mut self, channel: &str, join: bool) {
+        self.resolve_memberships(channel, |pending| pending.join == join, || Ok(()))
+    }
+
+    // Twitch won't let us join that channel
+    pub(crate) fn reject_membership(&mut self, msg: &Notice<'_>) {
+        let channel = msg.channel();
+        let error: fn(String) -> Error = match msg.msg_id() {
+            Some(MessageId::MsgBanned) => |channel| Error::BannedFromChannel { channel },
+            Some(MessageId::MsgChannelSuspended) | Some(MessageId::MsgChannelBlocked) => {
+                |channel| Error::ChannelUnavailable { channel }
+            }
+            _ => return,
+        };
+        self.resolve_memberships(
+            channel,
+            |pending| pending.join,
+            || Err(error(channel.to_string())),
+        )
+    }
+
+    fn resolve_memberships(
+        &mut self,
+        channel: &str,
+        filter: impl Fn(&PendingMembership) -> bool,
+        result: impl Fn() -> Result<(), Error>,
+    ) {
+        self.memberships.retain(|pending| {
+            if !pending.channel.eq_ignore_ascii_case(channel) || !filter(pending) {
+                return true;
+            }
+            let _ = pending.tx.try_send(result());
+            false
+        })
+    }
+
+    // returns the nonce of the rejected message, if it was waiting for a confirmation
+    pub(crate) fn reject(&mut self, msg: &Notice<'_>) -> Option<String> {
+        if !msg.msg_id().as_ref().map_or(false, MessageId::is_rejection) {
+            return None;
+        }
+        let channel = msg.channel();
+        if let Some(pos) = self
+            .pending
+            .iter()
+            .position(|p| p.channel.eq_ignore_ascii_case(channel))
+        {
+            let pending = self.pending.remove(pos);
+            let _ = pending
+                .tx
+                .try_send(Confirmed::Rejected(msg.clone().into_owned()));
+            return Some(pending.nonce);
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse<'a, T>(input: &'a str) -> T
+    where
+        T: crate::FromIrcMessage<'a>,
+        T::Error: std::fmt::Debug,
+    {
+        T::from_irc(crate::irc::parse_one(input).unwrap().1).unwrap()
+    }
+
+    #[test]
+    fn unique_nonces() {
+        let a = Confirmations::next_nonce();
+        let b = Confirmations::next_nonce();
+        assert_ne!(a, b);
+        assert!(!a.contains([' ', ';']));
+    }
+
+    #[test]
+    fn accepted() {
+        let mut confirmations = Confirmations::default();
+        let first = confirmations.register("abc".into(), "#museun");
+        let second = confirmations.register("def".into(), "#museun");
+
+        let msg: UserState<'_> =
+            parse("@client-nonce=def;id=1234;mod=0 :tmi.twitch.tv USERSTATE #museun\r\n");
+        confirmations.accept(&msg);
+        // and unrelated echoes are ignored
+        let msg: UserState<'_> = parse("@mod=0 :tmi.twitch.tv USERSTATE #museun\r\n");
+        confirmations.accept(&msg);
+
+        match futures_lite::future::block_on(second) {
+            Some(Confirmed::Accepted(msg)) => assert_eq!(msg.id(), Some("1234")),
+            confirmed => panic!("unexpected: {:?}", confirmed),
+        }
+        assert!(first.rx.try_recv().is_none());
+        assert_eq!(confirmations.pending.len(), 1);
+    }
+
+    #[test]
+    fn rejected() {
+        let mut confirmations = Confirmations::default();
+        let other = confirmations.register("abc".into(), "#shaken_bot");
+        let first = confirmations.register("def".into(), "#museun");
+        let second = confirmations.register("ghi".into(), "#museun");
+
+        let msg: Notice<'_> = parse(
+            "@msg-id=slow_on :tmi.twitch.tv NOTICE #museun :This room is now in slow mode.\r\n",
+        );
+        assert_eq!(confirmations.reject(&msg), None);
+        assert!(first.rx.try_recv().is_none());
+
+        let msg: Notice<'_> = parse("@msg-id=msg_followersonly :tmi.twitch.tv NOTICE #museun :This room is in followers-only mode.\r\n");
+        assert_eq!(confirmations.reject(&msg).as_deref(), Some("def"));
+
+        let confirmed = futures_lite::future::block_on(first).unwrap();
+        assert!(!confirmed.is_accepted());
+        assert!(second.rx.try_recv().is_none());
+        assert!(other.rx.try_recv().is_none());
+    }
+
+    #[test]
+    fn forgotten() {
+        let mut confirmations = Confirmations::default();
+        let confirmation = confirmations.register("abc".into(), "#museun");
+        confirmations.forget("abc");
+        assert!(futures_lite::future::block_on(confirmation).is_none());
+    }
+
+    #[test]
+    fn memberships() {
+        let mut confirmations = Confirmations::default();
+        let join = confirmations.register_membership("#museun", true);
+        let part = confirmations.register_membership("#museun", false);
+        let other = confirmations.register_membership("#shaken_bot", true);
+
+        confirmations.membership_changed("#museun", true);
+        assert!(futures_lite::future::block_on(join).is_ok());
+        assert!(part.rx.try_recv().is_none());
+        assert!(other.rx.try_recv().is_none());
+
+        let msg: Notice<'_> = parse("@msg-id=msg_banned :tmi.twitch.tv NOTICE #shaken_bot :You are permanently banned from talking in shaken_bot.\r\n");
+        confirmations.reject_membership(&msg);
+        assert!(matches!(
+            futures_lite::future::block_on(other),
+            Err(Error::BannedFromChannel { channel }) if channel == "#shaken_bot"
+        ));
+
+        let msg: Notice<'_> = parse("@msg-id=msg_channel_suspended :tmi.twitch.tv NOTICE #museun :This channel does not exist or has been suspended.\r\n");
+        confirmations.reject_membership(&msg);
+        // a part isn't rejected
+        assert!(part.rx.try_recv().is_none());
+        assert_eq!(confirmations.memberships.len(), 1);
+    }
+
+    #[test]
+    fn membership_timeout() {
+        let mut confirmations = Confirmations::default();
+        let join = confirmations
+            .register_membership("#museun", true)
+            .timeout(Duration::from_millis(10));
+        assert!(matches!(
+            futures_lite::future::block_on(join),
+            Err(Error::MembershipTimedOut { channel }) if channel == "#museun"
+        ));
+
+        // and the ones that timed out are forgotten
+        let _join = confirmations.register_membership("#museun", true);
+        assert_eq!(confirmations.memberships.len(), 1);
+    }
+
+    #[test]
+    #[cfg(feature = "testing")]
+    fn confirmed_messages() {
+        use crate::{
+            commands,
+            messages::Commands,
+            test::{user_config, MockServer},
+            Status,
+        };
+        use futures_lite::future::{block_on, zip};
+        use std::io::ErrorKind;
+
+        let server = MockServer::new();
+        block_on(async {
+            let (mut runner, mut conn) = server.connect_runner(&user_config()).await.unwrap();
+
+            let (joined, _) = zip(runner.join("museun"), conn.read_line()).await;
+            joined.unwrap();
+
+            let mut writer = runner.writer();
+            let rejections = writer.rejections();
+            let mut sent = vec![];
+            for msg in &["hello", "world"] {
+                let confirmation = writer
+                    .encode_confirmed(commands::privmsg("museun", msg))
+                    .await
+                    .unwrap();
+                let line = loop {
+                    runner.step().await.unwrap();
+                    if let Some(line) = conn.try_read_line().unwrap() {
+                        break line;
+                    }
+                };
+                assert_eq!(
+                    line,
+                    format!(
+                        "@client-nonce={} PRIVMSG #museun :{}\r\n",
+                        confirmation.nonce(),
+                        msg
+                    )
+                );
+                sent.push(confirmation);
+            }
+
+            conn.send(format!(
+                "@client-nonce={};id=abc;mod=0 :tmi.twitch.tv USERSTATE #museun",
+                sent[0].nonce()
+            ))
+            .unwrap();
+            conn.send("@msg-id=msg_ratelimit :tmi.twitch.tv NOTICE #museun :Your message was not sent because you are sending messages too quickly.").unwrap();
+            while !matches!(
+                runner.next_message().await.unwrap(),
+                Status::Message(Commands::Notice(..))
+            ) {}
+
+            let rejected = rejections.try_next().unwrap();
+            assert_eq!(rejected.nonce(), Some(sent[1].nonce()));
+
+            let mut sent = sent.into_iter();
+            match sent.next().unwrap().await.unwrap() {
+                Confirmed::Accepted(msg) => assert_eq!(msg.id(), Some("abc")),
+                confirmed => panic!("unexpected: {:?}", confirmed),
+            }
+            match sent.next().unwrap().await.unwrap() {
+                Confirmed::Rejected(msg) => assert_eq!(msg.msg_id(), Some(MessageId::MsgRatelimit)),
+                confirmed => panic!("unexpected: {:?}", confirmed),
+            }
+
+            let err = writer
+                .encode_confirmed(commands::join("museun"))
+                .await
+                .unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::InvalidInput);
+        });
+    }
+
+    #[test]
+    #[cfg(feature = "testing")]
+    fn confirmed_joins() {
+        use crate::test::{user_config, MockServer};
+        use futures_lite::future::{block_on, zip};
+
+        let server = MockServer::new();
+        block_on(async {
+            let (mut runner, mut conn) = server.connect_runner(&user_config()).await.unwrap();
+
+            let mut writer = runner.writer();
+            let joined = writer.join_confirmed("Museun").await.unwrap();
+            assert_eq!(joined.channel(), "#museun");
+
+            let driven = async {
+                let (line, res) = zip(conn.read_line(), joined).await;
+                assert_eq!(line.unwrap(), "JOIN #museun\r\n");
+                res.unwrap();
+
+                let parted = writer.part_confirmed("museun").await.unwrap();
+                let (line, res) = zip(conn.read_line(), parted).await;
+                assert_eq!(line.unwrap(), "PART #museun\r\n");
+                res.unwrap();
+            };
+            futures_lite::future::or(driven, async {
+                loop {
+                    runner.step().await.unwrap();
+                }
+            })
+            .await;
+            assert!(!runner.is_on_channel("museun"));
+        });
+    }
+}
@@ -0,0 +1,149 @@
+use crate::MaybeOwnedIndex;
+
+use std::{
+    collections::{HashMap, HashSet},
+    io,
+    time::{Duration, Instant},
+};
+
+// Twitch drops a message from a non-moderator if it's the same as their previous one, within this window
+const DUPLICATE_WINDOW: Duration = Duration::from_secs(30);
+
+/// What an [AsyncWriter](super::AsyncWriter) does with a `PRIVMSG` that Twitch would drop as a duplicate.
+///
+/// Twitch silently drops a message from a non-moderator if it's the same as the previous one they sent to that channel, within 30 seconds.
+///
+/// Set this with [AsyncWriter::set_duplicate_policy()](super::AsyncWriter::set_duplicate_policy()).
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum DuplicatePolicy {
+    /// Send it anyway. This is the default
+    #[default]
+    Allow,
+    /// Wait until Twitch would accept it
+    Delay,
+    /// Return a [Duplicate] error
+    Reject,
+    /// Append this (usually invisible) suffix, so it's no longer a duplicate
+    Suffix(String),
+}
+
+impl DuplicatePolicy {
+    /// The suffix commonly used to get around the duplicate message check: a space and an invisible tag character (`U+E0000`).
+    pub fn invisible_suffix() -> Self {
+        Self::Suffix(String::from(" \u{E0000}"))
+    }
+}
+
+/// The error returned when an [AsyncWriter](super::AsyncWriter) rejects a duplicate message, with [DuplicatePolicy::Reject].
+///
+/// This is wrapped in an `std::io::Error` with the `AlreadyExists` kind. Use [Duplicate::is_duplicate()] to check for it.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Duplicate;
+
+impl Duplicate {
+    /// Was this error caused by writing a duplicate message?
+    pub fn is_duplicate(err: &io::Error) -> bool {
+        err.get_ref().map_or(false, |err| err.is::<Self>())
+    }
+}
+
+impl std::fmt::Display for Duplicate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Twitch would drop this message as a duplicate of the previous one")
+    }
+}
+
+impl std::error::Error for Duplicate {}
+
+// this is shared between all of the clones of a writer
+#[derive(Debug, Default)]
+pub(crate) struct Duplicates {
+    pub(crate) policy: DuplicatePolicy,
+    // the last message sent to each channel, and when it'll be sent
+    last: HashMap<String, (String, Instant)>,
+    // the channels where we're a moderator
+    exempt: HashSet<String>,
+}
+
+impl Duplicates {
+    pub(crate) fn set_moderator(&mut self, channel: &str, is_moderator: bool) {
+        if is_moderator {
+            self.exempt.insert(channel.to_string());
+        } else {
+            self.exempt.remove(channel);
+        }
+    }
+
+    // applies the policy to the encoded lines, returning how long to wait before writing them
+    pub(crate) fn check(&mut self, data: &mut Vec<u8>) -> io::Result<Option<Duration>> {
+        if self.policy == DuplicatePolicy::Allow {
+            return Ok(None);
+        }
+
+        let input = std::str::from_utf8(data)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?
+            .to_string();
+
+        let now = Instant::now();
+        let mut delay = None;
+        let mut output = String::with_capacity(input.len());
+
+        for line in input.split_inclusive('\n') {
+            let (channel, index) = match privmsg(line) {
+                Some((channel, index)) if !self.exempt.contains(channel) => (channel, index),
+                _ => {
+                    output.push_str(line);
+                    continue;
+                }
+            };
+
+            let mut text = line[index].to_string();
+            let mut at = now;
+
+            if let Some((last, sent_at)) = self.last.get(channel) {
+                let until = *sent_at + DUPLICATE_WINDOW;
+                if *last == text && until > now {
+                    match &self.policy {
+                        DuplicatePolicy::Allow => {}
+                        DuplicatePolicy::Reject => {
+                            return Err(io::Error::new(io::ErrorKind::AlreadyExists, Duplicate))
+                        }
+                        DuplicatePolicy::Delay => {
+                            at = until;
+                            delay = Some(until - now);
+                        }
+                        DuplicatePolicy::Suffix(suffix) => text.push_str(suffix),
+                    }
+                }
+            }
+
+            output.push_str(&line[..index.start as usize]);
+            output.push_str(&text);
+            output.push_str(&line[index.end as usize..]);
+
+            self.last.insert(channel.to_string(), (text, at));
+        }
+
+        *data = output.into_bytes();
+        Ok(delay)
+    }
+}
+
+// the channel of the encoded data, if it's exactly one PRIVMSG line
+pub(crate) fn privmsg_channel(data: &[u8]) -> Option<String> {
+    let line = std::str::from_utf8(data).ok()?;
+    if line.find('\n').map_or(true, |pos| pos + 1 != line.len()) {
+        return None;
+    }
+    privmsg(line).map(|(channel, _)| channel.to_string())
+}
+
+// the channel and the index of the text of a PRIVMSG line
+pub(super) fn privmsg(line: &str) -> Option<(&str, MaybeOwnedIndex)> {
+    let (_, msg) = crate::irc::parse_one(line).ok()?;
+    if msg.get_command() != crate::irc::IrcMessage::PRIVMSG {
+        return None;
+    }
+    let channel = msg.nth_arg_index(0)?;
+    Some((&line[channel], msg.data?))
+}
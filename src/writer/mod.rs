@@ -1,7 +1,29 @@
 //! A set of writers
 
 mod async_writer;
-pub use async_writer::AsyncWriter;
+pub use async_writer::{AsyncWriter, Closed, RateLimited, ReadOnly, ShutDown};
 
 mod mpsc_writer;
 pub use mpsc_writer::MpscWriter;
+
+mod sink;
+pub use sink::{MessageSink, SinkFuture};
+
+mod duplicates;
+pub(crate) use duplicates::Duplicates;
+pub use duplicates::{Duplicate, DuplicatePolicy};
+
+mod restrictions;
+pub(crate) use restrictions::Restrictions;
+pub use restrictions::{RejectReason, RestrictionPolicy, WouldBeRejected};
+
+mod confirm;
+pub(crate) use confirm::Confirmations;
+pub use confirm::{Confirmation, Confirmed, MembershipConfirmation};
+
+mod rejected;
+pub use rejected::SendRejected;
+
+mod priority;
+pub(crate) use priority::Prioritized;
+pub use priority::Priority;
@@ -2,19 +2,32 @@ use crate::Encodable;
 
 use futures_lite::AsyncWrite;
 use std::{
+    future::Future,
     io::{self, Write},
     pin::Pin,
     task::{Context, Poll},
 };
 
+type SendFuture = Pin<Box<dyn Future<Output = Result<(), Box<[u8]>>> + Send + Sync>>;
+
 /// A mpsc-based writer.
 ///
 /// This can be used both a [std::io::Write] instance and an [AsyncWrite][async-write] instance.
 ///
+/// By default, a full channel is reported as an [`io::ErrorKind::WouldBlock`] error.
+/// A writer created with [`MpscWriter::blocking`] will instead wait until there is room in the channel.
+///
+/// By default, each message is sent as its own item on the channel. With [`MpscWriter::coalescing`], all of the complete lines that are buffered when it's flushed are sent as one item.
+///
+/// With the `tokio` feature, this is also a [tokio::io::AsyncWrite] instance.
+///
 /// [async-write]: futures_lite::AsyncWrite
 pub struct MpscWriter {
     buf: Vec<u8>,
     channel: crate::channel::Sender<Box<[u8]>>,
+    blocking: bool,
+    coalesce: bool,
+    sending: Option<SendFuture>,
 }
 
 impl std::fmt::Debug for MpscWriter {
@@ -28,6 +41,9 @@ impl Clone for MpscWriter {
         Self {
             buf: Vec::new(),
             channel: self.channel.clone(),
+            blocking: self.blocking,
+            coalesce: self.coalesce,
+            sending: None,
         }
     }
 }
@@ -38,9 +54,37 @@ impl MpscWriter {
         Self {
             buf: Vec::new(),
             channel,
+            blocking: false,
+            coalesce: false,
+            sending: None,
         }
     }
 
+    /// Merge every complete line that is buffered into a single channel item, when this is flushed.
+    ///
+    /// This means fewer allocations (and fewer wake-ups of the receiver) when you write many messages before flushing, e.g. with [AsyncWriter::encode_many()](super::AsyncWriter::encode_many()).
+    /// The receiver has to split the item into lines -- the [AsyncRunner](crate::AsyncRunner) does this.
+    pub fn coalescing(mut self, coalesce: bool) -> Self {
+        self.set_coalescing(coalesce);
+        self
+    }
+
+    pub(crate) fn set_coalescing(&mut self, coalesce: bool) {
+        self.coalesce = coalesce;
+    }
+
+    /// Create a new Writer over a bounded channel of `bound` messages, returning the Writer and the Receiver.
+    ///
+    /// When the channel is full, flushing this Writer will block (or wait, when used asynchronously) until there is room.
+    pub fn blocking(bound: usize) -> (Self, crate::channel::Receiver<Box<[u8]>>) {
+        let (tx, rx) = crate::channel::bounded(bound);
+        let this = Self {
+            blocking: true,
+            ..Self::new(tx)
+        };
+        (this, rx)
+    }
+
     /// Encode this message to the inner channel
     pub fn encode<M>(&mut self, msg: M) -> io::Result<()>
     where
@@ -50,22 +94,64 @@ impl MpscWriter {
         self.flush()
     }
 
+    /// Encode this message to the inner channel without ever blocking.
+    ///
+    /// If the channel is full, this returns an [`io::ErrorKind::WouldBlock`] error and the message is discarded, so you can try again later.
+    /// A message of several lines is only sent if there is room for all of them.
+    pub fn encode_now<M>(&mut self, msg: M) -> io::Result<()>
+    where
+        M: Encodable + Send,
+    {
+        use crate::channel::TrySendError;
+
+        let mut buf = Vec::new();
+        msg.encode(&mut buf)?;
+
+        let lines: Vec<&[u8]> = if self.coalesce {
+            vec![&buf]
+        } else {
+            buf.split_inclusive(|&c| c == b'\n').collect()
+        };
+
+        // don't send part of the message
+        if self.channel.room().map_or(false, |room| room < lines.len()) {
+            return Err(if self.channel.is_closed() {
+                closed()
+            } else {
+                would_block()
+            });
+        }
+
+        for line in lines {
+            match self.channel.try_send(line.into()) {
+                Ok(..) => {}
+                Err(TrySendError::Closed(..)) => return Err(closed()),
+                Err(TrySendError::Full(..)) => return Err(would_block()),
+            }
+        }
+        Ok(())
+    }
+
     fn split_buf(&mut self) -> Option<Box<[u8]>> {
-        let end = match self.buf.iter().position(|&c| c == b'\n') {
-            Some(p) if self.buf.get(p - 1) == Some(&b'\r') => p,
-            _ => return None,
+        let end = if self.coalesce {
+            // up to the last complete line
+            self.buf.windows(2).rposition(|w| w == b"\r\n")? + 1
+        } else {
+            match self.buf.iter().position(|&c| c == b'\n') {
+                Some(p) if p > 0 && self.buf.get(p - 1) == Some(&b'\r') => p,
+                _ => return None,
+            }
         };
 
-        // include the \n
-        let mut tail = self.buf.split_off(end + 1);
-        std::mem::swap(&mut self.buf, &mut tail);
-        Some(tail.into_boxed_slice())
+        // include the \n. this keeps the buffer's capacity for the next message
+        Some(self.buf.drain(..=end).collect())
     }
 
-    fn inner_flush(&mut self) -> std::io::Result<()> {
+    // sends every complete line that is buffered, without blocking
+    fn try_flush(&mut self) -> io::Result<()> {
         use crate::channel::TrySendError;
 
-        let tail = match self.split_buf() {
+        let mut tail = match self.split_buf() {
             Some(tail) => tail,
             None => {
                 log::warn!("cannot flush an incomplete buffer");
@@ -73,15 +159,66 @@ impl MpscWriter {
             }
         };
 
-        match self.channel.try_send(tail) {
-            Ok(..) => Ok(()),
-            Err(TrySendError::Closed(..)) => Err(io::Error::new(
-                io::ErrorKind::UnexpectedEof,
-                "writer was closed",
-            )),
-            Err(TrySendError::Full(..)) => unreachable!(),
+        loop {
+            match self.channel.try_send(tail) {
+                Ok(..) => {}
+                Err(TrySendError::Closed(..)) => return Err(closed()),
+                Err(TrySendError::Full(tail)) => {
+                    // put it back so it isn't lost
+                    self.buf.splice(0..0, tail.iter().copied());
+                    return Err(would_block());
+                }
+            }
+
+            match self.split_buf() {
+                Some(next) => tail = next,
+                None => return Ok(()),
+            }
         }
     }
+
+    fn inner_flush(&mut self) -> io::Result<()> {
+        match self.try_flush() {
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock && self.blocking => {
+                while let Some(tail) = self.split_buf() {
+                    futures_lite::future::block_on(self.channel.send(tail))
+                        .map_err(|_| closed())?;
+                }
+                Ok(())
+            }
+            res => res,
+        }
+    }
+
+    fn poll_inner_flush(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        loop {
+            if let Some(fut) = self.sending.as_mut() {
+                let res = futures_lite::ready!(fut.as_mut().poll(cx));
+                self.sending.take();
+                res.map_err(|_| closed())?;
+            }
+
+            match self.try_flush() {
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock && self.blocking => {
+                    let tail = self
+                        .split_buf()
+                        .expect("a complete line should be buffered");
+                    let channel = self.channel.clone();
+                    self.sending
+                        .replace(Box::pin(async move { channel.send(tail).await }));
+                }
+                res => return Poll::Ready(res),
+            }
+        }
+    }
+}
+
+fn closed() -> io::Error {
+    super::Closed::error()
+}
+
+fn would_block() -> io::Error {
+    io::Error::new(io::ErrorKind::WouldBlock, "writer channel is full")
 }
 
 impl Write for MpscWriter {
@@ -106,9 +243,8 @@ impl AsyncWrite for MpscWriter {
         Poll::Ready(Ok(buf.len()))
     }
 
-    fn poll_flush(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
-        let mut this = self.as_mut();
-        Poll::Ready(this.inner_flush())
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.as_mut().poll_inner_flush(cx)
     }
 
     fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
@@ -116,6 +252,25 @@ impl AsyncWrite for MpscWriter {
     }
 }
 
+#[cfg(feature = "tokio")]
+impl tokio::io::AsyncWrite for MpscWriter {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        AsyncWrite::poll_write(self, cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        AsyncWrite::poll_flush(self, cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        AsyncWrite::poll_close(self, cx)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -143,4 +298,185 @@ mod tests {
         assert!(m.flush().is_ok());
         assert_eq!(&*rx.try_recv().unwrap(), b"\r\n");
     }
+
+    #[test]
+    fn mpsc_coalescing() {
+        let (tx, rx) = crate::channel::unbounded();
+        let mut m = MpscWriter::new(tx).coalescing(true);
+
+        m.write_all(b"PING :foo\r\nPING :bar\r\nPING :b").unwrap();
+        m.flush().unwrap();
+        assert_eq!(&*rx.try_recv().unwrap(), b"PING :foo\r\nPING :bar\r\n");
+        assert!(rx.try_recv().is_none());
+
+        m.write_all(b"az\r\n").unwrap();
+        m.flush().unwrap();
+        assert_eq!(&*rx.try_recv().unwrap(), b"PING :baz\r\n");
+
+        m.encode_now(crate::commands::raw("PING :foo\r\nPING :bar"))
+            .unwrap();
+        assert_eq!(&*rx.try_recv().unwrap(), b"PING :foo\r\nPING :bar\r\n");
+        assert!(m.buf.is_empty());
+    }
+
+    #[test]
+    fn mpsc_full_would_block() {
+        let (tx, rx) = crate::channel::bounded(1);
+        let mut m = MpscWriter::new(tx);
+
+        m.encode(crate::commands::raw("foo")).unwrap();
+        let err = m.encode(crate::commands::raw("bar")).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::WouldBlock);
+
+        // it is kept around for the next flush
+        assert_eq!(&*rx.try_recv().unwrap(), b"foo\r\n");
+        assert!(m.flush().is_ok());
+        assert_eq!(&*rx.try_recv().unwrap(), b"bar\r\n");
+    }
+
+    #[test]
+    fn mpsc_encode_now() {
+        let (mut m, rx) = MpscWriter::blocking(1);
+
+        m.encode_now(crate::commands::raw("foo")).unwrap();
+        let err = m.encode_now(crate::commands::raw("bar")).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::WouldBlock);
+
+        // it was discarded
+        assert_eq!(&*rx.try_recv().unwrap(), b"foo\r\n");
+        assert!(m.flush().is_ok());
+        assert!(rx.try_recv().is_none());
+    }
+
+    #[test]
+    fn mpsc_encode_now_many_lines() {
+        let (tx, rx) = crate::channel::bounded(3);
+        let mut m = MpscWriter::new(tx);
+
+        m.encode_now(crate::commands::raw("foo")).unwrap();
+        let err = m
+            .encode_now(crate::commands::raw("PING :foo\r\nPING :bar\r\nPING :baz"))
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::WouldBlock);
+
+        // none of the lines were sent
+        assert_eq!(&*rx.try_recv().unwrap(), b"foo\r\n");
+        assert!(rx.try_recv().is_none());
+
+        m.encode_now(crate::commands::raw("PING :foo\r\nPING :bar\r\nPING :baz"))
+            .unwrap();
+        assert_eq!(rx.len(), 3);
+    }
+
+    #[test]
+    fn mpsc_blocking() {
+        let (m, rx) = MpscWriter::blocking(1);
+
+        let handle = std::thread::spawn(move || {
+            let mut m = m;
+            for i in 0..10 {
+                m.encode(crate::commands::raw(&i.to_string())).unwrap();
+            }
+        });
+
+        let out = futures_lite::future::block_on(async move {
+            let mut out = vec![];
+            while let Some(line) = rx.recv().await {
+                out.push(String::from_utf8(line.into()).unwrap());
+            }
+            out
+        });
+        handle.join().unwrap();
+
+        let expected = (0..10).map(|i| format!("{}\r\n", i)).collect::<Vec<_>>();
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn mpsc_blocking_async() {
+        use futures_lite::AsyncWriteExt;
+
+        let (mut m, rx) = MpscWriter::blocking(1);
+        let executor = async_executor::LocalExecutor::new();
+
+        let task = executor.spawn(async move {
+            for i in 0..10 {
+                let line = format!("{}\r\n", i);
+                AsyncWriteExt::write_all(&mut m, line.as_bytes())
+                    .await
+                    .unwrap();
+                AsyncWriteExt::flush(&mut m).await.unwrap();
+            }
+        });
+
+        let out = futures_lite::future::block_on(executor.run(async move {
+            let mut out = vec![];
+            for _ in 0..10 {
+                let line = rx.recv().await.unwrap();
+                out.push(String::from_utf8(line.into()).unwrap());
+            }
+            task.await;
+            out
+        }));
+
+        let expected = (0..10).map(|i| format!("{}\r\n", i)).collect::<Vec<_>>();
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    #[cfg(feature = "tokio")]
+    fn tokio_async_write() {
+        let (tx, rx) = crate::channel::bounded(1);
+        let mut m = MpscWriter::new(tx);
+
+        futures_lite::future::block_on(futures_lite::future::poll_fn(|cx| {
+            let mut m = Pin::new(&mut m);
+            futures_lite::ready!(tokio::io::AsyncWrite::poll_write(
+                m.as_mut(),
+                cx,
+                b"PING :foo\r\n"
+            ))?;
+            tokio::io::AsyncWrite::poll_flush(m, cx)
+        }))
+        .unwrap();
+
+        assert_eq!(&*rx.try_recv().unwrap(), b"PING :foo\r\n");
+    }
+
+    #[test]
+    #[cfg(feature = "testing")]
+    fn coalesced_writes() {
+        use crate::{
+            commands,
+            test::{user_config, MockServer},
+        };
+        use futures_lite::future::{block_on, zip};
+
+        let server = MockServer::new();
+        block_on(async {
+            let (mut runner, mut conn) = server.connect_runner(&user_config()).await.unwrap();
+
+            let (joined, _) = zip(runner.join("museun"), conn.read_line()).await;
+            joined.unwrap();
+
+            let mut writer = runner.writer();
+            writer.set_coalescing(true);
+            writer
+                .encode_many(&[
+                    commands::privmsg("museun", "1"),
+                    commands::privmsg("museun", "2"),
+                ])
+                .await
+                .unwrap();
+
+            let mut lines = vec![];
+            while lines.len() < 2 {
+                runner.step().await.unwrap();
+                while let Some(line) = conn.try_read_line().unwrap() {
+                    lines.push(line);
+                }
+            }
+            assert_eq!(lines, ["PRIVMSG #museun :1\r\n", "PRIVMSG #museun :2\r\n"]);
+        });
+    }
 }
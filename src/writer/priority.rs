@@ -0,0 +1,169 @@
+use crate::Encodable;
+
+use std::{
+    borrow::Cow,
+    io::{Result as IoResult, Write},
+};
+
+// a client-only tag (so a server would ignore it), which is removed by every writer before the message is sent
+const TAG: &[u8] = b"@+twitchchat/priority=";
+
+/// The priority of a message written with [AsyncWriter::encode_with_priority()](super::AsyncWriter::encode_with_priority()).
+///
+/// The [AsyncRunner](crate::AsyncRunner) sends its queued messages in priority order (and in the order they were written, for the same priority), so a backlog of chat messages never holds up a ban.
+///
+/// The runner answers `PING`s itself, so keep-alives are never queued behind anything.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum Priority {
+    /// Bulk messages, which can wait
+    Low,
+    /// Normal messages, e.g. chat replies. This is the default
+    #[default]
+    Normal,
+    /// Messages that should be sent as soon as possible, e.g. moderation actions
+    High,
+}
+
+impl Priority {
+    const fn as_tag_value(self) -> &'static [u8] {
+        match self {
+            Self::Low => b"low",
+            Self::Normal => b"normal",
+            Self::High => b"high",
+        }
+    }
+
+    // splits the priority tag off of an encoded line, if it has one
+    pub(crate) fn take(data: Box<[u8]>) -> (Self, Box<[u8]>) {
+        let rest = match data.strip_prefix(TAG) {
+            Some(rest) => rest,
+            None => return (Self::Normal, data),
+        };
+
+        let priority = [Self::Low, Self::Normal, Self::High]
+            .iter()
+            .copied()
+            .find(|p| rest.starts_with(p.as_tag_value()));
+
+        let priority = match priority {
+            Some(priority) => priority,
+            None => return (Self::Normal, data),
+        };
+
+        let rest = &rest[priority.as_tag_value().len()..];
+        let data = match rest.split_first() {
+            // there were other tags
+            Some((b';', rest)) => {
+                let mut tags = Vec::with_capacity(rest.len() + 1);
+                tags.push(b'@');
+                tags.extend_from_slice(rest);
+                tags.into_boxed_slice()
+            }
+            Some((b' ', rest)) => rest.into(),
+            _ => return (Self::Normal, data),
+        };
+        (priority, data)
+    }
+
+    // removes the priority tags from these lines, for the writers which don't queue by priority
+    pub(crate) fn strip(data: &[u8]) -> Cow<'_, [u8]> {
+        let mut lines = data.split_inclusive(|&c| c == b'\n');
+        if !lines.any(|line| line.starts_with(TAG)) {
+            return Cow::Borrowed(data);
+        }
+
+        let mut out = Vec::with_capacity(data.len());
+        for line in data.split_inclusive(|&c| c == b'\n') {
+            out.extend_from_slice(&Self::take(line.into()).1);
+        }
+        Cow::Owned(out)
+    }
+}
+
+// an encodable which tags each line with its priority
+pub(crate) struct Prioritized<M> {
+    pub(crate) msg: M,
+    pub(crate) priority: Priority,
+}
+
+impl<M> Encodable for Prioritized<M>
+where
+    M: Encodable,
+{
+    fn encode<W>(&self, buf: &mut W) -> IoResult<()>
+    where
+        W: Write + ?Sized,
+    {
+        let mut data = Vec::new();
+        self.msg.encode(&mut data)?;
+
+        for line in data.split_inclusive(|&c| c == b'\n') {
+            buf.write_all(TAG)?;
+            buf.write_all(self.priority.as_tag_value())?;
+            match line.split_first() {
+                Some((b'@', tags)) => {
+                    buf.write_all(b";")?;
+                    buf.write_all(tags)?;
+                }
+                _ => {
+                    buf.write_all(b" ")?;
+                    buf.write_all(line)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(msg: &str, priority: Priority) -> Box<[u8]> {
+        let mut data = Vec::new();
+        Prioritized { msg, priority }.encode(&mut data).unwrap();
+        data.into_boxed_slice()
+    }
+
+    #[test]
+    fn round_trip() {
+        let tests = &[
+            "PRIVMSG #museun :hello\r\n",
+            "@reply-parent-msg-id=abc PRIVMSG #museun :hello\r\n",
+        ];
+
+        for msg in tests {
+            for priority in &[Priority::Low, Priority::Normal, Priority::High] {
+                let (p, data) = Priority::take(encode(msg, *priority));
+                assert_eq!(p, *priority);
+                assert_eq!(&*data, msg.as_bytes());
+            }
+        }
+    }
+
+    #[test]
+    fn strip() {
+        let mut data = encode("PRIVMSG #museun :hello\r\n", Priority::High).into_vec();
+        data.extend_from_slice(b"PRIVMSG #museun :world\r\n");
+        data.extend_from_slice(&encode(
+            "@reply-parent-msg-id=abc PRIVMSG #museun :!\r\n",
+            Priority::Low,
+        ));
+
+        assert_eq!(
+            &*Priority::strip(&data),
+            &b"PRIVMSG #museun :hello\r\nPRIVMSG #museun :world\r\n@reply-parent-msg-id=abc PRIVMSG #museun :!\r\n"[..]
+        );
+
+        let msg = b"PRIVMSG #museun :hello\r\n";
+        assert!(matches!(Priority::strip(msg), Cow::Borrowed(..)));
+    }
+
+    #[test]
+    fn untagged() {
+        let msg = "@reply-parent-msg-id=abc PRIVMSG #museun :hello\r\n";
+        let (p, data) = Priority::take(msg.as_bytes().into());
+        assert_eq!(p, Priority::Normal);
+        assert_eq!(&*data, msg.as_bytes());
+    }
+}
@@ -0,0 +1,105 @@
+use crate::messages::{MessageId, Notice};
+
+/// A message we sent that Twitch didn't deliver, because of this `NOTICE`.
+///
+/// Get these from [AsyncWriter::rejections()](super::AsyncWriter::rejections()).
+/// The `NOTICE` is still produced by the [AsyncRunner](crate::AsyncRunner) as usual.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SendRejected {
+    pub(crate) notice: Notice<'static>,
+    pub(crate) nonce: Option<String>,
+}
+
+impl SendRejected {
+    /// The channel the message was sent to
+    pub fn channel(&self) -> &str {
+        self.notice.channel()
+    }
+
+    /// Why the message was rejected
+    pub fn msg_id(&self) -> MessageId<'_> {
+        self.notice.msg_id().unwrap_or(MessageId::Unknown(""))
+    }
+
+    /// The `NOTICE` Twitch sent
+    pub fn notice(&self) -> &Notice<'static> {
+        &self.notice
+    }
+
+    /// The `client-nonce` of the message, if it was sent with [AsyncWriter::encode_confirmed()](super::AsyncWriter::encode_confirmed())
+    pub fn nonce(&self) -> Option<&str> {
+        self.nonce.as_deref()
+    }
+
+    /// Would the message be accepted if it was sent again later? e.g. we were rate limited, or the channel is in slow mode
+    pub fn is_temporary(&self) -> bool {
+        use MessageId::*;
+        matches!(
+            self.msg_id(),
+            MsgDuplicate | MsgRatelimit | MsgSlowmode | WhisperLimitPerMin | WhisperLimitPerSec
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FromIrcMessage as _, IntoOwned as _};
+
+    fn rejected(input: &str) -> SendRejected {
+        let (_, msg) = crate::irc::parse_one(input).unwrap();
+        SendRejected {
+            notice: Notice::from_irc(msg).unwrap().into_owned(),
+            nonce: None,
+        }
+    }
+
+    #[test]
+    fn send_rejected() {
+        let msg = rejected("@msg-id=msg_ratelimit :tmi.twitch.tv NOTICE #museun :Your message was not sent because you are sending messages too quickly.\r\n");
+        assert_eq!(msg.channel(), "#museun");
+        assert_eq!(msg.msg_id(), MessageId::MsgRatelimit);
+        assert!(msg.is_temporary());
+
+        let msg = rejected("@msg-id=msg_followersonly :tmi.twitch.tv NOTICE #museun :This room is in followers-only mode.\r\n");
+        assert_eq!(msg.msg_id(), MessageId::MsgFollowersonly);
+        assert!(!msg.is_temporary());
+    }
+
+    #[test]
+    #[cfg(feature = "testing")]
+    fn rejected_messages() {
+        use crate::{
+            messages::Commands,
+            test::{user_config, MockServer},
+            Status,
+        };
+        use futures_lite::future::block_on;
+
+        let server = MockServer::new();
+        block_on(async {
+            let (mut runner, conn) = server.connect_runner(&user_config()).await.unwrap();
+
+            let rejections = runner.writer().rejections();
+
+            conn.send(
+                "@msg-id=slow_on :tmi.twitch.tv NOTICE #museun :This room is now in slow mode.",
+            )
+            .unwrap();
+            conn.send("@msg-id=msg_followersonly :tmi.twitch.tv NOTICE #museun :This room is in followers-only mode.").unwrap();
+            let mut notices = 0;
+            while notices < 2 {
+                if let Status::Message(Commands::Notice(..)) = runner.next_message().await.unwrap()
+                {
+                    notices += 1;
+                }
+            }
+
+            let rejected = rejections.try_next().unwrap();
+            assert_eq!(rejected.channel(), "#museun");
+            assert_eq!(rejected.msg_id(), MessageId::MsgFollowersonly);
+            assert_eq!(rejected.nonce(), None);
+            assert!(rejections.try_next().is_none());
+        });
+    }
+}
@@ -0,0 +1,334 @@
+use super::duplicates::privmsg;
+use crate::{
+    channel::{Receiver, Sender},
+    messages::{MessageId, Notice, RoomSettings},
+    twitch::Role,
+};
+
+use std::{collections::HashMap, io};
+
+/// What an [AsyncWriter](super::AsyncWriter) does with a `PRIVMSG` that Twitch would reject because of the channel's chat mode.
+///
+/// Twitch rejects messages in followers-only mode from chatters who don't follow the channel, and in subscribers-only mode from chatters who aren't subscribed.
+/// The writer knows the chat modes (from `ROOMSTATE`) and our role (from `USERSTATE`). Twitch doesn't say whether we follow the channel, so that's learned when a message is rejected, or from [AsyncWriter::set_following()](super::AsyncWriter::set_following()).
+///
+/// Set this with [AsyncWriter::set_restriction_policy()](super::AsyncWriter::set_restriction_policy()).
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum RestrictionPolicy {
+    /// Send it anyway. This is the default
+    #[default]
+    Allow,
+    /// Wait until Twitch would accept it, e.g. until subscribers-only mode is turned off
+    Delay,
+    /// Return a [WouldBeRejected] error
+    Reject,
+}
+
+/// Why Twitch would reject a message
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum RejectReason {
+    /// The channel is in followers-only mode, and we don't follow it
+    FollowersOnly,
+    /// The channel is in subscribers-only mode, and we aren't subscribed
+    SubscribersOnly,
+}
+
+impl std::fmt::Display for RejectReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::FollowersOnly => f.write_str("the channel is in followers-only mode"),
+            Self::SubscribersOnly => f.write_str("the channel is in subscribers-only mode"),
+        }
+    }
+}
+
+/// The error returned when an [AsyncWriter](super::AsyncWriter) doesn't send a message that Twitch would reject, with [RestrictionPolicy::Reject].
+///
+/// This is wrapped in an `std::io::Error` with the `PermissionDenied` kind. Use [WouldBeRejected::of()] to get it back.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WouldBeRejected {
+    /// The channel the message was for
+    pub channel: String,
+    /// Why Twitch would reject it
+    pub reason: RejectReason,
+}
+
+impl WouldBeRejected {
+    /// Get this from the error, if it was caused by a message that Twitch would reject
+    pub fn of(err: &io::Error) -> Option<&Self> {
+        err.get_ref().and_then(|err| err.downcast_ref::<Self>())
+    }
+}
+
+impl std::fmt::Display for WouldBeRejected {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Twitch would reject this message for '{}': {}",
+            self.channel, self.reason
+        )
+    }
+}
+
+impl std::error::Error for WouldBeRejected {}
+
+#[derive(Debug, Default)]
+struct ChannelState {
+    followers_only: bool,
+    subs_only: bool,
+    role: Role,
+    // this is None until we're told, or a message is rejected
+    following: Option<bool>,
+}
+
+impl ChannelState {
+    fn reason(&self) -> Option<RejectReason> {
+        // moderators, vips and the broadcaster can always speak
+        if matches!(self.role, Role::Moderator | Role::Vip | Role::Broadcaster) {
+            return None;
+        }
+        if self.subs_only && !matches!(self.role, Role::Subscriber { .. }) {
+            return Some(RejectReason::SubscribersOnly);
+        }
+        if self.followers_only && self.following == Some(false) {
+            return Some(RejectReason::FollowersOnly);
+        }
+        None
+    }
+}
+
+// this is shared between all of the clones of a writer, and the runner
+#[derive(Debug, Default)]
+pub(crate) struct Restrictions {
+    pub(crate) policy: RestrictionPolicy,
+    channels: HashMap<String, ChannelState>,
+    // the writers waiting (with the delay policy) for a channel to change
+    waiting: Vec<Sender<()>>,
+}
+
+impl Restrictions {
+    pub(crate) fn set_room_settings(&mut self, channel: &str, settings: &RoomSettings) {
+        let state = self.channels.entry(channel.to_string()).or_default();
+        state.followers_only = settings.followers_only.is_some();
+        state.subs_only = settings.subs_only;
+        self.changed();
+    }
+
+    pub(crate) fn set_role(&mut self, channel: &str, role: Role) {
+        let state = self.channels.entry(channel.to_string()).or_default();
+        if state.role != role {
+            state.role = role;
+            self.changed();
+        }
+    }
+
+    pub(crate) fn set_following(&mut self, channel: &str, following: bool) {
+        self.channels
+            .entry(channel.to_string())
+            .or_default()
+            .following
+            .replace(following);
+        self.changed();
+    }
+
+    // Twitch rejected a message because we don't follow the channel
+    pub(crate) fn learn(&mut self, msg: &Notice<'_>) {
+        if let Some(MessageId::MsgFollowersonly) | Some(MessageId::MsgFollowersonlyZero) =
+            msg.msg_id()
+        {
+            let state = self.channels.entry(msg.channel().to_string()).or_default();
+            state.followers_only = true;
+            state.following.replace(false);
+        }
+    }
+
+    // this is forgotten when we leave the channel
+    pub(crate) fn remove(&mut self, channel: &str) {
+        self.channels.remove(channel);
+        self.changed();
+    }
+
+    // applies the policy to the encoded lines. with the delay policy, this returns what to wait on before checking again
+    pub(crate) fn check(&mut self, data: &[u8]) -> io::Result<Option<Receiver<()>>> {
+        if self.policy == RestrictionPolicy::Allow || self.channels.is_empty() {
+            return Ok(None);
+        }
+
+        let input = std::str::from_utf8(data)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+
+        let rejected = input
+            .split_inclusive('\n')
+            .filter_map(|line| privmsg(line).map(|(channel, _)| channel))
+            .find_map(|channel| {
+                let reason = self.channels.get(channel)?.reason()?;
+                Some(WouldBeRejected {
+                    channel: channel.to_string(),
+                    reason,
+                })
+            });
+
+        match (rejected, self.policy) {
+            (None, _) => Ok(None),
+            (Some(rejected), RestrictionPolicy::Delay) => {
+                log::debug!(
+                    "waiting to send to '{}': {}",
+                    rejected.channel,
+                    rejected.reason
+                );
+                let (tx, rx) = crate::channel::bounded(1);
+                self.waiting.push(tx);
+                Ok(Some(rx))
+            }
+            (Some(rejected), _) => Err(io::Error::new(io::ErrorKind::PermissionDenied, rejected)),
+        }
+    }
+
+    fn changed(&mut self) {
+        for tx in self.waiting.drain(..) {
+            let _ = tx.try_send(());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(followers_only: bool, subs_only: bool) -> RoomSettings {
+        RoomSettings {
+            followers_only: Some(std::time::Duration::from_secs(0)).filter(|_| followers_only),
+            subs_only,
+            ..RoomSettings::default()
+        }
+    }
+
+    #[test]
+    fn rejected() {
+        let mut restrictions = Restrictions {
+            policy: RestrictionPolicy::Reject,
+            ..Restrictions::default()
+        };
+        let msg = b"PRIVMSG #museun :hello\r\n";
+        assert!(restrictions.check(msg).unwrap().is_none());
+
+        restrictions.set_room_settings("#museun", &settings(false, true));
+        let err = restrictions.check(msg).map(drop).unwrap_err();
+        assert_eq!(
+            WouldBeRejected::of(&err),
+            Some(&WouldBeRejected {
+                channel: "#museun".into(),
+                reason: RejectReason::SubscribersOnly
+            })
+        );
+
+        // subscribers (and moderators) can speak
+        restrictions.set_role("#museun", Role::Subscriber { tier: 1, months: 3 });
+        assert!(restrictions.check(msg).unwrap().is_none());
+        restrictions.set_role("#museun", Role::Moderator);
+        restrictions.set_room_settings("#museun", &settings(true, true));
+        assert!(restrictions.check(msg).unwrap().is_none());
+
+        // followers-only only rejects once we know we don't follow
+        restrictions.set_role("#museun", Role::Everyone);
+        restrictions.set_room_settings("#museun", &settings(true, false));
+        assert!(restrictions.check(msg).unwrap().is_none());
+        let notice: Notice<'_> = crate::FromIrcMessage::from_irc(
+            crate::irc::parse_one("@msg-id=msg_followersonly_zero :tmi.twitch.tv NOTICE #museun :This room is in followers-only mode.\r\n")
+                .unwrap()
+                .1,
+        )
+        .unwrap();
+        restrictions.learn(&notice);
+        let err = restrictions.check(msg).map(drop).unwrap_err();
+        assert_eq!(
+            WouldBeRejected::of(&err).unwrap().reason,
+            RejectReason::FollowersOnly
+        );
+        restrictions.set_following("#museun", true);
+        assert!(restrictions.check(msg).unwrap().is_none());
+
+        // other channels, and other commands, aren't checked
+        restrictions.set_following("#museun", false);
+        assert!(restrictions
+            .check(b"PRIVMSG #shaken_bot :hello\r\n")
+            .unwrap()
+            .is_none());
+        assert!(restrictions.check(b"JOIN #museun\r\n").unwrap().is_none());
+    }
+
+    #[test]
+    fn delayed() {
+        let mut restrictions = Restrictions {
+            policy: RestrictionPolicy::Delay,
+            ..Restrictions::default()
+        };
+        restrictions.set_room_settings("#museun", &settings(false, true));
+
+        let msg = b"PRIVMSG #museun :hello\r\n";
+        let wait = restrictions.check(msg).unwrap().unwrap();
+        assert!(wait.try_recv().is_none());
+
+        restrictions.set_room_settings("#museun", &settings(false, false));
+        assert!(wait.try_recv().is_some());
+        assert!(restrictions.check(msg).unwrap().is_none());
+    }
+
+    #[test]
+    #[cfg(feature = "testing")]
+    fn restricted_messages() {
+        use crate::{
+            commands,
+            messages::Commands,
+            runner::Error as RunnerError,
+            test::{user_config, MockServer},
+            Status,
+        };
+        use futures_lite::future::block_on;
+
+        let server = MockServer::new();
+        block_on(async {
+            let (mut runner, conn) = server.connect_runner(&user_config()).await.unwrap();
+
+            let mut writer = runner.writer();
+            writer.set_restriction_policy(RestrictionPolicy::Reject);
+
+            conn.send(":shaken_bot!shaken_bot@shaken_bot.tmi.twitch.tv JOIN #museun")
+                .unwrap();
+            conn.send("@emote-only=0;followers-only=-1;r9k=0;room-id=23196011;slow=0;subs-only=1 :tmi.twitch.tv ROOMSTATE #museun").unwrap();
+            while !matches!(
+                runner.next_message().await.unwrap(),
+                Status::Message(Commands::RoomState(..))
+            ) {}
+
+            let err = writer
+                .encode(commands::privmsg("museun", "hello"))
+                .await
+                .unwrap_err();
+            assert_eq!(
+                WouldBeRejected::of(&err).unwrap().reason,
+                RejectReason::SubscribersOnly
+            );
+            assert!(matches!(
+                RunnerError::from(err),
+                RunnerError::WouldBeRejected {
+                    reason: RejectReason::SubscribersOnly,
+                    ..
+                }
+            ));
+
+            // subscribers can speak
+            conn.send("@badge-info=subscriber/3;badges=subscriber/3;color=;display-name=shaken_bot;emote-sets=0;mod=0;subscriber=1;user-type= :tmi.twitch.tv USERSTATE #museun").unwrap();
+            while !matches!(
+                runner.next_message().await.unwrap(),
+                Status::Message(Commands::UserState(..))
+            ) {}
+            writer
+                .encode(commands::privmsg("museun", "hello"))
+                .await
+                .unwrap();
+        });
+    }
+}
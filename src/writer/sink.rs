@@ -0,0 +1,122 @@
+use super::{AsyncWriter, MpscWriter};
+use crate::{AsyncEncoder, Encodable};
+
+use futures_lite::{AsyncWrite, AsyncWriteExt as _};
+use std::{future::Future, io, pin::Pin};
+
+/// The future returned by a [MessageSink]
+pub type SinkFuture<'a> = Pin<Box<dyn Future<Output = io::Result<()>> + Send + 'a>>;
+
+/// Something [Encodable] messages can be sent to.
+///
+/// This is implemented for:
+/// * [AsyncEncoder], which writes straight into any [AsyncWrite] (e.g. a connection you manage yourself)
+/// * [MpscWriter], which sends them over a channel (e.g. to the [AsyncRunner](crate::AsyncRunner))
+/// * [AsyncWriter], which is what [AsyncRunner::writer()](crate::AsyncRunner::writer()) gives you
+/// * [runner::Writer](crate::runner::Writer), the writing half of a [split](crate::AsyncRunner::split()) runner
+///
+/// so code which sends messages doesn't need to know where they go.
+///
+/// ```
+/// # use twitchchat::{commands, writer::MessageSink, AsyncEncoder};
+/// async fn greet(sink: &mut impl MessageSink) -> std::io::Result<()> {
+///     sink.send(commands::privmsg("#museun", "hello")).await
+/// }
+///
+/// # futures_lite::future::block_on(async {
+/// let mut encoder = AsyncEncoder::new(futures_lite::io::Cursor::new(Vec::new()));
+/// greet(&mut encoder).await.unwrap();
+/// # });
+/// ```
+pub trait MessageSink: Send {
+    /// Send these already encoded lines, each ending with `\r\n`
+    fn send_bytes<'a>(&'a mut self, data: &'a [u8]) -> SinkFuture<'a>;
+
+    /// Encode this message, and send it
+    fn send<'a, M>(&'a mut self, msg: M) -> SinkFuture<'a>
+    where
+        M: Encodable + Send + 'a,
+        Self: Sized,
+    {
+        Box::pin(async move {
+            let mut data = Vec::new();
+            msg.encode(&mut data)?;
+            self.send_bytes(&data).await
+        })
+    }
+}
+
+impl<S> MessageSink for Box<S>
+where
+    S: MessageSink + ?Sized,
+{
+    fn send_bytes<'a>(&'a mut self, data: &'a [u8]) -> SinkFuture<'a> {
+        (**self).send_bytes(data)
+    }
+}
+
+impl<W> MessageSink for AsyncEncoder<W>
+where
+    W: AsyncWrite + Send + Sync + Unpin,
+{
+    fn send_bytes<'a>(&'a mut self, data: &'a [u8]) -> SinkFuture<'a> {
+        Box::pin(self.encode_bytes(data))
+    }
+}
+
+impl MessageSink for MpscWriter {
+    fn send_bytes<'a>(&'a mut self, data: &'a [u8]) -> SinkFuture<'a> {
+        Box::pin(async move {
+            self.write_all(data).await?;
+            self.flush().await
+        })
+    }
+}
+
+impl<W> MessageSink for AsyncWriter<W>
+where
+    W: AsyncWrite + Send + Sync + Unpin,
+{
+    fn send_bytes<'a>(&'a mut self, data: &'a [u8]) -> SinkFuture<'a> {
+        Box::pin(self.encode(data))
+    }
+}
+
+impl MessageSink for crate::runner::Writer {
+    fn send_bytes<'a>(&'a mut self, data: &'a [u8]) -> SinkFuture<'a> {
+        Box::pin(self.encode(data))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands;
+    use futures_lite::future::block_on;
+
+    async fn greet(sink: &mut impl MessageSink) -> io::Result<()> {
+        sink.send(commands::join("museun")).await?;
+        sink.send(commands::privmsg("#museun", "hello")).await
+    }
+
+    #[test]
+    fn sinks() {
+        const EXPECTED: &[u8] = b"JOIN #museun\r\nPRIVMSG #museun :hello\r\n";
+
+        let mut encoder = AsyncEncoder::new(futures_lite::io::Cursor::new(Vec::new()));
+        block_on(greet(&mut encoder)).unwrap();
+        assert_eq!(encoder.writer.get_ref(), EXPECTED);
+
+        let (tx, rx) = crate::channel::unbounded();
+        let mut writer = MpscWriter::new(tx);
+        block_on(greet(&mut writer)).unwrap();
+        assert_eq!(&*rx.try_recv().unwrap(), &EXPECTED[..14]);
+        assert_eq!(&*rx.try_recv().unwrap(), &EXPECTED[14..]);
+
+        // and as a trait object
+        let (tx, rx) = crate::channel::unbounded();
+        let mut sink: Box<dyn MessageSink> = Box::new(MpscWriter::new(tx));
+        block_on(sink.send_bytes(b"PING :1234\r\n")).unwrap();
+        assert_eq!(&*rx.try_recv().unwrap(), b"PING :1234\r\n");
+    }
+}